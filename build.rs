@@ -26,6 +26,7 @@ fn main() {
         .fail_on_error()
         .emit()
         .expect("failed to extract build information");
+    emit_wasmtime_version();
 
     let build_spin_tests = env::var("BUILD_SPIN_EXAMPLES")
         .map(|v| v == "1")
@@ -90,6 +91,25 @@ error: the `wasm32-wasi` target is not installed
     cargo_build(TIMER_TRIGGER_INTEGRATION_TEST);
 }
 
+// Emits the version of Wasmtime which Spin is built with, as locked in
+// Cargo.lock, for `spin info`.
+fn emit_wasmtime_version() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lockfile = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let version = lockfile
+        .split("[[package]]")
+        .find_map(|package| {
+            let mut lines = package.lines().map(str::trim);
+            lines.find(|line| *line == r#"name = "wasmtime""#)?;
+            lines
+                .next()?
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')
+        })
+        .unwrap_or("unknown");
+    println!("cargo:rustc-env=SPIN_WASMTIME_VERSION={version}");
+}
+
 fn build_wasm_test_program(name: &'static str, root: &'static str) {
     build_target_dep(root, Path::new("target/test-programs").join(name))
         .release()
@@ -90,11 +90,35 @@ impl AppLoader {
 
     /// Loads an [`App`] from the given `Loader`-implementation-specific `uri`.
     pub async fn load_app(&self, uri: String) -> Result<App> {
-        let locked = self
-            .inner
-            .load_app(&uri)
-            .await
-            .map_err(Error::LoaderError)?;
+        let locked = self.load_locked_app(&uri).await?;
+        self.validated_app(uri, locked)
+    }
+
+    /// Loads an [`OwnedApp`] from the given `Loader`-implementation-specific
+    /// `uri`; the [`OwnedApp`] takes ownership of this [`AppLoader`].
+    pub async fn load_owned_app(self, uri: String) -> Result<OwnedApp> {
+        OwnedApp::try_new_async(self, |loader| Box::pin(loader.load_app(uri))).await
+    }
+
+    /// Loads the [`LockedApp`] from the given `Loader`-implementation-specific
+    /// `uri`, without validating it. This lets the app be inspected before
+    /// [`DynamicHostComponent`]s are added, and then passed to
+    /// [`AppLoader::load_owned_locked_app`] rather than loaded again.
+    pub async fn load_locked_app(&self, uri: &str) -> Result<LockedApp> {
+        self.inner.load_app(uri).await.map_err(Error::LoaderError)
+    }
+
+    /// Validates a [`LockedApp`] loaded from the given `uri` by
+    /// [`AppLoader::load_locked_app`], returning an [`OwnedApp`] which takes
+    /// ownership of this [`AppLoader`].
+    pub async fn load_owned_locked_app(self, uri: String, locked: LockedApp) -> Result<OwnedApp> {
+        OwnedApp::try_new_async(self, |loader| {
+            Box::pin(async move { loader.validated_app(uri, locked) })
+        })
+        .await
+    }
+
+    fn validated_app(&self, uri: String, locked: LockedApp) -> Result<App> {
         let app = App {
             loader: self,
             uri,
@@ -105,12 +129,6 @@ impl AppLoader {
             .map_err(Error::ValidationError)?;
         Ok(app)
     }
-
-    /// Loads an [`OwnedApp`] from the given `Loader`-implementation-specific
-    /// `uri`; the [`OwnedApp`] takes ownership of this [`AppLoader`].
-    pub async fn load_owned_app(self, uri: String) -> Result<OwnedApp> {
-        OwnedApp::try_new_async(self, |loader| Box::pin(loader.load_app(uri))).await
-    }
 }
 
 impl std::fmt::Debug for AppLoader {
@@ -260,6 +278,12 @@ impl<'a, L: MaybeLoader> AppComponent<'a, L> {
         &self.locked.source
     }
 
+    /// Returns an iterator of the environment variables set for this
+    /// component.
+    pub fn environment(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.locked.env.iter()
+    }
+
     /// Returns an iterator of [`ContentPath`]s for this component's configured
     /// "directory mounts".
     pub fn files(&self) -> std::slice::Iter<ContentPath> {
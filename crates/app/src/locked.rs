@@ -78,6 +78,9 @@ pub struct ContentPath {
     pub content: ContentRef,
     /// WASI mount path
     pub path: PathBuf,
+    /// Whether the content may be written to
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub writable: bool,
 }
 
 /// A ContentRef represents content used by an application.
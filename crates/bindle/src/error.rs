@@ -27,6 +27,21 @@ pub enum PublishError {
         /// Underlying lower level error that caused your error
         source: std::io::Error,
     },
+    /// Writable file mounts refer to host directories, so can't be published
+    #[error("Component '{0}' has writable file mounts, which can't be published")]
+    WritableFilesNotSupported(String),
+    /// Component dependencies are composed by the runtime, which bindle
+    /// applications do not record
+    #[error("Component '{0}' has dependencies, which can't be published to bindle")]
+    DependenciesNotSupported(String),
+    /// Conditionally enabled components are not supported by bindle
+    /// applications
+    #[error("Component '{0}' is conditionally enabled, which can't be published to bindle")]
+    EnabledNotSupported(String),
+    /// Git component sources are fetched by the loader, which bindle
+    /// applications do not use
+    #[error("Component '{0}' has a git source, which can't be published to bindle")]
+    GitSourceNotSupported(String),
     /// Build artifact is missing
     #[error("Missing build artifact: '{0}'")]
     MissingBuildArtifact(String),
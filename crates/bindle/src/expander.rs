@@ -112,7 +112,25 @@ async fn bindle_component_manifest(
             let source = UrlSource::new(us)?;
             source.digest_str().to_owned()
         }
+        local_schema::RawModuleSource::Git(_) => {
+            return Err(PublishError::GitSourceNotSupported(local.id.clone()));
+        }
     };
+    let has_writable_files = local
+        .wasm
+        .files
+        .iter()
+        .flatten()
+        .any(|f| matches!(f, local_schema::RawFileMount::Placement(p) if p.writable));
+    if has_writable_files {
+        return Err(PublishError::WritableFilesNotSupported(local.id.clone()));
+    }
+    if local.dependencies.as_ref().map_or(false, |d| !d.is_empty()) {
+        return Err(PublishError::DependenciesNotSupported(local.id.clone()));
+    }
+    if local.enabled.is_some() {
+        return Err(PublishError::EnabledNotSupported(local.id.clone()));
+    }
     let asset_group = local.wasm.files.as_ref().map(|_| group_name_for(&local.id));
     Ok(bindle_schema::RawComponentManifest {
         id: local.id.clone(),
@@ -120,10 +138,18 @@ async fn bindle_component_manifest(
         source: source_digest,
         wasm: bindle_schema::RawWasmConfig {
             environment: local.wasm.environment.clone(),
+            environment_passthrough: local.wasm.environment_passthrough.clone(),
             files: asset_group,
             allowed_http_hosts: local.wasm.allowed_http_hosts.clone(),
+            allowed_outbound_hosts: local.wasm.allowed_outbound_hosts.clone(),
             key_value_stores: local.wasm.key_value_stores.clone(),
             sqlite_databases: local.wasm.sqlite_databases.clone(),
+            blob_containers: local.wasm.blob_containers.clone(),
+            messaging_channels: local.wasm.messaging_channels.clone(),
+            outbound_http_cache: local.wasm.outbound_http_cache,
+            limits: local.wasm.limits.clone(),
+            tmp_dir: local.wasm.tmp_dir.clone(),
+            extensions: local.wasm.extensions.clone(),
         },
         trigger: local.trigger.clone(),
         config: local.config.clone(),
@@ -161,6 +187,9 @@ async fn wasm_parcel(
 
             (dest_relative_path, absolute_path)
         }
+        local_schema::RawModuleSource::Git(_) => {
+            return Err(PublishError::GitSourceNotSupported(component.id.clone()));
+        }
     };
 
     file_parcel(&absolute_wasm_file, wasm_file, None, "application/wasm").await
@@ -469,6 +498,7 @@ mod test {
                 },
             ),
             namespace: None,
+            strict: None,
         }
     }
 
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::anyhow;
+use object_store::ObjectStore;
+use spin_app::{AppComponent, DynamicHostComponent};
+use spin_core::HostComponent;
+use spin_world::blob_store;
+
+use crate::{BlobStoreDispatch, BLOB_CONTAINERS_KEY};
+
+/// The blob store host component, which gives components the containers
+/// named in their `blob_containers`.
+pub struct BlobStoreComponent {
+    containers: Arc<HashMap<String, Arc<dyn ObjectStore>>>,
+}
+
+impl BlobStoreComponent {
+    /// Creates a component with the given containers, by name.
+    pub fn new(containers: impl IntoIterator<Item = (String, Arc<dyn ObjectStore>)>) -> Self {
+        Self {
+            containers: Arc::new(containers.into_iter().collect()),
+        }
+    }
+}
+
+impl HostComponent for BlobStoreComponent {
+    type Data = BlobStoreDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        blob_store::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        BlobStoreDispatch::new(self.containers.clone())
+    }
+}
+
+impl DynamicHostComponent for BlobStoreComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let containers = component
+            .get_metadata(BLOB_CONTAINERS_KEY)?
+            .unwrap_or_default();
+        data.init(containers.into_iter().collect());
+        Ok(())
+    }
+
+    fn validate_app(&self, app: &spin_app::App) -> anyhow::Result<()> {
+        let mut errors = vec![];
+
+        for component in app.components() {
+            for allowed in component
+                .get_metadata(BLOB_CONTAINERS_KEY)?
+                .unwrap_or_default()
+            {
+                if !self.containers.contains_key(&allowed) {
+                    let err = format!("- Component {} uses container '{allowed}'", component.id());
+                    errors.push(err);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let prologue = vec![
+                "One or more components use blob containers which are not defined.",
+                "Check the spelling, or pass a runtime configuration file that defines these containers.",
+                "Details:",
+            ];
+            let lines: Vec<_> = prologue
+                .into_iter()
+                .map(|s| s.to_owned())
+                .chain(errors)
+                .collect();
+            Err(anyhow!(lines.join("\n")))
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Lets components store objects, such as files too large for key-value
+//! values, in named containers backed by object stores: S3-compatible
+//! services, Azure Blob Storage, Google Cloud Storage, or a local directory.
+
+mod host_component;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use object_store::{path::Path, MultipartId, ObjectStore};
+use spin_app::{async_trait, MetadataKey};
+use spin_key_value::table::Table;
+use spin_world::blob_store::{self, Container, IncomingData, ObjectMetadata, OutgoingData};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::instrument;
+
+pub use host_component::BlobStoreComponent;
+pub use object_store;
+
+pub const BLOB_CONTAINERS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("blob_containers");
+
+const TABLE_CAPACITY: u32 = 256;
+
+pub use blob_store::Error;
+
+// An object being read.
+struct Incoming {
+    stream: BoxStream<'static, object_store::Result<Bytes>>,
+    // Data read from the stream but not yet returned to the guest.
+    buffered: Bytes,
+}
+
+// An object being written with a multipart upload.
+struct Outgoing {
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    id: MultipartId,
+    writer: Box<dyn AsyncWrite + Unpin + Send>,
+}
+
+pub struct BlobStoreDispatch {
+    allowed_containers: HashSet<String>,
+    containers: Arc<HashMap<String, Arc<dyn ObjectStore>>>,
+    open: Table<Arc<dyn ObjectStore>>,
+    incoming: Table<Incoming>,
+    outgoing: Table<Outgoing>,
+}
+
+impl BlobStoreDispatch {
+    pub fn new(containers: Arc<HashMap<String, Arc<dyn ObjectStore>>>) -> Self {
+        Self {
+            allowed_containers: HashSet::new(),
+            containers,
+            open: Table::new(TABLE_CAPACITY),
+            incoming: Table::new(TABLE_CAPACITY),
+            outgoing: Table::new(TABLE_CAPACITY),
+        }
+    }
+
+    pub fn init(&mut self, allowed_containers: HashSet<String>) {
+        self.allowed_containers = allowed_containers;
+    }
+
+    fn container(&self, container: Container) -> Result<&Arc<dyn ObjectStore>, Error> {
+        self.open.get(container).ok_or(Error::InvalidHandle)
+    }
+}
+
+#[async_trait]
+impl blob_store::Host for BlobStoreDispatch {
+    #[instrument(name = "spin_blob_store.open", skip(self), fields(otel.kind = "client"))]
+    async fn open(&mut self, name: String) -> Result<Result<Container, Error>> {
+        Ok(async {
+            if !self.allowed_containers.contains(&name) {
+                return Err(Error::AccessDenied);
+            }
+            let store = self
+                .containers
+                .get(&name)
+                .ok_or(Error::NoSuchContainer)?
+                .clone();
+            self.open.push(store).map_err(|()| Error::TableFull)
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_blob_store.get", skip(self, container), fields(otel.kind = "client"))]
+    async fn get(&mut self, container: Container, name: String) -> Result<Result<Vec<u8>, Error>> {
+        Ok(async {
+            let store = self.container(container)?;
+            let result = store.get(&object_path(&name)?).await.map_err(store_error)?;
+            let bytes = result.bytes().await.map_err(store_error)?;
+            Ok(bytes.to_vec())
+        }
+        .await)
+    }
+
+    #[instrument(
+        name = "spin_blob_store.put",
+        skip(self, container, data),
+        fields(otel.kind = "client")
+    )]
+    async fn put(
+        &mut self,
+        container: Container,
+        name: String,
+        data: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            let store = self.container(container)?;
+            store
+                .put(&object_path(&name)?, Bytes::from(data))
+                .await
+                .map_err(store_error)
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_blob_store.delete", skip(self, container), fields(otel.kind = "client"))]
+    async fn delete(&mut self, container: Container, name: String) -> Result<Result<(), Error>> {
+        Ok(async {
+            let store = self.container(container)?;
+            match store.delete(&object_path(&name)?).await {
+                Err(object_store::Error::NotFound { .. }) => Ok(()),
+                result => result.map_err(store_error),
+            }
+        }
+        .await)
+    }
+
+    #[instrument(
+        name = "spin_blob_store.object_info",
+        skip(self, container),
+        fields(otel.kind = "client")
+    )]
+    async fn object_info(
+        &mut self,
+        container: Container,
+        name: String,
+    ) -> Result<Result<ObjectMetadata, Error>> {
+        Ok(async {
+            let store = self.container(container)?;
+            let meta = store
+                .head(&object_path(&name)?)
+                .await
+                .map_err(store_error)?;
+            Ok(ObjectMetadata {
+                name,
+                size: meta.size as u64,
+                last_modified: meta.last_modified.timestamp().max(0) as u64,
+            })
+        }
+        .await)
+    }
+
+    #[instrument(
+        name = "spin_blob_store.list_objects",
+        skip(self, container),
+        fields(otel.kind = "client")
+    )]
+    async fn list_objects(
+        &mut self,
+        container: Container,
+        prefix: Option<String>,
+    ) -> Result<Result<Vec<String>, Error>> {
+        Ok(async {
+            let store = self.container(container)?;
+            let prefix = prefix.unwrap_or_default();
+            // Stores list by directory, so list the prefix's directory and
+            // keep the names which start with the whole prefix.
+            let dir = match prefix.rsplit_once('/') {
+                Some((dir, _)) => Some(object_path(dir)?),
+                None => None,
+            };
+            let objects = store
+                .list(dir.as_ref())
+                .await
+                .map_err(store_error)?
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(store_error)?;
+            Ok(objects
+                .into_iter()
+                .map(|meta| meta.location.to_string())
+                .filter(|name| name.starts_with(&prefix))
+                .collect())
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_blob_store.read", skip(self, container), fields(otel.kind = "client"))]
+    async fn read(
+        &mut self,
+        container: Container,
+        name: String,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<Result<IncomingData, Error>> {
+        Ok(async {
+            let store = self.container(container)?.clone();
+            let path = object_path(&name)?;
+            let stream = if start == 0 && end.is_none() {
+                store.get(&path).await.map_err(store_error)?.into_stream()
+            } else {
+                // Ranges are read whole, as not every store can stream them.
+                let size = store.head(&path).await.map_err(store_error)?.size;
+                let end = end.map_or(size, |end| size.min(end as usize));
+                let start = end.min(start as usize);
+                let bytes = store
+                    .get_range(&path, start..end)
+                    .await
+                    .map_err(store_error)?;
+                futures::stream::once(async move { Ok(bytes) }).boxed()
+            };
+            let incoming = Incoming {
+                stream,
+                buffered: Bytes::new(),
+            };
+            self.incoming.push(incoming).map_err(|()| Error::TableFull)
+        }
+        .await)
+    }
+
+    async fn read_chunk(
+        &mut self,
+        data: IncomingData,
+        max_bytes: u64,
+    ) -> Result<Result<Vec<u8>, Error>> {
+        Ok(async {
+            let incoming = self.incoming.get_mut(data).ok_or(Error::InvalidHandle)?;
+            while incoming.buffered.is_empty() {
+                match incoming.stream.next().await {
+                    Some(chunk) => incoming.buffered = chunk.map_err(store_error)?,
+                    None => return Ok(vec![]),
+                }
+            }
+            // An empty chunk would mean the end of the object.
+            let max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX).max(1);
+            let len = incoming.buffered.len().min(max_bytes);
+            Ok(incoming.buffered.split_to(len).to_vec())
+        }
+        .await)
+    }
+
+    async fn close_incoming(&mut self, data: IncomingData) -> Result<()> {
+        self.incoming.remove(data);
+        Ok(())
+    }
+
+    #[instrument(name = "spin_blob_store.write", skip(self, container), fields(otel.kind = "client"))]
+    async fn write(
+        &mut self,
+        container: Container,
+        name: String,
+    ) -> Result<Result<OutgoingData, Error>> {
+        Ok(async {
+            let store = self.container(container)?.clone();
+            let path = object_path(&name)?;
+            let (id, writer) = store.put_multipart(&path).await.map_err(store_error)?;
+            let outgoing = Outgoing {
+                store,
+                path,
+                id,
+                writer,
+            };
+            self.outgoing.push(outgoing).map_err(|()| Error::TableFull)
+        }
+        .await)
+    }
+
+    async fn write_chunk(
+        &mut self,
+        data: OutgoingData,
+        chunk: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            let outgoing = self.outgoing.get_mut(data).ok_or(Error::InvalidHandle)?;
+            outgoing.writer.write_all(&chunk).await.map_err(log_error)
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_blob_store.finish", skip(self, data), fields(otel.kind = "client"))]
+    async fn finish(&mut self, data: OutgoingData) -> Result<Result<(), Error>> {
+        Ok(async {
+            let mut outgoing = self.outgoing.remove(data).ok_or(Error::InvalidHandle)?;
+            if let Err(err) = outgoing.writer.shutdown().await {
+                abort_upload(outgoing).await;
+                return Err(log_error(err));
+            }
+            Ok(())
+        }
+        .await)
+    }
+
+    async fn abort(&mut self, data: OutgoingData) -> Result<()> {
+        if let Some(outgoing) = self.outgoing.remove(data) {
+            abort_upload(outgoing).await;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self, container: Container) -> Result<()> {
+        self.open.remove(container);
+        Ok(())
+    }
+}
+
+async fn abort_upload(outgoing: Outgoing) {
+    if let Err(err) = outgoing
+        .store
+        .abort_multipart(&outgoing.path, &outgoing.id)
+        .await
+    {
+        tracing::warn!("Failed to abort upload of {}: {err:?}", outgoing.path);
+    }
+}
+
+fn object_path(name: &str) -> Result<Path, Error> {
+    Path::parse(name).map_err(|err| Error::Io(format!("invalid object name {name:?}: {err}")))
+}
+
+fn store_error(err: object_store::Error) -> Error {
+    match err {
+        object_store::Error::NotFound { .. } => Error::NoSuchObject,
+        err => log_error(err),
+    }
+}
+
+pub fn log_error(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("blob store error: {err:?}");
+    Error::Io(format!("{err:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use blob_store::Host;
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    fn dispatch(allowed: &[&str]) -> BlobStoreDispatch {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let containers = HashMap::from([
+            ("default".to_owned(), store.clone()),
+            ("other".to_owned(), store),
+        ]);
+        let mut dispatch = BlobStoreDispatch::new(Arc::new(containers));
+        dispatch.init(allowed.iter().map(|name| name.to_string()).collect());
+        dispatch
+    }
+
+    #[tokio::test]
+    async fn containers_must_be_allowed() -> Result<()> {
+        let mut dispatch = dispatch(&["default", "missing"]);
+        assert!(dispatch.open("default".into()).await?.is_ok());
+        assert!(matches!(
+            dispatch.open("other".into()).await?,
+            Err(Error::AccessDenied)
+        ));
+        assert!(matches!(
+            dispatch.open("missing".into()).await?,
+            Err(Error::NoSuchContainer)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn objects_are_read_and_written_in_chunks() -> Result<()> {
+        let mut dispatch = dispatch(&["default"]);
+        let container = dispatch.open("default".into()).await?.unwrap();
+
+        let upload = dispatch
+            .write(container, "photos/cat.jpg".into())
+            .await?
+            .unwrap();
+        dispatch
+            .write_chunk(upload, b"meow ".to_vec())
+            .await?
+            .unwrap();
+        dispatch
+            .write_chunk(upload, b"purr".to_vec())
+            .await?
+            .unwrap();
+        dispatch.finish(upload).await?.unwrap();
+        dispatch
+            .put(container, "photos/dog.jpg".into(), b"woof".to_vec())
+            .await?
+            .unwrap();
+
+        let download = dispatch
+            .read(container, "photos/cat.jpg".into(), 2, Some(7))
+            .await?
+            .unwrap();
+        assert_eq!(dispatch.read_chunk(download, 3).await?.unwrap(), b"ow ");
+        assert_eq!(dispatch.read_chunk(download, 3).await?.unwrap(), b"pu");
+        assert!(dispatch.read_chunk(download, 3).await?.unwrap().is_empty());
+
+        let names = dispatch
+            .list_objects(container, Some("photos/c".into()))
+            .await?
+            .unwrap();
+        assert_eq!(names, ["photos/cat.jpg"]);
+
+        dispatch
+            .delete(container, "photos/cat.jpg".into())
+            .await?
+            .unwrap();
+        assert!(matches!(
+            dispatch.get(container, "photos/cat.jpg".into()).await?,
+            Err(Error::NoSuchObject)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+//! Cache of built modules, keyed by the fingerprint of their build.
+//!
+//! When a component's fingerprint changes, for instance on switching
+//! branches, the module built from the same sources and build configuration
+//! may still be in the cache, and can be restored instead of rebuilt. The
+//! cache is a local directory, optionally backed by a remote HTTP cache,
+//! such as one shared by CI runs. Failing to use the cache is never an
+//! error: the component is just built.
+//!
+//! The remote cache holds each module under its SHA-256 digest, and the
+//! digest under the fingerprint. A module fetched from it is only used if
+//! its contents match the digest.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{StatusCode, Url};
+
+const CONFIG_DIR: &str = "spin";
+const ARTIFACT_CACHE_DIR: &str = "build-artifacts";
+
+/// A cache of built modules.
+#[derive(Clone, Debug)]
+pub struct ArtifactCache {
+    dir: PathBuf,
+    remote: Option<Url>,
+}
+
+impl ArtifactCache {
+    /// Creates a cache in the given directory, or in the user's cache
+    /// directory if none is given. If a remote URL is given, modules missing
+    /// from the local cache are fetched from it, and built modules are
+    /// uploaded to it. The digest of the module built with fingerprint `key`
+    /// is at `<url>/<key>`, and the module itself at `<url>/sha256-<digest>`.
+    pub fn new(dir: Option<PathBuf>, remote: Option<Url>) -> Result<Self> {
+        let dir = match dir {
+            Some(dir) => dir,
+            None => Self::default_dir()?,
+        };
+        Ok(Self { dir, remote })
+    }
+
+    /// The default cache directory, under the user's cache directory.
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(dirs::cache_dir()
+            .context("cannot get cache directory")?
+            .join(CONFIG_DIR)
+            .join(ARTIFACT_CACHE_DIR))
+    }
+
+    /// Copies the module cached under the key to `dest`. Returns whether the
+    /// module was in the cache.
+    pub(crate) async fn restore(&self, key: &str, dest: &Path) -> bool {
+        match self.try_restore(key, dest).await {
+            Ok(restored) => restored,
+            Err(err) => {
+                tracing::warn!("Failed to restore {dest:?} from the build cache: {err:#}");
+                false
+            }
+        }
+    }
+
+    /// Adds the built module to the cache under the key.
+    pub(crate) async fn store(&self, key: &str, module: &Path) {
+        if let Err(err) = self.try_store(key, module).await {
+            tracing::warn!("Failed to add {module:?} to the build cache: {err:#}");
+        }
+    }
+
+    async fn try_restore(&self, key: &str, dest: &Path) -> Result<bool> {
+        let cached = self.dir.join(key);
+        if !cached.exists() {
+            let Some(remote) = &self.remote else {
+                return Ok(false);
+            };
+            let Some(contents) = fetch_remote(remote, key).await? else {
+                return Ok(false);
+            };
+            self.write_cached(key, &contents).await?;
+        }
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(&cached, dest).await?;
+        spin_common::cache_usage::mark_used(&cached);
+        Ok(true)
+    }
+
+    async fn try_store(&self, key: &str, module: &Path) -> Result<()> {
+        let contents = tokio::fs::read(module).await?;
+        self.write_cached(key, &contents).await?;
+        if let Some(remote) = &self.remote {
+            // The module is uploaded before its digest, so that the digest
+            // never refers to a module which is missing.
+            let digest = spin_common::sha256::hex_digest_from_bytes(&contents);
+            put_remote(remote, &module_key(&digest), contents).await?;
+            put_remote(remote, key, digest.into_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    // Writes to a temporary file first so that concurrent builds never see a
+    // partially written module.
+    async fn write_cached(&self, key: &str, contents: &[u8]) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let temp = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        tokio::fs::write(&temp, contents).await?;
+        tokio::fs::rename(&temp, self.dir.join(key)).await?;
+        Ok(())
+    }
+}
+
+// Fetches the module built with fingerprint `key` from the remote cache,
+// checking that its contents match the digest recorded for the fingerprint.
+async fn fetch_remote(remote: &Url, key: &str) -> Result<Option<Vec<u8>>> {
+    let Some(digest) = get_remote(remote, key).await? else {
+        return Ok(None);
+    };
+    let digest = String::from_utf8(digest)
+        .ok()
+        .map(|digest| digest.trim().to_owned())
+        .filter(|digest| is_hex_digest(digest))
+        .with_context(|| format!("Invalid digest for {key} in the build cache"))?;
+    let Some(contents) = get_remote(remote, &module_key(&digest)).await? else {
+        return Ok(None);
+    };
+    let actual = spin_common::sha256::hex_digest_from_bytes(&contents);
+    if actual != digest {
+        bail!("Module in the build cache has digest {actual}, but {digest} was expected");
+    }
+    Ok(Some(contents))
+}
+
+async fn get_remote(remote: &Url, key: &str) -> Result<Option<Vec<u8>>> {
+    let response = reqwest::get(remote_url(remote, key)?).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+}
+
+async fn put_remote(remote: &Url, key: &str, contents: Vec<u8>) -> Result<()> {
+    reqwest::Client::new()
+        .put(remote_url(remote, key)?)
+        .body(contents)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+// The remote cache key of the module with the given digest.
+fn module_key(digest: &str) -> String {
+    format!("sha256-{digest}")
+}
+
+fn is_hex_digest(digest: &str) -> bool {
+    digest.len() == 64
+        && digest
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn remote_url(remote: &Url, key: &str) -> Result<Url> {
+    let mut url = remote.clone();
+    url.path_segments_mut()
+        .map_err(|()| anyhow!("Invalid build cache URL {remote}"))?
+        .pop_if_empty()
+        .push(key);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    // Serves GET requests for the given paths from a remote cache, returning
+    // its URL.
+    async fn serve_remote(entries: HashMap<String, Vec<u8>>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/cache/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut connection, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match connection.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                let (status, body) = match entries.get(path) {
+                    Some(body) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", vec![]),
+                };
+                let head = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = connection.write_all(head.as_bytes()).await;
+                let _ = connection.write_all(&body).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn restores_stored_modules() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::new(Some(temp.path().join("cache")), None).unwrap();
+        let module = temp.path().join("target/module.wasm");
+        assert!(!cache.restore("key", &module).await);
+
+        std::fs::create_dir_all(module.parent().unwrap()).unwrap();
+        std::fs::write(&module, b"\0asm").unwrap();
+        cache.store("key", &module).await;
+        std::fs::remove_file(&module).unwrap();
+
+        assert!(cache.restore("key", &module).await);
+        assert_eq!(std::fs::read(&module).unwrap(), b"\0asm");
+        assert!(!cache.restore("other-key", &module).await);
+    }
+
+    #[test]
+    fn remote_urls_append_key() {
+        let remote = Url::parse("https://cache.example.com/spin/").unwrap();
+        assert_eq!(
+            remote_url(&remote, "abc").unwrap().as_str(),
+            "https://cache.example.com/spin/abc"
+        );
+        let remote = Url::parse("https://cache.example.com/spin").unwrap();
+        assert_eq!(
+            remote_url(&remote, "abc").unwrap().as_str(),
+            "https://cache.example.com/spin/abc"
+        );
+    }
+
+    #[tokio::test]
+    async fn restores_remote_modules_matching_their_digest() {
+        let module = b"\0asm".to_vec();
+        let digest = spin_common::sha256::hex_digest_from_bytes(&module);
+        let other_digest = spin_common::sha256::hex_digest_from_bytes(b"other");
+        let remote = serve_remote(HashMap::from([
+            ("/cache/good".to_owned(), digest.clone().into_bytes()),
+            (format!("/cache/sha256-{digest}"), module.clone()),
+            (
+                "/cache/tampered".to_owned(),
+                other_digest.clone().into_bytes(),
+            ),
+            (format!("/cache/sha256-{other_digest}"), module.clone()),
+        ]))
+        .await;
+
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::new(Some(temp.path().join("cache")), Some(remote)).unwrap();
+        let dest = temp.path().join("target/module.wasm");
+
+        assert!(cache.restore("good", &dest).await);
+        assert_eq!(std::fs::read(&dest).unwrap(), module);
+
+        assert!(!cache.restore("tampered", &dest).await);
+        assert!(!temp.path().join("cache/tampered").exists());
+        assert!(!cache.restore("missing", &dest).await);
+    }
+}
@@ -0,0 +1,128 @@
+//! Running build commands in containers, for `spin build --in-container`.
+//!
+//! The application directory is mounted into a container of the component's
+//! build image, and each build command runs in the container, so that builds
+//! need no local toolchain and produce the same artifacts everywhere.
+
+use std::path::{Component, Path};
+
+use anyhow::{bail, Context, Result};
+use subprocess::Exec;
+
+use crate::manifest::RawBuildConfig;
+
+/// The environment variable naming the container runtime to use, such as
+/// `podman`. Defaults to `docker`.
+pub const CONTAINER_RUNTIME_ENV: &str = "SPIN_CONTAINER_RUNTIME";
+const DEFAULT_CONTAINER_RUNTIME: &str = "docker";
+
+// Where the application directory is mounted in the container.
+const CONTAINER_APP_DIR: &str = "/spin-app";
+
+/// The container in which a component's build commands run.
+pub(crate) struct BuildContainer {
+    runtime: String,
+    image: String,
+    app_dir: String,
+    workdir: String,
+}
+
+impl BuildContainer {
+    pub fn new(id: &str, build: &RawBuildConfig, app_dir: &Path) -> Result<Self> {
+        let Some(image) = &build.image else {
+            bail!("Cannot build component {id} in a container: it has no build `image`");
+        };
+        let app_dir_str = app_dir
+            .to_str()
+            .with_context(|| format!("Non-unicode application directory {app_dir:?}"))?;
+
+        // The working directory must be relative, so is under the mount.
+        let mut workdir = CONTAINER_APP_DIR.to_owned();
+        for part in build.workdir.iter().flat_map(|w| w.components()) {
+            match part {
+                Component::Normal(part) => {
+                    let part = part
+                        .to_str()
+                        .with_context(|| format!("Non-unicode workdir {:?}", build.workdir))?;
+                    workdir.push('/');
+                    workdir.push_str(part);
+                }
+                Component::CurDir => {}
+                _ => bail!(
+                    "The workdir of component {id} must be within the application directory \
+                    to build it in a container"
+                ),
+            }
+        }
+
+        Ok(Self {
+            runtime: std::env::var(CONTAINER_RUNTIME_ENV)
+                .unwrap_or_else(|_| DEFAULT_CONTAINER_RUNTIME.to_owned()),
+            image: image.clone(),
+            app_dir: app_dir_str.to_owned(),
+            workdir,
+        })
+    }
+
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// Prepares to run the shell command in a new container.
+    pub fn exec(&self, command: &str) -> Exec {
+        let exec = Exec::cmd(&self.runtime)
+            .arg("run")
+            .arg("--rm")
+            .arg("--volume")
+            .arg(format!("{}:{CONTAINER_APP_DIR}", self.app_dir))
+            .arg("--workdir")
+            .arg(&self.workdir);
+        // Run as the owner of the application directory so that the build
+        // output is not owned by root.
+        #[cfg(unix)]
+        let exec = match std::fs::metadata(&self.app_dir) {
+            Ok(metadata) => {
+                use std::os::unix::fs::MetadataExt;
+                exec.arg("--user")
+                    .arg(format!("{}:{}", metadata.uid(), metadata.gid()))
+            }
+            Err(_) => exec,
+        };
+        exec.arg(&self.image).arg("sh").arg("-c").arg(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_config(toml: &str) -> RawBuildConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn workdir_is_under_mounted_app_dir() {
+        let build = build_config(
+            r#"
+            command = "cargo build"
+            image = "rust:1.69"
+            workdir = "./rust/component"
+            "#,
+        );
+        let container = BuildContainer::new("test", &build, Path::new("/app")).unwrap();
+        assert_eq!(container.workdir, "/spin-app/rust/component");
+        assert_eq!(container.image(), "rust:1.69");
+
+        let build = build_config(
+            r#"
+            command = "cargo build"
+            image = "rust:1.69"
+            workdir = "../elsewhere"
+            "#,
+        );
+        assert!(BuildContainer::new("test", &build, Path::new("/app")).is_err());
+
+        let build = build_config(r#"command = "cargo build""#);
+        assert!(BuildContainer::new("test", &build, Path::new("/app")).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Skipping the builds of components whose sources are unchanged.
+//!
+//! A component's fingerprint is a digest of its build configuration and of
+//! the files matching its `watch` patterns. The fingerprint of each
+//! component's last successful build is recorded in the application's `.spin`
+//! directory, and the component is not built again until its fingerprint
+//! changes or its built module goes missing.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use spin_common::sha256;
+use spin_loader::local::config::RawModuleSource;
+
+use crate::manifest::RawComponentManifest;
+
+const STATE_DIR: &str = ".spin";
+const FINGERPRINTS_FILE: &str = "build-fingerprints.json";
+
+/// Computes the fingerprint of the component's build. Returns `None` if the
+/// component has no `watch` patterns, as its sources are then unknown.
+pub(crate) fn fingerprint(
+    component: &RawComponentManifest,
+    app_dir: &Path,
+) -> Result<Option<String>> {
+    let Some(build) = &component.build else {
+        return Ok(None);
+    };
+    let Some(watch) = &build.watch else {
+        return Ok(None);
+    };
+    let workdir = match &build.workdir {
+        Some(workdir) => app_dir.join(workdir),
+        None => app_dir.to_owned(),
+    };
+
+    let mut files = vec![];
+    for pattern in watch {
+        let pattern = workdir.join(pattern);
+        let pattern = pattern
+            .to_str()
+            .with_context(|| format!("Non-unicode watch pattern {pattern:?}"))?;
+        for path in glob::glob(pattern)
+            .with_context(|| format!("Invalid watch pattern {pattern:?}"))?
+            .flatten()
+        {
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let mut contents = serde_json::to_string(build)?;
+    for path in files {
+        let digest = sha256::hex_digest_from_file(&path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let path = path.strip_prefix(app_dir).unwrap_or(&path);
+        contents.push_str(&format!("\n{}\0{digest}", path.display()));
+    }
+    Ok(Some(sha256::hex_digest_from_bytes(contents)))
+}
+
+/// The fingerprints of components' last successful builds.
+pub(crate) struct Fingerprints {
+    path: PathBuf,
+    // Component ID -> fingerprint
+    fingerprints: HashMap<String, String>,
+    changed: bool,
+}
+
+impl Fingerprints {
+    /// Loads the fingerprints recorded for the application. If they cannot
+    /// be loaded, every component is built.
+    pub fn load(app_dir: &Path) -> Self {
+        let path = app_dir.join(STATE_DIR).join(FINGERPRINTS_FILE);
+        let fingerprints = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|err| {
+                tracing::warn!("Ignoring invalid build fingerprints {path:?}: {err}");
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self {
+            path,
+            fingerprints,
+            changed: false,
+        }
+    }
+
+    /// Whether the component was last built with the given fingerprint, and
+    /// its built module is still present.
+    pub fn is_unchanged(
+        &self,
+        component: &RawComponentManifest,
+        fingerprint: Option<&str>,
+        app_dir: &Path,
+    ) -> bool {
+        let Some(fingerprint) = fingerprint else {
+            return false;
+        };
+        let module_exists = match &component.source {
+            RawModuleSource::FileReference(path) => app_dir.join(path).exists(),
+            RawModuleSource::Url(_) | RawModuleSource::Git(_) => true,
+        };
+        module_exists
+            && self.fingerprints.get(&component.id).map(String::as_str) == Some(fingerprint)
+    }
+
+    /// Records the fingerprint of a successful build.
+    pub fn record(&mut self, id: String, fingerprint: String) {
+        self.fingerprints.insert(id, fingerprint);
+        self.changed = true;
+    }
+
+    /// Forgets the fingerprint of a component whose build failed.
+    pub fn forget(&mut self, id: &str) {
+        self.changed |= self.fingerprints.remove(id).is_some();
+    }
+
+    /// Saves the fingerprints if they have changed. A failure to save them
+    /// only means that components are built again next time, so is not an
+    /// error.
+    pub fn save(&self) {
+        if !self.changed {
+            return;
+        }
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                let contents = serde_json::to_vec_pretty(&self.fingerprints)?;
+                std::fs::write(&self.path, contents)
+            });
+        if let Err(err) = result {
+            tracing::warn!(
+                "Failed to save build fingerprints to {:?}: {err}",
+                self.path
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(watch: &str) -> RawComponentManifest {
+        toml::from_str(&format!(
+            r#"
+            id = "test"
+            source = "test.wasm"
+            [build]
+            command = "echo done"
+            watch = ["{watch}"]
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn fingerprint_changes_with_sources() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let src = app_dir.path().join("src");
+        std::fs::create_dir(&src).unwrap();
+        std::fs::write(src.join("lib.rs"), "fn main() {}").unwrap();
+        let component = component("src/**/*.rs");
+
+        let before = fingerprint(&component, app_dir.path()).unwrap().unwrap();
+        assert_eq!(
+            fingerprint(&component, app_dir.path()).unwrap().unwrap(),
+            before
+        );
+
+        std::fs::write(src.join("lib.rs"), "fn main() { todo!() }").unwrap();
+        let after = fingerprint(&component, app_dir.path()).unwrap().unwrap();
+        assert_ne!(after, before);
+
+        let mut fingerprints = Fingerprints::load(app_dir.path());
+        fingerprints.record("test".into(), after.clone());
+        fingerprints.save();
+        let fingerprints = Fingerprints::load(app_dir.path());
+        // The built module does not exist yet.
+        assert!(!fingerprints.is_unchanged(&component, Some(&after), app_dir.path()));
+        std::fs::write(app_dir.path().join("test.wasm"), "").unwrap();
+        assert!(fingerprints.is_unchanged(&component, Some(&after), app_dir.path()));
+    }
+}
@@ -2,26 +2,113 @@
 
 //! A library for building Spin components.
 
+mod cache;
+mod container;
+mod fingerprint;
 mod manifest;
+mod message;
+mod preflight;
+
+pub use cache::ArtifactCache;
+pub use container::CONTAINER_RUNTIME_ENV;
+pub use message::MessageFormat;
 
 use anyhow::{anyhow, bail, Context, Result};
-use spin_loader::local::parent_dir;
+use futures::StreamExt;
+use spin_loader::local::{
+    config::{RawModuleSource, RawPreinitConfig},
+    parent_dir,
+};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader},
     path::{Path, PathBuf},
 };
-use subprocess::{Exec, Redirection};
+use subprocess::{Exec, ExitStatus, Redirection};
 
-use crate::manifest::{BuildAppInfoAnyVersion, RawComponentManifest};
+use crate::{
+    container::BuildContainer,
+    fingerprint::Fingerprints,
+    manifest::{BuildAppInfoAnyVersion, RawComponentManifest},
+    message::BuildMessage,
+};
 
 /// If present, run the build command of each component.
 pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()> {
-    let manifest_text = tokio::fs::read_to_string(manifest_file)
-        .await
-        .with_context(|| format!("Cannot read manifest file from {}", manifest_file.display()))?;
-    let app = toml::from_str(&manifest_text).map(BuildAppInfoAnyVersion::into_v1)?;
+    build_with_options(manifest_file, component_ids, &BuildOptions::default()).await
+}
+
+/// Options controlling how components are built.
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+    /// The maximum number of components to build at once. When components
+    /// are built concurrently, each line of build output is prefixed with
+    /// the ID of the component it is from.
+    pub jobs: usize,
+    /// Build components even if the files matching their `watch` patterns
+    /// are unchanged since they were last built.
+    pub force: bool,
+    /// The build profile whose build commands and sources are used for the
+    /// components which define it.
+    pub profile: Option<String>,
+    /// The environment, defined in the manifest, whose build command
+    /// overrides are used.
+    pub environment: Option<String>,
+    /// How build progress and the output of build commands are reported.
+    pub message_format: MessageFormat,
+    /// Run build commands in a container of each component's build image,
+    /// rather than on the host.
+    pub in_container: bool,
+    /// The cache from which to restore the modules of components whose
+    /// sources and build configuration are the same as in an earlier build,
+    /// instead of building them, and to which built modules are added.
+    pub cache: Option<ArtifactCache>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            jobs: 1,
+            force: false,
+            profile: None,
+            environment: None,
+            message_format: MessageFormat::Human,
+            in_container: false,
+            cache: None,
+        }
+    }
+}
+
+/// If present, run the build command of each component, skipping those whose
+/// sources are unchanged since they were last built successfully.
+pub async fn build_with_options(
+    manifest_file: &Path,
+    component_ids: &[String],
+    options: &BuildOptions,
+) -> Result<()> {
+    let manifest = spin_loader::local::read_manifest_toml_in_environment(
+        manifest_file,
+        options.environment.as_deref(),
+    )
+    .with_context(|| format!("Cannot read manifest file from {}", manifest_file.display()))?;
+    let mut app = manifest.try_into().map(BuildAppInfoAnyVersion::into_v1)?;
     let app_dir = parent_dir(manifest_file)?;
 
+    if let Some(profile) = &options.profile {
+        let mut defined = false;
+        for component in &mut app.components {
+            if let Some(component_profile) = component.profile.as_ref().and_then(|p| p.get(profile))
+            {
+                let build_command = component.build.as_mut().map(|build| &mut build.command);
+                component_profile.apply(&mut component.source, build_command);
+                defined = true;
+            }
+        }
+        if !defined {
+            bail!("No component defines the build profile {profile:?}");
+        }
+    }
+
     let components_to_build = if component_ids.is_empty() {
         app.components
     } else {
@@ -42,37 +129,234 @@ pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()>
             .collect()
     };
 
+    let json = options.message_format == MessageFormat::Json;
     if components_to_build.iter().all(|c| c.build.is_none()) {
-        println!("None of the components have a build command.");
-        println!("For information on specifying a build command, see https://developer.fermyon.com/spin/build#setting-up-for-spin-build.");
+        if json {
+            BuildMessage::BuildFinished { success: true }.emit();
+        } else {
+            println!("None of the components have a build command.");
+            println!("For information on specifying a build command, see https://developer.fermyon.com/spin/build#setting-up-for-spin-build.");
+        }
         return Ok(());
     }
 
-    components_to_build
-        .into_iter()
-        .map(|c| build_component(c, &app_dir))
-        .collect::<Result<Vec<_>, _>>()?;
+    let mut fingerprints = Fingerprints::load(&app_dir);
+    let mut to_build = vec![];
+    for component in components_to_build {
+        if component.build.is_none() {
+            continue;
+        }
+        let fingerprint = fingerprint::fingerprint(&component, &app_dir)?;
+        if !options.force && fingerprints.is_unchanged(&component, fingerprint.as_deref(), &app_dir)
+        {
+            if json {
+                BuildMessage::ComponentSkipped {
+                    component: &component.id,
+                }
+                .emit();
+            } else {
+                terminal::step!("Skipping", "component {} (sources unchanged)", component.id);
+            }
+            continue;
+        }
+        if let (false, Some(cache), Some(key), RawModuleSource::FileReference(module)) = (
+            options.force,
+            &options.cache,
+            &fingerprint,
+            &component.source,
+        ) {
+            if cache.restore(key, &app_dir.join(module)).await {
+                if json {
+                    BuildMessage::ComponentRestored {
+                        component: &component.id,
+                    }
+                    .emit();
+                } else {
+                    terminal::step!(
+                        "Restored",
+                        "component {} from the build cache",
+                        component.id
+                    );
+                }
+                fingerprints.record(component.id, key.clone());
+                continue;
+            }
+        }
+        to_build.push((component, fingerprint));
+    }
+
+    // Toolchains in build containers are the images' responsibility.
+    if !options.in_container {
+        preflight::check_toolchains(to_build.iter().flat_map(|(component, _)| {
+            let build = component
+                .build
+                .as_ref()
+                .expect("only components with builds");
+            build
+                .pre
+                .iter()
+                .flatten()
+                .chain(std::iter::once(&build.command))
+                .chain(build.post.iter().flatten())
+                .map(String::as_str)
+        }))?;
+    }
+
+    let modules: HashMap<_, _> = to_build
+        .iter()
+        .filter_map(|(component, _)| match &component.source {
+            RawModuleSource::FileReference(module) => {
+                Some((component.id.clone(), app_dir.join(module)))
+            }
+            RawModuleSource::Url(_) | RawModuleSource::Git(_) => None,
+        })
+        .collect();
+
+    let concurrent = options.jobs > 1 && to_build.len() > 1;
+    let output = match options.message_format {
+        MessageFormat::Json => CommandOutput::Json,
+        MessageFormat::Human if concurrent => CommandOutput::Prefixed,
+        MessageFormat::Human => CommandOutput::Inherit,
+    };
+    let results = if !concurrent {
+        let mut results = vec![];
+        for (component, fingerprint) in to_build {
+            let id = component.id.clone();
+            let result = build_component(component, &app_dir, output, options.in_container);
+            let failed = result.is_err();
+            results.push((id, fingerprint, result));
+            if failed {
+                break;
+            }
+        }
+        results
+    } else {
+        futures::stream::iter(to_build)
+            .map(|(component, fingerprint)| {
+                let app_dir = app_dir.clone();
+                let id = component.id.clone();
+                let in_container = options.in_container;
+                async move {
+                    let result = tokio::task::spawn_blocking(move || {
+                        build_component(component, &app_dir, output, in_container)
+                    })
+                    .await
+                    .context("Build task panicked")
+                    .and_then(|result| result);
+                    (id, fingerprint, result)
+                }
+            })
+            .buffer_unordered(options.jobs)
+            .collect()
+            .await
+    };
+
+    // Let every build finish before reporting the first failure.
+    let mut first_error = None;
+    for (id, fingerprint, result) in results {
+        match (result, fingerprint) {
+            (Ok(()), Some(fingerprint)) => {
+                if let (Some(cache), Some(module)) = (&options.cache, modules.get(&id)) {
+                    cache.store(&fingerprint, module).await;
+                }
+                fingerprints.record(id, fingerprint);
+            }
+            (Ok(()), None) => {}
+            (Err(err), _) => {
+                fingerprints.forget(&id);
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    fingerprints.save();
+    if json {
+        BuildMessage::BuildFinished {
+            success: first_error.is_none(),
+        }
+        .emit();
+    }
+    if let Some(err) = first_error {
+        return Err(err);
+    }
 
-    terminal::step!("Finished", "building all Spin components");
+    if !json {
+        terminal::step!("Finished", "building all Spin components");
+    }
     Ok(())
 }
 
-/// Run the build command of the component.
-fn build_component(raw: RawComponentManifest, app_dir: &Path) -> Result<()> {
+/// How the output of a component's build commands is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CommandOutput {
+    /// Written directly to the terminal.
+    Inherit,
+    /// Each line prefixed with the component ID, so that the output of
+    /// concurrent builds can be told apart.
+    Prefixed,
+    /// Each line reported as a JSON message.
+    Json,
+}
+
+/// Run the build command of the component, and its pre- and post-build
+/// commands, optionally in a container.
+fn build_component(
+    raw: RawComponentManifest,
+    app_dir: &Path,
+    output: CommandOutput,
+    in_container: bool,
+) -> Result<()> {
+    let id = raw.id.clone();
+    let result = run_component_build(raw, app_dir, output, in_container);
+    if output == CommandOutput::Json {
+        BuildMessage::ComponentFinished {
+            component: &id,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+        }
+        .emit();
+    }
+    result
+}
+
+fn run_component_build(
+    raw: RawComponentManifest,
+    app_dir: &Path,
+    output: CommandOutput,
+    in_container: bool,
+) -> Result<()> {
     match raw.build {
         Some(b) => {
-            terminal::step!("Building", "component {} with `{}`", raw.id, b.command);
             let workdir = construct_workdir(app_dir, b.workdir.as_ref())?;
-            if b.workdir.is_some() {
-                println!("Working directory: {:?}", workdir);
+            let container = if in_container {
+                Some(BuildContainer::new(&raw.id, &b, app_dir)?)
+            } else {
+                None
+            };
+            let shell = |command: &str| match &container {
+                Some(container) => container.exec(command),
+                None => Exec::shell(command).cwd(&workdir),
+            };
+            if output == CommandOutput::Json {
+                BuildMessage::ComponentStarted {
+                    component: &raw.id,
+                    command: &b.command,
+                }
+                .emit();
+            } else {
+                terminal::step!("Building", "component {} with `{}`", raw.id, b.command);
+                if let Some(container) = &container {
+                    println!("Container image: {}", container.image());
+                }
+                if b.workdir.is_some() {
+                    println!("Working directory: {:?}", workdir);
+                }
             }
 
-            let exit_status = Exec::shell(&b.command)
-                .cwd(workdir)
-                .stdout(Redirection::None)
-                .stderr(Redirection::None)
-                .stdin(Redirection::None)
-                .popen()
+            for command in b.pre.iter().flatten() {
+                run_hook(&raw.id, "pre-build", command, shell(command), output)?;
+            }
+
+            let exit_status = run_stage(&raw.id, "build", &b.command, shell(&b.command), output)
                 .map_err(|err| {
                     anyhow!(
                         "Cannot spawn build process '{:?}' for component {}: {}",
@@ -80,8 +364,7 @@ fn build_component(raw: RawComponentManifest, app_dir: &Path) -> Result<()> {
                         raw.id,
                         err
                     )
-                })?
-                .wait()?;
+                })?;
 
             if !exit_status.success() {
                 bail!(
@@ -91,12 +374,162 @@ fn build_component(raw: RawComponentManifest, app_dir: &Path) -> Result<()> {
                 );
             }
 
+            if let Some(preinit) = &b.preinit {
+                preinitialize_component(&raw.id, &raw.source, preinit, app_dir, output)?;
+            }
+
+            for command in b.post.iter().flatten() {
+                run_hook(&raw.id, "post-build", command, shell(command), output)?;
+            }
+
             Ok(())
         }
         _ => Ok(()),
     }
 }
 
+/// Runs one of the component's pre- or post-build commands.
+fn run_hook(id: &str, kind: &str, command: &str, exec: Exec, output: CommandOutput) -> Result<()> {
+    if output != CommandOutput::Json {
+        terminal::step!("Running", "{kind} command `{command}` for component {id}");
+    }
+    let exit_status = run_stage(id, kind, command, exec, output).map_err(|err| {
+        anyhow!("Cannot spawn {kind} command '{command}' for component {id}: {err}")
+    })?;
+    if !exit_status.success() {
+        bail!(
+            "The {kind} command '{command}' for component {id} failed with status {exit_status:?}"
+        );
+    }
+    Ok(())
+}
+
+const WIZER_COMMAND: &str = "wizer";
+const DEFAULT_INIT_FUNC: &str = "wizer.initialize";
+
+/// Runs the component's initialization function with Wizer, replacing the
+/// built module with the pre-initialized one.
+fn preinitialize_component(
+    id: &str,
+    source: &RawModuleSource,
+    preinit: &RawPreinitConfig,
+    app_dir: &Path,
+    output: CommandOutput,
+) -> Result<()> {
+    let RawModuleSource::FileReference(source) = source else {
+        bail!("Cannot pre-initialize component {id}: its source is not a local file");
+    };
+    let source = app_dir.join(source);
+    let init_func = preinit.init_func.as_deref().unwrap_or(DEFAULT_INIT_FUNC);
+    if output != CommandOutput::Json {
+        terminal::step!("Pre-initializing", "component {id} with `{init_func}`");
+    }
+
+    let output = source.with_extension("preinit.wasm");
+    let mut args = vec![
+        source.as_os_str().to_owned(),
+        "-o".into(),
+        output.as_os_str().to_owned(),
+        "--init-func".into(),
+        init_func.into(),
+        "--allow-wasi".into(),
+    ];
+    for dir in preinit.dirs.iter().flatten() {
+        args.push("--dir".into());
+        args.push(app_dir.join(dir).into_os_string());
+    }
+
+    let exit_status = run_stage(
+        id,
+        "preinit",
+        WIZER_COMMAND,
+        Exec::cmd(WIZER_COMMAND).args(&args).cwd(app_dir),
+        output,
+    )
+    .map_err(|err| {
+        anyhow!(
+            "Cannot run `{WIZER_COMMAND}` to pre-initialize component {id}: {err}. \
+            Pre-initialization requires Wizer, which can be installed with \
+            `cargo install wizer --all-features`."
+        )
+    })?;
+    if !exit_status.success() {
+        bail!("Pre-initializing component {id} failed with status {exit_status:?}");
+    }
+
+    std::fs::rename(&output, &source).with_context(|| {
+        format!(
+            "Cannot replace {} with the pre-initialized module",
+            source.display()
+        )
+    })
+}
+
+/// Runs one stage of the component's build, such as its build command,
+/// reporting when the stage starts and finishes in JSON output.
+fn run_stage(
+    component: &str,
+    stage: &str,
+    command: &str,
+    exec: Exec,
+    output: CommandOutput,
+) -> subprocess::Result<ExitStatus> {
+    if output != CommandOutput::Json {
+        return run_command(exec, component, output);
+    }
+    BuildMessage::CommandStarted {
+        component,
+        stage,
+        command,
+    }
+    .emit();
+    let exit_status = run_command(exec, component, output)?;
+    BuildMessage::CommandFinished {
+        component,
+        stage,
+        command,
+        success: exit_status.success(),
+        exit_code: match exit_status {
+            ExitStatus::Exited(code) => Some(code),
+            _ => None,
+        },
+    }
+    .emit();
+    Ok(exit_status)
+}
+
+/// Runs the command to completion, showing each line it writes to stdout or
+/// stderr as the output mode requires.
+fn run_command(
+    exec: Exec,
+    component: &str,
+    output: CommandOutput,
+) -> subprocess::Result<ExitStatus> {
+    let exec = exec.stdin(Redirection::None);
+    if output == CommandOutput::Inherit {
+        return exec
+            .stdout(Redirection::None)
+            .stderr(Redirection::None)
+            .join();
+    }
+
+    let mut process = exec
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Merge)
+        .popen()?;
+    let stdout = process.stdout.take().expect("stdout should be piped");
+    for line in BufReader::new(stdout).split(b'\n') {
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end();
+        match output {
+            CommandOutput::Json => BuildMessage::from_output(component, line).emit(),
+            _ => println!("[{component}] {line}"),
+        }
+    }
+    process.wait()
+}
+
 /// Constructs the absolute working directory in which to run the build command.
 fn construct_workdir(app_dir: &Path, workdir: Option<impl AsRef<Path>>) -> Result<PathBuf> {
     let mut cwd = app_dir.to_owned();
@@ -128,4 +561,29 @@ mod tests {
         let bad_trigger_file = test_data_root().join("bad_trigger.toml");
         build(&bad_trigger_file, &[]).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn concurrent_build_reports_failure() {
+        let manifest_file = test_data_root().join("failing_component.toml");
+        let options = BuildOptions {
+            jobs: 2,
+            force: true,
+            ..Default::default()
+        };
+        let err = build_with_options(&manifest_file, &[], &options)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("fails"), "{err:#}");
+        build_with_options(&manifest_file, &["succeeds".to_owned()], &options)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failing_pre_build_command_aborts_build() {
+        let manifest_file = test_data_root().join("failing_hook.toml");
+        let err = build(&manifest_file, &[]).await.unwrap_err();
+        assert!(err.to_string().contains("pre-build"), "{err:#}");
+        assert!(err.to_string().contains("exit 1"), "{err:#}");
+    }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
-use spin_loader::local::config::FixedStringVersion;
-use std::path::PathBuf;
+use spin_loader::local::config::{
+    FixedStringVersion, RawComponentProfile, RawModuleSource, RawPreinitConfig,
+};
+use std::{collections::HashMap, path::PathBuf};
 
 #[derive(Clone, Debug, Deserialize)]
 pub(crate) struct BuildAppInfoAnyVersion {
@@ -27,7 +29,9 @@ pub(crate) struct BuildAppInfoV1 {
 #[serde(rename_all = "snake_case")]
 pub(crate) struct RawComponentManifest {
     pub id: String,
+    pub source: RawModuleSource,
     pub build: Option<RawBuildConfig>,
+    pub profile: Option<HashMap<String, RawComponentProfile>>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -36,4 +40,8 @@ pub(crate) struct RawBuildConfig {
     pub command: String,
     pub workdir: Option<PathBuf>,
     pub watch: Option<Vec<String>>,
+    pub preinit: Option<RawPreinitConfig>,
+    pub pre: Option<Vec<String>>,
+    pub post: Option<Vec<String>>,
+    pub image: Option<String>,
 }
@@ -0,0 +1,115 @@
+//! Machine-readable build messages, for `spin build --message-format json`.
+//!
+//! Each message is a JSON object on its own line of standard output, whose
+//! `reason` field says what kind of message it is.
+
+use std::str::FromStr;
+
+use anyhow::bail;
+use serde::Serialize;
+
+/// How build progress and command output are reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Human-readable text.
+    #[default]
+    Human,
+    /// One JSON message per line.
+    Json,
+}
+
+impl FromStr for MessageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => bail!("unknown message format {s:?}: expected 'human' or 'json'"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub(crate) enum BuildMessage<'a> {
+    ComponentStarted {
+        component: &'a str,
+        command: &'a str,
+    },
+    ComponentSkipped {
+        component: &'a str,
+    },
+    ComponentRestored {
+        component: &'a str,
+    },
+    CommandStarted {
+        component: &'a str,
+        stage: &'a str,
+        command: &'a str,
+    },
+    CommandFinished {
+        component: &'a str,
+        stage: &'a str,
+        command: &'a str,
+        success: bool,
+        exit_code: Option<u32>,
+    },
+    // A line of command output which is not a compiler message.
+    Output {
+        component: &'a str,
+        line: &'a str,
+    },
+    // A line of command output which is itself a JSON object, such as a
+    // diagnostic from `cargo build --message-format json`.
+    CompilerMessage {
+        component: &'a str,
+        message: serde_json::Value,
+    },
+    ComponentFinished {
+        component: &'a str,
+        success: bool,
+        error: Option<String>,
+    },
+    BuildFinished {
+        success: bool,
+    },
+}
+
+impl<'a> BuildMessage<'a> {
+    /// Classifies a line of output from one of the component's commands.
+    pub fn from_output(component: &'a str, line: &'a str) -> Self {
+        match serde_json::from_str(line) {
+            Ok(message @ serde_json::Value::Object(_)) => {
+                Self::CompilerMessage { component, message }
+            }
+            _ => Self::Output { component, line },
+        }
+    }
+
+    pub fn emit(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{json}"),
+            Err(err) => tracing::warn!("Failed to serialize build message {self:?}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_output_lines_are_compiler_messages() {
+        let message = BuildMessage::from_output("test", r#"{"reason":"compiler-message"}"#);
+        assert!(matches!(message, BuildMessage::CompilerMessage { .. }));
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["reason"], "compiler-message");
+        assert_eq!(json["message"]["reason"], "compiler-message");
+
+        let message = BuildMessage::from_output("test", "   Compiling test v0.1.0");
+        assert!(matches!(message, BuildMessage::Output { .. }));
+        let message = BuildMessage::from_output("test", "42");
+        assert!(matches!(message, BuildMessage::Output { .. }));
+    }
+}
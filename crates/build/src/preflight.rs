@@ -0,0 +1,229 @@
+//! Checks, before building, that the toolchains which components' build
+//! commands use are installed.
+//!
+//! Only the toolchains used by Spin's templates are known. Commands which use
+//! anything else are left to fail, or not, when they run.
+
+use std::{collections::BTreeSet, path::PathBuf, process::Command};
+
+use anyhow::{bail, Result};
+
+struct Toolchain {
+    program: &'static str,
+    name: &'static str,
+    install: &'static str,
+    // The oldest version which can build Spin components, if any.
+    min_version: Option<(u32, u32, u32)>,
+}
+
+const TOOLCHAINS: &[Toolchain] = &[
+    Toolchain {
+        program: "cargo",
+        name: "Rust",
+        install: "Install Rust from https://www.rust-lang.org/tools/install",
+        min_version: None,
+    },
+    Toolchain {
+        program: "tinygo",
+        name: "TinyGo",
+        install: "Install TinyGo from https://tinygo.org/getting-started/install/",
+        min_version: Some((0, 27, 0)),
+    },
+    Toolchain {
+        program: "grain",
+        name: "Grain",
+        install: "Install Grain from https://grain-lang.org/docs/getting_grain",
+        min_version: None,
+    },
+    Toolchain {
+        program: "swiftc",
+        name: "SwiftWasm",
+        install: "Install SwiftWasm from https://book.swiftwasm.org/getting-started/setup.html",
+        min_version: None,
+    },
+    Toolchain {
+        program: "zig",
+        name: "Zig",
+        install: "Install Zig from https://ziglang.org/download/",
+        min_version: None,
+    },
+    Toolchain {
+        program: "npm",
+        name: "Node.js",
+        install: "Install Node.js from https://nodejs.org/",
+        min_version: None,
+    },
+];
+
+const RUST_WASM_TARGETS: &[&str] = &["wasm32-wasi", "wasm32-unknown-unknown"];
+
+/// Checks that the toolchains used by the build commands are installed,
+/// failing with installation guidance for any which are not.
+pub(crate) fn check_toolchains<'a>(commands: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut problems = BTreeSet::new();
+    for command in commands {
+        for args in simple_commands(command) {
+            let Some(toolchain) = TOOLCHAINS.iter().find(|t| t.program == args[0]) else {
+                continue;
+            };
+            if let Err(problem) = check_toolchain(toolchain) {
+                problems.insert(problem);
+                continue;
+            }
+            if toolchain.program == "cargo" {
+                if let Some(target) = rust_target(&args) {
+                    problems.extend(check_rust_target(target));
+                }
+            }
+        }
+    }
+    if !problems.is_empty() {
+        let problems = problems.into_iter().collect::<Vec<_>>();
+        bail!(
+            "Cannot build: required toolchains are missing.\n  {}",
+            problems.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+fn check_toolchain(toolchain: &Toolchain) -> Result<(), String> {
+    if find_program(toolchain.program).is_none() {
+        return Err(format!(
+            "{} (`{}`) is not installed. {}",
+            toolchain.name, toolchain.program, toolchain.install
+        ));
+    }
+    let Some(min_version) = toolchain.min_version else {
+        return Ok(());
+    };
+    let Some(version) = program_version(toolchain.program) else {
+        // Don't fail builds because of an unexpected version format.
+        tracing::debug!("Could not determine the version of {}", toolchain.program);
+        return Ok(());
+    };
+    if version < min_version {
+        let (major, minor, patch) = min_version;
+        let (found_major, found_minor, found_patch) = version;
+        return Err(format!(
+            "{} {found_major}.{found_minor}.{found_patch} is installed, but {major}.{minor}.{patch} or later is required. {}",
+            toolchain.name, toolchain.install
+        ));
+    }
+    Ok(())
+}
+
+fn check_rust_target(target: &str) -> Option<String> {
+    if !RUST_WASM_TARGETS.contains(&target) || find_program("rustup").is_none() {
+        return None;
+    }
+    let output = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .ok()?;
+    let installed = String::from_utf8_lossy(&output.stdout);
+    if installed.lines().any(|line| line.trim() == target) {
+        None
+    } else {
+        Some(format!(
+            "The Rust target {target} is not installed. Install it with `rustup target add {target}`"
+        ))
+    }
+}
+
+// Splits a shell command into the arguments of each of the simple commands
+// in it, skipping any leading environment variable assignments. This is only
+// an approximation of shell syntax, but enough to find the programs run.
+fn simple_commands(command: &str) -> Vec<Vec<&str>> {
+    command
+        .split(|c| matches!(c, ';' | '&' | '|' | '\n'))
+        .map(|simple| {
+            simple
+                .split_whitespace()
+                .skip_while(|word| word.contains('=') && !word.starts_with('-'))
+                .collect::<Vec<_>>()
+        })
+        .filter(|args| !args.is_empty())
+        .collect()
+}
+
+fn rust_target<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == "--target" {
+            return args.next().copied();
+        }
+        if let Some(target) = arg.strip_prefix("--target=") {
+            return Some(target);
+        }
+    }
+    None
+}
+
+fn find_program(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(program);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        let candidate = candidate.with_extension("exe");
+        (cfg!(windows) && candidate.is_file()).then_some(candidate)
+    })
+}
+
+fn program_version(program: &str) -> Option<(u32, u32, u32)> {
+    let output = Command::new(program).arg("version").output().ok()?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+// Finds the first version number, such as `0.27.0` in `tinygo version 0.27.0
+// linux/amd64`.
+fn parse_version(text: &str) -> Option<(u32, u32, u32)> {
+    text.split_whitespace().find_map(|word| {
+        let mut parts = word.trim_start_matches('v').split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = parts.next().map_or(Some(0), |patch| {
+            let digits = patch.split(|c: char| !c.is_ascii_digit()).next()?;
+            digits.parse().ok()
+        })?;
+        Some((major, minor, patch))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_programs_in_commands() {
+        let commands = simple_commands(
+            "RUSTFLAGS=-Ctarget-feature=+simd128 cargo build --target wasm32-wasi && wasm-opt -O out.wasm; npm run build | tee log",
+        );
+        let programs = commands.iter().map(|args| args[0]).collect::<Vec<_>>();
+        assert_eq!(programs, ["cargo", "wasm-opt", "npm", "tee"]);
+        assert_eq!(rust_target(&commands[0]), Some("wasm32-wasi"));
+        assert_eq!(
+            rust_target(&["cargo", "build", "--target=wasm32-wasi"]),
+            Some("wasm32-wasi")
+        );
+        assert_eq!(rust_target(&commands[2]), None);
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(
+            parse_version("tinygo version 0.27.0 linux/amd64 (using go version go1.20.3)"),
+            Some((0, 27, 0))
+        );
+        assert_eq!(parse_version("v18.16.0"), Some((18, 16, 0)));
+        assert_eq!(parse_version("zig 0.10"), Some((0, 10, 0)));
+        assert_eq!(parse_version("no version here"), None);
+    }
+
+    #[test]
+    fn unknown_programs_are_not_checked() {
+        check_toolchains(["echo done", "exit 1"]).unwrap();
+    }
+}
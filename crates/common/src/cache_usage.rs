@@ -0,0 +1,13 @@
+//! Tracking the use of cache entries
+
+use std::path::Path;
+
+use filetime::FileTime;
+
+/// Record that the cache entry at `path` was used, by updating its
+/// modification time, so that pruning the cache by age keeps the entries
+/// which are still in use. A failure only makes the entry look older, so is
+/// ignored.
+pub fn mark_used(path: impl AsRef<Path>) {
+    let _ = filetime::set_file_mtime(path, FileTime::now());
+}
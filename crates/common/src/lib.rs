@@ -9,6 +9,7 @@
 // - Code should have at least 2 dependents
 
 pub mod arg_parser;
+pub mod cache_usage;
 pub mod data_dir;
 pub mod sha256;
 pub mod sloth;
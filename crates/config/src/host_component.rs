@@ -4,22 +4,34 @@ use anyhow::Result;
 use once_cell::sync::OnceCell;
 use spin_app::{AppComponent, DynamicHostComponent};
 use spin_core::{async_trait, HostComponent};
-use spin_world::config;
+use spin_world::{config, wasi_config};
 
 use crate::{Error, Key, Provider, Resolver};
 
+#[derive(Clone)]
 pub struct ConfigHostComponent {
-    providers: Mutex<Vec<Box<dyn Provider>>>,
+    providers: Arc<Mutex<Vec<Box<dyn Provider>>>>,
     resolver: Arc<OnceCell<Resolver>>,
 }
 
 impl ConfigHostComponent {
     pub fn new(providers: Vec<Box<dyn Provider>>) -> Self {
         Self {
-            providers: Mutex::new(providers),
+            providers: Arc::new(Mutex::new(providers)),
             resolver: Default::default(),
         }
     }
+
+    /// Replaces the config Providers, for all components' subsequent config
+    /// lookups.
+    pub fn set_providers(&self, providers: Vec<Box<dyn Provider>>) {
+        // Held so that the resolver cannot be initialized concurrently
+        let mut pending = self.providers.lock().unwrap();
+        match self.resolver.get() {
+            Some(resolver) => resolver.set_providers(providers),
+            None => *pending = providers,
+        }
+    }
 }
 
 impl HostComponent for ConfigHostComponent {
@@ -29,7 +41,8 @@ impl HostComponent for ConfigHostComponent {
         linker: &mut spin_core::Linker<T>,
         get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
     ) -> anyhow::Result<()> {
-        config::add_to_linker(linker, get)
+        config::add_to_linker(linker, get)?;
+        wasi_config::add_to_linker(linker, get)
     }
 
     fn build_data(&self) -> Self::Data {
@@ -42,6 +55,7 @@ impl HostComponent for ConfigHostComponent {
 
 impl DynamicHostComponent for ConfigHostComponent {
     fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let mut pending = self.providers.lock().unwrap();
         self.resolver.get_or_try_init(|| {
             let mut resolver = Resolver::new(
                 component
@@ -55,11 +69,12 @@ impl DynamicHostComponent for ConfigHostComponent {
                     component.config().map(|(k, v)| (k.into(), v.into())),
                 )?;
             }
-            for provider in self.providers.lock().unwrap().drain(..) {
+            for provider in pending.drain(..) {
                 resolver.add_provider(provider);
             }
             Ok::<_, anyhow::Error>(resolver)
         })?;
+        drop(pending);
         data.component_id = Some(component.id().to_string());
         Ok(())
     }
@@ -89,6 +104,57 @@ impl config::Host for ComponentConfig {
     }
 }
 
+// Keys which the component's config doesn't define, including invalid ones,
+// have no value.
+#[async_trait]
+impl wasi_config::Host for ComponentConfig {
+    async fn get(
+        &mut self,
+        key: String,
+    ) -> Result<Result<Option<String>, wasi_config::ConfigError>> {
+        Ok(async {
+            // Set by DynamicHostComponent::update_data
+            let component_id = self.component_id.as_deref().unwrap();
+            let Ok(key) = Key::new(&key) else {
+                return Ok(None);
+            };
+            match self
+                .resolver
+                .get()
+                .unwrap()
+                .resolve(component_id, key)
+                .await
+            {
+                Ok(value) => Ok(Some(value)),
+                Err(Error::UnknownPath(_)) => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        }
+        .await)
+    }
+
+    async fn get_all(&mut self) -> Result<Result<Vec<(String, String)>, wasi_config::ConfigError>> {
+        Ok(async {
+            // Set by DynamicHostComponent::update_data
+            let component_id = self.component_id.as_deref().unwrap();
+            match self.resolver.get().unwrap().resolve_all(component_id).await {
+                Err(Error::UnknownPath(_)) => Ok(vec![]),
+                result => Ok(result?),
+            }
+        }
+        .await)
+    }
+}
+
+impl From<Error> for wasi_config::ConfigError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Provider(err) => Self::Upstream(err.to_string()),
+            other => Self::Io(other.to_string()),
+        }
+    }
+}
+
 impl From<Error> for config::Error {
     fn from(err: Error) -> Self {
         match err {
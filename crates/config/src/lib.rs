@@ -2,7 +2,12 @@ mod host_component;
 pub mod provider;
 mod template;
 
-use std::{borrow::Cow, collections::HashMap, fmt::Debug};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
 
 use spin_app::Variable;
 
@@ -16,7 +21,8 @@ pub struct Resolver {
     variables: HashMap<String, Variable>,
     // component ID -> config key -> config value template
     component_configs: HashMap<String, HashMap<String, Template>>,
-    providers: Vec<Box<dyn Provider>>,
+    // Replaced as a whole when the runtime config is reloaded
+    providers: RwLock<Vec<Arc<dyn Provider>>>,
 }
 
 impl Resolver {
@@ -56,7 +62,13 @@ impl Resolver {
 
     /// Adds a config Provider to the Resolver.
     pub fn add_provider(&mut self, provider: Box<dyn Provider>) {
-        self.providers.push(provider);
+        self.providers.get_mut().unwrap().push(provider.into());
+    }
+
+    /// Replaces the config Providers of the Resolver. Resolutions already in
+    /// progress complete with the previous Providers.
+    pub fn set_providers(&self, providers: Vec<Box<dyn Provider>>) {
+        *self.providers.write().unwrap() = providers.into_iter().map(Into::into).collect();
     }
 
     /// Resolves a config value for the given path.
@@ -73,6 +85,27 @@ impl Resolver {
         self.resolve_template(template).await
     }
 
+    /// Resolves all the config values of the given component, sorted by key.
+    pub async fn resolve_all(&self, component_id: &str) -> Result<Vec<(String, String)>> {
+        let configs = self.component_configs.get(component_id).ok_or_else(|| {
+            Error::UnknownPath(format!("no config for component {component_id:?}"))
+        })?;
+
+        let mut resolved = Vec::with_capacity(configs.len());
+        for (key, template) in configs {
+            resolved.push((key.clone(), self.resolve_template(template).await?));
+        }
+        resolved.sort();
+        Ok(resolved)
+    }
+
+    /// Resolves a template, such as `{{ feature_flag }}`, which is not part
+    /// of any component's config.
+    pub async fn resolve_expression(&self, expression: impl Into<String>) -> Result<String> {
+        let template = self.validate_template(expression.into())?;
+        self.resolve_template(&template).await
+    }
+
     async fn resolve_template(&self, template: &Template) -> Result<String> {
         let mut resolved_parts: Vec<Cow<str>> = Vec::with_capacity(template.parts().len());
         for part in template.parts() {
@@ -91,7 +124,8 @@ impl Resolver {
             // This should have been caught by validate_template
             .ok_or_else(|| Error::InvalidKey(key.to_string()))?;
 
-        for provider in &self.providers {
+        let providers = self.providers.read().unwrap().clone();
+        for provider in &providers {
             if let Some(value) = provider.get(&Key(key)).await.map_err(Error::Provider)? {
                 return Ok(value);
             }
@@ -260,6 +294,57 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolve_after_providers_replaced() {
+        let mut resolver = Resolver::new([(
+            "required".into(),
+            Variable {
+                default: Some("default-value".into()),
+                secret: false,
+            },
+        )])
+        .unwrap();
+        resolver
+            .add_component_config("test-component", [("k".into(), "{{ required }}".into())])
+            .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        let resolve = || resolver.resolve("test-component", Key("k"));
+        assert_eq!(resolve().await.unwrap(), "provider-value");
+
+        resolver.set_providers(vec![]);
+        assert_eq!(resolve().await.unwrap(), "default-value");
+    }
+
+    #[tokio::test]
+    async fn resolve_all_component_config() {
+        let mut resolver = Resolver::new([(
+            "required".into(),
+            Variable {
+                default: None,
+                secret: false,
+            },
+        )])
+        .unwrap();
+        resolver
+            .add_component_config(
+                "test-component",
+                [
+                    ("static_key".into(), "static-value".into()),
+                    ("provided_key".into(), "{{ required }}".into()),
+                ],
+            )
+            .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        assert_eq!(
+            resolver.resolve_all("test-component").await.unwrap(),
+            [
+                ("provided_key".to_string(), "provider-value".to_string()),
+                ("static_key".to_string(), "static-value".to_string()),
+            ]
+        );
+        resolver.resolve_all("other-component").await.unwrap_err();
+    }
+
     #[test]
     fn keys_good() {
         for key in ["a", "abc", "a1b2c3", "a_1", "a_1_b_3"] {
@@ -1,35 +1,192 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 use vaultrs::{
-    client::{VaultClient, VaultClientSettingsBuilder},
+    api::AuthInfo,
+    auth::{approle, kubernetes},
+    client::{Client, VaultClient, VaultClientSettingsBuilder},
     error::ClientError,
-    kv2,
+    kv2, token,
 };
 
 use crate::{Key, Provider};
 
+/// The default location of the service account token inside a Kubernetes pod.
+pub const DEFAULT_KUBERNETES_JWT_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+// Tokens are renewed (or re-acquired) when they are this close to expiring.
+const TOKEN_RENEWAL_MARGIN: Duration = Duration::from_secs(30);
+
 /// A config Provider that uses HashiCorp Vault.
 #[derive(Debug)]
 pub struct VaultProvider {
     url: String,
-    token: String,
+    auth: VaultAuth,
     mount: String,
     prefix: Option<String>,
+    namespace: Option<String>,
+    token: Mutex<Option<VaultToken>>,
+}
+
+/// The method used by a [`VaultProvider`] to authenticate with Vault.
+#[derive(Debug)]
+pub enum VaultAuth {
+    /// A static Vault token.
+    Token(String),
+    /// The AppRole auth method.
+    AppRole {
+        /// The mount path of the AppRole auth method, e.g. "approle".
+        mount: String,
+        role_id: String,
+        secret_id: String,
+    },
+    /// The Kubernetes auth method, using a service account token.
+    Kubernetes {
+        /// The mount path of the Kubernetes auth method, e.g. "kubernetes".
+        mount: String,
+        role: String,
+        /// Path to the service account JWT.
+        jwt_path: PathBuf,
+    },
 }
 
 impl VaultProvider {
     pub fn new(
         url: impl Into<String>,
-        token: impl Into<String>,
+        auth: VaultAuth,
         mount: impl Into<String>,
         prefix: Option<impl Into<String>>,
     ) -> Self {
         Self {
             url: url.into(),
-            token: token.into(),
+            auth,
             mount: mount.into(),
             prefix: prefix.map(Into::into),
+            namespace: None,
+            token: Default::default(),
+        }
+    }
+
+    /// Sets the Vault Enterprise namespace used for all requests.
+    pub fn with_namespace(mut self, namespace: Option<impl Into<String>>) -> Self {
+        self.namespace = namespace.map(Into::into);
+        self
+    }
+
+    async fn client(&self) -> Result<VaultClient> {
+        let mut settings = VaultClientSettingsBuilder::default()
+            .address(&self.url)
+            .build()?;
+        settings.namespace = self.namespace.clone();
+        let mut client = VaultClient::new(settings)?;
+        let token = self.current_token(&mut client).await?;
+        client.set_token(&token);
+        Ok(client)
+    }
+
+    // Returns a valid token, renewing or re-acquiring the cached one if it is
+    // close to expiry.
+    async fn current_token(&self, client: &mut VaultClient) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some(current) = cached.as_ref() {
+            if !current.needs_renewal() {
+                return Ok(current.value.clone());
+            }
+            if current.renewable {
+                client.set_token(&current.value);
+                match token::renew_self(client, None).await {
+                    Ok(info) => {
+                        let renewed = VaultToken::from_auth_info(info);
+                        let value = renewed.value.clone();
+                        *cached = Some(renewed);
+                        return Ok(value);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to renew Vault token; re-authenticating: {e}")
+                    }
+                }
+            }
+        }
+        let fresh = self.login(client).await?;
+        let value = fresh.value.clone();
+        *cached = Some(fresh);
+        Ok(value)
+    }
+
+    async fn login(&self, client: &mut VaultClient) -> Result<VaultToken> {
+        match &self.auth {
+            VaultAuth::Token(value) => {
+                client.set_token(value);
+                // Look up the token's TTL so that periodic tokens get renewed.
+                // Tokens without lookup permission are assumed not to expire.
+                let (renewable, expires_at) = match token::lookup_self(client).await {
+                    Ok(info) if info.ttl > 0 => (
+                        info.renewable,
+                        Some(Instant::now() + Duration::from_secs(info.ttl)),
+                    ),
+                    _ => (false, None),
+                };
+                Ok(VaultToken {
+                    value: value.clone(),
+                    renewable,
+                    expires_at,
+                })
+            }
+            VaultAuth::AppRole {
+                mount,
+                role_id,
+                secret_id,
+            } => {
+                let info = approle::login(client, mount, role_id, secret_id)
+                    .await
+                    .context("Failed to authenticate with Vault using AppRole")?;
+                Ok(VaultToken::from_auth_info(info))
+            }
+            VaultAuth::Kubernetes {
+                mount,
+                role,
+                jwt_path,
+            } => {
+                let jwt = tokio::fs::read_to_string(jwt_path).await.with_context(|| {
+                    format!("Failed to read Kubernetes service account token {jwt_path:?}")
+                })?;
+                let info = kubernetes::login(client, mount, role, jwt.trim())
+                    .await
+                    .context("Failed to authenticate with Vault using Kubernetes auth")?;
+                Ok(VaultToken::from_auth_info(info))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct VaultToken {
+    value: String,
+    renewable: bool,
+    expires_at: Option<Instant>,
+}
+
+impl VaultToken {
+    fn from_auth_info(info: AuthInfo) -> Self {
+        let expires_at = (info.lease_duration > 0)
+            .then(|| Instant::now() + Duration::from_secs(info.lease_duration));
+        Self {
+            value: info.client_token,
+            renewable: info.renewable,
+            expires_at,
+        }
+    }
+
+    fn needs_renewal(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => Instant::now() + TOKEN_RENEWAL_MARGIN >= expires_at,
+            None => false,
         }
     }
 }
@@ -42,12 +199,7 @@ struct Secret {
 #[async_trait]
 impl Provider for VaultProvider {
     async fn get(&self, key: &Key) -> Result<Option<String>> {
-        let client = VaultClient::new(
-            VaultClientSettingsBuilder::default()
-                .address(&self.url)
-                .token(&self.token)
-                .build()?,
-        )?;
+        let client = self.client().await?;
         let path = match &self.prefix {
             Some(prefix) => format!("{}/{}", prefix, key.0),
             None => key.0.to_string(),
@@ -0,0 +1,149 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use wasi_common as wasi_preview2;
+use wasi_common_preview1 as wasi_preview1;
+
+/// The wall clock time at which a [`ManualClock`] starts by default:
+/// 2023-01-01T00:00:00Z.
+const DEFAULT_START: Duration = Duration::from_secs(1_672_531_200);
+
+/// A clock which only moves when it is advanced, for making the time seen by
+/// guests reproducible in tests.
+///
+/// Clones share the same time, so a test can keep a clone and advance the
+/// clock of the stores it was given to (see [`crate::StoreBuilder::manual_clock`]).
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    state: Arc<Mutex<ClockState>>,
+    // The host instant which monotonic time is measured from, as guests
+    // using WASI Preview 1 see monotonic time as an instant.
+    base: Instant,
+}
+
+#[derive(Debug)]
+struct ClockState {
+    wall: SystemTime,
+    monotonic: Duration,
+}
+
+impl ManualClock {
+    /// Creates a clock whose wall time starts at the given time.
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ClockState {
+                wall: start,
+                monotonic: Duration::ZERO,
+            })),
+            base: Instant::now(),
+        }
+    }
+
+    /// Advances both the wall and monotonic time.
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.wall += by;
+        state.monotonic += by;
+    }
+
+    /// Sets the wall time, e.g. to just before a deadline. Monotonic time is
+    /// unaffected, so it can never go backwards.
+    pub fn set_wall_time(&self, time: SystemTime) {
+        self.state.lock().unwrap().wall = time;
+    }
+
+    /// Returns the wall time.
+    pub fn wall_time(&self) -> SystemTime {
+        self.state.lock().unwrap().wall
+    }
+
+    /// Returns the monotonic time, measured from when the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().monotonic
+    }
+
+    pub(crate) fn instant(&self) -> cap_std::time::Instant {
+        cap_std::time::Instant::from_std(self.base + self.elapsed())
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH + DEFAULT_START)
+    }
+}
+
+impl wasi_preview1::clocks::WasiSystemClock for ManualClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> cap_std::time::SystemTime {
+        cap_std::time::SystemTime::from_std(self.wall_time())
+    }
+}
+
+impl wasi_preview1::clocks::WasiMonotonicClock for ManualClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self, _precision: Duration) -> cap_std::time::Instant {
+        self.instant()
+    }
+}
+
+impl wasi_preview2::clocks::WasiWallClock for ManualClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.wall_time()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn dup(&self) -> Box<dyn wasi_preview2::clocks::WasiWallClock + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl wasi_preview2::clocks::WasiMonotonicClock for ManualClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.elapsed().as_nanos() as u64
+    }
+
+    fn dup(&self) -> Box<dyn wasi_preview2::clocks::WasiMonotonicClock + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_time() {
+        let clock = ManualClock::default();
+        let start = clock.wall_time();
+        clock.clone().advance(Duration::from_secs(90));
+        assert_eq!(clock.wall_time(), start + Duration::from_secs(90));
+        assert_eq!(clock.elapsed(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn setting_wall_time_leaves_monotonic_time() {
+        let clock = ManualClock::default();
+        clock.advance(Duration::from_secs(1));
+        clock.set_wall_time(UNIX_EPOCH);
+        assert_eq!(clock.wall_time(), UNIX_EPOCH);
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+    }
+}
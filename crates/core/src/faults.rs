@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+/// Latency and errors to inject into a component's calls to a host
+/// interface, so that its handling of slow or failing dependencies can be
+/// tested.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaultInjection {
+    /// How long to delay each call before it is made.
+    pub latency: Duration,
+    /// The probability, from 0 to 1, that a call fails instead of being made.
+    pub error_rate: f64,
+}
+
+impl FaultInjection {
+    /// Returns whether a call should fail, at random according to the error
+    /// rate.
+    pub fn should_fail(&self) -> bool {
+        self.error_rate > 0.0 && rand::random::<f64>() < self.error_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_rate_bounds_are_exact() {
+        let never = FaultInjection::default();
+        let always = FaultInjection {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            assert!(!never.should_fail());
+            assert!(always.should_fail());
+        }
+    }
+}
@@ -7,9 +7,12 @@
 
 #![deny(missing_docs)]
 
+mod clock;
+mod faults;
 mod host_component;
 mod io;
 mod limits;
+mod scratch;
 mod store;
 
 use std::{sync::Arc, time::Duration};
@@ -27,6 +30,8 @@ pub use wasmtime::{
 
 use self::host_component::{HostComponents, HostComponentsBuilder};
 
+pub use clock::ManualClock;
+pub use faults::FaultInjection;
 pub use host_component::{
     AnyHostComponentDataHandle, HostComponent, HostComponentDataHandle, HostComponentsData,
 };
@@ -50,6 +55,42 @@ impl Config {
     pub fn wasmtime_config(&mut self) -> &mut wasmtime::Config {
         &mut self.inner
     }
+
+    /// Enables fuel consumption, so that [`StoreBuilder::fuel`] limits how
+    /// much Wasm a [`Store`] may execute. This slows execution somewhat.
+    pub fn consume_fuel(&mut self, enable: bool) {
+        self.inner.consume_fuel(enable);
+    }
+}
+
+/// A resource limit which stopped the execution of a guest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// The guest ran past its deadline (see [`Store::set_deadline`]).
+    Deadline,
+    /// The guest ran out of fuel (see [`StoreBuilder::fuel`]).
+    Fuel,
+}
+
+impl LimitExceeded {
+    /// Returns the limit which caused the given guest execution error, if
+    /// any.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        match err.downcast_ref::<Trap>()? {
+            Trap::Interrupt => Some(Self::Deadline),
+            Trap::OutOfFuel => Some(Self::Fuel),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Deadline => f.write_str("execution time limit"),
+            Self::Fuel => f.write_str("fuel limit"),
+        }
+    }
 }
 
 impl Default for Config {
@@ -68,6 +109,8 @@ pub struct Data<T> {
     wasi: Wasi,
     host_components_data: HostComponentsData,
     store_limits: limits::StoreLimitsAsync,
+    // Held so that scratch directories are removed with the store.
+    _scratch_dirs: Vec<scratch::ScratchDir>,
 }
 
 impl<T> Data<T> {
@@ -22,6 +22,11 @@ impl ResourceLimiterAsync for StoreLimitsAsync {
         if can_grow {
             self.memory_consumed =
                 (self.memory_consumed as i64 + (desired as i64 - current as i64)) as u64;
+        } else {
+            tracing::warn!(
+                "Memory limit exceeded: instance requested {desired} bytes, limit is {} bytes",
+                self.max_memory_size.unwrap_or_default()
+            );
         }
         can_grow
     }
@@ -0,0 +1,671 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use tempfile::TempDir;
+
+/// A writable directory which is created empty for a store and removed when
+/// the store is dropped.
+pub(crate) struct ScratchDir {
+    dir: Option<TempDir>,
+    limit: Option<Arc<SizeLimit>>,
+}
+
+impl ScratchDir {
+    pub fn new(max_size: Option<u64>) -> std::io::Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let limit = max_size.map(|max_size| {
+            Arc::new(SizeLimit {
+                path: dir.path().to_owned(),
+                max_size,
+                estimate: Mutex::new(0),
+            })
+        });
+        Ok(Self {
+            dir: Some(dir),
+            limit,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir
+            .as_ref()
+            .expect("scratch dir is only taken on drop")
+            .path()
+    }
+
+    /// The limit which writes through the guest's view of the directory must
+    /// stay within, if any.
+    pub fn limit(&self) -> Option<Arc<SizeLimit>> {
+        self.limit.clone()
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        // Removing the directory walks it, so keep that off the executor
+        // when there is one.
+        if let Some(dir) = self.dir.take() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(handle) => drop(handle.spawn_blocking(move || drop(dir))),
+                Err(_) => drop(dir),
+            }
+        }
+    }
+}
+
+/// The maximum size of a scratch directory's contents.
+///
+/// Guest writes which could take the directory beyond the limit are refused.
+/// Measuring the directory means walking it, so the limit keeps a running
+/// overestimate of the size, which is only corrected by walking the
+/// directory when a write would take the estimate beyond the limit.
+pub(crate) struct SizeLimit {
+    path: PathBuf,
+    max_size: u64,
+    estimate: Mutex<u64>,
+}
+
+impl SizeLimit {
+    /// Makes room for a write which could grow the directory by up to
+    /// `growth` bytes, returning false if the write would exceed the limit.
+    async fn reserve(&self, growth: u64) -> bool {
+        if self.try_reserve(growth) {
+            return true;
+        }
+        let path = self.path.clone();
+        let Ok(size) = tokio::task::spawn_blocking(move || dir_size(&path)).await else {
+            return false;
+        };
+        *self.estimate.lock().unwrap() = size;
+        self.try_reserve(growth)
+    }
+
+    fn try_reserve(&self, growth: u64) -> bool {
+        let mut estimate = self.estimate.lock().unwrap();
+        match estimate.checked_add(growth) {
+            Some(size) if size <= self.max_size => {
+                *estimate = size;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// The most a write of `len` bytes at `offset` can grow a file of `size`
+// bytes by.
+fn write_growth(size: u64, offset: u64, len: u64) -> u64 {
+    offset.saturating_add(len).saturating_sub(size)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn bufs_len(bufs: &[std::io::IoSlice]) -> u64 {
+    bufs.iter().map(|buf| buf.len() as u64).sum()
+}
+
+// The WASI Preview 1 and Preview 2 implementations have separate, but
+// matching, directory and file traits, so the wrappers which enforce the
+// limit are duplicated between them.
+
+pub(crate) mod preview1 {
+    use std::{any::Any, io::SeekFrom, path::PathBuf, sync::Arc};
+
+    use wasi_common_preview1::{
+        dir::{ReaddirCursor, ReaddirEntity, WasiDir},
+        file::{Advice, FdFlags, FileType, Filestat, OFlags, WasiFile},
+        Error, ErrorExt, SystemTimeSpec,
+    };
+
+    use super::{bufs_len, write_growth, SizeLimit};
+
+    /// A directory whose files can only be written within a size limit.
+    pub(crate) struct SizeLimitedDir {
+        pub(crate) dir: Box<dyn WasiDir>,
+        pub(crate) limit: Arc<SizeLimit>,
+    }
+
+    struct SizeLimitedFile {
+        file: Box<dyn WasiFile>,
+        limit: Arc<SizeLimit>,
+    }
+
+    impl SizeLimitedFile {
+        async fn reserve(&self, offset: Option<u64>, len: u64) -> Result<(), Error> {
+            let size = self.file.get_filestat().await?.size;
+            let growth = match offset {
+                Some(offset) => write_growth(size, offset, len),
+                // Appends and writes at the cursor grow the file by at most
+                // the write, or by the gap if the cursor is past the end.
+                None => {
+                    let position = self.file.seek(SeekFrom::Current(0)).await?;
+                    write_growth(size, position.max(size), len)
+                }
+            };
+            if self.limit.reserve(growth).await {
+                Ok(())
+            } else {
+                Err(Error::too_big())
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WasiDir for SizeLimitedDir {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn open_file(
+            &self,
+            symlink_follow: bool,
+            path: &str,
+            oflags: OFlags,
+            read: bool,
+            write: bool,
+            fdflags: FdFlags,
+        ) -> Result<Box<dyn WasiFile>, Error> {
+            let file = self
+                .dir
+                .open_file(symlink_follow, path, oflags, read, write, fdflags)
+                .await?;
+            Ok(Box::new(SizeLimitedFile {
+                file,
+                limit: self.limit.clone(),
+            }))
+        }
+
+        async fn open_dir(
+            &self,
+            symlink_follow: bool,
+            path: &str,
+        ) -> Result<Box<dyn WasiDir>, Error> {
+            let dir = self.dir.open_dir(symlink_follow, path).await?;
+            Ok(Box::new(SizeLimitedDir {
+                dir,
+                limit: self.limit.clone(),
+            }))
+        }
+
+        async fn create_dir(&self, path: &str) -> Result<(), Error> {
+            self.dir.create_dir(path).await
+        }
+
+        async fn readdir(
+            &self,
+            cursor: ReaddirCursor,
+        ) -> Result<Box<dyn Iterator<Item = Result<ReaddirEntity, Error>> + Send>, Error> {
+            self.dir.readdir(cursor).await
+        }
+
+        async fn symlink(&self, old_path: &str, new_path: &str) -> Result<(), Error> {
+            self.dir.symlink(old_path, new_path).await
+        }
+
+        async fn remove_dir(&self, path: &str) -> Result<(), Error> {
+            self.dir.remove_dir(path).await
+        }
+
+        async fn unlink_file(&self, path: &str) -> Result<(), Error> {
+            self.dir.unlink_file(path).await
+        }
+
+        async fn read_link(&self, path: &str) -> Result<PathBuf, Error> {
+            self.dir.read_link(path).await
+        }
+
+        async fn get_filestat(&self) -> Result<Filestat, Error> {
+            self.dir.get_filestat().await
+        }
+
+        async fn get_path_filestat(
+            &self,
+            path: &str,
+            follow_symlinks: bool,
+        ) -> Result<Filestat, Error> {
+            self.dir.get_path_filestat(path, follow_symlinks).await
+        }
+
+        // Files may be moved or linked out of the directory, but not into it,
+        // as that would bypass the limit.
+        async fn rename(
+            &self,
+            path: &str,
+            dest_dir: &dyn WasiDir,
+            dest_path: &str,
+        ) -> Result<(), Error> {
+            match dest_dir.as_any().downcast_ref::<Self>() {
+                Some(dest_dir) => self.dir.rename(path, &*dest_dir.dir, dest_path).await,
+                None => self.dir.rename(path, dest_dir, dest_path).await,
+            }
+        }
+
+        async fn hard_link(
+            &self,
+            path: &str,
+            target_dir: &dyn WasiDir,
+            target_path: &str,
+        ) -> Result<(), Error> {
+            match target_dir.as_any().downcast_ref::<Self>() {
+                Some(target_dir) => {
+                    self.dir
+                        .hard_link(path, &*target_dir.dir, target_path)
+                        .await
+                }
+                None => self.dir.hard_link(path, target_dir, target_path).await,
+            }
+        }
+
+        async fn set_times(
+            &self,
+            path: &str,
+            atime: Option<SystemTimeSpec>,
+            mtime: Option<SystemTimeSpec>,
+            follow_symlinks: bool,
+        ) -> Result<(), Error> {
+            self.dir
+                .set_times(path, atime, mtime, follow_symlinks)
+                .await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WasiFile for SizeLimitedFile {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn get_filetype(&self) -> Result<FileType, Error> {
+            self.file.get_filetype().await
+        }
+
+        fn isatty(&self) -> bool {
+            self.file.isatty()
+        }
+
+        async fn datasync(&self) -> Result<(), Error> {
+            self.file.datasync().await
+        }
+
+        async fn sync(&self) -> Result<(), Error> {
+            self.file.sync().await
+        }
+
+        async fn get_fdflags(&self) -> Result<FdFlags, Error> {
+            self.file.get_fdflags().await
+        }
+
+        async fn set_fdflags(&mut self, flags: FdFlags) -> Result<(), Error> {
+            self.file.set_fdflags(flags).await
+        }
+
+        async fn get_filestat(&self) -> Result<Filestat, Error> {
+            self.file.get_filestat().await
+        }
+
+        async fn set_filestat_size(&self, size: u64) -> Result<(), Error> {
+            self.reserve(Some(size), 0).await?;
+            self.file.set_filestat_size(size).await
+        }
+
+        async fn advise(&self, offset: u64, len: u64, advice: Advice) -> Result<(), Error> {
+            self.file.advise(offset, len, advice).await
+        }
+
+        async fn allocate(&self, offset: u64, len: u64) -> Result<(), Error> {
+            self.reserve(Some(offset), len).await?;
+            self.file.allocate(offset, len).await
+        }
+
+        async fn set_times(
+            &self,
+            atime: Option<SystemTimeSpec>,
+            mtime: Option<SystemTimeSpec>,
+        ) -> Result<(), Error> {
+            self.file.set_times(atime, mtime).await
+        }
+
+        async fn read_vectored<'a>(
+            &self,
+            bufs: &mut [std::io::IoSliceMut<'a>],
+        ) -> Result<u64, Error> {
+            self.file.read_vectored(bufs).await
+        }
+
+        async fn read_vectored_at<'a>(
+            &self,
+            bufs: &mut [std::io::IoSliceMut<'a>],
+            offset: u64,
+        ) -> Result<u64, Error> {
+            self.file.read_vectored_at(bufs, offset).await
+        }
+
+        async fn write_vectored<'a>(&self, bufs: &[std::io::IoSlice<'a>]) -> Result<u64, Error> {
+            self.reserve(None, bufs_len(bufs)).await?;
+            self.file.write_vectored(bufs).await
+        }
+
+        async fn write_vectored_at<'a>(
+            &self,
+            bufs: &[std::io::IoSlice<'a>],
+            offset: u64,
+        ) -> Result<u64, Error> {
+            self.reserve(Some(offset), bufs_len(bufs)).await?;
+            self.file.write_vectored_at(bufs, offset).await
+        }
+
+        async fn seek(&self, pos: SeekFrom) -> Result<u64, Error> {
+            self.file.seek(pos).await
+        }
+
+        async fn peek(&self, buf: &mut [u8]) -> Result<u64, Error> {
+            self.file.peek(buf).await
+        }
+
+        fn num_ready_bytes(&self) -> Result<u64, Error> {
+            self.file.num_ready_bytes()
+        }
+
+        async fn readable(&self) -> Result<(), Error> {
+            self.file.readable().await
+        }
+
+        async fn writable(&self) -> Result<(), Error> {
+            self.file.writable().await
+        }
+    }
+}
+
+pub(crate) mod preview2 {
+    use std::{any::Any, path::PathBuf, sync::Arc};
+
+    use wasi_common::{
+        dir::{ReaddirCursor, ReaddirEntity, WasiDir},
+        file::{Advice, FdFlags, FileType, Filestat, OFlags, WasiFile},
+        Error, ErrorExt, SystemTimeSpec,
+    };
+
+    use super::{bufs_len, write_growth, SizeLimit};
+
+    /// A directory whose files can only be written within a size limit.
+    pub(crate) struct SizeLimitedDir {
+        pub(crate) dir: Box<dyn WasiDir>,
+        pub(crate) limit: Arc<SizeLimit>,
+    }
+
+    struct SizeLimitedFile {
+        file: Box<dyn WasiFile>,
+        limit: Arc<SizeLimit>,
+    }
+
+    impl SizeLimitedFile {
+        async fn reserve(&self, offset: Option<u64>, len: u64) -> Result<(), Error> {
+            let size = self.file.get_filestat().await?.size;
+            let growth = match offset {
+                Some(offset) => write_growth(size, offset, len),
+                // Appends grow the file by exactly the write.
+                None => len,
+            };
+            if self.limit.reserve(growth).await {
+                Ok(())
+            } else {
+                Err(Error::too_big())
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WasiDir for SizeLimitedDir {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn open_file(
+            &self,
+            symlink_follow: bool,
+            path: &str,
+            oflags: OFlags,
+            read: bool,
+            write: bool,
+            fdflags: FdFlags,
+        ) -> Result<Box<dyn WasiFile>, Error> {
+            let file = self
+                .dir
+                .open_file(symlink_follow, path, oflags, read, write, fdflags)
+                .await?;
+            Ok(Box::new(SizeLimitedFile {
+                file,
+                limit: self.limit.clone(),
+            }))
+        }
+
+        async fn open_dir(
+            &self,
+            symlink_follow: bool,
+            path: &str,
+        ) -> Result<Box<dyn WasiDir>, Error> {
+            let dir = self.dir.open_dir(symlink_follow, path).await?;
+            Ok(Box::new(SizeLimitedDir {
+                dir,
+                limit: self.limit.clone(),
+            }))
+        }
+
+        async fn create_dir(&self, path: &str) -> Result<(), Error> {
+            self.dir.create_dir(path).await
+        }
+
+        async fn readdir(
+            &self,
+            cursor: ReaddirCursor,
+        ) -> Result<Box<dyn Iterator<Item = Result<ReaddirEntity, Error>> + Send>, Error> {
+            self.dir.readdir(cursor).await
+        }
+
+        async fn symlink(&self, old_path: &str, new_path: &str) -> Result<(), Error> {
+            self.dir.symlink(old_path, new_path).await
+        }
+
+        async fn remove_dir(&self, path: &str) -> Result<(), Error> {
+            self.dir.remove_dir(path).await
+        }
+
+        async fn unlink_file(&self, path: &str) -> Result<(), Error> {
+            self.dir.unlink_file(path).await
+        }
+
+        async fn read_link(&self, path: &str) -> Result<PathBuf, Error> {
+            self.dir.read_link(path).await
+        }
+
+        async fn get_filestat(&self) -> Result<Filestat, Error> {
+            self.dir.get_filestat().await
+        }
+
+        async fn get_path_filestat(
+            &self,
+            path: &str,
+            follow_symlinks: bool,
+        ) -> Result<Filestat, Error> {
+            self.dir.get_path_filestat(path, follow_symlinks).await
+        }
+
+        // Files may be moved or linked out of the directory, but not into it,
+        // as that would bypass the limit.
+        async fn rename(
+            &self,
+            path: &str,
+            dest_dir: &dyn WasiDir,
+            dest_path: &str,
+        ) -> Result<(), Error> {
+            match dest_dir.as_any().downcast_ref::<Self>() {
+                Some(dest_dir) => self.dir.rename(path, &*dest_dir.dir, dest_path).await,
+                None => self.dir.rename(path, dest_dir, dest_path).await,
+            }
+        }
+
+        async fn hard_link(
+            &self,
+            path: &str,
+            target_dir: &dyn WasiDir,
+            target_path: &str,
+        ) -> Result<(), Error> {
+            match target_dir.as_any().downcast_ref::<Self>() {
+                Some(target_dir) => {
+                    self.dir
+                        .hard_link(path, &*target_dir.dir, target_path)
+                        .await
+                }
+                None => self.dir.hard_link(path, target_dir, target_path).await,
+            }
+        }
+
+        async fn set_times(
+            &self,
+            path: &str,
+            atime: Option<SystemTimeSpec>,
+            mtime: Option<SystemTimeSpec>,
+            follow_symlinks: bool,
+        ) -> Result<(), Error> {
+            self.dir
+                .set_times(path, atime, mtime, follow_symlinks)
+                .await
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WasiFile for SizeLimitedFile {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        async fn get_filetype(&self) -> Result<FileType, Error> {
+            self.file.get_filetype().await
+        }
+
+        fn isatty(&self) -> bool {
+            self.file.isatty()
+        }
+
+        async fn datasync(&self) -> Result<(), Error> {
+            self.file.datasync().await
+        }
+
+        async fn sync(&self) -> Result<(), Error> {
+            self.file.sync().await
+        }
+
+        async fn get_fdflags(&self) -> Result<FdFlags, Error> {
+            self.file.get_fdflags().await
+        }
+
+        async fn set_fdflags(&mut self, flags: FdFlags) -> Result<(), Error> {
+            self.file.set_fdflags(flags).await
+        }
+
+        async fn get_filestat(&self) -> Result<Filestat, Error> {
+            self.file.get_filestat().await
+        }
+
+        async fn set_filestat_size(&self, size: u64) -> Result<(), Error> {
+            self.reserve(Some(size), 0).await?;
+            self.file.set_filestat_size(size).await
+        }
+
+        async fn advise(&self, offset: u64, len: u64, advice: Advice) -> Result<(), Error> {
+            self.file.advise(offset, len, advice).await
+        }
+
+        async fn set_times(
+            &self,
+            atime: Option<SystemTimeSpec>,
+            mtime: Option<SystemTimeSpec>,
+        ) -> Result<(), Error> {
+            self.file.set_times(atime, mtime).await
+        }
+
+        async fn read_vectored_at<'a>(
+            &self,
+            bufs: &mut [std::io::IoSliceMut<'a>],
+            offset: u64,
+        ) -> Result<(u64, bool), Error> {
+            self.file.read_vectored_at(bufs, offset).await
+        }
+
+        async fn write_vectored_at<'a>(
+            &self,
+            bufs: &[std::io::IoSlice<'a>],
+            offset: u64,
+        ) -> Result<u64, Error> {
+            self.reserve(Some(offset), bufs_len(bufs)).await?;
+            self.file.write_vectored_at(bufs, offset).await
+        }
+
+        async fn append<'a>(&self, bufs: &[std::io::IoSlice<'a>]) -> Result<u64, Error> {
+            self.reserve(None, bufs_len(bufs)).await?;
+            self.file.append(bufs).await
+        }
+
+        async fn readable(&self) -> Result<(), Error> {
+            self.file.readable().await
+        }
+
+        async fn writable(&self) -> Result<(), Error> {
+            self.file.writable().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dir_size_includes_subdirectories() {
+        let scratch = ScratchDir::new(None).unwrap();
+        std::fs::write(scratch.path().join("a"), [0; 10]).unwrap();
+        std::fs::create_dir(scratch.path().join("sub")).unwrap();
+        std::fs::write(scratch.path().join("sub/b"), [0; 5]).unwrap();
+        assert_eq!(dir_size(scratch.path()), 15);
+
+        let path = scratch.path().to_owned();
+        drop(scratch);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn writes_are_refused_beyond_the_limit() {
+        let scratch = ScratchDir::new(Some(10)).unwrap();
+        let limit = scratch.limit().unwrap();
+        assert!(limit.reserve(6).await);
+        std::fs::write(scratch.path().join("a"), [0; 6]).unwrap();
+        assert!(!limit.reserve(6).await);
+
+        // The estimate is corrected from what is actually in the directory
+        std::fs::write(scratch.path().join("a"), [0; 2]).unwrap();
+        assert!(limit.reserve(6).await);
+        std::fs::write(scratch.path().join("b"), [0; 6]).unwrap();
+        assert!(!limit.reserve(3).await);
+    }
+
+    #[test]
+    fn write_growth_counts_gaps() {
+        assert_eq!(write_growth(10, 0, 5), 0);
+        assert_eq!(write_growth(10, 8, 5), 3);
+        assert_eq!(write_growth(10, 20, 5), 15);
+    }
+}
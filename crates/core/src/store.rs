@@ -1,6 +1,8 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::StdRng, SeedableRng};
 use std::{
     io::{Read, Write},
+    net::SocketAddr,
     path::{Path, PathBuf},
     time::{Duration, Instant},
 };
@@ -11,9 +13,11 @@ use wasi_common_preview1::{self as wasi_preview1, dir::DirCaps, file::FileCaps};
 use wasmtime_wasi as wasmtime_wasi_preview1;
 
 use crate::{
+    clock::ManualClock,
     host_component::{HostComponents, HostComponentsData},
     io::OutputBuffer,
     limits::StoreLimitsAsync,
+    scratch::{self, ScratchDir},
     Data,
 };
 
@@ -146,7 +150,14 @@ pub struct StoreBuilder {
     wasi: std::result::Result<Wasi, String>,
     host_components_data: HostComponentsData,
     store_limits: StoreLimitsAsync,
+    fuel: Option<u64>,
+    scratch_dirs: Vec<ScratchDir>,
     next_preopen_index: u32,
+    manual_clock: Option<ManualClock>,
+    random_seed: Option<u64>,
+    // Whether the WASI context has been configured, after which a Preview 1
+    // context can no longer be replaced.
+    wasi_configured: bool,
 }
 
 impl StoreBuilder {
@@ -163,7 +174,12 @@ impl StoreBuilder {
             wasi: Ok(wasi),
             host_components_data: host_components.new_data(),
             store_limits: StoreLimitsAsync::default(),
+            fuel: None,
+            scratch_dirs: vec![],
             next_preopen_index: WASI_FIRST_PREOPENED_DIR_FD,
+            manual_clock: None,
+            random_seed: None,
+            wasi_configured: false,
         }
     }
 
@@ -175,6 +191,16 @@ impl StoreBuilder {
         self.store_limits = StoreLimitsAsync::new(Some(max_memory_size), None);
     }
 
+    /// Sets the fuel available to the [`Store`]. Execution traps when the
+    /// fuel runs out.
+    ///
+    /// The engine must have fuel consumption enabled (see
+    /// [`crate::Config::consume_fuel`]). Without a limit, a store of such an
+    /// engine has as much fuel as it can hold.
+    pub fn fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
     /// Inherit stdin from the host process.
     pub fn inherit_stdin(&mut self) {
         self.with_wasi(|wasi| match wasi {
@@ -361,6 +387,138 @@ impl StoreBuilder {
         Ok(())
     }
 
+    /// Allows the guest to open sockets to the given addresses with WASI
+    /// sockets; no other addresses are reachable. Addresses are not granted
+    /// per protocol.
+    pub fn allow_outbound_addrs(&mut self, addrs: &[SocketAddr]) -> Result<()> {
+        self.try_with_wasi(|wasi| match wasi {
+            Wasi::Preview1(_) => Err(anyhow!(
+                "`StoreBuilder::allow_outbound_addrs` only supported with WASI Preview 2"
+            )),
+            Wasi::Preview2(ctx) => {
+                for addr in addrs {
+                    ctx.pool
+                        .insert_socket_addr(*addr, cap_std::ambient_authority());
+                }
+                Ok(())
+            }
+        })
+    }
+
+    /// Replaces the guest's WASI wall and monotonic clocks with the given
+    /// manual clock, so that time only passes when the clock is advanced.
+    ///
+    /// A WASI Preview 1 context's clocks are fixed when it is created, so with
+    /// Preview 1 this replaces the context, and must be called before it is
+    /// otherwise configured, as it is by trigger hooks.
+    pub fn manual_clock(&mut self, clock: &ManualClock) {
+        self.manual_clock = Some(clock.clone());
+        if self.is_preview1() {
+            self.replace_preview1_ctx();
+        } else {
+            self.with_wasi(|wasi| {
+                if let Wasi::Preview2(ctx) = wasi {
+                    ctx.clocks.default_wall_clock = Box::new(clock.clone());
+                    ctx.clocks.default_monotonic_clock = Box::new(clock.clone());
+                }
+            });
+        }
+    }
+
+    /// Replaces the guest's WASI random source with one seeded with the given
+    /// value, so that it produces the same sequence in every instance.
+    ///
+    /// As with [`Self::manual_clock`], with WASI Preview 1 this must be called
+    /// before the context is otherwise configured.
+    pub fn random_seed(&mut self, seed: u64) {
+        self.random_seed = Some(seed);
+        if self.is_preview1() {
+            self.replace_preview1_ctx();
+        } else {
+            self.with_wasi(|wasi| {
+                if let Wasi::Preview2(ctx) = wasi {
+                    ctx.random = Box::new(StdRng::seed_from_u64(seed));
+                }
+            });
+        }
+    }
+
+    fn is_preview1(&self) -> bool {
+        matches!(self.wasi, Ok(Wasi::Preview1(_)))
+    }
+
+    // Replaces the Preview 1 context with a new one using the manual clock
+    // and random seed, if set. The context is shared once built, so these
+    // can't be changed in place.
+    fn replace_preview1_ctx(&mut self) {
+        if self.wasi_configured {
+            self.wasi = Err(
+                "the clock and random seed must be set before WASI Preview 1 is configured".into(),
+            );
+            return;
+        }
+        let clocks = match &self.manual_clock {
+            Some(clock) => wasi_preview1::WasiClocks::new()
+                .with_system(clock.clone())
+                .with_monotonic(clock.clone()),
+            None => wasmtime_wasi_preview1::clocks_ctx(),
+        };
+        let random: Box<dyn rand::RngCore + Send + Sync> = match self.random_seed {
+            Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+            None => wasmtime_wasi_preview1::random_ctx(),
+        };
+        self.wasi = Ok(Wasi::Preview1(wasi_preview1::WasiCtx::new(
+            random,
+            clocks,
+            wasmtime_wasi_preview1::sched_ctx(),
+            wasi_preview1::table::Table::new(),
+        )));
+    }
+
+    /// Creates a new, empty directory and preopens it read-write at the given
+    /// guest path. The directory is removed when the store is dropped.
+    ///
+    /// If `max_size` is set, guest writes which could take the directory's
+    /// contents beyond that many bytes fail.
+    pub fn scratch_dir(&mut self, guest_path: PathBuf, max_size: Option<u64>) -> Result<()> {
+        let scratch = ScratchDir::new(max_size).context("failed to create scratch directory")?;
+        let Some(limit) = scratch.limit() else {
+            self.read_write_preopened_dir(scratch.path(), guest_path)?;
+            self.scratch_dirs.push(scratch);
+            return Ok(());
+        };
+
+        let dir =
+            || cap_std::fs::Dir::open_ambient_dir(scratch.path(), cap_std::ambient_authority());
+        let path = guest_path
+            .to_str()
+            .ok_or_else(|| anyhow!("non-utf8 path: {}", guest_path.display()))?;
+
+        self.try_with_wasi(|wasi| {
+            match wasi {
+                Wasi::Preview1(ctx) => ctx.push_preopened_dir(
+                    Box::new(scratch::preview1::SizeLimitedDir {
+                        dir: Box::new(wasmtime_wasi_preview1::dir::Dir::from_cap_std(dir()?)),
+                        limit,
+                    }),
+                    path,
+                )?,
+                Wasi::Preview2(ctx) => ctx.push_preopened_dir(
+                    Box::new(scratch::preview2::SizeLimitedDir {
+                        dir: Box::new(wasmtime_wasi_preview2::dir::Dir::from_cap_std(dir()?)),
+                        limit,
+                    }),
+                    path,
+                )?,
+            }
+            Ok(())
+        })?;
+
+        self.next_preopen_index += 1;
+        self.scratch_dirs.push(scratch);
+        Ok(())
+    }
+
     /// Returns a mutable reference to the built
     pub fn host_components_data(&mut self) -> &mut HostComponentsData {
         &mut self.host_components_data
@@ -379,6 +537,7 @@ impl StoreBuilder {
                 wasi,
                 host_components_data: self.host_components_data,
                 store_limits: self.store_limits,
+                _scratch_dirs: self.scratch_dirs,
             },
         );
 
@@ -390,6 +549,14 @@ impl StoreBuilder {
         // forever" for any plausible tick interval.
         inner.set_epoch_deadline(u64::MAX / 2);
 
+        // A store starts with no fuel, so when the engine consumes fuel a
+        // store without a limit is given as much as it can hold.
+        match self.fuel {
+            Some(fuel) => inner.add_fuel(fuel)?,
+            None if inner.fuel_consumed().is_some() => inner.add_fuel(u64::MAX)?,
+            None => {}
+        }
+
         Ok(Store {
             inner,
             epoch_tick_interval: self.epoch_tick_interval,
@@ -409,6 +576,7 @@ impl StoreBuilder {
     }
 
     fn try_with_wasi(&mut self, f: impl FnOnce(&mut Wasi) -> Result<()>) -> Result<()> {
+        self.wasi_configured = true;
         let wasi = self
             .wasi
             .as_mut()
@@ -5,7 +5,8 @@ use std::{
 };
 
 use spin_core::{
-    Component, Config, Engine, HostComponent, I32Exit, Store, StoreBuilder, Trap, Wasi,
+    Component, Config, Engine, HostComponent, I32Exit, LimitExceeded, ManualClock, Store,
+    StoreBuilder, Trap, Wasi,
 };
 use tempfile::TempDir;
 use tokio::fs;
@@ -120,10 +121,53 @@ async fn test_set_deadline_violated() {
     )
     .await
     .unwrap_err();
+    assert_eq!(
+        LimitExceeded::from_error(&err),
+        Some(LimitExceeded::Deadline)
+    );
     let trap = err.downcast::<Trap>().expect("trap");
     assert_eq!(trap, Trap::Interrupt);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuel_exhausted() {
+    let mut config = test_config();
+    config.consume_fuel(true);
+    let mut builder = Engine::builder(&config).unwrap();
+    builder.add_host_component(MultiplierHostComponent).unwrap();
+    let engine = builder.build();
+
+    let err = run_core_wasi_test_engine(
+        &engine,
+        ["echo"],
+        |store_builder| store_builder.fuel(100),
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(LimitExceeded::from_error(&err), Some(LimitExceeded::Fuel));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuel_unlimited() {
+    let mut config = test_config();
+    config.consume_fuel(true);
+    let mut builder = Engine::builder(&config).unwrap();
+    builder.add_host_component(MultiplierHostComponent).unwrap();
+    let engine = builder.build();
+
+    // A store without a limit runs to completion.
+    let stdout = run_core_wasi_test_engine(
+        &engine,
+        ["echo"],
+        |store_builder| store_builder.stdin_pipe(Cursor::new(b"DATA")),
+        |_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(stdout, "DATA");
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_host_component() {
     let stdout = run_core_wasi_test(["multiply", "5"], |_| {}).await.unwrap();
@@ -168,6 +212,22 @@ fn test_config() -> Config {
     config
 }
 
+#[test]
+fn test_preview1_manual_clock_set_before_configuration() {
+    let engine = test_engine();
+
+    let mut store_builder = engine.store_builder(Wasi::new_preview1());
+    store_builder.manual_clock(&ManualClock::default());
+    store_builder.random_seed(42);
+    store_builder.args(["arg"]).unwrap();
+    store_builder.build::<()>().unwrap();
+
+    let mut store_builder = engine.store_builder(Wasi::new_preview1());
+    store_builder.args(["arg"]).unwrap();
+    store_builder.manual_clock(&ManualClock::default());
+    assert!(store_builder.build::<()>().is_err());
+}
+
 fn test_engine() -> Engine<()> {
     let mut builder = Engine::builder(&test_config()).unwrap();
     builder.add_host_component(MultiplierHostComponent).unwrap();
@@ -10,6 +10,10 @@ use toml_edit::Document;
 
 /// Diagnoses for app manifest format problems.
 pub mod manifest;
+/// Diagnoses provided by plugins.
+pub mod plugin;
+/// Diagnoses for runtime configuration problems.
+pub mod runtime_config;
 /// Test helpers.
 pub mod test;
 /// Diagnoses for Wasm source problems.
@@ -18,6 +22,7 @@ pub mod wasm;
 /// Configuration for an app to be checked for problems.
 pub struct Checkup {
     manifest_path: PathBuf,
+    runtime_config_path: Option<PathBuf>,
     diagnostics: Vec<Box<dyn BoxingDiagnostic>>,
 }
 
@@ -26,20 +31,39 @@ impl Checkup {
     pub fn new(manifest_path: impl Into<PathBuf>) -> Self {
         let mut checkup = Self {
             manifest_path: manifest_path.into(),
+            runtime_config_path: None,
             diagnostics: vec![],
         };
         checkup.add_diagnostic::<manifest::version::VersionDiagnostic>();
         checkup.add_diagnostic::<manifest::trigger::TriggerDiagnostic>();
+        checkup.add_diagnostic::<manifest::route::RouteDiagnostic>();
         checkup.add_diagnostic::<wasm::missing::WasmMissingDiagnostic>();
+        checkup.add_diagnostic::<wasm::world::WasmWorldDiagnostic>();
+        checkup.add_diagnostic::<runtime_config::connectivity::ConnectivityDiagnostic>();
+        checkup.add_diagnostic::<runtime_config::registry::RegistryLoginDiagnostic>();
         checkup
     }
 
+    /// Check the backends configured by the runtime config file at the given
+    /// path, as well as the app.
+    pub fn with_runtime_config_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.runtime_config_path = Some(path.into());
+        self
+    }
+
     /// Add a detectable problem to this checkup.
     pub fn add_diagnostic<D: Diagnostic + Default + 'static>(&mut self) -> &mut Self {
         self.diagnostics.push(Box::<D>::default());
         self
     }
 
+    /// Add a detectable problem to this checkup, checked by the given
+    /// diagnostic, such as a [`plugin::PluginDiagnostic`].
+    pub fn add_diagnostic_instance<D: Diagnostic + 'static>(&mut self, diagnostic: D) -> &mut Self {
+        self.diagnostics.push(Box::new(diagnostic));
+        self
+    }
+
     fn patient(&self) -> Result<PatientApp> {
         let path = &self.manifest_path;
         ensure!(
@@ -54,9 +78,17 @@ impl Checkup {
             .parse()
             .with_context(|| format!("Couldn't parse manifest file at {path:?} as valid TOML"))?;
 
+        if let Some(runtime_config_path) = &self.runtime_config_path {
+            ensure!(
+                runtime_config_path.is_file(),
+                "No runtime config file found at {runtime_config_path:?}"
+            );
+        }
+
         Ok(PatientApp {
             manifest_path: path.into(),
             manifest_doc,
+            runtime_config_path: self.runtime_config_path.clone(),
         })
     }
 
@@ -97,6 +129,8 @@ pub struct PatientApp {
     pub manifest_path: PathBuf,
     /// Parsed app manifest TOML document.
     pub manifest_doc: Document,
+    /// Path to the runtime config file the app is run with, if any.
+    pub runtime_config_path: Option<PathBuf>,
 }
 
 /// The Diagnose trait implements the detection of a particular Spin app problem.
@@ -115,9 +149,20 @@ pub trait Diagnostic: Send + Sync {
 
 /// The Diagnosis trait represents a detected problem with a Spin app.
 pub trait Diagnosis: Debug + Send + Sync + 'static {
+    /// Return a stable identifier for this kind of problem, e.g.
+    /// "manifest-version-missing", for tools which handle particular
+    /// problems.
+    fn id(&self) -> String;
+
     /// Return a human-friendly description of this problem.
     fn description(&self) -> String;
 
+    /// Return where this problem lies, or None if it can't be attributed to
+    /// one place.
+    fn location(&self) -> Option<Location> {
+        None
+    }
+
     /// Return true if this problem is "critical", i.e. if the app's
     /// configuration or environment is invalid. Return false for
     /// "non-critical" problems like deprecations.
@@ -132,6 +177,78 @@ pub trait Diagnosis: Debug + Send + Sync + 'static {
     }
 }
 
+/// Where a problem lies.
+#[derive(Clone, Debug)]
+pub enum Location {
+    /// An item in the app manifest, by its path of keys and array indexes,
+    /// e.g. `["trigger", "base"]`.
+    Manifest(Vec<String>),
+    /// An item in the app manifest component with the given ID, by its path
+    /// of keys within the component, e.g. `["trigger", "route"]`.
+    Component(String, Vec<String>),
+    /// An item in the runtime config file, by its path of keys and array
+    /// indexes, e.g. `["key_value_store", "default"]`.
+    RuntimeConfig(Vec<String>),
+    /// A file, such as a Wasm source.
+    File(PathBuf),
+}
+
+/// A [`Location`] resolved to a file, and to a position in it where known.
+/// Lines and columns start at 1.
+#[derive(Clone, Debug)]
+pub struct FileLocation {
+    /// The file.
+    pub path: PathBuf,
+    /// The line of the position, if known.
+    pub line: Option<usize>,
+    /// The column of the position, in characters, if known.
+    pub column: Option<usize>,
+}
+
+impl PatientApp {
+    /// Resolve a location to a file, and to a line and column in it. Returns
+    /// None if the location is in a file the app doesn't have.
+    pub fn resolve_location(&self, location: &Location) -> Option<FileLocation> {
+        let (path, keys) = match location {
+            Location::Manifest(keys) => (self.manifest_path.clone(), keys.clone()),
+            Location::Component(id, keys) => {
+                let index = self
+                    .manifest_doc
+                    .get("component")
+                    .and_then(|components| components.as_array_of_tables())
+                    .and_then(|components| {
+                        components.iter().position(|component| {
+                            component.get("id").and_then(|value| value.as_str())
+                                == Some(id.as_str())
+                        })
+                    });
+                let mut path = vec!["component".to_owned()];
+                path.extend(index.map(|index| index.to_string()));
+                if index.is_some() {
+                    path.extend(keys.iter().cloned());
+                }
+                (self.manifest_path.clone(), path)
+            }
+            Location::RuntimeConfig(keys) => (self.runtime_config_path.clone()?, keys.clone()),
+            Location::File(path) => {
+                return Some(FileLocation {
+                    path: path.clone(),
+                    line: None,
+                    column: None,
+                })
+            }
+        };
+        let position = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| spin_loader::local::validate::locate(&text, &keys));
+        Some(FileLocation {
+            path,
+            line: position.map(|position| position.line),
+            column: position.map(|position| position.column),
+        })
+    }
+}
+
 /// The Treatment trait represents a (potential) fix for a detected problem.
 #[async_trait]
 pub trait Treatment: Sync {
@@ -6,6 +6,8 @@ use toml_edit::Document;
 
 use crate::Treatment;
 
+/// Diagnose app manifest HTTP route and component trigger problems.
+pub mod route;
 /// Diagnose app manifest trigger config problems.
 pub mod trigger;
 /// Diagnose app manifest version problems.
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use spin_http::{routes::RoutePattern, WELL_KNOWN_PREFIX};
+use toml::Value;
+use toml_edit::{Document, Item, Table};
+
+use crate::{Diagnosis, Diagnostic, Location, PatientApp, Treatment};
+
+use super::ManifestTreatment;
+
+/// RouteDiagnostic detects HTTP routes which can't be reached and component
+/// trigger config which doesn't match the app trigger type.
+#[derive(Default)]
+pub struct RouteDiagnostic;
+
+#[async_trait]
+impl Diagnostic for RouteDiagnostic {
+    type Diagnosis = RouteDiagnosis;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        let manifest: Value = toml_edit::de::from_document(patient.manifest_doc.clone())?;
+        let Some(trigger_type) = manifest
+            .get("trigger")
+            .and_then(|trigger| trigger.get("type"))
+            .and_then(Value::as_str)
+        else {
+            return Ok(vec![]);
+        };
+        let base = manifest
+            .get("trigger")
+            .and_then(|trigger| trigger.get("base"))
+            .and_then(Value::as_str)
+            .unwrap_or("/");
+        let Some(Value::Array(components)) = manifest.get("component") else {
+            return Ok(vec![]);
+        };
+
+        let mut diags = vec![];
+        let mut routes: HashMap<RoutePattern, String> = HashMap::new();
+        for (index, component) in components.iter().enumerate() {
+            let id = component
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or("<missing ID>")
+                .to_string();
+            let Some(trigger) = component.get("trigger").and_then(Value::as_table) else {
+                continue;
+            };
+
+            if let Some(key) = foreign_trigger_key(trigger_type, trigger) {
+                diags.push(RouteDiagnosis::MismatchedTrigger {
+                    index,
+                    id: id.clone(),
+                    trigger_type: trigger_type.to_string(),
+                    key,
+                    has_own_key: match trigger_type {
+                        "redis" => trigger.contains_key("channel"),
+                        _ => trigger.contains_key("route"),
+                    },
+                });
+            }
+            if trigger_type != "http" {
+                continue;
+            }
+            let Some(route) = trigger.get("route").and_then(Value::as_str) else {
+                continue;
+            };
+
+            let pattern = RoutePattern::from(base, route);
+            if is_reserved(&pattern) {
+                diags.push(RouteDiagnosis::ReservedRoute {
+                    index,
+                    id,
+                    route: route.to_string(),
+                });
+            } else if let Some(shadowed_id) = routes.get(&pattern) {
+                diags.push(RouteDiagnosis::DuplicateRoute {
+                    index,
+                    id,
+                    route: route.to_string(),
+                    shadowed_id: shadowed_id.clone(),
+                });
+            } else {
+                routes.insert(pattern, id);
+            }
+        }
+
+        Ok(diags)
+    }
+}
+
+// Returns a key of the component trigger config which belongs to a
+// different trigger type than the app's.
+fn foreign_trigger_key(
+    trigger_type: &str,
+    trigger: &toml::map::Map<String, Value>,
+) -> Option<String> {
+    let foreign_keys: &[&str] = match trigger_type {
+        "http" => &["channel"],
+        "redis" => &["route", "executor", "auth"],
+        _ => &[],
+    };
+    foreign_keys
+        .iter()
+        .find(|key| trigger.contains_key(**key))
+        .map(|key| key.to_string())
+}
+
+fn is_reserved(pattern: &RoutePattern) -> bool {
+    let path = pattern.path_or_prefix();
+    let reserved = WELL_KNOWN_PREFIX.trim_end_matches('/');
+    path == reserved || path.starts_with(WELL_KNOWN_PREFIX)
+}
+
+/// RouteDiagnosis represents a problem with HTTP routes or component trigger
+/// config.
+#[derive(Debug)]
+pub enum RouteDiagnosis {
+    /// Two components have the same route, so requests only reach the later
+    /// one.
+    DuplicateRoute {
+        /// The index of the later component in the manifest.
+        index: usize,
+        /// The ID of the later component.
+        id: String,
+        /// The route, as written in the later component.
+        route: String,
+        /// The ID of the earlier component, which never receives requests.
+        shadowed_id: String,
+    },
+    /// A route is under the prefix Spin reserves for its own endpoints.
+    ReservedRoute {
+        /// The index of the component in the manifest.
+        index: usize,
+        /// The ID of the component.
+        id: String,
+        /// The route, as written in the component.
+        route: String,
+    },
+    /// A component's trigger config is for a different trigger type.
+    MismatchedTrigger {
+        /// The index of the component in the manifest.
+        index: usize,
+        /// The ID of the component.
+        id: String,
+        /// The app trigger type.
+        trigger_type: String,
+        /// The key which belongs to a different trigger type.
+        key: String,
+        /// Whether the component trigger config has the route, or channel,
+        /// which the app trigger type needs.
+        has_own_key: bool,
+    },
+}
+
+impl RouteDiagnosis {
+    fn index(&self) -> usize {
+        match self {
+            Self::DuplicateRoute { index, .. }
+            | Self::ReservedRoute { index, .. }
+            | Self::MismatchedTrigger { index, .. } => *index,
+        }
+    }
+
+    // The route a treatment gives the component, if it changes it.
+    fn new_route(&self) -> Option<String> {
+        match self {
+            Self::DuplicateRoute { id, route, .. } => {
+                Some(format!("/{id}/{}", route.trim_start_matches('/')))
+            }
+            Self::ReservedRoute { route, .. } => {
+                let reserved = WELL_KNOWN_PREFIX.trim_end_matches('/');
+                let route = route.strip_prefix(reserved).unwrap_or(route);
+                Some(if route.is_empty() { "/" } else { route }.to_string())
+            }
+            Self::MismatchedTrigger {
+                id,
+                trigger_type,
+                has_own_key: false,
+                ..
+            } if trigger_type == "http" => Some(format!("/{id}/...")),
+            Self::MismatchedTrigger { .. } => None,
+        }
+    }
+}
+
+impl Diagnosis for RouteDiagnosis {
+    fn id(&self) -> String {
+        match self {
+            Self::DuplicateRoute { .. } => "route-duplicate",
+            Self::ReservedRoute { .. } => "route-reserved",
+            Self::MismatchedTrigger { .. } => "trigger-mismatched",
+        }
+        .into()
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Self::DuplicateRoute {
+                id,
+                route,
+                shadowed_id,
+                ..
+            } => format!(
+                "Components {shadowed_id:?} and {id:?} both have route {route:?}; \
+                 {shadowed_id:?} will never receive requests"
+            ),
+            Self::ReservedRoute { id, route, .. } => format!(
+                "Component {id:?} route {route:?} is reserved for Spin's own endpoints under {WELL_KNOWN_PREFIX:?}"
+            ),
+            Self::MismatchedTrigger {
+                id,
+                trigger_type,
+                key,
+                ..
+            } => format!(
+                "Component {id:?} trigger config has {key:?}, which is not used by the {trigger_type} trigger"
+            ),
+        }
+    }
+
+    fn location(&self) -> Option<Location> {
+        let key = match self {
+            Self::MismatchedTrigger { key, .. } => key.clone(),
+            _ => "route".into(),
+        };
+        Some(Location::Manifest(vec![
+            "component".into(),
+            self.index().to_string(),
+            "trigger".into(),
+            key,
+        ]))
+    }
+
+    fn treatment(&self) -> Option<&dyn Treatment> {
+        match self {
+            // A channel can't be guessed from a route
+            Self::MismatchedTrigger {
+                trigger_type,
+                has_own_key: false,
+                ..
+            } if trigger_type != "http" => None,
+            _ => Some(self),
+        }
+    }
+}
+
+#[async_trait]
+impl ManifestTreatment for RouteDiagnosis {
+    fn summary(&self) -> String {
+        match (self, self.new_route()) {
+            (Self::MismatchedTrigger { id, key, .. }, Some(route)) => {
+                format!("Replace trigger.{key} with trigger.route {route:?} for component {id:?}")
+            }
+            (Self::MismatchedTrigger { id, key, .. }, None) => {
+                format!("Remove trigger.{key} from component {id:?}")
+            }
+            (Self::DuplicateRoute { id, .. } | Self::ReservedRoute { id, .. }, Some(route)) => {
+                format!("Set trigger.route {route:?} for component {id:?}")
+            }
+            _ => "[invalid treatment]".into(),
+        }
+    }
+
+    async fn treat_manifest(&self, doc: &mut Document) -> anyhow::Result<()> {
+        let components = doc
+            .get_mut("component")
+            .context("missing components")?
+            .as_array_of_tables_mut()
+            .context("component sections aren't an 'array of tables'")?;
+        let component = components
+            .get_mut(self.index())
+            .context("component not found")?;
+        if component.get("trigger").is_none() {
+            component.insert("trigger", Item::Table(Table::new()));
+        }
+        let trigger = component
+            .get_mut("trigger")
+            .unwrap()
+            .as_table_like_mut()
+            .context("existing trigger value is not a table")?;
+
+        match self {
+            Self::MismatchedTrigger {
+                trigger_type,
+                has_own_key: false,
+                ..
+            } if trigger_type != "http" => bail!("cannot be fixed"),
+            Self::MismatchedTrigger { key, .. } => {
+                trigger.remove(key);
+            }
+            _ => {}
+        }
+        if let Some(route) = self.new_route() {
+            trigger.insert("route", Item::Value(route.into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{run_broken_test, run_correct_test};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_correct() {
+        run_correct_test::<RouteDiagnostic>("manifest_route").await;
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_route() {
+        let diag = run_broken_test::<RouteDiagnostic>("manifest_route", "duplicate").await;
+        assert!(matches!(diag, RouteDiagnosis::DuplicateRoute { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_reserved_route() {
+        let diag = run_broken_test::<RouteDiagnostic>("manifest_route", "reserved").await;
+        assert!(matches!(diag, RouteDiagnosis::ReservedRoute { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_trigger() {
+        let diag = run_broken_test::<RouteDiagnostic>("manifest_route", "mismatched_trigger").await;
+        assert!(matches!(diag, RouteDiagnosis::MismatchedTrigger { .. }));
+    }
+}
@@ -3,7 +3,7 @@ use async_trait::async_trait;
 use toml::Value;
 use toml_edit::{Document, InlineTable, Item, Table};
 
-use crate::{Diagnosis, Diagnostic, PatientApp, Treatment};
+use crate::{Diagnosis, Diagnostic, Location, PatientApp, Treatment};
 
 use super::ManifestTreatment;
 
@@ -97,6 +97,10 @@ impl TriggerDiagnosis {
             return Some(Self::InvalidHttpComponentTrigger(id, "not a table"));
         };
         let Some(route) = trigger.get("route") else {
+            if trigger.contains_key("channel") {
+                // Reported, and fixed, as a mismatched trigger by RouteDiagnostic
+                return None;
+            }
             return Some(Self::HttpComponentTriggerMissingRoute(id, single_component));
         };
         if route.as_str().is_none() {
@@ -110,6 +114,17 @@ impl TriggerDiagnosis {
 }
 
 impl Diagnosis for TriggerDiagnosis {
+    fn id(&self) -> String {
+        match self {
+            Self::MissingAppTrigger => "trigger-missing",
+            Self::InvalidAppTrigger(_) => "trigger-invalid",
+            Self::HttpAppTriggerMissingBase => "trigger-http-missing-base",
+            Self::HttpComponentTriggerMissingRoute(_, _) => "trigger-http-missing-route",
+            Self::InvalidHttpComponentTrigger(_, _) => "trigger-http-component-invalid",
+        }
+        .into()
+    }
+
     fn description(&self) -> String {
         match self {
             Self::MissingAppTrigger => "missing top-level trigger config".into(),
@@ -126,6 +141,19 @@ impl Diagnosis for TriggerDiagnosis {
         }
     }
 
+    fn location(&self) -> Option<Location> {
+        match self {
+            Self::MissingAppTrigger => None,
+            Self::InvalidAppTrigger(_) | Self::HttpAppTriggerMissingBase => {
+                Some(Location::Manifest(vec!["trigger".into()]))
+            }
+            Self::HttpComponentTriggerMissingRoute(id, _)
+            | Self::InvalidHttpComponentTrigger(id, _) => {
+                Some(Location::Component(id.clone(), vec!["trigger".into()]))
+            }
+        }
+    }
+
     fn treatment(&self) -> Option<&dyn Treatment> {
         match self {
             Self::MissingAppTrigger | Self::HttpAppTriggerMissingBase => Some(self),
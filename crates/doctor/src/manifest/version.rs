@@ -4,7 +4,7 @@ use serde::Deserialize;
 use toml::Value;
 use toml_edit::{de::from_document, Document, Item};
 
-use crate::{Diagnosis, Diagnostic, PatientApp, Treatment};
+use crate::{Diagnosis, Diagnostic, Location, PatientApp, Treatment};
 
 use super::ManifestTreatment;
 
@@ -55,6 +55,15 @@ pub enum VersionDiagnosis {
 }
 
 impl Diagnosis for VersionDiagnosis {
+    fn id(&self) -> String {
+        match self {
+            Self::MissingVersion => "manifest-version-missing",
+            Self::OldVersionKey => "manifest-version-deprecated-key",
+            Self::WrongValue(_) => "manifest-version-invalid",
+        }
+        .into()
+    }
+
     fn description(&self) -> String {
         match self {
             Self::MissingVersion => "Manifest missing 'spin_manifest_version' key".into(),
@@ -65,6 +74,14 @@ impl Diagnosis for VersionDiagnosis {
         }
     }
 
+    fn location(&self) -> Option<Location> {
+        match self {
+            Self::MissingVersion => None,
+            Self::OldVersionKey => Some(Location::Manifest(vec![SPIN_VERSION.into()])),
+            Self::WrongValue(_) => Some(Location::Manifest(vec![SPIN_MANIFEST_VERSION.into()])),
+        }
+    }
+
     fn is_critical(&self) -> bool {
         !matches!(self, Self::OldVersionKey)
     }
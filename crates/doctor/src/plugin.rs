@@ -0,0 +1,283 @@
+//! A plugin contributes checks by declaring, in its plugin manifest, the
+//! arguments with which `spin doctor` should run it:
+//!
+//! ```json
+//! "doctor": { "args": ["doctor"] }
+//! ```
+//!
+//! The plugin is run once per request. Spin writes the request, a JSON
+//! object, to the plugin's standard input:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "command": "diagnose",
+//!   "manifest_path": "/path/to/spin.toml",
+//!   "runtime_config_path": null
+//! }
+//! ```
+//!
+//! For `diagnose`, the plugin writes the problems it finds to its standard
+//! output:
+//!
+//! ```json
+//! {
+//!   "diagnoses": [
+//!     {
+//!       "id": "missing-cloud-link",
+//!       "description": "App is not linked to a cloud app",
+//!       "critical": false,
+//!       "location": ["component", "0"],
+//!       "treatment": { "summary": "Link the app", "dry_run": "Run `spin cloud link`" }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `critical` defaults to true. `location`, if given, is the path of keys and
+//! array indexes of the manifest item the problem lies in.
+//!
+//! If the user accepts a treatment, Spin runs the plugin again with the
+//! `treat` command and the `diagnosis` ID. The plugin applies the fix, which
+//! may rewrite the manifest, and exits with success, or writes an error to
+//! its standard error and exits with failure.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{Diagnosis, Diagnostic, DryRunNotSupported, Location, PatientApp, Treatment};
+
+/// The version of the doctor plugin protocol which Spin speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// PluginDiagnostic runs the checks of an installed plugin.
+#[derive(Clone, Debug)]
+pub struct PluginDiagnostic {
+    name: String,
+    binary: PathBuf,
+    args: Vec<String>,
+}
+
+impl PluginDiagnostic {
+    /// Return a diagnostic which runs the plugin binary with the given
+    /// arguments.
+    pub fn new(name: impl Into<String>, binary: impl Into<PathBuf>, args: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            binary: binary.into(),
+            args,
+        }
+    }
+
+    async fn request(&self, request: &Request<'_>) -> Result<Vec<u8>> {
+        let mut child = Command::new(&self.binary)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Couldn't run plugin {:?}", self.name))?;
+
+        let input = serde_json::to_vec(request)?;
+        let mut stdin = child.stdin.take().context("plugin stdin not captured")?;
+        stdin
+            .write_all(&input)
+            .await
+            .with_context(|| format!("Couldn't send request to plugin {:?}", self.name))?;
+        drop(stdin);
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            bail!(
+                "Plugin {:?} failed: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output.stdout)
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    version: u32,
+    command: &'a str,
+    manifest_path: &'a Path,
+    runtime_config_path: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnosis: Option<&'a str>,
+}
+
+impl<'a> Request<'a> {
+    fn new(command: &'a str, patient: &'a PatientApp) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            command,
+            manifest_path: &patient.manifest_path,
+            runtime_config_path: patient.runtime_config_path.as_deref(),
+            diagnosis: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DiagnoseResponse {
+    diagnoses: Vec<PluginDiagnosisResponse>,
+}
+
+#[derive(Deserialize)]
+struct PluginDiagnosisResponse {
+    id: String,
+    description: String,
+    #[serde(default = "default_critical")]
+    critical: bool,
+    #[serde(default)]
+    location: Option<Vec<String>>,
+    #[serde(default)]
+    treatment: Option<PluginTreatment>,
+}
+
+fn default_critical() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginTreatment {
+    summary: String,
+    #[serde(default)]
+    dry_run: Option<String>,
+}
+
+#[async_trait]
+impl Diagnostic for PluginDiagnostic {
+    type Diagnosis = PluginDiagnosis;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        let output = self.request(&Request::new("diagnose", patient)).await?;
+        let response: DiagnoseResponse = serde_json::from_slice(&output)
+            .with_context(|| format!("Plugin {:?} returned invalid diagnoses", self.name))?;
+        Ok(response
+            .diagnoses
+            .into_iter()
+            .map(|diagnosis| PluginDiagnosis {
+                plugin: self.clone(),
+                id: diagnosis.id,
+                description: diagnosis.description,
+                critical: diagnosis.critical,
+                location: diagnosis.location,
+                treatment: diagnosis.treatment,
+            })
+            .collect())
+    }
+}
+
+/// PluginDiagnosis represents a problem found by a plugin.
+#[derive(Debug)]
+pub struct PluginDiagnosis {
+    plugin: PluginDiagnostic,
+    id: String,
+    description: String,
+    critical: bool,
+    location: Option<Vec<String>>,
+    treatment: Option<PluginTreatment>,
+}
+
+impl Diagnosis for PluginDiagnosis {
+    fn id(&self) -> String {
+        format!("{}/{}", self.plugin.name, self.id)
+    }
+
+    fn description(&self) -> String {
+        format!("{} (from plugin {:?})", self.description, self.plugin.name)
+    }
+
+    fn location(&self) -> Option<Location> {
+        self.location.clone().map(Location::Manifest)
+    }
+
+    fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    fn treatment(&self) -> Option<&dyn Treatment> {
+        self.treatment.is_some().then_some(self)
+    }
+}
+
+#[async_trait]
+impl Treatment for PluginDiagnosis {
+    fn summary(&self) -> String {
+        self.treatment
+            .as_ref()
+            .map(|treatment| treatment.summary.clone())
+            .unwrap_or_else(|| "[invalid treatment]".into())
+    }
+
+    async fn dry_run(&self, _patient: &PatientApp) -> Result<String> {
+        self.treatment
+            .as_ref()
+            .and_then(|treatment| treatment.dry_run.clone())
+            .ok_or_else(|| DryRunNotSupported.into())
+    }
+
+    async fn treat(&self, patient: &mut PatientApp) -> Result<()> {
+        let request = Request {
+            diagnosis: Some(&self.id),
+            ..Request::new("treat", patient)
+        };
+        self.plugin.request(&request).await?;
+
+        // The plugin may have rewritten the manifest
+        let path = &patient.manifest_path;
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read Spin app manifest file at {path:?}"))?;
+        patient.manifest_doc = contents
+            .parse()
+            .with_context(|| format!("Couldn't parse manifest file at {path:?} as valid TOML"))?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use crate::Checkup;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_plugin_diagnoses() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("plugin");
+        fs::write(
+            &binary,
+            r#"#!/bin/sh
+cat > /dev/null
+echo '{"diagnoses": [{"id": "unlinked", "description": "App is not linked", "critical": false, "treatment": {"summary": "Link the app"}}]}'
+"#,
+        )
+        .unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let patient = Checkup::new("tests/data/manifest_version_correct.toml")
+            .patient()
+            .unwrap();
+        let diags = PluginDiagnostic::new("test", binary, vec!["doctor".into()])
+            .diagnose(&patient)
+            .await
+            .unwrap();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].id, "unlinked");
+        assert!(!diags[0].is_critical());
+        assert_eq!(diags[0].treatment().unwrap().summary(), "Link the app");
+    }
+}
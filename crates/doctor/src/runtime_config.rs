@@ -0,0 +1,50 @@
+/// Diagnose runtime config backends which cannot be reached or used.
+pub mod connectivity;
+/// Diagnose saved registry credentials which are no longer accepted.
+pub mod registry;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use toml::Value;
+
+/// The parts of a runtime config file which configure backends. Each
+/// backend is kept as TOML, so that types this doesn't know how to check
+/// are skipped rather than rejected.
+#[derive(Debug, Default, Deserialize)]
+pub struct RuntimeConfigProbe {
+    /// `[[config_provider]]` sections.
+    #[serde(default)]
+    pub config_provider: Vec<Value>,
+    /// `[key_value_store.<name>]` sections.
+    #[serde(default)]
+    pub key_value_store: BTreeMap<String, Value>,
+    /// `[sqlite_database.<name>]` sections.
+    #[serde(default)]
+    pub sqlite_database: BTreeMap<String, Value>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl RuntimeConfigProbe {
+    /// Read the runtime config file at the given path.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read runtime config file at {path:?}"))?;
+        let mut probe: Self = toml::from_str(&contents)
+            .with_context(|| format!("Couldn't parse runtime config file at {path:?}"))?;
+        probe.base_dir = path.parent().unwrap_or(Path::new(".")).to_owned();
+        Ok(probe)
+    }
+
+    /// Resolve a path in the runtime config file, which is relative to the
+    /// file's directory.
+    pub fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.base_dir.join(path)
+    }
+}
@@ -0,0 +1,329 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use toml::Value;
+use url::Url;
+
+use crate::{Diagnosis, Diagnostic, Location, PatientApp};
+
+use super::RuntimeConfigProbe;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const PROBE_FILE: &str = ".spin-doctor-probe";
+
+/// ConnectivityDiagnostic detects runtime config backends, such as key value
+/// stores, databases and config providers, which the app could not use.
+#[derive(Default)]
+pub struct ConnectivityDiagnostic;
+
+#[async_trait]
+impl Diagnostic for ConnectivityDiagnostic {
+    type Diagnosis = ConnectivityDiagnosis;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        let Some(path) = &patient.runtime_config_path else {
+            return Ok(vec![]);
+        };
+        let config = RuntimeConfigProbe::from_file(path)?;
+
+        let mut backends = vec![];
+        for (name, store) in &config.key_value_store {
+            backends.extend(Backend::from_store("key_value_store", name, store, &config));
+        }
+        for (name, database) in &config.sqlite_database {
+            backends.extend(Backend::from_store(
+                "sqlite_database",
+                name,
+                database,
+                &config,
+            ));
+        }
+        for (index, provider) in config.config_provider.iter().enumerate() {
+            backends.extend(Backend::from_provider(index, provider));
+        }
+
+        let mut diagnoses = vec![];
+        for backend in backends {
+            if let Err(problem) = backend.probe().await {
+                diagnoses.push(ConnectivityDiagnosis {
+                    hint: backend.hint(&problem),
+                    problem: format!("{problem:#}"),
+                    section: backend.section,
+                    keys: backend.keys,
+                });
+            }
+        }
+        Ok(diagnoses)
+    }
+}
+
+// A backend configured by the runtime config. This is not `Debug`, so that
+// secrets such as tokens cannot end up in diagnoses or logs.
+struct Backend {
+    // Where the backend is configured, e.g. `key_value_store.default`.
+    section: String,
+    // The path of keys to the section, for locating it in the file.
+    keys: Vec<String>,
+    kind: BackendKind,
+}
+
+enum BackendKind {
+    /// A local SQLite file, for the `spin` key value store and database types.
+    File(PathBuf),
+    /// A Redis server.
+    Redis(String),
+    /// A network service which is only checked for reachability.
+    Remote(String),
+    /// A Vault server, with the token the app authenticates with, if any.
+    Vault {
+        url: String,
+        token: Option<String>,
+        namespace: Option<String>,
+    },
+}
+
+impl Backend {
+    fn from_store(
+        table: &str,
+        name: &str,
+        store: &Value,
+        config: &RuntimeConfigProbe,
+    ) -> Option<Self> {
+        let section = format!("{table}.{name}");
+        let kind = match store.get("type")?.as_str()? {
+            "spin" => BackendKind::File(config.resolve_path(store.get("path")?.as_str()?)),
+            // A Redis Cluster is probed through its first seed node.
+            "redis" => BackendKind::Redis(
+                store
+                    .get("url")
+                    .or_else(|| store.get("cluster_urls")?.as_array()?.first())?
+                    .as_str()?
+                    .to_owned(),
+            ),
+            "libsql" => BackendKind::Remote(store.get("url")?.as_str()?.to_owned()),
+            "azure_cosmos" => BackendKind::Remote(format!(
+                "https://{}.documents.azure.com",
+                store.get("account")?.as_str()?
+            )),
+            _ => return None,
+        };
+        Some(Self {
+            section,
+            keys: vec![table.to_owned(), name.to_owned()],
+            kind,
+        })
+    }
+
+    fn from_provider(index: usize, provider: &Value) -> Option<Self> {
+        if provider.get("type")?.as_str()? != "vault" {
+            return None;
+        }
+        let string = |key: &str| provider.get(key).and_then(Value::as_str).map(str::to_owned);
+        Some(Self {
+            section: format!("config_provider[{index}]"),
+            keys: vec!["config_provider".to_owned(), index.to_string()],
+            kind: BackendKind::Vault {
+                url: string("url")?,
+                token: string("token"),
+                namespace: string("namespace"),
+            },
+        })
+    }
+
+    async fn probe(&self) -> Result<()> {
+        match &self.kind {
+            BackendKind::File(path) => probe_writable(path),
+            BackendKind::Redis(url) => probe_reachable(url, 6379).await,
+            BackendKind::Remote(url) => probe_reachable(url, 443).await,
+            BackendKind::Vault {
+                url,
+                token,
+                namespace,
+            } => probe_vault(url, token.as_deref(), namespace.as_deref()).await,
+        }
+    }
+
+    fn hint(&self, problem: &anyhow::Error) -> String {
+        match &self.kind {
+            BackendKind::File(path) => format!(
+                "Check that you can write to {:?}, or change `path` in [{}]",
+                path.parent().unwrap_or(path),
+                self.section
+            ),
+            BackendKind::Redis(_) => format!(
+                "Check that the Redis server is running and that `url` or `cluster_urls` in [{}] is correct",
+                self.section
+            ),
+            BackendKind::Remote(_) => format!(
+                "Check your network connection and the address in [{}]",
+                self.section
+            ),
+            BackendKind::Vault { .. } if problem.is::<VaultTokenRejected>() => format!(
+                "Renew the Vault token, or update `token` in [{}]",
+                self.section
+            ),
+            BackendKind::Vault { .. } => format!(
+                "Check that the Vault server is running and that `url` in [{}] is correct",
+                self.section
+            ),
+        }
+    }
+}
+
+/// ConnectivityDiagnosis represents a runtime config backend which could not
+/// be used, with a hint as to how to fix it.
+#[derive(Debug)]
+pub struct ConnectivityDiagnosis {
+    /// Where the backend is configured, e.g. `key_value_store.default`.
+    pub section: String,
+    keys: Vec<String>,
+    /// What went wrong when the backend was checked.
+    pub problem: String,
+    /// How the problem might be fixed.
+    pub hint: String,
+}
+
+impl Diagnosis for ConnectivityDiagnosis {
+    fn id(&self) -> String {
+        "runtime-config-unusable".into()
+    }
+
+    fn location(&self) -> Option<Location> {
+        Some(Location::RuntimeConfig(self.keys.clone()))
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Runtime config [{}] is not usable: {}\nHint: {}",
+            self.section, self.problem, self.hint
+        )
+    }
+}
+
+// SQLite creates the file, and its directory, on first use, so check the
+// nearest directory which already exists.
+fn probe_writable(path: &Path) -> Result<()> {
+    if path.is_file() {
+        let metadata = fs::metadata(path).with_context(|| format!("cannot read {path:?}"))?;
+        if metadata.permissions().readonly() {
+            bail!("{path:?} is read-only");
+        }
+        return Ok(());
+    }
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| anyhow!("no parent directory of {path:?} exists"))?;
+    let probe = dir.join(PROBE_FILE);
+    fs::write(&probe, b"").with_context(|| format!("cannot write to {dir:?}"))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+async fn probe_reachable(url: &str, default_port: u16) -> Result<()> {
+    let parsed = Url::parse(url).with_context(|| format!("invalid URL {url:?}"))?;
+    let host = parsed
+        .host_str()
+        .with_context(|| format!("URL {url:?} has no host"))?;
+    let port = parsed.port().unwrap_or(default_port);
+    match tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e).with_context(|| format!("cannot connect to {host}:{port}")),
+        Err(_) => bail!("timed out connecting to {host}:{port}"),
+    }
+}
+
+async fn probe_vault(url: &str, token: Option<&str>, namespace: Option<&str>) -> Result<()> {
+    let base = url.trim_end_matches('/');
+    let client = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build()?;
+    let Some(token) = token else {
+        // AppRole and Kubernetes logins need the app's environment, so just
+        // check that the server answers.
+        client
+            .get(format!("{base}/v1/sys/health"))
+            .send()
+            .await
+            .with_context(|| format!("cannot reach Vault at {url}"))?;
+        return Ok(());
+    };
+
+    let mut request = client
+        .get(format!("{base}/v1/auth/token/lookup-self"))
+        .header("X-Vault-Token", token);
+    if let Some(namespace) = namespace {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("cannot reach Vault at {url}"))?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::FORBIDDEN => Err(VaultTokenRejected.into()),
+        status => bail!("Vault at {url} returned {status}"),
+    }
+}
+
+#[derive(Debug)]
+struct VaultTokenRejected;
+
+impl std::fmt::Display for VaultTokenRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Vault rejected the token; it may have expired")
+    }
+}
+
+impl std::error::Error for VaultTokenRejected {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use crate::{test::assert_single_diagnosis, Checkup};
+
+    use super::*;
+
+    fn patient(runtime_config: &str) -> (PatientApp, NamedTempFile) {
+        let mut file = NamedTempFile::new().expect("creating tempfile");
+        file.write_all(runtime_config.as_bytes())
+            .expect("writing runtime config");
+        let patient = Checkup::new("tests/data/manifest_version_correct.toml")
+            .with_runtime_config_file(file.path())
+            .patient()
+            .expect("valid patient");
+        (patient, file)
+    }
+
+    #[tokio::test]
+    async fn test_writable_sqlite_path_is_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let (patient, _file) = patient(&format!(
+            "[sqlite_database.default]\ntype = \"spin\"\npath = {:?}\n",
+            dir.path().join("data/sqlite.db")
+        ));
+        let diags = ConnectivityDiagnostic.diagnose(&patient).await.unwrap();
+        assert!(diags.is_empty(), "expected no problems; got {diags:?}");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_is_diagnosed() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let (patient, _file) = patient(&format!(
+            "[key_value_store.default]\ntype = \"redis\"\nurl = \"redis://127.0.0.1:{port}\"\n"
+        ));
+        let diag = assert_single_diagnosis::<ConnectivityDiagnostic>(&patient).await;
+        assert_eq!(diag.section, "key_value_store.default");
+        assert!(diag.hint.contains("Redis server"), "{}", diag.hint);
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{Diagnosis, Diagnostic, PatientApp};
+
+/// RegistryLoginDiagnostic detects credentials saved by `spin registry login`
+/// which their registries no longer accept.
+#[derive(Default)]
+pub struct RegistryLoginDiagnostic;
+
+#[async_trait]
+impl Diagnostic for RegistryLoginDiagnostic {
+    type Diagnosis = RegistryLoginDiagnosis;
+
+    async fn diagnose(&self, _patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        Ok(spin_oci::Client::check_saved_logins()
+            .await?
+            .into_iter()
+            .filter_map(|(server, result)| {
+                let problem = result.err()?;
+                Some(RegistryLoginDiagnosis {
+                    server,
+                    problem: format!("{problem:#}"),
+                })
+            })
+            .collect())
+    }
+}
+
+/// RegistryLoginDiagnosis represents saved registry credentials which were
+/// rejected or could not be checked.
+#[derive(Debug)]
+pub struct RegistryLoginDiagnosis {
+    /// The registry server.
+    pub server: String,
+    /// Why the credentials could not be used.
+    pub problem: String,
+}
+
+impl Diagnosis for RegistryLoginDiagnosis {
+    fn id(&self) -> String {
+        "registry-login-rejected".into()
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Saved credentials for registry {} are not working: {}\nHint: Run `spin registry login {}` again",
+            self.server, self.problem, self.server
+        )
+    }
+
+    fn is_critical(&self) -> bool {
+        // Only apps which are pulled from, or pushed to, the registry are
+        // affected.
+        false
+    }
+}
@@ -1,5 +1,7 @@
 /// Diagnose missing Wasm sources.
 pub mod missing;
+/// Diagnose Wasm sources which don't fit their trigger.
+pub mod world;
 
 use std::path::{Path, PathBuf};
 
@@ -9,8 +11,9 @@ use spin_loader::{
     local::config::RawComponentManifest,
     local::{canonicalize_and_absolutize, config::RawModuleSource},
 };
+use spin_manifest::TriggerConfig;
 
-use crate::{Diagnosis, Diagnostic, PatientApp};
+use crate::{Diagnosis, Diagnostic, Location, PatientApp};
 
 /// PatientWasm represents a Wasm source to be checked for problems.
 #[derive(Debug)]
@@ -55,6 +58,14 @@ impl PatientWasm {
     pub fn has_build(&self) -> bool {
         self.component.build.is_some()
     }
+
+    pub fn trigger(&self) -> &TriggerConfig {
+        &self.component.trigger
+    }
+
+    pub fn location(&self) -> Location {
+        Location::Component(self.component.id.clone(), vec!["source".into()])
+    }
 }
 
 /// WasmDiagnose helps implement [`Diagnose`] for Wasm source problems.
@@ -3,7 +3,7 @@ use std::process::Command;
 use anyhow::{ensure, Context, Result};
 use async_trait::async_trait;
 
-use crate::{Diagnosis, PatientApp, Treatment};
+use crate::{Diagnosis, Location, PatientApp, Treatment};
 
 use super::{PatientWasm, WasmDiagnostic};
 
@@ -47,6 +47,10 @@ impl WasmMissing {
 }
 
 impl Diagnosis for WasmMissing {
+    fn id(&self) -> String {
+        "wasm-missing".into()
+    }
+
     fn description(&self) -> String {
         let id = self.0.component_id();
         let Some(rel_path) = self.0.source_path() else {
@@ -55,6 +59,10 @@ impl Diagnosis for WasmMissing {
         format!("Component {id:?} source {rel_path:?} is missing")
     }
 
+    fn location(&self) -> Option<Location> {
+        Some(self.0.location())
+    }
+
     fn treatment(&self) -> Option<&dyn Treatment> {
         self.0.has_build().then_some(self)
     }
@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use spin_manifest::{HttpExecutor, TriggerConfig};
+use wasmparser::{Encoding, Parser, Payload};
+
+use crate::{Diagnosis, Location, PatientApp};
+
+use super::{PatientWasm, WasmDiagnostic};
+
+/// WasmWorldDiagnostic detects Wasm sources which can't be run by their
+/// component's trigger, e.g. because they were built for the wrong target.
+#[derive(Default)]
+pub struct WasmWorldDiagnostic;
+
+#[async_trait]
+impl WasmDiagnostic for WasmWorldDiagnostic {
+    type Diagnosis = WasmWorldDiagnosis;
+
+    async fn diagnose_wasm(
+        &self,
+        _app: &PatientApp,
+        wasm: PatientWasm,
+    ) -> anyhow::Result<Vec<Self::Diagnosis>> {
+        // Missing sources are diagnosed by WasmMissingDiagnostic
+        let Some(abs_path) = wasm.abs_source_path().filter(|path| path.exists()) else {
+            return Ok(vec![]);
+        };
+        let bytes = tokio::fs::read(&abs_path)
+            .await
+            .with_context(|| format!("Couldn't read Wasm source {abs_path:?}"))?;
+
+        let info = match WasmInfo::parse(&bytes) {
+            Ok(info) => info,
+            Err(err) => return Ok(vec![WasmWorldDiagnosis::Invalid(wasm, format!("{err:#}"))]),
+        };
+        if let Some(import) = info
+            .imports
+            .iter()
+            .find(|import| import.starts_with("__wbindgen"))
+        {
+            return Ok(vec![WasmWorldDiagnosis::WasmBindgen(wasm, import.clone())]);
+        }
+
+        let Some(expected) = Expected::for_trigger(wasm.trigger(), info.is_component) else {
+            return Ok(vec![]);
+        };
+        if expected.export.is_none() {
+            return Ok(vec![WasmWorldDiagnosis::ComponentNotSupported(
+                wasm,
+                expected.trigger,
+            )]);
+        }
+        if !info
+            .exports
+            .iter()
+            .any(|e| Some(e) == expected.export.as_ref())
+        {
+            return Ok(vec![WasmWorldDiagnosis::MissingExport {
+                wasm,
+                expected,
+                is_component: info.is_component,
+                exports: info.exports,
+            }]);
+        }
+        Ok(vec![])
+    }
+}
+
+// What a Wasm source imports and exports, at the top level.
+#[derive(Debug, Default)]
+struct WasmInfo {
+    is_component: bool,
+    imports: Vec<String>,
+    exports: Vec<String>,
+}
+
+impl WasmInfo {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut info = Self::default();
+        // Components may nest modules and components; only the outermost
+        // imports and exports matter.
+        let mut depth = 0;
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload? {
+                Payload::Version { encoding, .. } => {
+                    if depth == 0 {
+                        info.is_component = encoding == Encoding::Component;
+                    }
+                    depth += 1;
+                }
+                Payload::End(_) => depth -= 1,
+                Payload::ImportSection(reader) if depth == 1 => {
+                    for import in reader {
+                        info.imports.push(import?.module.to_string());
+                    }
+                }
+                Payload::ExportSection(reader) if depth == 1 => {
+                    for export in reader {
+                        info.exports.push(export?.name.to_string());
+                    }
+                }
+                Payload::ComponentImportSection(reader) if depth == 1 => {
+                    for import in reader {
+                        info.imports.push(import?.name.to_string());
+                    }
+                }
+                Payload::ComponentExportSection(reader) if depth == 1 => {
+                    for export in reader {
+                        info.exports.push(export?.name.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// The export a trigger needs to run a Wasm source.
+#[derive(Debug)]
+pub struct Expected {
+    /// The trigger, e.g. "HTTP".
+    pub trigger: &'static str,
+    /// The export the trigger calls, or None if the trigger can't run this
+    /// kind of Wasm source.
+    pub export: Option<String>,
+}
+
+impl Expected {
+    fn for_trigger(trigger: &TriggerConfig, is_component: bool) -> Option<Self> {
+        let (trigger, module_export, component_export) = match trigger {
+            TriggerConfig::Http(config) => match &config.executor {
+                // Wagi can only run modules
+                Some(HttpExecutor::Wagi(wagi)) => ("Wagi", wagi.entrypoint.clone(), None),
+                _ => ("HTTP", "handle-http-request".into(), Some("inbound-http")),
+            },
+            TriggerConfig::Redis(_) => (
+                "Redis",
+                "handle-redis-message".into(),
+                Some("inbound-redis"),
+            ),
+            TriggerConfig::External(_) => return None,
+        };
+        Some(Self {
+            trigger,
+            export: if is_component {
+                component_export.map(str::to_owned)
+            } else {
+                Some(module_export)
+            },
+        })
+    }
+}
+
+/// WasmWorldDiagnosis represents a Wasm source which its trigger can't run.
+#[derive(Debug)]
+pub enum WasmWorldDiagnosis {
+    /// The source isn't valid Wasm.
+    Invalid(PatientWasm, String),
+    /// The source was built with wasm-bindgen, for the browser.
+    WasmBindgen(PatientWasm, String),
+    /// The source is a component, which the trigger can't run.
+    ComponentNotSupported(PatientWasm, &'static str),
+    /// The source doesn't export what the trigger calls.
+    MissingExport {
+        /// The Wasm source.
+        wasm: PatientWasm,
+        /// What the trigger needs.
+        expected: Expected,
+        /// Whether the source is a component rather than a core module.
+        is_component: bool,
+        /// What the source does export.
+        exports: Vec<String>,
+    },
+}
+
+impl WasmWorldDiagnosis {
+    fn wasm(&self) -> &PatientWasm {
+        match self {
+            Self::Invalid(wasm, _)
+            | Self::WasmBindgen(wasm, _)
+            | Self::ComponentNotSupported(wasm, _)
+            | Self::MissingExport { wasm, .. } => wasm,
+        }
+    }
+}
+
+impl Diagnosis for WasmWorldDiagnosis {
+    fn id(&self) -> String {
+        match self {
+            Self::Invalid(..) => "wasm-invalid",
+            Self::WasmBindgen(..) => "wasm-bindgen",
+            Self::ComponentNotSupported(..) => "wasm-component-not-supported",
+            Self::MissingExport { .. } => "wasm-missing-export",
+        }
+        .into()
+    }
+
+    fn location(&self) -> Option<Location> {
+        Some(self.wasm().location())
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Self::Invalid(wasm, err) => format!(
+                "Component {:?} source is not valid Wasm: {err}",
+                wasm.component_id()
+            ),
+            Self::WasmBindgen(wasm, import) => format!(
+                "Component {:?} source imports {import:?}, so it was built with wasm-bindgen for the browser. Build it for the wasm32-wasi target instead",
+                wasm.component_id()
+            ),
+            Self::ComponentNotSupported(wasm, trigger) => format!(
+                "Component {:?} source is a Wasm component, but the {trigger} executor can only run core modules. Build it as a module for the wasm32-wasi target",
+                wasm.component_id()
+            ),
+            Self::MissingExport {
+                wasm,
+                expected,
+                is_component,
+                exports,
+            } => {
+                let kind = if *is_component { "component" } else { "module" };
+                let exports = if exports.is_empty() {
+                    "nothing".to_owned()
+                } else {
+                    exports.join(", ")
+                };
+                format!(
+                    "Component {:?} source is a Wasm {kind} which doesn't export {:?}, so the {} trigger can't run it (it exports {exports}). Check that it is built with a Spin SDK for the {} trigger",
+                    wasm.component_id(),
+                    expected.export.as_deref().unwrap_or_default(),
+                    expected.trigger,
+                    expected.trigger,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        test::{assert_single_diagnosis, TestPatient},
+        Diagnostic,
+    };
+
+    use super::*;
+
+    fn patient(dir: &Path, wat: &str, trigger: &str) -> TestPatient {
+        let source = dir.join("component.wasm");
+        std::fs::write(&source, wat::parse_str(wat).unwrap()).unwrap();
+        TestPatient::from_toml_str(format!(
+            r#"
+            spin_manifest_version = "1"
+            name = "wasm-world-test"
+            version = "0.0.0"
+            trigger = {{ type = "http", base = "/" }}
+            [[component]]
+            id = "api"
+            source = {source:?}
+            trigger = {trigger}
+            "#
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_http_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let patient = patient(
+            dir.path(),
+            r#"(module (func (export "handle-http-request")))"#,
+            r#"{ route = "/..." }"#,
+        );
+        let diags = WasmWorldDiagnostic.diagnose(&patient).await.unwrap();
+        assert!(diags.is_empty(), "expected no problems; got {diags:?}");
+    }
+
+    #[tokio::test]
+    async fn test_command_module_for_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let patient = patient(
+            dir.path(),
+            r#"(module (func (export "_start")))"#,
+            r#"{ route = "/..." }"#,
+        );
+        let diag = assert_single_diagnosis::<WasmWorldDiagnostic>(&patient).await;
+        assert!(matches!(diag, WasmWorldDiagnosis::MissingExport { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_wasm_bindgen_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let patient = patient(
+            dir.path(),
+            r#"(module (import "__wbindgen_placeholder__" "__wbindgen_describe" (func)))"#,
+            r#"{ route = "/..." }"#,
+        );
+        let diag = assert_single_diagnosis::<WasmWorldDiagnostic>(&patient).await;
+        assert!(matches!(diag, WasmWorldDiagnosis::WasmBindgen(..)));
+    }
+
+    #[tokio::test]
+    async fn test_component_for_wagi() {
+        let dir = tempfile::tempdir().unwrap();
+        let patient = patient(
+            dir.path(),
+            "(component)",
+            r#"{ route = "/...", executor = { type = "wagi" } }"#,
+        );
+        let diag = assert_single_diagnosis::<WasmWorldDiagnostic>(&patient).await;
+        assert!(matches!(
+            diag,
+            WasmWorldDiagnosis::ComponentNotSupported(..)
+        ));
+    }
+}
@@ -11,6 +11,31 @@ pub struct HttpTriggerConfig {
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// The authentication required of requests before they reach the component
+    #[serde(default)]
+    pub auth: Option<HttpAuthConfig>,
+}
+
+/// JWT bearer token authentication for an HTTP component.
+///
+/// Requests must carry an `Authorization: Bearer` token signed by the
+/// issuer, or they are rejected without invoking the component.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpAuthConfig {
+    /// The issuer which tokens must be issued by, compared with their `iss`
+    /// claim.
+    pub issuer: String,
+    /// The URL of the issuer's JSON Web Key Set. If it is not set, it is
+    /// discovered from the issuer's OpenID Connect configuration.
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    /// Tokens must be intended for at least one of these audiences, if any.
+    #[serde(default)]
+    pub audiences: Vec<String>,
+    /// Scopes which tokens must all grant.
+    #[serde(default)]
+    pub scopes: Vec<String>,
 }
 
 /// The executor for the HTTP component.
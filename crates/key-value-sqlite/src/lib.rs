@@ -216,4 +216,73 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn wasi_keyvalue() -> Result<()> {
+        use spin_world::wasi_keyvalue_types as types;
+        use spin_world::{wasi_keyvalue_batch as batch, wasi_keyvalue_readwrite as readwrite};
+
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(DelegatingStoreManager::new([(
+                "default".to_owned(),
+                Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)) as _,
+            )])),
+        );
+
+        let denied = types::Host::open_bucket(&mut kv, "forbidden".to_owned())
+            .await?
+            .unwrap_err();
+        assert_eq!(
+            types::Host::trace(&mut kv, denied).await?,
+            "access to the bucket is denied"
+        );
+        types::Host::drop_error(&mut kv, denied).await?;
+
+        let bucket = types::Host::open_bucket(&mut kv, "default".to_owned())
+            .await?
+            .unwrap();
+        assert_eq!(
+            readwrite::Host::get(&mut kv, bucket, "bar".to_owned())
+                .await?
+                .unwrap(),
+            None
+        );
+        readwrite::Host::set(&mut kv, bucket, "bar".to_owned(), b"baz".to_vec())
+            .await?
+            .unwrap();
+        batch::Host::set_many(&mut kv, bucket, vec![("qux".to_owned(), b"quux".to_vec())])
+            .await?
+            .unwrap();
+
+        // Values written with wasi-keyvalue are visible through the Spin
+        // interface, as buckets are stores.
+        assert_eq!(b"baz" as &[_], &kv.get(bucket, "bar".to_owned()).await??);
+        assert_eq!(
+            batch::Host::get_many(
+                &mut kv,
+                bucket,
+                vec!["qux".to_owned(), "missing".to_owned()]
+            )
+            .await?
+            .unwrap(),
+            [Some(b"quux".to_vec()), None]
+        );
+
+        batch::Host::delete_many(&mut kv, bucket, vec!["bar".to_owned(), "qux".to_owned()])
+            .await?
+            .unwrap();
+        assert!(batch::Host::get_keys(&mut kv, bucket)
+            .await?
+            .unwrap()
+            .is_empty());
+
+        types::Host::drop_bucket(&mut kv, bucket).await?;
+        assert!(readwrite::Host::exists(&mut kv, bucket, "bar".to_owned())
+            .await?
+            .is_err());
+
+        Ok(())
+    }
 }
@@ -47,7 +47,10 @@ impl HostComponent for KeyValueComponent {
         linker: &mut spin_core::Linker<T>,
         get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
     ) -> anyhow::Result<()> {
-        super::key_value::add_to_linker(linker, get)
+        super::key_value::add_to_linker(linker, get)?;
+        spin_world::wasi_keyvalue_types::add_to_linker(linker, get)?;
+        spin_world::wasi_keyvalue_readwrite::add_to_linker(linker, get)?;
+        spin_world::wasi_keyvalue_batch::add_to_linker(linker, get)
     }
 
     fn build_data(&self) -> Self::Data {
@@ -4,10 +4,12 @@ use spin_core::async_trait;
 use spin_world::key_value;
 use std::{collections::HashSet, sync::Arc};
 use table::Table;
+use tracing::instrument;
 
 mod host_component;
 pub mod table;
 mod util;
+mod wasi;
 
 pub use host_component::{manager, KeyValueComponent};
 pub use util::{CachingStoreManager, DelegatingStoreManager, EmptyStoreManager};
@@ -15,6 +17,7 @@ pub use util::{CachingStoreManager, DelegatingStoreManager, EmptyStoreManager};
 pub const KEY_VALUE_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("key_value_stores");
 
 const DEFAULT_STORE_TABLE_CAPACITY: u32 = 256;
+const ERROR_TABLE_CAPACITY: u32 = 256;
 
 pub use key_value::{Error, Store as StoreHandle};
 
@@ -41,6 +44,9 @@ pub struct KeyValueDispatch {
     allowed_stores: HashSet<String>,
     manager: Arc<dyn StoreManager>,
     stores: Table<Arc<dyn Store>>,
+    // The descriptions of errors raised through the wasi-keyvalue
+    // interfaces, which refer to them by handle.
+    errors: Table<String>,
 }
 
 impl KeyValueDispatch {
@@ -53,6 +59,7 @@ impl KeyValueDispatch {
             allowed_stores: HashSet::new(),
             manager: Arc::new(EmptyStoreManager),
             stores: Table::new(capacity),
+            errors: Table::new(ERROR_TABLE_CAPACITY),
         }
     }
 
@@ -60,6 +67,20 @@ impl KeyValueDispatch {
         self.allowed_stores = allowed_stores;
         self.manager = manager;
     }
+
+    async fn open_store(&mut self, name: &str) -> Result<StoreHandle, Error> {
+        if self.allowed_stores.contains(name) {
+            self.stores
+                .push(self.manager.get(name).await?)
+                .map_err(|()| Error::StoreTableFull)
+        } else {
+            Err(Error::AccessDenied)
+        }
+    }
+
+    fn store(&self, store: StoreHandle) -> Result<&Arc<dyn Store>, Error> {
+        self.stores.get(store).ok_or(Error::InvalidStore)
+    }
 }
 
 impl Default for KeyValueDispatch {
@@ -70,19 +91,12 @@ impl Default for KeyValueDispatch {
 
 #[async_trait]
 impl key_value::Host for KeyValueDispatch {
+    #[instrument(name = "spin_key_value.open", skip(self), fields(otel.kind = "client"))]
     async fn open(&mut self, name: String) -> Result<Result<StoreHandle, Error>> {
-        Ok(async {
-            if self.allowed_stores.contains(&name) {
-                self.stores
-                    .push(self.manager.get(&name).await?)
-                    .map_err(|()| Error::StoreTableFull)
-            } else {
-                Err(Error::AccessDenied)
-            }
-        }
-        .await)
+        Ok(self.open_store(&name).await)
     }
 
+    #[instrument(name = "spin_key_value.get", skip(self, store), fields(otel.kind = "client"))]
     async fn get(&mut self, store: StoreHandle, key: String) -> Result<Result<Vec<u8>, Error>> {
         Ok(async {
             self.stores
@@ -94,6 +108,11 @@ impl key_value::Host for KeyValueDispatch {
         .await)
     }
 
+    #[instrument(
+        name = "spin_key_value.set",
+        skip(self, store, value),
+        fields(otel.kind = "client")
+    )]
     async fn set(
         &mut self,
         store: StoreHandle,
@@ -110,6 +129,7 @@ impl key_value::Host for KeyValueDispatch {
         .await)
     }
 
+    #[instrument(name = "spin_key_value.delete", skip(self, store), fields(otel.kind = "client"))]
     async fn delete(&mut self, store: StoreHandle, key: String) -> Result<Result<(), Error>> {
         Ok(async {
             self.stores
@@ -121,6 +141,7 @@ impl key_value::Host for KeyValueDispatch {
         .await)
     }
 
+    #[instrument(name = "spin_key_value.exists", skip(self, store), fields(otel.kind = "client"))]
     async fn exists(&mut self, store: StoreHandle, key: String) -> Result<Result<bool, Error>> {
         Ok(async {
             self.stores
@@ -132,6 +153,7 @@ impl key_value::Host for KeyValueDispatch {
         .await)
     }
 
+    #[instrument(name = "spin_key_value.get_keys", skip(self, store), fields(otel.kind = "client"))]
     async fn get_keys(&mut self, store: StoreHandle) -> Result<Result<Vec<String>, Error>> {
         Ok(async {
             self.stores
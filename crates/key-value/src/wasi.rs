@@ -0,0 +1,179 @@
+//! The wasi-keyvalue interfaces, which give components written against the
+//! WASI key-value proposal access to the same stores as the Spin `key-value`
+//! interface. Buckets are stores, and share their handles.
+
+use anyhow::{anyhow, Result};
+use spin_core::async_trait;
+use spin_world::{wasi_keyvalue_batch, wasi_keyvalue_readwrite, wasi_keyvalue_types};
+use tracing::instrument;
+
+use crate::{Error, KeyValueDispatch};
+
+type Bucket = wasi_keyvalue_types::Bucket;
+type WasiError = wasi_keyvalue_types::Error;
+
+impl KeyValueDispatch {
+    // Converts the result of a store operation to the wasi-keyvalue form, in
+    // which errors are handles. The outer result fails, trapping the guest,
+    // if the guest has too many unreleased errors.
+    fn wasi_result<T>(&mut self, result: Result<T, Error>) -> Result<Result<T, WasiError>> {
+        match result {
+            Ok(value) => Ok(Ok(value)),
+            Err(err) => {
+                let handle = self
+                    .errors
+                    .push(describe(&err))
+                    .map_err(|()| anyhow!("too many wasi-keyvalue errors have not been dropped"))?;
+                Ok(Err(handle))
+            }
+        }
+    }
+}
+
+fn describe(err: &Error) -> String {
+    match err {
+        Error::StoreTableFull => "too many buckets are open".into(),
+        Error::NoSuchStore => "no such bucket".into(),
+        Error::AccessDenied => "access to the bucket is denied".into(),
+        Error::InvalidStore => "invalid bucket handle".into(),
+        Error::NoSuchKey => "no such key".into(),
+        Error::Io(message) => message.clone(),
+    }
+}
+
+// Spin stores report a missing key as an error, which wasi-keyvalue reports
+// as the absence of a value.
+fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoSuchKey) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[async_trait]
+impl wasi_keyvalue_types::Host for KeyValueDispatch {
+    #[instrument(name = "wasi_keyvalue.open_bucket", skip(self), fields(otel.kind = "client"))]
+    async fn open_bucket(&mut self, name: String) -> Result<Result<Bucket, WasiError>> {
+        let result = self.open_store(&name).await;
+        self.wasi_result(result)
+    }
+
+    async fn drop_bucket(&mut self, bucket: Bucket) -> Result<()> {
+        self.stores.remove(bucket);
+        Ok(())
+    }
+
+    async fn trace(&mut self, error: WasiError) -> Result<String> {
+        self.errors
+            .get(error)
+            .cloned()
+            .ok_or_else(|| anyhow!("invalid wasi-keyvalue error handle {error}"))
+    }
+
+    async fn drop_error(&mut self, error: WasiError) -> Result<()> {
+        self.errors.remove(error);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl wasi_keyvalue_readwrite::Host for KeyValueDispatch {
+    #[instrument(name = "wasi_keyvalue.get", skip(self, bucket), fields(otel.kind = "client"))]
+    async fn get(
+        &mut self,
+        bucket: Bucket,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, WasiError>> {
+        let result = async { optional(self.store(bucket)?.get(&key).await) }.await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(
+        name = "wasi_keyvalue.set",
+        skip(self, bucket, value),
+        fields(otel.kind = "client")
+    )]
+    async fn set(
+        &mut self,
+        bucket: Bucket,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), WasiError>> {
+        let result = async { self.store(bucket)?.set(&key, &value).await }.await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(name = "wasi_keyvalue.delete", skip(self, bucket), fields(otel.kind = "client"))]
+    async fn delete(&mut self, bucket: Bucket, key: String) -> Result<Result<(), WasiError>> {
+        let result = async { self.store(bucket)?.delete(&key).await }.await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(name = "wasi_keyvalue.exists", skip(self, bucket), fields(otel.kind = "client"))]
+    async fn exists(&mut self, bucket: Bucket, key: String) -> Result<Result<bool, WasiError>> {
+        let result = async { self.store(bucket)?.exists(&key).await }.await;
+        self.wasi_result(result)
+    }
+}
+
+#[async_trait]
+impl wasi_keyvalue_batch::Host for KeyValueDispatch {
+    #[instrument(name = "wasi_keyvalue.get_many", skip_all, fields(otel.kind = "client"))]
+    async fn get_many(
+        &mut self,
+        bucket: Bucket,
+        keys: Vec<String>,
+    ) -> Result<Result<Vec<Option<Vec<u8>>>, WasiError>> {
+        let result = async {
+            let store = self.store(bucket)?;
+            let mut values = Vec::with_capacity(keys.len());
+            for key in &keys {
+                values.push(optional(store.get(key).await)?);
+            }
+            Ok(values)
+        }
+        .await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(name = "wasi_keyvalue.get_keys", skip(self, bucket), fields(otel.kind = "client"))]
+    async fn get_keys(&mut self, bucket: Bucket) -> Result<Result<Vec<String>, WasiError>> {
+        let result = async { self.store(bucket)?.get_keys().await }.await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(name = "wasi_keyvalue.set_many", skip_all, fields(otel.kind = "client"))]
+    async fn set_many(
+        &mut self,
+        bucket: Bucket,
+        key_values: Vec<(String, Vec<u8>)>,
+    ) -> Result<Result<(), WasiError>> {
+        let result = async {
+            let store = self.store(bucket)?;
+            for (key, value) in &key_values {
+                store.set(key, value).await?;
+            }
+            Ok(())
+        }
+        .await;
+        self.wasi_result(result)
+    }
+
+    #[instrument(name = "wasi_keyvalue.delete_many", skip_all, fields(otel.kind = "client"))]
+    async fn delete_many(
+        &mut self,
+        bucket: Bucket,
+        keys: Vec<String>,
+    ) -> Result<Result<(), WasiError>> {
+        let result = async {
+            let store = self.store(bucket)?;
+            for key in &keys {
+                store.delete(key).await?;
+            }
+            Ok(())
+        }
+        .await;
+        self.wasi_result(result)
+    }
+}
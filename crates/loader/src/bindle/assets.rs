@@ -53,7 +53,11 @@ impl Copier {
         let guest = "/".to_string();
         self.copy_all(parcels, &host).await?;
 
-        Ok(DirectoryMount { host, guest })
+        Ok(DirectoryMount {
+            host,
+            guest,
+            writable: false,
+        })
     }
 
     async fn copy_all(&self, parcels: &[Label], dir: impl AsRef<Path>) -> Result<()> {
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use spin_manifest::{ResourceLimits, TmpDir};
 use std::collections::HashMap;
 
 use crate::common::RawVariable;
@@ -47,10 +48,34 @@ pub struct RawWasmConfig {
     pub files: Option<String>,
     /// Optional list of HTTP hosts the component is allowed to connect.
     pub allowed_http_hosts: Option<Vec<String>>,
+    /// Optional list of hosts to which the component may open TCP or UDP
+    /// sockets.
+    pub allowed_outbound_hosts: Option<Vec<String>>,
     /// Optional list of key-value stores the component is allowed to use.
     pub key_value_stores: Option<Vec<String>>,
     /// Optional list of SQLite databases the component is allowed to use.
     pub sqlite_databases: Option<Vec<String>>,
+    /// Optional list of blob containers the component is allowed to use.
+    pub blob_containers: Option<Vec<String>>,
+    /// Optional list of messaging channels the component is allowed to
+    /// publish to.
+    pub messaging_channels: Option<Vec<String>>,
+    /// Whether the component's outbound GET requests may be served from the
+    /// runtime's response cache.
+    pub outbound_http_cache: Option<bool>,
+    /// Resource limits for each instance of the component.
+    pub limits: Option<ResourceLimits>,
+    /// The scratch directory mounted at `/tmp` in each instance of the
+    /// component, if any.
+    pub tmp_dir: Option<TmpDir>,
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: Option<HashMap<String, String>>,
+    /// Names of host environment variables to be forwarded into the Wasm
+    /// module at runtime. A name ending in `*` matches any variable with
+    /// that prefix.
+    pub environment_passthrough: Option<Vec<String>>,
+    /// The resources of each host extension, keyed by extension name, which
+    /// the component is allowed to use. Host extensions are added by
+    /// platforms which embed Spin.
+    pub extensions: Option<HashMap<String, Vec<String>>>,
 }
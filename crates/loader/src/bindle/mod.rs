@@ -23,9 +23,12 @@ use spin_manifest::{
     SpinVersion, WasmConfig,
 };
 
-use crate::bindle::{
-    config::{RawAppManifest, RawComponentManifest},
-    utils::{find_manifest, parcels_in_group},
+use crate::{
+    bindle::{
+        config::{RawAppManifest, RawComponentManifest},
+        utils::{find_manifest, parcels_in_group},
+    },
+    validation::{validate_allowed_outbound_hosts, validate_environment_passthrough},
 };
 pub use connection::BindleConnectionInfo;
 pub(crate) use utils::BindleReader;
@@ -100,7 +103,13 @@ async fn prepare(
 fn validate_raw_app_manifest(raw: &RawAppManifest) -> Result<()> {
     raw.components
         .iter()
-        .try_for_each(|c| validate_allowed_http_hosts(&c.wasm.allowed_http_hosts))
+        .try_for_each(|c| validate_allowed_http_hosts(&c.wasm.allowed_http_hosts))?;
+    raw.components
+        .iter()
+        .try_for_each(|c| validate_allowed_outbound_hosts(&c.wasm.allowed_outbound_hosts))?;
+    raw.components
+        .iter()
+        .try_for_each(|c| validate_environment_passthrough(&c.wasm.environment_passthrough))
 }
 
 /// Given a raw component manifest, prepare its assets and return a fully formed core component.
@@ -129,15 +138,31 @@ async fn core(
         None => vec![],
     };
     let environment = raw.wasm.environment.unwrap_or_default();
+    let environment_passthrough = raw.wasm.environment_passthrough.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
+    let allowed_outbound_hosts = raw.wasm.allowed_outbound_hosts.unwrap_or_default();
     let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
     let sqlite_databases = raw.wasm.sqlite_databases.unwrap_or_default();
+    let blob_containers = raw.wasm.blob_containers.unwrap_or_default();
+    let messaging_channels = raw.wasm.messaging_channels.unwrap_or_default();
+    let outbound_http_cache = raw.wasm.outbound_http_cache.unwrap_or_default();
+    let limits = raw.wasm.limits.unwrap_or_default();
+    let tmp_dir = raw.wasm.tmp_dir;
+    let extensions = raw.wasm.extensions.unwrap_or_default();
     let wasm = WasmConfig {
         environment,
+        environment_passthrough,
         mounts,
         allowed_http_hosts,
+        allowed_outbound_hosts,
         key_value_stores,
         sqlite_databases,
+        blob_containers,
+        messaging_channels,
+        outbound_http_cache,
+        limits,
+        tmp_dir,
+        extensions,
     };
     let config = raw.config.unwrap_or_default();
     Ok(CoreComponent {
@@ -146,6 +171,8 @@ async fn core(
         description,
         wasm,
         config,
+        dependencies: Default::default(),
+        enabled: None,
     })
 }
 
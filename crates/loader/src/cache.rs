@@ -10,6 +10,9 @@ const REGISTRY_CACHE_DIR: &str = "registry";
 const MANIFESTS_DIR: &str = "manifests";
 const WASM_DIR: &str = "wasm";
 const DATA_DIR: &str = "data";
+const TEMPLATES_DIR: &str = "templates";
+const UPLOADS_DIR: &str = "uploads";
+const GIT_DIR: &str = "git";
 
 /// Cache for registry entities.
 pub struct Cache {
@@ -21,37 +24,61 @@ impl Cache {
     /// Create a new cache given an optional root directory.
     pub async fn new(root: Option<PathBuf>) -> Result<Self> {
         let root = match root {
-            Some(root) => root,
-            None => dirs::cache_dir()
-                .context("cannot get cache directory")?
-                .join(CONFIG_DIR),
+            Some(root) => root.join(REGISTRY_CACHE_DIR),
+            None => Self::default_dir()?,
         };
-        let root = root.join(REGISTRY_CACHE_DIR);
         Self::ensure_dirs(&root).await?;
 
         Ok(Self { root })
     }
 
+    /// The default cache directory, under the user's cache directory.
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(dirs::cache_dir()
+            .context("cannot get cache directory")?
+            .join(CONFIG_DIR)
+            .join(REGISTRY_CACHE_DIR))
+    }
+
     /// The manifests directory for the current cache.
     pub fn manifests_dir(&self) -> PathBuf {
         self.root.join(MANIFESTS_DIR)
     }
 
     /// The Wasm bytes directory for the current cache.
-    fn wasm_dir(&self) -> PathBuf {
+    pub fn wasm_dir(&self) -> PathBuf {
         self.root.join(WASM_DIR)
     }
 
     /// The data directory for the current cache.
-    fn data_dir(&self) -> PathBuf {
+    pub fn data_dir(&self) -> PathBuf {
         self.root.join(DATA_DIR)
     }
 
+    /// The directory for template packs pulled from registries.
+    pub fn templates_dir(&self) -> PathBuf {
+        self.root.join(TEMPLATES_DIR)
+    }
+
+    /// The directory recording the state of uploads to registries.
+    pub fn uploads_dir(&self) -> PathBuf {
+        self.root.join(UPLOADS_DIR)
+    }
+
+    /// The directory of checkouts of git repositories providing component
+    /// sources.
+    pub fn git_dir(&self) -> PathBuf {
+        self.root.join(GIT_DIR)
+    }
+
     /// Return the path to a wasm file given its digest.
     pub fn wasm_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
         let path = &self.wasm_dir().join(digest.as_ref());
         match path.exists() {
-            true => Ok(path.into()),
+            true => {
+                spin_common::cache_usage::mark_used(path);
+                Ok(path.into())
+            }
             false => bail!(format!(
                 "cannot find wasm file for digest {}",
                 digest.as_ref()
@@ -63,7 +90,10 @@ impl Cache {
     pub fn data_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
         let path = &self.data_dir().join(digest.as_ref());
         match path.exists() {
-            true => Ok(path.into()),
+            true => {
+                spin_common::cache_usage::mark_used(path);
+                Ok(path.into())
+            }
             false => bail!(format!(
                 "cannot find data file for digest {}",
                 digest.as_ref()
@@ -73,14 +103,12 @@ impl Cache {
 
     /// Write the contents in the cache's wasm directory.
     pub async fn write_wasm(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
-        fs::write(self.wasm_dir().join(digest.as_ref()), bytes.as_ref()).await?;
-        Ok(())
+        write_atomically(self.wasm_dir(), digest.as_ref(), bytes.as_ref()).await
     }
 
     /// Write the contents in the cache's data directory.
     pub async fn write_data(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
-        fs::write(self.data_dir().join(digest.as_ref()), bytes.as_ref()).await?;
-        Ok(())
+        write_atomically(self.data_dir(), digest.as_ref(), bytes.as_ref()).await
     }
 
     /// Ensure the expected configuration directories are found in the root.
@@ -116,3 +144,20 @@ impl Cache {
         Ok(())
     }
 }
+
+// The cache may be shared by several Spin processes, so content is written
+// to a temporary file and renamed into place: readers see either no file or
+// the whole of it.
+async fn write_atomically(dir: PathBuf, name: &str, bytes: &[u8]) -> Result<()> {
+    let path = dir.join(name);
+    let bytes = bytes.to_vec();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut temp = tempfile::NamedTempFile::new_in(&dir)
+            .with_context(|| format!("cannot create temporary file in {}", dir.display()))?;
+        std::io::Write::write_all(&mut temp, &bytes)?;
+        temp.persist(&path)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(())
+    })
+    .await?
+}
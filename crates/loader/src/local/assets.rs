@@ -6,6 +6,7 @@ use crate::{
 };
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use futures::{future, stream, StreamExt};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use path_absolutize::Absolutize;
 use spin_manifest::DirectoryMount;
 use std::{
@@ -51,12 +52,59 @@ async fn prepare_component_with_temp_dir(
         base_dst.as_ref().display()
     );
 
-    let files = collect(raw_mounts, exclude_files, src)?;
+    let files = collect(raw_mounts, exclude_files, &src)?;
+    warn_about_unexpected_files(&files, id, &src);
     let host = create_dir(&base_dst, id).await?;
     let guest = "/".to_string();
     copy_all(&files, &host).await?;
 
-    Ok(vec![DirectoryMount { guest, host }])
+    let mut mounts = vec![DirectoryMount {
+        guest,
+        host,
+        writable: false,
+    }];
+    mounts.extend(writable_mounts(raw_mounts, &src)?);
+    Ok(mounts)
+}
+
+/// Resolve the writable directory placements, which are mounted directly
+/// rather than copied.
+fn writable_mounts(
+    raw_mounts: &[RawFileMount],
+    rel: impl AsRef<Path>,
+) -> Result<Vec<DirectoryMount>> {
+    let (_, placements) = uncase(raw_mounts);
+    placements
+        .iter()
+        .filter(|placement| placement.writable)
+        .map(|placement| {
+            let source = &placement.source;
+            let guest = placement
+                .destination
+                .to_str()
+                .context("unable to parse mount destination as UTF-8")?;
+            ensure!(
+                source.is_relative(),
+                "Cannot place {}: source paths must be relative",
+                source.display()
+            );
+            ensure!(
+                is_absolute_guest_path(guest),
+                "Cannot place at {guest}: guest paths must be absolute"
+            );
+            let host: PathBuf = rel.as_ref().join(source).absolutize()?.into();
+            ensure!(
+                host.is_dir(),
+                "Cannot place {}: source must be a directory",
+                host.display()
+            );
+            Ok(DirectoryMount {
+                guest: guest.to_owned(),
+                host,
+                writable: true,
+            })
+        })
+        .collect()
 }
 
 fn prepare_component_with_direct_mounts(
@@ -77,6 +125,7 @@ fn directory_mount(mount: &RawFileMount) -> anyhow::Result<DirectoryMount> {
                 .context("unable to parse mount destination as UTF-8")?
                 .to_owned(),
             host: placement.source.absolutize()?.into(),
+            writable: placement.writable,
         }),
         RawFileMount::Pattern(pattern) => {
             let as_path = Path::new(pattern);
@@ -84,6 +133,7 @@ fn directory_mount(mount: &RawFileMount) -> anyhow::Result<DirectoryMount> {
                 Ok(DirectoryMount {
                     guest: pattern.to_owned(),
                     host: as_path.absolutize()?.into(),
+                    writable: false,
                 })
             } else {
                 const PATTERN_CHARS: &[char] = &['*', '?', '['];
@@ -129,27 +179,39 @@ impl FileMount {
 }
 
 /// Generate a vector of file mounts for a component given all its file patterns.
+/// Writable directory placements are mounted directly, so are not included.
+///
+/// The `exclude_files` patterns follow `.gitignore` syntax, relative to `rel`:
+/// a pattern ending in `/` excludes a directory and everything in it, a
+/// pattern without a `/` but at its end matches at any depth, and a pattern
+/// starting with `!` re-includes files which an earlier pattern excluded,
+/// unless a directory containing them is excluded.
 pub fn collect(
     raw_mounts: &[RawFileMount],
     exclude_files: &[String],
     rel: impl AsRef<Path>,
 ) -> Result<Vec<FileMount>> {
     let (patterns, placements) = uncase(raw_mounts);
+    let placements = placements
+        .into_iter()
+        .filter(|placement| !placement.writable)
+        .collect::<Vec<_>>();
 
+    let exclusions = Exclusions::new(exclude_files, &rel)?;
     let pattern_files = collect_patterns(&patterns, &rel)?;
-    let placement_files = collect_placements(&placements, &rel)?;
+    let placement_files = collect_placements(&placements, &rel, &exclusions)?;
     let all_files = [pattern_files, placement_files].concat();
 
-    let exclude_patterns = convert_strings_to_glob_patterns(exclude_files, &rel)?;
-    Ok(get_included_files(all_files, &exclude_patterns))
+    Ok(get_included_files(all_files, &exclusions))
 }
 
 fn collect_placements(
     placements: &[RawDirectoryPlacement],
     rel: impl AsRef<Path>,
+    exclusions: &Exclusions,
 ) -> Result<Vec<FileMount>, anyhow::Error> {
     let results = placements.iter().map(|placement| {
-        collect_placement(placement, &rel).with_context(|| {
+        collect_placement(placement, &rel, exclusions).with_context(|| {
             format!(
                 "Failed to collect file mounts for {}",
                 placement.source.display()
@@ -177,6 +239,7 @@ fn collect_patterns(
 fn collect_placement(
     placement: &RawDirectoryPlacement,
     rel: impl AsRef<Path>,
+    exclusions: &Exclusions,
 ) -> Result<Vec<FileMount>> {
     let source = &placement.source;
     let guest_path = &placement.destination;
@@ -201,9 +264,13 @@ fn collect_placement(
         bail!("Cannot place {}: source must be a directory", abs.display());
     }
 
+    // Excluded directories are not walked.
     let walker = WalkDir::new(&abs);
     let files = walker
         .into_iter()
+        .filter_entry(|entry| {
+            !(entry.file_type().is_dir() && exclusions.excludes(entry.path(), true))
+        })
         .filter_map(|de| match de {
             Err(e) => Some(
                 Err(e).with_context(|| format!("Failed to walk directory under {}", abs.display())),
@@ -316,46 +383,197 @@ fn is_absolute_guest_path(path: impl AsRef<Path>) -> bool {
     path.as_ref().to_string_lossy().starts_with('/')
 }
 
-/// Convert strings to glob patterns
-fn convert_strings_to_glob_patterns<T: AsRef<str>>(
-    files: &[T],
-    rel: impl AsRef<Path>,
-) -> Result<Vec<glob::Pattern>> {
-    let file_paths = files
-        .iter()
-        .map(|f| match rel.as_ref().join(f.as_ref()).to_str() {
-            Some(abs) => Ok(abs.to_owned()),
-            None => Err(anyhow!(
-                "Can't join {} and {}",
-                rel.as_ref().display(),
-                f.as_ref()
-            )),
-        })
-        .collect::<Result<Vec<_>>>()?;
-    file_paths
-        .iter()
-        .map(|f| {
-            glob::Pattern::new(f).with_context(|| format!("can't convert {} to glob pattern", f))
-        })
-        .collect::<Result<Vec<glob::Pattern>>>()
+/// The files excluded from a component's mounts by its `exclude_files`
+/// patterns.
+struct Exclusions {
+    root: PathBuf,
+    patterns: Gitignore,
+}
+
+impl Exclusions {
+    fn new<T: AsRef<str>>(exclude_files: &[T], rel: impl AsRef<Path>) -> Result<Self> {
+        let root = rel.as_ref().to_path_buf();
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in exclude_files {
+            builder
+                .add_line(None, pattern.as_ref())
+                .with_context(|| format!("Invalid exclude_files pattern {:?}", pattern.as_ref()))?;
+        }
+        let patterns = builder.build().context("Invalid exclude_files patterns")?;
+        Ok(Self { root, patterns })
+    }
+
+    /// Whether the file or directory at the path is excluded. As in git, a
+    /// file in an excluded directory cannot be re-included.
+    fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let excluded_dir = relative
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| self.patterns.matched(dir, true).is_ignore());
+        excluded_dir || self.patterns.matched(relative, is_dir).is_ignore()
+    }
 }
 
-/// Remove files which match excluded patterns
-fn get_included_files(files: Vec<FileMount>, exclude_patterns: &[glob::Pattern]) -> Vec<FileMount> {
+/// Remove files which are excluded
+fn get_included_files(files: Vec<FileMount>, exclusions: &Exclusions) -> Vec<FileMount> {
     files
         .into_iter()
         .filter(|f| {
-            for exclude_pattern in exclude_patterns {
-                if exclude_pattern.matches_path(Path::new(&f.src)) {
-                    tracing::info!(
-                        "file: {} is excluded by pattern {}",
-                        f.src.display(),
-                        exclude_pattern
-                    );
-                    return false;
-                }
+            let excluded = exclusions.excludes(&f.src, false);
+            if excluded {
+                tracing::info!("file: {} is excluded", f.src.display());
             }
-            true
+            !excluded
+        })
+        .collect::<Vec<_>>()
+}
+
+// Files which are likely to hold credentials, in `.gitignore` syntax.
+const SENSITIVE_FILES: &[&str] = &[
+    ".env",
+    ".env.*",
+    ".git/",
+    ".ssh/",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+    "id_rsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    "*.key",
+    "*.pem",
+    "*.p12",
+    "*.pfx",
+];
+
+// Files larger than this are reported, as they make applications slower to
+// push and pull.
+const LARGE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+// The most files listed in each warning.
+const MAX_LISTED_FILES: usize = 5;
+
+/// Warn about mounted files which look like secrets or are large, as these
+/// are often mounted by accident through broad patterns.
+fn warn_about_unexpected_files(files: &[FileMount], id: &str, rel: impl AsRef<Path>) {
+    let sensitive = match Exclusions::new(SENSITIVE_FILES, &rel) {
+        Ok(sensitive) => sensitive,
+        Err(e) => {
+            tracing::warn!("Cannot check for sensitive files: {e:#}");
+            return;
+        }
+    };
+    let sensitive_files = files
+        .iter()
+        .filter(|f| sensitive.excludes(&f.src, false))
+        .map(|f| f.relative_dst.as_str())
+        .collect::<Vec<_>>();
+    if !sensitive_files.is_empty() {
+        terminal::warn!(
+            "Component {id:?} mounts files which may contain secrets: {}. If they are not needed, exclude them with `exclude_files`.",
+            list(&sensitive_files)
+        );
+    }
+
+    let large_files = files
+        .iter()
+        .filter(|f| {
+            std::fs::metadata(&f.src)
+                .map(|m| m.len() > LARGE_FILE_BYTES)
+                .unwrap_or_default()
         })
+        .map(|f| f.relative_dst.as_str())
+        .collect::<Vec<_>>();
+    if !large_files.is_empty() {
+        terminal::warn!(
+            "Component {id:?} mounts files larger than {} MB: {}. If they are not needed, exclude them with `exclude_files`.",
+            LARGE_FILE_BYTES / (1024 * 1024),
+            list(&large_files)
+        );
+    }
+}
+
+fn list(files: &[&str]) -> String {
+    let listed = files
+        .iter()
+        .take(MAX_LISTED_FILES)
+        .copied()
         .collect::<Vec<_>>()
+        .join(", ");
+    match files.len().checked_sub(MAX_LISTED_FILES) {
+        Some(more) if more > 0 => format!("{listed} and {more} more"),
+        _ => listed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, name).unwrap();
+    }
+
+    fn collected(exclude_files: &[&str], dir: &Path) -> Vec<String> {
+        let mounts = [
+            RawFileMount::Pattern("static/**/*".to_owned()),
+            RawFileMount::Placement(RawDirectoryPlacement {
+                source: "assets".into(),
+                destination: "/assets".into(),
+                writable: false,
+            }),
+        ];
+        let exclude_files = exclude_files
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        let mut files = collect(&mounts, &exclude_files, dir)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.relative_dst.replace('\\', "/"))
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn excludes_with_gitignore_semantics() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "static/index.html",
+            "static/debug.log",
+            "static/keep.log",
+            "static/node_modules/lib.js",
+            "static/nested/trace.log",
+            "assets/logo.png",
+            "assets/node_modules/lib.js",
+        ] {
+            write(dir.path(), name);
+        }
+
+        let files = collected(&["*.log", "!static/keep.log", "node_modules/"], dir.path());
+        assert_eq!(
+            files,
+            ["assets/logo.png", "static/index.html", "static/keep.log"]
+        );
+
+        // Files in an excluded directory cannot be re-included.
+        let files = collected(&["static/", "!static/index.html"], dir.path());
+        assert_eq!(files, ["assets/logo.png", "assets/node_modules/lib.js"]);
+    }
+
+    #[test]
+    fn lists_limited_number_of_files() {
+        assert_eq!("a, b", list(&["a", "b"]));
+        assert_eq!(
+            "a, b, c, d, e and 2 more",
+            list(&["a", "b", "c", "d", "e", "f", "g"])
+        );
+    }
 }
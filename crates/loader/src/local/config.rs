@@ -4,8 +4,11 @@
 
 #![deny(missing_docs)]
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use spin_manifest::{ApplicationTrigger, TriggerConfig};
+use spin_manifest::{
+    ApplicationTrigger, ComponentDependency, ResourceLimits, TmpDir, TriggerConfig,
+};
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::common::RawVariable;
@@ -22,7 +25,7 @@ pub(crate) type RawAppManifestAnyVersionPartial = RawAppManifestAnyVersionImpl<t
 pub(crate) type RawComponentManifestPartial = RawComponentManifestImpl<toml::Value>;
 
 /// Container for any version of the manifest.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 pub struct RawAppManifestAnyVersionImpl<C> {
     #[serde(alias = "spin_version")]
     //We don't actually use the version yet
@@ -51,19 +54,25 @@ impl<C> RawAppManifestAnyVersionImpl<C> {
     pub fn as_v1(&self) -> &RawAppManifestImpl<C> {
         &self.manifest
     }
+
+    /// Returns a mutable reference to the underlying V1 manifest
+    pub fn as_v1_mut(&mut self) -> &mut RawAppManifestImpl<C> {
+        &mut self.manifest
+    }
 }
 
 /// Application configuration local file format.
 /// This is the main structure spin.toml deserializes into.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawAppManifestImpl<C> {
     /// General application information.
     #[serde(flatten)]
     pub info: RawAppInformation,
 
-    /// Configuration for the application components.
-    #[serde(rename = "component")]
+    /// Configuration for the application components, which may all be
+    /// defined in included files.
+    #[serde(rename = "component", default)]
     pub components: Vec<RawComponentManifestImpl<C>>,
 
     /// Application-specific configuration schema.
@@ -72,7 +81,7 @@ pub struct RawAppManifestImpl<C> {
 }
 
 /// General application information.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawAppInformation {
     /// Name of the application.
@@ -87,10 +96,14 @@ pub struct RawAppInformation {
     pub trigger: ApplicationTrigger,
     /// Namespace for the application. (deprecated)
     pub namespace: Option<String>,
+    /// Whether the manifest is checked strictly when loaded: unknown keys,
+    /// unused variables and deprecated settings are errors rather than
+    /// being ignored.
+    pub strict: Option<bool>,
 }
 
 /// Core component configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawComponentManifestImpl<C> {
     /// The module source.
@@ -107,12 +120,77 @@ pub struct RawComponentManifestImpl<C> {
     pub trigger: C,
     /// Build configuration for the component.
     pub build: Option<RawBuildConfig>,
+    /// Named build profiles, such as `release`, which override the
+    /// component's source and build command when selected.
+    pub profile: Option<HashMap<String, RawComponentProfile>>,
     /// Component-specific configuration values.
     pub config: Option<HashMap<String, String>>,
+    /// Library components which satisfy the component's imports, keyed by
+    /// import name, e.g. `"cache" = { path = "libs/cache.wasm" }` or
+    /// `"cache" = { registry = "ghcr.io/fermyon/kv-cache:1.0.0" }`.
+    pub dependencies: Option<HashMap<String, ComponentDependency>>,
+    /// Whether the component runs, as an expression resolved from the
+    /// application's variables when it is loaded, such as
+    /// `"{{ debug_endpoints }}"`. A value of `true`, `yes`, `on` or `1`
+    /// enables the component; `false`, `no`, `off`, `0` or an empty value
+    /// disables it, along with its trigger. If omitted, the component is
+    /// always enabled.
+    pub enabled: Option<String>,
+}
+
+impl<C> RawComponentManifestImpl<C> {
+    /// Whether the component defines the named build profile.
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profile
+            .as_ref()
+            .map_or(false, |profiles| profiles.contains_key(name))
+    }
+
+    /// Replaces the component's source and build command with those of the
+    /// named build profile. A component which does not define the profile
+    /// is unchanged.
+    pub fn apply_profile(&mut self, name: &str) {
+        if let Some(profile) = self.profile.as_ref().and_then(|p| p.get(name)) {
+            let build_command = self.build.as_mut().map(|build| &mut build.command);
+            profile.apply(&mut self.source, build_command);
+        }
+    }
+}
+
+/// A named build profile for a component.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawComponentProfile {
+    /// The module source to use instead of the component's source, such as
+    /// the path of the module built by the profile's build command.
+    pub source: Option<RawModuleSource>,
+    /// The build settings to use instead of the component's.
+    pub build: Option<RawProfileBuildConfig>,
+}
+
+impl RawComponentProfile {
+    /// Replaces a component's source, and its build command if it has one,
+    /// with those of the profile.
+    pub fn apply(&self, source: &mut RawModuleSource, build_command: Option<&mut String>) {
+        if let Some(profile_source) = &self.source {
+            *source = profile_source.clone();
+        }
+        if let (Some(command), Some(profile_build)) = (build_command, &self.build) {
+            *command = profile_build.command.clone();
+        }
+    }
+}
+
+/// The build settings which a build profile overrides.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawProfileBuildConfig {
+    /// Build command.
+    pub command: String,
 }
 
 /// Build configuration for the component.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawBuildConfig {
     /// Build command.
@@ -123,10 +201,36 @@ pub struct RawBuildConfig {
     /// List of glob patterns to watch for changes. Used by spin watch to
     /// re-execute spin build and spin up when your source changes.
     pub watch: Option<Vec<String>>,
+    /// If present, the built module is pre-initialized with Wizer after the
+    /// build command succeeds.
+    pub preinit: Option<RawPreinitConfig>,
+    /// Commands, such as code generators, to run in order before the build
+    /// command. If one fails, the component is not built.
+    pub pre: Option<Vec<String>>,
+    /// Commands, such as `wasm-opt`, to run in order after the build command
+    /// and any pre-initialization. If one fails, the build fails.
+    pub post: Option<Vec<String>>,
+    /// The container image, with the component's build toolchain, in which
+    /// `spin build --in-container` runs the build commands.
+    pub image: Option<String>,
+}
+
+/// Pre-initialization of a component's module. The module's initialization
+/// function is run at build time, and the resulting state snapshotted into
+/// the module, so that it need not run when the module is instantiated.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct RawPreinitConfig {
+    /// The exported function which initializes the module. Defaults to
+    /// `wizer.initialize`.
+    pub init_func: Option<String>,
+    /// Directories, relative to `spin.toml`, which the initialization
+    /// function may read.
+    pub dirs: Option<Vec<PathBuf>>,
 }
 
 /// WebAssembly configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawWasmConfig {
     /// Files to be mapped inside the Wasm module at runtime.
@@ -135,33 +239,65 @@ pub struct RawWasmConfig {
     /// is either a file path or glob relative to the spin.toml file, or a
     /// mapping of a source path to an absolute mount path in the guest.
     pub files: Option<Vec<RawFileMount>>,
-    /// Optional list of file path or glob relative to the spin.toml that don't mount to wasm.
+    /// Optional list of patterns, in `.gitignore` syntax relative to the
+    /// spin.toml, of files that don't mount to wasm. A pattern ending in `/`
+    /// excludes a whole directory, and a pattern starting with `!`
+    /// re-includes files excluded by an earlier pattern.
     /// When exclude_files conflict with files config, exclude_files take precedence.
     pub exclude_files: Option<Vec<String>>,
     /// Optional list of HTTP hosts the component is allowed to connect.
     pub allowed_http_hosts: Option<Vec<String>>,
+    /// Optional list of hosts to which the component may open TCP or UDP
+    /// sockets.
+    pub allowed_outbound_hosts: Option<Vec<String>>,
     /// Optional list of key-value stores the component is allowed to use.
     pub key_value_stores: Option<Vec<String>>,
     /// Optional list of sqlite databases the component is allowed to use.
     pub sqlite_databases: Option<Vec<String>>,
+    /// Optional list of blob containers the component is allowed to use.
+    pub blob_containers: Option<Vec<String>>,
+    /// Optional list of messaging channels the component is allowed to
+    /// publish to.
+    pub messaging_channels: Option<Vec<String>>,
+    /// Whether the component's outbound GET requests may be served from the
+    /// runtime's response cache.
+    pub outbound_http_cache: Option<bool>,
+    /// Resource limits for each instance of the component.
+    pub limits: Option<ResourceLimits>,
+    /// The scratch directory mounted at `/tmp` in each instance of the
+    /// component, if any.
+    pub tmp_dir: Option<TmpDir>,
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: Option<HashMap<String, String>>,
+    /// Names of host environment variables to be forwarded into the Wasm
+    /// module at runtime. A name ending in `*` matches any variable with
+    /// that prefix.
+    pub environment_passthrough: Option<Vec<String>>,
+    /// The resources of each host extension, keyed by extension name, which
+    /// the component is allowed to use. Host extensions are added by
+    /// platforms which embed Spin.
+    pub extensions: Option<HashMap<String, Vec<String>>>,
 }
 
 /// An entry in the `files` list mapping a source path to an absolute
 /// mount path in the guest.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct RawDirectoryPlacement {
     /// The source to mount.
     pub source: PathBuf,
     /// Where to mount the directory specified in `source`.
     pub destination: PathBuf,
+    /// Whether the component may write to the directory. Writable
+    /// directories are mounted directly from `source` rather than copied,
+    /// so that writes are visible on the host.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub writable: bool,
 }
 
 /// A specification for a file or set of files to mount in the
 /// Wasm module.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
 pub enum RawFileMount {
     /// Mount a specified directory at a specified location.
@@ -171,13 +307,15 @@ pub enum RawFileMount {
 }
 
 /// Source for the module.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
 pub enum RawModuleSource {
     /// Local path or parcel reference to a module that needs to be linked.
     FileReference(PathBuf),
     /// Reference to a Wasm file at a URL
     Url(FileComponentUrlSource),
+    /// Wasm file in, or built from, a git repository
+    Git(FileComponentGitSource),
 }
 
 /// A component source from Bindle.
@@ -193,7 +331,7 @@ pub struct FileComponentBindleSource {
     pub parcel: String,
 }
 /// A component source from a URL.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub struct FileComponentUrlSource {
     /// The URL of the Wasm binary.
@@ -203,11 +341,42 @@ pub struct FileComponentUrlSource {
     pub digest: String,
 }
 
+/// A component source from a git repository.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct FileComponentGitSource {
+    /// The URL of the repository.
+    pub git: String,
+    /// The commit, tag or branch to use. Defaults to the repository's
+    /// default branch.
+    pub rev: Option<String>,
+    /// The path of the Wasm binary, relative to the root of the repository.
+    pub path: PathBuf,
+    /// Command, run in the root of the repository, which builds the Wasm
+    /// binary.
+    pub build: Option<String>,
+}
+
 /// FixedStringVersion represents a schema version field with a const value.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(into = "String", try_from = "String")]
 pub struct FixedStringVersion<const V: usize>;
 
+impl<const V: usize> JsonSchema for FixedStringVersion<V> {
+    fn schema_name() -> String {
+        format!("FixedStringVersion{V}")
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            enum_values: Some(vec![V.to_string().into()]),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<const V: usize> From<FixedStringVersion<V>> for String {
     fn from(_: FixedStringVersion<V>) -> String {
         V.to_string()
@@ -0,0 +1,257 @@
+//! Environments in which an application may be run.
+//!
+//! A manifest may define environments, such as `dev` and `prod`, as
+//! `[profile.<name>]` tables. Each overrides settings of the manifest when
+//! the environment is selected, so that one manifest serves every
+//! environment:
+//!
+//! ```toml
+//! [profile.prod]
+//! trigger = { base = "/api" }
+//! variables = { api_url = { default = "https://api.example.com" } }
+//!
+//! [profile.prod.component.api]
+//! allowed_outbound_hosts = ["https://api.example.com:443"]
+//! build = { command = "cargo build --target wasm32-wasi --release" }
+//! trigger = { route = "/..." }
+//! ```
+//!
+//! Variables and component triggers and builds are overridden key by key;
+//! allowed hosts are replaced.
+
+use anyhow::{bail, Context, Result};
+use toml::{value::Table, Value};
+
+const ENVIRONMENTS_KEY: &str = "profile";
+
+// The component settings which an environment may override, and whether
+// they are tables, overridden key by key, rather than replaced.
+const COMPONENT_OVERRIDES: &[(&str, bool)] = &[
+    ("allowed_http_hosts", false),
+    ("allowed_outbound_hosts", false),
+    ("build", true),
+    ("trigger", true),
+];
+
+/// Removes the environments defined by the manifest, returning them.
+pub(crate) fn take_environments(manifest: &mut Table) -> Result<Table> {
+    match manifest.remove(ENVIRONMENTS_KEY) {
+        None => Ok(Table::new()),
+        Some(Value::Table(environments)) => Ok(environments),
+        Some(_) => bail!("`profile` must be a table of environments"),
+    }
+}
+
+/// Applies the overrides of the named environment to the manifest.
+pub(crate) fn apply_environment(
+    manifest: &mut Table,
+    environments: &Table,
+    name: &str,
+) -> Result<()> {
+    let Some(environment) = environments.get(name) else {
+        let mut defined = environments.keys().map(String::as_str).collect::<Vec<_>>();
+        defined.sort_unstable();
+        bail!(
+            "The manifest does not define the environment {name:?}. Defined environments: {}",
+            if defined.is_empty() {
+                "none".to_owned()
+            } else {
+                defined.join(", ")
+            }
+        );
+    };
+    let environment = environment
+        .as_table()
+        .with_context(|| format!("Environment {name:?} must be a table"))?;
+
+    for (key, value) in environment {
+        match key.as_str() {
+            "trigger" => {
+                let trigger = manifest
+                    .get_mut("trigger")
+                    .and_then(Value::as_table_mut)
+                    .context("The manifest must have a `trigger` table")?;
+                merge_table(trigger, value, &format!("{name}.trigger"))?;
+            }
+            "variables" => override_variables(manifest, value, name)?,
+            "component" => override_components(manifest, value, name)?,
+            _ => bail!("Environment {name:?} cannot override `{key}`"),
+        }
+    }
+    Ok(())
+}
+
+fn override_variables(manifest: &mut Table, overrides: &Value, name: &str) -> Result<()> {
+    let overrides = overrides
+        .as_table()
+        .with_context(|| format!("`{name}.variables` must be a table"))?;
+    let mut no_variables = Table::new();
+    let variables = match manifest.get_mut("variables") {
+        Some(variables) => variables
+            .as_table_mut()
+            .context("`variables` must be a table")?,
+        None => &mut no_variables,
+    };
+    for (variable, value) in overrides {
+        let target = variables
+            .get_mut(variable)
+            .with_context(|| {
+                format!("Environment {name:?} overrides undefined variable {variable:?}")
+            })?
+            .as_table_mut()
+            .with_context(|| format!("Variable {variable:?} must be a table"))?;
+        merge_table(target, value, &format!("{name}.variables.{variable}"))?;
+    }
+    Ok(())
+}
+
+fn override_components(manifest: &mut Table, overrides: &Value, name: &str) -> Result<()> {
+    let overrides = overrides
+        .as_table()
+        .with_context(|| format!("`{name}.component` must be a table of components by ID"))?;
+    let components = manifest
+        .get_mut("component")
+        .and_then(Value::as_array_mut)
+        .context("The manifest must have `[[component]]` tables")?;
+    for (id, settings) in overrides {
+        let component = components
+            .iter_mut()
+            .filter_map(Value::as_table_mut)
+            .find(|c| c.get("id").and_then(Value::as_str) == Some(id))
+            .with_context(|| {
+                format!("Environment {name:?} overrides undefined component {id:?}")
+            })?;
+        let settings = settings
+            .as_table()
+            .with_context(|| format!("`{name}.component.{id}` must be a table"))?;
+        for (key, value) in settings {
+            let Some((_, is_table)) = COMPONENT_OVERRIDES.iter().find(|(k, _)| k == key) else {
+                bail!("Environment {name:?} cannot override `{key}` of component {id:?}");
+            };
+            if *is_table {
+                let target = component
+                    .entry(key.clone())
+                    .or_insert_with(|| Value::Table(Table::new()))
+                    .as_table_mut()
+                    .with_context(|| format!("`{key}` of component {id:?} must be a table"))?;
+                merge_table(target, value, &format!("{name}.component.{id}.{key}"))?;
+            } else {
+                component.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_table(target: &mut Table, overrides: &Value, path: &str) -> Result<()> {
+    let overrides = overrides
+        .as_table()
+        .with_context(|| format!("`{path}` must be a table"))?;
+    for (key, value) in overrides {
+        target.insert(key.clone(), value.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        spin_manifest_version = "1"
+        name = "envs"
+        version = "1.0.0"
+        trigger = { type = "http", base = "/" }
+
+        [variables]
+        api_url = { default = "http://localhost:3000" }
+
+        [[component]]
+        id = "api"
+        source = "api.wasm"
+        allowed_outbound_hosts = ["http://localhost:3000"]
+        [component.build]
+        command = "cargo build --target wasm32-wasi"
+        [component.trigger]
+        route = "/api/..."
+
+        [profile.prod]
+        trigger = { base = "/v1" }
+        variables = { api_url = { default = "https://api.example.com" } }
+
+        [profile.prod.component.api]
+        allowed_outbound_hosts = ["https://api.example.com:443"]
+        build = { command = "cargo build --target wasm32-wasi --release" }
+        trigger = { route = "/..." }
+    "#;
+
+    fn manifest() -> (Table, Table) {
+        let mut manifest: Table = toml::from_str(MANIFEST).unwrap();
+        let environments = take_environments(&mut manifest).unwrap();
+        (manifest, environments)
+    }
+
+    #[test]
+    fn environment_overrides_settings() {
+        let (mut manifest, environments) = manifest();
+        assert!(!manifest.contains_key(ENVIRONMENTS_KEY));
+        apply_environment(&mut manifest, &environments, "prod").unwrap();
+
+        assert_eq!("/v1", manifest["trigger"]["base"].as_str().unwrap());
+        assert_eq!("http", manifest["trigger"]["type"].as_str().unwrap());
+        assert_eq!(
+            "https://api.example.com",
+            manifest["variables"]["api_url"]["default"]
+                .as_str()
+                .unwrap()
+        );
+        let api = &manifest["component"][0];
+        assert_eq!(
+            "https://api.example.com:443",
+            api["allowed_outbound_hosts"][0].as_str().unwrap()
+        );
+        assert_eq!(1, api["allowed_outbound_hosts"].as_array().unwrap().len());
+        assert!(api["build"]["command"]
+            .as_str()
+            .unwrap()
+            .ends_with("--release"));
+        assert_eq!("/...", api["trigger"]["route"].as_str().unwrap());
+    }
+
+    #[test]
+    fn environment_must_be_defined() {
+        let (mut manifest, environments) = manifest();
+        let err = apply_environment(&mut manifest, &environments, "staging")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Defined environments: prod"), "{err}");
+    }
+
+    #[test]
+    fn environment_overrides_are_checked() {
+        let (mut manifest, _) = manifest();
+        let environments: Table = toml::from_str(
+            r#"
+            [dev.component.api]
+            source = "debug.wasm"
+            "#,
+        )
+        .unwrap();
+        let err = apply_environment(&mut manifest.clone(), &environments, "dev")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cannot override `source`"), "{err}");
+
+        let environments: Table = toml::from_str(
+            r#"
+            [dev.variables]
+            missing = { default = "x" }
+            "#,
+        )
+        .unwrap();
+        let err = apply_environment(&mut manifest, &environments, "dev")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("undefined variable \"missing\""), "{err}");
+    }
+}
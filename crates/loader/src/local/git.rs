@@ -0,0 +1,230 @@
+//! Component sources in git repositories.
+//!
+//! A component may be taken from a repository which has not published it
+//! elsewhere:
+//!
+//! ```toml
+//! source = { git = "https://github.com/example/components", rev = "v1.2.0", path = "target/wasm32-wasi/release/auth.wasm", build = "cargo build --target wasm32-wasi --release" }
+//! ```
+//!
+//! The repository is cloned into the cache, at the given revision, and the
+//! build command, if any, run in it to produce the module at `path`, which
+//! must be inside the repository. A revision which is a full commit hash is
+//! fetched and built only once; other revisions, such as branches, are
+//! fetched each time the application is loaded, and built when they move or
+//! the build command changes. A checkout is locked while it is fetched and
+//! built, so that concurrent loads wait for each other.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use tokio::process::Command;
+
+use super::config::FileComponentGitSource;
+use crate::cache::Cache;
+
+// Records the commit which was last checked out, and the build command
+// which was run in it, if any.
+const BUILT_MARKER: &str = ".spin-built";
+const LOCK_EXTENSION: &str = "lock";
+
+/// Fetches the source's repository at its revision, builds it if it has a
+/// build command, and returns the path of the module.
+pub(crate) async fn fetch(source: &FileComponentGitSource) -> Result<PathBuf> {
+    let cache = Cache::new(None).await?;
+    let dir = cache.git_dir().join(checkout_name(source));
+    let _lock = lock_checkout(&dir).await?;
+
+    if !dir.join(".git").is_dir() {
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Cannot create {}", dir.display()))?;
+        git(&dir, &["init", "--quiet"]).await?;
+        git(&dir, &["remote", "add", "--", "origin", &source.git]).await?;
+    }
+
+    let rev = source.rev.as_deref().unwrap_or("HEAD");
+    let built = tokio::fs::read_to_string(dir.join(BUILT_MARKER)).await.ok();
+    if is_commit_hash(rev) && built.as_deref() == Some(&built_marker(rev, source)) {
+        if let Ok(module) = module_path(&dir, source) {
+            tracing::debug!("Using cached checkout of {} at {rev}", source.git);
+            return Ok(module);
+        }
+    }
+
+    tracing::debug!("Fetching {} at {rev}", source.git);
+    git(
+        &dir,
+        &["fetch", "--quiet", "--depth", "1", "--", "origin", rev],
+    )
+    .await?;
+    let fetched = git(&dir, &["rev-parse", "FETCH_HEAD"]).await?;
+    let head = git(&dir, &["rev-parse", "--verify", "--quiet", "HEAD"])
+        .await
+        .ok();
+    if head.as_deref() != Some(fetched.as_str()) {
+        git(
+            &dir,
+            &["checkout", "--quiet", "--force", "--detach", &fetched],
+        )
+        .await?;
+    }
+
+    // The output is current if this commit was built with this command, and
+    // the module is still there.
+    let marker = built_marker(&fetched, source);
+    if built.as_deref() != Some(marker.as_str()) || module_path(&dir, source).is_err() {
+        if let Some(command) = &source.build {
+            build(&dir, command).await?;
+        }
+        tokio::fs::write(dir.join(BUILT_MARKER), &marker)
+            .await
+            .with_context(|| format!("Cannot record build of {}", source.git))?;
+    } else {
+        tracing::debug!("{} at {rev} is already built", source.git);
+    }
+    module_path(&dir, source).with_context(|| format!("Cannot use {} at {rev}", source.git))
+}
+
+// The module must exist, and be in the checkout: a path such as
+// `../../wasm/other.wasm` or a symlink out of the repository isn't allowed.
+fn module_path(dir: &Path, source: &FileComponentGitSource) -> Result<PathBuf> {
+    let root = dir.canonicalize()?;
+    let module = dir
+        .join(&source.path)
+        .canonicalize()
+        .with_context(|| format!("Module {} not found", source.path.display()))?;
+    if !module.starts_with(&root) {
+        bail!("Module {} is outside the repository", source.path.display());
+    }
+    Ok(module)
+}
+
+fn built_marker(commit: &str, source: &FileComponentGitSource) -> String {
+    match &source.build {
+        Some(command) => format!("{commit}\n{command}"),
+        None => commit.to_owned(),
+    }
+}
+
+// Locks the checkout against other Spin processes until the returned file is
+// dropped. The lock file is beside the checkout, so that it can be taken
+// before the checkout exists.
+async fn lock_checkout(dir: &Path) -> Result<std::fs::File> {
+    let lock_path = dir.with_extension(LOCK_EXTENSION);
+    tokio::task::spawn_blocking(move || -> Result<std::fs::File> {
+        if let Some(parent) = lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Cannot open {}", lock_path.display()))?;
+        fs2::FileExt::lock_exclusive(&file)
+            .with_context(|| format!("Cannot lock {}", lock_path.display()))?;
+        Ok(file)
+    })
+    .await?
+}
+
+// Each repository and revision is checked out separately, so that
+// applications using different revisions do not disturb each other.
+fn checkout_name(source: &FileComponentGitSource) -> String {
+    let rev = source.rev.as_deref().unwrap_or("HEAD");
+    let key = format!("{}#{rev}", source.git);
+    let digest = spin_common::sha256::hex_digest_from_bytes(key.as_bytes());
+    digest[..16].to_owned()
+}
+
+fn is_commit_hash(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .context("Cannot run git: is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+async fn build(dir: &Path, command: &str) -> Result<()> {
+    tracing::info!("Building git component source with `{command}`");
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .current_dir(dir)
+        .status()
+        .await
+        .with_context(|| format!("Cannot run build command `{command}`"))?;
+    if !status.success() {
+        bail!("Build command `{command}` failed with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(rev: Option<&str>) -> FileComponentGitSource {
+        FileComponentGitSource {
+            git: "https://github.com/example/components".to_owned(),
+            rev: rev.map(str::to_owned),
+            path: "auth.wasm".into(),
+            build: None,
+        }
+    }
+
+    #[test]
+    fn modules_must_be_in_the_checkout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let checkout = temp_dir.path().join("checkout");
+        std::fs::create_dir_all(checkout.join("target")).unwrap();
+        std::fs::write(checkout.join("target/auth.wasm"), b"").unwrap();
+        std::fs::write(temp_dir.path().join("outside.wasm"), b"").unwrap();
+
+        let mut source = source(None);
+        source.path = "target/auth.wasm".into();
+        assert!(module_path(&checkout, &source).is_ok());
+        source.path = "../outside.wasm".into();
+        assert!(module_path(&checkout, &source).is_err());
+        source.path = "target/missing.wasm".into();
+        assert!(module_path(&checkout, &source).is_err());
+    }
+
+    #[test]
+    fn builds_are_keyed_by_commit_and_command() {
+        let mut source = source(None);
+        let unbuilt = built_marker("abc", &source);
+        source.build = Some("make".to_owned());
+        assert_ne!(unbuilt, built_marker("abc", &source));
+        assert_ne!(built_marker("abc", &source), built_marker("def", &source));
+    }
+
+    #[test]
+    fn revisions_are_checked_out_separately() {
+        assert_ne!(
+            checkout_name(&source(Some("v1"))),
+            checkout_name(&source(Some("v2")))
+        );
+        assert_eq!(checkout_name(&source(None)), checkout_name(&source(None)));
+        assert!(is_commit_hash("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_commit_hash("main"));
+    }
+}
@@ -0,0 +1,361 @@
+//! Application manifests split across several files.
+//!
+//! A manifest may name other files with `include = ["components/*.toml"]`.
+//! Each pattern is a glob relative to the manifest's directory, and each
+//! file it matches may define `[[component]]` tables and `[variables]`,
+//! which are added to those of the manifest. Paths in included files, such
+//! as component sources, are relative to the manifest's directory, as they
+//! would be if the components were written in the manifest itself.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use toml::{value::Table, Value};
+
+use super::environment::{apply_environment, take_environments};
+
+const INCLUDE_KEY: &str = "include";
+const COMPONENT_KEY: &str = "component";
+const VARIABLES_KEY: &str = "variables";
+
+/// Reads the manifest file as TOML, with the components and variables of
+/// the files it includes merged into it. It is an error for a component ID
+/// or variable name to be defined in more than one file.
+pub fn read_manifest_toml(manifest_file: &Path) -> Result<Value> {
+    read_manifest_toml_in_environment(manifest_file, None)
+}
+
+/// As [`read_manifest_toml`], with the overrides of the named environment
+/// applied if one is given.
+pub fn read_manifest_toml_in_environment(
+    manifest_file: &Path,
+    environment: Option<&str>,
+) -> Result<Value> {
+    let mut manifest = read_table(manifest_file)?;
+    let environments = take_environments(&mut manifest)?;
+    if let Some(include) = manifest.remove(INCLUDE_KEY) {
+        merge_included(&mut manifest, include, manifest_file)?;
+    }
+    if let Some(environment) = environment {
+        apply_environment(&mut manifest, &environments, environment)?;
+    }
+    Ok(Value::Table(manifest))
+}
+
+fn merge_included(manifest: &mut Table, include: Value, manifest_file: &Path) -> Result<()> {
+    let patterns = include
+        .as_array()
+        .and_then(|patterns| {
+            patterns
+                .iter()
+                .map(Value::as_str)
+                .collect::<Option<Vec<_>>>()
+        })
+        .context("`include` must be a list of file patterns")?;
+
+    let manifest_dir = manifest_file.parent().unwrap_or_else(|| Path::new("."));
+    let mut origins = Origins::new(manifest_file, manifest)?;
+    for pattern in patterns {
+        for file in included_files(manifest_dir, pattern)? {
+            if same_file(&file, manifest_file) {
+                continue;
+            }
+            let included = read_table(&file)?;
+            merge(manifest, included, &file, &mut origins)?;
+        }
+    }
+    Ok(())
+}
+
+/// The files included by the manifest file, in the order in which they are
+/// merged.
+pub fn included_files_of(manifest_file: &Path) -> Result<Vec<PathBuf>> {
+    let manifest = read_table(manifest_file)?;
+    let manifest_dir = manifest_file.parent().unwrap_or_else(|| Path::new("."));
+    let mut files = vec![];
+    if let Some(patterns) = manifest.get(INCLUDE_KEY).and_then(Value::as_array) {
+        for pattern in patterns.iter().filter_map(Value::as_str) {
+            files.extend(
+                included_files(manifest_dir, pattern)?
+                    .into_iter()
+                    .filter(|file| !same_file(file, manifest_file)),
+            );
+        }
+    }
+    Ok(files)
+}
+
+fn read_table(file: &Path) -> Result<Table> {
+    let text = std::fs::read_to_string(file)
+        .with_context(|| format!("Cannot read manifest file {}", file.display()))?;
+    toml::from_str(&text).with_context(|| format!("Cannot parse {}", file.display()))
+}
+
+fn included_files(manifest_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let full_pattern = manifest_dir.join(pattern);
+    let full_pattern = full_pattern
+        .to_str()
+        .with_context(|| format!("Non-unicode include pattern {pattern:?}"))?;
+    let mut files = glob::glob(full_pattern)
+        .with_context(|| format!("Invalid include pattern {pattern:?}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Cannot read files matching include pattern {pattern:?}"))?;
+    if files.is_empty() {
+        bail!("Include pattern {pattern:?} does not match any files");
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// Which file defined each component and variable, for reporting conflicts.
+struct Origins {
+    components: Vec<(String, PathBuf)>,
+    variables: Vec<(String, PathBuf)>,
+}
+
+impl Origins {
+    fn new(manifest_file: &Path, manifest: &Table) -> Result<Self> {
+        let mut origins = Self {
+            components: vec![],
+            variables: vec![],
+        };
+        for component in components(manifest, manifest_file)? {
+            origins.add_component(component_id(component, manifest_file)?, manifest_file)?;
+        }
+        for name in variable_names(manifest, manifest_file)? {
+            origins.add_variable(&name, manifest_file)?;
+        }
+        Ok(origins)
+    }
+
+    fn add_component(&mut self, id: &str, file: &Path) -> Result<()> {
+        if let Some((_, first)) = self.components.iter().find(|(c, _)| c == id) {
+            bail!(
+                "Component {id:?} is defined in both {} and {}",
+                first.display(),
+                file.display()
+            );
+        }
+        self.components.push((id.to_owned(), file.to_owned()));
+        Ok(())
+    }
+
+    fn add_variable(&mut self, name: &str, file: &Path) -> Result<()> {
+        if let Some((_, first)) = self.variables.iter().find(|(v, _)| v == name) {
+            bail!(
+                "Variable {name:?} is defined in both {} and {}",
+                first.display(),
+                file.display()
+            );
+        }
+        self.variables.push((name.to_owned(), file.to_owned()));
+        Ok(())
+    }
+}
+
+fn merge(manifest: &mut Table, included: Table, file: &Path, origins: &mut Origins) -> Result<()> {
+    for (key, value) in included {
+        match key.as_str() {
+            COMPONENT_KEY => {
+                let Value::Array(new_components) = value else {
+                    bail!("{}: `component` must be an array of tables", file.display());
+                };
+                for component in &new_components {
+                    let component = component.as_table().with_context(|| {
+                        format!("{}: `component` must be an array of tables", file.display())
+                    })?;
+                    origins.add_component(component_id(component, file)?, file)?;
+                }
+                match manifest
+                    .entry(COMPONENT_KEY)
+                    .or_insert_with(|| Value::Array(vec![]))
+                {
+                    Value::Array(components) => components.extend(new_components),
+                    _ => bail!("`component` must be an array of tables"),
+                }
+            }
+            VARIABLES_KEY => {
+                let Value::Table(new_variables) = value else {
+                    bail!("{}: `variables` must be a table", file.display());
+                };
+                for name in new_variables.keys() {
+                    origins.add_variable(name, file)?;
+                }
+                match manifest
+                    .entry(VARIABLES_KEY)
+                    .or_insert_with(|| Value::Table(Table::new()))
+                {
+                    Value::Table(variables) => variables.extend(new_variables),
+                    _ => bail!("`variables` must be a table"),
+                }
+            }
+            _ => bail!(
+                "{}: included files may only define `[[component]]` and `[variables]`, not `{key}`",
+                file.display()
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn components<'a>(table: &'a Table, file: &Path) -> Result<Vec<&'a Table>> {
+    match table.get(COMPONENT_KEY) {
+        None => Ok(vec![]),
+        Some(Value::Array(components)) => components
+            .iter()
+            .map(|c| {
+                c.as_table().with_context(|| {
+                    format!("{}: `component` must be an array of tables", file.display())
+                })
+            })
+            .collect(),
+        Some(_) => bail!("{}: `component` must be an array of tables", file.display()),
+    }
+}
+
+fn variable_names(table: &Table, file: &Path) -> Result<Vec<String>> {
+    match table.get(VARIABLES_KEY) {
+        None => Ok(vec![]),
+        Some(Value::Table(variables)) => Ok(variables.keys().cloned().collect()),
+        Some(_) => bail!("{}: `variables` must be a table", file.display()),
+    }
+}
+
+fn component_id<'a>(component: &'a Table, file: &Path) -> Result<&'a str> {
+    component
+        .get("id")
+        .and_then(Value::as_str)
+        .with_context(|| format!("{}: every component must have an `id`", file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, text: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    const MANIFEST: &str = r#"
+        spin_manifest_version = "1"
+        name = "monorepo"
+        version = "1.0.0"
+        trigger = { type = "http", base = "/" }
+        include = ["components/*.toml"]
+
+        [variables]
+        greeting = { default = "hello" }
+
+        [[component]]
+        id = "root"
+        source = "root.wasm"
+        [component.trigger]
+        route = "/..."
+    "#;
+
+    #[test]
+    fn merges_included_components_and_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "spin.toml", MANIFEST);
+        write(
+            dir.path(),
+            "components/a.toml",
+            r#"
+            [variables]
+            name = { required = true }
+
+            [[component]]
+            id = "a"
+            source = "components/a/a.wasm"
+            [component.trigger]
+            route = "/a"
+            "#,
+        );
+        write(
+            dir.path(),
+            "components/b.toml",
+            r#"
+            [[component]]
+            id = "b"
+            source = "components/b/b.wasm"
+            [component.trigger]
+            route = "/b"
+            "#,
+        );
+
+        let manifest = read_manifest_toml(&dir.path().join("spin.toml")).unwrap();
+        assert!(manifest.get(INCLUDE_KEY).is_none());
+        let ids: Vec<_> = manifest[COMPONENT_KEY]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["root", "a", "b"]);
+        assert!(manifest[VARIABLES_KEY].get("greeting").is_some());
+        assert!(manifest[VARIABLES_KEY].get("name").is_some());
+
+        let included = included_files_of(&dir.path().join("spin.toml")).unwrap();
+        assert_eq!(2, included.len());
+    }
+
+    #[test]
+    fn reports_conflicts_between_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "spin.toml", MANIFEST);
+        write(
+            dir.path(),
+            "components/dup.toml",
+            r#"
+            [[component]]
+            id = "root"
+            source = "other.wasm"
+            [component.trigger]
+            route = "/other"
+            "#,
+        );
+        let err = read_manifest_toml(&dir.path().join("spin.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"root\" is defined in both"), "{err}");
+        assert!(err.contains("dup.toml"), "{err}");
+
+        write(
+            dir.path(),
+            "components/dup.toml",
+            r#"
+            [variables]
+            greeting = { default = "hi" }
+            "#,
+        );
+        let err = read_manifest_toml(&dir.path().join("spin.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Variable \"greeting\""), "{err}");
+
+        write(dir.path(), "components/dup.toml", r#"name = "nested""#);
+        let err = read_manifest_toml(&dir.path().join("spin.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not `name`"), "{err}");
+    }
+
+    #[test]
+    fn patterns_must_match_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "spin.toml", MANIFEST);
+        let err = read_manifest_toml(&dir.path().join("spin.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not match any files"), "{err}");
+    }
+}
@@ -6,6 +6,14 @@
 pub mod assets;
 /// Configuration representation for a Spin application as a local spin.toml file.
 pub mod config;
+mod environment;
+mod git;
+mod include;
+/// The JSON schema of the spin.toml manifest, for editors and validation.
+pub mod schema;
+mod strict;
+/// Validation of spin.toml manifests with located diagnostics.
+pub mod validate;
 
 #[cfg(test)]
 mod tests;
@@ -22,12 +30,20 @@ use outbound_http::allowed_http_hosts::validate_allowed_http_hosts;
 use path_absolutize::Absolutize;
 use reqwest::Url;
 use spin_manifest::{
-    Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
-    HttpConfig, ModuleSource, RedisConfig, SpinVersion, TriggerConfig, WasmConfig,
+    Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger,
+    ComponentDependency, CoreComponent, HttpConfig, ModuleSource, RedisConfig, SpinVersion,
+    TriggerConfig, WasmConfig,
 };
-use tokio::{fs::File, io::AsyncReadExt};
 
-use crate::{cache::Cache, validation::validate_key_value_stores};
+use crate::{
+    cache::Cache,
+    validation::{
+        validate_allowed_outbound_hosts, validate_blob_containers,
+        validate_environment_passthrough, validate_key_value_stores, validate_messaging_channels,
+    },
+};
+pub use include::{included_files_of, read_manifest_toml, read_manifest_toml_in_environment};
+
 use config::{
     FileComponentUrlSource, RawAppInformation, RawAppManifest, RawAppManifestAnyVersion,
     RawAppManifestAnyVersionPartial, RawComponentManifest, RawComponentManifestPartial,
@@ -40,28 +56,108 @@ use config::{
 pub async fn from_file(
     app: impl AsRef<Path>,
     base_dst: Option<impl AsRef<Path>>,
+) -> Result<Application> {
+    from_file_with_profile(app, base_dst, None, None).await
+}
+
+/// As [`from_file`], but using the sources of the named build profile for the
+/// components which define it, and with the overrides of the named
+/// environment applied.
+pub async fn from_file_with_profile(
+    app: impl AsRef<Path>,
+    base_dst: Option<impl AsRef<Path>>,
+    profile: Option<&str>,
+    environment: Option<&str>,
 ) -> Result<Application> {
     let app = absolutize(app)?;
-    let manifest = raw_manifest_from_file(&app).await?;
+    let mut manifest = raw_manifest_from_file_in_environment(&app, environment).await?;
     validate_raw_app_manifest(&manifest)?;
+    if let Some(profile) = profile {
+        apply_profile(&mut manifest, profile)?;
+    }
 
-    prepare_any_version(manifest, app, base_dst).await
+    prepare_any_version(manifest, app, base_dst, true).await
 }
 
-/// Reads the spin.toml file as a raw manifest.
+/// As [`from_file_with_profile`], but without downloading the modules of
+/// components whose sources are URLs or git repositories, or running their
+/// build commands, e.g. to describe an application without building it. The
+/// source of such a component is an empty buffer named for the URL or
+/// repository it would come from.
+pub async fn from_file_without_fetching(
+    app: impl AsRef<Path>,
+    base_dst: Option<impl AsRef<Path>>,
+    profile: Option<&str>,
+    environment: Option<&str>,
+) -> Result<Application> {
+    let app = absolutize(app)?;
+    let mut manifest = raw_manifest_from_file_in_environment(&app, environment).await?;
+    validate_raw_app_manifest(&manifest)?;
+    if let Some(profile) = profile {
+        apply_profile(&mut manifest, profile)?;
+    }
+
+    prepare_any_version(manifest, app, base_dst, false).await
+}
+
+/// Applies the named build profile to each component which defines it. It is
+/// an error if no component defines the profile.
+pub fn apply_profile(manifest: &mut RawAppManifestAnyVersion, profile: &str) -> Result<()> {
+    let components = &mut manifest.as_v1_mut().components;
+    if !components.iter().any(|c| c.has_profile(profile)) {
+        bail!("No component defines the build profile {profile:?}");
+    }
+    for component in components {
+        component.apply_profile(profile);
+    }
+    Ok(())
+}
+
+/// Reads the spin.toml file as a raw manifest, merging in any files it
+/// includes.
 pub async fn raw_manifest_from_file(app: &impl AsRef<Path>) -> Result<RawAppManifestAnyVersion> {
-    async fn from_file(app: &Path) -> anyhow::Result<RawAppManifestAnyVersion> {
-        let mut buf = vec![];
-        File::open(app).await?.read_to_end(&mut buf).await?;
-        raw_manifest_from_slice(&buf)
+    raw_manifest_from_file_in_environment(app, None).await
+}
+
+/// As [`raw_manifest_from_file`], with the overrides of the named environment
+/// applied if one is given.
+pub async fn raw_manifest_from_file_in_environment(
+    app: &impl AsRef<Path>,
+    environment: Option<&str>,
+) -> Result<RawAppManifestAnyVersion> {
+    async fn from_file(
+        app: &Path,
+        environment: Option<&str>,
+    ) -> anyhow::Result<RawAppManifestAnyVersion> {
+        let manifest = read_manifest_toml_in_environment(app, environment)?;
+        let manifest = resolve_partials(manifest.try_into()?)?;
+        if manifest.as_v1().info.strict == Some(true) {
+            strict::check_strict(app, &manifest)?;
+        }
+        Ok(manifest)
     }
 
-    let manifest = from_file(app.as_ref())
+    let manifest = from_file(app.as_ref(), environment)
         .await
         .with_context(|| anyhow!("Cannot read spin.toml manifest from {:?}", app.as_ref()))?;
     Ok(manifest)
 }
 
+/// Checks the spin.toml file strictly, as if it set `strict = true`: keys
+/// which Spin does not recognise, variables which no component uses, and
+/// deprecated settings are errors.
+pub async fn check_manifest_strictly(
+    app: &impl AsRef<Path>,
+    environment: Option<&str>,
+) -> Result<()> {
+    let manifest = raw_manifest_from_file_in_environment(app, environment).await?;
+    if manifest.as_v1().info.strict != Some(true) {
+        strict::check_strict(app.as_ref(), &manifest)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
 fn raw_manifest_from_slice(buf: &[u8]) -> Result<RawAppManifestAnyVersion> {
     let partially_parsed = toml::from_slice(buf)?;
     resolve_partials(partially_parsed)
@@ -95,9 +191,10 @@ async fn prepare_any_version(
     raw: RawAppManifestAnyVersion,
     src: impl AsRef<Path>,
     base_dst: Option<impl AsRef<Path>>,
+    fetch_sources: bool,
 ) -> Result<Application> {
     let manifest = raw.into_v1();
-    prepare(manifest, src, base_dst).await
+    prepare(manifest, src, base_dst, fetch_sources).await
 }
 
 /// Iterates over a vector of RawComponentManifest structs and throws an error if any component ids are duplicated
@@ -121,10 +218,26 @@ pub fn validate_raw_app_manifest(raw: &RawAppManifestAnyVersion) -> Result<()> {
         .components
         .iter()
         .try_for_each(|c| validate_allowed_http_hosts(&c.wasm.allowed_http_hosts))?;
+    manifest
+        .components
+        .iter()
+        .try_for_each(|c| validate_allowed_outbound_hosts(&c.wasm.allowed_outbound_hosts))?;
+    manifest
+        .components
+        .iter()
+        .try_for_each(|c| validate_environment_passthrough(&c.wasm.environment_passthrough))?;
     manifest
         .components
         .iter()
         .try_for_each(|c| validate_key_value_stores(&c.wasm.key_value_stores))?;
+    manifest
+        .components
+        .iter()
+        .try_for_each(|c| validate_blob_containers(&c.wasm.blob_containers))?;
+    manifest
+        .components
+        .iter()
+        .try_for_each(|c| validate_messaging_channels(&c.wasm.messaging_channels))?;
 
     Ok(())
 }
@@ -134,6 +247,7 @@ async fn prepare(
     raw: RawAppManifest,
     src: impl AsRef<Path>,
     base_dst: Option<impl AsRef<Path>>,
+    fetch_sources: bool,
 ) -> Result<Application> {
     let info = info(raw.info, &src);
 
@@ -148,7 +262,7 @@ async fn prepare(
     let components = future::join_all(
         raw.components
             .into_iter()
-            .map(|c| async { core(c, &src, base_dst.as_ref()).await }),
+            .map(|c| async { core(c, &src, base_dst.as_ref(), fetch_sources).await }),
     )
     .await
     .into_iter()
@@ -173,6 +287,7 @@ async fn core(
     raw: RawComponentManifest,
     src: impl AsRef<Path>,
     base_dst: Option<impl AsRef<Path>>,
+    fetch_sources: bool,
 ) -> Result<CoreComponent> {
     let id = raw.id;
     let src = parent_dir(src)?;
@@ -180,6 +295,12 @@ async fn core(
         config::RawModuleSource::FileReference(p) => {
             ModuleSource::FileReference(canonicalize_and_absolutize(p, &src)?)
         }
+        config::RawModuleSource::Url(us) if !fetch_sources => {
+            ModuleSource::Buffer(Vec::new(), us.url)
+        }
+        config::RawModuleSource::Git(gs) if !fetch_sources => {
+            ModuleSource::Buffer(Vec::new(), gs.git)
+        }
         config::RawModuleSource::Url(us) => {
             let source = UrlSource::new(&us)
                 .with_context(|| format!("Can't use Web source in component {}", id))?;
@@ -191,8 +312,30 @@ async fn core(
 
             ModuleSource::Buffer(bytes, us.url)
         }
+        config::RawModuleSource::Git(gs) => {
+            let path = git::fetch(&gs)
+                .await
+                .with_context(|| format!("Can't use source {} for component {}", gs.git, id))?;
+            ModuleSource::FileReference(path)
+        }
     };
 
+    let dependencies = raw
+        .dependencies
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(import, dependency)| {
+            let dependency = match dependency {
+                ComponentDependency::Path(p) => {
+                    ComponentDependency::Path(canonicalize_and_absolutize(p, &src)?)
+                }
+                registry => registry,
+            };
+            Ok((import, dependency))
+        })
+        .collect::<Result<_>>()
+        .with_context(|| format!("Invalid dependencies for component {id}"))?;
+
     let description = raw.description;
     let mounts = match raw.wasm.files {
         Some(f) => {
@@ -202,15 +345,31 @@ async fn core(
         None => vec![],
     };
     let environment = raw.wasm.environment.unwrap_or_default();
+    let environment_passthrough = raw.wasm.environment_passthrough.unwrap_or_default();
     let allowed_http_hosts = raw.wasm.allowed_http_hosts.unwrap_or_default();
+    let allowed_outbound_hosts = raw.wasm.allowed_outbound_hosts.unwrap_or_default();
     let key_value_stores = raw.wasm.key_value_stores.unwrap_or_default();
     let sqlite_databases = raw.wasm.sqlite_databases.unwrap_or_default();
+    let blob_containers = raw.wasm.blob_containers.unwrap_or_default();
+    let messaging_channels = raw.wasm.messaging_channels.unwrap_or_default();
+    let outbound_http_cache = raw.wasm.outbound_http_cache.unwrap_or_default();
+    let limits = raw.wasm.limits.unwrap_or_default();
+    let tmp_dir = raw.wasm.tmp_dir;
+    let extensions = raw.wasm.extensions.unwrap_or_default();
     let wasm = WasmConfig {
         environment,
+        environment_passthrough,
         mounts,
         allowed_http_hosts,
+        allowed_outbound_hosts,
         key_value_stores,
         sqlite_databases,
+        blob_containers,
+        messaging_channels,
+        outbound_http_cache,
+        limits,
+        tmp_dir,
+        extensions,
     };
     let config = raw.config.unwrap_or_default();
     Ok(CoreComponent {
@@ -219,6 +378,8 @@ async fn core(
         description,
         wasm,
         config,
+        dependencies,
+        enabled: raw.enabled,
     })
 }
 
@@ -418,7 +579,10 @@ fn resolve_partial_component(
         wasm: partial.wasm,
         trigger,
         build: partial.build,
+        profile: partial.profile,
         config: partial.config,
+        dependencies: partial.dependencies,
+        enabled: partial.enabled,
     })
 }
 
@@ -0,0 +1,112 @@
+//! The JSON schema of the spin.toml manifest.
+//!
+//! The schema is generated from the types into which the manifest is
+//! deserialised, so that it cannot drift from what Spin accepts. Editors
+//! can use it, through a TOML language server, to complete and check
+//! manifests as they are written.
+
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+
+use super::config::RawAppManifestAnyVersion;
+
+/// Top-level keys which are accepted but deprecated, with the key to use
+/// instead if there is one.
+pub const DEPRECATED_KEYS: &[(&str, Option<&str>)] = &[
+    ("spin_version", Some("spin_manifest_version")),
+    ("namespace", None),
+];
+
+/// Generates the JSON schema of the spin.toml manifest.
+pub fn manifest_schema() -> Value {
+    let generator = SchemaSettings::draft07().into_generator();
+    let schema = generator.into_root_schema_for::<RawAppManifestAnyVersion>();
+    let mut schema = serde_json::to_value(schema).expect("schema should serialise to JSON");
+    add_manifest_only_keys(&mut schema);
+    schema
+}
+
+// Keys which are handled as TOML before the manifest is deserialised, and
+// so are not described by the manifest types.
+fn add_manifest_only_keys(schema: &mut Value) {
+    schema["title"] = json!("Spin application manifest");
+
+    let properties = &mut schema["properties"];
+    properties["spin_version"] = json!({
+        "description": "Version of the manifest format.",
+        "deprecated": true,
+        "type": "string",
+        "enum": ["1"],
+    });
+    properties["include"] = json!({
+        "description": "Glob patterns, relative to the manifest, of files defining further components and variables.",
+        "type": "array",
+        "items": { "type": "string" },
+    });
+    properties["profile"] = json!({
+        "description": "Environments, such as `dev` and `prod`, which override settings of the manifest when selected with `--environment`.",
+        "type": "object",
+        "additionalProperties": {
+            "type": "object",
+            "properties": {
+                "trigger": { "type": "object" },
+                "variables": {
+                    "type": "object",
+                    "additionalProperties": { "type": "object" },
+                },
+                "component": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "properties": {
+                            "allowed_http_hosts": { "type": "array", "items": { "type": "string" } },
+                            "allowed_outbound_hosts": { "type": "array", "items": { "type": "string" } },
+                            "build": { "type": "object" },
+                            "trigger": { "type": "object" },
+                        },
+                        "additionalProperties": false,
+                    },
+                },
+            },
+            "additionalProperties": false,
+        },
+    });
+    if let Some(namespace) = properties.get_mut("namespace") {
+        namespace["deprecated"] = json!(true);
+    }
+
+    // Either spelling of the version key is required, but not both.
+    if let Some(required) = schema["required"].as_array_mut() {
+        required.retain(|key| *key != "spin_manifest_version");
+    }
+    schema["oneOf"] = json!([
+        { "required": ["spin_manifest_version"] },
+        { "required": ["spin_version"] },
+    ]);
+    schema["additionalProperties"] = json!(false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_describes_manifest() {
+        let schema = manifest_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        for key in [
+            "spin_manifest_version",
+            "name",
+            "trigger",
+            "component",
+            "variables",
+        ] {
+            assert!(properties.contains_key(key), "missing {key}");
+        }
+        assert_eq!(json!(true), properties["spin_version"]["deprecated"]);
+        assert!(schema["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("name")));
+    }
+}
@@ -0,0 +1,104 @@
+//! Strict checking of manifests.
+//!
+//! Some mistakes in a manifest are accepted silently: a misspelt key, such
+//! as `allowed_outbound_host`, is ignored, as is a variable which nothing
+//! uses, and deprecated settings still work. In strict mode, selected with
+//! `--strict` or with `strict = true` in the manifest, they are errors, so
+//! that they are caught before they cause failures at runtime.
+
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{bail, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{config::RawAppManifestAnyVersion, validate::validate_manifest_text};
+
+lazy_static! {
+    static ref TEMPLATE_VARIABLE: Regex =
+        Regex::new(r"\{\{\s*([a-zA-Z0-9_]+)\s*\}\}").expect("valid regex");
+}
+
+/// Checks the manifest file strictly, given the manifest it loads as.
+pub(crate) fn check_strict(manifest_file: &Path, raw: &RawAppManifestAnyVersion) -> Result<()> {
+    let text = std::fs::read_to_string(manifest_file)
+        .with_context(|| format!("Cannot read manifest file {}", manifest_file.display()))?;
+    let mut problems = validate_manifest_text(&text)
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    problems.extend(
+        unused_variables(raw)
+            .into_iter()
+            .map(|name| format!("error: variable {name:?} is not used by any component")),
+    );
+
+    if !problems.is_empty() {
+        bail!(
+            "{} is not valid in strict mode:\n{}",
+            manifest_file.display(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+// Variables which are not referenced by any component's config or
+// `enabled` expression, in name order.
+fn unused_variables(raw: &RawAppManifestAnyVersion) -> Vec<String> {
+    let manifest = raw.as_v1();
+    let used = manifest
+        .components
+        .iter()
+        .flat_map(|component| {
+            component
+                .config
+                .iter()
+                .flat_map(|config| config.values())
+                .chain(component.enabled.iter())
+        })
+        .flat_map(|template| TEMPLATE_VARIABLE.captures_iter(template))
+        .map(|captures| captures[1].to_owned())
+        .collect::<HashSet<_>>();
+    let mut unused = manifest
+        .variables
+        .keys()
+        .filter(|name| !used.contains(*name))
+        .cloned()
+        .collect::<Vec<_>>();
+    unused.sort();
+    unused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_unused_variables() {
+        let raw: RawAppManifestAnyVersion = toml::from_str(
+            r#"
+            spin_manifest_version = "1"
+            name = "strict"
+            version = "1.0.0"
+            trigger = { type = "http", base = "/" }
+
+            [variables]
+            api_key = { required = true }
+            admin_ui = { default = "false" }
+            unused = { default = "x" }
+
+            [[component]]
+            id = "api"
+            source = "api.wasm"
+            enabled = "{{ admin_ui }}"
+            [component.config]
+            key = "Bearer {{api_key}}"
+            [component.trigger]
+            route = "/..."
+            "#,
+        )
+        .unwrap();
+        assert_eq!(vec!["unused".to_owned()], unused_variables(&raw));
+    }
+}
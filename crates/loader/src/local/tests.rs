@@ -107,6 +107,7 @@ fn test_manifest() -> Result<()> {
         RawFileMount::Placement(RawDirectoryPlacement {
             source: PathBuf::from("valid-with-files"),
             destination: PathBuf::from("/vwf"),
+            writable: false,
         })
     );
     assert_eq!(
@@ -277,6 +278,77 @@ async fn test_insecure_allow_all_with_invalid_url() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_build_profile_overrides_source_and_command() -> Result<()> {
+    const MANIFEST: &str = include_str!("../../tests/build-profiles.toml");
+
+    let mut cfg_any = raw_manifest_from_str(MANIFEST)?;
+    apply_profile(&mut cfg_any, "release")?;
+    let cfg = cfg_any.into_v1();
+
+    let source = |i: usize| match &cfg.components[i].source {
+        RawModuleSource::FileReference(path) => path.clone(),
+        RawModuleSource::Url(_) => panic!("expected file source"),
+    };
+    assert_eq!(source(0), PathBuf::from("target/release/profiled.wasm"));
+    assert_eq!(
+        cfg.components[0].build.as_ref().unwrap().command,
+        "cargo build --release"
+    );
+    assert_eq!(source(1), PathBuf::from("unprofiled.wasm"));
+
+    let mut cfg_any = raw_manifest_from_str(MANIFEST)?;
+    let e = apply_profile(&mut cfg_any, "relaese")
+        .unwrap_err()
+        .to_string();
+    assert!(e.contains("relaese"), "{e}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_allowed_outbound_hosts_are_rejected() -> Result<()> {
+    const MANIFEST: &str = "tests/invalid-allowed-outbound-hosts.toml";
+
+    let temp_dir = tempfile::tempdir()?;
+    let dir = temp_dir.path();
+    let e = from_file(MANIFEST, Some(dir))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        e.contains("smtp.example.com:25"),
+        "Expected allowed_outbound_hosts parse error to contain `smtp.example.com:25`: {e}"
+    );
+    assert!(
+        !e.contains("tcp://smtp.example.com:587"),
+        "Expected `tcp://smtp.example.com:587` to be accepted: {e}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_udp_allowed_outbound_hosts_are_rejected() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let manifest = temp_dir.path().join("spin.toml");
+    let contents = std::fs::read_to_string("tests/invalid-allowed-outbound-hosts.toml")?
+        .replace("  \"smtp.example.com:25\",\n", "");
+    std::fs::write(&manifest, contents)?;
+    let e = from_file(&manifest, Some(temp_dir.path()))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        e.contains("udp://dns.example.com:53") && e.contains("UDP"),
+        "Expected UDP allowed_outbound_hosts entry to be rejected: {e}"
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_invalid_url_in_allowed_http_hosts_is_rejected() -> Result<()> {
     const MANIFEST: &str = "tests/invalid-url-in-allowed-http-hosts.toml";
@@ -0,0 +1,298 @@
+//! Validation of spin.toml manifests, reporting where in the file each
+//! problem lies.
+
+use std::{fmt, ops::Range, path::Path};
+
+use anyhow::{Context, Result};
+use jsonschema::JSONSchema;
+use toml_edit::{Document, Item};
+
+use super::{
+    raw_manifest_from_file_in_environment,
+    schema::{manifest_schema, DEPRECATED_KEYS},
+    validate_raw_app_manifest,
+};
+
+/// How serious a problem with a manifest is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The manifest cannot be loaded.
+    Error,
+    /// The manifest can be loaded, but should be changed.
+    Warning,
+}
+
+/// A position in a manifest file. Lines and columns start at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// The line of the position.
+    pub line: usize,
+    /// The column of the position, in characters.
+    pub column: usize,
+}
+
+/// A problem found in a manifest.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A description of the problem.
+    pub message: String,
+    /// Where in the manifest the problem lies, if it can be attributed to
+    /// one place.
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, location: Option<Location>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            location,
+        }
+    }
+
+    fn warning(message: impl Into<String>, location: Option<Location>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            location,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Location { line, column }) = self.location {
+            write!(f, "{line}:{column}: ")?;
+        }
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// Validates the manifest file against the manifest schema, and checks that
+/// it loads, along with the files it includes, in each of the environments
+/// it defines. Returns the problems found, which are empty if the manifest
+/// is valid; it is only an error if the file cannot be read.
+pub async fn validate_manifest_file(manifest_file: &Path) -> Result<Vec<Diagnostic>> {
+    let text = std::fs::read_to_string(manifest_file)
+        .with_context(|| format!("Cannot read manifest file {}", manifest_file.display()))?;
+    let mut diagnostics = validate_manifest_text(&text);
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Ok(diagnostics);
+    }
+
+    // The schema cannot express everything that is checked on load, such as
+    // the form of allowed hosts, nor check the files the manifest includes.
+    let mut environments = vec![None];
+    if let Ok(toml::Value::Table(manifest)) = toml::from_str(&text) {
+        if let Some(toml::Value::Table(profiles)) = manifest.get("profile") {
+            environments.extend(profiles.keys().cloned().map(Some));
+        }
+    }
+    for environment in environments {
+        let loaded = raw_manifest_from_file_in_environment(&manifest_file, environment.as_deref())
+            .await
+            .and_then(|raw| validate_raw_app_manifest(&raw));
+        if let Err(e) = loaded {
+            let message = format!("{e:#}");
+            diagnostics.push(Diagnostic::error(
+                match environment {
+                    Some(environment) => format!("in environment {environment:?}: {message}"),
+                    None => message,
+                },
+                None,
+            ));
+        }
+    }
+    Ok(diagnostics)
+}
+
+/// Validates the text of a manifest against the manifest schema.
+pub fn validate_manifest_text(text: &str) -> Vec<Diagnostic> {
+    let manifest: toml::Value = match toml::from_str(text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let location = e.line_col().map(|(line, column)| Location {
+                line: line + 1,
+                column: column + 1,
+            });
+            return vec![Diagnostic::error(e.to_string(), location)];
+        }
+    };
+    // The document is only used to find where keys are written.
+    let document = text.parse::<Document>().ok();
+    let locate = |path: &[String]| {
+        document
+            .as_ref()
+            .and_then(|document| span_of(document, path))
+            .map(|span| location_of(text, span.start))
+    };
+
+    let instance = match serde_json::to_value(&manifest) {
+        Ok(instance) => instance,
+        Err(e) => return vec![Diagnostic::error(e.to_string(), None)],
+    };
+    let schema = manifest_schema();
+    let validator = JSONSchema::compile(&schema).expect("manifest schema should be valid");
+
+    let mut diagnostics = vec![];
+    if let Err(errors) = validator.validate(&instance) {
+        for error in errors {
+            let path = pointer_segments(&error.instance_path.to_string());
+            let message = match path.is_empty() {
+                true => error.to_string(),
+                false => format!("`{}`: {error}", path.join(".")),
+            };
+            diagnostics.push(Diagnostic::error(message, locate(&path)));
+        }
+    }
+
+    for (key, replacement) in DEPRECATED_KEYS {
+        if instance.get(key).is_some() {
+            let message = match replacement {
+                Some(replacement) => format!("`{key}` is deprecated; use `{replacement}`"),
+                None => format!("`{key}` is deprecated and ignored"),
+            };
+            diagnostics.push(Diagnostic::warning(message, locate(&[key.to_string()])));
+        }
+    }
+    diagnostics
+}
+
+/// Finds where the item at the given path of keys and array indexes, such
+/// as `["component", "0", "trigger"]`, is written in TOML text. If the path
+/// does not exist, this is where its innermost existing ancestor is written.
+pub fn locate(text: &str, path: &[String]) -> Option<Location> {
+    let document = text.parse::<Document>().ok()?;
+    span_of(&document, path).map(|span| location_of(text, span.start))
+}
+
+// The segments of a JSON pointer such as `/component/0/trigger`.
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+// The span of the innermost item on the path which the document records.
+fn span_of(document: &Document, path: &[String]) -> Option<Range<usize>> {
+    let mut item: &Item = document.as_item();
+    let mut span = None;
+    for segment in path {
+        let next = match segment.parse::<usize>() {
+            Ok(index) if item.is_array() || item.is_array_of_tables() => item.get(index),
+            _ => item.get(segment.as_str()),
+        };
+        let Some(next) = next else {
+            break;
+        };
+        item = next;
+        span = item.span().or(span);
+    }
+    span
+}
+
+fn location_of(text: &str, offset: usize) -> Location {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Location {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"spin_manifest_version = "1"
+name = "validate"
+version = "1.0.0"
+trigger = { type = "http", base = "/" }
+
+[[component]]
+id = "hello"
+source = "hello.wasm"
+[component.trigger]
+route = "/hello"
+"#;
+
+    #[test]
+    fn valid_manifest_has_no_problems() {
+        let diagnostics = validate_manifest_text(MANIFEST);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    #[tokio::test]
+    async fn root_may_only_include_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_file = dir.path().join("spin.toml");
+        let root = r#"spin_manifest_version = "1"
+name = "validate"
+version = "1.0.0"
+trigger = { type = "http", base = "/" }
+include = ["components/*.toml"]
+"#;
+        std::fs::write(&manifest_file, root).unwrap();
+        std::fs::create_dir(dir.path().join("components")).unwrap();
+        std::fs::write(
+            dir.path().join("components/hello.toml"),
+            r#"[[component]]
+id = "hello"
+source = "hello.wasm"
+[component.trigger]
+route = "/hello"
+"#,
+        )
+        .unwrap();
+
+        let diagnostics = validate_manifest_text(root);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let diagnostics = validate_manifest_file(&manifest_file).await.unwrap();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+
+    #[test]
+    fn errors_are_located() {
+        let text = MANIFEST.replace(
+            r#"id = "hello""#,
+            r#"id = "hello"
+sourse = "typo.wasm""#,
+        );
+        let diagnostics = validate_manifest_text(&text);
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.severity == Severity::Error && d.location.is_some()));
+
+        let text = MANIFEST.replace("name = \"validate\"", "name = ");
+        let diagnostics = validate_manifest_text(&text);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(2, diagnostics[0].location.unwrap().line);
+    }
+
+    #[test]
+    fn deprecated_keys_are_warned_about() {
+        let text = MANIFEST.replace("spin_manifest_version", "spin_version");
+        let diagnostics = validate_manifest_text(&text);
+        assert_eq!(1, diagnostics.len(), "{diagnostics:?}");
+        assert_eq!(Severity::Warning, diagnostics[0].severity);
+        assert_eq!(1, diagnostics[0].location.unwrap().line);
+    }
+
+    #[test]
+    fn locations_count_lines_and_characters() {
+        let text = "a = 1\nb = \"é\" c";
+        assert_eq!(Location { line: 2, column: 1 }, location_of(text, 6));
+        assert_eq!(
+            Location { line: 2, column: 9 },
+            location_of(text, text.len() - 1)
+        );
+    }
+}
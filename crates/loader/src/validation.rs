@@ -1,4 +1,30 @@
 use anyhow::{ensure, Context, Result};
+use spin_manifest::{AllowedOutboundHost, SocketProtocol};
+
+pub(crate) fn validate_allowed_outbound_hosts(hosts: &Option<Vec<String>>) -> Result<()> {
+    for host in hosts.iter().flatten() {
+        let host = host.parse::<AllowedOutboundHost>()?;
+        // WASI grants socket addresses for every protocol at once, so a UDP
+        // entry can't be granted without also allowing TCP.
+        ensure!(
+            host.protocol == SocketProtocol::Tcp,
+            "invalid allowed_outbound_hosts entry {:?}: UDP sockets are not supported",
+            host.to_string()
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_environment_passthrough(names: &Option<Vec<String>>) -> Result<()> {
+    for name in names.iter().flatten() {
+        let prefix = name.strip_suffix('*').unwrap_or(name);
+        ensure!(
+            !prefix.is_empty() && !prefix.contains(['*', '=']),
+            "invalid environment_passthrough entry {name:?}: expected a variable name, optionally ending in `*`"
+        );
+    }
+    Ok(())
+}
 
 pub(crate) fn validate_key_value_stores(key_value_stores: &Option<Vec<String>>) -> Result<()> {
     for store in key_value_stores.iter().flatten() {
@@ -8,6 +34,22 @@ pub(crate) fn validate_key_value_stores(key_value_stores: &Option<Vec<String>>)
     Ok(())
 }
 
+pub(crate) fn validate_blob_containers(blob_containers: &Option<Vec<String>>) -> Result<()> {
+    for container in blob_containers.iter().flatten() {
+        validate_component_like_label(container)
+            .with_context(|| format!("invalid container label {container:?}"))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_messaging_channels(messaging_channels: &Option<Vec<String>>) -> Result<()> {
+    for channel in messaging_channels.iter().flatten() {
+        validate_component_like_label(channel)
+            .with_context(|| format!("invalid channel label {channel:?}"))?;
+    }
+    Ok(())
+}
+
 // For forward-compatibility with component model value imports, validate that
 // the given string is like a component model label, except (currently) with
 // snake_case instead of kebab-case.
@@ -73,4 +115,15 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn environment_passthrough_allows_trailing_wildcard_only() -> Result<()> {
+        validate_environment_passthrough(&Some(vec!["LANG".to_owned(), "AWS_*".to_owned()]))?;
+        for invalid in ["", "*", "*_KEY", "A*B", "A=B"] {
+            validate_environment_passthrough(&Some(vec![invalid.to_owned()]))
+                .err()
+                .with_context(|| format!("{invalid:?} should be invalid"))?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use spin_app::{AppComponent, DynamicHostComponent, MetadataKey};
+use spin_core::HostComponent;
+use spin_world::lock;
+
+use crate::{LockDispatch, LockStore};
+
+// Set by spin-trigger's locked app loader from the application manifest.
+const APP_NAME_KEY: MetadataKey = MetadataKey::new("name");
+
+/// The lock host component, which gives every component of an application
+/// the same locks in a shared store.
+pub struct LockComponent {
+    store: Arc<dyn LockStore>,
+}
+
+impl LockComponent {
+    pub fn new(store: impl LockStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+}
+
+impl HostComponent for LockComponent {
+    type Data = LockDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        lock::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        LockDispatch::new(self.store.clone())
+    }
+}
+
+impl DynamicHostComponent for LockComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let app_name = component
+            .app
+            .get_metadata(APP_NAME_KEY)?
+            .unwrap_or_default();
+        data.init(app_name);
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Lets components coordinate exclusive work, such as scheduled jobs and
+//! migrations, across concurrent invocations and Spin instances, with named
+//! locks held by expiring leases. Locks are kept in Redis, or in a SQLite
+//! database which instances on the same host share.
+
+mod host_component;
+mod redis_store;
+mod sqlite_store;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use spin_app::async_trait;
+use spin_world::lock;
+use tracing::instrument;
+
+pub use host_component::LockComponent;
+pub use lock::Error;
+pub use redis_store::RedisLockStore;
+pub use sqlite_store::{DatabaseLocation, SqliteLockStore};
+
+/// The longest a lease may be taken or renewed for, so that a lock held by
+/// an instance which stopped is always freed eventually.
+pub const MAX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A store of locks, each held by at most one unexpired lease, identified by
+/// a token.
+#[async_trait]
+pub trait LockStore: Send + Sync {
+    /// Takes the named lock for `token` for `ttl`, unless another lease
+    /// which has not expired holds it. Returns whether the lock was taken.
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool>;
+
+    /// Extends the lease to `ttl` from now, if it still holds the lock.
+    /// Returns whether it does.
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool>;
+
+    /// Releases the lock, if the lease holds it.
+    async fn release(&self, name: &str, token: &str) -> Result<()>;
+}
+
+pub struct LockDispatch {
+    store: Arc<dyn LockStore>,
+    // Lock names are qualified with the application name, so that
+    // applications sharing a store don't share locks.
+    namespace: String,
+}
+
+impl LockDispatch {
+    pub fn new(store: Arc<dyn LockStore>) -> Self {
+        Self {
+            store,
+            namespace: String::new(),
+        }
+    }
+
+    pub fn init(&mut self, namespace: String) {
+        self.namespace = namespace;
+    }
+
+    fn qualified_name(&self, name: &str) -> String {
+        format!("{}/{name}", self.namespace)
+    }
+}
+
+#[async_trait]
+impl lock::Host for LockDispatch {
+    #[instrument(name = "spin_lock.lock", skip(self), fields(otel.kind = "client"))]
+    async fn lock(&mut self, name: String, ttl_ms: u64) -> Result<Result<Option<String>, Error>> {
+        Ok(async {
+            let ttl = ttl(ttl_ms)?;
+            let token = uuid::Uuid::new_v4().to_string();
+            let acquired = self
+                .store
+                .acquire(&self.qualified_name(&name), &token, ttl)
+                .await
+                .map_err(log_error)?;
+            Ok(acquired.then_some(token))
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_lock.renew", skip(self, lease), fields(otel.kind = "client"))]
+    async fn renew(
+        &mut self,
+        name: String,
+        lease: String,
+        ttl_ms: u64,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            let ttl = ttl(ttl_ms)?;
+            let renewed = self
+                .store
+                .renew(&self.qualified_name(&name), &lease, ttl)
+                .await
+                .map_err(log_error)?;
+            if renewed {
+                Ok(())
+            } else {
+                Err(Error::LeaseLost)
+            }
+        }
+        .await)
+    }
+
+    #[instrument(name = "spin_lock.release", skip(self, lease), fields(otel.kind = "client"))]
+    async fn release(&mut self, name: String, lease: String) -> Result<Result<(), Error>> {
+        Ok(self
+            .store
+            .release(&self.qualified_name(&name), &lease)
+            .await
+            .map_err(log_error))
+    }
+}
+
+fn ttl(ttl_ms: u64) -> Result<Duration, Error> {
+    let ttl = Duration::from_millis(ttl_ms);
+    if ttl.is_zero() || ttl > MAX_TTL {
+        return Err(Error::InvalidTtl);
+    }
+    Ok(ttl)
+}
+
+pub fn log_error(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("lock error: {err:?}");
+    Error::Io(format!("{err:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use lock::Host;
+
+    use super::*;
+
+    fn dispatch(store: &Arc<dyn LockStore>, app: &str) -> LockDispatch {
+        let mut dispatch = LockDispatch::new(store.clone());
+        dispatch.init(app.to_owned());
+        dispatch
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn leases_hold_locks_until_released() -> Result<()> {
+        let store: Arc<dyn LockStore> = Arc::new(SqliteLockStore::new(DatabaseLocation::InMemory));
+        let mut first = dispatch(&store, "app");
+        let mut second = dispatch(&store, "app");
+        let mut other_app = dispatch(&store, "other-app");
+
+        let lease = first
+            .lock("migrate".into(), 60_000)
+            .await?
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.lock("migrate".into(), 60_000).await?.unwrap(), None);
+        assert!(other_app
+            .lock("migrate".into(), 60_000)
+            .await?
+            .unwrap()
+            .is_some());
+
+        first
+            .renew("migrate".into(), lease.clone(), 60_000)
+            .await?
+            .unwrap();
+        assert!(matches!(
+            second
+                .renew("migrate".into(), "not-the-lease".into(), 60_000)
+                .await?,
+            Err(Error::LeaseLost)
+        ));
+
+        first
+            .release("migrate".into(), lease.clone())
+            .await?
+            .unwrap();
+        assert!(matches!(
+            first.renew("migrate".into(), lease, 60_000).await?,
+            Err(Error::LeaseLost)
+        ));
+        assert!(second
+            .lock("migrate".into(), 60_000)
+            .await?
+            .unwrap()
+            .is_some());
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expired_leases_lose_locks() -> Result<()> {
+        let store: Arc<dyn LockStore> = Arc::new(SqliteLockStore::new(DatabaseLocation::InMemory));
+        let mut first = dispatch(&store, "app");
+        let mut second = dispatch(&store, "app");
+
+        let lease = first.lock("job".into(), 1).await?.unwrap().unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(second.lock("job".into(), 60_000).await?.unwrap().is_some());
+        assert!(matches!(
+            first.renew("job".into(), lease, 60_000).await?,
+            Err(Error::LeaseLost)
+        ));
+        assert!(matches!(
+            first.lock("job".into(), 0).await?,
+            Err(Error::InvalidTtl)
+        ));
+        Ok(())
+    }
+}
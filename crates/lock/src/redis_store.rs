@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use redis::{aio::Connection, parse_redis_url, RedisResult, Script};
+use spin_app::async_trait;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::LockStore;
+
+// Locks are kept under their own prefix, so they don't clash with other
+// data in the database.
+const KEY_PREFIX: &str = "spin-lock:";
+
+// Each script only changes the lock if the lease holds it.
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+  return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+  return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Keeps locks in Redis, as keys which expire with their leases.
+pub struct RedisLockStore {
+    database_url: url::Url,
+    // Made when first needed, and made again after a command fails.
+    connection: Mutex<Option<Connection>>,
+}
+
+impl RedisLockStore {
+    pub fn new(address: &str) -> Result<Self> {
+        let database_url = parse_redis_url(address).context("Invalid Redis URL")?;
+        Ok(Self {
+            database_url,
+            connection: Mutex::new(None),
+        })
+    }
+
+    // Returns the connection, connecting if there is none.
+    async fn connection(&self) -> Result<MutexGuard<'_, Option<Connection>>> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            let client = redis::Client::open(self.database_url.clone())?;
+            *connection = Some(client.get_async_connection().await?);
+        }
+        Ok(connection)
+    }
+}
+
+// Returns the result of a command, dropping the connection if it failed so
+// that the next command reconnects.
+fn checked<T>(connection: &mut Option<Connection>, result: RedisResult<T>) -> Result<T> {
+    if result.is_err() {
+        *connection = None;
+    }
+    Ok(result?)
+}
+
+#[async_trait]
+impl LockStore for RedisLockStore {
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool> {
+        let mut conn = self.connection().await?;
+        let result = redis::cmd("SET")
+            .arg(format!("{KEY_PREFIX}{name}"))
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(conn.as_mut().unwrap())
+            .await;
+        let reply: Option<String> = checked(&mut conn, result)?;
+        Ok(reply.is_some())
+    }
+
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool> {
+        let mut conn = self.connection().await?;
+        let result = Script::new(RENEW_SCRIPT)
+            .key(format!("{KEY_PREFIX}{name}"))
+            .arg(token)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async(conn.as_mut().unwrap())
+            .await;
+        let renewed: i64 = checked(&mut conn, result)?;
+        Ok(renewed == 1)
+    }
+
+    async fn release(&self, name: &str, token: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+        let result = Script::new(RELEASE_SCRIPT)
+            .key(format!("{KEY_PREFIX}{name}"))
+            .arg(token)
+            .invoke_async(conn.as_mut().unwrap())
+            .await;
+        let _: i64 = checked(&mut conn, result)?;
+        Ok(())
+    }
+}
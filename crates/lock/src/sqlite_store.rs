@@ -0,0 +1,133 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use spin_app::async_trait;
+use tokio::{sync::OnceCell, task};
+
+use crate::LockStore;
+
+// How long to wait for another instance's write to the database to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub enum DatabaseLocation {
+    InMemory,
+    Path(PathBuf),
+}
+
+/// Keeps locks in a SQLite database, which Spin instances on the same host
+/// can share.
+pub struct SqliteLockStore {
+    location: DatabaseLocation,
+    connection: OnceCell<Arc<Mutex<Connection>>>,
+}
+
+impl SqliteLockStore {
+    pub fn new(location: DatabaseLocation) -> Self {
+        Self {
+            location,
+            connection: OnceCell::new(),
+        }
+    }
+
+    async fn connection(&self) -> Result<Arc<Mutex<Connection>>> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| {
+                let location = self.location.clone();
+                async move { task::spawn_blocking(move || open(&location)).await? }
+            })
+            .await?;
+        Ok(connection.clone())
+    }
+
+    // Runs `f` with the connection on a blocking thread, as SQLite calls
+    // block while another instance writes to the database.
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let connection = self.connection().await?;
+        task::spawn_blocking(move || Ok(f(&connection.lock().unwrap())?)).await?
+    }
+}
+
+fn open(location: &DatabaseLocation) -> Result<Arc<Mutex<Connection>>> {
+    let connection = match location {
+        DatabaseLocation::InMemory => Connection::open_in_memory(),
+        DatabaseLocation::Path(path) => Connection::open(path),
+    }?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS spin_lock (
+           name       TEXT PRIMARY KEY,
+           token      TEXT NOT NULL,
+           expires_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(Arc::new(Mutex::new(connection)))
+}
+
+#[async_trait]
+impl LockStore for SqliteLockStore {
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool> {
+        // A held lock is only replaced if its lease has expired.
+        let (name, token) = (name.to_owned(), token.to_owned());
+        let now = now_millis();
+        let changed = self
+            .with_connection(move |conn| {
+                conn.execute(
+                    "INSERT INTO spin_lock (name, token, expires_at) VALUES (?1, ?2, ?4)
+                     ON CONFLICT (name) DO UPDATE
+                     SET token = excluded.token, expires_at = excluded.expires_at
+                     WHERE spin_lock.expires_at <= ?3",
+                    params![name, token, now, expires_at(now, ttl)],
+                )
+            })
+            .await?;
+        Ok(changed == 1)
+    }
+
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool> {
+        let (name, token) = (name.to_owned(), token.to_owned());
+        let now = now_millis();
+        let changed = self
+            .with_connection(move |conn| {
+                conn.execute(
+                    "UPDATE spin_lock SET expires_at = ?4
+                     WHERE name = ?1 AND token = ?2 AND expires_at > ?3",
+                    params![name, token, now, expires_at(now, ttl)],
+                )
+            })
+            .await?;
+        Ok(changed == 1)
+    }
+
+    async fn release(&self, name: &str, token: &str) -> Result<()> {
+        let (name, token) = (name.to_owned(), token.to_owned());
+        self.with_connection(move |conn| {
+            conn.execute(
+                "DELETE FROM spin_lock WHERE name = ?1 AND token = ?2",
+                params![name, token],
+            )
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+fn expires_at(now: i64, ttl: Duration) -> i64 {
+    now.saturating_add(ttl.as_millis() as i64)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
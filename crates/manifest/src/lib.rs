@@ -4,11 +4,13 @@
 
 use std::{
     collections::HashMap,
-    fmt::{Debug, Formatter},
+    fmt::{Debug, Display, Formatter},
     path::PathBuf,
+    str::FromStr,
 };
 
 use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A trigger error.
@@ -26,6 +28,9 @@ pub enum Error {
     /// Non-string 'type' key in trigger declaration.
     #[error("the trigger type must be a string")]
     NonStringTriggerType,
+    /// Invalid entry in `allowed_outbound_hosts`.
+    #[error("invalid allowed_outbound_hosts entry {0:?}: expected tcp://<host>:<port> or udp://<host>:<port>")]
+    InvalidOutboundHost(String),
 }
 
 /// An ordered map of component IDs to some value.
@@ -89,6 +94,25 @@ pub struct CoreComponent {
     pub wasm: WasmConfig,
     /// Per-component configuration values.
     pub config: HashMap<String, String>,
+    /// Components which satisfy the component's imports, by import name.
+    /// They are composed with the component when it is loaded.
+    pub dependencies: HashMap<String, ComponentDependency>,
+    /// An expression, such as `{{ admin_ui }}`, resolved from the
+    /// application's variables when the application is loaded. The
+    /// component runs only if it resolves to true; unset means always.
+    pub enabled: Option<String>,
+}
+
+/// A component which satisfies an import of another component.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum ComponentDependency {
+    /// A component file. In the manifest, the path is relative to the
+    /// directory of `spin.toml`.
+    Path(PathBuf),
+    /// A component published to a registry, such as
+    /// `ghcr.io/fermyon/kv-cache:1.0.0`.
+    Registry(String),
 }
 
 /// A custom config variable.
@@ -131,12 +155,12 @@ pub enum ApplicationTrigger {
     External(ExternalTriggerConfiguration),
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", untagged)]
 enum ApplicationTriggerSerialised {
     Internal(InternalApplicationTriggerSerialised),
     /// A trigger type that is not built in.
-    External(HashMap<String, toml::Value>),
+    External(#[schemars(with = "HashMap<String, serde_json::Value>")] HashMap<String, toml::Value>),
 }
 
 /// Deserialisation helper - we need all unmatched `trigger.type` values to
@@ -152,7 +176,7 @@ struct ApplicationTriggerDeserialised {
     parameters: toml::Value,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Eq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Eq, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 enum InternalApplicationTriggerSerialised {
     /// HTTP trigger type.
@@ -203,8 +227,19 @@ impl From<ApplicationTrigger> for ApplicationTriggerSerialised {
     }
 }
 
+// The manifest form of a trigger is that which it serialises to.
+impl JsonSchema for ApplicationTrigger {
+    fn schema_name() -> String {
+        "ApplicationTrigger".to_owned()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        ApplicationTriggerSerialised::json_schema(gen)
+    }
+}
+
 /// HTTP trigger configuration.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
 pub struct HttpTriggerConfiguration {
     /// Base path for the HTTP application.
     pub base: String,
@@ -228,7 +263,7 @@ impl TryFrom<ApplicationTrigger> for HttpTriggerConfiguration {
 }
 
 /// Redis trigger configuration.
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, JsonSchema)]
 pub struct RedisTriggerConfiguration {
     /// Address of Redis server.
     pub address: String,
@@ -264,14 +299,141 @@ impl ExternalTriggerConfiguration {
 pub struct WasmConfig {
     /// Environment variables to be mapped inside the Wasm module at runtime.
     pub environment: HashMap<String, String>,
+    /// Names of host environment variables to be forwarded into the Wasm
+    /// module at runtime. A name ending in `*` matches any variable with
+    /// that prefix.
+    pub environment_passthrough: Vec<String>,
     /// List of directory mounts that need to be mapped inside the WebAssembly module.
     pub mounts: Vec<DirectoryMount>,
     /// Optional list of HTTP hosts the component is allowed to connect.
     pub allowed_http_hosts: Vec<String>,
+    /// Optional list of hosts to which the component may open TCP sockets,
+    /// each of the form `tcp://<host>:<port>`. The `udp://` scheme is
+    /// reserved for UDP sockets, which are not yet supported.
+    pub allowed_outbound_hosts: Vec<String>,
     /// Optional list of key-value stores the component is allowed to use.
     pub key_value_stores: Vec<String>,
     /// Optional list of sqlite databases the component is allowed to use.
     pub sqlite_databases: Vec<String>,
+    /// Optional list of blob containers the component is allowed to use.
+    pub blob_containers: Vec<String>,
+    /// Optional list of messaging channels the component is allowed to
+    /// publish to.
+    pub messaging_channels: Vec<String>,
+    /// Whether the component's outbound GET requests may be served from the
+    /// runtime's response cache.
+    pub outbound_http_cache: bool,
+    /// Resource limits for each instance of the component.
+    pub limits: ResourceLimits,
+    /// The scratch directory mounted at `/tmp` in each instance of the
+    /// component, if any.
+    pub tmp_dir: Option<TmpDir>,
+    /// The resources of each host extension, keyed by extension name, which
+    /// the component is allowed to use.
+    pub extensions: HashMap<String, Vec<String>>,
+}
+
+/// Resource limits for each instance of a component. Unset limits are not
+/// enforced.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct ResourceLimits {
+    /// The maximum size of the instance's linear memory, in megabytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_mb: Option<u64>,
+    /// The fuel available to the instance. Executing Wasm consumes fuel,
+    /// roughly one unit per instruction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fuel: Option<u64>,
+    /// The maximum time for which the instance may run, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Returns these limits, with any unset limit taken from `other`.
+    pub fn or(self, other: &Self) -> Self {
+        Self {
+            max_memory_mb: self.max_memory_mb.or(other.max_memory_mb),
+            max_fuel: self.max_fuel.or(other.max_fuel),
+            timeout_ms: self.timeout_ms.or(other.timeout_ms),
+        }
+    }
+}
+
+/// A host to which a component may open sockets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowedOutboundHost {
+    /// The socket protocol.
+    pub protocol: SocketProtocol,
+    /// The host name or IP address.
+    pub host: String,
+    /// The port.
+    pub port: u16,
+}
+
+/// The protocol of an [`AllowedOutboundHost`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketProtocol {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+}
+
+impl FromStr for AllowedOutboundHost {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidOutboundHost(s.to_owned());
+        let (scheme, address) = s.split_once("://").ok_or_else(invalid)?;
+        let protocol = match scheme {
+            "tcp" => SocketProtocol::Tcp,
+            "udp" => SocketProtocol::Udp,
+            _ => return Err(invalid()),
+        };
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        // IPv6 addresses are bracketed, as in URLs.
+        let host = match host.strip_prefix('[') {
+            Some(host) => host.strip_suffix(']').ok_or_else(invalid)?,
+            None if host.contains(':') => return Err(invalid()),
+            None => host,
+        };
+        if host.is_empty() || host.contains(['/', '[', ']']) {
+            return Err(invalid());
+        }
+        let port = port.parse().map_err(|_| invalid())?;
+        Ok(Self {
+            protocol,
+            host: host.to_owned(),
+            port,
+        })
+    }
+}
+
+impl Display for AllowedOutboundHost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scheme = match self.protocol {
+            SocketProtocol::Tcp => "tcp",
+            SocketProtocol::Udp => "udp",
+        };
+        if self.host.contains(':') {
+            write!(f, "{scheme}://[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{scheme}://{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// A writable scratch directory, created empty for each instance of a
+/// component and removed when the instance is dropped.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct TmpDir {
+    /// The size, in megabytes, which the directory must stay within. Writes
+    /// which could take the directory beyond it fail.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size_mb: Option<u64>,
 }
 
 /// Directory mount for the assets of a component.
@@ -281,6 +443,8 @@ pub struct DirectoryMount {
     pub guest: String,
     /// Host directory source for mounting inside the module.
     pub host: PathBuf,
+    /// Whether the module may write to the directory.
+    pub writable: bool,
 }
 
 /// Source for the entrypoint Wasm module of a component.
@@ -309,12 +473,16 @@ impl Debug for ModuleSource {
     }
 }
 /// Configuration for the HTTP trigger.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct HttpConfig {
     /// HTTP route the component will be invoked for.
     pub route: String,
     /// The HTTP executor the component requires.
     pub executor: Option<HttpExecutor>,
+    /// The authentication required of requests before they reach the
+    /// component.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
 }
 
 impl Default for HttpConfig {
@@ -322,16 +490,41 @@ impl Default for HttpConfig {
         Self {
             route: "/".to_string(),
             executor: Default::default(),
+            auth: None,
         }
     }
 }
 
+/// JWT bearer token authentication for an HTTP component.
+///
+/// Requests must carry an `Authorization: Bearer` token signed by the
+/// issuer, or they are rejected without invoking the component. The
+/// validated claims are passed to the component in the `spin-auth-claims`
+/// header.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub struct HttpAuth {
+    /// The issuer which tokens must be issued by, compared with their `iss`
+    /// claim.
+    pub issuer: String,
+    /// The URL of the issuer's JSON Web Key Set. If it is not set, it is
+    /// discovered from the issuer's OpenID Connect configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_url: Option<String>,
+    /// Tokens must be intended for at least one of these audiences, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audiences: Vec<String>,
+    /// Scopes which tokens must all grant.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
 /// The executor for the HTTP component.
 /// The component can either implement the Spin HTTP interface,
 /// or the Wagi CGI interface.
 ///
 /// If an executor is not specified, the inferred default is `HttpExecutor::Spin`.
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 pub enum HttpExecutor {
     /// The component implements the Spin HTTP interface.
@@ -347,7 +540,7 @@ impl Default for HttpExecutor {
 }
 
 /// Wagi specific configuration for the http executor.
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(default, deny_unknown_fields, rename_all = "camelCase")]
 pub struct WagiConfig {
     /// The name of the entrypoint.
@@ -378,7 +571,7 @@ impl Default for WagiConfig {
 }
 
 /// Configuration for the Redis trigger.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct RedisConfig {
     /// Redis channel to subscribe.
     pub channel: String,
@@ -389,7 +582,7 @@ pub struct RedisConfig {
 /// The executor for the Redis component.
 ///
 /// If an executor is not specified, the inferred default is `RedisExecutor::Spin`.
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", tag = "type")]
 pub enum RedisExecutor {
     /// The component implements the Spin Redis interface.
@@ -403,7 +596,7 @@ impl Default for RedisExecutor {
 }
 
 /// Trigger configuration.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "camelCase", untagged)]
 pub enum TriggerConfig {
     /// HTTP trigger configuration
@@ -411,7 +604,7 @@ pub enum TriggerConfig {
     /// Redis trigger configuration
     Redis(RedisConfig),
     /// External trigger configuration
-    External(HashMap<String, toml::Value>),
+    External(#[schemars(with = "HashMap<String, serde_json::Value>")] HashMap<String, toml::Value>),
 }
 
 impl Default for TriggerConfig {
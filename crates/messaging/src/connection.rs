@@ -0,0 +1,46 @@
+use std::{fmt::Debug, future::Future, sync::Arc};
+
+use spin_world::messaging::Error;
+use tokio::sync::Mutex;
+
+use crate::publish_failed;
+
+/// A connection to a broker, which is made when it is first needed and made
+/// again once it has failed.
+pub(crate) struct Reconnecting<T> {
+    current: Mutex<Option<Arc<T>>>,
+}
+
+impl<T> Reconnecting<T> {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Returns the current connection, or makes one with `connect`.
+    pub async fn get<F, Fut, E>(&self, connect: F) -> Result<Arc<T>, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Debug,
+    {
+        let mut current = self.current.lock().await;
+        if let Some(connection) = &*current {
+            return Ok(connection.clone());
+        }
+        let connection = Arc::new(connect().await.map_err(publish_failed)?);
+        *current = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Drops the given connection, which has failed, so that the next
+    /// publish reconnects. A connection which has already been replaced is
+    /// left alone.
+    pub async fn discard(&self, connection: &Arc<T>) {
+        let mut current = self.current.lock().await;
+        if matches!(&*current, Some(c) if Arc::ptr_eq(c, connection)) {
+            *current = None;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::anyhow;
+use spin_app::{AppComponent, DynamicHostComponent};
+use spin_core::HostComponent;
+use spin_world::messaging;
+
+use crate::{MessagingDispatch, Publisher, MESSAGING_CHANNELS_KEY};
+
+/// The messaging host component, which gives components the channels named
+/// in their `messaging_channels`.
+pub struct MessagingComponent {
+    channels: Arc<HashMap<String, Arc<dyn Publisher>>>,
+}
+
+impl MessagingComponent {
+    /// Creates a component with the given channels, by name.
+    pub fn new(channels: impl IntoIterator<Item = (String, Arc<dyn Publisher>)>) -> Self {
+        Self {
+            channels: Arc::new(channels.into_iter().collect()),
+        }
+    }
+}
+
+impl HostComponent for MessagingComponent {
+    type Data = MessagingDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        messaging::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        MessagingDispatch::new(self.channels.clone())
+    }
+}
+
+impl DynamicHostComponent for MessagingComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let channels = component
+            .get_metadata(MESSAGING_CHANNELS_KEY)?
+            .unwrap_or_default();
+        data.init(channels.into_iter().collect());
+        Ok(())
+    }
+
+    fn validate_app(&self, app: &spin_app::App) -> anyhow::Result<()> {
+        let mut errors = vec![];
+
+        for component in app.components() {
+            for allowed in component
+                .get_metadata(MESSAGING_CHANNELS_KEY)?
+                .unwrap_or_default()
+            {
+                if !self.channels.contains_key(&allowed) {
+                    let err = format!("- Component {} uses channel '{allowed}'", component.id());
+                    errors.push(err);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let prologue = vec![
+                "One or more components use messaging channels which are not defined.",
+                "Check the spelling, or pass a runtime configuration file that defines these channels.",
+                "Details:",
+            ];
+            let lines: Vec<_> = prologue
+                .into_iter()
+                .map(|s| s.to_owned())
+                .chain(errors)
+                .collect();
+            Err(anyhow!(lines.join("\n")))
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use rskafka::{
+    client::{
+        partition::{Compression, PartitionClient, UnknownTopicHandling},
+        ClientBuilder,
+    },
+    record::Record,
+};
+use spin_app::async_trait;
+use spin_world::messaging::{Error, Message};
+
+use crate::{connection::Reconnecting, publish_failed, Publisher};
+
+/// Publishes messages to a partition of a Kafka topic, with their metadata
+/// as record headers.
+pub struct KafkaPublisher {
+    brokers: Vec<String>,
+    topic: String,
+    partition: i32,
+    client: Reconnecting<PartitionClient>,
+}
+
+impl KafkaPublisher {
+    /// Creates a publisher which connects to the given bootstrap brokers,
+    /// e.g. `localhost:9092`, when it first publishes.
+    pub fn new(brokers: Vec<String>, topic: impl Into<String>, partition: i32) -> Self {
+        Self {
+            brokers,
+            topic: topic.into(),
+            partition,
+            client: Reconnecting::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for KafkaPublisher {
+    async fn publish(&self, message: &Message) -> Result<(), Error> {
+        let client = self
+            .client
+            .get(|| async {
+                let client = ClientBuilder::new(self.brokers.clone()).build().await?;
+                client
+                    .partition_client(
+                        self.topic.clone(),
+                        self.partition,
+                        UnknownTopicHandling::Error,
+                    )
+                    .await
+            })
+            .await?;
+        let record = Record {
+            key: None,
+            value: Some(message.payload.clone()),
+            headers: message
+                .metadata
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone().into_bytes()))
+                .collect(),
+            timestamp: chrono::Utc::now(),
+        };
+        if let Err(err) = client
+            .produce(vec![record], Compression::NoCompression)
+            .await
+        {
+            self.client.discard(&client).await;
+            return Err(publish_failed(err));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Lets components publish messages to named channels, which runtime config
+//! maps to a NATS subject, Kafka topic, RabbitMQ exchange or SQS queue, so
+//! that they can emit events without protocol-specific code.
+
+#[cfg(any(feature = "kafka", feature = "nats", feature = "rabbitmq"))]
+mod connection;
+mod host_component;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "rabbitmq")]
+mod rabbitmq;
+#[cfg(feature = "sqs")]
+mod sqs;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use spin_app::{async_trait, MetadataKey};
+use spin_world::messaging::{self, Message};
+use tracing::instrument;
+
+pub use host_component::MessagingComponent;
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaPublisher;
+#[cfg(feature = "nats")]
+pub use nats::NatsPublisher;
+#[cfg(feature = "rabbitmq")]
+pub use rabbitmq::RabbitMqPublisher;
+#[cfg(feature = "sqs")]
+pub use sqs::SqsPublisher;
+
+pub const MESSAGING_CHANNELS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("messaging_channels");
+
+pub use messaging::Error;
+
+/// A backend which publishes the messages sent to one channel.
+#[async_trait]
+pub trait Publisher: Send + Sync {
+    async fn publish(&self, message: &Message) -> Result<(), Error>;
+}
+
+pub struct MessagingDispatch {
+    allowed_channels: HashSet<String>,
+    channels: Arc<HashMap<String, Arc<dyn Publisher>>>,
+}
+
+impl MessagingDispatch {
+    pub fn new(channels: Arc<HashMap<String, Arc<dyn Publisher>>>) -> Self {
+        Self {
+            allowed_channels: HashSet::new(),
+            channels,
+        }
+    }
+
+    pub fn init(&mut self, allowed_channels: HashSet<String>) {
+        self.allowed_channels = allowed_channels;
+    }
+}
+
+#[async_trait]
+impl messaging::Host for MessagingDispatch {
+    #[instrument(name = "spin_messaging.publish", skip(self, message), fields(otel.kind = "producer"))]
+    async fn publish(&mut self, channel: String, message: Message) -> Result<Result<(), Error>> {
+        Ok(async {
+            if !self.allowed_channels.contains(&channel) {
+                return Err(Error::AccessDenied);
+            }
+            let publisher = self.channels.get(&channel).ok_or(Error::NoSuchChannel)?;
+            publisher.publish(&message).await
+        }
+        .await)
+    }
+}
+
+/// Logs an error from a backend, and returns it as a
+/// [`Error::PublishFailed`]. The details, which may name hosts or
+/// credentials, are only logged.
+pub fn publish_failed(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("messaging error: {err:?}");
+    Error::PublishFailed("the message could not be published".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use messaging::Host;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingPublisher(Mutex<Vec<Vec<u8>>>);
+
+    #[async_trait]
+    impl Publisher for RecordingPublisher {
+        async fn publish(&self, message: &Message) -> Result<(), Error> {
+            self.0.lock().unwrap().push(message.payload.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_are_published_to_allowed_channels() -> Result<()> {
+        let orders = Arc::new(RecordingPublisher::default());
+        let channels = HashMap::from([
+            ("orders".to_owned(), orders.clone() as Arc<dyn Publisher>),
+            (
+                "audit".to_owned(),
+                Arc::new(RecordingPublisher::default()) as _,
+            ),
+        ]);
+        let mut dispatch = MessagingDispatch::new(Arc::new(channels));
+        dispatch.init(["orders", "missing"].map(String::from).into());
+
+        let message = || Message {
+            payload: b"order 42".to_vec(),
+            metadata: vec![("content-type".into(), "text/plain".into())],
+        };
+        dispatch.publish("orders".into(), message()).await?.unwrap();
+        assert!(matches!(
+            dispatch.publish("audit".into(), message()).await?,
+            Err(Error::AccessDenied)
+        ));
+        assert!(matches!(
+            dispatch.publish("missing".into(), message()).await?,
+            Err(Error::NoSuchChannel)
+        ));
+        assert_eq!(*orders.0.lock().unwrap(), [b"order 42".to_vec()]);
+        Ok(())
+    }
+}
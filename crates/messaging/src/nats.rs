@@ -0,0 +1,54 @@
+use spin_app::async_trait;
+use spin_world::messaging::{Error, Message};
+
+use crate::{connection::Reconnecting, publish_failed, Publisher};
+
+/// Publishes messages to a NATS subject, with their metadata as headers.
+pub struct NatsPublisher {
+    url: String,
+    subject: String,
+    client: Reconnecting<async_nats::Client>,
+}
+
+impl NatsPublisher {
+    /// Creates a publisher which connects to the server at `url`, e.g.
+    /// `nats://localhost:4222`, when it first publishes.
+    pub fn new(url: impl Into<String>, subject: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            subject: subject.into(),
+            client: Reconnecting::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for NatsPublisher {
+    async fn publish(&self, message: &Message) -> Result<(), Error> {
+        let client = self
+            .client
+            .get(|| async_nats::connect(self.url.as_str()))
+            .await?;
+        let mut headers = async_nats::HeaderMap::new();
+        for (name, value) in &message.metadata {
+            headers.append(name.as_str(), value.as_str());
+        }
+        let result = async {
+            client
+                .publish_with_headers(
+                    self.subject.clone(),
+                    headers,
+                    message.payload.clone().into(),
+                )
+                .await
+                .map_err(publish_failed)?;
+            // Publishing only queues the message; wait for it to be sent.
+            client.flush().await.map_err(publish_failed)
+        }
+        .await;
+        if result.is_err() {
+            self.client.discard(&client).await;
+        }
+        result
+    }
+}
@@ -0,0 +1,80 @@
+use lapin::{
+    options::BasicPublishOptions,
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties, Channel, Connection, ConnectionProperties,
+};
+use spin_app::async_trait;
+use spin_world::messaging::{Error, Message};
+
+use crate::{connection::Reconnecting, publish_failed, Publisher};
+
+/// Publishes messages to a RabbitMQ exchange with a routing key, with their
+/// metadata as headers.
+pub struct RabbitMqPublisher {
+    url: String,
+    exchange: String,
+    routing_key: String,
+    // The connection is kept alongside its channel, which closes with it.
+    channel: Reconnecting<(Connection, Channel)>,
+}
+
+impl RabbitMqPublisher {
+    /// Creates a publisher which connects to the broker at `url`, e.g.
+    /// `amqp://localhost:5672`, when it first publishes. An empty
+    /// `exchange` is the default exchange, which routes to the queue named
+    /// by the routing key.
+    pub fn new(
+        url: impl Into<String>,
+        exchange: impl Into<String>,
+        routing_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            exchange: exchange.into(),
+            routing_key: routing_key.into(),
+            channel: Reconnecting::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for RabbitMqPublisher {
+    async fn publish(&self, message: &Message) -> Result<(), Error> {
+        let connection = self
+            .channel
+            .get(|| async {
+                let connection =
+                    Connection::connect(&self.url, ConnectionProperties::default()).await?;
+                let channel = connection.create_channel().await?;
+                Ok::<_, lapin::Error>((connection, channel))
+            })
+            .await?;
+        let (_, channel) = &*connection;
+        let mut headers = FieldTable::default();
+        for (name, value) in &message.metadata {
+            headers.insert(
+                ShortString::from(name.clone()),
+                AMQPValue::LongString(LongString::from(value.clone())),
+            );
+        }
+        let result = async {
+            channel
+                .basic_publish(
+                    &self.exchange,
+                    &self.routing_key,
+                    BasicPublishOptions::default(),
+                    &message.payload,
+                    BasicProperties::default().with_headers(headers),
+                )
+                .await?
+                // Wait for the broker to confirm the message, if confirms are on.
+                .await
+        }
+        .await;
+        if let Err(err) = result {
+            self.channel.discard(&connection).await;
+            return Err(publish_failed(err));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use aws_sdk_sqs::{config::Region, types::MessageAttributeValue, Client};
+use spin_app::async_trait;
+use spin_world::messaging::{Error, Message};
+use tokio::sync::OnceCell;
+
+use crate::{publish_failed, Publisher};
+
+/// Sends messages to an SQS queue, with their metadata as string message
+/// attributes. SQS message bodies are text, so payloads must be UTF-8.
+pub struct SqsPublisher {
+    queue_url: String,
+    region: Option<String>,
+    client: OnceCell<Client>,
+}
+
+impl SqsPublisher {
+    /// Creates a publisher for the queue at `queue_url`. Credentials, and
+    /// the region if it is not given, are read from the usual AWS
+    /// environment variables and config files when it first publishes.
+    pub fn new(queue_url: impl Into<String>, region: Option<String>) -> Self {
+        Self {
+            queue_url: queue_url.into(),
+            region,
+            client: OnceCell::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for SqsPublisher {
+    async fn publish(&self, message: &Message) -> Result<(), Error> {
+        let body = std::str::from_utf8(&message.payload)
+            .map_err(|_| Error::InvalidMessage("SQS message payloads must be UTF-8 text".into()))?;
+        let client = self
+            .client
+            .get_or_init(|| async {
+                let mut loader = aws_config::from_env();
+                if let Some(region) = &self.region {
+                    loader = loader.region(Region::new(region.clone()));
+                }
+                Client::new(&loader.load().await)
+            })
+            .await;
+        let mut request = client
+            .send_message()
+            .queue_url(&self.queue_url)
+            .message_body(body);
+        for (name, value) in &message.metadata {
+            let attribute = MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(value)
+                .build();
+            request = request.message_attributes(name, attribute);
+        }
+        request.send().await.map_err(publish_failed)?;
+        Ok(())
+    }
+}
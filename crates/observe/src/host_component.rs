@@ -0,0 +1,29 @@
+use spin_app::{AppComponent, DynamicHostComponent};
+use spin_core::HostComponent;
+use spin_world::observe;
+
+use crate::ObserveDispatch;
+
+pub struct ObserveComponent;
+
+impl HostComponent for ObserveComponent {
+    type Data = ObserveDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        observe::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        ObserveDispatch::new()
+    }
+}
+
+impl DynamicHostComponent for ObserveComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        data.component_id = component.id().to_owned();
+        Ok(())
+    }
+}
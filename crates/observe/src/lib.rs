@@ -0,0 +1,98 @@
+//! Lets components open spans and record metrics which are exported along
+//! with the runtime's own telemetry.
+
+mod host_component;
+
+use spin_app::async_trait;
+use spin_key_value::table;
+use spin_telemetry::guest::GuestSpan;
+use spin_world::observe;
+
+pub use host_component::ObserveComponent;
+
+/// An implementation of the observe host interface for a component.
+pub struct ObserveDispatch {
+    component_id: String,
+    spans: table::Table<GuestSpan>,
+}
+
+impl ObserveDispatch {
+    fn new() -> Self {
+        Self {
+            component_id: String::new(),
+            spans: table::Table::new(256),
+        }
+    }
+}
+
+#[async_trait]
+impl observe::Host for ObserveDispatch {
+    async fn open_span(
+        &mut self,
+        name: String,
+        parent: Option<observe::Span>,
+    ) -> anyhow::Result<Result<observe::Span, observe::Error>> {
+        Ok(async {
+            let parent = match parent {
+                Some(parent) => Some(self.spans.get(parent).ok_or(observe::Error::InvalidSpan)?),
+                None => None,
+            };
+            let span = GuestSpan::start(name, &self.component_id, parent);
+            self.spans
+                .push(span)
+                .map_err(|()| observe::Error::TooManySpans)
+        }
+        .await)
+    }
+
+    async fn set_attribute(
+        &mut self,
+        span: observe::Span,
+        key: String,
+        value: String,
+    ) -> anyhow::Result<Result<(), observe::Error>> {
+        Ok(async {
+            let span = self.spans.get(span).ok_or(observe::Error::InvalidSpan)?;
+            span.set_attribute(key, value);
+            Ok(())
+        }
+        .await)
+    }
+
+    async fn close_span(&mut self, span: observe::Span) -> anyhow::Result<()> {
+        if let Some(span) = self.spans.remove(span) {
+            span.end();
+        }
+        Ok(())
+    }
+
+    async fn increment_counter(
+        &mut self,
+        name: String,
+        value: u64,
+        labels: Vec<(String, String)>,
+    ) -> anyhow::Result<Result<(), observe::Error>> {
+        Ok(spin_telemetry::metrics::increment_guest_counter(
+            &self.component_id,
+            &name,
+            value,
+            &labels,
+        )
+        .map_err(|err| observe::Error::InvalidMetric(err.to_string())))
+    }
+
+    async fn record_histogram(
+        &mut self,
+        name: String,
+        value: f64,
+        labels: Vec<(String, String)>,
+    ) -> anyhow::Result<Result<(), observe::Error>> {
+        Ok(spin_telemetry::metrics::record_guest_histogram(
+            &self.component_id,
+            &name,
+            value,
+            &labels,
+        )
+        .map_err(|err| observe::Error::InvalidMetric(err.to_string())))
+    }
+}
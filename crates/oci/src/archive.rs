@@ -0,0 +1,421 @@
+//! Archives of pulled applications, for moving them between registries, or
+//! into the cache, without network access between the two.
+//!
+//! An archive is a gzipped tarball in the OCI image layout: an `index.json`
+//! naming the application's manifest, with the manifest, config and layers
+//! stored by digest under `blobs`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::{
+    manifest::{OciDescriptor, OciImageManifest},
+    Reference,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    client::{write_atomically, WASM_LAYER_MEDIA_TYPE},
+    qualify_reference,
+    upload::Uploader,
+    Client,
+};
+
+const OCI_LAYOUT_FILE: &str = "oci-layout";
+const INDEX_FILE: &str = "index.json";
+const BLOBS_DIR: &str = "blobs";
+const OCI_LAYOUT: &str = r#"{"imageLayoutVersion":"1.0.0"}"#;
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Index {
+    schema_version: u32,
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+    size: usize,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+impl Client {
+    /// Pull a Spin application and save it, with all its layers, to an
+    /// archive file.
+    pub async fn save(&mut self, reference: &str, archive: &Path) -> Result<()> {
+        self.pull(reference).await?;
+
+        let manifest_bytes = tokio::fs::read(self.manifest_path(reference).await?).await?;
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&manifest_bytes).context("cannot parse cached manifest")?;
+        let config = tokio::fs::read(self.lockfile_path(reference).await?).await?;
+
+        let mut blobs = vec![(manifest.config.digest.clone(), config)];
+        for layer in &manifest.layers {
+            let path = if layer.media_type == WASM_LAYER_MEDIA_TYPE {
+                self.cache.wasm_file(&layer.digest)?
+            } else {
+                self.cache.data_file(&layer.digest)?
+            };
+            blobs.push((layer.digest.clone(), tokio::fs::read(path).await?));
+        }
+        let manifest_digest = sha256_digest(&manifest_bytes);
+        let index = Index {
+            schema_version: 2,
+            manifests: vec![Descriptor {
+                media_type: manifest
+                    .media_type
+                    .clone()
+                    .unwrap_or_else(|| OCI_MANIFEST_MEDIA_TYPE.to_owned()),
+                digest: manifest_digest.clone(),
+                size: manifest_bytes.len(),
+                annotations: [(REF_NAME_ANNOTATION.to_owned(), reference.to_owned())]
+                    .into_iter()
+                    .collect(),
+            }],
+        };
+        blobs.push((manifest_digest, manifest_bytes));
+
+        write_archive(archive, &index, &blobs)
+    }
+
+    /// Load an application saved by [`Client::save`] and push it to the
+    /// given reference, returning the reference it was pushed to.
+    pub async fn load(&mut self, archive: &Path, reference: &str) -> Result<String> {
+        let app = ArchivedApp::read(archive)?;
+        let target: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        let auth = Self::auth(&target).await?;
+        let mut uploader = Uploader::new(&target, auth, self.insecure, &self.cache)?;
+        let mut pushed = HashSet::new();
+        for (layer, bytes) in &app.layers {
+            // Identical files share a layer digest, and are pushed once.
+            if pushed.insert(&layer.digest) {
+                uploader.push_blob(bytes, &layer.digest).await?;
+            }
+        }
+        uploader
+            .push_blob(&app.config, &app.manifest.config.digest)
+            .await?;
+        uploader
+            .push_manifest(&target, &app.manifest)
+            .await
+            .context("cannot push Spin application")?;
+        Ok(target.to_string())
+    }
+
+    /// Load an application saved by [`Client::save`] into the cache under
+    /// the given reference, so that it can be run without access to that
+    /// registry. The reference the archive records is not used, as nothing
+    /// vouches for it. Returns the reference under which the application was
+    /// loaded.
+    pub async fn load_into_cache(&mut self, archive: &Path, reference: &str) -> Result<String> {
+        let app = ArchivedApp::read(archive)?;
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        if let Some(digest) = reference.digest() {
+            if digest != app.manifest_digest {
+                bail!(
+                    "the archived application has digest {}, not {digest}",
+                    app.manifest_digest
+                );
+            }
+        }
+        for (layer, bytes) in &app.layers {
+            if layer.media_type == WASM_LAYER_MEDIA_TYPE {
+                self.cache.write_wasm(bytes, &layer.digest).await?;
+            } else {
+                self.cache.write_data(bytes, &layer.digest).await?;
+            }
+        }
+        let reference = reference.to_string();
+        write_atomically(&self.manifest_path(&reference).await?, &app.manifest_bytes).await?;
+        write_atomically(&self.lockfile_path(&reference).await?, &app.config).await?;
+        Ok(reference)
+    }
+
+    /// The reference an archive written by [`Client::save`] says it was
+    /// saved from, if any.
+    pub fn archived_reference(archive: &Path) -> Result<Option<String>> {
+        Ok(ArchivedApp::read(archive)?.saved_from)
+    }
+}
+
+/// An application read from an archive, with every blob checked against its
+/// digest.
+struct ArchivedApp {
+    manifest_bytes: Vec<u8>,
+    manifest_digest: String,
+    manifest: OciImageManifest,
+    config: Vec<u8>,
+    layers: Vec<(OciDescriptor, Vec<u8>)>,
+    saved_from: Option<String>,
+}
+
+impl ArchivedApp {
+    fn read(archive: &Path) -> Result<Self> {
+        let entries = read_archive(archive)?;
+        let index: Index = serde_json::from_slice(
+            entries
+                .get(INDEX_FILE)
+                .with_context(|| format!("{} has no {INDEX_FILE}", archive.display()))?,
+        )
+        .context("cannot parse archive index")?;
+        let descriptor = index
+            .manifests
+            .into_iter()
+            .next()
+            .context("archive index contains no manifest")?;
+        // Layers may share a digest, so blobs are copied out of the archive
+        // rather than taken.
+        let read_blob = |digest: &str| -> Result<Vec<u8>> {
+            let bytes = entries
+                .get(&blob_path(digest)?)
+                .cloned()
+                .with_context(|| format!("archive is missing blob {digest}"))?;
+            if sha256_digest(&bytes) != digest {
+                bail!("blob {digest} in the archive does not match its digest");
+            }
+            Ok(bytes)
+        };
+        let manifest_bytes = read_blob(&descriptor.digest)?;
+        let manifest: OciImageManifest =
+            serde_json::from_slice(&manifest_bytes).context("cannot parse archived manifest")?;
+        let config = read_blob(&manifest.config.digest)?;
+        let layers = manifest
+            .layers
+            .iter()
+            .map(|layer| Ok((layer.clone(), read_blob(&layer.digest)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            manifest_bytes,
+            manifest_digest: descriptor.digest,
+            manifest,
+            config,
+            layers,
+            saved_from: descriptor.annotations.get(REF_NAME_ANNOTATION).cloned(),
+        })
+    }
+}
+
+// Writes an archive of the application with the given index and blobs. Each
+// blob is written once, however many layers share its digest.
+fn write_archive(archive: &Path, index: &Index, blobs: &[(String, Vec<u8>)]) -> Result<()> {
+    let file = std::fs::File::create(archive)
+        .with_context(|| format!("cannot create {}", archive.display()))?;
+    let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+    append(&mut tarball, OCI_LAYOUT_FILE, OCI_LAYOUT.as_bytes())?;
+    append(&mut tarball, INDEX_FILE, &serde_json::to_vec(index)?)?;
+    let mut written = HashSet::new();
+    for (digest, bytes) in blobs {
+        if written.insert(digest) {
+            append(&mut tarball, &blob_path(digest)?, bytes)?;
+        }
+    }
+    tarball
+        .into_inner()?
+        .finish()
+        .with_context(|| format!("cannot write {}", archive.display()))?;
+    Ok(())
+}
+
+fn append<W: std::io::Write>(
+    tarball: &mut tar::Builder<W>,
+    path: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tarball
+        .append_data(&mut header, path, bytes)
+        .with_context(|| format!("cannot add {path} to archive"))
+}
+
+fn read_archive(archive: &Path) -> Result<HashMap<String, Vec<u8>>> {
+    let file = std::fs::File::open(archive)
+        .with_context(|| format!("cannot open {}", archive.display()))?;
+    let mut tarball = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut entries = HashMap::new();
+    for entry in tarball
+        .entries()
+        .with_context(|| format!("cannot read {}", archive.display()))?
+    {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut bytes = vec![];
+        std::io::Read::read_to_end(&mut entry, &mut bytes)?;
+        entries.insert(path, bytes);
+    }
+    Ok(entries)
+}
+
+fn blob_path(digest: &str) -> Result<String> {
+    let (algorithm, hex) = digest
+        .split_once(':')
+        .with_context(|| format!("invalid digest {digest}"))?;
+    Ok(format!("{BLOBS_DIR}/{algorithm}/{hex}"))
+}
+
+fn sha256_digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes an archive without checking its blobs.
+    fn write_unchecked_archive(path: &Path, blobs: &[(&str, &[u8])], index: &Index) {
+        let file = std::fs::File::create(path).unwrap();
+        let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        ));
+        append(
+            &mut tarball,
+            INDEX_FILE,
+            &serde_json::to_vec(index).unwrap(),
+        )
+        .unwrap();
+        for (digest, bytes) in blobs {
+            append(&mut tarball, &blob_path(digest).unwrap(), bytes).unwrap();
+        }
+        tarball.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn archives_are_checked_against_their_digests() {
+        let config = b"{}";
+        let config_digest = sha256_digest(config);
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "config": {
+                "mediaType": "application/vnd.fermyon.spin.application.v1+config",
+                "digest": config_digest,
+                "size": config.len(),
+            },
+            "layers": [],
+        }))
+        .unwrap();
+        let manifest_digest = sha256_digest(&manifest);
+        let index = Index {
+            schema_version: 2,
+            manifests: vec![Descriptor {
+                media_type: OCI_MANIFEST_MEDIA_TYPE.to_owned(),
+                digest: manifest_digest.clone(),
+                size: manifest.len(),
+                annotations: [(
+                    REF_NAME_ANNOTATION.to_owned(),
+                    "ghcr.io/fermyon/app:v1".to_owned(),
+                )]
+                .into_iter()
+                .collect(),
+            }],
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("app.tar.gz");
+        write_unchecked_archive(
+            &archive,
+            &[(&manifest_digest, &manifest), (&config_digest, config)],
+            &index,
+        );
+        let app = ArchivedApp::read(&archive).unwrap();
+        assert_eq!(app.manifest_digest, manifest_digest);
+        assert_eq!(app.saved_from.as_deref(), Some("ghcr.io/fermyon/app:v1"));
+
+        write_unchecked_archive(
+            &archive,
+            &[(&manifest_digest, &manifest), (&config_digest, b"{ }")],
+            &index,
+        );
+        assert!(ArchivedApp::read(&archive).is_err());
+    }
+
+    #[test]
+    fn identical_layers_round_trip() {
+        let config = b"{}".to_vec();
+        let config_digest = sha256_digest(&config);
+        let file = b"hello".to_vec();
+        let file_digest = sha256_digest(&file);
+        let layer = serde_json::json!({
+            "mediaType": "application/vnd.wasm.content.layer.v1+data",
+            "digest": file_digest,
+            "size": file.len(),
+        });
+        let manifest = serde_json::to_vec(&serde_json::json!({
+            "schemaVersion": 2,
+            "config": {
+                "mediaType": "application/vnd.fermyon.spin.application.v1+config",
+                "digest": config_digest,
+                "size": config.len(),
+            },
+            "layers": [layer, layer],
+        }))
+        .unwrap();
+        let manifest_digest = sha256_digest(&manifest);
+        let index = Index {
+            schema_version: 2,
+            manifests: vec![Descriptor {
+                media_type: OCI_MANIFEST_MEDIA_TYPE.to_owned(),
+                digest: manifest_digest.clone(),
+                size: manifest.len(),
+                annotations: HashMap::new(),
+            }],
+        };
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("app.tar.gz");
+        write_archive(
+            &archive,
+            &index,
+            &[
+                (config_digest, config),
+                (file_digest.clone(), file.clone()),
+                (file_digest.clone(), file.clone()),
+                (manifest_digest, manifest),
+            ],
+        )
+        .unwrap();
+        let blob_count = read_archive(&archive)
+            .unwrap()
+            .keys()
+            .filter(|path| path.starts_with(BLOBS_DIR))
+            .count();
+        assert_eq!(blob_count, 3);
+
+        let app = ArchivedApp::read(&archive).unwrap();
+        assert_eq!(app.layers.len(), 2);
+        for (layer, bytes) in &app.layers {
+            assert_eq!(layer.digest, file_digest);
+            assert_eq!(bytes, &file);
+        }
+    }
+
+    #[test]
+    fn blobs_are_stored_by_digest() {
+        assert_eq!("blobs/sha256/abc123", blob_path("sha256:abc123").unwrap());
+        assert!(blob_path("abc123").is_err());
+        assert_eq!(
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            sha256_digest(b"")
+        );
+    }
+}
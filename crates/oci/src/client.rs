@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, Context, Result};
 use docker_credential::DockerCredential;
@@ -6,22 +9,34 @@ use futures_util::future;
 use futures_util::stream::{self, StreamExt, TryStreamExt};
 use oci_distribution::{
     client::{Config, ImageLayer},
-    manifest::OciImageManifest,
+    manifest::{
+        OciImageManifest, OciManifest, IMAGE_MANIFEST_LIST_MEDIA_TYPE, IMAGE_MANIFEST_MEDIA_TYPE,
+        OCI_IMAGE_INDEX_MEDIA_TYPE, OCI_IMAGE_MEDIA_TYPE,
+    },
     secrets::RegistryAuth,
     Reference,
 };
 use reqwest::Url;
 use spin_app::locked::{ContentPath, ContentRef};
 use spin_loader::cache::Cache;
-use spin_manifest::Application;
+use spin_manifest::{Application, ApplicationInformation};
+use spin_trigger::RuntimeConfig;
 use tokio::fs;
 use walkdir::WalkDir;
 
-use crate::auth::AuthConfig;
+use crate::{
+    auth::AuthConfig,
+    credential_helper, dependency,
+    mirror::Mirrors,
+    qualify_reference,
+    signing::TrustPolicy,
+    upload::Uploader,
+    variant::{self, Variant},
+};
 
 // TODO: the media types for application, wasm module, and data layer are not final.
 const SPIN_APPLICATION_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.application.v1+config";
-const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
+pub(crate) const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
 const DATA_MEDIATYPE: &str = "application/vnd.wasm.content.layer.v1+data";
 
 const CONFIG_FILE: &str = "config.json";
@@ -30,11 +45,30 @@ const MANIFEST_FILE: &str = "manifest.json";
 
 const MAX_PARALLEL_PULL: usize = 16;
 
+const MANIFEST_MEDIA_TYPES: &[&str] = &[
+    OCI_IMAGE_MEDIA_TYPE,
+    IMAGE_MANIFEST_MEDIA_TYPE,
+    OCI_IMAGE_INDEX_MEDIA_TYPE,
+    IMAGE_MANIFEST_LIST_MEDIA_TYPE,
+];
+
+/// The annotation recording when an artifact was created, as an RFC 3339 timestamp.
+pub const ANNOTATION_CREATED: &str = "org.opencontainers.image.created";
+/// The annotation recording who is responsible for an artifact.
+pub const ANNOTATION_AUTHORS: &str = "org.opencontainers.image.authors";
+const ANNOTATION_TITLE: &str = "org.opencontainers.image.title";
+const ANNOTATION_VERSION: &str = "org.opencontainers.image.version";
+const ANNOTATION_DESCRIPTION: &str = "org.opencontainers.image.description";
+
 /// Client for interacting with an OCI registry for Spin applications.
 pub struct Client {
     /// Global cache for the metadata, Wasm modules, and static assets pulled from OCI registries.
     pub cache: Cache,
-    oci: oci_distribution::Client,
+    pub(crate) oci: oci_distribution::Client,
+    pub(crate) trust_policy: Option<TrustPolicy>,
+    variant: Option<Variant>,
+    pub(crate) mirrors: Mirrors,
+    pub(crate) insecure: bool,
 }
 
 impl Client {
@@ -43,21 +77,149 @@ impl Client {
         let client = oci_distribution::Client::new(Self::build_config(insecure));
         let cache = Cache::new(cache_root).await?;
 
-        Ok(Self { oci: client, cache })
+        Ok(Self {
+            oci: client,
+            cache,
+            trust_policy: None,
+            variant: None,
+            mirrors: Mirrors::default(),
+            insecure,
+        })
+    }
+
+    /// Create a client which pulls applications as the registry pull and
+    /// trust settings of the runtime config direct. The given mirrors are
+    /// tried before those in the runtime config.
+    pub async fn from_runtime_config(
+        insecure: bool,
+        runtime_config: Option<&RuntimeConfig>,
+        mut mirrors: Mirrors,
+    ) -> Result<Self> {
+        let pull = match runtime_config {
+            Some(config) => config.registry_pull()?.unwrap_or_default(),
+            None => Default::default(),
+        };
+        let mut client = Self::new(insecure, pull.cache_dir).await?;
+        for (registry, registry_mirrors) in &pull.mirrors {
+            for mirror in registry_mirrors {
+                mirrors.add(registry, mirror);
+            }
+        }
+        client.set_mirrors(mirrors);
+        let trust = match runtime_config {
+            Some(config) => config.registry_trust()?,
+            None => None,
+        };
+        if let Some(trust) = trust {
+            let mut policy = TrustPolicy::load(&trust.trusted_keys, trust.require_signatures)?;
+            for (issuer, key_files) in &trust.issuer_keys {
+                policy = policy.load_issuer_keys(issuer, key_files)?;
+            }
+            client.set_trust_policy(
+                policy
+                    .with_required_issuers(trust.required_issuers)
+                    .with_allowed_registries(trust.allowed_registries)
+                    .with_require_digest(trust.require_digest)
+                    .with_deny_latest(trust.deny_latest),
+            );
+        }
+        Ok(client)
+    }
+
+    /// Check the signatures of applications pulled by this client against
+    /// the given policy.
+    pub fn set_trust_policy(&mut self, policy: TrustPolicy) {
+        self.trust_policy = Some(policy);
+    }
+
+    /// Pull the given variant of applications published as an index of
+    /// variants, rather than the variant for the host platform.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = Some(variant);
+    }
+
+    /// Pull applications from the given mirrors of their registries, falling
+    /// back to the registries themselves.
+    pub fn set_mirrors(&mut self, mirrors: Mirrors) {
+        self.mirrors = mirrors;
+    }
+
+    pub(crate) fn verifies_signatures(&self) -> bool {
+        self.trust_policy.is_some()
     }
 
     /// Push a Spin application to an OCI registry and return the digest (or None
     /// if the digest cannot be determined).
+    ///
+    /// The manifest is annotated with the application's name, version,
+    /// description and authors, and the time of the push. The given
+    /// annotations are added to these, replacing any with the same key.
     pub async fn push(
         &mut self,
         app: &Application,
         reference: impl AsRef<str>,
+        annotations: HashMap<String, String>,
     ) -> Result<Option<String>> {
-        let reference: Reference = reference
-            .as_ref()
+        let reference: Reference = qualify_reference(reference.as_ref())
             .parse()
             .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
         let auth = Self::auth(&reference).await?;
+        let mut uploader = Uploader::new(&reference, auth, self.insecure, &self.cache)?;
+        let manifest = Self::upload_app(&mut uploader, app, annotations).await?;
+        let response = uploader
+            .push_manifest(&reference, &manifest)
+            .await
+            .context("cannot push Spin application")?;
+
+        tracing::info!("Pushed {:?}", response);
+
+        let digest = digest_from_url(&response);
+        Ok(digest)
+    }
+
+    /// Push several variants of a Spin application to an OCI registry as an
+    /// image index, from which `pull` selects one, and return the digest of
+    /// the index (or None if the digest cannot be determined). The manifest
+    /// of each variant is annotated as for [`Client::push`].
+    pub async fn push_variants(
+        &mut self,
+        variants: &[(Variant, Application)],
+        reference: impl AsRef<str>,
+        annotations: HashMap<String, String>,
+    ) -> Result<Option<String>> {
+        let reference: Reference = qualify_reference(reference.as_ref())
+            .parse()
+            .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
+        let auth = Self::auth(&reference).await?;
+        let mut uploader = Uploader::new(&reference, auth, self.insecure, &self.cache)?;
+
+        let mut entries = Vec::new();
+        for (variant, app) in variants {
+            let manifest = Self::upload_app(&mut uploader, app, annotations.clone()).await?;
+            let entry = uploader
+                .push_variant_manifest(&manifest, variant)
+                .await
+                .with_context(|| format!("cannot push variant {variant}"))?;
+            entries.push(entry);
+        }
+        let response = uploader
+            .push_index(&reference, entries)
+            .await
+            .context("cannot push Spin application index")?;
+
+        tracing::info!("Pushed {:?}", response);
+
+        let digest = digest_from_url(&response);
+        Ok(digest)
+    }
+
+    // Uploads the layers and config of an application, returning the
+    // manifest which refers to them.
+    async fn upload_app(
+        uploader: &mut Uploader,
+        app: &Application,
+        annotations: HashMap<String, String>,
+    ) -> Result<OciImageManifest> {
         let working_dir = tempfile::tempdir()?;
 
         // Create a locked application from the application manifest.
@@ -92,11 +254,28 @@ impl Client {
                 digest: Some(digest.clone()),
             };
 
+            // Add a layer for each component file on which the component
+            // depends, and refer to it by digest. Dependencies in registries
+            // are pulled by whoever runs the application.
+            let mut dependency_digests = HashMap::new();
+            for path in dependency::dependency_files(&c)? {
+                let layer = Self::wasm_layer(&path).await?;
+                dependency_digests.insert(path, layer.sha256_digest());
+                layers.push(layer);
+            }
+            dependency::refer_to_pushed_dependencies(&mut c, &dependency_digests)?;
+
             // Add a layer for each file referenced in the mount directory.
             // Note that this is in fact a directory, and not a single file, so we need to
             // recursively traverse it and add layers for each file.
             let mut files = Vec::new();
             for f in c.files {
+                if f.writable {
+                    bail!(
+                        "Component {:?} has writable file mounts, which can't be pushed",
+                        c.id
+                    );
+                }
                 let source = f
                     .content
                     .source
@@ -122,6 +301,7 @@ impl Client {
                                 digest: Some(digest.clone()),
                             },
                             path: PathBuf::from(spin_loader::to_relative(entry.path(), &source)?),
+                            writable: false,
                         });
                     }
                 }
@@ -137,67 +317,112 @@ impl Client {
             media_type: SPIN_APPLICATION_MEDIA_TYPE.to_string(),
             annotations: None,
         };
-        let manifest = OciImageManifest::build(&layers, &oci_config, None);
-        let response = self
-            .oci
-            .push(&reference, &layers, oci_config, &auth, Some(manifest))
-            .await
-            .map(|push_response| push_response.manifest_url)
-            .context("cannot push Spin application")?;
-
-        tracing::info!("Pushed {:?}", response);
-
-        let digest = digest_from_url(&response);
-        Ok(digest)
+        let mut manifest_annotations = app_annotations(&app.info);
+        manifest_annotations.extend(annotations);
+        let manifest = OciImageManifest::build(&layers, &oci_config, Some(manifest_annotations));
+        for layer in &layers {
+            uploader
+                .push_blob(&layer.data, &layer.sha256_digest())
+                .await?;
+        }
+        uploader
+            .push_blob(&oci_config.data, &manifest.config.digest)
+            .await?;
+        Ok(manifest)
     }
 
-    /// Pull a Spin application from an OCI registry.
+    /// Pull a Spin application from an OCI registry, or from a mirror of
+    /// the registry if one is configured.
     pub async fn pull(&mut self, reference: &str) -> Result<()> {
-        let reference: Reference = reference.parse().context("cannot parse reference")?;
-        let auth = Self::auth(&reference).await?;
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .context("cannot parse reference")?;
+        if let Some(policy) = &self.trust_policy {
+            policy.check_reference(&reference)?;
+        }
+        for mirrored in self.mirrors.references_for(&reference)? {
+            match self.pull_from(&mirrored, &reference).await {
+                Ok(()) => return Ok(()),
+                Err(e) => tracing::warn!("Cannot pull {reference} from mirror {mirrored}: {e:#}"),
+            }
+        }
+        self.pull_from(&reference, &reference).await
+    }
 
-        // Pull the manifest from the registry.
-        let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
+    // Pulls the application from `source`, which is the reference or a
+    // mirror of it, and caches it under the reference.
+    async fn pull_from(&mut self, source: &Reference, reference: &Reference) -> Result<()> {
+        let auth = Self::auth(source).await?;
+
+        // Pull the manifest from the registry. If the reference is to an
+        // index of variants, the signature is of the index, and the
+        // manifest of the selected variant is pulled from it.
+        let (manifest, digest) = match self.pull_verified_manifest(source, &auth).await? {
+            (OciManifest::Image(manifest), digest) => {
+                if let Some(policy) = self.trust_policy.clone() {
+                    self.verify(source, reference, &digest, &policy).await?;
+                }
+                (manifest, digest)
+            }
+            (OciManifest::ImageIndex(index), digest) => {
+                if let Some(policy) = self.trust_policy.clone() {
+                    self.verify(source, reference, &digest, &policy).await?;
+                }
+                let entry = variant::select(&index, self.variant.as_ref())?;
+                let variant_reference: Reference = format!(
+                    "{}/{}@{}",
+                    source.registry(),
+                    source.repository(),
+                    entry.digest
+                )
+                .parse()
+                .context("cannot construct variant reference")?;
+                tracing::debug!("Pulling variant {}", variant_reference);
+                // The index is what was verified, so the variant must be the
+                // manifest it names: pulling by digest checks that it is.
+                self.pull_verified_image_manifest(&variant_reference, &auth)
+                    .await?
+            }
+        };
 
         let manifest_json = serde_json::to_string(&manifest)?;
         tracing::debug!("Pulled manifest: {}", manifest_json);
 
-        // Write the manifest in `<cache_root>/registry/oci/manifests/repository:<tag_or_latest>/manifest.json`
-        let m = self.manifest_path(&reference.to_string()).await?;
-        fs::write(&m, &manifest_json).await?;
-
         let mut cfg_bytes = Vec::new();
         self.oci
-            .pull_blob(&reference, &manifest.config.digest, &mut cfg_bytes)
+            .pull_blob(source, &manifest.config.digest, &mut cfg_bytes)
             .await?;
+        if !digest_matches(&cfg_bytes, &manifest.config.digest) {
+            bail!(
+                "Config {} does not match its digest: the application may have been tampered with",
+                manifest.config.digest
+            );
+        }
         let cfg = std::str::from_utf8(&cfg_bytes)?;
         tracing::debug!("Pulled config: {}", cfg);
 
-        // Write the config object in `<cache_root>/registry/oci/manifests/repository:<tag_or_latest>/config.json`
-        let c = self.lockfile_path(&reference.to_string()).await?;
-        fs::write(&c, &cfg).await?;
-
         // If a layer is a Wasm module, write it in the Wasm directory.
         // Otherwise, write it in the data directory.
         stream::iter(manifest.layers)
             .map(|layer| {
                 let this = &self;
-                let reference = reference.clone();
+                let source = source.clone();
                 async move {
                     // Skip pulling if the digest already exists in the wasm or data directories.
-                    if this.cache.wasm_file(&layer.digest).is_ok()
-                        || this.cache.data_file(&layer.digest).is_ok()
-                    {
+                    if this.cached_layer(&layer.digest).await.is_some() {
                         tracing::debug!("Layer {} already exists in cache", &layer.digest);
                     } else {
                         tracing::debug!("Pulling layer {}", &layer.digest);
                         let mut bytes = Vec::new();
                         match this
                             .oci
-                            .pull_blob(&reference, &layer.digest, &mut bytes)
+                            .pull_blob(&source, &layer.digest, &mut bytes)
                             .await
                         {
-                            Err(e) => return Err(e),
+                            Err(e) => return Err(e.into()),
+                            _ if !digest_matches(&bytes, &layer.digest) => {
+                                bail!("Layer {} does not match its digest: the application may have been tampered with", layer.digest);
+                            }
                             _ => match layer.media_type.as_str() {
                                 WASM_LAYER_MEDIA_TYPE => {
                                     let _ = this.cache.write_wasm(&bytes, &layer.digest).await;
@@ -214,11 +439,74 @@ impl Client {
             .buffer_unordered(MAX_PARALLEL_PULL)
             .try_for_each(future::ok)
             .await?;
-        tracing::info!("Pulled {}@{}", reference, digest);
+
+        // The manifest and config are only written once every layer is in
+        // the cache, so that a cached config always means a complete pull.
+        // Write the manifest in `<cache_root>/registry/oci/manifests/repository:<tag_or_latest>/manifest.json`
+        let m = self.manifest_path(&reference.to_string()).await?;
+        write_atomically(&m, manifest_json.as_bytes()).await?;
+        // Write the config object in `<cache_root>/registry/oci/manifests/repository:<tag_or_latest>/config.json`
+        let c = self.lockfile_path(&reference.to_string()).await?;
+        write_atomically(&c, &cfg_bytes).await?;
+        tracing::info!("Pulled {}@{}", source, digest);
 
         Ok(())
     }
 
+    /// Pull a manifest, with its digest computed from the manifest itself
+    /// rather than taken from the registry. If the reference names a digest,
+    /// the manifest must have that digest.
+    pub(crate) async fn pull_verified_manifest(
+        &mut self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<(OciManifest, String)> {
+        let (bytes, _) = self
+            .oci
+            .pull_manifest_raw(reference, auth, MANIFEST_MEDIA_TYPES)
+            .await?;
+        let digest = sha256_digest(&bytes);
+        if let Some(expected) = reference.digest() {
+            if expected != digest {
+                bail!("{reference} has digest {digest}: the manifest may have been tampered with");
+            }
+        }
+        let manifest = serde_json::from_slice(&bytes)
+            .with_context(|| format!("cannot parse manifest of {reference}"))?;
+        Ok((manifest, digest))
+    }
+
+    /// Pull an image manifest as [`Client::pull_verified_manifest`] does.
+    pub(crate) async fn pull_verified_image_manifest(
+        &mut self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<(OciImageManifest, String)> {
+        match self.pull_verified_manifest(reference, auth).await? {
+            (OciManifest::Image(manifest), digest) => Ok((manifest, digest)),
+            (OciManifest::ImageIndex(_), _) => {
+                bail!("{reference} is an image index, not an image manifest")
+            }
+        }
+    }
+
+    /// The path of a cached layer, if it is cached and still matches its
+    /// digest. A layer which has been altered in the cache is pulled again.
+    pub(crate) async fn cached_layer(&self, digest: &str) -> Option<PathBuf> {
+        let path = self
+            .cache
+            .wasm_file(digest)
+            .or_else(|_| self.cache.data_file(digest))
+            .ok()?;
+        let bytes = fs::read(&path).await.ok()?;
+        if digest_matches(&bytes, digest) {
+            Some(path)
+        } else {
+            tracing::warn!("Cached layer {digest} does not match its digest; pulling it again");
+            None
+        }
+    }
+
     /// Create a new wasm layer based on a file.
     pub async fn wasm_layer(file: &Path) -> Result<ImageLayer> {
         tracing::log::trace!("Reading wasm module from {:?}", file);
@@ -231,9 +519,8 @@ impl Client {
 
     /// Get the file path to an OCI manifest given a reference.
     /// If the directory for the manifest does not exist, this will create it.
-    async fn manifest_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
-        let reference: Reference = reference
-            .as_ref()
+    pub(crate) async fn manifest_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
+        let reference: Reference = qualify_reference(reference.as_ref())
             .parse()
             .context("cannot parse OCI reference")?;
         let p = self
@@ -254,8 +541,7 @@ impl Client {
 
     /// Get the file path to the OCI configuration object given a reference.
     pub async fn lockfile_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
-        let reference: Reference = reference
-            .as_ref()
+        let reference: Reference = qualify_reference(reference.as_ref())
             .parse()
             .context("cannot parse reference")?;
         let p = self
@@ -308,6 +594,28 @@ impl Client {
         auth.save_default().await
     }
 
+    /// Check that each credential set saved by [`Client::login`] is still
+    /// accepted by its registry, returning the result for each registry in
+    /// name order.
+    pub async fn check_saved_logins() -> Result<Vec<(String, Result<()>)>> {
+        let auth = AuthConfig::load_default().await?;
+        let mut servers = auth.auths.keys().cloned().collect::<Vec<_>>();
+        servers.sort();
+
+        let mut results = vec![];
+        for server in servers {
+            let result = match AuthConfig::get_auth_from_default(&server).await {
+                Ok(RegistryAuth::Basic(username, password)) => {
+                    Self::validate_credentials(&server, &username, &password).await
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            };
+            results.push((server, result));
+        }
+        Ok(results)
+    }
+
     /// Validate the credentials by attempting to send an authenticated request to the registry.
     async fn validate_credentials(
         server: impl AsRef<str>,
@@ -339,29 +647,37 @@ impl Client {
     }
 
     /// Construct the registry authentication based on the reference.
-    async fn auth(reference: &Reference) -> Result<RegistryAuth> {
+    pub(crate) async fn auth(reference: &Reference) -> Result<RegistryAuth> {
         let server = reference
             .resolve_registry()
             .strip_suffix('/')
             .unwrap_or_else(|| reference.resolve_registry());
 
-        match AuthConfig::get_auth_from_default(server).await {
-            Ok(c) => Ok(c),
-            Err(_) => match docker_credential::get_credential(server) {
-                Err(e) => {
-                    tracing::trace!("Cannot retrieve credentials from Docker, attempting to use anonymous auth: {}", e);
-                    Ok(RegistryAuth::Anonymous)
-                }
+        if let Ok(c) = AuthConfig::get_auth_from_default(server).await {
+            return Ok(c);
+        }
+        match credential_helper::get_auth(server) {
+            Ok(Some(c)) => return Ok(c),
+            Ok(None) => (),
+            Err(e) => tracing::warn!("Cannot get credentials for {server}: {e:#}"),
+        }
+        match docker_credential::get_credential(server) {
+            Err(e) => {
+                tracing::trace!(
+                    "Cannot retrieve credentials from Docker, attempting to use anonymous auth: {}",
+                    e
+                );
+                Ok(RegistryAuth::Anonymous)
+            }
 
-                Ok(DockerCredential::UsernamePassword(username, password)) => {
-                    tracing::trace!("Found Docker credentials");
-                    Ok(RegistryAuth::Basic(username, password))
-                }
-                Ok(DockerCredential::IdentityToken(_)) => {
-                    tracing::trace!("Cannot use contents of Docker config, identity token not supported. Using anonymous auth");
-                    Ok(RegistryAuth::Anonymous)
-                }
-            },
+            Ok(DockerCredential::UsernamePassword(username, password)) => {
+                tracing::trace!("Found Docker credentials");
+                Ok(RegistryAuth::Basic(username, password))
+            }
+            Ok(DockerCredential::IdentityToken(_)) => {
+                tracing::trace!("Cannot use contents of Docker config, identity token not supported. Using anonymous auth");
+                Ok(RegistryAuth::Anonymous)
+            }
         }
     }
 
@@ -380,6 +696,47 @@ impl Client {
     }
 }
 
+// The standard OCI annotations which can be derived from the application
+// manifest.
+fn app_annotations(info: &ApplicationInformation) -> HashMap<String, String> {
+    let mut annotations = HashMap::new();
+    annotations.insert(ANNOTATION_TITLE.to_owned(), info.name.clone());
+    annotations.insert(ANNOTATION_VERSION.to_owned(), info.version.clone());
+    if let Some(description) = &info.description {
+        annotations.insert(ANNOTATION_DESCRIPTION.to_owned(), description.clone());
+    }
+    if !info.authors.is_empty() {
+        annotations.insert(ANNOTATION_AUTHORS.to_owned(), info.authors.join(", "));
+    }
+    annotations.insert(
+        ANNOTATION_CREATED.to_owned(),
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    );
+    annotations
+}
+
+pub(crate) fn digest_matches(bytes: &[u8], digest: &str) -> bool {
+    sha256_digest(bytes) == digest
+}
+
+pub(crate) fn sha256_digest(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+/// Write a file in the cache such that readers see either the old contents
+/// or the new, never a partly written file.
+pub(crate) async fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .context("cache file has no parent directory")?;
+    let temp = tempfile::NamedTempFile::new_in(dir)?.into_temp_path();
+    fs::write(&temp, bytes).await?;
+    temp.persist(path)
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    Ok(())
+}
+
 fn digest_from_url(manifest_url: &str) -> Option<String> {
     // The URL is in the form "https://host/v2/refname/manifests/sha256:..."
     let manifest_url = Url::parse(manifest_url).ok()?;
@@ -396,6 +753,14 @@ fn digest_from_url(manifest_url: &str) -> Option<String> {
 mod test {
     use super::*;
 
+    #[test]
+    fn digests_are_computed_from_content() {
+        let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(digest, sha256_digest(b"hello"));
+        assert!(digest_matches(b"hello", digest));
+        assert!(!digest_matches(b"hellO", digest));
+    }
+
     #[test]
     fn can_parse_digest_from_manifest_url() {
         let manifest_url = "https://ghcr.io/v2/itowlson/osf/manifests/sha256:0a867093096e0ef01ef749b12b6e7a90e4952eda107f89a676eeedce63a8361f";
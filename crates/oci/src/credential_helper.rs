@@ -0,0 +1,202 @@
+//! Registry credentials from Docker credential helpers.
+//!
+//! The Docker config file (`$DOCKER_CONFIG/config.json`, or
+//! `~/.docker/config.json`) may name a helper program for each registry in
+//! `credHelpers`, and one for all other registries in `credsStore`. Registries
+//! of cloud providers whose helpers are commonly installed without being
+//! configured, such as ECR and Artifact Registry, use those helpers if they
+//! are on the path.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::secrets::RegistryAuth;
+use serde::Deserialize;
+
+const DOCKER_CONFIG_ENV: &str = "DOCKER_CONFIG";
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+// Helpers return this user name for identity tokens, which can't be used as
+// basic credentials.
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfig {
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+    creds_store: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredential {
+    username: String,
+    secret: String,
+}
+
+/// Get credentials for the registry from a credential helper, if one is
+/// configured or known for it. Returns `None` if there is no helper for the
+/// registry or the helper has no credentials for it.
+pub(crate) fn get_auth(server: &str) -> Result<Option<RegistryAuth>> {
+    let config = load_docker_config()?;
+    let Some(helper) = helper_for(&config, server) else {
+        return Ok(None);
+    };
+    for candidate in server_names(server) {
+        if let Some(credential) = run_helper(&helper, candidate)? {
+            if credential.username == IDENTITY_TOKEN_USERNAME {
+                tracing::trace!(
+                    "Credential helper {helper} returned an identity token, which is not supported"
+                );
+                return Ok(None);
+            }
+            tracing::trace!("Found credentials for {server} from credential helper {helper}");
+            return Ok(Some(RegistryAuth::Basic(
+                credential.username,
+                credential.secret,
+            )));
+        }
+    }
+    Ok(None)
+}
+
+fn load_docker_config() -> Result<DockerConfig> {
+    let dir = match std::env::var_os(DOCKER_CONFIG_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => match dirs::home_dir() {
+            Some(home) => home.join(".docker"),
+            None => return Ok(DockerConfig::default()),
+        },
+    };
+    let path = dir.join("config.json");
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .with_context(|| format!("cannot parse Docker config {}", path.display())),
+        Err(_) => Ok(DockerConfig::default()),
+    }
+}
+
+fn helper_for(config: &DockerConfig, server: &str) -> Option<String> {
+    server_names(server)
+        .into_iter()
+        .find_map(|name| config.cred_helpers.get(name).cloned())
+        .or_else(|| cloud_helper(server).filter(|helper| helper_installed(helper)))
+        .or_else(|| config.creds_store.clone())
+}
+
+// The helpers of cloud registries, which are often installed by the cloud's
+// tooling without being added to the Docker config.
+fn cloud_helper(server: &str) -> Option<String> {
+    let host = server.split(':').next().unwrap_or(server);
+    if host.contains(".dkr.ecr.") && host.ends_with(".amazonaws.com") {
+        Some("ecr-login".to_owned())
+    } else if host.ends_with("-docker.pkg.dev") || host == "gcr.io" || host.ends_with(".gcr.io") {
+        Some("gcloud".to_owned())
+    } else {
+        None
+    }
+}
+
+// Docker records Docker Hub credentials under its v1 index URL.
+fn server_names(server: &str) -> Vec<&str> {
+    if server == "index.docker.io" || server == "docker.io" || server == "registry-1.docker.io" {
+        vec![server, DOCKER_HUB_SERVER]
+    } else {
+        vec![server]
+    }
+}
+
+fn helper_program(helper: &str) -> String {
+    format!("docker-credential-{helper}")
+}
+
+fn helper_installed(helper: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    let program = helper_program(helper);
+    std::env::split_paths(&path).any(|dir| {
+        dir.join(&program).is_file()
+            || (cfg!(windows) && dir.join(&program).with_extension("exe").is_file())
+    })
+}
+
+// Runs `docker-credential-<helper> get`, which reads the server from stdin
+// and writes the credentials as JSON. Helpers fail with "credentials not
+// found" if they have none for the server.
+fn run_helper(helper: &str, server: &str) -> Result<Option<HelperCredential>> {
+    let program = helper_program(helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("cannot run credential helper {program}"))?;
+    child
+        .stdin
+        .take()
+        .context("cannot write to credential helper")?
+        .write_all(server.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stdout);
+        if message.contains("credentials not found") {
+            return Ok(None);
+        }
+        bail!(
+            "credential helper {program} failed: {}{}",
+            message.trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let credential = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("cannot parse output of credential helper {program}"))?;
+    Ok(Some(credential))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chooses_helpers() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{
+                "auths": { "ghcr.io": {} },
+                "credHelpers": { "ghcr.io": "gh", "https://index.docker.io/v1/": "desktop" },
+                "credsStore": "osxkeychain"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(Some("gh".to_owned()), helper_for(&config, "ghcr.io"));
+        assert_eq!(
+            Some("desktop".to_owned()),
+            helper_for(&config, "index.docker.io")
+        );
+        assert_eq!(
+            Some("osxkeychain".to_owned()),
+            helper_for(&config, "registry.example.com")
+        );
+        assert_eq!(None, helper_for(&DockerConfig::default(), "ghcr.io"));
+    }
+
+    #[test]
+    fn knows_cloud_registries() {
+        assert_eq!(
+            Some("ecr-login".to_owned()),
+            cloud_helper("123456789012.dkr.ecr.us-east-1.amazonaws.com")
+        );
+        assert_eq!(
+            Some("gcloud".to_owned()),
+            cloud_helper("us-central1-docker.pkg.dev")
+        );
+        assert_eq!(Some("gcloud".to_owned()), cloud_helper("eu.gcr.io"));
+        assert_eq!(None, cloud_helper("ghcr.io"));
+    }
+}
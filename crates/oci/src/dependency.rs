@@ -0,0 +1,383 @@
+//! Library components on which application components depend.
+//!
+//! Dependencies are composed with the components which declare them when
+//! the application is loaded. Dependencies in a registry are pulled into the
+//! cache before then. A published dependency is an artifact whose manifest
+//! has a single Wasm layer, as pushed by tools such as `oras` and `wkg`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use oci_distribution::Reference;
+use reqwest::Url;
+use spin_app::locked::{LockedApp, LockedComponent};
+use spin_loader::cache::Cache;
+use spin_trigger::locked::{LockedDependency, DEPENDENCIES_KEY};
+
+use crate::{
+    client::{digest_matches, WASM_LAYER_MEDIA_TYPE},
+    qualify_reference, Client,
+};
+
+// Media types under which the Wasm layer of a component may be published.
+const COMPONENT_MEDIA_TYPES: &[&str] = &[WASM_LAYER_MEDIA_TYPE, "application/wasm"];
+
+/// Whether any component of the application depends on a component in a
+/// registry.
+pub fn has_registry_dependencies(locked: &LockedApp) -> Result<bool> {
+    for component in &locked.components {
+        if let Some(dependencies) = dependencies(component)? {
+            if dependencies
+                .values()
+                .any(|d| matches!(d, LockedDependency::Registry(_)))
+            {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+impl Client {
+    /// Pull the components in registries on which the application's
+    /// components depend into the cache, and refer to them there.
+    pub async fn resolve_dependencies(&mut self, locked: &mut LockedApp) -> Result<()> {
+        for component in &mut locked.components {
+            let Some(mut dependencies) = dependencies(component)? else {
+                continue;
+            };
+            for (import, dependency) in dependencies.iter_mut() {
+                let LockedDependency::Registry(reference) = dependency else {
+                    continue;
+                };
+                let path = self.pull_component(reference).await.with_context(|| {
+                    format!(
+                        "cannot pull dependency {import:?} of component {:?}",
+                        component.id
+                    )
+                })?;
+                let url = Url::from_file_path(&path)
+                    .map_err(|_| anyhow!("cannot construct file URL for {path:?}"))?;
+                *dependency = LockedDependency::Source(url.to_string());
+            }
+            set_dependencies(component, &dependencies)?;
+        }
+        Ok(())
+    }
+
+    /// Pull a component published to a registry into the cache, from a
+    /// mirror of the registry if one is configured, returning its path.
+    pub async fn pull_component(&mut self, reference: &str) -> Result<PathBuf> {
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        if let Some(policy) = &self.trust_policy {
+            policy.check_reference(&reference)?;
+        }
+        for mirrored in self.mirrors.references_for(&reference)? {
+            match self.pull_component_from(&mirrored, &reference).await {
+                Ok(path) => return Ok(path),
+                Err(e) => tracing::warn!("Cannot pull {reference} from mirror {mirrored}: {e:#}"),
+            }
+        }
+        self.pull_component_from(&reference, &reference).await
+    }
+
+    // Pulls the component from `source`, which is the reference or a mirror
+    // of it.
+    async fn pull_component_from(
+        &mut self,
+        source: &Reference,
+        reference: &Reference,
+    ) -> Result<PathBuf> {
+        let auth = Self::auth(source).await?;
+        let (manifest, digest) = self.pull_verified_image_manifest(source, &auth).await?;
+        if let Some(policy) = self.trust_policy.clone() {
+            self.verify(source, reference, &digest, &policy).await?;
+        }
+
+        let layers = manifest
+            .layers
+            .iter()
+            .filter(|layer| COMPONENT_MEDIA_TYPES.contains(&layer.media_type.as_str()))
+            .collect::<Vec<_>>();
+        let [layer] = layers.as_slice() else {
+            bail!(
+                "{source} is not a component: expected one Wasm layer, found {}",
+                layers.len()
+            );
+        };
+        if let Some(path) = self.cached_layer(&layer.digest).await {
+            tracing::debug!("Component {} already exists in cache", layer.digest);
+            return Ok(path);
+        }
+
+        let mut bytes = vec![];
+        self.oci
+            .pull_blob(source, &layer.digest, &mut bytes)
+            .await
+            .with_context(|| format!("cannot pull layer {}", layer.digest))?;
+        if !digest_matches(&bytes, &layer.digest) {
+            bail!("layer {} does not match its digest", layer.digest);
+        }
+        self.cache.write_wasm(&bytes, &layer.digest).await?;
+        tracing::info!("Pulled {source}@{digest}");
+        self.cache.wasm_file(&layer.digest)
+    }
+}
+
+/// The dependencies of a locked component, if it has any.
+pub(crate) fn dependencies(
+    component: &LockedComponent,
+) -> Result<Option<HashMap<String, LockedDependency>>> {
+    component
+        .metadata
+        .get(DEPENDENCIES_KEY.as_ref())
+        .map(|value| serde_json::from_value(value.clone()))
+        .transpose()
+        .with_context(|| format!("invalid dependencies of component {:?}", component.id))
+}
+
+/// Replaces the dependencies of a locked component.
+pub(crate) fn set_dependencies(
+    component: &mut LockedComponent,
+    dependencies: &HashMap<String, LockedDependency>,
+) -> Result<()> {
+    component.metadata.insert(
+        DEPENDENCIES_KEY.as_ref().to_owned(),
+        serde_json::to_value(dependencies)?,
+    );
+    Ok(())
+}
+
+/// The component files on which a locked component depends, which are
+/// pushed with the application. Dependencies in registries are pulled by
+/// whoever runs the application.
+pub(crate) fn dependency_files(component: &LockedComponent) -> Result<Vec<PathBuf>> {
+    let Some(dependencies) = dependencies(component)? else {
+        return Ok(vec![]);
+    };
+    dependencies
+        .values()
+        .filter_map(|dependency| match dependency {
+            LockedDependency::Source(source) => Some(spin_trigger::parse_file_url(source)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Refers to each component file on which a locked component depends by the
+/// digest of the layer it was pushed as, from `digests`, keyed by file path.
+pub(crate) fn refer_to_pushed_dependencies(
+    component: &mut LockedComponent,
+    digests: &HashMap<PathBuf, String>,
+) -> Result<()> {
+    rewrite_dependencies(component, |dependency| {
+        let LockedDependency::Source(source) = dependency else {
+            return Ok(None);
+        };
+        let path = spin_trigger::parse_file_url(source)?;
+        let digest = digests
+            .get(&path)
+            .with_context(|| format!("dependency {path:?} was not pushed"))?;
+        Ok(Some(LockedDependency::Digest(digest.clone())))
+    })
+}
+
+/// Refers to each dependency which was pushed with the application by the
+/// path of its content in the cache, once the application is pulled.
+pub(crate) fn refer_to_pulled_dependencies(
+    component: &mut LockedComponent,
+    cache: &Cache,
+) -> Result<()> {
+    rewrite_dependencies(component, |dependency| {
+        let LockedDependency::Digest(digest) = dependency else {
+            return Ok(None);
+        };
+        let path = cache.wasm_file(digest)?;
+        let url = Url::from_file_path(&path)
+            .map_err(|_| anyhow!("cannot construct file URL for {path:?}"))?;
+        Ok(Some(LockedDependency::Source(url.to_string())))
+    })
+}
+
+// Replaces each dependency of the component for which `f` returns another.
+fn rewrite_dependencies(
+    component: &mut LockedComponent,
+    mut f: impl FnMut(&LockedDependency) -> Result<Option<LockedDependency>>,
+) -> Result<()> {
+    let Some(mut dependencies) = dependencies(component)? else {
+        return Ok(());
+    };
+    for dependency in dependencies.values_mut() {
+        if let Some(rewritten) = f(dependency)? {
+            *dependency = rewritten;
+        }
+    }
+    set_dependencies(component, &dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::client::sha256_digest;
+
+    const COMPONENT: &[u8] = b"\0asm\x0d\0\x01\0";
+
+    fn app_depending_on(dependencies: serde_json::Value) -> LockedApp {
+        serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 0,
+            "triggers": [],
+            "components": [{
+                "id": "app",
+                "metadata": { "dependencies": dependencies },
+                "source": { "content_type": "application/wasm", "content": {} },
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn file_url(path: &std::path::Path) -> String {
+        Url::from_file_path(path).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn dependency_files_are_rewritten_when_pushed_and_pulled() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib.wasm");
+        std::fs::write(&lib, COMPONENT).unwrap();
+        let digest = sha256_digest(COMPONENT);
+
+        let mut app = app_depending_on(serde_json::json!({
+            "lib": { "source": file_url(&lib) },
+            "remote": { "registry": "ghcr.io/fermyon/remote:1.0.0" },
+        }));
+        let component = &mut app.components[0];
+        assert_eq!(vec![lib.clone()], dependency_files(component).unwrap());
+
+        refer_to_pushed_dependencies(component, &HashMap::from([(lib, digest.clone())])).unwrap();
+        let pushed = dependencies(component).unwrap().unwrap();
+        assert_eq!(LockedDependency::Digest(digest.clone()), pushed["lib"]);
+        assert_eq!(
+            LockedDependency::Registry("ghcr.io/fermyon/remote:1.0.0".to_owned()),
+            pushed["remote"]
+        );
+
+        let cache = Cache::new(Some(dir.path().to_owned())).await.unwrap();
+        cache.write_wasm(COMPONENT, &digest).await.unwrap();
+        refer_to_pulled_dependencies(component, &cache).unwrap();
+        let pulled = dependencies(component).unwrap().unwrap();
+        let LockedDependency::Source(source) = &pulled["lib"] else {
+            panic!("expected a source, got {:?}", pulled["lib"]);
+        };
+        let path = spin_trigger::parse_file_url(source).unwrap();
+        assert_eq!(COMPONENT, std::fs::read(path).unwrap());
+        assert_eq!(pushed["remote"], pulled["remote"]);
+    }
+
+    #[test]
+    fn dependency_not_pushed_is_an_error() {
+        let mut app = app_depending_on(serde_json::json!({
+            "lib": { "source": "file:///lib.wasm" },
+        }));
+        assert!(refer_to_pushed_dependencies(&mut app.components[0], &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn detects_registry_dependencies() {
+        let local = app_depending_on(serde_json::json!({
+            "lib": { "source": "file:///lib.wasm" },
+        }));
+        assert!(!has_registry_dependencies(&local).unwrap());
+        let remote = app_depending_on(serde_json::json!({
+            "lib": { "registry": "ghcr.io/fermyon/lib:1.0.0" },
+        }));
+        assert!(has_registry_dependencies(&remote).unwrap());
+    }
+
+    // Serves a single-layer component artifact at `lib:1.0.0`.
+    async fn serve_component(listener: TcpListener, component: &'static [u8]) {
+        let layer_digest = sha256_digest(component);
+        let config_digest = sha256_digest(b"{}");
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": config_digest,
+                "size": 2,
+            },
+            "layers": [{
+                "mediaType": WASM_LAYER_MEDIA_TYPE,
+                "digest": layer_digest,
+                "size": component.len(),
+            }],
+        })
+        .to_string();
+        let manifest_digest = sha256_digest(manifest.as_bytes());
+        loop {
+            let (mut connection, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 4096];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let read = connection.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+            let request = String::from_utf8_lossy(&request).into_owned();
+            let path = request.split_whitespace().nth(1).unwrap();
+            let (headers, body): (String, &[u8]) = if path == "/v2/lib/manifests/1.0.0" {
+                (
+                    format!(
+                        "content-type: application/vnd.oci.image.manifest.v1+json\r\ndocker-content-digest: {manifest_digest}\r\n"
+                    ),
+                    manifest.as_bytes(),
+                )
+            } else if path == format!("/v2/lib/blobs/{layer_digest}") {
+                (String::new(), component)
+            } else if path == "/v2/" {
+                (String::new(), b"")
+            } else {
+                let response =
+                    "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+                connection.write_all(response.as_bytes()).await.unwrap();
+                continue;
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\n{headers}content-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            connection.write_all(head.as_bytes()).await.unwrap();
+            connection.write_all(body).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn registry_dependencies_are_pulled_into_the_cache() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reference = format!("{}/lib:1.0.0", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve_component(listener, COMPONENT));
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let mut client = Client::new(true, Some(cache_dir.path().to_owned()))
+            .await
+            .unwrap();
+        let mut app = app_depending_on(serde_json::json!({
+            "lib": { "registry": reference },
+        }));
+        client.resolve_dependencies(&mut app).await.unwrap();
+        server.abort();
+
+        assert!(!has_registry_dependencies(&app).unwrap());
+        let resolved = dependencies(&app.components[0]).unwrap().unwrap();
+        let LockedDependency::Source(source) = &resolved["lib"] else {
+            panic!("expected a source, got {:?}", resolved["lib"]);
+        };
+        let path = spin_trigger::parse_file_url(source).unwrap();
+        assert_eq!(COMPONENT, std::fs::read(path).unwrap());
+    }
+}
@@ -1,16 +1,77 @@
 //! OCI registries integration.
 #![deny(missing_docs)]
 
+mod archive;
 mod auth;
 mod client;
+mod credential_helper;
+mod dependency;
 mod loader;
+mod mirror;
+mod referrers;
+mod sbom;
+mod signing;
+mod templates;
+mod upload;
+mod variant;
 
-pub use client::Client;
+use std::borrow::Cow;
+
+use once_cell::sync::OnceCell;
+
+pub use client::{Client, ANNOTATION_AUTHORS, ANNOTATION_CREATED};
+pub use dependency::has_registry_dependencies;
 pub use loader::OciLoader;
+pub use mirror::Mirrors;
+pub use referrers::Referrer;
+pub use sbom::{Sbom, SbomFormat, TemplateProvenance};
+pub use signing::{SigningKey, TrustPolicy, VerifyingKey};
+pub use templates::TEMPLATES_LAYER_MEDIA_TYPE;
+pub use variant::Variant;
 
 /// URL scheme used for the locked app "origin" metadata field for OCI-sourced apps.
 pub const ORIGIN_URL_SCHEME: &str = "vnd.fermyon.origin-oci";
 
+/// The environment variable naming the registry of references which don't
+/// name one, such as `fermyon/app:v1`. If it is unset, such references are
+/// to Docker Hub.
+pub const DEFAULT_REGISTRY_ENV: &str = "SPIN_DEFAULT_REGISTRY";
+
+static DEFAULT_REGISTRY: OnceCell<String> = OnceCell::new();
+
+/// Sets the registry of references which don't name one, unless
+/// `SPIN_DEFAULT_REGISTRY` names one. Only the first call has any effect.
+pub fn set_default_registry(registry: String) {
+    let _ = DEFAULT_REGISTRY.set(registry);
+}
+
+/// Returns the reference with the registry named by `SPIN_DEFAULT_REGISTRY`,
+/// or set by [`set_default_registry`], if there is one and the reference
+/// doesn't name a registry.
+pub fn qualify_reference(reference: &str) -> Cow<str> {
+    match std::env::var(DEFAULT_REGISTRY_ENV) {
+        Ok(registry) if !registry.is_empty() => with_default_registry(reference, &registry),
+        _ => match DEFAULT_REGISTRY.get() {
+            Some(registry) if !registry.is_empty() => with_default_registry(reference, registry),
+            _ => reference.into(),
+        },
+    }
+}
+
+fn with_default_registry<'a>(reference: &'a str, registry: &str) -> Cow<'a, str> {
+    // As for Docker, the first part of a reference names a registry if it
+    // looks like a host name.
+    let names_registry = match reference.split_once('/') {
+        Some((first, _)) => first.contains(['.', ':']) || first == "localhost",
+        None => false,
+    };
+    if names_registry {
+        reference.into()
+    } else {
+        format!("{}/{reference}", registry.trim_end_matches('/')).into()
+    }
+}
+
 /// Applies heuristics to check if the given string "looks like" it may be
 /// an OCI reference.
 ///
@@ -45,3 +106,27 @@ pub fn is_probably_oci_reference(maybe_oci: &str) -> bool {
     // Passed all the tests; likely a reference
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_registry_applies_to_unqualified_references() {
+        assert_eq!(
+            with_default_registry("fermyon/app:v1", "ghcr.io"),
+            "ghcr.io/fermyon/app:v1"
+        );
+        assert_eq!(
+            with_default_registry("app:v1", "ghcr.io/"),
+            "ghcr.io/app:v1"
+        );
+        for qualified in [
+            "quay.io/fermyon/app:v1",
+            "localhost:5000/app:v1",
+            "localhost/app:v1",
+        ] {
+            assert_eq!(with_default_registry(qualified, "ghcr.io"), qualified);
+        }
+    }
+}
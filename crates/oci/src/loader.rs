@@ -6,7 +6,7 @@ use reqwest::Url;
 use spin_app::locked::{ContentPath, ContentRef, LockedApp, LockedComponent};
 use spin_loader::cache::Cache;
 
-use crate::{Client, ORIGIN_URL_SCHEME};
+use crate::{dependency, qualify_reference, Client, ORIGIN_URL_SCHEME};
 
 /// OciLoader loads an OCI app in preparation for running with Spin.
 pub struct OciLoader {
@@ -23,10 +23,18 @@ impl OciLoader {
 
     /// Loads a LockedApp with the given OCI client and reference.
     pub async fn load_app(&self, client: &mut Client, reference: &str) -> Result<LockedApp> {
-        // Fetch app
-        client.pull(reference).await.with_context(|| {
-            format!("cannot pull Spin application from registry reference {reference:?}")
-        })?;
+        // Fetch app. Without network access to the registry, an app pulled
+        // or loaded into the cache before is used, unless signatures must be
+        // verified.
+        if let Err(e) = client.pull(reference).await {
+            let cached = client.lockfile_path(&reference).await?.exists();
+            if !cached || client.verifies_signatures() {
+                return Err(e).with_context(|| {
+                    format!("cannot pull Spin application from registry reference {reference:?}")
+                });
+            }
+            tracing::warn!("Cannot pull {reference} ({e:#}); using the cached application");
+        }
 
         // Read locked app
         let lockfile_path = client
@@ -40,7 +48,8 @@ impl OciLoader {
             .with_context(|| format!("failed to decode locked app from {lockfile_path:?}"))?;
 
         // Update origin metadata
-        let resolved_reference = Reference::try_from(reference).context("invalid reference")?;
+        let resolved_reference = Reference::try_from(qualify_reference(reference).as_ref())
+            .context("invalid reference")?;
         let origin_uri = format!("{ORIGIN_URL_SCHEME}:{resolved_reference}");
         locked_app
             .metadata
@@ -66,6 +75,9 @@ impl OciLoader {
         let wasm_path = cache.wasm_file(wasm_digest)?;
         component.source.content = content_ref(wasm_path)?;
 
+        // Update the paths of dependencies stored with the app
+        dependency::refer_to_pulled_dependencies(component, cache)?;
+
         if !component.files.is_empty() {
             let mount_dir = self.working_dir.join(&component.id);
             for file in &mut component.files {
@@ -95,6 +107,7 @@ impl OciLoader {
             component.files = vec![ContentPath {
                 content: content_ref(mount_dir)?,
                 path: "/".into(),
+                writable: false,
             }]
         }
 
@@ -110,14 +123,18 @@ fn content_digest(content_ref: &ContentRef) -> Result<&str> {
 }
 
 fn content_ref(path: impl AsRef<Path>) -> Result<ContentRef> {
-    let path = std::fs::canonicalize(path)?;
-    let url = Url::from_file_path(path).map_err(|_| anyhow!("couldn't build file URL"))?;
     Ok(ContentRef {
-        source: Some(url.to_string()),
+        source: Some(content_url(path)?),
         ..Default::default()
     })
 }
 
+fn content_url(path: impl AsRef<Path>) -> Result<String> {
+    let path = std::fs::canonicalize(path)?;
+    let url = Url::from_file_path(path).map_err(|_| anyhow!("couldn't build file URL"))?;
+    Ok(url.to_string())
+}
+
 fn is_safe_to_join(path: impl AsRef<Path>) -> bool {
     // This could be loosened, but currently should always be true
     path.as_ref()
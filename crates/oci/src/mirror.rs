@@ -0,0 +1,91 @@
+//! Registry mirrors.
+//!
+//! A mirror serves the repositories of a registry under its own host, and
+//! optionally a path prefix: with `ghcr.io` mirrored at
+//! `mirror.example.com/ghcr`, `ghcr.io/fermyon/app:v1` is pulled as
+//! `mirror.example.com/ghcr/fermyon/app:v1`. Mirrors are tried in order,
+//! and the registry itself is tried if none of them can provide the
+//! application.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use oci_distribution::Reference;
+
+/// Mirrors to pull from in place of registries.
+#[derive(Clone, Debug, Default)]
+pub struct Mirrors {
+    mirrors: HashMap<String, Vec<String>>,
+}
+
+impl Mirrors {
+    /// Add a mirror for the given registry, to be tried after any mirrors
+    /// already added for it.
+    pub fn add(&mut self, registry: &str, mirror: &str) {
+        self.mirrors
+            .entry(canonical_registry(registry).to_owned())
+            .or_default()
+            .push(mirror.trim_end_matches('/').to_owned());
+    }
+
+    /// Whether no mirrors are configured.
+    pub fn is_empty(&self) -> bool {
+        self.mirrors.is_empty()
+    }
+
+    /// The references at which the mirrors of the reference's registry
+    /// serve it, in the order in which they are to be tried.
+    pub(crate) fn references_for(&self, reference: &Reference) -> Result<Vec<Reference>> {
+        let Some(mirrors) = self.mirrors.get(canonical_registry(reference.registry())) else {
+            return Ok(vec![]);
+        };
+        let suffix = match (reference.digest(), reference.tag()) {
+            (Some(digest), _) => format!("@{digest}"),
+            (None, Some(tag)) => format!(":{tag}"),
+            (None, None) => String::new(),
+        };
+        mirrors
+            .iter()
+            .map(|mirror| {
+                format!("{mirror}/{}{suffix}", reference.repository())
+                    .parse()
+                    .with_context(|| format!("invalid mirror {mirror}"))
+            })
+            .collect()
+    }
+}
+
+// Docker Hub is known by several names.
+fn canonical_registry(registry: &str) -> &str {
+    match registry {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_serve_repositories_under_prefix() {
+        let mut mirrors = Mirrors::default();
+        mirrors.add("ghcr.io", "mirror.example.com/ghcr/");
+        mirrors.add("ghcr.io", "localhost:5000");
+        mirrors.add("index.docker.io", "hub.example.com");
+
+        let reference: Reference = "ghcr.io/fermyon/app:v1".parse().unwrap();
+        let mirrored = mirrors.references_for(&reference).unwrap();
+        assert_eq!(2, mirrored.len());
+        assert_eq!("mirror.example.com", mirrored[0].registry());
+        assert_eq!("ghcr/fermyon/app", mirrored[0].repository());
+        assert_eq!(Some("v1"), mirrored[0].tag());
+        assert_eq!("localhost:5000", mirrored[1].registry());
+
+        let reference: Reference = "docker.io/fermyon/app:v1".parse().unwrap();
+        assert_eq!(1, mirrors.references_for(&reference).unwrap().len());
+
+        let reference: Reference = "quay.io/fermyon/app:v1".parse().unwrap();
+        assert!(mirrors.references_for(&reference).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Artifacts attached to Spin applications, such as signatures, SBOMs and
+//! attestations.
+//!
+//! Attached artifacts are found through the OCI 1.1 referrers API, or the
+//! referrers tag schema on registries which lack it, and through the tags
+//! under which cosign stores signatures and attestations.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use oci_distribution::Reference;
+use serde::Serialize;
+
+use crate::{qualify_reference, signing::SIMPLE_SIGNING_MEDIA_TYPE, upload::Uploader, Client};
+
+const DSSE_ENVELOPE_MEDIA_TYPE: &str = "application/vnd.dsse.envelope.v1+json";
+const ANNOTATION_IMAGE_TITLE: &str = "org.opencontainers.image.title";
+
+// Cosign tags signatures and attestations with the digest of their subject,
+// with these suffixes.
+const COSIGN_TAGS: &[(&str, &str)] = &[
+    ("sig", SIMPLE_SIGNING_MEDIA_TYPE),
+    ("att", DSSE_ENVELOPE_MEDIA_TYPE),
+];
+
+/// An artifact attached to an application.
+#[derive(Clone, Debug, Serialize)]
+pub struct Referrer {
+    /// The digest of the artifact's manifest.
+    pub digest: String,
+    /// The type of the artifact, such as `application/spdx+json`, if known.
+    pub artifact_type: Option<String>,
+    /// The size of the artifact's manifest in bytes.
+    pub size: usize,
+    /// The annotations of the artifact.
+    pub annotations: HashMap<String, String>,
+    /// The tag under which the artifact is stored, for artifacts found by
+    /// tag rather than by the referrers API.
+    pub tag: Option<String>,
+}
+
+impl Client {
+    /// List the artifacts attached to the application with the given
+    /// reference, of the given type if one is given.
+    pub async fn referrers(
+        &mut self,
+        reference: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Referrer>> {
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        let auth = Self::auth(&reference).await?;
+        let mut uploader = Uploader::new(&reference, auth, self.insecure, &self.cache)?;
+        let subject = uploader.manifest_descriptor(&reference).await?;
+
+        let mut referrers = uploader
+            .referrers(&subject.digest, artifact_type)
+            .await?
+            .into_iter()
+            .map(|descriptor| Referrer {
+                digest: descriptor.digest,
+                artifact_type: descriptor.artifact_type,
+                size: descriptor.size,
+                annotations: descriptor.annotations.unwrap_or_default(),
+                tag: None,
+            })
+            .collect::<Vec<_>>();
+
+        for (suffix, media_type) in COSIGN_TAGS {
+            if artifact_type.map_or(false, |t| t != *media_type) {
+                continue;
+            }
+            let tag = format!("{}.{suffix}", subject.digest.replace(':', "-"));
+            let tagged = with_tag(&reference, &tag)?;
+            if let Some(descriptor) = uploader.find_manifest(&tagged).await? {
+                referrers.push(Referrer {
+                    digest: descriptor.digest,
+                    artifact_type: Some(media_type.to_string()),
+                    size: descriptor.size,
+                    annotations: HashMap::new(),
+                    tag: Some(tag),
+                });
+            }
+        }
+        Ok(referrers)
+    }
+
+    /// Pull the layers of the attached artifact with the given digest from
+    /// the repository of the given reference into a directory, returning the
+    /// paths written. Each layer is named by its title annotation if it has
+    /// one, and otherwise by its digest.
+    pub async fn pull_referrer(
+        &mut self,
+        reference: &str,
+        digest: &str,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        let artifact = with_digest(&reference, digest)?;
+        let auth = Self::auth(&artifact).await?;
+        let (manifest, _) = self
+            .oci
+            .pull_image_manifest(&artifact, &auth)
+            .await
+            .with_context(|| format!("cannot pull artifact {digest}"))?;
+
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("cannot create {}", dir.display()))?;
+        let mut paths = vec![];
+        for layer in &manifest.layers {
+            let name = layer
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(ANNOTATION_IMAGE_TITLE))
+                .and_then(|title| Path::new(title).file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| layer.digest.replace(':', "-"));
+            let mut bytes = vec![];
+            self.oci
+                .pull_blob(&artifact, &layer.digest, &mut bytes)
+                .await
+                .with_context(|| format!("cannot pull layer {}", layer.digest))?;
+            let path = dir.join(name);
+            tokio::fs::write(&path, bytes)
+                .await
+                .with_context(|| format!("cannot write {}", path.display()))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+fn with_tag(reference: &Reference, tag: &str) -> Result<Reference> {
+    format!("{}/{}:{tag}", reference.registry(), reference.repository())
+        .parse()
+        .context("cannot construct artifact reference")
+}
+
+fn with_digest(reference: &Reference, digest: &str) -> Result<Reference> {
+    format!(
+        "{}/{}@{digest}",
+        reference.registry(),
+        reference.repository()
+    )
+    .parse()
+    .context("cannot construct artifact reference")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifact_references_share_repository() {
+        let reference: Reference = "ghcr.io/fermyon/app:v1".parse().unwrap();
+        let tagged = with_tag(&reference, "sha256-abc123.sig").unwrap();
+        assert_eq!("fermyon/app", tagged.repository());
+        assert_eq!(Some("sha256-abc123.sig"), tagged.tag());
+
+        let digest = "sha256:0a867093096e0ef01ef749b12b6e7a90e4952eda107f89a676eeedce63a8361f";
+        let pinned = with_digest(&reference, digest).unwrap();
+        assert_eq!("ghcr.io", pinned.registry());
+        assert_eq!(Some(digest), pinned.digest());
+    }
+}
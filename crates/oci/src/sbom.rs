@@ -0,0 +1,482 @@
+//! Software bills of materials for Spin applications.
+//!
+//! An SBOM lists the application's components with the hashes of their Wasm
+//! modules and the hosts, stores and databases they declare they use, and
+//! the template repositories the application was generated from. SBOMs are
+//! attached to pushed applications as OCI referrers: artifacts whose
+//! `subject` is the application's manifest, which the registry lists by that
+//! manifest's digest.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::Reference;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use spin_manifest::{Application, ModuleSource};
+
+use crate::{qualify_reference, upload::Uploader, Client};
+
+const CYCLONEDX_MEDIA_TYPE: &str = "application/vnd.cyclonedx+json";
+const SPDX_MEDIA_TYPE: &str = "application/spdx+json";
+const ANNOTATION_SBOM_FORMAT: &str = "dev.fermyon.spin.sbom.format";
+
+/// A format in which to write an SBOM.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON.
+    #[default]
+    CycloneDx,
+    /// SPDX 2.3 JSON.
+    Spdx,
+}
+
+impl SbomFormat {
+    fn media_type(self) -> &'static str {
+        match self {
+            Self::CycloneDx => CYCLONEDX_MEDIA_TYPE,
+            Self::Spdx => SPDX_MEDIA_TYPE,
+        }
+    }
+}
+
+impl FromStr for SbomFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "cyclonedx" => Ok(Self::CycloneDx),
+            "spdx" => Ok(Self::Spdx),
+            _ => bail!("Unknown SBOM format {s:?}: expected `cyclonedx` or `spdx`"),
+        }
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CycloneDx => f.write_str("cyclonedx"),
+            Self::Spdx => f.write_str("spdx"),
+        }
+    }
+}
+
+/// A template repository from which an application was generated, as
+/// pinned in its templates lockfile.
+#[derive(Clone, Debug)]
+pub struct TemplateProvenance {
+    /// The URL of the Git repository.
+    pub repository: String,
+    /// The commit the templates were installed from.
+    pub revision: String,
+}
+
+/// The contents of an application's SBOM.
+pub struct Sbom {
+    name: String,
+    version: String,
+    description: Option<String>,
+    authors: Vec<String>,
+    components: Vec<SbomComponent>,
+    templates: Vec<TemplateProvenance>,
+}
+
+struct SbomComponent {
+    id: String,
+    sha256: String,
+    // Declared dependencies, as (kind, value) pairs such as
+    // ("allowed_outbound_host", "https://example.com").
+    dependencies: Vec<(&'static str, String)>,
+}
+
+impl Sbom {
+    /// Collect the SBOM of an application, hashing its components' Wasm
+    /// modules.
+    pub fn from_app(app: &Application, templates: Vec<TemplateProvenance>) -> Result<Self> {
+        let components = app
+            .components
+            .iter()
+            .map(|c| {
+                let wasm = match &c.source {
+                    ModuleSource::FileReference(path) => {
+                        std::fs::read(path).with_context(|| {
+                            format!("cannot read Wasm module of component {}", c.id)
+                        })?
+                    }
+                    ModuleSource::Buffer(bytes, _) => bytes.clone(),
+                };
+                let declared = [
+                    ("allowed_http_host", &c.wasm.allowed_http_hosts),
+                    ("allowed_outbound_host", &c.wasm.allowed_outbound_hosts),
+                    ("key_value_store", &c.wasm.key_value_stores),
+                    ("sqlite_database", &c.wasm.sqlite_databases),
+                    ("blob_container", &c.wasm.blob_containers),
+                    ("messaging_channel", &c.wasm.messaging_channels),
+                ];
+                Ok(SbomComponent {
+                    id: c.id.clone(),
+                    sha256: format!("{:x}", Sha256::digest(&wasm)),
+                    dependencies: declared
+                        .into_iter()
+                        .flat_map(|(kind, values)| values.iter().map(move |v| (kind, v.clone())))
+                        .collect(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name: app.info.name.clone(),
+            version: app.info.version.clone(),
+            description: app.info.description.clone(),
+            authors: app.info.authors.clone(),
+            components,
+            templates,
+        })
+    }
+
+    /// Write the SBOM in the given format.
+    pub fn render(&self, format: SbomFormat) -> Result<Vec<u8>> {
+        let created = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let mut document = match format {
+            SbomFormat::CycloneDx => self.cyclonedx(&created),
+            SbomFormat::Spdx => self.spdx(&created),
+        };
+        remove_nulls(&mut document);
+        Ok(serde_json::to_vec_pretty(&document)?)
+    }
+
+    fn cyclonedx(&self, created: &str) -> Value {
+        let app_ref = format!("app:{}", self.name);
+        let mut components = vec![];
+        let mut depends_on = vec![];
+        for c in &self.components {
+            let bom_ref = format!("component:{}", c.id);
+            let properties = c
+                .dependencies
+                .iter()
+                .map(|(kind, value)| json!({ "name": format!("spin:{kind}"), "value": value }))
+                .collect::<Vec<_>>();
+            components.push(json!({
+                "type": "application",
+                "bom-ref": bom_ref,
+                "name": c.id,
+                "hashes": [{ "alg": "SHA-256", "content": c.sha256 }],
+                "properties": properties,
+            }));
+            depends_on.push(bom_ref);
+        }
+        for t in &self.templates {
+            let bom_ref = format!("template:{}@{}", t.repository, t.revision);
+            components.push(json!({
+                "type": "framework",
+                "bom-ref": bom_ref,
+                "name": t.repository,
+                "version": t.revision,
+                "externalReferences": [{ "type": "vcs", "url": t.repository }],
+                "properties": [{ "name": "spin:role", "value": "template-repository" }],
+            }));
+            depends_on.push(bom_ref);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": created,
+                "tools": {
+                    "components": [{
+                        "type": "application",
+                        "name": "spin",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    }],
+                },
+                "component": {
+                    "type": "application",
+                    "bom-ref": app_ref,
+                    "name": self.name,
+                    "version": self.version,
+                    "description": self.description,
+                    "authors": self.authors.iter().map(|a| json!({ "name": a })).collect::<Vec<_>>(),
+                },
+            },
+            "components": components,
+            "dependencies": [{ "ref": app_ref, "dependsOn": depends_on }],
+        })
+    }
+
+    fn spdx(&self, created: &str) -> Value {
+        const APP_ID: &str = "SPDXRef-Application";
+        let mut packages = vec![json!({
+            "SPDXID": APP_ID,
+            "name": self.name,
+            "versionInfo": self.version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "description": self.description,
+            "originator": (!self.authors.is_empty())
+                .then(|| format!("Person: {}", self.authors.join(", "))),
+        })];
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": APP_ID,
+        })];
+        for c in &self.components {
+            let id = format!("SPDXRef-Component-{}", spdx_id(&c.id));
+            let comment = c
+                .dependencies
+                .iter()
+                .map(|(kind, value)| format!("{kind}: {value}"))
+                .collect::<Vec<_>>();
+            packages.push(json!({
+                "SPDXID": id,
+                "name": c.id,
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "checksums": [{ "algorithm": "SHA256", "checksumValue": c.sha256 }],
+                "comment": (!comment.is_empty()).then(|| comment.join("\n")),
+            }));
+            relationships.push(json!({
+                "spdxElementId": APP_ID,
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": id,
+            }));
+        }
+        for (i, t) in self.templates.iter().enumerate() {
+            let id = format!("SPDXRef-Template-{i}");
+            packages.push(json!({
+                "SPDXID": id,
+                "name": t.repository,
+                "versionInfo": t.revision,
+                "downloadLocation": format!("git+{}@{}", t.repository, t.revision),
+                "filesAnalyzed": false,
+            }));
+            relationships.push(json!({
+                "spdxElementId": APP_ID,
+                "relationshipType": "GENERATED_FROM",
+                "relatedSpdxElement": id,
+            }));
+        }
+        let hashes = self
+            .components
+            .iter()
+            .map(|c| c.sha256.as_str())
+            .collect::<String>();
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": format!("{}-{}", self.name, self.version),
+            "documentNamespace": format!(
+                "urn:spin:sbom:{}:{}:{:x}",
+                spdx_id(&self.name),
+                spdx_id(&self.version),
+                Sha256::digest(hashes.as_bytes())
+            ),
+            "creationInfo": {
+                "created": created,
+                "creators": [format!("Tool: spin-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+// Neither schema allows nulls for absent optional fields.
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+// SPDX identifiers may contain only letters, numbers, `.` and `-`.
+fn spdx_id(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+impl Client {
+    /// Attach an SBOM to the manifest with the given digest in the
+    /// repository of the given reference, returning the digest of the SBOM
+    /// artifact.
+    pub async fn attach_sbom(
+        &mut self,
+        reference: &str,
+        digest: &str,
+        sbom: &Sbom,
+        format: SbomFormat,
+    ) -> Result<String> {
+        let subject: Reference = format!("{}@{digest}", repository(reference)?)
+            .parse()
+            .context("cannot construct SBOM subject reference")?;
+        let auth = Self::auth(&subject).await?;
+        let mut uploader = Uploader::new(&subject, auth, self.insecure, &self.cache)?;
+        let subject = uploader.manifest_descriptor(&subject).await?;
+        let annotations: HashMap<_, _> = [
+            (ANNOTATION_SBOM_FORMAT.to_owned(), format.to_string()),
+            (
+                crate::ANNOTATION_CREATED.to_owned(),
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        uploader
+            .push_referrer(
+                &subject,
+                format.media_type(),
+                &sbom.render(format)?,
+                annotations,
+            )
+            .await
+            .context("cannot push SBOM")
+    }
+
+    /// Fetch the SBOM attached to the application with the given reference.
+    /// If a format is given, only an SBOM in that format is accepted;
+    /// otherwise the most recently attached SBOM in any format is.
+    pub async fn pull_sbom(
+        &mut self,
+        reference: &str,
+        format: Option<SbomFormat>,
+    ) -> Result<(SbomFormat, Vec<u8>)> {
+        let formats = match format {
+            Some(format) => vec![format],
+            None => vec![SbomFormat::CycloneDx, SbomFormat::Spdx],
+        };
+        let mut found = vec![];
+        for format in formats {
+            for referrer in self.referrers(reference, Some(format.media_type())).await? {
+                let created = referrer
+                    .annotations
+                    .get(crate::ANNOTATION_CREATED)
+                    .cloned()
+                    .unwrap_or_default();
+                found.push((created, format, referrer.digest));
+            }
+        }
+        // RFC 3339 timestamps in UTC sort chronologically.
+        found.sort();
+        let Some((_, format, digest)) = found.pop() else {
+            bail!("{reference} has no attached SBOM");
+        };
+
+        let sbom_reference: Reference = format!("{}@{digest}", repository(reference)?)
+            .parse()
+            .context("cannot construct SBOM reference")?;
+        let auth = Self::auth(&sbom_reference).await?;
+        let (manifest, _) = self
+            .oci
+            .pull_image_manifest(&sbom_reference, &auth)
+            .await
+            .context("cannot pull SBOM manifest")?;
+        let layer = manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == format.media_type())
+            .context("SBOM artifact has no SBOM layer")?;
+        let mut bytes = vec![];
+        self.oci
+            .pull_blob(&sbom_reference, &layer.digest, &mut bytes)
+            .await
+            .context("cannot pull SBOM")?;
+        Ok((format, bytes))
+    }
+}
+
+fn repository(reference: &str) -> Result<String> {
+    let reference: Reference = qualify_reference(reference)
+        .parse()
+        .with_context(|| format!("cannot parse reference {reference}"))?;
+    Ok(format!(
+        "{}/{}",
+        reference.registry(),
+        reference.repository()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_sbom() -> Sbom {
+        Sbom {
+            name: "hello".to_owned(),
+            version: "1.0.0".to_owned(),
+            description: None,
+            authors: vec!["Fermyon Engineering <engineering@fermyon.com>".to_owned()],
+            components: vec![SbomComponent {
+                id: "hello_world".to_owned(),
+                sha256: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                    .to_owned(),
+                dependencies: vec![("key_value_store", "default".to_owned())],
+            }],
+            templates: vec![TemplateProvenance {
+                repository: "https://github.com/fermyon/spin".to_owned(),
+                revision: "abc123".to_owned(),
+            }],
+        }
+    }
+
+    #[test]
+    fn renders_cyclonedx() {
+        let sbom: Value =
+            serde_json::from_slice(&test_sbom().render(SbomFormat::CycloneDx).unwrap()).unwrap();
+        assert_eq!("CycloneDX", sbom["bomFormat"]);
+        assert_eq!("hello", sbom["metadata"]["component"]["name"]);
+        assert!(sbom["metadata"]["component"].get("description").is_none());
+        let component = &sbom["components"][0];
+        assert_eq!("hello_world", component["name"]);
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            component["hashes"][0]["content"]
+        );
+        assert_eq!("spin:key_value_store", component["properties"][0]["name"]);
+        assert_eq!("abc123", sbom["components"][1]["version"]);
+        assert_eq!(
+            2,
+            sbom["dependencies"][0]["dependsOn"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn renders_spdx() {
+        let sbom: Value =
+            serde_json::from_slice(&test_sbom().render(SbomFormat::Spdx).unwrap()).unwrap();
+        assert_eq!("SPDX-2.3", sbom["spdxVersion"]);
+        let packages = sbom["packages"].as_array().unwrap();
+        assert_eq!(3, packages.len());
+        assert_eq!("SPDXRef-Component-hello-world", packages[1]["SPDXID"]);
+        assert_eq!(
+            "git+https://github.com/fermyon/spin@abc123",
+            packages[2]["downloadLocation"]
+        );
+        assert!(sbom["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["relationshipType"] == "GENERATED_FROM"));
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!(SbomFormat::CycloneDx, "CycloneDX".parse().unwrap());
+        assert_eq!(SbomFormat::Spdx, "spdx".parse().unwrap());
+        assert!("swid".parse::<SbomFormat>().is_err());
+    }
+}
@@ -0,0 +1,651 @@
+//! Signing applications pushed to OCI registries, and verifying them when
+//! they are pulled.
+//!
+//! Signatures are stored the way cosign stores them: as an artifact in the
+//! same repository, tagged `sha256-<digest>.sig`, whose layer is a "simple
+//! signing" payload naming the signed manifest digest, with the Ed25519
+//! signature of the payload in the layer's annotations. The signer may name
+//! itself in the payload's optional `Issuer` field, which a trust policy can
+//! require. As anyone with a trusted key could name any issuer, a trust
+//! policy only accepts an issuer from the keys it trusts for that issuer.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::{Signer, Verifier};
+use oci_distribution::{
+    client::{Config, ImageLayer},
+    manifest::OciImageManifest,
+    Reference,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{client::digest_matches, qualify_reference, Client};
+
+pub(crate) const SIMPLE_SIGNING_MEDIA_TYPE: &str =
+    "application/vnd.dev.cosign.simplesigning.v1+json";
+const SIGNATURE_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+const SIGNATURE_TYPE: &str = "cosign container image signature";
+const ISSUER_FIELD: &str = "Issuer";
+const LATEST_TAG: &str = "latest";
+
+const PRIVATE_KEY_LABEL: &str = "SPIN PRIVATE KEY";
+const PUBLIC_KEY_LABEL: &str = "SPIN PUBLIC KEY";
+
+/// A key with which to sign applications.
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+/// A key with which to verify application signatures.
+#[derive(Clone)]
+pub struct VerifyingKey(ed25519_dalek::VerifyingKey);
+
+/// Which signatures are trusted when pulling applications, whether
+/// applications must be signed, and which references may be pulled.
+#[derive(Clone)]
+pub struct TrustPolicy {
+    keys: Vec<TrustedKey>,
+    require_signatures: bool,
+    required_issuers: Vec<String>,
+    allowed_registries: Vec<String>,
+    require_digest: bool,
+    deny_latest: bool,
+}
+
+// A trusted key, and the issuer it is trusted to sign as, if any.
+#[derive(Clone)]
+struct TrustedKey {
+    key: VerifyingKey,
+    issuer: Option<String>,
+}
+
+impl SigningKey {
+    /// Generate a new key.
+    pub fn generate() -> Self {
+        Self(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng))
+    }
+
+    /// Load a key saved by [`SigningKey::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = read_key_file(path, PRIVATE_KEY_LABEL)?;
+        Ok(Self(ed25519_dalek::SigningKey::from_bytes(&bytes)))
+    }
+
+    /// Save the key to a file, readable only by its owner.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_key_file(path, PRIVATE_KEY_LABEL, &self.0.to_bytes(), true)
+    }
+
+    /// The key with which to verify signatures made with this key.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.verifying_key())
+    }
+}
+
+impl VerifyingKey {
+    /// Load a key saved by [`VerifyingKey::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = read_key_file(path, PUBLIC_KEY_LABEL)?;
+        let key = ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+            .with_context(|| format!("{} is not a valid public key", path.display()))?;
+        Ok(Self(key))
+    }
+
+    /// Save the key to a file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_key_file(path, PUBLIC_KEY_LABEL, self.0.as_bytes(), false)
+    }
+}
+
+impl TrustPolicy {
+    /// Trust signatures made with the given keys. If `require_signatures` is
+    /// true, applications without such a signature are refused.
+    pub fn new(keys: Vec<VerifyingKey>, require_signatures: bool) -> Self {
+        Self {
+            keys: keys
+                .into_iter()
+                .map(|key| TrustedKey { key, issuer: None })
+                .collect(),
+            require_signatures,
+            required_issuers: vec![],
+            allowed_registries: vec![],
+            require_digest: false,
+            deny_latest: false,
+        }
+    }
+
+    /// Trust signatures made with the keys in the given public key files.
+    pub fn load(key_files: &[PathBuf], require_signatures: bool) -> Result<Self> {
+        let keys = key_files
+            .iter()
+            .map(|path| VerifyingKey::load(path))
+            .collect::<Result<_>>()?;
+        Ok(Self::new(keys, require_signatures))
+    }
+
+    /// Trust signatures made with the given keys, including as signatures
+    /// from the given issuer.
+    pub fn with_issuer_keys(mut self, issuer: &str, keys: Vec<VerifyingKey>) -> Self {
+        self.keys.extend(keys.into_iter().map(|key| TrustedKey {
+            key,
+            issuer: Some(issuer.to_owned()),
+        }));
+        self
+    }
+
+    /// Trust signatures made with the keys in the given public key files,
+    /// including as signatures from the given issuer.
+    pub fn load_issuer_keys(self, issuer: &str, key_files: &[PathBuf]) -> Result<Self> {
+        let keys = key_files
+            .iter()
+            .map(|path| VerifyingKey::load(path))
+            .collect::<Result<_>>()?;
+        Ok(self.with_issuer_keys(issuer, keys))
+    }
+
+    /// Require a trusted signature whose signer names one of the given
+    /// issuers, made with a key trusted for that issuer by
+    /// [`TrustPolicy::with_issuer_keys`]. If the list is empty, any issuer is
+    /// accepted.
+    pub fn with_required_issuers(mut self, issuers: Vec<String>) -> Self {
+        self.required_issuers = issuers;
+        self
+    }
+
+    /// Only pull from the given registries, each either a registry host such
+    /// as `ghcr.io` or a repository prefix such as `ghcr.io/fermyon`. If the
+    /// list is empty, any registry is allowed.
+    pub fn with_allowed_registries(mut self, registries: Vec<String>) -> Self {
+        self.allowed_registries = registries;
+        self
+    }
+
+    /// Whether to refuse references which do not name a digest.
+    pub fn with_require_digest(mut self, require_digest: bool) -> Self {
+        self.require_digest = require_digest;
+        self
+    }
+
+    /// Whether to refuse references to the `latest` tag, including those
+    /// with neither a tag nor a digest.
+    pub fn with_deny_latest(mut self, deny_latest: bool) -> Self {
+        self.deny_latest = deny_latest;
+        self
+    }
+
+    fn signatures_required(&self) -> bool {
+        self.require_signatures || !self.required_issuers.is_empty()
+    }
+
+    /// Check that the policy allows pulling the given reference, before
+    /// anything is pulled.
+    pub(crate) fn check_reference(&self, reference: &Reference) -> Result<()> {
+        if !self.allowed_registries.is_empty() {
+            let repository = format!("{}/{}", reference.registry(), reference.repository());
+            let allowed = self.allowed_registries.iter().any(|allowed| {
+                let allowed = allowed.trim_end_matches('/');
+                repository == allowed || repository.starts_with(&format!("{allowed}/"))
+            });
+            if !allowed {
+                bail!(
+                    "{reference} is not in an allowed registry. Allowed registries: {}",
+                    self.allowed_registries.join(", ")
+                );
+            }
+        }
+        if self.require_digest && reference.digest().is_none() {
+            bail!("{reference} does not name a digest, and only pulls by digest are allowed");
+        }
+        if self.deny_latest
+            && reference.digest().is_none()
+            && reference.tag().map_or(true, |tag| tag == LATEST_TAG)
+        {
+            bail!("{reference} refers to the `latest` tag, which is not allowed: give a version tag or a digest");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SimpleSigningPayload {
+    critical: Critical,
+    optional: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Critical {
+    identity: Identity,
+    image: Image,
+    #[serde(rename = "type")]
+    signature_type: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Identity {
+    #[serde(rename = "docker-reference")]
+    docker_reference: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Image {
+    #[serde(rename = "docker-manifest-digest")]
+    docker_manifest_digest: String,
+}
+
+impl Client {
+    /// Sign the manifest with the given digest in the repository of the
+    /// given reference, pushing the signature to the same repository. If an
+    /// issuer is given, the signature names it as the signer.
+    pub async fn sign(
+        &mut self,
+        reference: &str,
+        digest: &str,
+        key: &SigningKey,
+        issuer: Option<&str>,
+    ) -> Result<()> {
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+        let auth = Self::auth(&reference).await?;
+
+        let payload = serde_json::to_vec(&SimpleSigningPayload {
+            critical: Critical {
+                identity: Identity {
+                    docker_reference: docker_reference(&reference),
+                },
+                image: Image {
+                    docker_manifest_digest: digest.to_owned(),
+                },
+                signature_type: SIGNATURE_TYPE.to_owned(),
+            },
+            optional: issuer.map(|issuer| serde_json::json!({ ISSUER_FIELD: issuer })),
+        })?;
+        let signature = key.0.sign(&payload);
+        let annotations = [(
+            SIGNATURE_ANNOTATION.to_owned(),
+            BASE64.encode(signature.to_bytes()),
+        )]
+        .into_iter()
+        .collect();
+        let layers = vec![ImageLayer::new(
+            payload,
+            SIMPLE_SIGNING_MEDIA_TYPE.to_owned(),
+            Some(annotations),
+        )];
+        let config = Config {
+            data: b"{}".to_vec(),
+            media_type: SIGNATURE_CONFIG_MEDIA_TYPE.to_owned(),
+            annotations: None,
+        };
+        let manifest = OciImageManifest::build(&layers, &config, None);
+
+        let signature_reference = signature_reference(&reference, digest)?;
+        self.oci
+            .push(&signature_reference, &layers, config, &auth, Some(manifest))
+            .await
+            .context("cannot push signature")?;
+        tracing::info!("Pushed signature {}", signature_reference);
+        Ok(())
+    }
+
+    /// Check the signature of the manifest with the given digest, pulled
+    /// from `source` (the reference or a mirror of it), against the trust
+    /// policy. A signature which does not verify, is for another repository,
+    /// or is not from a required issuer, is always an error; a missing one is
+    /// an error only if the policy requires signatures.
+    pub(crate) async fn verify(
+        &mut self,
+        source: &Reference,
+        reference: &Reference,
+        digest: &str,
+        policy: &TrustPolicy,
+    ) -> Result<()> {
+        if policy.keys.is_empty() {
+            if policy.signatures_required() {
+                bail!("Signatures are required, but no trusted keys are configured");
+            }
+            return Ok(());
+        }
+        if !policy.required_issuers.is_empty()
+            && !policy.keys.iter().any(|trusted| {
+                trusted
+                    .issuer
+                    .as_ref()
+                    .map_or(false, |issuer| policy.required_issuers.contains(issuer))
+            })
+        {
+            bail!(
+                "Signatures from {} are required, but no keys are trusted for them",
+                policy.required_issuers.join(", ")
+            );
+        }
+
+        let signature_reference = signature_reference(source, digest)?;
+        let auth = Self::auth(source).await?;
+        let manifest = match self
+            .pull_verified_image_manifest(&signature_reference, &auth)
+            .await
+        {
+            Ok((manifest, _)) => manifest,
+            Err(e) => {
+                if policy.signatures_required() {
+                    bail!("{reference} is not signed, and signatures are required");
+                }
+                tracing::debug!("No signature found for {reference}: {e}");
+                return Ok(());
+            }
+        };
+
+        for layer in &manifest.layers {
+            if layer.media_type != SIMPLE_SIGNING_MEDIA_TYPE {
+                continue;
+            }
+            let Some(signature) = layer
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(SIGNATURE_ANNOTATION))
+            else {
+                continue;
+            };
+            let mut payload = Vec::new();
+            self.oci
+                .pull_blob(&signature_reference, &layer.digest, &mut payload)
+                .await
+                .context("cannot pull signature")?;
+            if !digest_matches(&payload, &layer.digest) {
+                continue;
+            }
+            if signature_verifies(
+                &payload,
+                signature,
+                &docker_reference(reference),
+                digest,
+                &policy.keys,
+                &policy.required_issuers,
+            ) {
+                tracing::info!("Verified signature of {reference}@{digest}");
+                return Ok(());
+            }
+        }
+
+        if !policy.required_issuers.is_empty() {
+            bail!(
+                "{reference} has no signature from a required issuer ({}) which verifies with a trusted key",
+                policy.required_issuers.join(", ")
+            );
+        }
+        bail!("The signature of {reference} does not verify with any trusted key: the application may have been tampered with")
+    }
+}
+
+// The repository named by signatures of the given reference.
+fn docker_reference(reference: &Reference) -> String {
+    format!("{}/{}", reference.registry(), reference.repository())
+}
+
+fn signature_verifies(
+    payload: &[u8],
+    signature: &str,
+    repository: &str,
+    digest: &str,
+    keys: &[TrustedKey],
+    required_issuers: &[String],
+) -> bool {
+    let Ok(signed) = serde_json::from_slice::<SimpleSigningPayload>(payload) else {
+        return false;
+    };
+    // A signature for the same content in another repository doesn't
+    // vouch for this one.
+    if signed.critical.identity.docker_reference != repository
+        || signed.critical.image.docker_manifest_digest != digest
+    {
+        return false;
+    }
+    let issuer = signed
+        .optional
+        .as_ref()
+        .and_then(|optional| optional.get(ISSUER_FIELD))
+        .and_then(|issuer| issuer.as_str());
+    // The issuer named by the signer only counts if the key is trusted for
+    // that issuer.
+    let issuer_trusted = |trusted: &TrustedKey| {
+        required_issuers.is_empty()
+            || match (trusted.issuer.as_deref(), issuer) {
+                (Some(trusted_issuer), Some(issuer)) => {
+                    trusted_issuer == issuer && required_issuers.iter().any(|r| r == issuer)
+                }
+                _ => false,
+            }
+    };
+    let Some(signature) = BASE64
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| ed25519_dalek::Signature::from_slice(&bytes).ok())
+    else {
+        return false;
+    };
+    keys.iter()
+        .filter(|trusted| issuer_trusted(trusted))
+        .any(|trusted| trusted.key.0.verify(payload, &signature).is_ok())
+}
+
+// Signatures are tagged by the digest of what they sign, with the colon,
+// which is not allowed in tags, replaced.
+fn signature_reference(reference: &Reference, digest: &str) -> Result<Reference> {
+    let tag = format!("{}.sig", digest.replace(':', "-"));
+    format!(
+        "{}/{}:{}",
+        reference.registry(),
+        reference.repository(),
+        tag
+    )
+    .parse()
+    .context("cannot construct signature reference")
+}
+
+fn read_key_file(path: &Path, label: &str) -> Result<[u8; 32]> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read key file {}", path.display()))?;
+    let encoded = text
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+    let bytes = BASE64
+        .decode(encoded.trim())
+        .ok()
+        .filter(|_| text.contains(&format!("BEGIN {label}")))
+        .with_context(|| format!("{} is not a {} file", path.display(), label.to_lowercase()))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("{} contains a key of the wrong length", path.display()))
+}
+
+// Private key files are created readable only by their owner, so the key is
+// never readable by anyone else, even briefly.
+fn write_key_file(path: &Path, label: &str, bytes: &[u8], private: bool) -> Result<()> {
+    use std::io::Write;
+    let text = format!(
+        "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+        BASE64.encode(bytes)
+    );
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    let write = || -> std::io::Result<()> {
+        let mut file = options.open(path)?;
+        #[cfg(unix)]
+        if private {
+            // The mode only applies to new files.
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(text.as_bytes())
+    };
+    write().with_context(|| format!("cannot write key file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_round_trip_and_verify_signatures() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let private_path = temp_dir.path().join("test.key");
+        let public_path = temp_dir.path().join("test.pub");
+
+        let key = SigningKey::generate();
+        key.save(&private_path).unwrap();
+        key.verifying_key().save(&public_path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&private_path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let key = SigningKey::load(&private_path).unwrap();
+        let trusted_as = |issuer: Option<&str>| {
+            vec![TrustedKey {
+                key: VerifyingKey::load(&public_path).unwrap(),
+                issuer: issuer.map(str::to_owned),
+            }]
+        };
+        let trusted = trusted_as(None);
+        assert!(VerifyingKey::load(&private_path).is_err());
+
+        let digest = "sha256:0a867093096e0ef01ef749b12b6e7a90e4952eda107f89a676eeedce63a8361f";
+        let payload = serde_json::to_vec(&SimpleSigningPayload {
+            critical: Critical {
+                identity: Identity {
+                    docker_reference: "ghcr.io/fermyon/app".to_owned(),
+                },
+                image: Image {
+                    docker_manifest_digest: digest.to_owned(),
+                },
+                signature_type: SIGNATURE_TYPE.to_owned(),
+            },
+            optional: Some(serde_json::json!({ ISSUER_FIELD: "release" })),
+        })
+        .unwrap();
+        let signature = BASE64.encode(key.0.sign(&payload).to_bytes());
+
+        assert!(signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &trusted,
+            &[]
+        ));
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            "sha256:tampered",
+            &trusted,
+            &[]
+        ));
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/attacker/app",
+            digest,
+            &trusted,
+            &[]
+        ));
+        let untrusted = vec![TrustedKey {
+            key: SigningKey::generate().verifying_key(),
+            issuer: Some("release".to_owned()),
+        }];
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &untrusted,
+            &[]
+        ));
+        // The signer names itself as the release issuer, which only counts
+        // if the key is trusted for that issuer.
+        assert!(signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &trusted_as(Some("release")),
+            &["release".to_owned()]
+        ));
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &trusted,
+            &["release".to_owned()]
+        ));
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &trusted_as(Some("ci")),
+            &["release".to_owned()]
+        ));
+        assert!(!signature_verifies(
+            &payload,
+            &signature,
+            "ghcr.io/fermyon/app",
+            digest,
+            &trusted_as(Some("security")),
+            &["security".to_owned()]
+        ));
+    }
+
+    #[test]
+    fn policy_restricts_references() {
+        let check = |policy: &TrustPolicy, reference: &str| {
+            policy.check_reference(&reference.parse().unwrap())
+        };
+        let digest = "sha256:0a867093096e0ef01ef749b12b6e7a90e4952eda107f89a676eeedce63a8361f";
+
+        let policy = TrustPolicy::new(vec![], false).with_allowed_registries(vec![
+            "ghcr.io/fermyon".to_owned(),
+            "registry.example.com".to_owned(),
+        ]);
+        assert!(check(&policy, "ghcr.io/fermyon/app:v1").is_ok());
+        assert!(check(&policy, "registry.example.com/team/app:v1").is_ok());
+        assert!(check(&policy, "ghcr.io/fermyonx/app:v1").is_err());
+        assert!(check(&policy, "docker.io/fermyon/app:v1").is_err());
+
+        let policy = TrustPolicy::new(vec![], false).with_deny_latest(true);
+        assert!(check(&policy, "ghcr.io/fermyon/app:v1").is_ok());
+        assert!(check(&policy, "ghcr.io/fermyon/app:latest").is_err());
+        assert!(check(&policy, "ghcr.io/fermyon/app").is_err());
+        assert!(check(&policy, &format!("ghcr.io/fermyon/app@{digest}")).is_ok());
+
+        let policy = TrustPolicy::new(vec![], false).with_require_digest(true);
+        assert!(check(&policy, "ghcr.io/fermyon/app:v1").is_err());
+        assert!(check(&policy, &format!("ghcr.io/fermyon/app@{digest}")).is_ok());
+    }
+
+    #[test]
+    fn signatures_are_tagged_by_digest() {
+        let reference: Reference = "ghcr.io/fermyon/app:v1".parse().unwrap();
+        let signature_reference = signature_reference(&reference, "sha256:abc123").unwrap();
+        assert_eq!("ghcr.io", signature_reference.registry());
+        assert_eq!("fermyon/app", signature_reference.repository());
+        assert_eq!(Some("sha256-abc123.sig"), signature_reference.tag());
+    }
+}
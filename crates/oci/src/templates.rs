@@ -0,0 +1,184 @@
+//! Template packs distributed through OCI registries.
+//!
+//! A template pack is an OCI artifact with a layer of media type
+//! [`TEMPLATES_LAYER_MEDIA_TYPE`]: a gzipped tarball laid out like a template
+//! repository, with each template in a subdirectory of `templates`. Pulled
+//! packs are unpacked into the cache by digest, and the digest last pulled
+//! for each tag is recorded, so that packs can be installed again without
+//! network access.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::Reference;
+use tokio::fs;
+
+use crate::{
+    client::{digest_matches, write_atomically},
+    qualify_reference, Client,
+};
+
+/// The media type of the layer containing a template pack.
+pub const TEMPLATES_LAYER_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.templates.v1.tar+gzip";
+
+const PACKS_DIR: &str = "packs";
+const REFS_DIR: &str = "refs";
+const LATEST_TAG: &str = "latest";
+
+impl Client {
+    /// Pull a template pack from an OCI registry, returning the directory
+    /// into which it was unpacked. A reference pinned to a digest which has
+    /// been pulled before is not pulled again; if the registry cannot be
+    /// reached, the pack last pulled for the reference's tag is used.
+    pub async fn pull_templates(&mut self, reference: &str) -> Result<PathBuf> {
+        let reference: Reference = qualify_reference(reference)
+            .parse()
+            .with_context(|| format!("cannot parse reference {reference}"))?;
+
+        if let Some(digest) = reference.digest() {
+            let dir = self.templates_pack_dir(digest);
+            if dir.is_dir() {
+                tracing::debug!("Using previously pulled template pack {digest}");
+                return Ok(dir);
+            }
+        }
+
+        let auth = Self::auth(&reference).await?;
+        let (manifest, digest) = match self.pull_verified_image_manifest(&reference, &auth).await {
+            Ok(pulled) => pulled,
+            Err(err) => {
+                // A pack pulled for the tag is not necessarily the pinned one.
+                let last_pulled = match reference.digest() {
+                    Some(_) => None,
+                    None => self.last_pulled_templates(&reference).await,
+                };
+                if let Some(dir) = last_pulled {
+                    tracing::warn!("Cannot pull {reference} ({err}); using the pack pulled before");
+                    return Ok(dir);
+                }
+                return Err(err).with_context(|| format!("cannot pull {reference}"));
+            }
+        };
+        let dir = self.templates_pack_dir(&digest);
+        if !dir.is_dir() {
+            let layer = manifest
+                .layers
+                .iter()
+                .find(|layer| layer.media_type == TEMPLATES_LAYER_MEDIA_TYPE)
+                .with_context(|| {
+                    format!("{reference} is not a template pack: it has no {TEMPLATES_LAYER_MEDIA_TYPE} layer")
+                })?;
+            let mut bytes = Vec::new();
+            self.oci
+                .pull_blob(&reference, &layer.digest, &mut bytes)
+                .await
+                .with_context(|| format!("cannot pull template pack layer {}", layer.digest))?;
+            if !digest_matches(&bytes, &layer.digest) {
+                bail!(
+                    "template pack layer {} does not match its digest",
+                    layer.digest
+                );
+            }
+            unpack(&bytes, &dir)?;
+        }
+
+        if let Some(tag) = reference.tag() {
+            let ref_file = self.templates_ref_file(&reference, tag);
+            if let Some(parent) = ref_file.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            write_atomically(&ref_file, digest.as_bytes()).await?;
+        }
+
+        tracing::info!("Pulled template pack {}@{}", reference, digest);
+        Ok(dir)
+    }
+
+    async fn last_pulled_templates(&self, reference: &Reference) -> Option<PathBuf> {
+        let ref_file = self.templates_ref_file(reference, reference.tag().unwrap_or(LATEST_TAG));
+        let digest = fs::read_to_string(ref_file).await.ok()?;
+        let dir = self.templates_pack_dir(digest.trim());
+        dir.is_dir().then_some(dir)
+    }
+
+    fn templates_pack_dir(&self, digest: &str) -> PathBuf {
+        // Digests contain a colon, which cannot appear in Windows paths.
+        self.cache
+            .templates_dir()
+            .join(PACKS_DIR)
+            .join(digest.replace(':', "_"))
+    }
+
+    fn templates_ref_file(&self, reference: &Reference, tag: &str) -> PathBuf {
+        ref_file(&self.cache.templates_dir().join(REFS_DIR), reference, tag)
+    }
+}
+
+// Registries may have a port, and Windows paths cannot contain its colon.
+fn ref_file(refs_dir: &Path, reference: &Reference, tag: &str) -> PathBuf {
+    let mut file = refs_dir.join(reference.registry().replace(':', "_"));
+    file.extend(reference.repository().split('/'));
+    file.push(tag);
+    file
+}
+
+// Unpacks into a temporary directory first so that a partly unpacked pack is
+// never mistaken for a pulled one.
+fn unpack(bytes: &[u8], dir: &Path) -> Result<()> {
+    let parent = dir
+        .parent()
+        .context("template pack directory has no parent")?;
+    std::fs::create_dir_all(parent)?;
+    let temp_dir = tempfile::tempdir_in(parent)?;
+    tar::Archive::new(flate2::read::GzDecoder::new(bytes))
+        .unpack(temp_dir.path())
+        .context("cannot unpack template pack")?;
+    std::fs::rename(temp_dir.into_path(), dir).context("cannot save template pack")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ref_files_have_no_colons() {
+        let reference: Reference = "localhost:5000/templates/http:v1".parse().unwrap();
+        let file = ref_file(Path::new("refs"), &reference, reference.tag().unwrap());
+        assert_eq!(
+            file,
+            Path::new("refs")
+                .join("localhost_5000")
+                .join("templates")
+                .join("http")
+                .join("v1")
+        );
+    }
+
+    #[test]
+    fn unpacks_template_pack() {
+        let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let contents = b"manifest_version = \"1\"\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tarball
+            .append_data(
+                &mut header,
+                "templates/test/metadata/spin-template.toml",
+                &contents[..],
+            )
+            .unwrap();
+        let bytes = tarball.into_inner().unwrap().finish().unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("packs").join("sha256_abc");
+        unpack(&bytes, &dir).unwrap();
+
+        let manifest = dir.join("templates/test/metadata/spin-template.toml");
+        assert_eq!(std::fs::read(manifest).unwrap(), contents);
+    }
+}
@@ -0,0 +1,827 @@
+//! Uploading applications to OCI registries.
+//!
+//! Blobs the registry already has are not uploaded again, and blobs pushed
+//! before to another repository on the same registry are mounted from there
+//! rather than uploaded. Other blobs are uploaded in chunks. The location of
+//! each upload is recorded in the cache until it completes, so that an
+//! interrupted upload resumes from the last chunk the registry received,
+//! even in a later push.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::{manifest::OciImageManifest, secrets::RegistryAuth, Reference};
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spin_loader::cache::Cache;
+
+use crate::{
+    client::write_atomically,
+    variant::{IndexEntry, Variant},
+};
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const MAX_CHUNK_ATTEMPTS: usize = 3;
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const OCI_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const DOCKER_MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+const EMPTY_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const EMPTY_CONFIG: &[u8] = b"{}";
+const CONTENT_DIGEST_HEADER: &str = "Docker-Content-Digest";
+const OCI_SUBJECT_HEADER: &str = "OCI-Subject";
+
+const PUSHED_DIR: &str = "pushed";
+
+/// Uploads blobs and manifests to one repository.
+pub(crate) struct Uploader {
+    http: reqwest::Client,
+    registry_url: Url,
+    registry: String,
+    repository: String,
+    auth: RegistryAuth,
+    authorization: Option<String>,
+    state_dir: PathBuf,
+}
+
+/// A description of a manifest, as listed in indexes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Descriptor {
+    pub media_type: String,
+    pub digest: String,
+    pub size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<HashMap<String, String>>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReferrersIndex {
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl Uploader {
+    pub(crate) fn new(
+        reference: &Reference,
+        auth: RegistryAuth,
+        insecure: bool,
+        cache: &Cache,
+    ) -> Result<Self> {
+        let scheme = if insecure { "http" } else { "https" };
+        let registry = reference.resolve_registry().to_owned();
+        let registry_url = Url::parse(&format!("{scheme}://{registry}"))
+            .with_context(|| format!("invalid registry {registry}"))?;
+        Ok(Self {
+            http: reqwest::Client::new(),
+            registry_url,
+            registry,
+            repository: reference.repository().to_owned(),
+            auth,
+            authorization: None,
+            state_dir: cache.uploads_dir(),
+        })
+    }
+
+    /// Upload a blob unless the registry already has it.
+    pub(crate) async fn push_blob(&mut self, data: &[u8], digest: &str) -> Result<()> {
+        if self.blob_exists(digest).await? {
+            tracing::debug!("Registry already has blob {digest}");
+            return Ok(());
+        }
+        if self.mount_blob(digest).await? {
+            tracing::debug!("Mounted blob {digest}");
+            self.record_pushed(digest).await;
+            return Ok(());
+        }
+
+        let (mut location, mut offset) = self.resume_or_start_upload(digest).await?;
+        while offset < data.len() {
+            let end = (offset + CHUNK_SIZE).min(data.len());
+            let mut attempt = 1;
+            loop {
+                match self
+                    .patch_chunk(&location, &data[offset..end], offset)
+                    .await
+                {
+                    Ok(next) => {
+                        location = next;
+                        offset = end;
+                        break;
+                    }
+                    Err(e) if attempt < MAX_CHUNK_ATTEMPTS => {
+                        tracing::debug!("Retrying upload of {digest} after error: {e:#}");
+                        attempt += 1;
+                        // The registry may have received part of the chunk, or
+                        // all of it, in which case there is nothing to resend.
+                        if let Some(received) = self.upload_offset(&location).await {
+                            offset = received.min(data.len());
+                        }
+                        if offset >= end {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        return Err(e).with_context(|| format!("cannot upload blob {digest}"))
+                    }
+                }
+            }
+            self.record_upload(digest, &location).await;
+        }
+
+        let mut url = self.resolve(&location)?;
+        url.query_pairs_mut().append_pair("digest", digest);
+        let response = self
+            .send(|http| http.put(url.clone()).header(header::CONTENT_LENGTH, 0))
+            .await?;
+        if response.status() != StatusCode::CREATED {
+            bail!(
+                "cannot complete upload of blob {digest}: registry returned {}",
+                response.status()
+            );
+        }
+        self.forget_upload(digest).await;
+        self.record_pushed(digest).await;
+        Ok(())
+    }
+
+    /// Upload an artifact, such as an SBOM, which refers to the given
+    /// manifest, returning the digest of the artifact's manifest. Registries
+    /// which do not index referrers themselves are given the referrers tag
+    /// schema's index instead.
+    pub(crate) async fn push_referrer(
+        &mut self,
+        subject: &Descriptor,
+        artifact_type: &str,
+        data: &[u8],
+        annotations: HashMap<String, String>,
+    ) -> Result<String> {
+        let data_digest = format!("sha256:{:x}", Sha256::digest(data));
+        let config_digest = format!("sha256:{:x}", Sha256::digest(EMPTY_CONFIG));
+        self.push_blob(data, &data_digest).await?;
+        self.push_blob(EMPTY_CONFIG, &config_digest).await?;
+
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_MANIFEST_MEDIA_TYPE,
+            "artifactType": artifact_type,
+            "config": {
+                "mediaType": EMPTY_MEDIA_TYPE,
+                "digest": config_digest,
+                "size": EMPTY_CONFIG.len(),
+            },
+            "layers": [{
+                "mediaType": artifact_type,
+                "digest": data_digest,
+                "size": data.len(),
+            }],
+            "subject": subject,
+            "annotations": annotations,
+        });
+        let body = serde_json::to_vec(&manifest)?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&body));
+        let size = body.len();
+        let response = self
+            .send_manifest(&digest, OCI_MANIFEST_MEDIA_TYPE, body)
+            .await?;
+
+        if !response.headers().contains_key(OCI_SUBJECT_HEADER) {
+            let mut index = self.referrers_tag_index(&subject.digest).await?;
+            index.manifests.push(Descriptor {
+                media_type: OCI_MANIFEST_MEDIA_TYPE.to_owned(),
+                digest: digest.clone(),
+                size,
+                artifact_type: Some(artifact_type.to_owned()),
+                annotations: Some(annotations),
+            });
+            let index = serde_json::json!({
+                "schemaVersion": 2,
+                "mediaType": OCI_INDEX_MEDIA_TYPE,
+                "manifests": index.manifests,
+            });
+            self.send_manifest(
+                &referrers_tag(&subject.digest),
+                OCI_INDEX_MEDIA_TYPE,
+                serde_json::to_vec(&index)?,
+            )
+            .await
+            .context("cannot update referrers index")?;
+        }
+        Ok(digest)
+    }
+
+    /// The artifacts which refer to the manifest with the given digest, of
+    /// the given type if one is given. Registries without the referrers API
+    /// are read through the referrers tag schema.
+    pub(crate) async fn referrers(
+        &mut self,
+        digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Descriptor>> {
+        let mut url = self.repository_url(&format!("referrers/{digest}"))?;
+        if let Some(artifact_type) = artifact_type {
+            url.query_pairs_mut()
+                .append_pair("artifactType", artifact_type);
+        }
+        let response = self
+            .send(|http| {
+                http.get(url.clone())
+                    .header(header::ACCEPT, OCI_INDEX_MEDIA_TYPE)
+            })
+            .await?;
+        let index = match response.status() {
+            StatusCode::NOT_FOUND => self.referrers_tag_index(digest).await?,
+            status if status.is_success() => serde_json::from_slice(&response.bytes().await?)
+                .context("cannot parse referrers")?,
+            status => bail!("registry returned {status} for referrers of {digest}"),
+        };
+        // Registries may ignore the type filter, so it is applied here.
+        Ok(index
+            .manifests
+            .into_iter()
+            .filter(|m| artifact_type.is_none() || m.artifact_type.as_deref() == artifact_type)
+            .collect())
+    }
+
+    /// The descriptor of the manifest with the given reference.
+    pub(crate) async fn manifest_descriptor(
+        &mut self,
+        reference: &Reference,
+    ) -> Result<Descriptor> {
+        self.find_manifest(reference)
+            .await?
+            .with_context(|| format!("manifest {} not found", tag(reference)))
+    }
+
+    /// The descriptor of the manifest with the given reference, or `None`
+    /// if the registry does not have it.
+    pub(crate) async fn find_manifest(
+        &mut self,
+        reference: &Reference,
+    ) -> Result<Option<Descriptor>> {
+        let tag = tag(reference);
+        let url = self.repository_url(&format!("manifests/{tag}"))?;
+        let accept = [
+            OCI_MANIFEST_MEDIA_TYPE,
+            OCI_INDEX_MEDIA_TYPE,
+            DOCKER_MANIFEST_MEDIA_TYPE,
+        ]
+        .join(", ");
+        let response = self
+            .send(|http| http.head(url.clone()).header(header::ACCEPT, &accept))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            bail!("registry returned {} for manifest {tag}", response.status());
+        }
+        let headers = response.headers();
+        let header_str = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let digest = match (header_str(CONTENT_DIGEST_HEADER), reference.digest()) {
+            (Some(digest), _) | (None, Some(digest)) => digest.to_owned(),
+            (None, None) => bail!("registry did not return the digest of {tag}"),
+        };
+        let size = header_str(header::CONTENT_LENGTH.as_str())
+            .and_then(|length| length.parse().ok())
+            .context("registry did not return the size of the manifest")?;
+        Ok(Some(Descriptor {
+            media_type: header_str(header::CONTENT_TYPE.as_str())
+                .unwrap_or(OCI_MANIFEST_MEDIA_TYPE)
+                .to_owned(),
+            digest,
+            size,
+            artifact_type: None,
+            annotations: None,
+        }))
+    }
+
+    /// Upload a manifest, returning the URL at which the registry stored it.
+    pub(crate) async fn push_manifest(
+        &mut self,
+        reference: &Reference,
+        manifest: &OciImageManifest,
+    ) -> Result<String> {
+        let media_type = manifest_media_type(manifest);
+        self.put_manifest(tag(reference), &media_type, serde_json::to_vec(manifest)?)
+            .await
+    }
+
+    /// Upload the manifest of one variant of an application, untagged,
+    /// returning the entry for it in the application's index.
+    pub(crate) async fn push_variant_manifest(
+        &mut self,
+        manifest: &OciImageManifest,
+        variant: &Variant,
+    ) -> Result<IndexEntry> {
+        let media_type = manifest_media_type(manifest);
+        let body = serde_json::to_vec(manifest)?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&body));
+        let size = body.len();
+        self.put_manifest(&digest, &media_type, body).await?;
+        Ok(IndexEntry::new(&media_type, digest, size, variant))
+    }
+
+    /// Upload an index of the given variants, returning the URL at which the
+    /// registry stored it.
+    pub(crate) async fn push_index(
+        &mut self,
+        reference: &Reference,
+        manifests: Vec<IndexEntry>,
+    ) -> Result<String> {
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": OCI_INDEX_MEDIA_TYPE,
+            "manifests": manifests,
+        });
+        self.put_manifest(
+            tag(reference),
+            OCI_INDEX_MEDIA_TYPE,
+            serde_json::to_vec(&index)?,
+        )
+        .await
+    }
+
+    async fn put_manifest(&mut self, tag: &str, media_type: &str, body: Vec<u8>) -> Result<String> {
+        let response = self.send_manifest(tag, media_type, body).await?;
+        match response.headers().get(header::LOCATION) {
+            Some(location) => Ok(self.resolve(location.to_str()?)?.to_string()),
+            None => Ok(self
+                .repository_url(&format!("manifests/{tag}"))?
+                .to_string()),
+        }
+    }
+
+    async fn send_manifest(
+        &mut self,
+        tag: &str,
+        media_type: &str,
+        body: Vec<u8>,
+    ) -> Result<Response> {
+        let url = self.repository_url(&format!("manifests/{tag}"))?;
+        let response = self
+            .send(|http| {
+                http.put(url.clone())
+                    .header(header::CONTENT_TYPE, media_type)
+                    .body(body.clone())
+            })
+            .await?;
+        if response.status() != StatusCode::CREATED {
+            bail!("registry returned {} for manifest", response.status());
+        }
+        Ok(response)
+    }
+
+    // Registries without the referrers API list referrers in an index
+    // tagged with the subject's digest.
+    async fn referrers_tag_index(&mut self, digest: &str) -> Result<ReferrersIndex> {
+        let url = self.repository_url(&format!("manifests/{}", referrers_tag(digest)))?;
+        let response = self
+            .send(|http| {
+                http.get(url.clone())
+                    .header(header::ACCEPT, OCI_INDEX_MEDIA_TYPE)
+            })
+            .await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(ReferrersIndex::default()),
+            status if status.is_success() => serde_json::from_slice(&response.bytes().await?)
+                .context("cannot parse referrers index"),
+            status => bail!("registry returned {status} for referrers of {digest}"),
+        }
+    }
+
+    async fn blob_exists(&mut self, digest: &str) -> Result<bool> {
+        let url = self.repository_url(&format!("blobs/{digest}"))?;
+        let response = self.send(|http| http.head(url.clone())).await?;
+        Ok(response.status().is_success())
+    }
+
+    // Registries may mount a blob from another repository which the user
+    // can read, rather than have it uploaded again.
+    async fn mount_blob(&mut self, digest: &str) -> Result<bool> {
+        let Some(from) = self.pushed_from(digest).await else {
+            return Ok(false);
+        };
+        if from == self.repository {
+            return Ok(false);
+        }
+        let mut url = self.repository_url("blobs/uploads/")?;
+        url.query_pairs_mut()
+            .append_pair("mount", digest)
+            .append_pair("from", &from);
+        let response = self.send(|http| http.post(url.clone())).await?;
+        Ok(response.status() == StatusCode::CREATED)
+    }
+
+    async fn resume_or_start_upload(&mut self, digest: &str) -> Result<(String, usize)> {
+        if let Ok(location) = tokio::fs::read_to_string(self.upload_file(digest)).await {
+            if let Some(offset) = self.upload_offset(&location).await {
+                tracing::debug!("Resuming upload of {digest} from byte {offset}");
+                return Ok((location, offset));
+            }
+        }
+
+        let url = self.repository_url("blobs/uploads/")?;
+        let response = self
+            .send(|http| http.post(url.clone()).header(header::CONTENT_LENGTH, 0))
+            .await?;
+        if response.status() != StatusCode::ACCEPTED {
+            bail!(
+                "cannot start upload of blob {digest}: registry returned {}",
+                response.status()
+            );
+        }
+        let location = location(&response)?;
+        self.record_upload(digest, &location).await;
+        Ok((location, 0))
+    }
+
+    async fn patch_chunk(&mut self, location: &str, chunk: &[u8], offset: usize) -> Result<String> {
+        let url = self.resolve(location)?;
+        let range = format!("{}-{}", offset, offset + chunk.len() - 1);
+        let response = self
+            .send(|http| {
+                http.request(Method::PATCH, url.clone())
+                    .header(header::CONTENT_TYPE, "application/octet-stream")
+                    .header(header::CONTENT_RANGE, &range)
+                    .body(chunk.to_vec())
+            })
+            .await?;
+        if response.status() != StatusCode::ACCEPTED {
+            bail!("registry returned {} for chunk {range}", response.status());
+        }
+        location(&response)
+    }
+
+    // The number of bytes the registry has received for an upload, or None
+    // if the upload no longer exists.
+    async fn upload_offset(&mut self, location: &str) -> Option<usize> {
+        let url = self.resolve(location).ok()?;
+        let response = self.send(|http| http.get(url.clone())).await.ok()?;
+        if response.status() != StatusCode::NO_CONTENT {
+            return None;
+        }
+        match response.headers().get(header::RANGE) {
+            // The range is inclusive, e.g. `0-1023` after 1024 bytes.
+            Some(range) => {
+                let (_, end) = range.to_str().ok()?.split_once('-')?;
+                end.parse::<usize>().ok().map(|end| end + 1)
+            }
+            None => Some(0),
+        }
+    }
+
+    // Sends a request, authenticating as the registry asks if it refuses
+    // the request.
+    async fn send(
+        &mut self,
+        request: impl Fn(&reqwest::Client) -> RequestBuilder,
+    ) -> Result<Response> {
+        let response = self.authorize(request(&self.http)).send().await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|c| c.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        self.authenticate(&challenge).await?;
+        Ok(self.authorize(request(&self.http)).send().await?)
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.authorization {
+            Some(authorization) => request.header(header::AUTHORIZATION, authorization),
+            None => request,
+        }
+    }
+
+    async fn authenticate(&mut self, challenge: &str) -> Result<()> {
+        let (scheme, params) = challenge.split_once(' ').unwrap_or((challenge, ""));
+        if scheme.eq_ignore_ascii_case("basic") {
+            let RegistryAuth::Basic(username, password) = &self.auth else {
+                bail!("the registry requires credentials: run `spin registry login`");
+            };
+            let credentials = base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                format!("{username}:{password}"),
+            );
+            self.authorization = Some(format!("Basic {credentials}"));
+            return Ok(());
+        }
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            bail!("unsupported registry authentication scheme {scheme:?}");
+        }
+
+        let params = challenge_params(params);
+        let realm = params
+            .iter()
+            .find(|(k, _)| k == "realm")
+            .map(|(_, v)| v.as_str())
+            .context("registry authentication challenge has no realm")?;
+        let mut url = Url::parse(realm).context("invalid registry authentication realm")?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some((_, service)) = params.iter().find(|(k, _)| k == "service") {
+                query.append_pair("service", service);
+            }
+            query.append_pair(
+                "scope",
+                &format!("repository:{}:pull,push", self.repository),
+            );
+        }
+        let mut request = self.http.get(url);
+        if let RegistryAuth::Basic(username, password) = &self.auth {
+            request = request.basic_auth(username, Some(password));
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            bail!(
+                "cannot authenticate with the registry: it returned {}",
+                response.status()
+            );
+        }
+        let token: TokenResponse = serde_json::from_slice(&response.bytes().await?)
+            .context("cannot parse registry authentication token")?;
+        let token = token
+            .token
+            .or(token.access_token)
+            .context("registry returned no authentication token")?;
+        self.authorization = Some(format!("Bearer {token}"));
+        Ok(())
+    }
+
+    fn repository_url(&self, path: &str) -> Result<Url> {
+        Ok(self
+            .registry_url
+            .join(&format!("/v2/{}/{path}", self.repository))?)
+    }
+
+    // Upload locations may be relative to the registry.
+    fn resolve(&self, location: &str) -> Result<Url> {
+        self.registry_url
+            .join(location)
+            .with_context(|| format!("invalid upload location {location}"))
+    }
+
+    // Records are best effort: failing to write one only loses the chance
+    // to resume or mount later.
+    async fn record_upload(&self, digest: &str, location: &str) {
+        let path = self.upload_file(digest);
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let _ = write_atomically(&path, location.as_bytes()).await;
+    }
+
+    async fn forget_upload(&self, digest: &str) {
+        let _ = tokio::fs::remove_file(self.upload_file(digest)).await;
+    }
+
+    async fn record_pushed(&self, digest: &str) {
+        let path = self.pushed_file(digest);
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let _ = write_atomically(&path, self.repository.as_bytes()).await;
+    }
+
+    async fn pushed_from(&self, digest: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.pushed_file(digest))
+            .await
+            .ok()
+    }
+
+    fn upload_file(&self, digest: &str) -> PathBuf {
+        self.state_dir
+            .join(&self.registry)
+            .join(&self.repository)
+            .join(digest.replace(':', "_"))
+    }
+
+    fn pushed_file(&self, digest: &str) -> PathBuf {
+        self.state_dir
+            .join(PUSHED_DIR)
+            .join(&self.registry)
+            .join(digest.replace(':', "_"))
+    }
+}
+
+fn tag(reference: &Reference) -> &str {
+    reference
+        .digest()
+        .or_else(|| reference.tag())
+        .unwrap_or("latest")
+}
+
+fn referrers_tag(digest: &str) -> String {
+    digest.replace(':', "-")
+}
+
+fn manifest_media_type(manifest: &OciImageManifest) -> String {
+    manifest
+        .media_type
+        .clone()
+        .unwrap_or_else(|| OCI_MANIFEST_MEDIA_TYPE.to_owned())
+}
+
+fn location(response: &Response) -> Result<String> {
+    Ok(response
+        .headers()
+        .get(header::LOCATION)
+        .context("registry did not return an upload location")?
+        .to_str()?
+        .to_owned())
+}
+
+// Parses the parameters of a `WWW-Authenticate` challenge, such as
+// `realm="https://auth.example.com/token",service="registry.example.com"`.
+fn challenge_params(params: &str) -> Vec<(String, String)> {
+    let mut parsed = vec![];
+    let mut rest = params.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_owned();
+        let value = value.trim_start();
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, remainder)) => (value, remainder),
+                None => (quoted, ""),
+            },
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        parsed.push((key, value.to_owned()));
+        rest = remainder.trim_start_matches(',').trim();
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    const UPLOAD_LOCATION: &str = "/v2/app/blobs/uploads/1";
+
+    // A registry which loses its response to the first chunk uploaded,
+    // having kept `kept` bytes of it.
+    #[derive(Default)]
+    struct FlakyRegistry {
+        kept: Option<usize>,
+        received: Vec<u8>,
+        chunk_sizes: Vec<usize>,
+        completed: bool,
+    }
+
+    impl FlakyRegistry {
+        fn respond(&mut self, method: &str, path: &str, body: &[u8]) -> String {
+            match (method, path) {
+                ("HEAD", _) => status_line("404 Not Found", ""),
+                ("POST", _) => status_line("202 Accepted", UPLOAD_LOCATION),
+                ("PATCH", _) => {
+                    self.chunk_sizes.push(body.len());
+                    match self.kept.take() {
+                        Some(kept) => {
+                            self.received.extend_from_slice(&body[..kept]);
+                            status_line("500 Internal Server Error", "")
+                        }
+                        None => {
+                            self.received.extend_from_slice(body);
+                            status_line("202 Accepted", UPLOAD_LOCATION)
+                        }
+                    }
+                }
+                ("GET", _) => format!(
+                    "HTTP/1.1 204 No Content\r\nrange: 0-{}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    self.received.len() - 1
+                ),
+                ("PUT", _) => {
+                    self.completed = true;
+                    status_line("201 Created", "")
+                }
+                _ => status_line("400 Bad Request", ""),
+            }
+        }
+    }
+
+    fn status_line(status: &str, location: &str) -> String {
+        let location = match location {
+            "" => String::new(),
+            location => format!("location: {location}\r\n"),
+        };
+        format!("HTTP/1.1 {status}\r\n{location}content-length: 0\r\nconnection: close\r\n\r\n")
+    }
+
+    async fn serve(listener: TcpListener, registry: Arc<Mutex<FlakyRegistry>>) {
+        loop {
+            let (mut connection, _) = listener.accept().await.unwrap();
+            let (method, path, body) = read_request(&mut connection).await;
+            let response = registry.lock().unwrap().respond(&method, &path, &body);
+            connection.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    async fn read_request(connection: &mut TcpStream) -> (String, String, Vec<u8>) {
+        let mut request = vec![];
+        let mut buf = [0; 4096];
+        let headers_end = loop {
+            let read = connection.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..headers_end]).to_lowercase();
+        let content_length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: "))
+            .map_or(0, |length| length.trim().parse().unwrap());
+        while request.len() < headers_end + content_length {
+            let read = connection.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+        }
+        let mut request_line = head.split_whitespace();
+        let method = request_line.next().unwrap().to_uppercase();
+        let path = request_line.next().unwrap().to_owned();
+        (method, path, request[headers_end..].to_vec())
+    }
+
+    async fn push_to_flaky_registry(data: &[u8], kept: usize) -> FlakyRegistry {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reference: Reference = format!("{}/app:v1", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let registry = Arc::new(Mutex::new(FlakyRegistry {
+            kept: Some(kept),
+            ..Default::default()
+        }));
+        let server = tokio::spawn(serve(listener, registry.clone()));
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(Some(cache_dir.path().to_owned())).await.unwrap();
+        let mut uploader =
+            Uploader::new(&reference, RegistryAuth::Anonymous, true, &cache).unwrap();
+        let digest = format!("sha256:{:x}", Sha256::digest(data));
+        uploader.push_blob(data, &digest).await.unwrap();
+        server.abort();
+
+        let mut received = registry.lock().unwrap();
+        std::mem::take(&mut *received)
+    }
+
+    #[tokio::test]
+    async fn resumes_partly_received_chunk() {
+        let data = b"hello, world";
+        let registry = push_to_flaky_registry(data, 5).await;
+        assert_eq!(registry.received, data);
+        assert_eq!(registry.chunk_sizes, [data.len(), data.len() - 5]);
+        assert!(registry.completed);
+    }
+
+    #[tokio::test]
+    async fn does_not_resend_fully_received_chunk() {
+        let data = b"hello, world";
+        let registry = push_to_flaky_registry(data, data.len()).await;
+        assert_eq!(registry.received, data);
+        assert_eq!(registry.chunk_sizes, [data.len()]);
+        assert!(registry.completed);
+    }
+
+    #[test]
+    fn parses_challenge_params() {
+        let params = challenge_params(
+            r#"realm="https://ghcr.io/token",service="ghcr.io",scope="repository:fermyon/app:pull""#,
+        );
+        assert_eq!(
+            params,
+            [
+                ("realm".to_owned(), "https://ghcr.io/token".to_owned()),
+                ("service".to_owned(), "ghcr.io".to_owned()),
+                ("scope".to_owned(), "repository:fermyon/app:pull".to_owned()),
+            ]
+        );
+        assert_eq!(
+            challenge_params("realm=unquoted, service=x"),
+            [
+                ("realm".to_owned(), "unquoted".to_owned()),
+                ("service".to_owned(), "x".to_owned()),
+            ]
+        );
+    }
+}
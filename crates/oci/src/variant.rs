@@ -0,0 +1,217 @@
+//! Variants of an application published together in an OCI image index.
+//!
+//! Each variant is either for a platform, such as `linux/arm64`, for apps
+//! whose components differ by the architecture of their native
+//! dependencies, or named, such as `production`, for other differences. When
+//! pulling an index, the requested variant is chosen; without a request, the
+//! variant for the host platform, or else the first variant, is.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use oci_distribution::manifest::{ImageIndexEntry, OciImageIndex};
+use serde::Serialize;
+
+/// The annotation naming a variant in an image index.
+pub(crate) const VARIANT_ANNOTATION: &str = "dev.fermyon.spin.variant";
+
+/// A variant of an application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// The variant for an operating system and architecture, as named in
+    /// OCI platforms, e.g. `linux` and `amd64`.
+    Platform {
+        /// The operating system.
+        os: String,
+        /// The CPU architecture.
+        architecture: String,
+    },
+    /// A variant with a name.
+    Named(String),
+}
+
+impl Variant {
+    /// The variant for the platform Spin is running on.
+    pub fn host() -> Self {
+        let architecture = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            "x86" => "386",
+            other => other,
+        };
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            other => other,
+        };
+        Self::Platform {
+            os: os.to_owned(),
+            architecture: architecture.to_owned(),
+        }
+    }
+
+    fn matches(&self, entry: &ImageIndexEntry) -> bool {
+        match self {
+            Self::Platform { os, architecture } => entry
+                .platform
+                .as_ref()
+                .map_or(false, |p| &p.os == os && &p.architecture == architecture),
+            Self::Named(name) => {
+                entry
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(VARIANT_ANNOTATION))
+                    == Some(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('/') {
+            Some((os, architecture)) if !os.is_empty() && !architecture.is_empty() => {
+                Ok(Self::Platform {
+                    os: os.to_owned(),
+                    architecture: architecture.to_owned(),
+                })
+            }
+            Some(_) => bail!("Invalid platform {s:?}: expected `<os>/<architecture>`"),
+            None if s.is_empty() => bail!("Variant name cannot be empty"),
+            None => Ok(Self::Named(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Platform { os, architecture } => write!(f, "{os}/{architecture}"),
+            Self::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Chooses the entry of an index for the requested variant, or the default
+/// variant if none is requested.
+pub(crate) fn select<'a>(
+    index: &'a OciImageIndex,
+    requested: Option<&Variant>,
+) -> Result<&'a ImageIndexEntry> {
+    match requested {
+        Some(variant) => index
+            .manifests
+            .iter()
+            .find(|entry| variant.matches(entry))
+            .with_context(|| {
+                format!(
+                    "The application has no variant {variant}. Available variants: {}",
+                    available(index)
+                )
+            }),
+        None => {
+            let host = Variant::host();
+            index
+                .manifests
+                .iter()
+                .find(|entry| host.matches(entry))
+                .or_else(|| index.manifests.first())
+                .context("The application index contains no variants")
+        }
+    }
+}
+
+fn available(index: &OciImageIndex) -> String {
+    index
+        .manifests
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(VARIANT_ANNOTATION).cloned())
+                .or_else(|| {
+                    entry
+                        .platform
+                        .as_ref()
+                        .map(|p| format!("{}/{}", p.os, p.architecture))
+                })
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An entry of an image index being pushed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IndexEntry {
+    pub media_type: String,
+    pub digest: String,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<IndexPlatform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<std::collections::HashMap<String, String>>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct IndexPlatform {
+    pub architecture: String,
+    pub os: String,
+}
+
+impl IndexEntry {
+    pub(crate) fn new(media_type: &str, digest: String, size: usize, variant: &Variant) -> Self {
+        let (platform, annotations) = match variant {
+            Variant::Platform { os, architecture } => (
+                Some(IndexPlatform {
+                    architecture: architecture.clone(),
+                    os: os.clone(),
+                }),
+                None,
+            ),
+            Variant::Named(name) => (
+                None,
+                Some(
+                    [(VARIANT_ANNOTATION.to_owned(), name.clone())]
+                        .into_iter()
+                        .collect(),
+                ),
+            ),
+        };
+        Self {
+            media_type: media_type.to_owned(),
+            digest,
+            size,
+            platform,
+            annotations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_variants() {
+        assert_eq!(
+            Variant::Platform {
+                os: "linux".to_owned(),
+                architecture: "arm64".to_owned()
+            },
+            "linux/arm64".parse().unwrap()
+        );
+        assert_eq!(
+            Variant::Named("production".to_owned()),
+            "production".parse().unwrap()
+        );
+        assert!("linux/".parse::<Variant>().is_err());
+        assert!("".parse::<Variant>().is_err());
+        assert_eq!(
+            "linux/arm64",
+            "linux/arm64".parse::<Variant>().unwrap().to_string()
+        );
+    }
+}
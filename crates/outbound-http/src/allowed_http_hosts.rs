@@ -1,5 +1,8 @@
+use std::net::IpAddr;
+
 use anyhow::{anyhow, Result};
 use reqwest::Url;
+use url::Host;
 
 const ALLOW_ALL_HOSTS: &str = "insecure:allow-all";
 
@@ -31,39 +34,119 @@ impl AllowedHttpHosts {
 /// An HTTP host allow-list entry.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct AllowedHttpHost {
-    domain: String,
-    port: Option<u16>,
+    host: HostMatcher,
+    port: PortMatcher,
+}
+
+/// The hosts matched by an allow-list entry.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum HostMatcher {
+    /// A single host name or IP address.
+    Name(String),
+    /// Any IP address within a CIDR block.
+    Cidr(IpCidr),
+}
+
+impl Default for HostMatcher {
+    fn default() -> Self {
+        Self::Name(String::new())
+    }
+}
+
+/// The ports matched by an allow-list entry.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+enum PortMatcher {
+    /// Only URLs without an explicit port.
+    #[default]
+    Default,
+    /// Only URLs with exactly this port.
+    Port(u16),
+    /// Any port in the inclusive range, including the scheme's default port.
+    Range(u16, u16),
+    /// Any port.
+    Any,
 }
 
 impl AllowedHttpHost {
     /// Creates a new allow-list entry.
     pub fn new(name: impl Into<String>, port: Option<u16>) -> Self {
         Self {
-            domain: name.into(),
-            port,
+            host: HostMatcher::Name(name.into()),
+            port: port.map(PortMatcher::Port).unwrap_or_default(),
         }
     }
 
     /// An allow-list entry that specifies a host and allows the default port.
     pub fn host(name: impl Into<String>) -> Self {
-        Self {
-            domain: name.into(),
-            port: None,
-        }
+        Self::new(name, None)
     }
 
     /// An allow-list entry that specifies a host and port.
     pub fn host_and_port(name: impl Into<String>, port: u16) -> Self {
-        Self {
-            domain: name.into(),
-            port: Some(port),
-        }
+        Self::new(name, Some(port))
     }
 
     fn allow(&self, url: &url::Url) -> bool {
         (url.scheme() == "http" || url.scheme() == "https")
-            && self.domain == url.host_str().unwrap_or_default()
-            && self.port == url.port()
+            && self.allow_host(url)
+            && self.allow_port(url)
+    }
+
+    fn allow_host(&self, url: &url::Url) -> bool {
+        match &self.host {
+            HostMatcher::Name(domain) => domain == url.host_str().unwrap_or_default(),
+            HostMatcher::Cidr(cidr) => match url.host() {
+                Some(Host::Ipv4(addr)) => cidr.contains(IpAddr::V4(addr)),
+                Some(Host::Ipv6(addr)) => cidr.contains(IpAddr::V6(addr)),
+                _ => false,
+            },
+        }
+    }
+
+    fn allow_port(&self, url: &url::Url) -> bool {
+        match self.port {
+            PortMatcher::Default => url.port().is_none(),
+            PortMatcher::Port(port) => url.port() == Some(port),
+            PortMatcher::Range(low, high) => url
+                .port_or_known_default()
+                .map_or(false, |port| (low..=high).contains(&port)),
+            PortMatcher::Any => true,
+        }
+    }
+}
+
+/// An IP address block in CIDR notation, e.g. `10.0.0.0/8`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    fn parse(text: &str) -> Option<Self> {
+        let (addr, prefix_len) = text.split_once('/')?;
+        let addr: IpAddr = addr.parse().ok()?;
+        let prefix_len: u8 = prefix_len.parse().ok()?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        (prefix_len <= max_len).then_some(Self { addr, prefix_len })
+    }
+
+    fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
     }
 }
 
@@ -100,6 +183,9 @@ pub fn parse_allowed_http_hosts(raw: &Option<Vec<String>>) -> Result<AllowedHttp
 }
 
 fn parse_allowed_http_host(text: &str) -> Result<AllowedHttpHost, String> {
+    if let Some(host) = parse_allowed_http_host_pattern(text)? {
+        return Ok(host);
+    }
     // If you call Url::parse, it accepts things like `localhost:3001`, inferring
     // `localhost` as a scheme. That's unhelpful for us, so we do a crude check
     // before trying to treat the string as a URL.
@@ -110,6 +196,68 @@ fn parse_allowed_http_host(text: &str) -> Result<AllowedHttpHost, String> {
     }
 }
 
+// Parses entries with a CIDR block (`10.0.0.0/8`, `[fd00::/8]`) or a port
+// wildcard or range (`host:*`, `host:8000-8999`). These aren't valid URLs, so
+// they can't go through the URL-based parsing. Returns `None` for entries
+// which don't use either, so that they are parsed as ordinary hosts.
+fn parse_allowed_http_host_pattern(text: &str) -> Result<Option<AllowedHttpHost>, String> {
+    let rest = match text.split_once("://") {
+        Some(("http" | "https", rest)) => rest,
+        Some(_) => return Ok(None),
+        None => text,
+    };
+    let rest = rest.strip_suffix('/').unwrap_or(rest);
+
+    // IPv6 addresses must be bracketed so that the port separator is unambiguous
+    let port_sep = if rest.starts_with('[') {
+        rest.find("]:").map(|i| i + 1)
+    } else {
+        rest.rfind(':')
+    };
+    let (host_text, port_text) = match port_sep {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    let unbracketed = host_text
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host_text);
+
+    let cidr = IpCidr::parse(unbracketed);
+    let port_pattern = port_text.filter(|p| *p == "*" || p.contains('-'));
+    if cidr.is_none() && port_pattern.is_none() {
+        return Ok(None);
+    }
+
+    let host = match cidr {
+        Some(cidr) => HostMatcher::Cidr(cidr),
+        None => {
+            let parsed = parse_allowed_http_host_from_unschemed(host_text)?;
+            if parsed.port != PortMatcher::Default {
+                return Err(format!("{} isn't a valid host or host:port string", text));
+            }
+            parsed.host
+        }
+    };
+    let port = match port_text {
+        None => PortMatcher::Default,
+        Some("*") => PortMatcher::Any,
+        Some(port_text) => parse_port_range(port_text)
+            .ok_or_else(|| format!("{} has an invalid port or port range", text))?,
+    };
+    Ok(Some(AllowedHttpHost { host, port }))
+}
+
+fn parse_port_range(text: &str) -> Option<PortMatcher> {
+    match text.split_once('-') {
+        Some((low, high)) => {
+            let (low, high) = (low.parse().ok()?, high.parse().ok()?);
+            (low <= high).then_some(PortMatcher::Range(low, high))
+        }
+        None => text.parse().ok().map(PortMatcher::Port),
+    }
+}
+
 fn parse_allowed_http_host_from_unschemed(text: &str) -> Result<AllowedHttpHost, String> {
     // Host name parsing is quite hairy (thanks, IPv6), so punt it off to the
     // Url type which gets paid big bucks to do it properly. (But preserve the
@@ -303,4 +451,45 @@ mod test {
         assert!(!allowed.allow(&Url::parse("http://example.com/").unwrap()));
         assert!(!allowed.allow(&Url::parse("http://google.com/").unwrap()));
     }
+
+    #[test]
+    fn test_allowed_hosts_accepts_port_wildcards_and_ranges() {
+        let allowed = parse_allowed_http_hosts(&to_vec_owned(&[
+            "spin.fermyon.dev:*",
+            "http://example.com:8000-8999",
+        ]))
+        .unwrap();
+        assert!(allowed.allow(&Url::parse("https://spin.fermyon.dev/").unwrap()));
+        assert!(allowed.allow(&Url::parse("https://spin.fermyon.dev:1234/").unwrap()));
+        assert!(allowed.allow(&Url::parse("http://example.com:8000/").unwrap()));
+        assert!(allowed.allow(&Url::parse("http://example.com:8999/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://example.com:9000/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://example.com/").unwrap()));
+    }
+
+    #[test]
+    fn test_allowed_hosts_accepts_cidr_blocks() {
+        let allowed = parse_allowed_http_hosts(&to_vec_owned(&[
+            "10.0.0.0/8",
+            "http://192.168.1.0/24:*",
+            "[fd00::/8]:8080",
+        ]))
+        .unwrap();
+        assert!(allowed.allow(&Url::parse("http://10.1.2.3/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://10.1.2.3:8080/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://11.0.0.1/").unwrap()));
+        assert!(allowed.allow(&Url::parse("http://192.168.1.200:3000/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://192.168.2.1:3000/").unwrap()));
+        assert!(allowed.allow(&Url::parse("http://[fd12::1]:8080/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://[fe80::1]:8080/").unwrap()));
+        assert!(!allowed.allow(&Url::parse("http://example.com/").unwrap()));
+    }
+
+    #[test]
+    fn test_allowed_hosts_rejects_invalid_patterns() {
+        assert!(parse_allowed_http_host("10.0.0.0/33").is_err());
+        assert!(parse_allowed_http_host("example.com:9000-8000").is_err());
+        assert!(parse_allowed_http_host("example.com:80-x").is_err());
+        assert!(parse_allowed_http_host("example.com:80:*").is_err());
+    }
 }
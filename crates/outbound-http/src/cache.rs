@@ -0,0 +1,407 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use spin_core::async_trait;
+use spin_key_value::StoreManager;
+
+const KEY_VALUE_PREFIX: &str = "spin-outbound-http-cache:";
+
+/// Where cached outbound HTTP responses are stored.
+#[derive(Clone)]
+pub enum ResponseCacheConfig {
+    /// An in-memory cache holding up to `max_entries` responses.
+    Memory { max_entries: NonZeroUsize },
+    /// A key-value store, allowing cached responses to be shared between
+    /// instances and to survive restarts.
+    KeyValue {
+        manager: Arc<dyn StoreManager>,
+        store: String,
+    },
+}
+
+impl std::fmt::Debug for ResponseCacheConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Memory { max_entries } => f
+                .debug_struct("Memory")
+                .field("max_entries", max_entries)
+                .finish(),
+            Self::KeyValue { store, .. } => {
+                f.debug_struct("KeyValue").field("store", store).finish()
+            }
+        }
+    }
+}
+
+impl ResponseCacheConfig {
+    pub(crate) fn build(&self) -> ResponseCache {
+        let store: Box<dyn CacheStore> = match self {
+            Self::Memory { max_entries } => {
+                Box::new(MemoryCacheStore(Mutex::new(LruCache::new(*max_entries))))
+            }
+            Self::KeyValue { manager, store } => Box::new(KeyValueCacheStore {
+                manager: manager.clone(),
+                store: store.clone(),
+            }),
+        };
+        ResponseCache { store }
+    }
+}
+
+/// A shared cache of responses to outbound `GET` requests, following the
+/// `Cache-Control`, `ETag` and `Last-Modified` response headers.
+pub(crate) struct ResponseCache {
+    store: Box<dyn CacheStore>,
+}
+
+impl std::fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseCache").finish_non_exhaustive()
+    }
+}
+
+/// The outcome of looking up a request in the cache.
+pub(crate) enum Lookup {
+    /// A fresh response which can be returned without contacting the server.
+    Fresh(CachedResponse),
+    /// A stale response which must be revalidated using the given headers.
+    Stale(CachedResponse, HeaderMap),
+    /// No usable cached response.
+    Miss,
+}
+
+impl ResponseCache {
+    pub async fn lookup(&self, url: &str, request_headers: &HeaderMap) -> Lookup {
+        if has_directive(request_headers, "no-store") || has_credentials(request_headers) {
+            return Lookup::Miss;
+        }
+        let Some(cached) = self.store.get(url).await else {
+            return Lookup::Miss;
+        };
+        if cached.is_fresh() && !has_directive(request_headers, "no-cache") {
+            return Lookup::Fresh(cached);
+        }
+        let mut conditional = HeaderMap::new();
+        if let Some(etag) = cached.etag.as_deref().and_then(|v| v.parse().ok()) {
+            conditional.insert(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(modified) = cached.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+            conditional.insert(header::IF_MODIFIED_SINCE, modified);
+        }
+        if conditional.is_empty() {
+            Lookup::Miss
+        } else {
+            Lookup::Stale(cached, conditional)
+        }
+    }
+
+    /// Stores a response, if its headers allow it to be cached.
+    pub async fn store(
+        &self,
+        url: &str,
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) {
+        if let Some(cached) = CachedResponse::new(request_headers, status, headers, body) {
+            self.store.set(url, &cached).await;
+        }
+    }
+
+    /// Refreshes a stale response after the server confirmed that it is
+    /// unchanged, returning the response to use.
+    pub async fn revalidated(
+        &self,
+        url: &str,
+        mut cached: CachedResponse,
+        headers: &HeaderMap,
+    ) -> CachedResponse {
+        cached.stored_at = now_secs();
+        cached.max_age = freshness_lifetime(headers).unwrap_or(cached.max_age);
+        self.store.set(url, &cached).await;
+        cached
+    }
+}
+
+/// A response held in the cache.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct CachedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    stored_at: u64,
+    max_age: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CachedResponse {
+    fn new(
+        request_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Option<Self> {
+        if status != StatusCode::OK
+            || has_directive(request_headers, "no-store")
+            || has_credentials(request_headers)
+            || has_directive(headers, "no-store")
+            || has_directive(headers, "private")
+            || headers.contains_key(header::VARY)
+        {
+            return None;
+        }
+        let etag = header_string(headers, header::ETAG);
+        let last_modified = header_string(headers, header::LAST_MODIFIED);
+        let max_age = if has_directive(headers, "no-cache") {
+            0
+        } else {
+            freshness_lifetime(headers).unwrap_or(0)
+        };
+        // A response that is never fresh is only useful if it can be revalidated
+        if max_age == 0 && etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        let headers = headers
+            .iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_owned())))
+            .collect();
+        Some(Self {
+            status: status.as_u16(),
+            headers,
+            body: body.to_vec(),
+            stored_at: now_secs(),
+            max_age,
+            etag,
+            last_modified,
+        })
+    }
+
+    fn is_fresh(&self) -> bool {
+        now_secs() < self.stored_at.saturating_add(self.max_age)
+    }
+}
+
+#[async_trait]
+trait CacheStore: Send + Sync {
+    async fn get(&self, url: &str) -> Option<CachedResponse>;
+    async fn set(&self, url: &str, response: &CachedResponse);
+}
+
+struct MemoryCacheStore(Mutex<LruCache<String, CachedResponse>>);
+
+#[async_trait]
+impl CacheStore for MemoryCacheStore {
+    async fn get(&self, url: &str) -> Option<CachedResponse> {
+        self.0.lock().unwrap().get(url).cloned()
+    }
+
+    async fn set(&self, url: &str, response: &CachedResponse) {
+        self.0.lock().unwrap().put(url.to_owned(), response.clone());
+    }
+}
+
+struct KeyValueCacheStore {
+    manager: Arc<dyn StoreManager>,
+    store: String,
+}
+
+// Key-value errors are logged rather than failing the request; the cache is
+// only an optimization.
+#[async_trait]
+impl CacheStore for KeyValueCacheStore {
+    async fn get(&self, url: &str) -> Option<CachedResponse> {
+        let store = self.manager.get(&self.store).await.ok()?;
+        let bytes = store.get(&format!("{KEY_VALUE_PREFIX}{url}")).await.ok()?;
+        match serde_json::from_slice(&bytes) {
+            Ok(response) => Some(response),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid cached outbound HTTP response for {url}: {err}");
+                None
+            }
+        }
+    }
+
+    async fn set(&self, url: &str, response: &CachedResponse) {
+        let result = async {
+            let store = self.manager.get(&self.store).await?;
+            let bytes = serde_json::to_vec(response).map_err(spin_key_value::log_error)?;
+            store.set(&format!("{KEY_VALUE_PREFIX}{url}"), &bytes).await
+        }
+        .await;
+        if let Err(err) = result {
+            tracing::warn!("Failed to cache outbound HTTP response for {url}: {err:?}");
+        }
+    }
+}
+
+// Responses are cached by URL alone and shared by every instance, so a request
+// carrying credentials neither uses nor fills the cache, lest one caller's
+// response be served to another.
+fn has_credentials(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::AUTHORIZATION)
+        || headers.contains_key(header::PROXY_AUTHORIZATION)
+        || headers.contains_key(header::COOKIE)
+}
+
+fn has_directive(headers: &HeaderMap, directive: &str) -> bool {
+    cache_directives(headers).any(|(name, _)| name.eq_ignore_ascii_case(directive))
+}
+
+// Returns the lifetime of a response from `s-maxage` or `max-age`.
+fn freshness_lifetime(headers: &HeaderMap) -> Option<u64> {
+    let mut max_age = None;
+    for (name, value) in cache_directives(headers) {
+        let value = value.and_then(|v| v.trim_matches('"').parse().ok());
+        if name.eq_ignore_ascii_case("s-maxage") {
+            return value;
+        } else if name.eq_ignore_ascii_case("max-age") {
+            max_age = value;
+        }
+    }
+    max_age
+}
+
+fn cache_directives(headers: &HeaderMap) -> impl Iterator<Item = (&str, Option<&str>)> {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|directive| match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (directive.trim(), None),
+        })
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v: &HeaderValue| v.to_str().ok())
+        .map(str::to_owned)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.parse().unwrap(), HeaderValue::from_static(v)))
+            .collect()
+    }
+
+    fn memory_cache() -> ResponseCache {
+        ResponseCacheConfig::Memory {
+            max_entries: NonZeroUsize::new(10).unwrap(),
+        }
+        .build()
+    }
+
+    #[test]
+    fn parses_freshness_lifetime() {
+        assert_eq!(
+            freshness_lifetime(&headers(&[("cache-control", "public, max-age=60")])),
+            Some(60)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[("cache-control", "max-age=60, s-maxage=10")])),
+            Some(10)
+        );
+        assert_eq!(freshness_lifetime(&headers(&[])), None);
+    }
+
+    #[tokio::test]
+    async fn serves_fresh_responses() {
+        let cache = memory_cache();
+        let resp = headers(&[("cache-control", "max-age=60")]);
+        cache
+            .store("http://a/", &HeaderMap::new(), StatusCode::OK, &resp, b"hi")
+            .await;
+        match cache.lookup("http://a/", &HeaderMap::new()).await {
+            Lookup::Fresh(cached) => assert_eq!(cached.body, b"hi"),
+            _ => panic!("expected fresh response"),
+        }
+        let no_cache = headers(&[("cache-control", "no-cache")]);
+        assert!(matches!(
+            cache.lookup("http://a/", &no_cache).await,
+            Lookup::Miss
+        ));
+    }
+
+    #[tokio::test]
+    async fn revalidates_stale_responses_with_etag() {
+        let cache = memory_cache();
+        let resp = headers(&[("cache-control", "no-cache"), ("etag", "\"v1\"")]);
+        cache
+            .store("http://a/", &HeaderMap::new(), StatusCode::OK, &resp, b"hi")
+            .await;
+        match cache.lookup("http://a/", &HeaderMap::new()).await {
+            Lookup::Stale(_, conditional) => {
+                assert_eq!(conditional[header::IF_NONE_MATCH], "\"v1\"")
+            }
+            _ => panic!("expected stale response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_store_uncacheable_responses() {
+        let cache = memory_cache();
+        for resp in [
+            headers(&[("cache-control", "no-store, max-age=60")]),
+            headers(&[("cache-control", "private, max-age=60")]),
+            headers(&[("cache-control", "max-age=60"), ("vary", "accept")]),
+            headers(&[]),
+        ] {
+            cache
+                .store("http://a/", &HeaderMap::new(), StatusCode::OK, &resp, b"hi")
+                .await;
+            assert!(matches!(
+                cache.lookup("http://a/", &HeaderMap::new()).await,
+                Lookup::Miss
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_requests_with_credentials() {
+        let cache = memory_cache();
+        let resp = headers(&[("cache-control", "max-age=60")]);
+        for request in [
+            headers(&[("authorization", "Bearer secret")]),
+            headers(&[("cookie", "session=secret")]),
+        ] {
+            cache
+                .store("http://a/", &request, StatusCode::OK, &resp, b"private")
+                .await;
+            assert!(matches!(
+                cache.lookup("http://a/", &HeaderMap::new()).await,
+                Lookup::Miss
+            ));
+        }
+
+        cache
+            .store("http://a/", &HeaderMap::new(), StatusCode::OK, &resp, b"hi")
+            .await;
+        let authorized = headers(&[("authorization", "Bearer secret")]);
+        assert!(matches!(
+            cache.lookup("http://a/", &authorized).await,
+            Lookup::Miss
+        ));
+    }
+}
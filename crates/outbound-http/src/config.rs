@@ -0,0 +1,288 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, ClientBuilder, NoProxy, Proxy, Url};
+use spin_core::FaultInjection;
+
+use crate::{
+    cache::{ResponseCache, ResponseCacheConfig},
+    mock::{MockConfig, MockResponse},
+    resilience::{CircuitBreaker, CircuitBreakerConfig, RetryConfig},
+    tls::TlsConfig,
+};
+
+/// Configuration of the clients used to send outbound HTTP requests on behalf
+/// of components.
+#[derive(Clone, Debug, Default)]
+pub struct OutboundHttpConfig {
+    /// An explicit proxy for all outbound requests. If unset, the standard
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables are
+    /// honored.
+    pub proxy: Option<ProxyConfig>,
+    /// TLS settings keyed by destination host name.
+    pub tls: HashMap<String, TlsConfig>,
+    /// Retry policies keyed by destination host name.
+    pub retry: HashMap<String, RetryConfig>,
+    /// Circuit breaker settings keyed by destination host name.
+    pub circuit_breakers: HashMap<String, CircuitBreakerConfig>,
+    /// The response cache used by components which opt in to caching.
+    pub cache: Option<ResponseCacheConfig>,
+    /// Connection pooling and protocol settings. Unset values use reqwest's
+    /// defaults.
+    pub connection: ConnectionConfig,
+    /// Canned responses for matching requests, which are then not sent. The
+    /// first matching mock applies.
+    pub mocks: Vec<MockConfig>,
+    /// Latency and errors to inject into requests, keyed by component ID.
+    pub faults: HashMap<String, FaultInjection>,
+}
+
+/// Connection pooling and protocol settings for outbound HTTP.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionConfig {
+    /// The maximum number of idle connections kept open per host.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept in the pool.
+    pub idle_timeout: Option<Duration>,
+    /// Which HTTP versions may be used.
+    pub http_version: HttpVersionPreference,
+    /// The interval between TCP keepalive probes on open connections.
+    pub tcp_keepalive: Option<Duration>,
+}
+
+/// Which HTTP versions outbound connections may use.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HttpVersionPreference {
+    /// HTTP/2 if negotiated via ALPN, otherwise HTTP/1.1.
+    #[default]
+    Auto,
+    /// Only HTTP/1.1.
+    Http1,
+    /// HTTP/2 without negotiation, multiplexing requests to a host over a
+    /// single connection. Servers must support HTTP/2.
+    Http2,
+}
+
+/// An explicitly configured outbound HTTP proxy.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyConfig {
+    /// The proxy URL, e.g. `http://proxy.example.com:3128`.
+    pub url: String,
+    /// Username for proxy basic authentication.
+    pub username: Option<String>,
+    /// Password for proxy basic authentication.
+    pub password: Option<String>,
+    /// Hosts which should be reached directly rather than through the proxy,
+    /// using the same syntax as the `NO_PROXY` environment variable.
+    pub no_proxy: Vec<String>,
+}
+
+impl OutboundHttpConfig {
+    /// Builds the clients that apply this configuration.
+    pub fn build_clients(&self) -> Result<HttpClients> {
+        let default = self
+            .client_builder()?
+            .build()
+            .context("Failed to build outbound HTTP client")?;
+        let by_host = self
+            .tls
+            .iter()
+            .map(|(host, tls)| {
+                let client = tls
+                    .apply(self.client_builder()?)
+                    .and_then(|builder| Ok(builder.build()?))
+                    .with_context(|| format!("Invalid TLS configuration for host {host:?}"))?;
+                Ok((host.to_ascii_lowercase(), client))
+            })
+            .collect::<Result<_>>()?;
+        let retry = self
+            .retry
+            .iter()
+            .map(|(host, retry)| (host.to_ascii_lowercase(), retry.clone()))
+            .collect();
+        let breakers = self
+            .circuit_breakers
+            .iter()
+            .map(|(host, config)| {
+                if config.failure_threshold == 0 {
+                    bail!("The circuit breaker failure threshold for host {host:?} must be at least 1");
+                }
+                let breaker = Arc::new(CircuitBreaker::new(config.clone()));
+                Ok((host.to_ascii_lowercase(), breaker))
+            })
+            .collect::<Result<_>>()?;
+        for mock in &self.mocks {
+            mock.response
+                .validate()
+                .with_context(|| format!("Invalid mock response for {}{}", mock.host, mock.path))?;
+        }
+        Ok(HttpClients {
+            default,
+            by_host,
+            retry,
+            breakers,
+            cache: self.cache.as_ref().map(ResponseCacheConfig::build),
+            mocks: self.mocks.clone(),
+        })
+    }
+
+    fn client_builder(&self) -> Result<ClientBuilder> {
+        let mut builder = self.connection.apply(Client::builder());
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.build()?);
+        }
+        Ok(builder)
+    }
+}
+
+impl ConnectionConfig {
+    fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(max_idle) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(idle_timeout);
+        }
+        if self.tcp_keepalive.is_some() {
+            builder = builder.tcp_keepalive(self.tcp_keepalive);
+        }
+        match self.http_version {
+            HttpVersionPreference::Auto => builder,
+            HttpVersionPreference::Http1 => builder.http1_only(),
+            HttpVersionPreference::Http2 => builder.http2_prior_knowledge(),
+        }
+    }
+}
+
+impl ProxyConfig {
+    fn build(&self) -> Result<Proxy> {
+        let mut proxy = Proxy::all(&self.url)
+            .with_context(|| format!("Invalid outbound HTTP proxy URL {:?}", self.url))?;
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or_default());
+        }
+        if !self.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(NoProxy::from_string(&self.no_proxy.join(",")));
+        }
+        Ok(proxy)
+    }
+}
+
+/// The set of clients used for outbound HTTP, along with the per-host retry
+/// and circuit breaker state, selected by destination host, the shared
+/// response cache and any mock responses.
+#[derive(Debug)]
+pub struct HttpClients {
+    default: Client,
+    by_host: HashMap<String, Client>,
+    retry: HashMap<String, RetryConfig>,
+    breakers: HashMap<String, Arc<CircuitBreaker>>,
+    cache: Option<ResponseCache>,
+    mocks: Vec<MockConfig>,
+}
+
+impl HttpClients {
+    /// Returns the client to use for requests to the given URL.
+    pub fn for_url(&self, url: &Url) -> &Client {
+        lookup(&self.by_host, url).unwrap_or(&self.default)
+    }
+
+    pub(crate) fn retry_for(&self, url: &Url) -> Option<&RetryConfig> {
+        lookup(&self.retry, url)
+    }
+
+    pub(crate) fn breaker_for(&self, url: &Url) -> Option<&Arc<CircuitBreaker>> {
+        lookup(&self.breakers, url)
+    }
+
+    pub(crate) fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    pub(crate) fn mock_for(&self, method: &http::Method, url: &Url) -> Option<&MockResponse> {
+        self.mocks
+            .iter()
+            .find(|mock| mock.matches(method, url))
+            .map(|mock| &mock.response)
+    }
+}
+
+fn lookup<'a, T>(by_host: &'a HashMap<String, T>, url: &Url) -> Option<&'a T> {
+    if by_host.is_empty() {
+        return None;
+    }
+    by_host.get(&url.host_str()?.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builds_client_with_authenticated_proxy() {
+        let config = OutboundHttpConfig {
+            proxy: Some(ProxyConfig {
+                url: "http://proxy.example.com:3128".into(),
+                username: Some("user".into()),
+                password: Some("pass".into()),
+                no_proxy: vec!["localhost".into(), ".internal".into()],
+            }),
+            ..Default::default()
+        };
+        config.build_clients().unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_proxy_url() {
+        let config = OutboundHttpConfig {
+            proxy: Some(ProxyConfig {
+                url: "not a url".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(config.build_clients().is_err());
+    }
+
+    #[test]
+    fn selects_client_by_host() {
+        let config = OutboundHttpConfig {
+            tls: [(
+                "Dev.Local".to_owned(),
+                TlsConfig {
+                    insecure_skip_verify: true,
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let clients = config.build_clients().unwrap();
+        assert_eq!(clients.by_host.len(), 1);
+        let dev = Url::parse("https://dev.local:8443/").unwrap();
+        let other = Url::parse("https://example.com/").unwrap();
+        assert!(std::ptr::eq(
+            clients.for_url(&dev),
+            &clients.by_host["dev.local"]
+        ));
+        assert!(std::ptr::eq(clients.for_url(&other), &clients.default));
+    }
+
+    #[test]
+    fn rejects_zero_failure_threshold() {
+        let config = OutboundHttpConfig {
+            circuit_breakers: [(
+                "example.com".to_owned(),
+                CircuitBreakerConfig {
+                    failure_threshold: 0,
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        assert!(config.build_clients().is_err());
+    }
+}
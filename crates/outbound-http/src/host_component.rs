@@ -1,12 +1,29 @@
+use std::{collections::HashMap, sync::Arc};
+
 use anyhow::Result;
 
 use spin_app::DynamicHostComponent;
-use spin_core::{Data, HostComponent, Linker};
+use spin_core::{Data, FaultInjection, HostComponent, Linker};
 use spin_world::http;
 
-use crate::{allowed_http_hosts::parse_allowed_http_hosts, OutboundHttp};
+use crate::{
+    allowed_http_hosts::parse_allowed_http_hosts, HttpClients, OutboundHttp, OutboundHttpConfig,
+};
+
+pub struct OutboundHttpComponent {
+    // Shared by all instances so that connections can be reused across requests
+    clients: Arc<HttpClients>,
+    faults: HashMap<String, FaultInjection>,
+}
 
-pub struct OutboundHttpComponent;
+impl OutboundHttpComponent {
+    pub fn new(config: &OutboundHttpConfig) -> Result<Self> {
+        Ok(Self {
+            clients: Arc::new(config.build_clients()?),
+            faults: config.faults.clone(),
+        })
+    }
+}
 
 impl HostComponent for OutboundHttpComponent {
     type Data = OutboundHttp;
@@ -19,7 +36,7 @@ impl HostComponent for OutboundHttpComponent {
     }
 
     fn build_data(&self) -> Self::Data {
-        Default::default()
+        OutboundHttp::new(self.clients.clone())
     }
 }
 
@@ -31,6 +48,10 @@ impl DynamicHostComponent for OutboundHttpComponent {
     ) -> anyhow::Result<()> {
         let hosts = component.get_metadata(crate::ALLOWED_HTTP_HOSTS_KEY)?;
         data.allowed_hosts = parse_allowed_http_hosts(&hosts)?;
+        data.cache_responses = component
+            .get_metadata(crate::OUTBOUND_HTTP_CACHE_KEY)?
+            .unwrap_or_default();
+        data.faults = self.faults.get(component.id()).copied();
         Ok(())
     }
 }
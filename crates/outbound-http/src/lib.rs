@@ -1,29 +1,71 @@
 pub mod allowed_http_hosts;
+mod cache;
+mod config;
 mod host_component;
+mod mock;
+mod resilience;
+mod streaming;
+mod tls;
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use anyhow::Result;
 use http::HeaderMap;
-use reqwest::{Client, Url};
+use reqwest::Url;
 use spin_app::MetadataKey;
-use spin_core::async_trait;
+use spin_core::{async_trait, FaultInjection};
+use spin_key_value::table::Table;
 use spin_world::{
     http as outbound_http,
-    http_types::{HeadersParam, HttpError, Method, RequestResult, Response},
+    http_types::{
+        HeadersParam, HttpError, IncomingBody, Method, OutgoingBody, RequestResult, Response,
+        StreamingResponse,
+    },
 };
+use tracing::instrument;
 
 use allowed_http_hosts::AllowedHttpHosts;
+pub use cache::ResponseCacheConfig;
+use cache::{CachedResponse, Lookup, ResponseCache};
+pub use config::{
+    ConnectionConfig, HttpClients, HttpVersionPreference, OutboundHttpConfig, ProxyConfig,
+};
 pub use host_component::OutboundHttpComponent;
+pub use mock::{MockConfig, MockResponse};
+pub use resilience::{CircuitBreakerConfig, RetryConfig};
+use streaming::OutgoingRequest;
+pub use tls::TlsConfig;
 
 pub const ALLOWED_HTTP_HOSTS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("allowed_http_hosts");
+pub const OUTBOUND_HTTP_CACHE_KEY: MetadataKey<bool> = MetadataKey::new("outbound_http_cache");
+
+const DEFAULT_STREAM_TABLE_CAPACITY: u32 = 256;
 
 /// A very simple implementation for outbound HTTP requests.
-#[derive(Default, Clone)]
 pub struct OutboundHttp {
     /// List of hosts guest modules are allowed to make requests to.
     pub allowed_hosts: AllowedHttpHosts,
-    client: Option<Client>,
+    /// Whether responses to GET requests may be served from the response cache.
+    pub cache_responses: bool,
+    /// Latency and errors to inject into the component's requests, if any.
+    pub faults: Option<FaultInjection>,
+    clients: Arc<HttpClients>,
+    outgoing_bodies: Table<OutgoingRequest>,
+    incoming_bodies: Table<reqwest::Response>,
+}
+
+impl OutboundHttp {
+    /// Creates a new instance that sends requests with the given clients.
+    pub fn new(clients: Arc<HttpClients>) -> Self {
+        Self {
+            allowed_hosts: Default::default(),
+            cache_responses: false,
+            faults: None,
+            clients,
+            outgoing_bodies: Table::new(DEFAULT_STREAM_TABLE_CAPACITY),
+            incoming_bodies: Table::new(DEFAULT_STREAM_TABLE_CAPACITY),
+        }
+    }
 }
 
 impl OutboundHttp {
@@ -35,52 +77,311 @@ impl OutboundHttp {
         let url = Url::parse(url).map_err(|_| HttpError::InvalidUrl)?;
         Ok(self.allowed_hosts.allow(&url))
     }
+
+    fn check_allowed(&self, uri: &str) -> Result<(), HttpError> {
+        if !self.is_allowed(uri).map_err(|_| HttpError::RuntimeError)? {
+            tracing::log::info!("Destination not allowed: {}", uri);
+            return Err(HttpError::DestinationNotAllowed);
+        }
+        Ok(())
+    }
+
+    // Delays or fails the request if faults are being injected.
+    async fn inject_faults(&self, uri: &str) -> Result<(), HttpError> {
+        let Some(faults) = &self.faults else {
+            return Ok(());
+        };
+        tokio::time::sleep(faults.latency).await;
+        if faults.should_fail() {
+            tracing::log::info!("Injected failure of outbound request to {uri}");
+            return Err(HttpError::RuntimeError);
+        }
+        Ok(())
+    }
+
+    // Sends a request, retrying transient failures according to the retry
+    // policy for the destination host and honoring its circuit breaker.
+    async fn send_with_retries(
+        &self,
+        method: http::Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, HttpError> {
+        let client = self.clients.for_url(&url);
+        let breaker = self.clients.breaker_for(&url);
+        let retry = self
+            .clients
+            .retry_for(&url)
+            .filter(|_| resilience::is_idempotent(&method));
+        let max_attempts = retry.map(|r| r.max_attempts.max(1)).unwrap_or(1);
+
+        let mut attempt = 1;
+        loop {
+            if let Some(breaker) = breaker {
+                check_breaker(breaker, &url)?;
+            }
+            let result = client
+                .request(method.clone(), url.clone())
+                .headers(headers.clone())
+                .body(body.clone())
+                .send()
+                .await;
+            if let Some(breaker) = breaker {
+                breaker.record(matches!(&result, Ok(resp) if !resp.status().is_server_error()));
+            }
+            let transient = match &result {
+                Ok(resp) => resilience::is_transient_status(resp.status()),
+                Err(err) => resilience::is_transient_error(err),
+            };
+            match retry {
+                Some(retry) if transient && attempt < max_attempts => {
+                    let delay = retry.backoff(attempt);
+                    tracing::debug!(
+                        "Retrying outbound HTTP request to {url} in {delay:?} (attempt {attempt} of {max_attempts} failed)"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return result.map_err(log_reqwest_error),
+            }
+        }
+    }
+}
+
+impl OutboundHttp {
+    // Sends a GET request through the response cache, revalidating a stale
+    // cached response where possible.
+    async fn send_cached(
+        &self,
+        cache: &ResponseCache,
+        url: Url,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<Response, HttpError> {
+        let key = url.to_string();
+        let (stale, request_headers) = match cache.lookup(&key, &headers).await {
+            Lookup::Fresh(cached) => {
+                tracing::log::trace!("Returning cached response for outbound request to {key}");
+                return Ok(cached.into());
+            }
+            Lookup::Stale(cached, conditional) => {
+                let mut request_headers = headers.clone();
+                request_headers.extend(conditional);
+                (Some(cached), request_headers)
+            }
+            Lookup::Miss => (None, headers.clone()),
+        };
+
+        let resp = self
+            .send_with_retries(http::Method::GET, url, request_headers, body)
+            .await?;
+        if let Some(cached) = stale {
+            if resp.status() == http::StatusCode::NOT_MODIFIED {
+                return Ok(cache.revalidated(&key, cached, resp.headers()).await.into());
+            }
+        }
+
+        let status = resp.status();
+        let resp_headers = resp.headers().clone();
+        let resp_body = resp.bytes().await.map_err(|_| HttpError::RuntimeError)?;
+        cache
+            .store(&key, &headers, status, &resp_headers, &resp_body)
+            .await;
+        Ok(Response {
+            status: status.as_u16(),
+            headers: response_headers(&resp_headers).map_err(|_| HttpError::RuntimeError)?,
+            body: Some(resp_body.to_vec()),
+        })
+    }
+}
+
+impl From<CachedResponse> for Response {
+    fn from(cached: CachedResponse) -> Self {
+        Self {
+            status: cached.status,
+            headers: Some(cached.headers),
+            body: Some(cached.body),
+        }
+    }
+}
+
+fn check_breaker(breaker: &resilience::CircuitBreaker, url: &Url) -> Result<(), HttpError> {
+    if breaker.allow() {
+        Ok(())
+    } else {
+        tracing::warn!("Outbound HTTP circuit breaker is open; rejecting request to {url}");
+        Err(HttpError::RuntimeError)
+    }
 }
 
 #[async_trait]
 impl outbound_http::Host for OutboundHttp {
+    #[instrument(
+        name = "spin_outbound_http.send_request",
+        skip_all,
+        fields(otel.kind = "client", url.full = %req.uri, http.request.method = ?req.method)
+    )]
     async fn send_request(&mut self, req: RequestResult) -> Result<Result<Response, HttpError>> {
         Ok(async {
             tracing::log::trace!("Attempting to send outbound HTTP request to {}", req.uri);
-            if !self
-                .is_allowed(&req.uri)
-                .map_err(|_| HttpError::RuntimeError)?
-            {
-                tracing::log::info!("Destination not allowed: {}", req.uri);
-                return Err(HttpError::DestinationNotAllowed);
-            }
+            self.check_allowed(&req.uri)?;
+            self.inject_faults(&req.uri).await?;
 
             let method = method_from(req.method);
             let url = Url::parse(&req.uri).map_err(|_| HttpError::InvalidUrl)?;
-            let headers = request_headers(
+            let mut headers = request_headers(
                 &req.headers
                     .iter()
                     .map(|(k, v)| (k.as_str(), v.as_str()))
                     .collect::<Vec<_>>(),
             )
             .map_err(|_| HttpError::RuntimeError)?;
+            spin_telemetry::inject_trace_context(&mut headers);
+            spin_telemetry::inject_request_id(&mut headers);
             let body = req.body.unwrap_or_default().to_vec();
 
             if !req.params.is_empty() {
                 tracing::log::warn!("HTTP params field is deprecated");
             }
 
-            // Allow reuse of Client's internal connection pool for multiple requests
-            // in a single component execution
-            let client = self.client.get_or_insert_with(Default::default);
+            if let Some(mock) = self.clients.mock_for(&method, &url) {
+                tracing::log::trace!("Returning mock response for outbound request to {url}");
+                return Ok(mock.to_response());
+            }
+
+            let cache = self
+                .clients
+                .cache()
+                .filter(|_| self.cache_responses && method == http::Method::GET);
+            if let Some(cache) = cache {
+                return self.send_cached(cache, url, headers, body).await;
+            }
 
-            let resp = client
+            let resp = self.send_with_retries(method, url, headers, body).await?;
+            tracing::log::trace!("Returning response from outbound request to {}", req.uri);
+            response_from_reqwest(resp).await
+        }
+        .await)
+    }
+
+    #[instrument(
+        name = "spin_outbound_http.start_request",
+        skip_all,
+        fields(otel.kind = "client", url.full = %uri, http.request.method = ?method)
+    )]
+    async fn start_request(
+        &mut self,
+        method: Method,
+        uri: String,
+        headers: Vec<(String, String)>,
+    ) -> Result<Result<OutgoingBody, HttpError>> {
+        Ok(async {
+            tracing::log::trace!("Attempting to start streaming outbound HTTP request to {uri}");
+            self.check_allowed(&uri)?;
+            self.inject_faults(&uri).await?;
+
+            let url = Url::parse(&uri).map_err(|_| HttpError::InvalidUrl)?;
+            let mut headers = request_headers(
+                &headers
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect::<Vec<_>>(),
+            )
+            .map_err(|_| HttpError::RuntimeError)?;
+            spin_telemetry::inject_trace_context(&mut headers);
+            spin_telemetry::inject_request_id(&mut headers);
+
+            let method = method_from(method);
+            if let Some(mock) = self.clients.mock_for(&method, &url) {
+                tracing::log::trace!("Returning mock response for outbound request to {url}");
+                return self
+                    .outgoing_bodies
+                    .push(OutgoingRequest::mocked(mock.to_reqwest()))
+                    .map_err(|()| HttpError::TooManyRequests);
+            }
+
+            let breaker = self.clients.breaker_for(&url).cloned();
+            if let Some(breaker) = &breaker {
+                check_breaker(breaker, &url)?;
+            }
+            let builder = self
+                .clients
+                .for_url(&url)
                 .request(method, url)
-                .headers(headers)
-                .body(body)
-                .send()
+                .headers(headers);
+            let request = OutgoingRequest::start(builder, breaker);
+            self.outgoing_bodies
+                .push(request)
+                .map_err(|()| HttpError::TooManyRequests)
+        }
+        .await)
+    }
+
+    async fn write_request_body(
+        &mut self,
+        req: OutgoingBody,
+        chunk: Vec<u8>,
+    ) -> Result<Result<(), HttpError>> {
+        Ok(async {
+            self.outgoing_bodies
+                .get_mut(req)
+                .ok_or(HttpError::RequestError)?
+                .write(chunk)
+                .await
+        }
+        .await)
+    }
+
+    async fn finish_request(
+        &mut self,
+        req: OutgoingBody,
+    ) -> Result<Result<StreamingResponse, HttpError>> {
+        Ok(async {
+            let request = self
+                .outgoing_bodies
+                .remove(req)
+                .ok_or(HttpError::RequestError)?;
+            let resp = request.finish().await?;
+
+            let status = resp.status().as_u16();
+            let headers = response_headers(resp.headers())
+                .map_err(|_| HttpError::RuntimeError)?
+                .unwrap_or_default();
+            let body = self
+                .incoming_bodies
+                .push(resp)
+                .map_err(|()| HttpError::TooManyRequests)?;
+            Ok(StreamingResponse {
+                status,
+                headers,
+                body,
+            })
+        }
+        .await)
+    }
+
+    async fn read_response_body(
+        &mut self,
+        resp: IncomingBody,
+    ) -> Result<Result<Option<Vec<u8>>, HttpError>> {
+        Ok(async {
+            let chunk = self
+                .incoming_bodies
+                .get_mut(resp)
+                .ok_or(HttpError::RequestError)?
+                .chunk()
                 .await
                 .map_err(log_reqwest_error)?;
-            tracing::log::trace!("Returning response from outbound request to {}", req.uri);
-            response_from_reqwest(resp).await
+            Ok(chunk.map(|bytes| bytes.to_vec()))
         }
         .await)
     }
+
+    async fn close_response_body(&mut self, resp: IncomingBody) -> Result<()> {
+        self.incoming_bodies.remove(resp);
+        Ok(())
+    }
 }
 
 fn log_reqwest_error(err: reqwest::Error) -> HttpError {
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use spin_world::http_types::Response;
+
+/// A canned response which outbound requests matching a host and path
+/// pattern receive instead of being sent, so that components which depend on
+/// third-party APIs can be developed and tested offline.
+#[derive(Clone, Debug, Default)]
+pub struct MockConfig {
+    /// The host name which requests must be for, or `*` for any host.
+    pub host: String,
+    /// The path which requests must be for. A path ending in `/...` matches
+    /// that path and any path under it.
+    pub path: String,
+    /// The method which requests must use. If unset, any method matches.
+    pub method: Option<http::Method>,
+    /// The response to return.
+    pub response: MockResponse,
+}
+
+/// The response returned for a mocked outbound request.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    /// The response status code.
+    pub status: u16,
+    /// The response headers.
+    pub headers: Vec<(String, String)>,
+    /// The response body.
+    pub body: Vec<u8>,
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            body: vec![],
+        }
+    }
+}
+
+impl MockConfig {
+    pub(crate) fn matches(&self, method: &http::Method, url: &Url) -> bool {
+        if self.method.as_ref().map_or(false, |m| m != method) {
+            return false;
+        }
+        let host_matches = self.host == "*"
+            || url
+                .host_str()
+                .map_or(false, |host| host.eq_ignore_ascii_case(&self.host));
+        host_matches && path_matches(&self.path, url.path())
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    match pattern.strip_suffix("/...") {
+        Some(prefix) => {
+            path == prefix
+                || path
+                    .strip_prefix(prefix)
+                    .map_or(false, |rest| rest.starts_with('/'))
+        }
+        None => path == pattern.trim_end_matches('/'),
+    }
+}
+
+impl MockResponse {
+    pub(crate) fn validate(&self) -> Result<()> {
+        http::StatusCode::from_u16(self.status)
+            .with_context(|| format!("invalid status {}", self.status))?;
+        for (name, value) in &self.headers {
+            http::header::HeaderName::from_str(name)
+                .with_context(|| format!("invalid header name {name:?}"))?;
+            http::header::HeaderValue::from_str(value)
+                .with_context(|| format!("invalid value for header {name:?}"))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_response(&self) -> Response {
+        Response {
+            status: self.status,
+            headers: Some(self.headers.clone()),
+            body: Some(self.body.clone()),
+        }
+    }
+
+    pub(crate) fn to_reqwest(&self) -> reqwest::Response {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        // The status and headers were validated when the mock was configured.
+        builder
+            .body(self.body.clone())
+            .expect("invalid mock response")
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mock(host: &str, path: &str, method: Option<http::Method>) -> MockConfig {
+        MockConfig {
+            host: host.into(),
+            path: path.into(),
+            method,
+            response: Default::default(),
+        }
+    }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn matches_host_and_exact_path() {
+        let mock = mock("API.example.com", "/v1/users", None);
+        assert!(mock.matches(&http::Method::GET, &url("https://api.example.com/v1/users")));
+        assert!(mock.matches(
+            &http::Method::POST,
+            &url("https://api.example.com/v1/users/")
+        ));
+        assert!(!mock.matches(
+            &http::Method::GET,
+            &url("https://api.example.com/v1/users/1")
+        ));
+        assert!(!mock.matches(&http::Method::GET, &url("https://example.com/v1/users")));
+    }
+
+    #[test]
+    fn matches_wildcard_path_and_any_host() {
+        let mock = mock("*", "/v1/...", Some(http::Method::GET));
+        assert!(mock.matches(&http::Method::GET, &url("https://a.example.com/v1")));
+        assert!(mock.matches(&http::Method::GET, &url("http://b.example.com/v1/users/1")));
+        assert!(!mock.matches(&http::Method::GET, &url("https://a.example.com/v10")));
+        assert!(!mock.matches(&http::Method::PUT, &url("https://a.example.com/v1/users")));
+    }
+
+    #[test]
+    fn root_wildcard_matches_every_path() {
+        let mock = mock("api.example.com", "/...", None);
+        assert!(mock.matches(&http::Method::GET, &url("https://api.example.com")));
+        assert!(mock.matches(&http::Method::GET, &url("https://api.example.com/a/b")));
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Retry policy for outbound HTTP requests to a particular host.
+///
+/// Only requests with idempotent methods are retried, and only after a
+/// connection failure, a timeout, or a 502, 503 or 504 response.
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    /// The maximum number of attempts, including the first.
+    pub max_attempts: u32,
+    /// The delay before the first retry. Each subsequent delay is doubled.
+    pub initial_backoff: Duration,
+    /// The upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the delay before the given retry (starting from 1).
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Circuit breaker settings for outbound HTTP requests to a particular host.
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive failures after which the circuit opens.
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a trial request is let through.
+    /// Until the trial request completes, other requests are rejected; if it
+    /// has not completed after this long again, another is let through.
+    pub reset_timeout: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            reset_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A circuit breaker shared by all requests to a host. Requests are rejected
+/// while the circuit is open; once the reset timeout has passed, a single
+/// trial request is let through, and its outcome decides whether the circuit
+/// closes or re-opens.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    circuit: Circuit,
+}
+
+#[derive(Debug, Default)]
+enum Circuit {
+    #[default]
+    Closed,
+    Open {
+        until: Instant,
+    },
+    // A trial request was let through at `since`, and its outcome is awaited.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns whether a request may be sent. Once the circuit has been open
+    /// for the reset timeout, this returns `true` for one trial request.
+    pub(crate) fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let trial_due = match state.circuit {
+            Circuit::Closed => return true,
+            Circuit::Open { until } => now >= until,
+            // The trial request's outcome may never be recorded, e.g. if it
+            // was abandoned, so another is let through eventually.
+            Circuit::HalfOpen { since } => now >= since + self.config.reset_timeout,
+        };
+        if trial_due {
+            state.circuit = Circuit::HalfOpen { since: now };
+        }
+        trial_due
+    }
+
+    /// Records the outcome of a request.
+    pub(crate) fn record(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        if success {
+            *state = Default::default();
+            return;
+        }
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        let trial_failed = matches!(state.circuit, Circuit::HalfOpen { .. });
+        if trial_failed || state.consecutive_failures >= self.config.failure_threshold {
+            state.circuit = Circuit::Open {
+                until: Instant::now() + self.config.reset_timeout,
+            };
+        }
+    }
+}
+
+/// Whether a request with the given method may safely be sent more than once.
+pub(crate) fn is_idempotent(method: &http::Method) -> bool {
+    matches!(
+        *method,
+        http::Method::GET
+            | http::Method::HEAD
+            | http::Method::PUT
+            | http::Method::DELETE
+            | http::Method::OPTIONS
+    )
+}
+
+/// Whether a response status indicates a transient upstream failure.
+pub(crate) fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502 | 503 | 504)
+}
+
+/// Whether a request error indicates a transient failure.
+pub(crate) fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let retry = RetryConfig {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        assert_eq!(retry.backoff(1), Duration::from_millis(100));
+        assert_eq!(retry.backoff(2), Duration::from_millis(200));
+        assert_eq!(retry.backoff(3), Duration::from_millis(400));
+        assert_eq!(retry.backoff(4), Duration::from_millis(500));
+        assert_eq!(retry.backoff(40), Duration::from_millis(500));
+    }
+
+    // Simulates the reset timeout elapsing while the circuit is open.
+    fn elapse_reset_timeout(breaker: &CircuitBreaker) {
+        breaker.state.lock().unwrap().circuit = Circuit::Open {
+            until: Instant::now(),
+        };
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_resets() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            reset_timeout: Duration::from_secs(60),
+        });
+        breaker.record(false);
+        assert!(breaker.allow());
+        breaker.record(false);
+        assert!(!breaker.allow());
+
+        elapse_reset_timeout(&breaker);
+        assert!(breaker.allow());
+        breaker.record(true);
+        assert!(breaker.allow());
+        breaker.record(false);
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn half_open_breaker_admits_one_trial_request() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_secs(60),
+        });
+        breaker.record(false);
+        assert!(!breaker.allow());
+
+        elapse_reset_timeout(&breaker);
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        assert!(!breaker.allow());
+
+        // A failed trial re-opens the circuit for the full reset timeout.
+        breaker.record(false);
+        assert!(!breaker.allow());
+
+        elapse_reset_timeout(&breaker);
+        assert!(breaker.allow());
+        breaker.record(true);
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn abandoned_trial_request_is_replaced() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            reset_timeout: Duration::from_secs(60),
+        });
+        breaker.record(false);
+        breaker.state.lock().unwrap().circuit = Circuit::HalfOpen {
+            since: Instant::now() - Duration::from_secs(61),
+        };
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+    }
+}
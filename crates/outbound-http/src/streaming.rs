@@ -0,0 +1,200 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use futures::{channel::mpsc, future, stream, SinkExt, StreamExt};
+use reqwest::{Body, RequestBuilder, Response};
+use spin_world::http_types::HttpError;
+use tokio::task::JoinHandle;
+
+use crate::{log_reqwest_error, resilience::CircuitBreaker};
+
+// The number of body chunks buffered between the guest and the connection.
+const BODY_CHANNEL_CAPACITY: usize = 16;
+
+type BodyChunk = Result<Vec<u8>, std::io::Error>;
+
+/// An outbound request whose body is being streamed from the guest.
+///
+/// The request is sent as soon as it is started, so that chunks written by
+/// the guest flow to the upstream server without being buffered in full. If
+/// the request is dropped without being finished, the body ends in an error,
+/// so the upstream server never sees a truncated body as complete.
+pub(crate) struct OutgoingRequest {
+    body: mpsc::Sender<BodyChunk>,
+    finished: Arc<AtomicBool>,
+    response: JoinHandle<Result<Response, reqwest::Error>>,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl OutgoingRequest {
+    pub fn start(builder: RequestBuilder, breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        let (body, finished, stream) = body_channel();
+        let request = builder.body(Body::wrap_stream(stream));
+        let response = tokio::spawn(request.send());
+        Self {
+            body,
+            finished,
+            response,
+            breaker,
+        }
+    }
+
+    /// Starts a request which receives the given response rather than being
+    /// sent. The body written by the guest is discarded.
+    pub fn mocked(response: Response) -> Self {
+        let (body, finished, stream) = body_channel();
+        let response = tokio::spawn(async move {
+            stream.for_each(|_| future::ready(())).await;
+            Ok(response)
+        });
+        Self {
+            body,
+            finished,
+            response,
+            breaker: None,
+        }
+    }
+
+    pub async fn write(&mut self, chunk: Vec<u8>) -> Result<(), HttpError> {
+        self.body.send(Ok(chunk)).await.map_err(|_| {
+            // The receiving side is only dropped if the request has failed.
+            tracing::warn!("Outbound HTTP request ended before its body was complete");
+            HttpError::RequestError
+        })
+    }
+
+    pub async fn finish(mut self) -> Result<Response, HttpError> {
+        // Closing the channel ends the body stream once the buffered chunks
+        // have been sent.
+        self.finished.store(true, Ordering::Release);
+        self.body.close_channel();
+        let result = match (&mut self.response).await {
+            Ok(result) => result.map_err(log_reqwest_error),
+            Err(err) => {
+                tracing::warn!("Outbound HTTP request task failed: {err:?}");
+                Err(HttpError::RuntimeError)
+            }
+        };
+        if let Some(breaker) = &self.breaker {
+            breaker.record(matches!(&result, Ok(resp) if !resp.status().is_server_error()));
+        }
+        result
+    }
+}
+
+impl Drop for OutgoingRequest {
+    fn drop(&mut self) {
+        // A no-op if the request was finished.
+        self.response.abort();
+    }
+}
+
+// Returns the sending side of a request body, a flag to set before the body
+// is ended deliberately, and the body stream. If the sender goes away without
+// the flag being set, the stream ends in an error rather than ending cleanly.
+fn body_channel() -> (
+    mpsc::Sender<BodyChunk>,
+    Arc<AtomicBool>,
+    impl futures::Stream<Item = BodyChunk> + Send + Sync + 'static,
+) {
+    let (sender, receiver) = mpsc::channel::<BodyChunk>(BODY_CHANNEL_CAPACITY);
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_check = finished.clone();
+    let end = stream::once(async move {
+        if finished_check.load(Ordering::Acquire) {
+            None
+        } else {
+            Some(Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "the request body was abandoned before it was complete",
+            )))
+        }
+    })
+    .filter_map(future::ready);
+    (sender, finished, receiver.chain(end))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    const TERMINATING_CHUNK: &str = "0\r\n\r\n";
+
+    async fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        (listener, url)
+    }
+
+    // Reads the request until its body has been terminated or the
+    // connection closed.
+    async fn read_request(connection: &mut TcpStream) -> String {
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        loop {
+            let read = tokio::time::timeout(Duration::from_secs(10), connection.read(&mut buf))
+                .await
+                .expect("timed out reading request")
+                .unwrap_or(0);
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..read]);
+            if request.ends_with(TERMINATING_CHUNK.as_bytes()) {
+                break;
+            }
+        }
+        String::from_utf8(request).unwrap()
+    }
+
+    #[tokio::test]
+    async fn body_is_streamed_to_server() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            let (mut connection, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut connection).await;
+            connection
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            request
+        });
+
+        let mut request = OutgoingRequest::start(reqwest::Client::new().post(url), None);
+        request.write(b"hello, ".to_vec()).await.unwrap();
+        request.write(b"world".to_vec()).await.unwrap();
+        let response = request.finish().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let request = server.await.unwrap();
+        assert!(request.contains("transfer-encoding: chunked"));
+        assert!(request.contains("\r\n7\r\nhello, \r\n"));
+        assert!(request.contains("\r\n5\r\nworld\r\n"));
+        assert!(request.ends_with(TERMINATING_CHUNK));
+    }
+
+    #[tokio::test]
+    async fn dropped_body_is_not_completed() {
+        let (listener, url) = listen().await;
+        let server = tokio::spawn(async move {
+            let (mut connection, _) = listener.accept().await.unwrap();
+            read_request(&mut connection).await
+        });
+
+        let mut request = OutgoingRequest::start(reqwest::Client::new().post(url), None);
+        request.write(b"hello, ".to_vec()).await.unwrap();
+        drop(request);
+
+        let request = server.await.unwrap();
+        assert!(!request.ends_with(TERMINATING_CHUNK));
+    }
+}
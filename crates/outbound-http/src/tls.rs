@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Certificate, ClientBuilder};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    ClientConfig, RootCertStore, ServerName,
+};
+use sha2::{Digest, Sha256};
+
+/// TLS settings applied to outbound HTTP requests to a particular host.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    /// PEM files containing additional root certificates to trust.
+    pub ca_certs: Vec<PathBuf>,
+    /// SHA-256 fingerprints of the DER-encoded server certificates to accept,
+    /// as hex strings (optionally colon-separated). If set, the server's
+    /// certificate must both verify and match one of these.
+    pub pinned_certs: Vec<String>,
+    /// Skip all certificate verification. Only intended for local development
+    /// services using self-signed certificates.
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsConfig {
+    pub(crate) fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder> {
+        if self.insecure_skip_verify {
+            if !self.pinned_certs.is_empty() {
+                bail!("TLS certificate pinning cannot be combined with insecure_skip_verify");
+            }
+            return Ok(builder.danger_accept_invalid_certs(true));
+        }
+
+        if self.pinned_certs.is_empty() {
+            let mut builder = builder;
+            for path in &self.ca_certs {
+                let pem = fs::read(path)
+                    .with_context(|| format!("Failed to read CA certificate file {path:?}"))?;
+                let cert = Certificate::from_pem(&pem)
+                    .with_context(|| format!("Invalid CA certificate file {path:?}"))?;
+                builder = builder.add_root_certificate(cert);
+            }
+            return Ok(builder);
+        }
+
+        // Pinning needs a custom certificate verifier, which means handing
+        // reqwest a complete rustls configuration.
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_native_certs::load_native_certs()
+            .context("Failed to load platform root certificates")?
+        {
+            // Ignore platform certificates that rustls can't parse, as
+            // reqwest does.
+            let _ = roots.add(&rustls::Certificate(cert.0));
+        }
+        for path in &self.ca_certs {
+            for cert in load_certs(path)
+                .with_context(|| format!("Failed to read CA certificate file {path:?}"))?
+            {
+                roots
+                    .add(&cert)
+                    .with_context(|| format!("Invalid CA certificate file {path:?}"))?;
+            }
+        }
+        let pins = self
+            .pinned_certs
+            .iter()
+            .map(|pin| parse_fingerprint(pin))
+            .collect::<Result<Vec<_>>>()?;
+        let verifier = PinnedCertVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            pins,
+        };
+        let tls = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        Ok(builder.use_preconfigured_tls(tls))
+    }
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<rustls::Certificate>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    rustls_pemfile::certs(&mut reader)
+        .map(|mut certs| certs.drain(..).map(rustls::Certificate).collect())
+}
+
+fn parse_fingerprint(pin: &str) -> Result<String> {
+    let fingerprint = pin.replace(':', "").to_ascii_lowercase();
+    if fingerprint.len() != 64 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid certificate pin {pin:?}: expected a hex-encoded SHA-256 fingerprint");
+    }
+    Ok(fingerprint)
+}
+
+/// Verifies the server certificate chain as usual, then additionally requires
+/// the end-entity certificate to match one of the pinned fingerprints.
+struct PinnedCertVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<String>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let fingerprint = format!("{:x}", Sha256::digest(&end_entity.0));
+        if !self.pins.contains(&fingerprint) {
+            tracing::warn!("Outbound HTTP server certificate {fingerprint} does not match any pin");
+            return Err(rustls::Error::General(
+                "server certificate does not match any pinned certificate".into(),
+            ));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_fingerprints() {
+        let pin = "AB:".repeat(31) + "AB";
+        assert_eq!(parse_fingerprint(&pin).unwrap(), "ab".repeat(32));
+        assert!(parse_fingerprint("abcd").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn rejects_pinning_with_insecure() {
+        let config = TlsConfig {
+            pinned_certs: vec!["ab".repeat(32)],
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+        assert!(config.apply(reqwest::Client::builder()).is_err());
+    }
+}
@@ -9,6 +9,7 @@ use spin_world::{
     redis as outbound_redis,
     redis_types::{Error, RedisParameter, RedisResult},
 };
+use tracing::instrument;
 
 pub use host_component::OutboundRedisComponent;
 
@@ -37,8 +38,14 @@ pub struct OutboundRedis {
     connections: HashMap<String, Connection>,
 }
 
+// Spans don't record arguments, as Redis addresses may contain credentials.
 #[async_trait]
 impl outbound_redis::Host for OutboundRedis {
+    #[instrument(
+        name = "spin_outbound_redis.publish",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn publish(
         &mut self,
         address: String,
@@ -53,6 +60,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.get",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn get(&mut self, address: String, key: String) -> Result<Result<Vec<u8>, Error>> {
         Ok(async {
             let conn = self.get_conn(&address).await.map_err(log_error)?;
@@ -62,6 +74,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.set",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn set(
         &mut self,
         address: String,
@@ -76,6 +93,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.incr",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn incr(&mut self, address: String, key: String) -> Result<Result<i64, Error>> {
         Ok(async {
             let conn = self.get_conn(&address).await.map_err(log_error)?;
@@ -85,6 +107,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.del",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn del(&mut self, address: String, keys: Vec<String>) -> Result<Result<i64, Error>> {
         Ok(async {
             let conn = self.get_conn(&address).await.map_err(log_error)?;
@@ -94,6 +121,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.sadd",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn sadd(
         &mut self,
         address: String,
@@ -108,6 +140,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.smembers",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn smembers(
         &mut self,
         address: String,
@@ -121,6 +158,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.srem",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn srem(
         &mut self,
         address: String,
@@ -135,6 +177,11 @@ impl outbound_redis::Host for OutboundRedis {
         .await)
     }
 
+    #[instrument(
+        name = "spin_outbound_redis.execute",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis", db.operation = command)
+    )]
     async fn execute(
         &mut self,
         address: String,
@@ -0,0 +1,70 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use spin_app::{AppComponent, DynamicHostComponent, MetadataKey};
+use spin_core::HostComponent;
+use spin_manifest::AllowedOutboundHost;
+use spin_world::smtp;
+
+use crate::{OutboundSmtp, SmtpServer, SmtpServerConfig};
+
+// Set by spin-trigger's locked app loader from the component's manifest.
+const ALLOWED_OUTBOUND_HOSTS_KEY: MetadataKey<Vec<String>> =
+    MetadataKey::new("allowed_outbound_hosts");
+
+/// The smtp host component, which gives components the servers configured
+/// in runtime config which their `allowed_outbound_hosts` permit.
+pub struct OutboundSmtpComponent {
+    servers: Arc<HashMap<String, SmtpServer>>,
+}
+
+impl OutboundSmtpComponent {
+    /// Creates a component with the given servers, by name.
+    pub fn new(servers: impl IntoIterator<Item = (String, SmtpServerConfig)>) -> Result<Self> {
+        let servers = servers
+            .into_iter()
+            .map(|(name, config)| Ok((name, SmtpServer::new(config)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            servers: Arc::new(servers),
+        })
+    }
+}
+
+impl HostComponent for OutboundSmtpComponent {
+    type Data = OutboundSmtp;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> Result<()> {
+        smtp::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        // The servers the component may use are set by `update_data`.
+        OutboundSmtp {
+            servers: self.servers.clone(),
+            allowed_servers: Default::default(),
+        }
+    }
+}
+
+impl DynamicHostComponent for OutboundSmtpComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> Result<()> {
+        // Entries were validated when the app was loaded.
+        let allowed_hosts = component
+            .get_metadata(ALLOWED_OUTBOUND_HOSTS_KEY)?
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|host| host.parse::<AllowedOutboundHost>().ok())
+            .collect::<Vec<_>>();
+        data.allowed_servers = self
+            .servers
+            .iter()
+            .filter(|(_, server)| allowed_hosts.iter().any(|host| server.is_allowed_by(host)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! Lets components send email through SMTP servers which the host
+//! configures, managing their TLS and credentials. A component may only use
+//! a server whose `tcp://<host>:<port>` is in its `allowed_outbound_hosts`.
+
+mod host_component;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
+};
+use spin_app::async_trait;
+use spin_manifest::{AllowedOutboundHost, SocketProtocol};
+use spin_world::smtp;
+use tracing::instrument;
+
+pub use host_component::OutboundSmtpComponent;
+
+/// How the connection to an SMTP server is secured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// Upgrade the connection with STARTTLS, usually on port 587.
+    #[default]
+    Starttls,
+    /// Connect with TLS, usually on port 465.
+    Tls,
+    /// Send in plain text. Only suitable for local test servers.
+    None,
+}
+
+/// The configuration of an SMTP server which components may send email
+/// through.
+#[derive(Clone, Debug)]
+pub struct SmtpServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// The username and password to log in with, if the server requires
+    /// them.
+    pub credentials: Option<(String, String)>,
+    /// The sender of messages which don't give one.
+    pub default_sender: Option<String>,
+}
+
+// A configured server, with the transport which connects to it. The
+// transport pools connections, so it is shared by all instances.
+struct SmtpServer {
+    host: String,
+    port: u16,
+    default_sender: Option<String>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpServer {
+    fn new(config: SmtpServerConfig) -> Result<Self> {
+        let mut builder = match config.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        }
+        .port(config.port);
+        if let Some((username, password)) = config.credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            host: config.host,
+            port: config.port,
+            default_sender: config.default_sender,
+            transport: builder.build(),
+        })
+    }
+
+    fn is_allowed_by(&self, allowed: &AllowedOutboundHost) -> bool {
+        allowed.protocol == SocketProtocol::Tcp
+            && allowed.host.eq_ignore_ascii_case(&self.host)
+            && allowed.port == self.port
+    }
+}
+
+/// The state of the smtp interface for one instance.
+pub struct OutboundSmtp {
+    servers: Arc<HashMap<String, SmtpServer>>,
+    // The names of the servers which the component may use.
+    allowed_servers: HashSet<String>,
+}
+
+impl OutboundSmtp {
+    fn server(&self, name: &str) -> Result<&SmtpServer, smtp::Error> {
+        self.servers
+            .get(name)
+            .filter(|_| self.allowed_servers.contains(name))
+            .ok_or(smtp::Error::AccessDenied)
+    }
+}
+
+#[async_trait]
+impl smtp::Host for OutboundSmtp {
+    #[instrument(name = "spin_outbound_smtp.send", skip(self, message), fields(otel.kind = "client"))]
+    async fn send(
+        &mut self,
+        server: String,
+        message: smtp::Message,
+    ) -> Result<Result<(), smtp::Error>> {
+        Ok(async {
+            let server = self.server(&server)?;
+            let email = build_message(message, server.default_sender.as_deref())?;
+            server
+                .transport
+                .send(email)
+                .await
+                .map_err(|err| smtp::Error::SendFailed(err.to_string()))?;
+            Ok(())
+        }
+        .await)
+    }
+}
+
+fn build_message(
+    message: smtp::Message,
+    default_sender: Option<&str>,
+) -> Result<lettre::Message, smtp::Error> {
+    let sender = message
+        .sender
+        .as_deref()
+        .or(default_sender)
+        .ok_or_else(|| {
+            invalid_message("the message has no sender, and the server has no default sender")
+        })?;
+    if message.to.is_empty() && message.cc.is_empty() && message.bcc.is_empty() {
+        return Err(invalid_message("the message has no recipients"));
+    }
+
+    let mut builder = lettre::Message::builder()
+        .from(mailbox(sender)?)
+        .subject(message.subject);
+    for to in &message.to {
+        builder = builder.to(mailbox(to)?);
+    }
+    for cc in &message.cc {
+        builder = builder.cc(mailbox(cc)?);
+    }
+    for bcc in &message.bcc {
+        builder = builder.bcc(mailbox(bcc)?);
+    }
+    if let Some(reply_to) = &message.reply_to {
+        builder = builder.reply_to(mailbox(reply_to)?);
+    }
+
+    match (message.text_body, message.html_body) {
+        (Some(text), Some(html)) => {
+            builder.multipart(MultiPart::alternative_plain_html(text, html))
+        }
+        (None, Some(html)) => builder.header(ContentType::TEXT_HTML).body(html),
+        (text, None) => builder
+            .header(ContentType::TEXT_PLAIN)
+            .body(text.unwrap_or_default()),
+    }
+    .map_err(invalid_message)
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, smtp::Error> {
+    address
+        .parse()
+        .map_err(|err| invalid_message(format!("invalid address {address:?}: {err}")))
+}
+
+fn invalid_message(err: impl std::fmt::Display) -> smtp::Error {
+    smtp::Error::InvalidMessage(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> smtp::Message {
+        smtp::Message {
+            sender: None,
+            to: vec!["Ada <ada@example.com>".to_owned()],
+            cc: vec![],
+            bcc: vec![],
+            reply_to: None,
+            subject: "Welcome".to_owned(),
+            text_body: Some("Hello".to_owned()),
+            html_body: Some("<p>Hello</p>".to_owned()),
+        }
+    }
+
+    #[test]
+    fn messages_use_the_default_sender() {
+        let email = build_message(message(), Some("noreply@example.com")).unwrap();
+        let headers = email.headers().to_string();
+        assert!(headers.contains("From: noreply@example.com"));
+        assert!(headers.contains("ada@example.com"));
+        assert!(headers.contains("multipart/alternative"));
+
+        assert!(matches!(
+            build_message(message(), None),
+            Err(smtp::Error::InvalidMessage(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected() {
+        let mut bad_address = message();
+        bad_address.to = vec!["not an address".to_owned()];
+        assert!(matches!(
+            build_message(bad_address, Some("noreply@example.com")),
+            Err(smtp::Error::InvalidMessage(_))
+        ));
+
+        let mut no_recipients = message();
+        no_recipients.to.clear();
+        assert!(matches!(
+            build_message(no_recipients, Some("noreply@example.com")),
+            Err(smtp::Error::InvalidMessage(_))
+        ));
+    }
+
+    // Building a transport starts its connection pool, which needs a
+    // runtime.
+    #[tokio::test]
+    async fn servers_are_allowed_by_tcp_host_and_port() {
+        let server = |host: &str, port| SmtpServer {
+            host: host.to_owned(),
+            port,
+            default_sender: None,
+            transport: AsyncSmtpTransport::<Tokio1Executor>::unencrypted_localhost(),
+        };
+        let allowed = "tcp://SMTP.example.com:587".parse().unwrap();
+        assert!(server("smtp.example.com", 587).is_allowed_by(&allowed));
+        assert!(!server("smtp.example.com", 465).is_allowed_by(&allowed));
+        assert!(!server("smtp.example.org", 587).is_allowed_by(&allowed));
+    }
+}
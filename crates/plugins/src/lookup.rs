@@ -1,4 +1,5 @@
 use crate::{error::*, git::GitSource, manifest::PluginManifest, store::manifest_file_name};
+use once_cell::sync::OnceCell;
 use semver::Version;
 use std::{
     fs::File,
@@ -16,6 +17,19 @@ const PLUGINS_REPO_MANIFESTS_DIRECTORY: &str = "manifests";
 
 const SPIN_PLUGINS_REPO: &str = "https://github.com/fermyon/spin-plugins/";
 
+/// The environment variable naming a git repository of plugin manifests to
+/// use instead of the spin-plugins repository.
+pub const PLUGINS_REPO_ENV: &str = "SPIN_PLUGINS_REPO";
+
+static DEFAULT_PLUGINS_REPOS: OnceCell<Vec<String>> = OnceCell::new();
+
+/// Sets the git repositories of plugin manifests to use instead of the
+/// spin-plugins repository, unless `SPIN_PLUGINS_REPO` names one. Plugins are
+/// looked up in each repository in turn. Only the first call has any effect.
+pub fn set_default_plugins_repos(repos: Vec<String>) {
+    let _ = DEFAULT_PLUGINS_REPOS.set(repos);
+}
+
 /// Looks up plugin manifests in centralized spin plugin repository.
 pub struct PluginLookup {
     pub name: String,
@@ -30,18 +44,42 @@ impl PluginLookup {
         }
     }
 
+    /// Returns the plugin's manifest from the first plugins repository which
+    /// has it.
     pub async fn get_manifest_from_repository(
         &self,
         plugins_dir: &Path,
     ) -> PluginLookupResult<PluginManifest> {
-        let url = plugins_repo_url()?;
+        let mut result = None;
+        for url in plugins_repo_urls()? {
+            match self.get_manifest_from(&url, plugins_dir).await {
+                Err(Error::NotFound(e)) => {
+                    log::info!("Plugin {} is not in {url}: {e}", self.name);
+                    result = Some(Err(Error::NotFound(e)));
+                }
+                Err(Error::ConnectionFailed(e)) => {
+                    log::warn!("Failed to fetch plugins repository {url}: {e}");
+                    result = Some(Err(Error::ConnectionFailed(e)));
+                }
+                found => return found,
+            }
+        }
+        result.expect("there is always at least one plugins repository")
+    }
+
+    async fn get_manifest_from(
+        &self,
+        url: &Url,
+        plugins_dir: &Path,
+    ) -> PluginLookupResult<PluginManifest> {
         log::info!("Pulling manifest for plugin {} from {url}", self.name);
-        fetch_plugins_repo(&url, plugins_dir, false)
+        fetch_plugins_repo(url, plugins_dir, false)
             .await
             .map_err(|e| {
                 Error::ConnectionFailed(ConnectionFailedError::new(url.to_string(), e.to_string()))
             })?;
-        let expected_path = spin_plugins_repo_manifest_path(&self.name, &self.version, plugins_dir);
+        let expected_path =
+            spin_plugins_repo_manifest_path(&self.name, &self.version, url, plugins_dir);
         let file = File::open(&expected_path).map_err(|e| {
             Error::NotFound(NotFoundError::new(
                 Some(self.name.clone()),
@@ -60,8 +98,30 @@ impl PluginLookup {
     }
 }
 
-pub fn plugins_repo_url() -> Result<Url, url::ParseError> {
-    Url::parse(SPIN_PLUGINS_REPO)
+/// Returns the plugins repositories, in the order in which plugins are
+/// looked up in them.
+pub fn plugins_repo_urls() -> Result<Vec<Url>, url::ParseError> {
+    match std::env::var(PLUGINS_REPO_ENV) {
+        Ok(repo) if !repo.is_empty() => Ok(vec![Url::parse(&repo)?]),
+        _ => match DEFAULT_PLUGINS_REPOS.get() {
+            Some(repos) if !repos.is_empty() => repos.iter().map(|repo| Url::parse(repo)).collect(),
+            _ => Ok(vec![Url::parse(SPIN_PLUGINS_REPO)?]),
+        },
+    }
+}
+
+// A repository other than spin-plugins is cloned to its own directory, so
+// that the manifests of different repositories aren't mixed up.
+fn plugins_repo_local_directory(repo_url: &Url) -> String {
+    if repo_url.as_str() == SPIN_PLUGINS_REPO {
+        return PLUGINS_REPO_LOCAL_DIRECTORY.to_owned();
+    }
+    let name = repo_url
+        .as_str()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("{PLUGINS_REPO_LOCAL_DIRECTORY}-{name}")
 }
 
 pub async fn fetch_plugins_repo(
@@ -69,7 +129,7 @@ pub async fn fetch_plugins_repo(
     plugins_dir: &Path,
     update: bool,
 ) -> anyhow::Result<()> {
-    let git_root = plugin_manifests_repo_path(plugins_dir);
+    let git_root = plugin_manifests_repo_path(repo_url, plugins_dir);
     let git_source = GitSource::new(repo_url, None, &git_root);
     if git_root.join(".git").exists() {
         if update {
@@ -81,8 +141,8 @@ pub async fn fetch_plugins_repo(
     Ok(())
 }
 
-fn plugin_manifests_repo_path(plugins_dir: &Path) -> PathBuf {
-    plugins_dir.join(PLUGINS_REPO_LOCAL_DIRECTORY)
+fn plugin_manifests_repo_path(repo_url: &Url, plugins_dir: &Path) -> PathBuf {
+    plugins_dir.join(plugins_repo_local_directory(repo_url))
 }
 
 // Given a name and option version, outputs expected file name for the plugin.
@@ -98,15 +158,24 @@ fn manifest_file_name_version(plugin_name: &str, version: &Option<semver::Versio
 fn spin_plugins_repo_manifest_path(
     plugin_name: &str,
     plugin_version: &Option<Version>,
+    repo_url: &Url,
     plugins_dir: &Path,
 ) -> PathBuf {
-    spin_plugins_repo_manifest_dir(plugins_dir)
+    plugin_manifests_repo_path(repo_url, plugins_dir)
+        .join(PLUGINS_REPO_MANIFESTS_DIRECTORY)
         .join(plugin_name)
         .join(manifest_file_name_version(plugin_name, plugin_version))
 }
 
-pub fn spin_plugins_repo_manifest_dir(plugins_dir: &Path) -> PathBuf {
-    plugins_dir
-        .join(PLUGINS_REPO_LOCAL_DIRECTORY)
-        .join(PLUGINS_REPO_MANIFESTS_DIRECTORY)
+/// Returns the manifests directories of the local copies of the plugins
+/// repositories, in the order in which plugins are looked up in them.
+pub fn spin_plugins_repo_manifest_dirs(
+    plugins_dir: &Path,
+) -> Result<Vec<PathBuf>, url::ParseError> {
+    Ok(plugins_repo_urls()?
+        .iter()
+        .map(|url| {
+            plugin_manifests_repo_path(url, plugins_dir).join(PLUGINS_REPO_MANIFESTS_DIRECTORY)
+        })
+        .collect())
 }
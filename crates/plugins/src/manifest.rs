@@ -27,6 +27,9 @@ pub struct PluginManifest {
     license: String,
     /// Points to source package[s] of the plugin..
     pub(crate) packages: Vec<PluginPackage>,
+    /// How to run the plugin's own `spin doctor` checks, if it has any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    doctor: Option<PluginDoctor>,
 }
 
 impl PluginManifest {
@@ -52,6 +55,10 @@ impl PluginManifest {
         Url::parse(self.homepage.as_deref()?).ok()
     }
 
+    pub fn doctor(&self) -> Option<&PluginDoctor> {
+        self.doctor.as_ref()
+    }
+
     pub fn has_compatible_package(&self) -> bool {
         self.packages.iter().any(|p| p.matches_current_os_arch())
     }
@@ -66,6 +73,16 @@ impl PluginManifest {
     }
 }
 
+/// Describes how `spin doctor` runs a plugin's checks and treatments. The
+/// plugin is run with these arguments, and speaks the doctor plugin protocol
+/// on its standard input and output.
+#[derive(Serialize, Debug, Deserialize, PartialEq)]
+pub struct PluginDoctor {
+    /// Arguments with which to run the plugin, such as `["doctor"]`.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
 /// Describes compatibility and location of a plugin source.
 #[derive(Serialize, Debug, Deserialize, PartialEq)]
 pub struct PluginPackage {
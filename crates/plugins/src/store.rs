@@ -82,24 +82,26 @@ impl PluginStore {
         // |  |- foo.json
         // |- bar
         //    |- bar.json
-        let catalogue_dir =
-            crate::lookup::spin_plugins_repo_manifest_dir(self.get_plugins_directory());
-
-        // Catalogue directory doesn't exist so likely nothing has been installed.
-        if !catalogue_dir.exists() {
-            return Ok(Vec::new());
+        // There is one catalogue per plugins repository.
+        let catalogue_dirs =
+            crate::lookup::spin_plugins_repo_manifest_dirs(self.get_plugins_directory())?;
+
+        let mut manifests = vec![];
+        for catalogue_dir in catalogue_dirs {
+            // Catalogue directory doesn't exist so likely nothing has been installed.
+            if !catalogue_dir.exists() {
+                continue;
+            }
+
+            let plugin_dirs = catalogue_dir
+                .read_dir()
+                .with_context(|| format!("reading manifest catalogue at {catalogue_dir:?}"))?
+                .filter_map(|d| d.ok())
+                .map(|d| d.path())
+                .filter(|p| p.is_dir());
+            let manifest_paths = plugin_dirs.flat_map(|path| Self::json_files_in(&path));
+            manifests.extend(manifest_paths.filter_map(|path| Self::try_read_manifest_from(&path)));
         }
-
-        let plugin_dirs = catalogue_dir
-            .read_dir()
-            .context("reading manifest catalogue at {catalogue_dir:?}")?
-            .filter_map(|d| d.ok())
-            .map(|d| d.path())
-            .filter(|p| p.is_dir());
-        let manifest_paths = plugin_dirs.flat_map(|path| Self::json_files_in(&path));
-        let manifests: Vec<_> = manifest_paths
-            .filter_map(|path| Self::try_read_manifest_from(&path))
-            .collect();
         Ok(manifests)
     }
 
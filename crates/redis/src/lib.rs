@@ -5,12 +5,17 @@ mod spin;
 use std::collections::HashMap;
 
 use anyhow::{anyhow, Context, Result};
-use futures::StreamExt;
+use futures::{future::Either, StreamExt};
 use redis::{Client, ConnectionLike};
 use serde::{de::IgnoredAny, Deserialize, Serialize};
 use spin_app::MetadataKey;
-use spin_core::async_trait;
-use spin_trigger::{cli::NoArgs, TriggerAppEngine, TriggerExecutor};
+use spin_core::{async_trait, LimitExceeded};
+use spin_trigger::{
+    cli::NoArgs,
+    record::{RecordedBody, TriggerEvent},
+    Invocation, TriggerAppEngine, TriggerExecutor,
+};
+use tracing::instrument;
 
 use crate::spin::SpinRedisExecutor;
 
@@ -89,38 +94,122 @@ impl TriggerExecutor for RedisTrigger {
         }
 
         let mut stream = pubsub.on_message();
-        loop {
-            match stream.next().await {
-                Some(msg) => drop(self.handle(msg).await),
-                None => {
-                    tracing::trace!("Empty message");
-                    if !client.check_connection() {
-                        tracing::info!("No Redis connection available");
-                        break Ok(());
+        let messages = async {
+            loop {
+                match stream.next().await {
+                    Some(msg) => drop(self.handle(msg).await),
+                    None => {
+                        tracing::trace!("Empty message");
+                        if !client.check_connection() {
+                            tracing::info!("No Redis connection available");
+                            break Ok(());
+                        }
                     }
-                }
-            };
+                };
+            }
+        };
+        // Replace the pooled instances taken by messages, and run the tasks
+        // enqueued by messages, while waiting for more.
+        let background = futures::future::join(
+            self.engine.maintain_instance_pools(),
+            self.engine.run_background_tasks(),
+        );
+        futures::pin_mut!(messages, background);
+        match futures::future::select(messages, background).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => unreachable!("background work runs until dropped"),
         }
     }
+
+    fn routes(&self) -> Vec<(String, String)> {
+        let mut routes = self
+            .channel_components
+            .iter()
+            .map(|(channel, component)| (format!("channel {channel}"), component.clone()))
+            .collect::<Vec<_>>();
+        routes.sort();
+        routes
+    }
 }
 
 impl RedisTrigger {
+    /// Returns the engine with which the trigger runs the application's
+    /// components.
+    pub fn engine(&self) -> &TriggerAppEngine<Self> {
+        &self.engine
+    }
+
+    /// Handles a message as if it had been published to the given channel,
+    /// without a Redis server, e.g. for `spin shell`.
+    pub async fn handle_message(&self, channel: &str, payload: &[u8]) -> Result<()> {
+        let component_id = self
+            .channel_components
+            .get(channel)
+            .with_context(|| format!("No component subscribes to channel {channel:?}"))?;
+        self.execute(component_id, channel, payload).await
+    }
+
     // Handle the message.
+    #[instrument(
+        name = "spin_redis_trigger.handle_message",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.destination.name = msg.get_channel_name(),
+            spin.component_id = tracing::field::Empty,
+        )
+    )]
     async fn handle(&self, msg: redis::Msg) -> Result<()> {
         let channel = msg.get_channel_name();
         tracing::info!("Received message on channel {:?}", channel);
 
-        if let Some(component_id) = self.channel_components.get(channel) {
-            tracing::trace!("Executing Redis component {component_id:?}");
-            let executor = SpinRedisExecutor;
-            executor
-                .execute(&self.engine, component_id, channel, msg.get_payload_bytes())
-                .await?
-        } else {
-            tracing::debug!("No subscription found for {:?}", channel);
+        let component_id = self.channel_components.get(channel);
+        let result = match component_id {
+            Some(component_id) => {
+                self.execute(component_id, channel, msg.get_payload_bytes())
+                    .await
+            }
+            None => {
+                tracing::debug!("No subscription found for {:?}", channel);
+                Ok(())
+            }
+        };
+        if let Some(recorder) = self.engine.recorder() {
+            recorder.record(TriggerEvent::Redis {
+                component: component_id.cloned(),
+                channel: channel.to_owned(),
+                payload: RecordedBody::from(msg.get_payload_bytes()),
+                succeeded: result.is_ok(),
+            });
         }
+        result
+    }
 
-        Ok(())
+    async fn execute(&self, component_id: &str, channel: &str, payload: &[u8]) -> Result<()> {
+        tracing::Span::current().record("spin.component_id", component_id);
+        tracing::trace!("Executing Redis component {component_id:?}");
+        let _inflight = spin_telemetry::metrics::track_inflight("redis");
+        let executor = SpinRedisExecutor;
+        let invocation = Invocation::new(Self::TRIGGER_TYPE, component_id)
+            .with_metadata("messaging.destination.name", channel);
+        let start = std::time::Instant::now();
+        let result = self
+            .engine
+            .invoke(
+                &invocation,
+                executor.execute(&self.engine, component_id, channel, payload),
+            )
+            .await;
+        spin_telemetry::metrics::record_trigger_message(
+            "redis",
+            component_id,
+            result.is_ok(),
+            start.elapsed(),
+        );
+        if let Some(limit) = result.as_ref().err().and_then(LimitExceeded::from_error) {
+            tracing::error!("Component {component_id:?} exceeded its {limit}");
+        }
+        result
     }
 }
 
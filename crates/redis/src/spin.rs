@@ -20,14 +20,16 @@ impl RedisExecutor for SpinRedisExecutor {
     ) -> Result<()> {
         tracing::trace!("Executing request using the Spin executor for component {component_id}");
 
-        let (instance, store) = engine.prepare_instance(component_id).await?;
-        let EitherInstance::Component(instance) = instance else {
+        let mut reusable = engine.prepare_reusable_instance(component_id).await?;
+        let EitherInstance::Component(instance) = &reusable.instance else {
             unreachable!()
         };
+        let instance = *instance;
 
-        match Self::execute_impl(store, instance, channel, payload.to_vec()).await {
+        match Self::execute_impl(&mut reusable.store, instance, channel, payload.to_vec()).await {
             Ok(()) => {
                 tracing::trace!("Request finished OK");
+                engine.release_instance(reusable);
                 Ok(())
             }
             Err(e) => {
@@ -40,19 +42,22 @@ impl RedisExecutor for SpinRedisExecutor {
 
 impl SpinRedisExecutor {
     pub async fn execute_impl(
-        mut store: Store,
+        store: &mut Store,
         instance: Instance,
         _channel: &str,
         payload: Vec<u8>,
     ) -> Result<()> {
         let func = instance
-            .exports(&mut store)
+            .exports(&mut *store)
             .instance("inbound-redis")
             .ok_or_else(|| anyhow!("no inbound-redis instance found"))?
             .typed_func::<(PayloadParam,), (Result<(), Error>,)>("handle-message")?;
 
-        match func.call_async(store, (&payload,)).await? {
-            (Ok(()) | Err(Error::Success),) => Ok(()),
+        let (result,) = func.call_async(&mut *store, (&payload,)).await?;
+        // Lets the instance handle another message, if it is reused.
+        func.post_return_async(store).await?;
+        match result {
+            Ok(()) | Err(Error::Success) => Ok(()),
             _ => Err(anyhow!("`handle-message` returned an error")),
         }
     }
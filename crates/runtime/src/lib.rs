@@ -0,0 +1,444 @@
+//! A library API for running Spin applications in process, for Rust servers
+//! which embed the Spin runtime rather than running the `spin` binary.
+//!
+//! An [`AppBuilder`] loads an application from its manifest or from a
+//! registry, and starts its triggers in the current Tokio runtime:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use spin_runtime::AppBuilder;
+//!
+//! let app = AppBuilder::from_manifest("path/to/spin.toml")
+//!     .triggers(["http"])
+//!     .http_address("127.0.0.1:0".parse()?)
+//!     .runtime_config(|config| config.set_instance_pool_size(4))
+//!     .start()
+//!     .await?;
+//! println!("Serving on {}", app.http_address().unwrap());
+//! // ...
+//! app.stop().await;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The HTTP and Redis triggers are supported. Applications loaded from a
+//! manifest should be built first, e.g. with `spin build`.
+#![deny(missing_docs)]
+
+use std::{
+    collections::BTreeSet,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use spin_app::locked::LockedApp;
+use spin_redis_engine::RedisTrigger;
+use spin_trigger::{
+    cli::NoArgs,
+    loader::TriggerLoader,
+    locked::{remove_environment_passthrough, write_locked_app},
+    HostComponentInitData, RuntimeConfig, TriggerExecutor, TriggerExecutorBuilder,
+};
+use spin_trigger_http::HttpTrigger;
+use tempfile::TempDir;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// The port the HTTP trigger listens on, on the loopback address, unless
+/// another address is given.
+const DEFAULT_HTTP_PORT: u16 = 3000;
+
+/// Where an application is loaded from.
+#[derive(Clone, Debug)]
+pub enum AppSource {
+    /// A manifest (spin.toml) file, or a directory containing one.
+    Manifest(PathBuf),
+    /// A registry reference, such as `ghcr.io/example/app:v1`.
+    Registry(String),
+}
+
+type ConfigureRuntime = Box<dyn Fn(&mut RuntimeConfig) + Send + Sync>;
+
+/// Builds and starts a [`RunningApp`].
+pub struct AppBuilder {
+    source: AppSource,
+    profile: Option<String>,
+    environment: Option<String>,
+    triggers: Option<Vec<String>>,
+    runtime_config_files: Vec<PathBuf>,
+    configure_runtime: Vec<ConfigureRuntime>,
+    http_address: SocketAddr,
+    insecure: bool,
+    allow_env_passthrough: bool,
+}
+
+impl AppBuilder {
+    /// Creates a builder for the application loaded from the given source.
+    pub fn new(source: AppSource) -> Self {
+        Self {
+            source,
+            profile: None,
+            environment: None,
+            triggers: None,
+            runtime_config_files: vec![],
+            configure_runtime: vec![],
+            http_address: SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_HTTP_PORT)),
+            insecure: false,
+            allow_env_passthrough: false,
+        }
+    }
+
+    /// Creates a builder for the application with the given manifest (spin.toml)
+    /// file, or in the given directory.
+    pub fn from_manifest(path: impl Into<PathBuf>) -> Self {
+        Self::new(AppSource::Manifest(path.into()))
+    }
+
+    /// Creates a builder for the application with the given registry reference.
+    pub fn from_registry(reference: impl Into<String>) -> Self {
+        Self::new(AppSource::Registry(reference.into()))
+    }
+
+    /// Runs the modules built by the given build profile, such as `release`.
+    /// This only applies to applications loaded from a manifest.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Applies the overrides of the given environment, such as `prod`, from
+    /// the manifest's `[profile.<environment>]` table. This only applies to
+    /// applications loaded from a manifest.
+    pub fn environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Starts only the triggers of the given types, such as `http`. By
+    /// default, all the application's triggers are started.
+    pub fn triggers(mut self, trigger_types: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.triggers = Some(trigger_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Merges the given runtime config file. Later files take precedence over
+    /// earlier ones.
+    pub fn runtime_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.runtime_config_files.push(path.into());
+        self
+    }
+
+    /// Configures the runtime programmatically, after any runtime config
+    /// files are merged. Settings made here take precedence over the files.
+    /// The function is called once for each trigger.
+    pub fn runtime_config(
+        mut self,
+        configure: impl Fn(&mut RuntimeConfig) + Send + Sync + 'static,
+    ) -> Self {
+        self.configure_runtime.push(Box::new(configure));
+        self
+    }
+
+    /// Sets the address the HTTP trigger listens on. Port 0 picks a free port,
+    /// which [`RunningApp::http_address`] returns. The default is
+    /// `127.0.0.1:3000`.
+    pub fn http_address(mut self, address: SocketAddr) -> Self {
+        self.http_address = address;
+        self
+    }
+
+    /// Allows connecting to registries over HTTP rather than HTTPS.
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
+    /// Lets the components of an application from a registry read the host
+    /// environment variables named by their `environment_passthrough`
+    /// entries. By default, those entries are ignored for registry
+    /// applications.
+    pub fn allow_env_passthrough(mut self, allow: bool) -> Self {
+        self.allow_env_passthrough = allow;
+        self
+    }
+
+    /// Loads the application and starts its triggers.
+    pub async fn start(self) -> Result<RunningApp> {
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-runtime-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+
+        let (locked_app, app_dir) = match &self.source {
+            AppSource::Manifest(path) => {
+                let manifest = resolve_manifest(path)?;
+                let app_dir = manifest.parent().map(Path::to_owned);
+                let locked_app = self
+                    .load_manifest(&manifest, app_dir.clone(), working_dir.path())
+                    .await?;
+                (locked_app, app_dir)
+            }
+            AppSource::Registry(reference) => {
+                let locked_app = self.load_registry(reference, working_dir.path()).await?;
+                (locked_app, None)
+            }
+        };
+        let trigger_types = self.trigger_types(&locked_app)?;
+
+        let locked_url = write_locked_app(&locked_app, working_dir.path()).await?;
+
+        let mut running = RunningApp {
+            http_address: None,
+            triggers: vec![],
+            working_dir,
+        };
+        for trigger_type in trigger_types {
+            let runtime_config = self.build_runtime_config(app_dir.clone())?;
+            let task = match trigger_type.as_str() {
+                HttpTrigger::TRIGGER_TYPE => {
+                    let trigger =
+                        build_trigger::<HttpTrigger>(&running, &locked_url, runtime_config).await?;
+                    let listener = TcpListener::bind(self.http_address)
+                        .await
+                        .with_context(|| format!("Failed to listen on {}", self.http_address))?;
+                    running.http_address = Some(listener.local_addr()?);
+                    tokio::spawn(trigger.serve_listener(listener))
+                }
+                RedisTrigger::TRIGGER_TYPE => {
+                    let trigger =
+                        build_trigger::<RedisTrigger>(&running, &locked_url, runtime_config)
+                            .await?;
+                    tokio::spawn(trigger.run(NoArgs))
+                }
+                _ => unreachable!("trigger types are checked when selected"),
+            };
+            running.triggers.push((trigger_type, task));
+        }
+        Ok(running)
+    }
+
+    async fn load_manifest(
+        &self,
+        manifest: &Path,
+        app_dir: Option<PathBuf>,
+        working_dir: &Path,
+    ) -> Result<LockedApp> {
+        let app = spin_loader::local::from_file_with_profile(
+            manifest,
+            Some(working_dir),
+            self.profile.as_deref(),
+            self.environment.as_deref(),
+        )
+        .await?;
+        let mut locked_app = spin_trigger::locked::build_locked_app(app, working_dir)?;
+        if spin_oci::has_registry_dependencies(&locked_app)? {
+            let mut client = self.registry_client(app_dir).await?;
+            client.resolve_dependencies(&mut locked_app).await?;
+        }
+        Ok(locked_app)
+    }
+
+    async fn load_registry(&self, reference: &str, working_dir: &Path) -> Result<LockedApp> {
+        let mut client = self.registry_client(None).await?;
+        let mut locked_app = spin_oci::OciLoader::new(working_dir)
+            .load_app(&mut client, reference)
+            .await?;
+        client.resolve_dependencies(&mut locked_app).await?;
+        if !self.allow_env_passthrough {
+            remove_environment_passthrough(&mut locked_app);
+        }
+        Ok(locked_app)
+    }
+
+    // Registries are trusted, mirrored and cached as the runtime config
+    // says, as they are by `spin up`.
+    async fn registry_client(&self, app_dir: Option<PathBuf>) -> Result<spin_oci::Client> {
+        let runtime_config = self.build_runtime_config(app_dir)?;
+        spin_oci::Client::from_runtime_config(
+            self.insecure,
+            Some(&runtime_config),
+            Default::default(),
+        )
+        .await
+        .context("Failed to create registry client")
+    }
+
+    // Returns the types of the triggers to start, in a stable order.
+    fn trigger_types(&self, locked_app: &LockedApp) -> Result<BTreeSet<String>> {
+        let app_types = locked_app
+            .triggers
+            .iter()
+            .map(|trigger| trigger.trigger_type.clone())
+            .collect::<BTreeSet<_>>();
+        let selected = match &self.triggers {
+            Some(selected) => {
+                if let Some(missing) = selected.iter().find(|t| !app_types.contains(*t)) {
+                    bail!("The application has no {missing:?} triggers");
+                }
+                selected.iter().cloned().collect()
+            }
+            None => app_types,
+        };
+        if let Some(unsupported) = selected.iter().find(|t| !is_supported(t)) {
+            bail!(
+                "The {unsupported:?} trigger cannot run in process; select the triggers to start with `AppBuilder::triggers`"
+            );
+        }
+        Ok(selected)
+    }
+
+    fn build_runtime_config(&self, app_dir: Option<PathBuf>) -> Result<RuntimeConfig> {
+        let mut runtime_config = RuntimeConfig::new(app_dir);
+        for path in &self.runtime_config_files {
+            runtime_config.merge_config_file(path)?;
+        }
+        for configure in &self.configure_runtime {
+            configure(&mut runtime_config);
+        }
+        Ok(runtime_config)
+    }
+}
+
+fn is_supported(trigger_type: &str) -> bool {
+    [HttpTrigger::TRIGGER_TYPE, RedisTrigger::TRIGGER_TYPE].contains(&trigger_type)
+}
+
+fn resolve_manifest(path: &Path) -> Result<PathBuf> {
+    let manifest = if path.is_dir() {
+        path.join("spin.toml")
+    } else {
+        path.to_owned()
+    };
+    manifest
+        .canonicalize()
+        .with_context(|| format!("Failed to find application manifest {manifest:?}"))
+}
+
+async fn build_trigger<Executor: TriggerExecutor>(
+    running: &RunningApp,
+    locked_url: &str,
+    runtime_config: RuntimeConfig,
+) -> Result<Executor>
+where
+    Executor::TriggerConfig: serde::de::DeserializeOwned,
+{
+    let loader = TriggerLoader::new(running.working_dir.path(), false);
+    TriggerExecutorBuilder::<Executor>::new(loader)
+        .build(
+            locked_url.to_owned(),
+            runtime_config,
+            HostComponentInitData::default(),
+        )
+        .await
+        .with_context(|| format!("Failed to start the {:?} trigger", Executor::TRIGGER_TYPE))
+}
+
+/// A Spin application running in process. Its triggers stop when this is
+/// dropped.
+pub struct RunningApp {
+    http_address: Option<SocketAddr>,
+    triggers: Vec<(String, JoinHandle<Result<()>>)>,
+    working_dir: TempDir,
+}
+
+impl RunningApp {
+    /// Returns the address the HTTP trigger is listening on, if it was
+    /// started.
+    pub fn http_address(&self) -> Option<SocketAddr> {
+        self.http_address
+    }
+
+    /// Returns the types of the triggers which were started.
+    pub fn trigger_types(&self) -> impl Iterator<Item = &str> {
+        self.triggers
+            .iter()
+            .map(|(trigger_type, _)| trigger_type.as_str())
+    }
+
+    /// Waits until a trigger stops, e.g. because it failed, and returns its
+    /// result. The other triggers are stopped.
+    pub async fn wait(mut self) -> Result<()> {
+        if self.triggers.is_empty() {
+            return Ok(());
+        }
+        let tasks = self.triggers.iter_mut().map(|(_, task)| task);
+        let (result, index, _) = futures::future::select_all(tasks).await;
+        let trigger_type = &self.triggers[index].0;
+        match result {
+            Ok(result) => result.with_context(|| format!("The {trigger_type:?} trigger failed")),
+            Err(err) => Err(anyhow!(err).context(format!("The {trigger_type:?} trigger panicked"))),
+        }
+    }
+
+    /// Stops the application's triggers. Requests in progress are abandoned.
+    pub async fn stop(mut self) {
+        for (_, task) in self.triggers.drain(..) {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for RunningApp {
+    fn drop(&mut self) {
+        for (_, task) in &self.triggers {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_app(trigger_types: &[&str]) -> LockedApp {
+        let triggers = trigger_types
+            .iter()
+            .enumerate()
+            .map(|(i, trigger_type)| {
+                serde_json::json!({
+                    "id": format!("trigger-{i}"),
+                    "trigger_type": trigger_type,
+                    "trigger_config": {},
+                })
+            })
+            .collect::<Vec<_>>();
+        let json = serde_json::json!({
+            "spin_lock_version": 0,
+            "triggers": triggers,
+            "components": [],
+        });
+        LockedApp::from_json(json.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn selects_trigger_types() {
+        let app = locked_app(&["http", "redis", "http"]);
+        let all = AppBuilder::from_manifest("spin.toml")
+            .trigger_types(&app)
+            .unwrap();
+        assert_eq!(all.into_iter().collect::<Vec<_>>(), ["http", "redis"]);
+
+        let http = AppBuilder::from_manifest("spin.toml")
+            .triggers(["http"])
+            .trigger_types(&app)
+            .unwrap();
+        assert_eq!(http.into_iter().collect::<Vec<_>>(), ["http"]);
+    }
+
+    #[test]
+    fn rejects_missing_and_unsupported_trigger_types() {
+        let app = locked_app(&["http", "sqs"]);
+        assert!(AppBuilder::from_manifest("spin.toml")
+            .trigger_types(&app)
+            .is_err());
+        assert!(AppBuilder::from_manifest("spin.toml")
+            .triggers(["redis"])
+            .trigger_types(&app)
+            .is_err());
+        assert!(AppBuilder::from_manifest("spin.toml")
+            .triggers(["http"])
+            .trigger_types(&app)
+            .is_ok());
+    }
+}
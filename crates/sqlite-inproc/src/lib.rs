@@ -3,6 +3,7 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use async_trait::async_trait;
 use spin_sqlite::Connection;
 use spin_world::sqlite;
 
@@ -31,43 +32,22 @@ impl InProcConnection {
     }
 }
 
+#[async_trait]
 impl Connection for InProcConnection {
-    fn query(
+    async fn query(
         &self,
         query: &str,
         parameters: Vec<spin_world::sqlite::Value>,
     ) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
-        let conn = self.connection.lock().unwrap();
-        let mut statement = conn
-            .prepare_cached(query)
-            .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
-        let columns = statement
-            .column_names()
-            .into_iter()
-            .map(ToOwned::to_owned)
-            .collect();
-        let rows = statement
-            .query_map(
-                rusqlite::params_from_iter(convert_data(parameters.into_iter())),
-                |row| {
-                    let mut values = vec![];
-                    for column in 0.. {
-                        let value = row.get::<usize, ValueWrapper>(column);
-                        if let Err(rusqlite::Error::InvalidColumnIndex(_)) = value {
-                            break;
-                        }
-                        let value = value?.0;
-                        values.push(value);
-                    }
-                    Ok(spin_world::sqlite::RowResult { values })
-                },
-            )
-            .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
-        let rows = rows
-            .into_iter()
-            .map(|r| r.map_err(|e| spin_world::sqlite::Error::Io(e.to_string())))
-            .collect::<Result<_, spin_world::sqlite::Error>>()?;
-        Ok(spin_world::sqlite::QueryResult { columns, rows })
+        // rusqlite blocks the thread, and queries wait for the connection's
+        // lock, so they run on the blocking thread pool.
+        let connection = self.connection.clone();
+        let query = query.to_owned();
+        tokio::task::spawn_blocking(move || {
+            execute_query(&connection.lock().unwrap(), &query, parameters)
+        })
+        .await
+        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?
     }
 
     fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
@@ -77,6 +57,43 @@ impl Connection for InProcConnection {
     }
 }
 
+fn execute_query(
+    conn: &rusqlite::Connection,
+    query: &str,
+    parameters: Vec<spin_world::sqlite::Value>,
+) -> Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error> {
+    let mut statement = conn
+        .prepare_cached(query)
+        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
+    let columns = statement
+        .column_names()
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect();
+    let rows = statement
+        .query_map(
+            rusqlite::params_from_iter(convert_data(parameters.into_iter())),
+            |row| {
+                let mut values = vec![];
+                for column in 0.. {
+                    let value = row.get::<usize, ValueWrapper>(column);
+                    if let Err(rusqlite::Error::InvalidColumnIndex(_)) = value {
+                        break;
+                    }
+                    let value = value?.0;
+                    values.push(value);
+                }
+                Ok(spin_world::sqlite::RowResult { values })
+            },
+        )
+        .map_err(|e| spin_world::sqlite::Error::Io(e.to_string()))?;
+    let rows = rows
+        .into_iter()
+        .map(|r| r.map_err(|e| spin_world::sqlite::Error::Io(e.to_string())))
+        .collect::<Result<_, spin_world::sqlite::Error>>()?;
+    Ok(spin_world::sqlite::QueryResult { columns, rows })
+}
+
 fn convert_data(
     arguments: impl Iterator<Item = spin_world::sqlite::Value>,
 ) -> impl Iterator<Item = rusqlite::types::Value> {
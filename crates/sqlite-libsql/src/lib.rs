@@ -1,3 +1,6 @@
+use std::future::Future;
+
+use async_trait::async_trait;
 use libsql_client::DatabaseClient;
 use spin_world::sqlite::{self, RowResult};
 
@@ -14,8 +17,9 @@ impl LibsqlClient {
     }
 }
 
+#[async_trait]
 impl spin_sqlite::Connection for LibsqlClient {
-    fn query(
+    async fn query(
         &self,
         query: &str,
         parameters: Vec<sqlite::Value>,
@@ -24,17 +28,11 @@ impl spin_sqlite::Connection for LibsqlClient {
             libsql_client::statement::Statement::with_args(query, &convert_parameters(&parameters));
         let client = self.client.clone();
 
-        // It's a bit buried under thread and async shenanigans, but this stanza
-        // just calls libsql's `Client::execute(Statement)` function (and maps the
-        // error case). (It is tricky to make a function to name it, though, because
-        // of Send constraints.)
-        let result = std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(client.execute(stmt))
-        })
-        .join()
-        .unwrap_or_else(|_| Err(anyhow::anyhow!("internal thread error")))
-        .map_err(|e| sqlite::Error::Io(e.to_string()))?;
+        // This just calls libsql's `Client::execute(Statement)` function (and
+        // maps the error case), on a thread of its own.
+        let result = run_on_thread(move || async move { client.execute(stmt).await })
+            .await
+            .map_err(|e| sqlite::Error::Io(e.to_string()))?;
 
         Ok(sqlite::QueryResult {
             columns: result.columns,
@@ -56,7 +54,8 @@ impl spin_sqlite::Connection for LibsqlClient {
         .map(libsql_client::Statement::from)
         .collect();
 
-        // As in `query`, the shenanigans just wrap a call to libsql's `Client::batch()`.
+        // Batches are only executed synchronously, e.g. at startup, so this
+        // waits on a thread of its own for libsql's `Client::batch()`.
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(client.batch(stmts))
@@ -68,6 +67,24 @@ impl spin_sqlite::Connection for LibsqlClient {
     }
 }
 
+// Runs a future on a thread with its own runtime, and waits for it without
+// blocking the caller's thread. The future is created on that thread, so it
+// need not be `Send`.
+async fn run_on_thread<T, F, Fut>(f: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _ = tx.send(rt.block_on(f()));
+    });
+    rx.await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("internal thread error")))
+}
+
 fn convert_rows(rows: Vec<libsql_client::Row>) -> Vec<RowResult> {
     rows.into_iter()
         .map(|r| {
@@ -3,6 +3,7 @@ mod host_component;
 use spin_app::{async_trait, MetadataKey};
 use spin_key_value::table;
 use std::{collections::HashSet, sync::Arc};
+use tracing::instrument;
 
 pub use host_component::SqliteComponent;
 
@@ -20,8 +21,11 @@ pub trait ConnectionsStore: Send + Sync {
 }
 
 /// A trait abstracting over operations to a SQLite database
+#[async_trait]
 pub trait Connection: Send + Sync {
-    fn query(
+    /// Runs a query. Implementations which block on the database must do so
+    /// off the async executor, so that other requests are not stalled.
+    async fn query(
         &self,
         query: &str,
         parameters: Vec<spin_world::sqlite::Value>,
@@ -68,6 +72,7 @@ impl SqliteDispatch {
 
 #[async_trait]
 impl spin_world::sqlite::Host for SqliteDispatch {
+    #[instrument(name = "spin_sqlite.open", skip(self), fields(otel.kind = "client"))]
     async fn open(
         &mut self,
         database: String,
@@ -86,16 +91,22 @@ impl spin_world::sqlite::Host for SqliteDispatch {
         }))
     }
 
+    #[instrument(
+        name = "spin_sqlite.execute",
+        skip(self, connection, query, parameters),
+        fields(otel.kind = "client", db.statement = %query)
+    )]
     async fn execute(
         &mut self,
         connection: spin_world::sqlite::Connection,
         query: String,
         parameters: Vec<spin_world::sqlite::Value>,
     ) -> anyhow::Result<Result<spin_world::sqlite::QueryResult, spin_world::sqlite::Error>> {
-        Ok(tokio::task::block_in_place(|| {
-            let conn = self.get_connection(connection)?;
-            conn.query(&query, parameters)
-        }))
+        let conn = match self.get_connection(connection) {
+            Ok(conn) => conn.clone(),
+            Err(e) => return Ok(Err(e)),
+        };
+        Ok(conn.query(&query, parameters).await)
     }
 
     async fn close(&mut self, connection: spin_world::sqlite::Connection) -> anyhow::Result<()> {
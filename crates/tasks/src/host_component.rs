@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use spin_app::{AppComponent, DynamicHostComponent, MetadataKey};
+use spin_core::HostComponent;
+use spin_world::tasks;
+
+use crate::{TaskStore, TasksDispatch};
+
+// Set by spin-trigger's locked app loader from the application manifest.
+const APP_NAME_KEY: MetadataKey = MetadataKey::new("name");
+
+/// The tasks host component, which enqueues the tasks of every component of
+/// an application in a shared store.
+pub struct TasksComponent {
+    store: Arc<TaskStore>,
+}
+
+impl TasksComponent {
+    pub fn new(store: TaskStore) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    /// The store which tasks are enqueued in, from which the trigger runs
+    /// them.
+    pub fn store(&self) -> Arc<TaskStore> {
+        self.store.clone()
+    }
+}
+
+impl HostComponent for TasksComponent {
+    type Data = TasksDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        tasks::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        TasksDispatch::new(self.store.clone())
+    }
+}
+
+impl DynamicHostComponent for TasksComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let app_name = component
+            .app
+            .get_metadata(APP_NAME_KEY)?
+            .unwrap_or_default();
+        let component_ids = component
+            .app
+            .components()
+            .map(|component| component.id().to_owned())
+            .collect();
+        data.init(app_name, component.id().to_owned(), component_ids);
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Lets components enqueue follow-up invocations, of themselves or of other
+//! components, to run in the background once a delay has passed, so that
+//! work such as sending notifications needn't hold up a response. Tasks are
+//! persisted in a SQLite database until they have run, and are retried with
+//! exponential backoff if they fail. A task is not run before the invocation
+//! which enqueued it has finished.
+
+mod host_component;
+mod store;
+
+use std::{collections::HashSet, mem, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use spin_app::async_trait;
+use spin_world::tasks::{self, RetryPolicy};
+use tracing::instrument;
+
+pub use host_component::TasksComponent;
+use store::HeldTask;
+pub use store::{DatabaseLocation, TaskStore};
+pub use tasks::Error;
+
+/// The longest a task may be delayed, or wait between retries.
+pub const MAX_DELAY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// An enqueued task, to be handled by the `inbound-task` export of a
+/// component.
+#[derive(Clone, Debug)]
+pub struct Task {
+    pub id: String,
+    pub component_id: String,
+    pub payload: Vec<u8>,
+    /// The number of attempts made at the task, including one being made.
+    pub attempt: u32,
+    pub max_retries: u32,
+    /// How long to wait before the first retry.
+    pub backoff: Duration,
+}
+
+impl Task {
+    // The wait before the next attempt, which doubles with each retry.
+    fn retry_backoff(&self) -> Duration {
+        let factor = 2u32.saturating_pow(self.attempt.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(MAX_DELAY)
+    }
+}
+
+pub struct TasksDispatch {
+    store: Arc<TaskStore>,
+    // Tasks are qualified with the application name, so that applications
+    // sharing a state directory don't run each other's tasks.
+    app: String,
+    component_id: String,
+    component_ids: HashSet<String>,
+    // The tasks enqueued by the current invocation, which are held until it
+    // finishes.
+    held: Vec<HeldTask>,
+}
+
+impl TasksDispatch {
+    pub fn new(store: Arc<TaskStore>) -> Self {
+        Self {
+            store,
+            app: String::new(),
+            component_id: String::new(),
+            component_ids: HashSet::new(),
+            held: vec![],
+        }
+    }
+
+    pub fn init(&mut self, app: String, component_id: String, component_ids: HashSet<String>) {
+        self.app = app;
+        self.component_id = component_id;
+        self.component_ids = component_ids;
+    }
+
+    /// Lets the tasks enqueued by the current invocation run, as it has
+    /// finished. This happens when the instance is dropped, so it need only
+    /// be called for an instance which is reused.
+    pub fn invocation_finished(&mut self) {
+        if !self.held.is_empty() {
+            self.store.release(mem::take(&mut self.held));
+        }
+    }
+}
+
+impl Drop for TasksDispatch {
+    fn drop(&mut self) {
+        self.invocation_finished();
+    }
+}
+
+#[async_trait]
+impl tasks::Host for TasksDispatch {
+    #[instrument(name = "spin_tasks.enqueue", skip(self, payload), fields(otel.kind = "producer"))]
+    async fn enqueue(
+        &mut self,
+        component: Option<String>,
+        payload: Vec<u8>,
+        delay_ms: u64,
+        retry: Option<RetryPolicy>,
+    ) -> Result<Result<String, Error>> {
+        Ok(async {
+            let component_id = component.unwrap_or_else(|| self.component_id.clone());
+            if !self.component_ids.contains(&component_id) {
+                return Err(Error::NoSuchComponent);
+            }
+            let retry = retry.unwrap_or(RetryPolicy {
+                max_retries: 0,
+                backoff_ms: 0,
+            });
+            let task = Task {
+                id: uuid::Uuid::new_v4().to_string(),
+                component_id,
+                payload,
+                attempt: 0,
+                max_retries: retry.max_retries,
+                backoff: Duration::from_millis(retry.backoff_ms).min(MAX_DELAY),
+            };
+            let delay = Duration::from_millis(delay_ms).min(MAX_DELAY);
+            let held = self
+                .store
+                .hold(&self.app, &task, delay)
+                .await
+                .map_err(log_error)?;
+            self.held.push(held);
+            Ok(task.id)
+        }
+        .await)
+    }
+}
+
+pub fn log_error(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("tasks error: {err:?}");
+    Error::Io(format!("{err:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks::Host;
+
+    use super::*;
+
+    fn dispatch(store: &Arc<TaskStore>, component_id: &str) -> TasksDispatch {
+        let mut dispatch = TasksDispatch::new(store.clone());
+        let component_ids = ["web", "worker"].into_iter().map(String::from).collect();
+        dispatch.init("app".into(), component_id.into(), component_ids);
+        dispatch
+    }
+
+    fn components(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tasks_run_once_due() -> Result<()> {
+        let store = Arc::new(TaskStore::new(DatabaseLocation::InMemory));
+        let mut web = dispatch(&store, "web");
+
+        let now = web.enqueue(None, b"now".to_vec(), 0, None).await?.unwrap();
+        web.enqueue(Some("worker".into()), b"later".to_vec(), 60_000, None)
+            .await?
+            .unwrap();
+        assert!(matches!(
+            web.enqueue(Some("missing".into()), vec![], 0, None).await?,
+            Err(Error::NoSuchComponent)
+        ));
+
+        // Tasks are held until the invocation which enqueued them finishes.
+        assert!(store
+            .claim_due("app", &components(&["web"]))
+            .await?
+            .is_empty());
+        web.invocation_finished();
+        store.enqueued().await;
+
+        // Tasks are only claimed by the instance running their component,
+        // and only once.
+        assert!(store
+            .claim_due("app", &components(&["worker"]))
+            .await?
+            .is_empty());
+        let due = store.claim_due("app", &components(&["web"])).await?;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, now);
+        assert_eq!(due[0].payload, b"now");
+        assert_eq!(due[0].attempt, 1);
+        assert!(store
+            .claim_due("app", &components(&["web", "worker"]))
+            .await?
+            .is_empty());
+        assert!(store
+            .claim_due("other-app", &components(&["web"]))
+            .await?
+            .is_empty());
+
+        assert!(!store.finish(&due[0], true).await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_tasks_are_retried_as_allowed() -> Result<()> {
+        let store = Arc::new(TaskStore::new(DatabaseLocation::InMemory));
+        let mut worker = dispatch(&store, "worker");
+        let retry = RetryPolicy {
+            max_retries: 1,
+            backoff_ms: 0,
+        };
+        worker.enqueue(None, vec![], 0, Some(retry)).await?.unwrap();
+        drop(worker);
+        store.enqueued().await;
+        let worker_ids = components(&["worker"]);
+
+        let first = store.claim_due("app", &worker_ids).await?.remove(0);
+        assert!(store.finish(&first, false).await?);
+        let second = store.claim_due("app", &worker_ids).await?.remove(0);
+        assert_eq!(second.attempt, 2);
+        assert!(!store.finish(&second, false).await?);
+        assert!(store.claim_due("app", &worker_ids).await?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn retry_backoff_doubles() {
+        let mut task = Task {
+            id: "id".into(),
+            component_id: "worker".into(),
+            payload: vec![],
+            attempt: 1,
+            max_retries: 3,
+            backoff: Duration::from_millis(100),
+        };
+        assert_eq!(task.retry_backoff(), Duration::from_millis(100));
+        task.attempt = 3;
+        assert_eq!(task.retry_backoff(), Duration::from_millis(400));
+        task.attempt = 100;
+        assert_eq!(task.retry_backoff(), MAX_DELAY);
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use rusqlite::{params, params_from_iter, Connection, ToSql};
+use tokio::{
+    sync::{Notify, OnceCell},
+    task,
+};
+
+use crate::Task;
+
+// How long to wait for another instance's write to the database to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// How long a task is held by the instance running it, or by the invocation
+// which enqueued it. If the instance stops before the task is finished or
+// released, the task runs once this has passed.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+// The most tasks which are taken to run at once.
+const CLAIM_BATCH_SIZE: usize = 16;
+
+#[derive(Clone)]
+pub enum DatabaseLocation {
+    InMemory,
+    Path(PathBuf),
+}
+
+/// A task which has been enqueued by an invocation which has not finished,
+/// and so is not yet run.
+pub(crate) struct HeldTask {
+    id: String,
+    run_at: i64,
+}
+
+/// Keeps enqueued tasks in a SQLite database until they have run.
+pub struct TaskStore {
+    location: DatabaseLocation,
+    connection: OnceCell<Arc<Mutex<Connection>>>,
+    enqueued: Notify,
+}
+
+impl TaskStore {
+    pub fn new(location: DatabaseLocation) -> Self {
+        Self {
+            location,
+            connection: OnceCell::new(),
+            enqueued: Notify::new(),
+        }
+    }
+
+    async fn connection(&self) -> Result<Arc<Mutex<Connection>>> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| {
+                let location = self.location.clone();
+                async move { task::spawn_blocking(move || open(&location)).await? }
+            })
+            .await?;
+        Ok(connection.clone())
+    }
+
+    // Runs `f` with the connection on a blocking thread, as SQLite calls
+    // block while another instance writes to the database.
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let connection = self.connection().await?;
+        task::spawn_blocking(move || f(&mut connection.lock().unwrap())).await?
+    }
+
+    /// Adds a task for the application, to run once `delay` has passed, but
+    /// not before it is released with [`Self::release`].
+    pub(crate) async fn hold(&self, app: &str, task: &Task, delay: Duration) -> Result<HeldTask> {
+        let held = HeldTask {
+            id: task.id.clone(),
+            run_at: after(delay),
+        };
+        let (app, task) = (app.to_owned(), task.clone());
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT INTO spin_task
+                   (id, app, component, payload, attempts, max_retries, backoff_ms, run_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    task.id,
+                    app,
+                    task.component_id,
+                    task.payload,
+                    task.attempt,
+                    task.max_retries,
+                    millis(task.backoff),
+                    after(CLAIM_TIMEOUT),
+                ],
+            )?;
+            Ok(())
+        })
+        .await?;
+        Ok(held)
+    }
+
+    /// Lets held tasks run once they are due, in the background.
+    pub(crate) fn release(self: &Arc<Self>, held: Vec<HeldTask>) {
+        let store = self.clone();
+        let release = async move {
+            let released = store
+                .with_connection(move |connection| {
+                    // A task which has already been claimed, because the
+                    // invocation which held it outlived its hold, is left
+                    // alone.
+                    for task in held {
+                        connection.execute(
+                            "UPDATE spin_task SET run_at = ?2 WHERE id = ?1 AND attempts = 0",
+                            params![task.id, task.run_at],
+                        )?;
+                    }
+                    Ok(())
+                })
+                .await;
+            match released {
+                Ok(()) => store.enqueued.notify_one(),
+                Err(err) => tracing::warn!("Failed to release enqueued tasks: {err:?}"),
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn(release)),
+            // The tasks run once their hold times out.
+            Err(_) => tracing::warn!("Enqueued tasks released outside of a runtime"),
+        }
+    }
+
+    /// Takes the application's tasks which are due for the given components,
+    /// counting an attempt at each. Each task must be given to
+    /// [`Self::finish`] once it has run, or it runs again once its claim
+    /// times out.
+    pub async fn claim_due(&self, app: &str, component_ids: &[String]) -> Result<Vec<Task>> {
+        if component_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let (app, component_ids) = (app.to_owned(), component_ids.to_owned());
+        self.with_connection(move |connection| {
+            let now = after(Duration::ZERO);
+            let placeholders = vec!["?"; component_ids.len()].join(", ");
+            let mut query_params: Vec<&dyn ToSql> = vec![&app, &now];
+            query_params.extend(component_ids.iter().map(|id| id as &dyn ToSql));
+            let due = connection
+                .prepare(&format!(
+                    "SELECT id, component, payload, attempts, max_retries, backoff_ms, run_at
+                     FROM spin_task
+                     WHERE app = ? AND run_at <= ? AND component IN ({placeholders})
+                     ORDER BY run_at
+                     LIMIT {CLAIM_BATCH_SIZE}"
+                ))?
+                .query_map(params_from_iter(query_params), |row| {
+                    let task = Task {
+                        id: row.get(0)?,
+                        component_id: row.get(1)?,
+                        payload: row.get(2)?,
+                        attempt: row.get(3)?,
+                        max_retries: row.get(4)?,
+                        backoff: Duration::from_millis(row.get(5)?),
+                    };
+                    let run_at: i64 = row.get(6)?;
+                    Ok((task, run_at))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // Another instance sharing the database may claim a task first,
+            // in which case its run time has changed.
+            let claimed_until = after(CLAIM_TIMEOUT);
+            let mut claimed = vec![];
+            for (mut task, run_at) in due {
+                let changed = connection.execute(
+                    "UPDATE spin_task SET attempts = attempts + 1, run_at = ?3
+                     WHERE id = ?1 AND run_at = ?2",
+                    params![task.id, run_at, claimed_until],
+                )?;
+                if changed == 1 {
+                    task.attempt += 1;
+                    claimed.push(task);
+                }
+            }
+            Ok(claimed)
+        })
+        .await
+    }
+
+    /// Removes a claimed task which has run, unless it failed and its retry
+    /// policy allows another attempt, in which case it is scheduled after
+    /// its backoff. Returns whether the task will be retried.
+    pub async fn finish(&self, task: &Task, succeeded: bool) -> Result<bool> {
+        let retry = !succeeded && task.attempt <= task.max_retries;
+        let (id, run_at) = (task.id.clone(), after(task.retry_backoff()));
+        self.with_connection(move |connection| {
+            if retry {
+                connection.execute(
+                    "UPDATE spin_task SET run_at = ?2 WHERE id = ?1",
+                    params![id, run_at],
+                )?;
+            } else {
+                connection.execute("DELETE FROM spin_task WHERE id = ?1", params![id])?;
+            }
+            Ok(())
+        })
+        .await?;
+        Ok(retry)
+    }
+
+    /// Waits until a task enqueued in this store by this instance is
+    /// released.
+    pub async fn enqueued(&self) {
+        self.enqueued.notified().await
+    }
+}
+
+fn open(location: &DatabaseLocation) -> Result<Arc<Mutex<Connection>>> {
+    let connection = match location {
+        DatabaseLocation::InMemory => Connection::open_in_memory(),
+        DatabaseLocation::Path(path) => Connection::open(path),
+    }?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS spin_task (
+           id          TEXT PRIMARY KEY,
+           app         TEXT NOT NULL,
+           component   TEXT NOT NULL,
+           payload     BLOB NOT NULL,
+           attempts    INTEGER NOT NULL,
+           max_retries INTEGER NOT NULL,
+           backoff_ms  INTEGER NOT NULL,
+           run_at      INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(Arc::new(Mutex::new(connection)))
+}
+
+fn after(delay: Duration) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, millis);
+    now.saturating_add(millis(delay))
+}
+
+fn millis(duration: Duration) -> i64 {
+    duration.as_millis().try_into().unwrap_or(i64::MAX)
+}
@@ -0,0 +1,112 @@
+//! Tracks the trigger invocation, if any, which each span belongs to.
+
+use std::{cell::RefCell, sync::Arc};
+
+use once_cell::sync::OnceCell;
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// The span field which identifies the component handling a trigger event.
+pub const COMPONENT_ID_FIELD: &str = "spin.component_id";
+
+/// The span field which identifies the request being handled.
+pub const REQUEST_ID_FIELD: &str = "spin.request_id";
+
+// The invocation of each span, shared with its descendants. Its fields may be
+// recorded on trigger spans after they are created, so are filled in later.
+#[derive(Default)]
+struct Invocation {
+    component_id: OnceCell<String>,
+    request_id: OnceCell<String>,
+}
+
+thread_local! {
+    // The invocations of the spans entered on this thread.
+    static ENTERED: RefCell<Vec<Arc<Invocation>>> = RefCell::new(vec![]);
+}
+
+fn current<T>(f: impl FnOnce(&Invocation) -> Option<T>) -> Option<T> {
+    ENTERED.with(|entered| entered.borrow().last().map(Arc::as_ref).and_then(f))
+}
+
+/// Returns the ID of the component handling the current trigger event.
+pub(crate) fn current_component_id() -> Option<String> {
+    current(|inv| inv.component_id.get().cloned())
+}
+
+/// Returns the ID of the request being handled in the current span, if any.
+pub fn current_request_id() -> Option<String> {
+    current(|inv| inv.request_id.get().cloned())
+}
+
+/// Tracks which invocation each span belongs to.
+pub(crate) struct InvocationContextLayer;
+
+impl<S> Layer<S> for InvocationContextLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let fields = attrs.fields();
+        let invocation = if fields.field(COMPONENT_ID_FIELD).is_some()
+            || fields.field(REQUEST_ID_FIELD).is_some()
+        {
+            let invocation = Arc::new(Invocation::default());
+            attrs.record(&mut InvocationVisitor(&invocation));
+            invocation
+        } else {
+            span.parent()
+                .and_then(|parent| parent.extensions().get::<Arc<Invocation>>().cloned())
+                .unwrap_or_default()
+        };
+        span.extensions_mut().insert(invocation);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(invocation) = span.extensions().get::<Arc<Invocation>>() {
+                values.record(&mut InvocationVisitor(invocation));
+            }
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let invocation = span
+                .extensions()
+                .get::<Arc<Invocation>>()
+                .cloned()
+                .unwrap_or_default();
+            ENTERED.with(|entered| entered.borrow_mut().push(invocation));
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if ctx.span(id).is_some() {
+            ENTERED.with(|entered| entered.borrow_mut().pop());
+        }
+    }
+}
+
+struct InvocationVisitor<'a>(&'a Invocation);
+
+impl Visit for InvocationVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        let cell = match field.name() {
+            COMPONENT_ID_FIELD => &self.0.component_id,
+            REQUEST_ID_FIELD => &self.0.request_id,
+            _ => return,
+        };
+        let _ = cell.set(value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"))
+    }
+}
@@ -0,0 +1,43 @@
+//! Spans opened by components, which are exported along with the runtime's
+//! own spans.
+
+use opentelemetry::{
+    global,
+    trace::{Span, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::COMPONENT_ID_FIELD;
+
+/// A span opened by a component. The span ends when it is dropped, if it
+/// has not been ended explicitly.
+pub struct GuestSpan {
+    context: Context,
+}
+
+impl GuestSpan {
+    /// Starts a span which is a child of `parent` or, if that is `None`, of
+    /// the current span of the runtime.
+    pub fn start(name: String, component_id: &str, parent: Option<&GuestSpan>) -> Self {
+        let parent_context = match parent {
+            Some(parent) => parent.context.clone(),
+            None => tracing::Span::current().context(),
+        };
+        let mut span = global::tracer("spin").start_with_context(name, &parent_context);
+        span.set_attribute(KeyValue::new(COMPONENT_ID_FIELD, component_id.to_owned()));
+        Self {
+            context: parent_context.with_span(span),
+        }
+    }
+
+    /// Sets an attribute on the span.
+    pub fn set_attribute(&self, key: String, value: String) {
+        self.context.span().set_attribute(KeyValue::new(key, value));
+    }
+
+    /// Ends the span.
+    pub fn end(self) {
+        self.context.span().end();
+    }
+}
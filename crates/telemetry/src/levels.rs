@@ -0,0 +1,203 @@
+//! Log levels for the runtime and for individual components, which take
+//! precedence over the `RUST_LOG` filter.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use tracing::{metadata::LevelFilter, span, subscriber::Interest, Metadata, Subscriber};
+use tracing_subscriber::{
+    layer::{Context, Filter},
+    registry::LookupSpan,
+    EnvFilter,
+};
+
+use crate::context::current_component_id;
+
+/// Log levels which override the `RUST_LOG` filter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogLevels {
+    /// The level for log lines emitted outside of any component's execution.
+    pub runtime: Option<LevelFilter>,
+    /// The level for log lines emitted while executing each component,
+    /// keyed by component ID.
+    pub components: HashMap<String, LevelFilter>,
+}
+
+impl LogLevels {
+    /// The level which applies to the given component, if any.
+    pub fn component_level(&self, component_id: &str) -> Option<LevelFilter> {
+        self.components.get(component_id).copied().or(self.runtime)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.runtime.is_none() && self.components.is_empty()
+    }
+
+    // The most verbose of the configured levels.
+    fn max_level(&self) -> LevelFilter {
+        self.components
+            .values()
+            .copied()
+            .chain(self.runtime)
+            .max()
+            .unwrap_or(LevelFilter::OFF)
+    }
+
+    // The most verbose level which may be enabled, given the hint of the
+    // `RUST_LOG` filter, which applies where no level is configured.
+    fn max_level_hint(&self, env_hint: Option<LevelFilter>) -> Option<LevelFilter> {
+        if self.runtime.is_some() {
+            Some(self.max_level())
+        } else {
+            Some(self.max_level().max(env_hint?))
+        }
+    }
+}
+
+// The configured levels are replaced as a whole, and each replacement bumps
+// the generation. Every event is filtered by them, so each thread keeps the
+// levels of the generation it last saw, and only takes the lock when the
+// generation has changed.
+static LOG_LEVELS: RwLock<Option<Arc<LogLevels>>> = RwLock::new(None);
+static LOG_LEVELS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CACHED_LOG_LEVELS: RefCell<(u64, Option<Arc<LogLevels>>)> = RefCell::new((0, None));
+}
+
+/// Sets the log levels which override the `RUST_LOG` filter.
+pub fn set_log_levels(levels: LogLevels) {
+    *LOG_LEVELS.write().unwrap() = (!levels.is_empty()).then(|| Arc::new(levels));
+    LOG_LEVELS_GENERATION.fetch_add(1, Ordering::Release);
+    // Callsites which `RUST_LOG` disabled may now be enabled, or vice versa.
+    tracing::callsite::rebuild_interest_cache();
+}
+
+fn current_log_levels() -> Option<Arc<LogLevels>> {
+    let generation = LOG_LEVELS_GENERATION.load(Ordering::Acquire);
+    CACHED_LOG_LEVELS
+        .try_with(|cached| {
+            let mut cached = cached.borrow_mut();
+            if cached.0 != generation {
+                *cached = (generation, LOG_LEVELS.read().unwrap().clone());
+            }
+            cached.1.clone()
+        })
+        // Events may be emitted as the thread's locals are destroyed.
+        .unwrap_or_else(|_| LOG_LEVELS.read().unwrap().clone())
+}
+
+fn current_component_level(levels: &LogLevels) -> Option<LevelFilter> {
+    match current_component_id() {
+        Some(id) => levels.component_level(&id),
+        None => levels.runtime,
+    }
+}
+
+/// Filters log output by the configured [`LogLevels`], falling back to the
+/// `RUST_LOG` filter where no level is configured.
+pub(crate) struct LogLevelFilter(pub EnvFilter);
+
+impl<S> Filter<S> for LogLevelFilter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        if let Some(levels) = current_log_levels() {
+            if let Some(level) = current_component_level(&levels) {
+                return level >= *meta.level();
+            }
+        }
+        Filter::<S>::enabled(&self.0, meta, cx)
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        let interest = Filter::<S>::callsite_enabled(&self.0, meta);
+        match current_log_levels() {
+            // A callsite which neither `RUST_LOG` nor any configured level
+            // enables stays disabled.
+            Some(levels) if interest.is_never() && levels.max_level() < *meta.level() => interest,
+            // Otherwise, whether it is enabled depends on the current
+            // component.
+            Some(_) => Interest::sometimes(),
+            None => interest,
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        let env_hint = Filter::<S>::max_level_hint(&self.0);
+        match current_log_levels() {
+            Some(levels) => levels.max_level_hint(env_hint),
+            None => env_hint,
+        }
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_new_span(&self.0, attrs, id, ctx)
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        Filter::<S>::on_record(&self.0, id, values, ctx)
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_enter(&self.0, id, ctx)
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_exit(&self.0, id, ctx)
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_close(&self.0, id, ctx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn component_level_falls_back_to_runtime_level() {
+        let levels = LogLevels {
+            runtime: Some(LevelFilter::WARN),
+            components: [("chatty".to_owned(), LevelFilter::ERROR)].into(),
+        };
+        assert_eq!(levels.component_level("chatty"), Some(LevelFilter::ERROR));
+        assert_eq!(levels.component_level("quiet"), Some(LevelFilter::WARN));
+        assert_eq!(LogLevels::default().component_level("quiet"), None);
+    }
+
+    #[test]
+    fn max_level_hint_covers_configured_and_env_levels() {
+        let components = LogLevels {
+            runtime: None,
+            components: [("chatty".to_owned(), LevelFilter::DEBUG)].into(),
+        };
+        assert_eq!(
+            components.max_level_hint(Some(LevelFilter::INFO)),
+            Some(LevelFilter::DEBUG)
+        );
+        assert_eq!(
+            components.max_level_hint(Some(LevelFilter::TRACE)),
+            Some(LevelFilter::TRACE)
+        );
+        assert_eq!(components.max_level_hint(None), None);
+
+        // With a runtime level, the `RUST_LOG` filter never applies.
+        let runtime = LogLevels {
+            runtime: Some(LevelFilter::WARN),
+            ..components
+        };
+        assert_eq!(
+            runtime.max_level_hint(Some(LevelFilter::TRACE)),
+            Some(LevelFilter::DEBUG)
+        );
+    }
+}
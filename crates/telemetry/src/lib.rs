@@ -0,0 +1,241 @@
+//! Diagnostic output for Spin: log output to stderr or the system log,
+//! Prometheus metrics and, if configured, export of trace spans to an
+//! OpenTelemetry collector.
+
+#![deny(missing_docs)]
+
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::OnceCell;
+use tracing_subscriber::{
+    filter::{filter_fn, LevelFilter},
+    fmt,
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+mod context;
+pub mod guest;
+mod levels;
+pub mod metrics;
+mod otlp;
+mod propagation;
+#[cfg(unix)]
+mod system_log;
+
+pub use context::{current_request_id, COMPONENT_ID_FIELD, REQUEST_ID_FIELD};
+pub use levels::{set_log_levels, LogLevels};
+pub use otlp::set_default_otlp_endpoint;
+pub use propagation::{
+    extract_trace_context, incoming_request_id, inject_request_id, inject_trace_context,
+    REQUEST_ID_HEADER,
+};
+
+/// The environment variable which selects the [`LogFormat`].
+pub const LOG_FORMAT_ENV: &str = "SPIN_LOG_FORMAT";
+
+/// The environment variable which selects the [`LogTarget`].
+pub const LOG_TARGET_ENV: &str = "SPIN_LOG_TARGET";
+
+/// The tracing target of events which carry the output of a component when
+/// logging to the system log.
+pub const COMPONENT_OUTPUT_TARGET: &str = "spin_component";
+
+/// The format in which log lines are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// One JSON record per line.
+    Json,
+}
+
+impl LogFormat {
+    /// Reads the log format from the `SPIN_LOG_FORMAT` environment variable,
+    /// defaulting to that set by [`LogFormat::set_default`], or otherwise
+    /// [`LogFormat::Text`], if it is unset.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(LOG_FORMAT_ENV) {
+            Ok(value) => value
+                .parse()
+                .with_context(|| format!("Invalid {LOG_FORMAT_ENV}")),
+            Err(_) => Ok(DEFAULT_LOG_FORMAT.get().copied().unwrap_or_default()),
+        }
+    }
+
+    /// Sets the log format used if `SPIN_LOG_FORMAT` is unset. Only the first
+    /// call has any effect.
+    pub fn set_default(format: Self) {
+        let _ = DEFAULT_LOG_FORMAT.set(format);
+    }
+
+    /// The name of the format, as accepted by [`LogFormat::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => bail!("unknown log format {s:?}: expected 'text' or 'json'"),
+        }
+    }
+}
+
+static DEFAULT_LOG_FORMAT: OnceCell<LogFormat> = OnceCell::new();
+
+/// Where log lines are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogTarget {
+    /// The standard error stream.
+    #[default]
+    Stderr,
+    /// The local syslog daemon.
+    Syslog,
+    /// systemd-journald.
+    Journald,
+}
+
+impl LogTarget {
+    /// Reads the log target from the `SPIN_LOG_TARGET` environment variable,
+    /// defaulting to [`LogTarget::Stderr`] if it is unset.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(LOG_TARGET_ENV) {
+            Ok(value) => value
+                .parse()
+                .with_context(|| format!("Invalid {LOG_TARGET_ENV}")),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// The name of the target, as accepted by [`LogTarget::from_str`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stderr => "stderr",
+            Self::Syslog => "syslog",
+            Self::Journald => "journald",
+        }
+    }
+}
+
+impl FromStr for LogTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "stderr" => Ok(Self::Stderr),
+            "syslog" => Ok(Self::Syslog),
+            "journald" => Ok(Self::Journald),
+            _ => bail!("unknown log target {s:?}: expected 'stderr', 'syslog' or 'journald'"),
+        }
+    }
+}
+
+/// Installs the global tracing subscriber.
+///
+/// Log output is written to `target`, in the given `format` if that is stderr,
+/// filtered by
+/// `filter` except where overridden by [`set_log_levels`]. If an OTLP endpoint
+/// is configured through the standard `OTEL_EXPORTER_OTLP_*` environment
+/// variables, spans are also exported to it, independently of `filter`.
+///
+/// Spans which have not yet been exported are flushed when the returned
+/// guard is dropped.
+pub fn init(
+    filter: EnvFilter,
+    ansi: bool,
+    format: LogFormat,
+    target: LogTarget,
+) -> Result<ShutdownGuard> {
+    let log_layer = match (target, format) {
+        (LogTarget::Stderr, LogFormat::Text) => fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(ansi)
+            .boxed(),
+        (LogTarget::Stderr, LogFormat::Json) => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(std::io::stderr)
+            .boxed(),
+        #[cfg(unix)]
+        (LogTarget::Syslog, _) => system_log::SyslogLayer::new()?.boxed(),
+        #[cfg(unix)]
+        (LogTarget::Journald, _) => system_log::journald_layer()?.boxed(),
+        #[cfg(not(unix))]
+        (target, _) => bail!(
+            "log target '{}' is not supported on this platform",
+            target.as_str()
+        ),
+    };
+    // Component output is sent to the system log as events, which must not be
+    // dropped by the default filter.
+    let filter = match target {
+        LogTarget::Stderr => filter,
+        _ => filter.add_directive(format!("{COMPONENT_OUTPUT_TARGET}=trace").parse()?),
+    };
+    let log_layer = log_layer.with_filter(levels::LogLevelFilter(filter));
+
+    let otel_layer = match otlp::OtlpConfig::from_env()? {
+        Some(config) => {
+            let tracer = config.install_tracer()?;
+            let layer = tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(LevelFilter::INFO);
+            Some(layer)
+        }
+        None => None,
+    };
+    let exporting = otel_layer.is_some();
+
+    let metrics_layer = metrics::HostCallMetricsLayer.with_filter(filter_fn(|meta| {
+        meta.is_span() && meta.name().starts_with("spin_")
+    }));
+
+    tracing_subscriber::registry()
+        .with(context::InvocationContextLayer)
+        .with(log_layer)
+        .with(otel_layer)
+        .with(metrics_layer)
+        .init();
+
+    Ok(ShutdownGuard { exporting })
+}
+
+/// Flushes exported spans when dropped.
+#[must_use]
+pub struct ShutdownGuard {
+    exporting: bool,
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        if self.exporting {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_log_targets() {
+        for target in [LogTarget::Stderr, LogTarget::Syslog, LogTarget::Journald] {
+            assert_eq!(target.as_str().parse::<LogTarget>().unwrap(), target);
+        }
+        assert!("file".parse::<LogTarget>().is_err());
+    }
+}
@@ -0,0 +1,479 @@
+//! Prometheus metrics for the Spin runtime.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use once_cell::sync::Lazy;
+use prometheus::{
+    histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, IntGaugeVec, Registry, TextEncoder,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context as LayerContext, Layer};
+
+static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    instantiation_duration: HistogramVec,
+    instance_pool_takes: IntCounterVec,
+    host_calls: IntCounterVec,
+    inflight: IntGaugeVec,
+    trigger_messages: IntCounterVec,
+    trigger_message_duration: HistogramVec,
+    trigger_retries: IntCounterVec,
+    trigger_dead_letters: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let http_requests = IntCounterVec::new(
+            opts!(
+                "spin_http_requests_total",
+                "HTTP requests handled by components"
+            ),
+            &["component_id", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            histogram_opts!(
+                "spin_http_request_duration_seconds",
+                "Time taken by components to handle HTTP requests"
+            ),
+            &["component_id", "route"],
+        )
+        .unwrap();
+        let instantiation_duration = HistogramVec::new(
+            histogram_opts!(
+                "spin_component_instantiation_duration_seconds",
+                "Time taken to instantiate components"
+            ),
+            &["component_id"],
+        )
+        .unwrap();
+        let instance_pool_takes = IntCounterVec::new(
+            opts!(
+                "spin_instance_pool_takes_total",
+                "Requests for instances of components with an instance pool"
+            ),
+            &["component_id", "outcome"],
+        )
+        .unwrap();
+        let host_calls = IntCounterVec::new(
+            opts!(
+                "spin_host_calls_total",
+                "Calls made by components to host interfaces"
+            ),
+            &["interface", "function"],
+        )
+        .unwrap();
+        let inflight = IntGaugeVec::new(
+            opts!(
+                "spin_trigger_inflight_events",
+                "Trigger events currently being handled"
+            ),
+            &["trigger_type"],
+        )
+        .unwrap();
+
+        let trigger_messages = IntCounterVec::new(
+            opts!(
+                "spin_trigger_messages_total",
+                "Messages handled by components of message-driven triggers"
+            ),
+            &["trigger_type", "component_id", "outcome"],
+        )
+        .unwrap();
+        let trigger_message_duration = HistogramVec::new(
+            histogram_opts!(
+                "spin_trigger_message_duration_seconds",
+                "Time taken by components to handle messages"
+            ),
+            &["trigger_type", "component_id"],
+        )
+        .unwrap();
+        let trigger_retries = IntCounterVec::new(
+            opts!(
+                "spin_trigger_retries_total",
+                "Failed messages given back to be delivered to components again"
+            ),
+            &["trigger_type", "component_id"],
+        )
+        .unwrap();
+        let trigger_dead_letters = IntCounterVec::new(
+            opts!(
+                "spin_trigger_dead_letters_total",
+                "Failed messages moved to a dead-letter queue"
+            ),
+            &["trigger_type", "component_id"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(instantiation_duration.clone()),
+            Box::new(instance_pool_takes.clone()),
+            Box::new(host_calls.clone()),
+            Box::new(inflight.clone()),
+            Box::new(trigger_messages.clone()),
+            Box::new(trigger_message_duration.clone()),
+            Box::new(trigger_retries.clone()),
+            Box::new(trigger_dead_letters.clone()),
+        ] {
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            instantiation_duration,
+            instance_pool_takes,
+            host_calls,
+            inflight,
+            trigger_messages,
+            trigger_message_duration,
+            trigger_retries,
+            trigger_dead_letters,
+        }
+    }
+}
+
+/// Records an HTTP request handled by a component.
+pub fn record_http_request(component_id: &str, route: &str, status: u16, duration: Duration) {
+    METRICS
+        .http_requests
+        .with_label_values(&[component_id, route, &status.to_string()])
+        .inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&[component_id, route])
+        .observe(duration.as_secs_f64());
+}
+
+/// Records a message handled by a component of a message-driven trigger,
+/// such as Redis.
+pub fn record_trigger_message(
+    trigger_type: &str,
+    component_id: &str,
+    succeeded: bool,
+    duration: Duration,
+) {
+    let outcome = if succeeded { "success" } else { "error" };
+    METRICS
+        .trigger_messages
+        .with_label_values(&[trigger_type, component_id, outcome])
+        .inc();
+    METRICS
+        .trigger_message_duration
+        .with_label_values(&[trigger_type, component_id])
+        .observe(duration.as_secs_f64());
+}
+
+/// Records a message which a component failed to handle being given back to
+/// its trigger's source, to be delivered again. Only triggers whose source
+/// redelivers messages, such as Azure Service Bus, record retries; Redis
+/// pub/sub messages are delivered once.
+pub fn record_trigger_retry(trigger_type: &str, component_id: &str) {
+    METRICS
+        .trigger_retries
+        .with_label_values(&[trigger_type, component_id])
+        .inc();
+}
+
+/// Records a message which a component failed to handle too many times being
+/// moved to its trigger source's dead-letter queue.
+pub fn record_trigger_dead_letter(trigger_type: &str, component_id: &str) {
+    METRICS
+        .trigger_dead_letters
+        .with_label_values(&[trigger_type, component_id])
+        .inc();
+}
+
+/// Records the time taken to instantiate a component.
+pub fn record_instantiation(component_id: &str, duration: Duration) {
+    METRICS
+        .instantiation_duration
+        .with_label_values(&[component_id])
+        .observe(duration.as_secs_f64());
+}
+
+/// Returns the number of instantiations of a component recorded so far, and
+/// the total time they took.
+pub fn instantiation_totals(component_id: &str) -> (u64, Duration) {
+    let histogram = METRICS
+        .instantiation_duration
+        .with_label_values(&[component_id]);
+    (
+        histogram.get_sample_count(),
+        Duration::from_secs_f64(histogram.get_sample_sum()),
+    )
+}
+
+/// Records a request for an instance from a component's instance pool, and
+/// whether a ready instance was available.
+pub fn record_instance_pool_take(component_id: &str, hit: bool) {
+    let outcome = if hit { "hit" } else { "miss" };
+    METRICS
+        .instance_pool_takes
+        .with_label_values(&[component_id, outcome])
+        .inc();
+}
+
+/// Counts a trigger event as in flight until the returned guard is dropped.
+pub fn track_inflight(trigger_type: &str) -> InflightGuard {
+    let gauge = METRICS.inflight.with_label_values(&[trigger_type]);
+    gauge.inc();
+    InflightGuard(gauge)
+}
+
+/// Decrements the in-flight gauge for a trigger event when dropped.
+pub struct InflightGuard(prometheus::IntGauge);
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+// Metrics defined by components, keyed by name, with their label names.
+static GUEST_COUNTERS: Lazy<Mutex<HashMap<String, (Vec<String>, IntCounterVec)>>> =
+    Lazy::new(Default::default);
+static GUEST_HISTOGRAMS: Lazy<Mutex<HashMap<String, (Vec<String>, HistogramVec)>>> =
+    Lazy::new(Default::default);
+
+/// Adds `value` to a counter defined by a component. The counter is created
+/// on first use, and every later use must give the same label names.
+pub fn increment_guest_counter(
+    component_id: &str,
+    name: &str,
+    value: u64,
+    labels: &[(String, String)],
+) -> Result<()> {
+    let (label_names, label_values) = guest_labels(component_id, labels)?;
+    let mut counters = GUEST_COUNTERS.lock().unwrap();
+    let (names, counter) = match counters.entry(name.to_owned()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            check_guest_metric_name(name)?;
+            let counter = IntCounterVec::new(
+                opts!(name, "Counter defined by an application component"),
+                &label_names,
+            )?;
+            METRICS.registry.register(Box::new(counter.clone()))?;
+            entry.insert((label_names.iter().map(|n| n.to_string()).collect(), counter))
+        }
+    };
+    ensure!(
+        *names == label_names,
+        "counter {name:?} was previously used with labels {names:?}"
+    );
+    counter.with_label_values(&label_values).inc_by(value);
+    Ok(())
+}
+
+/// Records `value` in a histogram defined by a component. The histogram is
+/// created on first use, and every later use must give the same label names.
+pub fn record_guest_histogram(
+    component_id: &str,
+    name: &str,
+    value: f64,
+    labels: &[(String, String)],
+) -> Result<()> {
+    let (label_names, label_values) = guest_labels(component_id, labels)?;
+    let mut histograms = GUEST_HISTOGRAMS.lock().unwrap();
+    let (names, histogram) = match histograms.entry(name.to_owned()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            check_guest_metric_name(name)?;
+            let histogram = HistogramVec::new(
+                histogram_opts!(name, "Histogram defined by an application component"),
+                &label_names,
+            )?;
+            METRICS.registry.register(Box::new(histogram.clone()))?;
+            entry.insert((
+                label_names.iter().map(|n| n.to_string()).collect(),
+                histogram,
+            ))
+        }
+    };
+    ensure!(
+        *names == label_names,
+        "histogram {name:?} was previously used with labels {names:?}"
+    );
+    histogram.with_label_values(&label_values).observe(value);
+    Ok(())
+}
+
+fn check_guest_metric_name(name: &str) -> Result<()> {
+    if name.starts_with("spin_") {
+        bail!("metric names starting with 'spin_' are reserved for the runtime");
+    }
+    Ok(())
+}
+
+// Returns the label names and values for a guest metric, ordered by name and
+// with the component ID first.
+fn guest_labels<'a>(
+    component_id: &'a str,
+    labels: &'a [(String, String)],
+) -> Result<(Vec<&'a str>, Vec<&'a str>)> {
+    let mut labels: Vec<_> = labels
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    labels.sort();
+    for pair in labels.windows(2) {
+        ensure!(pair[0].0 != pair[1].0, "duplicate label {:?}", pair[0].0);
+    }
+    ensure!(
+        labels.iter().all(|(name, _)| *name != "component_id"),
+        "the 'component_id' label is reserved for the runtime"
+    );
+    Ok(std::iter::once(("component_id", component_id))
+        .chain(labels)
+        .unzip())
+}
+
+/// Binds a listener on `addr` which serves metrics at `/metrics`, returning
+/// a future which runs the server.
+pub fn serve(addr: SocketAddr) -> Result<impl Future<Output = Result<()>>> {
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("Failed to bind metrics listener on {addr}"))?
+        .serve(make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|req| async { Ok::<_, Infallible>(handle(req)) }))
+        }));
+    tracing::info!("Serving metrics on http://{addr}/metrics");
+    Ok(async move { server.await.context("Metrics listener failed") })
+}
+
+fn handle(req: Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET || req.uri().path() != "/metrics" {
+        let mut not_found = Response::new(Body::empty());
+        *not_found.status_mut() = StatusCode::NOT_FOUND;
+        return not_found;
+    }
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(err) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {err}");
+        let mut error = Response::new(Body::empty());
+        *error.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        return error;
+    }
+    let mut response = Response::new(Body::from(buffer));
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        encoder.format_type().parse().expect("valid content type"),
+    );
+    response
+}
+
+/// Counts host calls from the spans created for them. Host call spans are
+/// named `spin_<interface>.<function>` and have `otel.kind = "client"`.
+pub(crate) struct HostCallMetricsLayer;
+
+impl<S: Subscriber> Layer<S> for HostCallMetricsLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: LayerContext<'_, S>) {
+        let mut visitor = ClientSpanVisitor(false);
+        attrs.record(&mut visitor);
+        if !visitor.0 {
+            return;
+        }
+        let name = attrs.metadata().name();
+        if let Some((interface, function)) =
+            name.strip_prefix("spin_").and_then(|n| n.split_once('.'))
+        {
+            METRICS
+                .host_calls
+                .with_label_values(&[interface, function])
+                .inc();
+        }
+    }
+}
+
+struct ClientSpanVisitor(bool);
+
+impl Visit for ClientSpanVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "otel.kind" && value == "client" {
+            self.0 = true;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_recorded_metrics() {
+        record_http_request("hello", "/hello", 200, Duration::from_millis(5));
+        {
+            let _inflight = track_inflight("http");
+        }
+        let body = handle(Request::get("/metrics").body(Body::empty()).unwrap());
+        assert_eq!(body.status(), StatusCode::OK);
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&METRICS.registry.gather(), &mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(
+            r#"spin_http_requests_total{component_id="hello",route="/hello",status="200"} 1"#
+        ));
+        assert!(text.contains(r#"spin_trigger_inflight_events{trigger_type="http"} 0"#));
+    }
+
+    #[test]
+    fn guest_metrics_require_consistent_labels() {
+        let labels = vec![("kind".to_owned(), "new".to_owned())];
+        increment_guest_counter("hello", "orders_total", 2, &labels).unwrap();
+        increment_guest_counter("hello", "orders_total", 1, &labels).unwrap();
+        assert!(increment_guest_counter("hello", "orders_total", 1, &[]).is_err());
+
+        record_guest_histogram("hello", "order_value", 9.5, &labels).unwrap();
+        assert!(record_guest_histogram("hello", "orders_total", 1.0, &labels).is_err());
+        assert!(record_guest_histogram("hello", "spin_orders", 1.0, &[]).is_err());
+
+        let duplicate = vec![
+            ("kind".to_owned(), "new".to_owned()),
+            ("kind".to_owned(), "old".to_owned()),
+        ];
+        assert!(increment_guest_counter("hello", "dupes_total", 1, &duplicate).is_err());
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&METRICS.registry.gather(), &mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(r#"orders_total{component_id="hello",kind="new"} 3"#));
+    }
+
+    #[test]
+    fn other_paths_are_not_found() {
+        let resp = handle(Request::get("/").body(Body::empty()).unwrap());
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::OnceCell;
+use opentelemetry::{
+    global,
+    sdk::{
+        propagation::TraceContextPropagator,
+        trace::{self, Sampler, Tracer},
+        Resource,
+    },
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+
+const OTEL_EXPORTER_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const OTEL_EXPORTER_OTLP_TRACES_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+const OTEL_EXPORTER_OTLP_HEADERS: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+const OTEL_TRACES_SAMPLER_ARG: &str = "OTEL_TRACES_SAMPLER_ARG";
+const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
+
+const DEFAULT_SERVICE_NAME: &str = "spin";
+
+static DEFAULT_ENDPOINT: OnceCell<String> = OnceCell::new();
+
+/// Sets the OTLP endpoint to which spans are exported if none of the
+/// `OTEL_EXPORTER_OTLP_*` environment variables names one. Only the first
+/// call has any effect, and it must be made before [`crate::init`].
+pub fn set_default_otlp_endpoint(endpoint: String) {
+    let _ = DEFAULT_ENDPOINT.set(endpoint);
+}
+
+/// OTLP trace export settings, read from the standard OpenTelemetry
+/// environment variables.
+#[derive(Debug, PartialEq)]
+pub(crate) struct OtlpConfig {
+    endpoint: String,
+    headers: HashMap<String, String>,
+    sample_ratio: f64,
+    service_name: String,
+}
+
+impl OtlpConfig {
+    /// Returns `None` if no OTLP endpoint is configured.
+    pub fn from_env() -> Result<Option<Self>> {
+        Self::from_vars(|name| {
+            std::env::var(name).ok().or_else(|| match name {
+                OTEL_EXPORTER_OTLP_ENDPOINT => DEFAULT_ENDPOINT.get().cloned(),
+                _ => None,
+            })
+        })
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>> {
+        // As in the OTLP spec, the traces-specific endpoint is used as is,
+        // while the signal path is appended to the general one.
+        let endpoint = match var(OTEL_EXPORTER_OTLP_TRACES_ENDPOINT) {
+            Some(endpoint) => endpoint,
+            None => match var(OTEL_EXPORTER_OTLP_ENDPOINT) {
+                Some(base) => format!("{}/v1/traces", base.trim_end_matches('/')),
+                None => return Ok(None),
+            },
+        };
+        let headers = match var(OTEL_EXPORTER_OTLP_HEADERS) {
+            Some(headers) => parse_headers(&headers)?,
+            None => HashMap::new(),
+        };
+        let sample_ratio = match var(OTEL_TRACES_SAMPLER_ARG) {
+            Some(ratio) => {
+                let ratio: f64 = ratio
+                    .parse()
+                    .with_context(|| format!("Invalid {OTEL_TRACES_SAMPLER_ARG} {ratio:?}"))?;
+                if !(0.0..=1.0).contains(&ratio) {
+                    bail!("{OTEL_TRACES_SAMPLER_ARG} must be between 0 and 1");
+                }
+                ratio
+            }
+            None => 1.0,
+        };
+        let service_name =
+            var(OTEL_SERVICE_NAME).unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_owned());
+        Ok(Some(Self {
+            endpoint,
+            headers,
+            sample_ratio,
+            service_name,
+        }))
+    }
+
+    /// Installs a global tracer provider which exports spans in batches.
+    pub fn install_tracer(&self) -> Result<Tracer> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let exporter = opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&self.endpoint)
+            .with_headers(self.headers.clone());
+        let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(self.sample_ratio)));
+        let resource = Resource::new([KeyValue::new("service.name", self.service_name.clone())]);
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(exporter)
+            .with_trace_config(
+                trace::config()
+                    .with_sampler(sampler)
+                    .with_resource(resource),
+            )
+            .install_batch(opentelemetry::runtime::Tokio)
+            .context("Failed to set up OpenTelemetry trace export")
+    }
+}
+
+// Parses headers in the `key1=value1,key2=value2` format used by
+// `OTEL_EXPORTER_OTLP_HEADERS`.
+fn parse_headers(text: &str) -> Result<HashMap<String, String>> {
+    text.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_owned(), value.trim().to_owned())),
+            None => bail!("Invalid {OTEL_EXPORTER_OTLP_HEADERS} entry {pair:?}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Option<OtlpConfig>> {
+        OtlpConfig::from_vars(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn disabled_without_endpoint() {
+        assert_eq!(
+            config(&[(OTEL_EXPORTER_OTLP_HEADERS, "a=b")]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_endpoint_headers_and_sampling() {
+        let config = config(&[
+            (OTEL_EXPORTER_OTLP_ENDPOINT, "http://collector:4318/"),
+            (OTEL_EXPORTER_OTLP_HEADERS, "api-key=secret, tenant=spin"),
+            (OTEL_TRACES_SAMPLER_ARG, "0.25"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.endpoint, "http://collector:4318/v1/traces");
+        assert_eq!(config.headers["api-key"], "secret");
+        assert_eq!(config.headers["tenant"], "spin");
+        assert_eq!(config.sample_ratio, 0.25);
+        assert_eq!(config.service_name, "spin");
+    }
+
+    #[test]
+    fn traces_endpoint_is_used_as_is() {
+        let config = config(&[
+            (OTEL_EXPORTER_OTLP_ENDPOINT, "http://collector:4318"),
+            (
+                OTEL_EXPORTER_OTLP_TRACES_ENDPOINT,
+                "http://traces:4318/custom",
+            ),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.endpoint, "http://traces:4318/custom");
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(config(&[
+            (OTEL_EXPORTER_OTLP_ENDPOINT, "http://collector:4318"),
+            (OTEL_EXPORTER_OTLP_HEADERS, "no-value"),
+        ])
+        .is_err());
+        assert!(config(&[
+            (OTEL_EXPORTER_OTLP_ENDPOINT, "http://collector:4318"),
+            (OTEL_TRACES_SAMPLER_ARG, "2"),
+        ])
+        .is_err());
+    }
+}
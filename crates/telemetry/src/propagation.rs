@@ -0,0 +1,101 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::current_request_id;
+
+/// The header which carries the ID of a request.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longer incoming request IDs are replaced rather than propagated.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Adds W3C trace context headers for the current span to an outgoing
+/// request, so that upstream services can continue the trace. Does nothing
+/// if trace export is not enabled.
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// Sets the parent of the current span from the W3C trace context headers of
+/// an incoming request, if present.
+pub fn extract_trace_context(headers: &HeaderMap) {
+    let parent =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    tracing::Span::current().set_parent(parent);
+}
+
+/// Returns the ID of an incoming request: its `x-request-id` header if that
+/// is present and reasonable, otherwise a newly generated ID.
+pub fn incoming_request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_owned)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Adds the ID of the request being handled to an outgoing request, unless
+/// the outgoing request already has one.
+pub fn inject_request_id(headers: &mut HeaderMap) {
+    if headers.contains_key(REQUEST_ID_HEADER) {
+        return;
+    }
+    if let Some(value) = current_request_id().and_then(|id| HeaderValue::from_str(&id).ok()) {
+        headers.insert(REQUEST_ID_HEADER, value);
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn honors_reasonable_incoming_request_ids() {
+        let mut headers = HeaderMap::new();
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("abc-123"));
+        assert_eq!(incoming_request_id(&headers), "abc-123");
+
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static(""));
+        assert_ne!(incoming_request_id(&headers), "");
+
+        let long = "x".repeat(MAX_REQUEST_ID_LEN + 1);
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_str(&long).unwrap());
+        assert_ne!(incoming_request_id(&headers), long);
+
+        let generated = incoming_request_id(&HeaderMap::new());
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+    }
+}
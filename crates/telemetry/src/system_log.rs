@@ -0,0 +1,93 @@
+//! Log output to the system log: syslog or systemd-journald.
+
+use std::{fmt::Write, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+const IDENTIFIER: &str = "spin";
+
+/// Writes log events to the local syslog daemon.
+pub(crate) struct SyslogLayer {
+    logger: Mutex<syslog::Logger<syslog::LoggerBackend, syslog::Formatter3164>>,
+}
+
+impl SyslogLayer {
+    pub fn new() -> Result<Self> {
+        let formatter = syslog::Formatter3164 {
+            facility: syslog::Facility::LOG_DAEMON,
+            hostname: None,
+            process: IDENTIFIER.to_owned(),
+            pid: std::process::id(),
+        };
+        let logger =
+            syslog::unix(formatter).map_err(|err| anyhow!("Failed to connect to syslog: {err}"))?;
+        Ok(Self {
+            logger: Mutex::new(logger),
+        })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SyslogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let message = visitor.finish();
+
+        let mut logger = self.logger.lock().unwrap();
+        // Errors can't be reported through the log, and shouldn't interrupt
+        // whatever is being logged.
+        let _ = match *event.metadata().level() {
+            Level::ERROR => logger.err(message),
+            Level::WARN => logger.warning(message),
+            Level::INFO => logger.info(message),
+            Level::DEBUG | Level::TRACE => logger.debug(message),
+        };
+    }
+}
+
+/// Formats the fields of an event as its message followed by `key=value`
+/// pairs for the other fields.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        self.message.push_str(&self.fields);
+        self.message
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Returns a layer which writes log events to systemd-journald, which maps
+/// their levels to priorities.
+pub(crate) fn journald_layer() -> Result<tracing_journald::Layer> {
+    let layer = tracing_journald::layer()
+        .map_err(|err| anyhow!("Failed to connect to journald: {err}"))?
+        .with_syslog_identifier(IDENTIFIER.to_owned());
+    Ok(layer)
+}
@@ -37,6 +37,8 @@ impl AppInfo {
 #[derive(Debug, serde::Deserialize)]
 pub(crate) struct AppInfoV1 {
     trigger: TriggerInfo,
+    #[serde(default, rename = "component")]
+    components: Vec<toml::Value>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -77,4 +79,8 @@ impl AppInfo {
     pub fn trigger_type(&self) -> &str {
         &self.as_v1().trigger.trigger_type
     }
+
+    pub fn has_components(&self) -> bool {
+        !self.as_v1().components.is_empty()
+    }
 }
@@ -0,0 +1,42 @@
+// Template bundles: gzipped tarballs of installed templates, for moving
+// template sets to machines which can't reach the templates' sources. A
+// bundle is laid out like a template repository, with each template in a
+// subdirectory of `templates`, so it installs like any other source.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+const BUNDLE_TEMPLATES_DIR: &str = "templates";
+
+pub(crate) fn write_bundle(template_dirs: &[PathBuf], bundle: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::create(bundle)
+        .with_context(|| format!("Failed to create bundle {}", bundle.display()))?;
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        file,
+        flate2::Compression::default(),
+    ));
+
+    for template_dir in template_dirs {
+        let name = template_dir
+            .file_name()
+            .with_context(|| format!("Invalid template directory {}", template_dir.display()))?;
+        builder
+            .append_dir_all(Path::new(BUNDLE_TEMPLATES_DIR).join(name), template_dir)
+            .with_context(|| format!("Failed to add {} to bundle", template_dir.display()))?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .with_context(|| format!("Failed to write bundle {}", bundle.display()))?;
+    Ok(())
+}
+
+pub(crate) fn unpack_bundle(bundle: &Path, dest_dir: &Path) -> anyhow::Result<()> {
+    let file = std::fs::File::open(bundle)
+        .with_context(|| format!("Failed to open bundle {}", bundle.display()))?;
+    tar::Archive::new(flate2::read::GzDecoder::new(file))
+        .unpack(dest_dir)
+        .with_context(|| format!("Failed to unpack bundle {}", bundle.display()))
+}
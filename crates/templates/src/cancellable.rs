@@ -13,6 +13,14 @@ impl<T, E> Cancellable<T, E> {
         }
     }
 
+    pub(crate) fn into_option(self) -> Result<Option<T>, E> {
+        match self {
+            Self::Ok(value) => Ok(Some(value)),
+            Self::Cancelled => Ok(None),
+            Self::Err(e) => Err(e),
+        }
+    }
+
     pub(crate) fn and_then<U>(self, f: impl Fn(T) -> Result<U, E>) -> Cancellable<U, E> {
         match self {
             Self::Ok(value) => match f(value) {
@@ -15,3 +15,58 @@ impl StringConstraints {
         Ok(text)
     }
 }
+
+#[derive(Clone, Debug)]
+pub(crate) struct IntConstraints {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl IntConstraints {
+    pub fn validate(&self, text: String) -> anyhow::Result<String> {
+        let value: i64 = text
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Input '{}' is not a whole number", text))?;
+        if let Some(min) = self.min {
+            if value < min {
+                anyhow::bail!("Input '{}' is less than the minimum {}", text, min);
+            }
+        }
+        if let Some(max) = self.max {
+            if value > max {
+                anyhow::bail!("Input '{}' is greater than the maximum {}", text, max);
+            }
+        }
+        Ok(value.to_string())
+    }
+}
+
+/// Accepts the usual spellings of yes and no, and normalises them to `true`
+/// and `false` for use in templates.
+pub(crate) fn validate_bool(text: String) -> anyhow::Result<String> {
+    match text.trim().to_lowercase().as_str() {
+        "true" | "yes" | "y" => Ok("true".to_owned()),
+        "false" | "no" | "n" => Ok("false".to_owned()),
+        _ => anyhow::bail!("Input '{}' is not true or false", text),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct EnumConstraints {
+    pub allowed_values: Vec<String>,
+}
+
+impl EnumConstraints {
+    pub fn validate(&self, text: String) -> anyhow::Result<String> {
+        if self.allowed_values.contains(&text) {
+            Ok(text)
+        } else {
+            anyhow::bail!(
+                "Input '{}' is not one of the allowed values: {}",
+                text,
+                self.allowed_values.join(", ")
+            )
+        }
+    }
+}
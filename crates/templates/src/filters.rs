@@ -1,7 +1,7 @@
 // liquid_derive::FilterReflection violates this lint
 #![allow(clippy::box_default)]
 
-use heck::{ToKebabCase, ToSnakeCase, ToUpperCamelCase};
+use heck::{ToKebabCase, ToLowerCamelCase, ToSnakeCase, ToUpperCamelCase};
 use liquid_core::{Filter, ParseFilter, Runtime, ValueView};
 use liquid_derive::FilterReflection;
 
@@ -62,6 +62,33 @@ impl Filter for PascalCaseFilter {
     }
 }
 
+#[derive(Clone, liquid_derive::ParseFilter, liquid_derive::FilterReflection)]
+#[filter(
+    name = "camel_case",
+    description = "Change text to camelCase.",
+    parsed(CamelCaseFilter)
+)]
+pub(crate) struct CamelCaseFilterParser;
+
+#[derive(Debug, Default, liquid_derive::Display_filter)]
+#[name = "camel_case"]
+struct CamelCaseFilter;
+
+impl Filter for CamelCaseFilter {
+    fn evaluate(
+        &self,
+        input: &dyn ValueView,
+        _runtime: &dyn Runtime,
+    ) -> Result<liquid::model::Value, liquid_core::error::Error> {
+        let input = input
+            .as_scalar()
+            .ok_or_else(|| liquid_core::error::Error::with_msg("String expected"))?;
+
+        let input = input.into_string().to_string().to_lower_camel_case();
+        Ok(input.to_value())
+    }
+}
+
 #[derive(Clone, liquid_derive::ParseFilter, liquid_derive::FilterReflection)]
 #[filter(
     name = "snake_case",
@@ -123,3 +150,25 @@ impl Filter for HttpWildcardFilter {
         Ok(wildcard_route.to_value())
     }
 }
+
+#[derive(Clone, liquid_derive::ParseFilter, liquid_derive::FilterReflection)]
+#[filter(
+    name = "uuid",
+    description = "Generate a random UUID. The input is ignored.",
+    parsed(UuidFilter)
+)]
+pub(crate) struct UuidFilterParser;
+
+#[derive(Debug, Default, liquid_derive::Display_filter)]
+#[name = "uuid"]
+struct UuidFilter;
+
+impl Filter for UuidFilter {
+    fn evaluate(
+        &self,
+        _input: &dyn ValueView,
+        _runtime: &dyn Runtime,
+    ) -> Result<liquid::model::Value, liquid_core::error::Error> {
+        Ok(uuid::Uuid::new_v4().to_string().to_value())
+    }
+}
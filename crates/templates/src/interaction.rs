@@ -2,13 +2,13 @@ use std::{collections::HashMap, path::Path};
 
 use crate::{
     cancellable::Cancellable,
-    template::{TemplateParameter, TemplateParameterDataType},
+    template::{PostRunStep, TemplateParameter, TemplateParameterDataType},
     Run,
 };
 
 use anyhow::anyhow;
 // use console::style;
-use dialoguer::{Confirm, Input};
+use dialoguer::{Confirm, Input, Select};
 
 pub(crate) trait InteractionStrategy {
     fn allow_generate_into(&self, target_dir: &Path) -> Cancellable<(), anyhow::Error>;
@@ -33,6 +33,7 @@ pub(crate) trait InteractionStrategy {
         run: &Run,
         parameter: &TemplateParameter,
     ) -> Cancellable<String, anyhow::Error>;
+    fn allow_post_run_step(&self, step: &PostRunStep) -> bool;
 }
 
 pub(crate) struct Interactive;
@@ -61,7 +62,7 @@ impl InteractionStrategy for Interactive {
         parameter: &TemplateParameter,
     ) -> Cancellable<String, anyhow::Error> {
         match run.options.values.get(parameter.id()) {
-            Some(s) => Cancellable::Ok(s.clone()),
+            Some(s) => provided_value(parameter, s),
             None => match (run.options.accept_defaults, parameter.default_value()) {
                 (true, Some(v)) => Cancellable::Ok(v.to_string()),
                 _ => match crate::interaction::prompt_parameter(parameter) {
@@ -71,6 +72,16 @@ impl InteractionStrategy for Interactive {
             },
         }
     }
+
+    fn allow_post_run_step(&self, step: &PostRunStep) -> bool {
+        // If the user can't be asked, e.g. because input is not a terminal,
+        // the step is skipped.
+        Confirm::new()
+            .with_prompt(step.prompt())
+            .default(true)
+            .interact()
+            .unwrap_or(false)
+    }
 }
 
 impl InteractionStrategy for Silent {
@@ -92,13 +103,34 @@ impl InteractionStrategy for Silent {
         parameter: &TemplateParameter,
     ) -> Cancellable<String, anyhow::Error> {
         match run.options.values.get(parameter.id()) {
-            Some(s) => Cancellable::Ok(s.clone()),
+            Some(s) => provided_value(parameter, s),
             None => match (run.options.accept_defaults, parameter.default_value()) {
                 (true, Some(v)) => Cancellable::Ok(v.to_string()),
-                _ => Cancellable::Err(anyhow!("Parameter '{}' not provided", parameter.id())),
+                _ => Cancellable::Err(anyhow!(
+                    "Parameter '{}' not provided. Provide it with `--value {}=<{}>`",
+                    parameter.id(),
+                    parameter.id(),
+                    parameter.data_type().value_hint()
+                )),
             },
         }
     }
+
+    fn allow_post_run_step(&self, _step: &PostRunStep) -> bool {
+        false
+    }
+}
+
+// Values given on the command line have been validated already, but are
+// validated again for the normalised value, e.g. `true` for `yes`.
+fn provided_value(
+    parameter: &TemplateParameter,
+    value: &str,
+) -> Cancellable<String, anyhow::Error> {
+    match parameter.validate_value(value) {
+        Ok(value) => Cancellable::Ok(value),
+        Err(e) => Cancellable::Err(e),
+    }
 }
 
 pub(crate) fn confirm(text: &str) -> std::io::Result<bool> {
@@ -111,7 +143,13 @@ pub(crate) fn prompt_parameter(parameter: &TemplateParameter) -> Option<String>
 
     loop {
         let input = match parameter.data_type() {
-            TemplateParameterDataType::String(_) => ask_free_text(prompt, default_value),
+            TemplateParameterDataType::String(_) | TemplateParameterDataType::Int(_) => {
+                ask_free_text(prompt, default_value)
+            }
+            TemplateParameterDataType::Bool => ask_yes_no(prompt, default_value),
+            TemplateParameterDataType::Enum(constraints) => {
+                ask_choice(prompt, &constraints.allowed_values, default_value)
+            }
         };
 
         match input {
@@ -138,6 +176,31 @@ fn ask_free_text(prompt: &str, default_value: &Option<String>) -> anyhow::Result
     Ok(result)
 }
 
+fn ask_yes_no(prompt: &str, default_value: &Option<String>) -> anyhow::Result<String> {
+    let mut confirm = Confirm::new();
+    confirm.with_prompt(prompt);
+    if let Some(s) = default_value {
+        confirm.default(s == "true");
+    }
+    let result = confirm.interact()?;
+    Ok(result.to_string())
+}
+
+fn ask_choice(
+    prompt: &str,
+    choices: &[String],
+    default_value: &Option<String>,
+) -> anyhow::Result<String> {
+    let mut select = Select::new();
+    select.with_prompt(prompt).items(choices);
+    let default_index = default_value
+        .as_ref()
+        .and_then(|d| choices.iter().position(|c| c == d));
+    select.default(default_index.unwrap_or_default());
+    let index = select.interact()?;
+    Ok(choices[index].clone())
+}
+
 fn is_directory_empty(path: &Path) -> bool {
     if !path.exists() {
         return true;
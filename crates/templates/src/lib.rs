@@ -3,6 +3,7 @@
 #![deny(missing_docs)]
 
 mod app_info;
+mod bundle;
 mod cancellable;
 mod constraints;
 mod custom_filters;
@@ -11,7 +12,9 @@ mod environment;
 mod filters;
 mod git;
 mod interaction;
+mod lockfile;
 mod manager;
+mod merge;
 mod reader;
 mod renderer;
 mod run;
@@ -20,7 +23,9 @@ mod store;
 mod template;
 mod writer;
 
+pub use lockfile::{LockedRepository, TemplatesLockfile, TEMPLATES_LOCKFILE};
 pub use manager::*;
 pub use run::{Run, RunOptions};
 pub use source::TemplateSource;
 pub use template::{Template, TemplateVariantInfo};
+pub use writer::RunPreview;
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// The name of the file in which a project pins the template repositories
+/// it uses.
+pub const TEMPLATES_LOCKFILE: &str = "spin-templates.lock";
+
+const LOCKFILE_HEADER: &str =
+    "# Generated by `spin templates pin`. `spin templates upgrade` installs
+# these revisions when run in this directory.
+";
+
+/// The template repositories pinned by a project, so that everyone working
+/// on the project generates components from the same templates.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TemplatesLockfile {
+    #[serde(default, rename = "repository")]
+    repositories: Vec<LockedRepository>,
+}
+
+/// A template repository pinned to a commit.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LockedRepository {
+    /// The URL of the Git repository.
+    pub git: String,
+    /// The commit to install templates from.
+    pub revision: String,
+}
+
+impl TemplatesLockfile {
+    /// Loads the lockfile at the given path.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Can't read templates lockfile {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Can't parse templates lockfile {}", path.display()))
+    }
+
+    /// Loads the lockfile at the given path, if there is one.
+    pub fn load_if_exists(path: &Path) -> anyhow::Result<Option<Self>> {
+        if path.exists() {
+            Self::load(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Saves the lockfile to the given path.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = toml::to_string_pretty(self).context("Can't serialise templates lockfile")?;
+        std::fs::write(path, format!("{LOCKFILE_HEADER}\n{text}"))
+            .with_context(|| format!("Can't write templates lockfile {}", path.display()))
+    }
+
+    /// The pinned repositories.
+    pub fn repositories(&self) -> &[LockedRepository] {
+        &self.repositories
+    }
+
+    /// The commit to which the given repository is pinned, if it is pinned.
+    pub fn revision(&self, repo: &str) -> Option<&str> {
+        self.repositories
+            .iter()
+            .find(|r| same_repo(&r.git, repo))
+            .map(|r| r.revision.as_str())
+    }
+
+    /// Pins the given repository to the given commit, replacing any existing
+    /// pin.
+    pub fn pin(&mut self, repo: &str, revision: &str) {
+        let locked = LockedRepository {
+            git: repo.to_owned(),
+            revision: revision.to_owned(),
+        };
+        match self
+            .repositories
+            .iter_mut()
+            .find(|r| same_repo(&r.git, repo))
+        {
+            Some(existing) => *existing = locked,
+            None => self.repositories.push(locked),
+        }
+    }
+}
+
+// The same repository may be given with or without a trailing slash or
+// `.git` suffix.
+fn same_repo(first: &str, second: &str) -> bool {
+    fn normalize(repo: &str) -> &str {
+        let repo = repo.trim_end_matches('/');
+        repo.strip_suffix(".git").unwrap_or(repo)
+    }
+    normalize(first) == normalize(second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_round_trip() {
+        let mut lockfile = TemplatesLockfile::default();
+        lockfile.pin("https://github.com/fermyon/spin", "abc123");
+        lockfile.pin("https://github.com/fermyon/spin-js-sdk", "def456");
+        lockfile.pin("https://github.com/fermyon/spin.git", "789abc");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(TEMPLATES_LOCKFILE);
+        lockfile.save(&path).unwrap();
+
+        let lockfile = TemplatesLockfile::load_if_exists(&path).unwrap().unwrap();
+        assert_eq!(2, lockfile.repositories().len());
+        assert_eq!(
+            Some("789abc"),
+            lockfile.revision("https://github.com/fermyon/spin/")
+        );
+        assert_eq!(
+            Some("def456"),
+            lockfile.revision("https://github.com/fermyon/spin-js-sdk")
+        );
+        assert_eq!(None, lockfile.revision("https://github.com/fermyon/other"));
+
+        let missing = temp_dir.path().join("missing.lock");
+        assert!(TemplatesLockfile::load_if_exists(&missing)
+            .unwrap()
+            .is_none());
+    }
+}
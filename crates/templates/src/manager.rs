@@ -3,6 +3,7 @@ use std::path::Path;
 use anyhow::Context;
 
 use crate::{
+    reader::RawInstalledFrom,
     source::TemplateSource,
     store::{TemplateLayout, TemplateStore},
     template::Template,
@@ -130,12 +131,21 @@ impl TemplateManager {
             .await
             .context("Could not find templates in source")?;
 
+        let install_record = source.to_install_record(&local_source);
+        let shared_partials = local_source.shared_partials_dir();
+
         let mut installed = vec![];
         let mut skipped = vec![];
 
         for template_dir in template_dirs {
             let install_result = self
-                .install_one(&template_dir, options, source, reporter)
+                .install_one(
+                    &template_dir,
+                    options,
+                    &install_record,
+                    shared_partials.as_deref(),
+                    reporter,
+                )
                 .await
                 .with_context(|| {
                     format!("Failed to install template from {}", template_dir.display())
@@ -156,7 +166,8 @@ impl TemplateManager {
         &self,
         source_dir: &Path,
         options: &InstallOptions,
-        source: &TemplateSource,
+        install_record: &Option<RawInstalledFrom>,
+        shared_partials: Option<&Path>,
         reporter: &impl ProgressReporter,
     ) -> anyhow::Result<InstallationResult> {
         let layout = TemplateLayout::new(source_dir);
@@ -190,11 +201,18 @@ impl TemplateManager {
                     ))
                 }
                 ExistsBehaviour::Update => {
-                    copy_template_over_existing(id, source_dir, &dest_dir, source).await?
+                    copy_template_over_existing(
+                        id,
+                        source_dir,
+                        &dest_dir,
+                        install_record,
+                        shared_partials,
+                    )
+                    .await?
                 }
             }
         } else {
-            copy_template_into(id, source_dir, &dest_dir, source).await?
+            copy_template_into(id, source_dir, &dest_dir, install_record, shared_partials).await?
         };
 
         Ok(InstallationResult::Installed(template))
@@ -213,6 +231,37 @@ impl TemplateManager {
             })
     }
 
+    /// Exports the specified installed templates, or all installed templates
+    /// if none are specified, to a bundle which can be installed with
+    /// `TemplateSource::Bundle`. Returns the IDs of the exported templates.
+    pub async fn export(&self, ids: &[String], bundle: &Path) -> anyhow::Result<Vec<String>> {
+        let ids = if ids.is_empty() {
+            self.list()
+                .await?
+                .templates
+                .iter()
+                .map(|t| t.id().to_owned())
+                .collect()
+        } else {
+            ids.to_vec()
+        };
+
+        if ids.is_empty() {
+            anyhow::bail!("There are no installed templates to export");
+        }
+
+        let template_dirs = ids
+            .iter()
+            .map(|id| match self.store.get_layout(id) {
+                Some(_) => Ok(self.store.get_directory(id)),
+                None => Err(anyhow::anyhow!("Template {id} is not installed")),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        crate::bundle::write_bundle(&template_dirs, bundle)?;
+        Ok(ids)
+    }
+
     /// Lists all installed templates.
     pub async fn list(&self) -> anyhow::Result<ListResults> {
         let mut templates = vec![];
@@ -268,7 +317,8 @@ async fn copy_template_over_existing(
     id: &str,
     source_dir: &Path,
     dest_dir: &Path,
-    source: &TemplateSource,
+    install_record: &Option<RawInstalledFrom>,
+    shared_partials: Option<&Path>,
 ) -> anyhow::Result<Template> {
     // The nearby directory to which we initially copy the source
     let stage_dir = dest_dir.with_extension(".stage");
@@ -302,9 +352,10 @@ async fn copy_template_over_existing(
 
     // Copy template source into stage directory, and do best effort
     // cleanup if it goes wrong.
-    let copy_to_stage_err = copy_template_into(id, source_dir, &stage_dir, source)
-        .await
-        .err();
+    let copy_to_stage_err =
+        copy_template_into(id, source_dir, &stage_dir, install_record, shared_partials)
+            .await
+            .err();
     if let Some(e) = copy_to_stage_err {
         let _ = tokio::fs::remove_dir_all(&stage_dir).await;
         return Err(e);
@@ -354,7 +405,8 @@ async fn copy_template_into(
     id: &str,
     source_dir: &Path,
     dest_dir: &Path,
-    source: &TemplateSource,
+    install_record: &Option<RawInstalledFrom>,
+    shared_partials: Option<&Path>,
 ) -> anyhow::Result<Template> {
     tokio::fs::create_dir_all(&dest_dir)
         .await
@@ -375,18 +427,52 @@ async fn copy_template_into(
         )
     })?;
 
-    write_install_record(dest_dir, source);
+    if let Some(shared_partials) = shared_partials {
+        copy_shared_partials(id, shared_partials, dest_dir)?;
+    }
+
+    write_install_record(dest_dir, install_record);
 
     load_template_from(id, dest_dir)
 }
 
-fn write_install_record(dest_dir: &Path, source: &TemplateSource) {
+// Shared partials are copied into each template, so that installed templates
+// are self-contained. A template's own partials take precedence.
+fn copy_shared_partials(id: &str, shared_partials: &Path, dest_dir: &Path) -> anyhow::Result<()> {
+    let partials_dir = TemplateLayout::new(dest_dir).partials_dir();
+    std::fs::create_dir_all(&partials_dir).with_context(|| {
+        format!(
+            "Failed to create directory {} for {}",
+            partials_dir.display(),
+            id
+        )
+    })?;
+
+    let mut options = copy_content();
+    options.skip_exist = true;
+    fs_extra::dir::copy(shared_partials, &partials_dir, &options).with_context(|| {
+        format!(
+            "Failed to copy shared partials from {} to {} for {}",
+            shared_partials.display(),
+            partials_dir.display(),
+            id
+        )
+    })?;
+    Ok(())
+}
+
+fn write_install_record(dest_dir: &Path, install_record: &Option<RawInstalledFrom>) {
     let layout = TemplateLayout::new(dest_dir);
     let install_record_path = layout.install_record_file();
 
+    // Templates from sources without a record, such as bundles, keep any
+    // record they were copied with.
+    let Some(install_record) = install_record else {
+        return;
+    };
+
     // A failure here shouldn't fail the install
-    let install_record = source.to_install_record();
-    if let Ok(record_text) = toml::to_string_pretty(&install_record) {
+    if let Ok(record_text) = toml::to_string_pretty(install_record) {
         _ = std::fs::write(install_record_path, record_text);
     }
 }
@@ -863,6 +949,290 @@ mod tests {
         assert!(message.contains("p1/clappy = b👏i👏s👏c👏u👏i👏t👏s"));
     }
 
+    #[tokio::test]
+    async fn can_run_template_with_typed_parameters() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(test_data_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let run_with = |values: &[(&str, &str)]| {
+            let dest_temp_dir = tempdir().unwrap();
+            let output_dir = dest_temp_dir.path().join("myproj");
+            let options = RunOptions {
+                variant: crate::template::TemplateVariantInfo::NewApplication,
+                output_path: output_dir.clone(),
+                name: "typed-test".to_owned(),
+                values: values
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                accept_defaults: true,
+            };
+            let template = manager.get("typed-parameters").unwrap().unwrap();
+            (dest_temp_dir, output_dir, template.run(options))
+        };
+
+        let (_dest, output_dir, run) = run_with(&[("name", "widget"), ("enabled", "yes")]);
+        run.silent().await.unwrap();
+        let content = tokio::fs::read_to_string(output_dir.join("test.txt"))
+            .await
+            .unwrap();
+        assert_contains(&content, "name = widget");
+        assert_contains(&content, "enabled = true");
+        assert_contains(&content, "replicas = 1");
+        assert_contains(&content, "tier = free");
+
+        let (_dest, _, run) = run_with(&[("name", "widget"), ("replicas", "11")]);
+        let err = run.silent().await.unwrap_err().to_string();
+        assert_contains(&err, "greater than the maximum 10");
+
+        let (_dest, _, run) = run_with(&[("name", "widget"), ("tier", "enterprise")]);
+        let err = run.silent().await.unwrap_err().to_string();
+        assert_contains(&err, "free, pro");
+
+        let (_dest, _, run) = run_with(&[("enabled", "maybe")]);
+        let err = run.silent().await.unwrap_err().to_string();
+        assert_contains(&err, "not true or false");
+
+        let (_dest, _, run) = run_with(&[]);
+        let err = run.silent().await.unwrap_err().to_string();
+        assert_contains(&err, "--value name=<text>");
+    }
+
+    #[tokio::test]
+    async fn can_install_exported_bundle() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path().join("source-store"));
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(project_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let bundle = temp_dir.path().join("bundle.tar.gz");
+        let exported = manager
+            .export(&["http-rust".to_owned(), "redirect".to_owned()], &bundle)
+            .await
+            .unwrap();
+        assert_eq!(2, exported.len());
+
+        manager
+            .export(&["not-installed".to_owned()], &bundle)
+            .await
+            .expect_err("Expected exporting an uninstalled template to fail");
+        let exported = manager.export(&[], &bundle).await.unwrap();
+        assert_eq!(TPLS_IN_THIS, exported.len());
+
+        let store = TemplateStore::new(temp_dir.path().join("dest-store"));
+        let dest_manager = TemplateManager { store };
+        let install_result = dest_manager
+            .install(
+                &TemplateSource::Bundle(bundle),
+                &InstallOptions::default(),
+                &DiscardingReporter,
+            )
+            .await
+            .unwrap();
+        assert_eq!(TPLS_IN_THIS, install_result.installed.len());
+
+        let template = dest_manager.get("http-rust").unwrap().unwrap();
+        assert_eq!(
+            format!("{}", project_root().display()),
+            template.installed_from_or_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn can_use_partials_and_builtin_filters() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(test_data_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let template = manager.get("partials-and-filters").unwrap().unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let output_dir = dest_temp_dir.path().join("myproj");
+        let values = [("service".to_owned(), "my service".to_owned())]
+            .into_iter()
+            .collect();
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::NewApplication,
+            output_path: output_dir.clone(),
+            name: "partials-test".to_owned(),
+            values,
+            accept_defaults: false,
+        };
+
+        template.run(options).silent().await.unwrap();
+
+        let content = tokio::fs::read_to_string(output_dir.join("test.txt"))
+            .await
+            .unwrap();
+        assert_contains(&content, "shared header for my-service");
+        assert_contains(&content, "camel = myService");
+        assert_contains(&content, "template footer");
+        assert!(!content.contains("shared footer"));
+
+        let uuid = content
+            .lines()
+            .find_map(|line| line.strip_prefix("uuid = "))
+            .expect("expected a uuid line");
+        assert_eq!(36, uuid.trim().len());
+    }
+
+    #[tokio::test]
+    async fn silent_runs_skip_post_run_steps() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(test_data_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let template = manager.get("post-run-steps").unwrap().unwrap();
+        let steps = template
+            .post_run_steps(&TemplateVariantInfo::NewApplication)
+            .collect::<Vec<_>>();
+        assert_eq!(1, steps.len());
+        assert_eq!("echo done > post-run.txt", steps[0].command());
+        assert_eq!(
+            "Write a marker file (runs `echo done > post-run.txt`)?",
+            steps[0].prompt()
+        );
+        let add_component = TemplateVariantInfo::AddComponent {
+            manifest_path: temp_dir.path().join("spin.toml"),
+        };
+        assert_eq!(0, template.post_run_steps(&add_component).count());
+
+        let dest_temp_dir = tempdir().unwrap();
+        let output_dir = dest_temp_dir.path().join("myproj");
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::NewApplication,
+            output_path: output_dir.clone(),
+            name: "post-run-test".to_owned(),
+            values: HashMap::new(),
+            accept_defaults: false,
+        };
+
+        template.run(options).silent().await.unwrap();
+
+        assert!(output_dir.join("test.txt").exists());
+        assert!(!output_dir.join("post-run.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn dry_run_previews_without_writing() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(project_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let application_dir = dest_temp_dir.path().join("multi");
+
+        let template = manager.get("http-empty").unwrap().unwrap();
+        let values = [
+            ("project-description".to_owned(), "my desc".to_owned()),
+            ("http-base".to_owned(), "/".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::NewApplication,
+            output_path: application_dir.clone(),
+            name: "my multi project".to_owned(),
+            values,
+            accept_defaults: false,
+        };
+
+        let preview = template
+            .run(options)
+            .silent_dry_run()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(preview.files().contains(&application_dir.join("spin.toml")));
+        assert!(preview.manifest_diffs().is_empty());
+        assert!(!application_dir.exists());
+
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::NewApplication,
+            output_path: application_dir.clone(),
+            name: "my multi project".to_owned(),
+            values: [
+                ("project-description".to_owned(), "my desc".to_owned()),
+                ("http-base".to_owned(), "/".to_owned()),
+            ]
+            .into_iter()
+            .collect(),
+            accept_defaults: false,
+        };
+        template.run(options).silent().await.unwrap();
+
+        let spin_toml_path = application_dir.join("spin.toml");
+        let spin_toml = tokio::fs::read_to_string(&spin_toml_path).await.unwrap();
+
+        let template = manager.get("http-rust").unwrap().unwrap();
+        let values = [
+            ("project-description".to_owned(), "hello".to_owned()),
+            ("http-path".to_owned(), "/hello".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::AddComponent {
+                manifest_path: spin_toml_path.clone(),
+            },
+            output_path: PathBuf::from("hello"),
+            name: "hello".to_owned(),
+            values,
+            accept_defaults: false,
+        };
+
+        let preview = template
+            .run(options)
+            .silent_dry_run()
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(preview
+            .files()
+            .contains(&application_dir.join("hello/Cargo.toml")));
+        assert_eq!(1, preview.manifest_diffs().len());
+        let (diff_path, diff) = &preview.manifest_diffs()[0];
+        assert_eq!(&spin_toml_path, diff_path);
+        assert_contains(diff, "+id = \"hello\"");
+        assert_contains(&preview.to_string(), "Would change");
+
+        assert!(!application_dir.join("hello").exists());
+        assert_eq!(
+            spin_toml,
+            tokio::fs::read_to_string(&spin_toml_path).await.unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn can_add_component_from_template() {
         let temp_dir = tempdir().unwrap();
@@ -965,6 +1335,105 @@ mod tests {
         assert!(spin_toml.contains("source = \"encore/target/wasm32-wasi/release/hello_2.wasm\""));
     }
 
+    #[tokio::test]
+    async fn can_add_component_from_template_without_add_support() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(project_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let application_dir = dest_temp_dir.path().join("multi");
+
+        // Set up the containing app
+        {
+            let template = manager.get("http-rust").unwrap().unwrap();
+
+            let values = [
+                ("project-description".to_owned(), "my desc".to_owned()),
+                ("http-base".to_owned(), "/".to_owned()),
+                ("http-path".to_owned(), "/rust".to_owned()),
+            ]
+            .into_iter()
+            .collect();
+            let options = RunOptions {
+                variant: crate::template::TemplateVariantInfo::NewApplication,
+                output_path: application_dir.clone(),
+                name: "my multi project".to_owned(),
+                values,
+                accept_defaults: false,
+            };
+
+            template.run(options).silent().await.unwrap();
+        }
+
+        let spin_toml_path = application_dir.join("spin.toml");
+        let add_grain = |output_dir: &str, route: &str| {
+            let values = [
+                ("project-description".to_owned(), "grain".to_owned()),
+                ("http-path".to_owned(), route.to_owned()),
+            ]
+            .into_iter()
+            .collect();
+            RunOptions {
+                variant: crate::template::TemplateVariantInfo::AddComponent {
+                    manifest_path: spin_toml_path.clone(),
+                },
+                output_path: PathBuf::from(output_dir),
+                name: output_dir.to_owned(),
+                values,
+                accept_defaults: true,
+            }
+        };
+
+        // The Grain template has no add_component section, so its own
+        // component is added
+        {
+            let template = manager.get("http-grain").unwrap().unwrap();
+            template
+                .run(add_grain("grain", "/grain"))
+                .silent()
+                .await
+                .unwrap();
+        }
+
+        assert!(application_dir.join("grain/main.gr").exists());
+        assert!(!application_dir.join("grain/spin.toml").exists());
+
+        let spin_toml = tokio::fs::read_to_string(&spin_toml_path).await.unwrap();
+        let manifest: toml::Value = toml::from_str(&spin_toml).unwrap();
+        let components = manifest["component"].as_array().unwrap();
+        assert_eq!(2, components.len());
+        assert_eq!("grain", components[1]["id"].as_str().unwrap());
+        assert_eq!("grain/main.wasm", components[1]["source"].as_str().unwrap());
+        assert_eq!("grain", components[1]["build"]["workdir"].as_str().unwrap());
+        assert_eq!("/", manifest["trigger"]["base"].as_str().unwrap());
+
+        // A component whose route is already handled can't be added, and
+        // nothing is written
+        {
+            let template = manager.get("http-grain").unwrap().unwrap();
+            let err = template
+                .run(add_grain("other", "/grain/"))
+                .silent()
+                .await
+                .expect_err("Expected to fail to add component with a used route");
+            assert!(
+                format!("{err:#}").contains("/grain"),
+                "unexpected error {err:#}"
+            );
+        }
+
+        assert!(!application_dir.join("other").exists());
+        let unchanged_spin_toml = tokio::fs::read_to_string(&spin_toml_path).await.unwrap();
+        assert_eq!(spin_toml, unchanged_spin_toml);
+    }
+
     #[tokio::test]
     async fn cannot_add_component_that_does_not_match_trigger() {
         let temp_dir = tempdir().unwrap();
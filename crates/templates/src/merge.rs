@@ -0,0 +1,229 @@
+// Adding components to an existing application manifest. The manifest is
+// edited rather than rewritten, so that its comments and formatting are
+// preserved.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use toml_edit::{ArrayOfTables, Document, Item, Table, Value};
+
+/// Adds the components, variables and trigger settings in `addition` to
+/// the `existing` manifest, returning the new manifest text. If
+/// `component_dir` is given, `addition` is a whole application manifest,
+/// whose component paths are relative to that directory rather than to
+/// the existing manifest.
+pub(crate) fn merge_manifest(
+    existing: &str,
+    addition: &str,
+    component_dir: Option<&Path>,
+) -> anyhow::Result<String> {
+    let existing_value: toml::Value =
+        toml::from_str(existing).context("Can't parse existing manifest")?;
+    let addition_value: toml::Value =
+        toml::from_str(addition).context("Can't parse the component to add")?;
+    check_conflicts(&existing_value, &addition_value)?;
+
+    let mut doc: Document = existing.parse()?;
+    let mut addition_doc: Document = addition.parse()?;
+
+    if let Some(dir) = component_dir {
+        let dir = dir.to_string_lossy().replace('\\', "/");
+        if let Some(components) = addition_doc
+            .get_mut("component")
+            .and_then(Item::as_array_of_tables_mut)
+        {
+            for component in components.iter_mut() {
+                rebase_component(component, &dir);
+            }
+        }
+    }
+
+    let mut next_position = last_position(doc.as_table()) + 1;
+
+    // Only settings the application doesn't have are added: the trigger
+    // type was checked when the template was chosen, and the application's
+    // own settings, such as the HTTP base, win.
+    if let (Some(trigger), Some(new_trigger)) = (
+        doc.get_mut("trigger").and_then(Item::as_table_like_mut),
+        addition_doc.get("trigger").and_then(Item::as_table_like),
+    ) {
+        for (key, item) in new_trigger.iter() {
+            if !trigger.contains_key(key) {
+                trigger.insert(key, item.clone());
+            }
+        }
+    }
+
+    if let Some(new_variables) = addition_doc.get("variables") {
+        match doc.get_mut("variables").and_then(Item::as_table_like_mut) {
+            Some(variables) => {
+                let new_variables = new_variables
+                    .as_table_like()
+                    .context("`variables` must be a table")?;
+                for (name, variable) in new_variables.iter() {
+                    if !variables.contains_key(name) {
+                        let mut variable = variable.clone();
+                        place_after(&mut variable, &mut next_position);
+                        variables.insert(name, variable);
+                    }
+                }
+            }
+            None => {
+                let mut variables = new_variables.clone();
+                place_after(&mut variables, &mut next_position);
+                doc.insert("variables", variables);
+            }
+        }
+    }
+
+    if let Some(new_components) = addition_doc
+        .get("component")
+        .and_then(Item::as_array_of_tables)
+    {
+        let components = doc
+            .entry("component")
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .context("The existing manifest's `component` is not an array of tables")?;
+        for component in new_components.iter() {
+            let mut component = component.clone();
+            component.decor_mut().set_prefix("\n");
+            place_table_after(&mut component, &mut next_position);
+            components.push(component);
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+fn check_conflicts(existing: &toml::Value, addition: &toml::Value) -> anyhow::Result<()> {
+    for component in components(addition) {
+        let id = component_id(component);
+        for other in components(existing) {
+            let other_id = component_id(other);
+            if !id.is_empty() && id == other_id {
+                bail!("The application already has a component with ID '{id}'");
+            }
+            if let (Some(route), Some(other_route)) = (http_route(component), http_route(other)) {
+                if route == other_route {
+                    bail!(
+                        "Component '{id}' would handle route '{route}', but component '{other_id}' already handles it"
+                    );
+                }
+            }
+        }
+    }
+
+    let new_variables = addition.get("variables").and_then(toml::Value::as_table);
+    let variables = existing.get("variables").and_then(toml::Value::as_table);
+    if let (Some(new_variables), Some(variables)) = (new_variables, variables) {
+        for (name, variable) in new_variables {
+            if variables.get(name).map_or(false, |v| v != variable) {
+                bail!("The application already has a variable named '{name}', defined differently");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn components(manifest: &toml::Value) -> impl Iterator<Item = &toml::Value> {
+    manifest
+        .get("component")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn component_id(component: &toml::Value) -> &str {
+    component
+        .get("id")
+        .and_then(toml::Value::as_str)
+        .unwrap_or_default()
+}
+
+// Trailing slashes don't affect which requests a route matches.
+fn http_route(component: &toml::Value) -> Option<&str> {
+    let route = component.get("trigger")?.get("route")?.as_str()?;
+    match route.trim_end_matches('/') {
+        "" => Some("/"),
+        route => Some(route),
+    }
+}
+
+fn rebase_component(component: &mut Table, dir: &str) {
+    if let Some(source) = component.get_mut("source").and_then(Item::as_value_mut) {
+        rebase_path(source, dir);
+    }
+    if let Some(build) = component.get_mut("build").and_then(Item::as_table_like_mut) {
+        match build.get_mut("workdir").and_then(Item::as_value_mut) {
+            Some(workdir) => rebase_path(workdir, dir),
+            None => {
+                build.insert("workdir", toml_edit::value(dir));
+            }
+        }
+    }
+    if let Some(files) = component.get_mut("files").and_then(Item::as_array_mut) {
+        for file in files.iter_mut() {
+            match file {
+                Value::String(_) => rebase_path(file, dir),
+                Value::InlineTable(mount) => {
+                    if let Some(source) = mount.get_mut("source") {
+                        rebase_path(source, dir);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn rebase_path(value: &mut Value, dir: &str) {
+    let Some(path) = value.as_str() else {
+        return;
+    };
+    if Path::new(path).is_absolute() {
+        return;
+    }
+    let rebased = match path.trim_start_matches("./") {
+        "" | "." => dir.to_owned(),
+        path => format!("{dir}/{path}"),
+    };
+    let decor = value.decor().clone();
+    *value = Value::from(rebased);
+    *value.decor_mut() = decor;
+}
+
+// toml_edit orders tables by their position in the document they were parsed
+// from, so tables taken from the addition are renumbered to come after all
+// those in the existing manifest.
+fn last_position(table: &Table) -> usize {
+    table.iter().fold(
+        table.position().unwrap_or(0),
+        |last, (_, item)| match item {
+            Item::Table(table) => last.max(last_position(table)),
+            Item::ArrayOfTables(tables) => tables.iter().map(last_position).fold(last, usize::max),
+            _ => last,
+        },
+    )
+}
+
+fn place_after(item: &mut Item, next_position: &mut usize) {
+    match item {
+        Item::Table(table) => place_table_after(table, next_position),
+        Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                place_table_after(table, next_position);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn place_table_after(table: &mut Table, next_position: &mut usize) {
+    table.set_position(*next_position);
+    *next_position += 1;
+    for (_, item) in table.iter_mut() {
+        place_after(item, next_position);
+    }
+}
@@ -23,6 +23,7 @@ pub(crate) struct RawTemplateManifestV1 {
     pub add_component: Option<RawTemplateVariant>,
     pub parameters: Option<IndexMap<String, RawParameter>>,
     pub custom_filters: Option<Vec<RawCustomFilter>>,
+    pub post_run: Option<IndexMap<String, RawPostRunStep>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,6 +32,7 @@ pub(crate) struct RawTemplateVariant {
     pub supported: Option<bool>,
     pub skip_files: Option<Vec<String>>,
     pub skip_parameters: Option<Vec<String>>,
+    pub skip_post_run: Option<Vec<String>>,
     pub snippets: Option<HashMap<String, String>>,
 }
 
@@ -40,9 +42,42 @@ pub(crate) struct RawParameter {
     #[serde(rename = "type")]
     pub data_type: String,
     pub prompt: String,
-    #[serde(rename = "default")]
+    #[serde(rename = "default", default, deserialize_with = "deserialize_scalar")]
     pub default_value: Option<String>,
     pub pattern: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+// Defaults of `bool` and `int` parameters may be written as TOML booleans
+// and integers rather than strings.
+fn deserialize_scalar<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        String(String),
+        Bool(bool),
+        Int(i64),
+    }
+
+    Ok(
+        Option::<Scalar>::deserialize(deserializer)?.map(|scalar| match scalar {
+            Scalar::String(s) => s,
+            Scalar::Bool(b) => b.to_string(),
+            Scalar::Int(i) => i.to_string(),
+        }),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) struct RawPostRunStep {
+    pub command: String,
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -59,8 +94,21 @@ pub(crate) fn parse_manifest_toml(text: impl AsRef<str>) -> anyhow::Result<RawTe
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case", untagged)]
 pub(crate) enum RawInstalledFrom {
-    Git { git: String },
-    File { dir: String },
+    Git {
+        git: String,
+        // The commit that was installed, and the branch or tag it was
+        // installed from, if any.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        revision: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+    },
+    File {
+        dir: String,
+    },
+    Oci {
+        oci: String,
+    },
 }
 
 pub(crate) fn parse_installed_from(text: impl AsRef<str>) -> Option<RawInstalledFrom> {
@@ -17,7 +17,9 @@ pub(crate) enum TemplateContent {
 }
 
 pub(crate) enum RenderOperation {
-    AppendToml(PathBuf, TemplateContent),
+    // The optional path is the component directory, relative to the manifest,
+    // when the content is a whole application manifest rather than a snippet.
+    MergeManifest(PathBuf, TemplateContent, Option<PathBuf>),
     WriteFile(PathBuf, TemplateContent),
 }
 
@@ -59,10 +61,14 @@ impl RenderOperation {
                 let rendered = content.render(globals)?;
                 Ok(TemplateOutput::WriteFile(path, rendered))
             }
-            Self::AppendToml(path, content) => {
+            Self::MergeManifest(path, content, component_dir) => {
                 let rendered = content.render(globals)?;
                 let rendered_text = String::from_utf8(rendered)?;
-                Ok(TemplateOutput::AppendToml(path, rendered_text))
+                Ok(TemplateOutput::MergeManifest(
+                    path,
+                    rendered_text,
+                    component_dir,
+                ))
             }
         }
     }
@@ -15,9 +15,12 @@ use crate::{
 };
 use crate::{
     renderer::{RenderOperation, TemplateContent, TemplateRenderer},
-    template::Template,
+    template::{PostRunStep, Template},
+    writer::RunPreview,
 };
 
+type Partials = liquid::partials::EagerCompiler<liquid::partials::InMemorySource>;
+
 /// Executes a template to the point where it is ready to generate
 /// artefacts.
 pub struct Run {
@@ -59,18 +62,80 @@ impl Run {
         self.run(Silent).await
     }
 
+    /// Runs the template interactively, as `interactive`, but instead of
+    /// generating anything returns a preview of what would be generated.
+    /// Returns `None` if the user cancels.
+    pub async fn interactive_dry_run(&self) -> anyhow::Result<Option<RunPreview>> {
+        self.dry_run(Interactive).await
+    }
+
+    /// Runs the template silently, as `silent`, but instead of generating
+    /// anything returns a preview of what would be generated.
+    pub async fn silent_dry_run(&self) -> anyhow::Result<Option<RunPreview>> {
+        self.dry_run(Silent).await
+    }
+
+    async fn dry_run(
+        &self,
+        interaction: impl InteractionStrategy,
+    ) -> anyhow::Result<Option<RunPreview>> {
+        let preview = self
+            .build_renderer(&interaction)
+            .await
+            .and_then(|t| t.render())
+            .and_then_async(|o| async move { o.preview().await })
+            .await
+            .into_option()?;
+        Ok(preview.map(|mut preview| {
+            preview.post_run_commands = self
+                .template
+                .post_run_steps(&self.options.variant)
+                .map(|step| step.command().to_owned())
+                .collect();
+            preview
+        }))
+    }
+
     async fn run(&self, interaction: impl InteractionStrategy) -> anyhow::Result<()> {
-        self.build_renderer(interaction)
+        self.build_renderer(&interaction)
             .await
             .and_then(|t| t.render())
             .and_then_async(|o| async move { o.write().await })
             .await
+            .and_then_async(|_| self.post_run(&interaction))
+            .await
             .err()
     }
 
+    // Steps such as installing dependencies, so that the generated project
+    // builds straight away. The project has been generated by this point, so
+    // a failed step is reported but doesn't fail the run.
+    async fn post_run(&self, interaction: &impl InteractionStrategy) -> anyhow::Result<()> {
+        let dir = self.generation_target_dir();
+        for step in self.template.post_run_steps(&self.options.variant) {
+            if !interaction.allow_post_run_step(step) {
+                println!(
+                    "Skipped `{}`: you may need to run it in {} yourself",
+                    step.command(),
+                    dir.display()
+                );
+                continue;
+            }
+            if let Err(e) = run_post_run_step(step, &dir).await {
+                eprintln!(
+                    "Warning: {:#}. You may need to run `{}` in {} yourself",
+                    e,
+                    step.command(),
+                    dir.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
     async fn build_renderer(
         &self,
-        interaction: impl InteractionStrategy,
+        interaction: &impl InteractionStrategy,
     ) -> Cancellable<TemplateRenderer, anyhow::Error> {
         self.build_renderer_raw(interaction).await.into()
     }
@@ -82,7 +147,7 @@ impl Run {
     // a better way but I don't see one yet...
     async fn build_renderer_raw(
         &self,
-        interaction: impl InteractionStrategy,
+        interaction: &impl InteractionStrategy,
     ) -> anyhow::Result<Option<TemplateRenderer>> {
         self.validate_trigger().await?;
 
@@ -114,7 +179,13 @@ impl Run {
             .map(|(id, path)| self.snippet_operation(id, path))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
-        let render_operations = files.into_iter().chain(snippets).collect();
+        let components = self.component_manifest_operation()?;
+
+        let render_operations = files
+            .into_iter()
+            .chain(snippets)
+            .chain(components)
+            .collect();
 
         match interaction.populate_parameters(self) {
             Cancellable::Ok(parameter_values) => {
@@ -233,16 +304,17 @@ impl Run {
         let abs_snippet_file = snippets_dir.join(snippet_file);
         let file_content = std::fs::read(abs_snippet_file)
             .with_context(|| format!("Error reading snippet file {}", snippet_file))?;
-        let content = TemplateContent::infer_from_bytes(file_content, &self.template_parser())
+        let content = TemplateContent::infer_from_bytes(file_content, &self.template_parser()?)
             .with_context(|| format!("Error parsing snippet file {}", snippet_file))?;
 
         match id {
             "component" => {
                 match &self.options.variant {
                     TemplateVariantInfo::AddComponent { manifest_path } =>
-                        Ok(RenderOperation::AppendToml(
+                        Ok(RenderOperation::MergeManifest(
                             manifest_path.clone(),
                             content,
+                            None,
                         )),
                     TemplateVariantInfo::NewApplication =>
                         Err(anyhow::anyhow!("Spin doesn't know what to do with a 'component' snippet outside an 'add component' operation")),
@@ -255,6 +327,34 @@ impl Run {
         }
     }
 
+    fn component_manifest_operation(&self) -> anyhow::Result<Option<RenderOperation>> {
+        let TemplateVariantInfo::AddComponent { manifest_path } = &self.options.variant else {
+            return Ok(None);
+        };
+        let Some(component_manifest) = self.template.component_manifest(&self.options.variant)
+        else {
+            return Ok(None);
+        };
+        let file_content = std::fs::read(&component_manifest).with_context(|| {
+            format!(
+                "Error reading template manifest {}",
+                component_manifest.display()
+            )
+        })?;
+        let content = TemplateContent::infer_from_bytes(file_content, &self.template_parser()?)
+            .with_context(|| {
+                format!(
+                    "Error parsing template manifest {}",
+                    component_manifest.display()
+                )
+            })?;
+        Ok(Some(RenderOperation::MergeManifest(
+            manifest_path.clone(),
+            content,
+            Some(self.relative_target_dir().to_owned()),
+        )))
+    }
+
     fn list_content_files(from: &Path) -> anyhow::Result<Vec<PathBuf>> {
         let walker = WalkDir::new(from);
         let files = walker
@@ -275,7 +375,7 @@ impl Run {
 
     // TODO: async when we know where things sit
     fn read_all(&self, paths: Vec<PathBuf>) -> anyhow::Result<Vec<(PathBuf, TemplateContent)>> {
-        let template_parser = self.template_parser();
+        let template_parser = self.template_parser()?;
         let contents = paths
             .iter()
             .map(std::fs::read)
@@ -307,17 +407,58 @@ impl Run {
         pathdiff::diff_paths(source, src_dir).map(|rel| (dest_dir.join(rel), cont))
     }
 
-    fn template_parser(&self) -> liquid::Parser {
+    fn template_parser(&self) -> anyhow::Result<liquid::Parser> {
         let mut builder = liquid::ParserBuilder::with_stdlib()
             .filter(crate::filters::KebabCaseFilterParser)
             .filter(crate::filters::PascalCaseFilterParser)
+            .filter(crate::filters::CamelCaseFilterParser)
             .filter(crate::filters::SnakeCaseFilterParser)
-            .filter(crate::filters::HttpWildcardFilterParser);
+            .filter(crate::filters::HttpWildcardFilterParser)
+            .filter(crate::filters::UuidFilterParser)
+            .partials(self.template_partials()?);
         for filter in self.template.custom_filters() {
             builder = builder.filter(filter);
         }
-        builder
-            .build()
-            .expect("can't fail due to no partials support")
+        builder.build().context("Error parsing template partials")
+    }
+
+    // Partials are included by their path relative to the partials directory,
+    // e.g. `{% include "license/header.txt" %}`.
+    fn template_partials(&self) -> anyhow::Result<Partials> {
+        let mut partials = Partials::empty();
+        if let Some(partials_dir) = self.template.partials_dir() {
+            for path in Self::list_content_files(partials_dir)? {
+                let name = path
+                    .strip_prefix(partials_dir)?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Error reading partial {}", name))?;
+                partials.add(name, text);
+            }
+        }
+        Ok(partials)
+    }
+}
+
+async fn run_post_run_step(step: &PostRunStep, dir: &Path) -> anyhow::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    let status = command
+        .arg(step.command())
+        .current_dir(dir)
+        .status()
+        .await
+        .with_context(|| format!("Failed to run `{}`", step.command()))?;
+    if !status.success() {
+        return Err(anyhow!("`{}` failed ({})", step.command(), status));
     }
+    Ok(())
 }
@@ -8,6 +8,7 @@ use url::Url;
 use crate::{directory::subdirectories, git::UnderstandGitResult};
 
 const TEMPLATE_SOURCE_DIR: &str = "templates";
+const SHARED_PARTIALS_DIR: &str = "partials";
 const TEMPLATE_VERSION_TAG_PREFIX: &str = "spin/templates/v";
 
 /// A source from which to install templates.
@@ -25,6 +26,25 @@ pub enum TemplateSource {
     /// Templates much be in a `/templates` directory under the specified
     /// root.
     File(PathBuf),
+    /// Install from a template pack pulled from an OCI registry.
+    ///
+    /// Templates much be in a `/templates` directory under the root of the
+    /// pack.
+    Oci(OciTemplateSource),
+    /// Install from a bundle exported by `TemplateManager::export`.
+    ///
+    /// Templates installed from a bundle keep the record of where they were
+    /// originally installed from, so that they can be upgraded from there.
+    Bundle(PathBuf),
+}
+
+/// A template pack which has been pulled from an OCI registry.
+#[derive(Debug)]
+pub struct OciTemplateSource {
+    /// The reference from which the pack was pulled.
+    reference: String,
+    /// The directory into which the pack was unpacked.
+    dir: PathBuf,
 }
 
 /// Settings for installing templates from a Git repository.
@@ -34,6 +54,9 @@ pub struct GitTemplateSource {
     url: Url,
     /// The branch or tag from which to install templates; inferred if omitted.
     branch: Option<String>,
+    /// The commit from which to install templates, if pinned. This overrides
+    /// the branch.
+    revision: Option<String>,
     /// The version of the Spin client, used for branch inference.
     // We have to pass this through because vergen is only on the root bin
     spin_version: String,
@@ -53,14 +76,49 @@ impl TemplateSource {
         Ok(Self::Git(GitTemplateSource {
             url,
             branch: branch.clone(),
+            revision: None,
             spin_version: spin_version.to_owned(),
         }))
     }
 
-    pub(crate) fn to_install_record(&self) -> Option<crate::reader::RawInstalledFrom> {
+    /// Creates a `TemplateSource` referring to the specified commit of the
+    /// specified Git repository. The revision must be a commit hash.
+    pub fn try_from_pinned_git(
+        git_url: impl AsRef<str>,
+        revision: impl Into<String>,
+        spin_version: &str,
+    ) -> anyhow::Result<Self> {
+        let revision = revision.into();
+        // Revisions come from lockfiles, which may not be trustworthy, and are
+        // passed to git on its command line.
+        if revision.is_empty() || !revision.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Invalid template revision '{revision}': expected a commit hash");
+        }
+        let mut source = Self::try_from_git(git_url, &None, spin_version)?;
+        if let Self::Git(g) = &mut source {
+            g.revision = Some(revision);
+        }
+        Ok(source)
+    }
+
+    /// Creates a `TemplateSource` referring to a template pack pulled from
+    /// the given OCI reference and unpacked into the given directory.
+    pub fn from_pulled_oci(reference: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self::Oci(OciTemplateSource {
+            reference: reference.into(),
+            dir: dir.into(),
+        })
+    }
+
+    pub(crate) fn to_install_record(
+        &self,
+        local: &LocalTemplateSource,
+    ) -> Option<crate::reader::RawInstalledFrom> {
         match self {
             Self::Git(g) => Some(crate::reader::RawInstalledFrom::Git {
                 git: g.url.to_string(),
+                revision: local.revision.clone(),
+                branch: local.branch.clone(),
             }),
             Self::File(p) => {
                 // Saving a relative path would be meaningless (but should never happen)
@@ -72,6 +130,10 @@ impl TemplateSource {
                     None
                 }
             }
+            Self::Oci(o) => Some(crate::reader::RawInstalledFrom::Oci {
+                oci: o.reference.clone(),
+            }),
+            Self::Bundle(_) => None,
         }
     }
 
@@ -88,6 +150,10 @@ impl TemplateSource {
 
 pub(crate) struct LocalTemplateSource {
     root: PathBuf,
+    // For Git sources, the commit checked out, and the branch or tag it was
+    // checked out from.
+    revision: Option<String>,
+    branch: Option<String>,
     _temp_dir: Option<TempDir>,
 }
 
@@ -96,18 +162,27 @@ impl TemplateSource {
         match self {
             Self::Git(git_source) => clone_local(git_source).await,
             Self::File(path) => check_local(path).await,
+            Self::Oci(oci_source) => check_local(&oci_source.dir).await,
+            Self::Bundle(bundle) => unpack_local(bundle).await,
         }
     }
 
     pub(crate) fn requires_copy(&self) -> bool {
         match self {
-            Self::Git { .. } => true,
-            Self::File(_) => false,
+            Self::Git { .. } | Self::Bundle(_) => true,
+            Self::File(_) | Self::Oci(_) => false,
         }
     }
 }
 
 impl LocalTemplateSource {
+    /// The directory of partials shared by all the templates in the source,
+    /// if there is one.
+    pub fn shared_partials_dir(&self) -> Option<PathBuf> {
+        let partials_dir = self.root.join(SHARED_PARTIALS_DIR);
+        partials_dir.is_dir().then_some(partials_dir)
+    }
+
     pub async fn template_directories(&self) -> anyhow::Result<Vec<PathBuf>> {
         let templates_root = self.root.join(TEMPLATE_SOURCE_DIR);
         if templates_root.exists() {
@@ -133,29 +208,77 @@ async fn clone_local(git_source: &GitTemplateSource) -> anyhow::Result<LocalTemp
 
     let url_str = git_source.url.as_str();
 
-    let actual_branch = match &git_source.branch {
-        Some(b) => Some(b.clone()),
-        None => version_matched_tag(url_str, &git_source.spin_version).await,
-    };
-
     let mut git = Command::new("git");
     git.arg("clone");
-    git.arg("--depth").arg("1");
 
-    if let Some(b) = actual_branch {
-        git.arg("--branch").arg(b);
-    }
+    // A commit can't be cloned directly, so pinned sources clone the whole
+    // repo and then check the commit out.
+    let branch = match &git_source.revision {
+        Some(_) => None,
+        None => {
+            let actual_branch = match &git_source.branch {
+                Some(b) => Some(b.clone()),
+                None => version_matched_tag(url_str, &git_source.spin_version).await,
+            };
+            git.arg("--depth").arg("1");
+            if let Some(b) = &actual_branch {
+                git.arg("--branch").arg(b);
+            }
+            actual_branch
+        }
+    };
 
     git.arg(url_str).arg(&path);
 
     let clone_result = git.output().await.understand_git_result();
-    match clone_result {
-        Ok(_) => Ok(LocalTemplateSource {
-            root: path,
-            _temp_dir: Some(temp_dir),
-        }),
-        Err(e) => Err(anyhow!("Error cloning Git repo {}: {}", url_str, e)),
+    if let Err(e) = clone_result {
+        return Err(anyhow!("Error cloning Git repo {}: {}", url_str, e));
+    }
+
+    if let Some(revision) = &git_source.revision {
+        let mut git = Command::new("git");
+        git.arg("-C").arg(&path);
+        git.arg("checkout")
+            .arg("--quiet")
+            .arg("--detach")
+            .arg(revision)
+            .arg("--");
+        if let Err(e) = git.output().await.understand_git_result() {
+            return Err(anyhow!(
+                "Error checking out revision {} of Git repo {}: {}",
+                revision,
+                url_str,
+                e
+            ));
+        }
     }
+
+    // Not knowing the commit shouldn't fail the install
+    let mut git = Command::new("git");
+    git.arg("-C").arg(&path);
+    git.arg("rev-parse").arg("HEAD");
+    let revision = match git.output().await.understand_git_result() {
+        Ok(stdout) => Some(String::from_utf8_lossy(&stdout).trim().to_owned()),
+        Err(_) => None,
+    };
+
+    Ok(LocalTemplateSource {
+        root: path,
+        revision,
+        branch,
+        _temp_dir: Some(temp_dir),
+    })
+}
+
+async fn unpack_local(bundle: &Path) -> anyhow::Result<LocalTemplateSource> {
+    let temp_dir = tempdir()?;
+    crate::bundle::unpack_bundle(bundle, temp_dir.path())?;
+    Ok(LocalTemplateSource {
+        root: temp_dir.path().to_owned(),
+        revision: None,
+        branch: None,
+        _temp_dir: Some(temp_dir),
+    })
 }
 
 async fn version_matched_tag(url: &str, spin_version: &str) -> Option<String> {
@@ -185,6 +308,8 @@ async fn check_local(path: &Path) -> anyhow::Result<LocalTemplateSource> {
     if path.exists() {
         Ok(LocalTemplateSource {
             root: path.to_owned(),
+            revision: None,
+            branch: None,
             _temp_dir: None,
         })
     } else {
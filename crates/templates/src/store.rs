@@ -69,6 +69,7 @@ const METADATA_DIR_NAME: &str = "metadata";
 const FILTERS_DIR_NAME: &str = "filters";
 const CONTENT_DIR_NAME: &str = "content";
 const SNIPPETS_DIR_NAME: &str = "snippets";
+const PARTIALS_DIR_NAME: &str = "partials";
 
 const MANIFEST_FILE_NAME: &str = "spin-template.toml";
 
@@ -105,6 +106,10 @@ impl TemplateLayout {
         self.metadata_dir().join(SNIPPETS_DIR_NAME)
     }
 
+    pub fn partials_dir(&self) -> PathBuf {
+        self.metadata_dir().join(PARTIALS_DIR_NAME)
+    }
+
     pub fn install_record_file(&self) -> PathBuf {
         self.template_dir.join(INSTALLATION_RECORD_FILE_NAME)
     }
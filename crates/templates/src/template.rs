@@ -8,13 +8,17 @@ use indexmap::IndexMap;
 use regex::Regex;
 
 use crate::{
-    constraints::StringConstraints,
+    constraints::{EnumConstraints, IntConstraints, StringConstraints},
     custom_filters::CustomFilterParser,
-    reader::{RawCustomFilter, RawParameter, RawTemplateManifest, RawTemplateVariant},
+    reader::{
+        RawCustomFilter, RawParameter, RawPostRunStep, RawTemplateManifest, RawTemplateVariant,
+    },
     run::{Run, RunOptions},
     store::TemplateLayout,
 };
 
+const MANIFEST_FILE: &str = "spin.toml";
+
 /// A Spin template.
 #[derive(Debug)]
 pub struct Template {
@@ -26,14 +30,20 @@ pub struct Template {
     variants: HashMap<TemplateVariantKind, TemplateVariant>,
     parameters: Vec<TemplateParameter>,
     custom_filters: Vec<CustomFilterParser>,
+    post_run_steps: Vec<PostRunStep>,
     snippets_dir: Option<PathBuf>,
+    partials_dir: Option<PathBuf>,
     content_dir: Option<PathBuf>, // TODO: maybe always need a spin.toml file in there?
 }
 
 #[derive(Debug)]
 enum InstalledFrom {
-    Git(String),
+    Git {
+        repo: String,
+        revision: Option<String>,
+    },
     Directory(String),
+    Oci(String),
     Unknown,
 }
 
@@ -84,7 +94,11 @@ impl TemplateVariantInfo {
 pub(crate) struct TemplateVariant {
     skip_files: Vec<String>,
     skip_parameters: Vec<String>,
+    skip_post_run: Vec<String>,
     snippets: HashMap<String, String>,
+    // The content manifest whose components are merged into the application,
+    // for templates which don't say how to add components.
+    component_manifest: Option<String>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -96,6 +110,9 @@ pub(crate) enum TemplateTriggerCompatibility {
 #[derive(Clone, Debug)]
 pub(crate) enum TemplateParameterDataType {
     String(StringConstraints),
+    Bool,
+    Int(IntConstraints),
+    Enum(EnumConstraints),
 }
 
 #[derive(Debug)]
@@ -106,6 +123,15 @@ pub(crate) struct TemplateParameter {
     default_value: Option<String>,
 }
 
+/// A command to run in the generated directory after the template has run,
+/// such as installing dependencies.
+#[derive(Clone, Debug)]
+pub(crate) struct PostRunStep {
+    id: String,
+    command: String,
+    description: Option<String>,
+}
+
 impl Template {
     pub(crate) fn load_from(layout: &TemplateLayout) -> anyhow::Result<Self> {
         let manifest_path = layout.manifest_path();
@@ -135,6 +161,12 @@ impl Template {
             None
         };
 
+        let partials_dir = if layout.partials_dir().exists() {
+            Some(layout.partials_dir())
+        } else {
+            None
+        };
+
         let installed_from = read_install_record(layout);
 
         let template = match raw {
@@ -144,10 +176,16 @@ impl Template {
                 description: raw.description.clone(),
                 installed_from,
                 trigger: Self::parse_trigger_type(raw.trigger_type, layout),
-                variants: Self::parse_template_variants(raw.new_application, raw.add_component),
+                variants: Self::parse_template_variants(
+                    raw.new_application,
+                    raw.add_component,
+                    layout,
+                ),
                 parameters: Self::parse_parameters(&raw.parameters)?,
                 custom_filters: Self::load_custom_filters(layout, &raw.custom_filters)?,
+                post_run_steps: Self::parse_post_run_steps(raw.post_run),
                 snippets_dir,
+                partials_dir,
                 content_dir,
             },
         };
@@ -195,7 +233,17 @@ impl Template {
         // TODO: this is kind of specialised - should we do the discarding of
         // non-Git sources at the application layer?
         match &self.installed_from {
-            InstalledFrom::Git(url) => Some(url),
+            InstalledFrom::Git { repo, .. } => Some(repo),
+            _ => None,
+        }
+    }
+
+    /// The Git commit from which the template was installed, if it was
+    /// installed from Git by a version of Spin which records it; otherwise
+    /// None.
+    pub fn source_revision(&self) -> Option<&str> {
+        match &self.installed_from {
+            InstalledFrom::Git { revision, .. } => revision.as_deref(),
             _ => None,
         }
     }
@@ -204,8 +252,9 @@ impl Template {
     /// from.
     pub fn installed_from_or_empty(&self) -> &str {
         match &self.installed_from {
-            InstalledFrom::Git(repo) => repo,
+            InstalledFrom::Git { repo, .. } => repo,
             InstalledFrom::Directory(path) => path,
+            InstalledFrom::Oci(reference) => reference,
             InstalledFrom::Unknown => "",
         }
     }
@@ -225,6 +274,16 @@ impl Template {
             .filter(|p| !variant.skip_parameter(p))
     }
 
+    pub(crate) fn post_run_steps(
+        &self,
+        variant_kind: &TemplateVariantInfo,
+    ) -> impl Iterator<Item = &PostRunStep> {
+        let variant = self.variant(variant_kind).unwrap(); // TODO: for now
+        self.post_run_steps
+            .iter()
+            .filter(|s| !variant.skip_post_run_step(s))
+    }
+
     pub(crate) fn parameter(&self, name: impl AsRef<str>) -> Option<&TemplateParameter> {
         self.parameters.iter().find(|p| p.id == name.as_ref())
     }
@@ -241,6 +300,10 @@ impl Template {
         &self.snippets_dir
     }
 
+    pub(crate) fn partials_dir(&self) -> &Option<PathBuf> {
+        &self.partials_dir
+    }
+
     /// Checks if the template supports the specified variant mode.
     pub fn supports_variant(&self, variant: &TemplateVariantInfo) -> bool {
         self.variants.contains_key(&variant.kind())
@@ -251,6 +314,17 @@ impl Template {
         &variant.snippets
     }
 
+    /// The content manifest from which to take the components to add, if the
+    /// variant adds the template's own components rather than snippets.
+    pub(crate) fn component_manifest(&self, variant_kind: &TemplateVariantInfo) -> Option<PathBuf> {
+        let variant = self.variant(variant_kind)?;
+        let content_dir = self.content_dir.as_ref()?;
+        variant
+            .component_manifest
+            .as_ref()
+            .map(|file| content_dir.join(file))
+    }
+
     /// Creates a runner for the template, governed by the given options. Call
     /// the relevant associated function of the `Run` to execute the template
     /// as appropriate to your application (e.g. `interactive()` to prompt the user
@@ -285,17 +359,35 @@ impl Template {
     fn parse_template_variants(
         new_application: Option<RawTemplateVariant>,
         add_component: Option<RawTemplateVariant>,
+        layout: &TemplateLayout,
     ) -> HashMap<TemplateVariantKind, TemplateVariant> {
         let mut variants = HashMap::default();
         if let Some(vt) = Self::get_variant(new_application, true) {
             variants.insert(TemplateVariantKind::NewApplication, vt);
         }
-        if let Some(vt) = Self::get_variant(add_component, false) {
+        let add_component = match add_component {
+            None => Self::component_manifest_variant(layout),
+            add_component => Self::get_variant(add_component, false),
+        };
+        if let Some(vt) = add_component {
             variants.insert(TemplateVariantKind::AddComponent, vt);
         }
         variants
     }
 
+    // A template which doesn't say how to add components can still add the
+    // components from its application manifest, if it has any.
+    fn component_manifest_variant(layout: &TemplateLayout) -> Option<TemplateVariant> {
+        match crate::app_info::AppInfo::from_layout(layout) {
+            Some(Ok(app_info)) if app_info.has_components() => Some(TemplateVariant {
+                skip_files: vec![MANIFEST_FILE.to_owned()],
+                component_manifest: Some(MANIFEST_FILE.to_owned()),
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
     fn get_variant(
         raw: Option<RawTemplateVariant>,
         default_supported: bool,
@@ -322,10 +414,23 @@ impl Template {
         TemplateVariant {
             skip_files: raw.skip_files.unwrap_or_default(),
             skip_parameters: raw.skip_parameters.unwrap_or_default(),
+            skip_post_run: raw.skip_post_run.unwrap_or_default(),
             snippets: raw.snippets.unwrap_or_default(),
+            component_manifest: None,
         }
     }
 
+    fn parse_post_run_steps(raw: Option<IndexMap<String, RawPostRunStep>>) -> Vec<PostRunStep> {
+        raw.unwrap_or_default()
+            .into_iter()
+            .map(|(id, step)| PostRunStep {
+                id,
+                command: step.command,
+                description: step.description,
+            })
+            .collect()
+    }
+
     fn parse_parameters(
         raw: &Option<IndexMap<String, RawParameter>>,
     ) -> anyhow::Result<Vec<TemplateParameter>> {
@@ -389,12 +494,18 @@ impl Template {
 impl TemplateParameter {
     fn from_raw(id: &str, raw: &RawParameter) -> anyhow::Result<Self> {
         let data_type = TemplateParameterDataType::parse(raw)?;
+        let default_value = raw
+            .default_value
+            .clone()
+            .map(|value| data_type.validate_value(value))
+            .transpose()
+            .with_context(|| format!("Invalid default value for parameter '{id}'"))?;
 
         Ok(Self {
             id: id.to_owned(),
             data_type,
             prompt: raw.prompt.clone(),
-            default_value: raw.default_value.clone(),
+            default_value,
         })
     }
 
@@ -423,13 +534,47 @@ impl TemplateParameterDataType {
     fn parse(raw: &RawParameter) -> anyhow::Result<Self> {
         match &raw.data_type[..] {
             "string" => Ok(Self::String(parse_string_constraints(raw)?)),
+            "bool" => Ok(Self::Bool),
+            "int" => Ok(Self::Int(IntConstraints {
+                min: raw.min,
+                max: raw.max,
+            })),
+            "enum" => Ok(Self::Enum(parse_enum_constraints(raw)?)),
             _ => Err(anyhow!("Unrecognised data type '{}'", raw.data_type)),
         }
     }
 
+    /// A description of the values the parameter accepts, for messages.
+    pub(crate) fn value_hint(&self) -> String {
+        match self {
+            TemplateParameterDataType::String(_) => "text".to_owned(),
+            TemplateParameterDataType::Bool => "true|false".to_owned(),
+            TemplateParameterDataType::Int(_) => "number".to_owned(),
+            TemplateParameterDataType::Enum(constraints) => constraints.allowed_values.join("|"),
+        }
+    }
+
     fn validate_value(&self, value: String) -> anyhow::Result<String> {
         match self {
             TemplateParameterDataType::String(constraints) => constraints.validate(value),
+            TemplateParameterDataType::Bool => crate::constraints::validate_bool(value),
+            TemplateParameterDataType::Int(constraints) => constraints.validate(value),
+            TemplateParameterDataType::Enum(constraints) => constraints.validate(value),
+        }
+    }
+}
+
+impl PostRunStep {
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The question to ask before running the step, e.g. "Install
+    /// dependencies (runs `npm install`)?".
+    pub fn prompt(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{} (runs `{}`)?", description, self.command),
+            None => format!("Run `{}`?", self.command),
         }
     }
 }
@@ -445,6 +590,10 @@ impl TemplateVariant {
     pub(crate) fn skip_parameter(&self, parameter: &TemplateParameter) -> bool {
         self.skip_parameters.iter().any(|p| &parameter.id == p)
     }
+
+    pub(crate) fn skip_post_run_step(&self, step: &PostRunStep) -> bool {
+        self.skip_post_run.iter().any(|s| &step.id == s)
+    }
 }
 
 fn parse_string_constraints(raw: &RawParameter) -> anyhow::Result<StringConstraints> {
@@ -453,13 +602,28 @@ fn parse_string_constraints(raw: &RawParameter) -> anyhow::Result<StringConstrai
     Ok(StringConstraints { regex })
 }
 
+fn parse_enum_constraints(raw: &RawParameter) -> anyhow::Result<EnumConstraints> {
+    match &raw.allowed_values {
+        Some(allowed_values) if !allowed_values.is_empty() => Ok(EnumConstraints {
+            allowed_values: allowed_values.clone(),
+        }),
+        _ => Err(anyhow!(
+            "Parameters of type 'enum' must have 'allowed_values'"
+        )),
+    }
+}
+
 fn read_install_record(layout: &TemplateLayout) -> InstalledFrom {
     use crate::reader::{parse_installed_from, RawInstalledFrom};
 
     let installed_from_text = std::fs::read_to_string(layout.install_record_file()).ok();
     match installed_from_text.and_then(parse_installed_from) {
-        Some(RawInstalledFrom::Git { git }) => InstalledFrom::Git(git),
+        Some(RawInstalledFrom::Git { git, revision, .. }) => InstalledFrom::Git {
+            repo: git,
+            revision,
+        },
         Some(RawInstalledFrom::File { dir }) => InstalledFrom::Directory(dir),
+        Some(RawInstalledFrom::Oci { oci }) => InstalledFrom::Oci(oci),
         None => InstalledFrom::Unknown,
     }
 }
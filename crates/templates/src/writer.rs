@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 
@@ -8,7 +11,7 @@ pub(crate) struct TemplateOutputs {
 
 pub(crate) enum TemplateOutput {
     WriteFile(PathBuf, Vec<u8>),
-    AppendToml(PathBuf, String),
+    MergeManifest(PathBuf, String, Option<PathBuf>),
 }
 
 impl TemplateOutputs {
@@ -17,37 +20,140 @@ impl TemplateOutputs {
     }
 
     pub async fn write(&self) -> anyhow::Result<()> {
+        // Merge into manifests before writing anything, so that a conflict
+        // with the existing application leaves the application untouched.
+        let manifests = self.merged_manifests().await?;
+
         for output in &self.outputs {
-            output.write().await?;
+            if let TemplateOutput::WriteFile(path, contents) = output {
+                write_file(path, contents).await?;
+            }
+        }
+        for (path, (_, new_toml)) in manifests {
+            tokio::fs::write(path, new_toml)
+                .await
+                .with_context(|| format!("Can't save changes to {}", path.display()))?;
         }
         Ok(())
     }
+
+    /// Describes what `write` would do, without writing anything.
+    pub async fn preview(&self) -> anyhow::Result<RunPreview> {
+        let files = self
+            .outputs
+            .iter()
+            .filter_map(|output| match output {
+                TemplateOutput::WriteFile(path, _) => Some(path.clone()),
+                TemplateOutput::MergeManifest(..) => None,
+            })
+            .collect();
+        let manifest_diffs = self
+            .merged_manifests()
+            .await?
+            .into_iter()
+            .map(|(path, (old_toml, new_toml))| {
+                let name = path.display().to_string();
+                let diff = similar::udiff::unified_diff(
+                    Default::default(),
+                    &old_toml,
+                    &new_toml,
+                    3,
+                    Some((&name, &name)),
+                );
+                (path.clone(), diff)
+            })
+            .collect();
+        Ok(RunPreview {
+            files,
+            manifest_diffs,
+            post_run_commands: vec![],
+        })
+    }
+
+    // Returns the original and merged text of each manifest that outputs
+    // are merged into.
+    async fn merged_manifests(&self) -> anyhow::Result<HashMap<&PathBuf, (String, String)>> {
+        let mut manifests: HashMap<&PathBuf, (String, String)> = HashMap::new();
+        for output in &self.outputs {
+            if let TemplateOutput::MergeManifest(path, text, component_dir) = output {
+                let (original_toml, existing_toml) = match manifests.remove(path) {
+                    Some(toml) => toml,
+                    None => {
+                        let toml = tokio::fs::read_to_string(path)
+                            .await
+                            .with_context(|| format!("Can't open {} to add to", path.display()))?;
+                        (toml.clone(), toml)
+                    }
+                };
+                let new_toml =
+                    crate::merge::merge_manifest(&existing_toml, text, component_dir.as_deref())
+                        .with_context(|| format!("Can't add to {}", path.display()))?;
+                manifests.insert(path, (original_toml, new_toml));
+            }
+        }
+        Ok(manifests)
+    }
 }
 
-impl TemplateOutput {
-    pub async fn write(&self) -> anyhow::Result<()> {
-        match &self {
-            TemplateOutput::WriteFile(path, contents) => {
-                let dir = path.parent().with_context(|| {
-                    format!("Can't get directory containing {}", path.display())
-                })?;
-                tokio::fs::create_dir_all(&dir)
-                    .await
-                    .with_context(|| format!("Failed to create directory {}", dir.display()))?;
-                tokio::fs::write(&path, &contents)
-                    .await
-                    .with_context(|| format!("Failed to write file {}", path.display()))?;
+/// What running a template would do: the files it would create, the changes
+/// it would make to existing manifests, and the commands it would offer to
+/// run afterwards.
+#[derive(Debug)]
+pub struct RunPreview {
+    files: Vec<PathBuf>,
+    manifest_diffs: Vec<(PathBuf, String)>,
+    pub(crate) post_run_commands: Vec<String>,
+}
+
+impl RunPreview {
+    /// The files which would be created, or overwritten.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The manifests which would be changed, each with a unified diff of
+    /// the change.
+    pub fn manifest_diffs(&self) -> &[(PathBuf, String)] {
+        &self.manifest_diffs
+    }
+
+    /// The commands which would be offered to run after generation.
+    pub fn post_run_commands(&self) -> &[String] {
+        &self.post_run_commands
+    }
+}
+
+impl std::fmt::Display for RunPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.files.is_empty() {
+            writeln!(f, "Would create:")?;
+            for path in &self.files {
+                writeln!(f, "  {}", path.display())?;
             }
-            TemplateOutput::AppendToml(path, text) => {
-                let existing_toml = tokio::fs::read_to_string(path)
-                    .await
-                    .with_context(|| format!("Can't open {} to append", path.display()))?;
-                let new_toml = format!("{}\n\n{}", existing_toml.trim_end(), text);
-                tokio::fs::write(path, new_toml)
-                    .await
-                    .with_context(|| format!("Can't save changes to {}", path.display()))?;
+        }
+        for (path, diff) in &self.manifest_diffs {
+            writeln!(f, "Would change {}:", path.display())?;
+            write!(f, "{diff}")?;
+        }
+        if !self.post_run_commands.is_empty() {
+            writeln!(f, "Would then offer to run:")?;
+            for command in &self.post_run_commands {
+                writeln!(f, "  {command}")?;
             }
         }
         Ok(())
     }
 }
+
+async fn write_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let dir = path
+        .parent()
+        .with_context(|| format!("Can't get directory containing {}", path.display()))?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    tokio::fs::write(&path, &contents)
+        .await
+        .with_context(|| format!("Failed to write file {}", path.display()))?;
+    Ok(())
+}
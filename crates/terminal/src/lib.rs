@@ -3,6 +3,8 @@
 //! This library is used by Spin to print out messages in an appropriate format
 //! that is easy for users to read. This is not meant as a general purpose library.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use once_cell::sync::OnceCell;
 use termcolor::{ColorSpec, StandardStream, StandardStreamLock, WriteColor};
 
@@ -64,21 +66,140 @@ fn set_color(stream: &'static StandardStream, spec: ColorSpec) -> ColorText {
     ColorText(lock)
 }
 
+/// The environment variable which selects whether output is colored:
+/// `always`, `never`, or `auto` to color output to terminals only.
+pub const COLOR_ENV: &str = "SPIN_COLOR";
+
+static DEFAULT_COLOR: OnceCell<String> = OnceCell::new();
+
+/// Sets whether output is colored, as `SPIN_COLOR` does, unless it is set.
+/// Only the first call has any effect, and it must be made before anything
+/// is printed.
+pub fn set_default_color(color: &str) {
+    let _ = DEFAULT_COLOR.set(color.to_owned());
+}
+
+/// Returns whether output is colored: `always`, `never` or `auto`, from
+/// `SPIN_COLOR` or [`set_default_color`], if either sets it.
+pub fn color_preference() -> Option<String> {
+    std::env::var(COLOR_ENV)
+        .ok()
+        .or_else(|| DEFAULT_COLOR.get().cloned())
+}
+
 fn color_choice(stream: atty::Stream) -> termcolor::ColorChoice {
-    if atty::is(stream) {
-        termcolor::ColorChoice::Auto
+    match color_preference().as_deref() {
+        Ok("always") => termcolor::ColorChoice::Always,
+        Ok("never") => termcolor::ColorChoice::Never,
+        _ if atty::is(stream) => termcolor::ColorChoice::Auto,
+        _ => termcolor::ColorChoice::Never,
+    }
+}
+
+/// The environment variable which sets how much Spin prints about what it
+/// is doing: `quiet`, `normal` or `verbose`. Commands set it for the
+/// processes they run, such as triggers, so that those print as much as they
+/// do.
+pub const VERBOSITY_ENV: &str = "SPIN_VERBOSITY";
+
+/// How much Spin prints about what it is doing. Errors, warnings and the
+/// results of commands are printed regardless.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// No progress or status messages.
+    Quiet,
+    #[default]
+    Normal,
+    /// Additional details, as well as progress and status messages.
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Quiet => "quiet",
+            Self::Normal => "normal",
+            Self::Verbose => "verbose",
+        }
+    }
+}
+
+static VERBOSITY: OnceCell<Verbosity> = OnceCell::new();
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Sets the verbosity of this process, e.g. from a command's `--quiet` and
+/// `--verbose` flags. It must be set before anything is printed, and only the
+/// first call has any effect.
+pub fn set_verbosity(verbosity: Verbosity) {
+    let _ = VERBOSITY.set(verbosity);
+}
+
+/// The verbosity of this process, from [`set_verbosity`] or otherwise
+/// `SPIN_VERBOSITY`. It is read once, so must be set before anything is
+/// printed.
+pub fn verbosity() -> Verbosity {
+    *VERBOSITY.get_or_init(|| match std::env::var(VERBOSITY_ENV).as_deref() {
+        Ok("quiet") => Verbosity::Quiet,
+        Ok("verbose") => Verbosity::Verbose,
+        _ => Verbosity::Normal,
+    })
+}
+
+/// Prints status messages, such as those of [`step!`], to stderr rather than
+/// stdout, e.g. so that stdout holds only a command's JSON output.
+pub fn print_status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn print_step(step: impl std::fmt::Display, message: std::fmt::Arguments) {
+    if verbosity() == Verbosity::Quiet {
+        return;
+    }
+    if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        crate::ceprint!(colors::bold_green(), "{step}");
+        eprintln!(" {message}");
+    } else {
+        crate::cprint!(colors::bold_green(), "{step}");
+        println!(" {message}");
+    }
+}
+
+#[doc(hidden)]
+pub fn print_detail(message: std::fmt::Arguments) {
+    if verbosity() < Verbosity::Verbose {
+        return;
+    }
+    if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{message}");
     } else {
-        termcolor::ColorChoice::Never
+        println!("{message}");
     }
 }
 
+/// Prints a progress or status message, such as `Building component x`,
+/// unless the verbosity is quiet.
 #[macro_export]
 macro_rules! step {
-    ($step:expr, $($arg:tt)*) => {{
+    ($step:expr, $($arg:tt)*) => {
+        $crate::print_step($step, format_args!($($arg)*))
+    };
+}
 
-        $crate::cprint!($crate::colors::bold_green(), $step);
-        print!(" ");
-        println!($($arg)*);
+/// Prints a message only if the verbosity is verbose.
+#[macro_export]
+macro_rules! detail {
+    ($($arg:tt)*) => {
+        $crate::print_detail(format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {{
+        $crate::ceprint!($crate::colors::bold_yellow(), "Warning");
+        eprint!(": ");
+        eprintln!($($arg)*);
     }};
 }
 
@@ -122,6 +243,10 @@ pub mod colors {
         new(Color::Green, true)
     }
 
+    pub fn bold_yellow() -> ColorSpec {
+        new(Color::Yellow, true)
+    }
+
     fn new(color: Color, bold: bool) -> ColorSpec {
         let mut s = ColorSpec::new();
         s.set_fg(Some(color)).set_bold(bold);
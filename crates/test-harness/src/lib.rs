@@ -0,0 +1,448 @@
+//! An in-process harness for integration testing Spin HTTP applications with
+//! `cargo test`.
+//!
+//! A [`TestApp`] loads an application from its manifest and serves it on an
+//! ephemeral port, with a state directory of its own, so that each test
+//! starts from empty key value stores and databases. Helpers send requests
+//! to the application and inspect and seed its stores:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let app = spin_test_harness::TestApp::start("spin.toml").await?;
+//! let response = app.get("/items").await?;
+//! assert_eq!(response.status(), 200);
+//! assert_eq!(app.kv_get("last-request").await?, Some(b"/items".to_vec()));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! For time-dependent logic, [`TestAppBuilder::manual_clock`] gives guests a
+//! clock which only moves when the test advances it, and
+//! [`TestAppBuilder::random_seed`] makes their random numbers reproducible.
+//!
+//! Applications should be built before they are tested, e.g. with
+//! `spin build`.
+#![deny(missing_docs)]
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Method, RequestBuilder, Response};
+use spin_app::AppComponent;
+use spin_core::StoreBuilder;
+use spin_key_value::Store;
+use spin_sqlite::Connection;
+use spin_trigger::{
+    loader::TriggerLoader, HostComponentInitData, RuntimeConfig, TriggerExecutorBuilder,
+    TriggerHooks,
+};
+use spin_trigger_http::HttpTrigger;
+use tempfile::TempDir;
+use tokio::{net::TcpListener, task::JoinHandle};
+use url::Url;
+
+pub use spin_core::ManualClock;
+pub use spin_world::sqlite::{QueryResult, RowResult, Value};
+
+/// The store or database which the application uses if it does not name one.
+const DEFAULT_STORE: &str = "default";
+
+/// Builds a [`TestApp`] with options.
+#[derive(Debug)]
+pub struct TestAppBuilder {
+    manifest: PathBuf,
+    profile: Option<String>,
+    environment: Option<String>,
+    runtime_config_file: Option<PathBuf>,
+    clock: Option<ManualClock>,
+    random_seed: Option<u64>,
+}
+
+impl TestAppBuilder {
+    /// Runs the modules built by the given build profile, such as `release`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Applies the overrides of the given environment, such as `prod`, from
+    /// the manifest's `[profile.<environment>]` table.
+    pub fn environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Uses the given runtime config file, e.g. to configure additional key
+    /// value stores or to mock outbound HTTP requests. Stores which it gives
+    /// paths for are not isolated.
+    pub fn runtime_config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.runtime_config_file = Some(path.into());
+        self
+    }
+
+    /// Gives components a clock which starts at the given wall time and only
+    /// moves when advanced with [`TestApp::clock`].
+    pub fn manual_clock(mut self, start: SystemTime) -> Self {
+        self.clock = Some(ManualClock::new(start));
+        self
+    }
+
+    /// Seeds the random numbers which components get from WASI, so that each
+    /// request sees the same sequence.
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    /// Loads the application and starts serving it.
+    pub async fn start(self) -> Result<TestApp> {
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-test-harness-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let state_dir = tempfile::Builder::new()
+            .prefix("spin-test-harness-state-")
+            .tempdir()
+            .context("Failed to create state directory")?;
+
+        let manifest = self
+            .manifest
+            .canonicalize()
+            .with_context(|| format!("Failed to find application manifest {:?}", self.manifest))?;
+        let app_dir = manifest.parent().map(Path::to_owned);
+        let app = spin_loader::local::from_file_with_profile(
+            &manifest,
+            Some(working_dir.path()),
+            self.profile.as_deref(),
+            self.environment.as_deref(),
+        )
+        .await?;
+        let locked_app = spin_trigger::locked::build_locked_app(app, working_dir.path())?;
+        let locked_url =
+            spin_trigger::locked::write_locked_app(&locked_app, working_dir.path()).await?;
+
+        let loader = TriggerLoader::new(working_dir.path(), false);
+        let mut builder = TriggerExecutorBuilder::<HttpTrigger>::new(loader);
+        builder.hooks(DeterminismHooks {
+            clock: self.clock.clone(),
+            random_seed: self.random_seed,
+        });
+        let trigger = builder
+            .build(
+                locked_url,
+                self.runtime_config(app_dir.clone(), state_dir.path())?,
+                HostComponentInitData::default(),
+            )
+            .await?;
+
+        // The harness opens stores of its own onto the application's state,
+        // so that tests see what the application has written.
+        let runtime_config = self.runtime_config(app_dir, state_dir.path())?;
+        let mut stores = HashMap::new();
+        for (name, manager) in runtime_config.key_value_stores()? {
+            let store = manager
+                .get(&name)
+                .await
+                .map_err(|err| anyhow!("Failed to open key value store {name:?}: {err:?}"))?;
+            stores.insert(name, store);
+        }
+        let databases = runtime_config.sqlite_databases()?.into_iter().collect();
+
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .context("Failed to bind listener")?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(trigger.serve_listener(listener));
+
+        Ok(TestApp {
+            base_url: Url::parse(&format!("http://{addr}"))?,
+            client: reqwest::Client::new(),
+            stores,
+            databases,
+            server,
+            clock: self.clock,
+            _working_dir: working_dir,
+            _state_dir: state_dir,
+        })
+    }
+
+    fn runtime_config(&self, app_dir: Option<PathBuf>, state_dir: &Path) -> Result<RuntimeConfig> {
+        let mut runtime_config = RuntimeConfig::new(app_dir);
+        if let Some(runtime_config_file) = &self.runtime_config_file {
+            runtime_config.merge_config_file(runtime_config_file)?;
+        }
+        runtime_config.set_state_dir(state_dir.to_string_lossy());
+        Ok(runtime_config)
+    }
+}
+
+/// A Spin HTTP application running in process. The application stops, and
+/// its state is deleted, when this is dropped.
+pub struct TestApp {
+    base_url: Url,
+    client: reqwest::Client,
+    stores: HashMap<String, Arc<dyn Store>>,
+    databases: HashMap<String, Arc<dyn Connection>>,
+    server: JoinHandle<Result<()>>,
+    clock: Option<ManualClock>,
+    _working_dir: TempDir,
+    _state_dir: TempDir,
+}
+
+impl TestApp {
+    /// Returns a builder for the application with the given manifest.
+    pub fn builder(manifest: impl Into<PathBuf>) -> TestAppBuilder {
+        TestAppBuilder {
+            manifest: manifest.into(),
+            profile: None,
+            environment: None,
+            runtime_config_file: None,
+            clock: None,
+            random_seed: None,
+        }
+    }
+
+    /// Loads the application with the given manifest and starts serving it.
+    pub async fn start(manifest: impl Into<PathBuf>) -> Result<Self> {
+        Self::builder(manifest).start().await
+    }
+
+    /// The URL at which the application is served.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The clock which components see, if the application was started with
+    /// [`TestAppBuilder::manual_clock`].
+    pub fn clock(&self) -> Option<&ManualClock> {
+        self.clock.as_ref()
+    }
+
+    /// Returns the URL of the given path, with any query string.
+    pub fn url(&self, path: &str) -> Result<Url> {
+        self.base_url
+            .join(path)
+            .with_context(|| format!("Invalid path {path:?}"))
+    }
+
+    /// Returns a request to the given path, for the test to add headers or a
+    /// body to and send.
+    pub fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        Ok(self.client.request(method, self.url(path)?))
+    }
+
+    /// Sends a GET request to the given path.
+    pub async fn get(&self, path: &str) -> Result<Response> {
+        Ok(self.request(Method::GET, path)?.send().await?)
+    }
+
+    /// Sends a POST request with the given body to the given path.
+    pub async fn post(&self, path: &str, body: impl Into<reqwest::Body>) -> Result<Response> {
+        Ok(self.request(Method::POST, path)?.body(body).send().await?)
+    }
+
+    /// Returns the value of a key in the default key value store, or None if
+    /// it is not set.
+    pub async fn kv_get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.kv_get_in(DEFAULT_STORE, key).await
+    }
+
+    /// Returns the value of a key in the given key value store, or None if it
+    /// is not set.
+    pub async fn kv_get_in(&self, store: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let store = self.store(store)?;
+        match store.get(key).await {
+            Ok(value) => Ok(Some(value)),
+            Err(spin_key_value::Error::NoSuchKey) => Ok(None),
+            Err(err) => Err(anyhow!("Failed to get key {key:?}: {err:?}")),
+        }
+    }
+
+    /// Sets a key in the default key value store, e.g. to seed data before a
+    /// request.
+    pub async fn kv_set(&self, key: &str, value: impl AsRef<[u8]>) -> Result<()> {
+        self.kv_set_in(DEFAULT_STORE, key, value).await
+    }
+
+    /// Sets a key in the given key value store.
+    pub async fn kv_set_in(&self, store: &str, key: &str, value: impl AsRef<[u8]>) -> Result<()> {
+        self.store(store)?
+            .set(key, value.as_ref())
+            .await
+            .map_err(|err| anyhow!("Failed to set key {key:?}: {err:?}"))
+    }
+
+    /// Returns the keys in the default key value store.
+    pub async fn kv_keys(&self) -> Result<Vec<String>> {
+        self.store(DEFAULT_STORE)?
+            .get_keys()
+            .await
+            .map_err(|err| anyhow!("Failed to list keys: {err:?}"))
+    }
+
+    /// Runs a query against the default SQLite database.
+    pub async fn sqlite_query(&self, query: &str, parameters: Vec<Value>) -> Result<QueryResult> {
+        self.sqlite_query_in(DEFAULT_STORE, query, parameters).await
+    }
+
+    /// Runs a query against the given SQLite database.
+    pub async fn sqlite_query_in(
+        &self,
+        database: &str,
+        query: &str,
+        parameters: Vec<Value>,
+    ) -> Result<QueryResult> {
+        self.database(database)?
+            .query(query, parameters)
+            .await
+            .map_err(|err| anyhow!("Failed to run query {query:?}: {err:?}"))
+    }
+
+    /// Executes statements, such as a schema and seed data, against the
+    /// default SQLite database.
+    pub fn sqlite_execute(&self, statements: &str) -> Result<()> {
+        self.database(DEFAULT_STORE)?.execute_batch(statements)
+    }
+
+    fn store(&self, name: &str) -> Result<&Arc<dyn Store>> {
+        self.stores
+            .get(name)
+            .with_context(|| format!("No key value store named {name:?}"))
+    }
+
+    fn database(&self, name: &str) -> Result<&Arc<dyn Connection>> {
+        self.databases
+            .get(name)
+            .with_context(|| format!("No SQLite database named {name:?}"))
+    }
+}
+
+struct DeterminismHooks {
+    clock: Option<ManualClock>,
+    random_seed: Option<u64>,
+}
+
+impl TriggerHooks for DeterminismHooks {
+    fn component_store_builder(
+        &self,
+        _component: &AppComponent,
+        store_builder: &mut StoreBuilder,
+    ) -> Result<()> {
+        if let Some(clock) = &self.clock {
+            store_builder.manual_clock(clock);
+        }
+        if let Some(seed) = self.random_seed {
+            store_builder.random_seed(seed);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A prebuilt module which serves the files mounted into it.
+    const FILESERVER_MODULE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/watch/static-fileserver/spin_static_fs.wasm"
+    );
+
+    #[tokio::test]
+    async fn serves_application() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("assets")).unwrap();
+        std::fs::write(dir.path().join("assets/hello.txt"), "hello").unwrap();
+        let manifest = dir.path().join("spin.toml");
+        std::fs::write(
+            &manifest,
+            format!(
+                r#"
+                spin_manifest_version = "1"
+                name = "fileserver"
+                trigger = {{ type = "http", base = "/" }}
+                version = "0.1.0"
+
+                [[component]]
+                id = "fileserver"
+                source = {FILESERVER_MODULE:?}
+                files = [{{ source = "assets", destination = "/" }}]
+                [component.trigger]
+                route = "/static/..."
+                "#
+            ),
+        )
+        .unwrap();
+
+        let app = TestApp::start(&manifest).await.unwrap();
+        let response = app.get("/static/hello.txt").await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "hello");
+        assert_eq!(app.get("/other").await.unwrap().status(), 404);
+    }
+
+    #[tokio::test]
+    async fn fuel_limit_applies_only_to_its_component() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("assets")).unwrap();
+        std::fs::write(dir.path().join("assets/hello.txt"), "hello").unwrap();
+        let manifest = dir.path().join("spin.toml");
+        std::fs::write(
+            &manifest,
+            format!(
+                r#"
+                spin_manifest_version = "1"
+                name = "fileserver"
+                trigger = {{ type = "http", base = "/" }}
+                version = "0.1.0"
+
+                [[component]]
+                id = "limited"
+                source = {FILESERVER_MODULE:?}
+                files = [{{ source = "assets", destination = "/" }}]
+                limits = {{ max_fuel = 1 }}
+                [component.trigger]
+                route = "/limited/..."
+
+                [[component]]
+                id = "unlimited"
+                source = {FILESERVER_MODULE:?}
+                files = [{{ source = "assets", destination = "/" }}]
+                [component.trigger]
+                route = "/unlimited/..."
+                "#
+            ),
+        )
+        .unwrap();
+
+        let app = TestApp::start(&manifest).await.unwrap();
+        assert_eq!(app.get("/limited/hello.txt").await.unwrap().status(), 503);
+        let response = app.get("/unlimited/hello.txt").await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn missing_manifest_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("spin.toml");
+        let err = TestApp::start(&manifest).await.err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("Failed to find application manifest"),
+            "{err:#}"
+        );
+    }
+}
@@ -73,6 +73,7 @@ impl HttpTestConfig {
             component: "test-component".to_string(),
             route: route.into(),
             executor: None,
+            auth: None,
         };
         self
     }
@@ -86,6 +87,7 @@ impl HttpTestConfig {
             component: "test-component".to_string(),
             route: route.into(),
             executor: Some(HttpExecutorType::Wagi(wagi_config)),
+            auth: None,
         };
         self
     }
@@ -0,0 +1,503 @@
+//! Implementation of the Spin Azure Service Bus trigger.
+//!
+//! The trigger receives messages from queues and topic subscriptions in
+//! peek-lock mode. A message is completed as soon as its component handles
+//! it, and otherwise abandoned, so that it is delivered again, or
+//! dead-lettered once it has been delivered the component's `max_deliveries`
+//! times. The locks of messages are renewed while they are being handled.
+
+mod spin;
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use azservicebus::{
+    receiver::DeadLetterOptions, ServiceBusClient, ServiceBusClientOptions, ServiceBusReceiveMode,
+    ServiceBusReceivedMessage, ServiceBusReceiver, ServiceBusReceiverOptions,
+};
+use azure_identity::ImdsManagedIdentityCredential;
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_app::MetadataKey;
+use spin_core::{async_trait, LimitExceeded};
+use spin_trigger::{
+    cli::NoArgs,
+    runtime_config::azure_service_bus::{azure_service_bus, AzureServiceBusAuth},
+    Invocation, TriggerAppEngine, TriggerExecutor,
+};
+use tracing::instrument;
+
+use crate::spin::SpinServiceBusExecutor;
+
+const TRIGGER_METADATA_KEY: MetadataKey<TriggerMetadata> = MetadataKey::new("trigger");
+
+// The runtime config section used by apps which don't name one.
+const DEFAULT_SERVICE_BUS: &str = "default";
+// The most messages received from an entity at once, which are handled
+// concurrently.
+const RECEIVE_BATCH_SIZE: u32 = 10;
+// How long a receive waits for a message before it is retried.
+const RECEIVE_MAX_WAIT: Duration = Duration::from_secs(60);
+// How long to wait before receiving again after a receive fails.
+const RECEIVE_RETRY_DELAY: Duration = Duration::from_secs(5);
+// How often the locks of messages being handled are renewed. This is within
+// the default lock duration of an entity, which is one minute.
+const LOCK_RENEWAL_INTERVAL: Duration = Duration::from_secs(20);
+
+pub(crate) type RuntimeData = ();
+pub(crate) type Store = spin_core::Store<RuntimeData>;
+
+/// The Spin Azure Service Bus trigger.
+pub struct AzureServiceBusTrigger {
+    engine: TriggerAppEngine<Self>,
+    // How to connect to the Service Bus namespace
+    auth: AzureServiceBusAuth,
+    // The entities to receive messages from, with their components
+    subscriptions: Vec<Subscription>,
+}
+
+/// Azure Service Bus trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureServiceBusTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Queue to receive messages from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<String>,
+    /// Topic to receive messages from, through `subscription`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// Subscription of `topic` to receive messages from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<String>,
+    /// The number of deliveries after which a message the component fails
+    /// to handle is dead-lettered, rather than abandoned. Defaults to the
+    /// entity's own maximum delivery count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deliveries: Option<u32>,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TriggerMetadata {
+    r#type: String,
+    // The name of the `[azure_service_bus.<name>]` runtime config section
+    #[serde(default)]
+    service_bus: Option<String>,
+}
+
+/// A queue or topic subscription from which messages are received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entity {
+    Queue(String),
+    Subscription { topic: String, subscription: String },
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queue(queue) => write!(f, "queue {queue}"),
+            Self::Subscription {
+                topic,
+                subscription,
+            } => write!(f, "topic {topic} subscription {subscription}"),
+        }
+    }
+}
+
+struct Subscription {
+    entity: Entity,
+    component: String,
+    max_deliveries: Option<u32>,
+}
+
+impl AzureServiceBusTriggerConfig {
+    /// Returns the entity from which the component receives messages.
+    pub fn entity(&self) -> Result<Entity> {
+        match (&self.queue, &self.topic, &self.subscription) {
+            (Some(queue), None, None) => Ok(Entity::Queue(queue.clone())),
+            (None, Some(topic), Some(subscription)) => Ok(Entity::Subscription {
+                topic: topic.clone(),
+                subscription: subscription.clone(),
+            }),
+            _ => bail!(
+                "Component {:?} must set either queue, or topic and subscription",
+                self.component
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl TriggerExecutor for AzureServiceBusTrigger {
+    const TRIGGER_TYPE: &'static str = "azure-service-bus";
+    type RuntimeData = RuntimeData;
+    type TriggerConfig = AzureServiceBusTriggerConfig;
+    type RunConfig = NoArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let metadata = engine.app().require_metadata(TRIGGER_METADATA_KEY)?;
+        let name = metadata
+            .service_bus
+            .as_deref()
+            .unwrap_or(DEFAULT_SERVICE_BUS);
+        let auth = azure_service_bus(engine.runtime_config(), name)?.with_context(|| {
+            format!(
+                "No Azure Service Bus named {name:?}: add an [azure_service_bus.{name}] runtime config section"
+            )
+        })?;
+
+        let subscriptions = engine
+            .trigger_configs()
+            .map(|(_, config)| {
+                Ok(Subscription {
+                    entity: config.entity()?,
+                    component: config.component.clone(),
+                    max_deliveries: config.max_deliveries,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            engine,
+            auth,
+            subscriptions,
+        })
+    }
+
+    /// Run the Azure Service Bus trigger indefinitely.
+    async fn run(self, _config: Self::RunConfig) -> Result<()> {
+        let options = ServiceBusClientOptions::default();
+        let mut client = match &self.auth {
+            AzureServiceBusAuth::ConnectionString(connection_string) => {
+                tracing::info!("Connecting to Azure Service Bus with a connection string");
+                ServiceBusClient::new_from_connection_string(connection_string, options).await
+            }
+            AzureServiceBusAuth::ManagedIdentity {
+                namespace,
+                client_id,
+            } => {
+                tracing::info!("Connecting to Azure Service Bus namespace {namespace}");
+                let mut credential = ImdsManagedIdentityCredential::default();
+                if let Some(client_id) = client_id {
+                    credential = credential.with_client_id(client_id);
+                }
+                ServiceBusClient::new_from_credential(namespace, credential, options).await
+            }
+        }
+        .context("Azure Service Bus trigger failed to connect")?;
+
+        let mut receivers = vec![];
+        for subscription in &self.subscriptions {
+            tracing::info!(
+                "Receiving messages from {} for component {:?}",
+                subscription.entity,
+                subscription.component
+            );
+            let options = ServiceBusReceiverOptions {
+                receive_mode: ServiceBusReceiveMode::PeekLock,
+                ..Default::default()
+            };
+            let receiver = match &subscription.entity {
+                Entity::Queue(queue) => client.create_receiver_for_queue(queue, options).await,
+                Entity::Subscription {
+                    topic,
+                    subscription,
+                } => {
+                    client
+                        .create_receiver_for_subscription(topic, subscription, options)
+                        .await
+                }
+            }
+            .with_context(|| format!("Failed to receive messages from {}", subscription.entity))?;
+            receivers.push(self.receive(subscription, receiver));
+        }
+
+        // Replace the pooled instances taken by messages, and run the tasks
+        // enqueued by messages, while waiting for more.
+        futures::future::join3(
+            futures::future::join_all(receivers),
+            self.engine.maintain_instance_pools(),
+            self.engine.run_background_tasks(),
+        )
+        .await;
+        Ok(())
+    }
+
+    fn routes(&self) -> Vec<(String, String)> {
+        let mut routes = self
+            .subscriptions
+            .iter()
+            .map(|subscription| {
+                (
+                    subscription.entity.to_string(),
+                    subscription.component.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        routes.sort();
+        routes
+    }
+}
+
+impl AzureServiceBusTrigger {
+    /// Returns the engine with which the trigger runs the application's
+    /// components.
+    pub fn engine(&self) -> &TriggerAppEngine<Self> {
+        &self.engine
+    }
+
+    // Receives messages from the subscription's entity until the trigger is
+    // dropped. The messages of each batch are handled concurrently, and each
+    // is settled as soon as it has been handled. The locks of the messages
+    // still being handled are renewed, so that they aren't delivered again
+    // while a component is handling them.
+    async fn receive(&self, subscription: &Subscription, mut receiver: ServiceBusReceiver) {
+        loop {
+            let messages = match receiver
+                .receive_messages_with_max_wait_time(RECEIVE_BATCH_SIZE, Some(RECEIVE_MAX_WAIT))
+                .await
+            {
+                Ok(messages) => messages,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to receive messages from {}: {e}",
+                        subscription.entity
+                    );
+                    tokio::time::sleep(RECEIVE_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            let mut pending = HashMap::new();
+            let mut handling = FuturesUnordered::new();
+            for (index, message) in messages.into_iter().enumerate() {
+                // The body is copied so that the message can be renewed
+                // while it is being handled.
+                let body = message
+                    .body()
+                    .map(<[u8]>::to_vec)
+                    .map_err(|_| anyhow!("The message body is not binary data"));
+                handling.push(async move { (index, self.handle(subscription, body).await) });
+                pending.insert(index, message);
+            }
+
+            let mut renewal = tokio::time::interval_at(
+                tokio::time::Instant::now() + LOCK_RENEWAL_INTERVAL,
+                LOCK_RENEWAL_INTERVAL,
+            );
+            while let Some(outcome) = next_outcome(&mut handling, &mut renewal, &pending).await {
+                match outcome {
+                    Outcome::Handled(index, result) => {
+                        let message = pending
+                            .remove(&index)
+                            .expect("handled message should be pending");
+                        if let Err(e) = settle(subscription, &mut receiver, &message, result).await
+                        {
+                            tracing::error!(
+                                "Failed to settle message from {}: {e:?}",
+                                subscription.entity
+                            );
+                        }
+                    }
+                    Outcome::RenewLocks => {
+                        for message in pending.values_mut() {
+                            if let Err(e) = receiver.renew_message_lock(message).await {
+                                tracing::warn!(
+                                    "Failed to renew lock of message from {}: {e}",
+                                    subscription.entity
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Handle the message.
+    #[instrument(
+        name = "spin_azure_service_bus_trigger.handle_message",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.destination.name = %subscription.entity,
+            spin.component_id = %subscription.component,
+        )
+    )]
+    async fn handle(&self, subscription: &Subscription, body: Result<Vec<u8>>) -> Result<()> {
+        tracing::info!("Received message from {}", subscription.entity);
+        self.execute(&subscription.component, &subscription.entity, &body?)
+            .await
+    }
+
+    async fn execute(&self, component_id: &str, entity: &Entity, body: &[u8]) -> Result<()> {
+        tracing::trace!("Executing Azure Service Bus component {component_id:?}");
+        let _inflight = spin_telemetry::metrics::track_inflight(Self::TRIGGER_TYPE);
+        let executor = SpinServiceBusExecutor;
+        let invocation = Invocation::new(Self::TRIGGER_TYPE, component_id)
+            .with_metadata("messaging.destination.name", entity.to_string());
+        let start = std::time::Instant::now();
+        let result = self
+            .engine
+            .invoke(
+                &invocation,
+                executor.execute(&self.engine, component_id, body),
+            )
+            .await;
+        spin_telemetry::metrics::record_trigger_message(
+            Self::TRIGGER_TYPE,
+            component_id,
+            result.is_ok(),
+            start.elapsed(),
+        );
+        if let Some(limit) = result.as_ref().err().and_then(LimitExceeded::from_error) {
+            tracing::error!("Component {component_id:?} exceeded its {limit}");
+        }
+        result
+    }
+}
+
+// What happened next while a batch of messages was being handled.
+enum Outcome {
+    // The message with the given index in the batch was handled.
+    Handled(usize, Result<()>),
+    // The locks of the messages still being handled are due to be renewed.
+    RenewLocks,
+}
+
+// Waits for a message to be handled, or for locks to be due for renewal.
+// Returns `None` once every message has been handled.
+async fn next_outcome<F>(
+    handling: &mut FuturesUnordered<F>,
+    renewal: &mut tokio::time::Interval,
+    pending: &HashMap<usize, ServiceBusReceivedMessage>,
+) -> Option<Outcome>
+where
+    F: std::future::Future<Output = (usize, Result<()>)>,
+{
+    if pending.is_empty() {
+        return None;
+    }
+    tokio::select! {
+        Some((index, result)) = handling.next() => Some(Outcome::Handled(index, result)),
+        _ = renewal.tick() => Some(Outcome::RenewLocks),
+    }
+}
+
+/// What is done with a received message once it has been handled.
+#[derive(Debug, PartialEq, Eq)]
+enum Settlement {
+    /// Remove the message from the entity.
+    Complete,
+    /// Release the message's lock, so that it is delivered again.
+    Abandon,
+    /// Move the message to the entity's dead-letter queue.
+    DeadLetter,
+}
+
+impl Settlement {
+    fn of(succeeded: bool, delivery_count: Option<u32>, max_deliveries: Option<u32>) -> Self {
+        if succeeded {
+            return Self::Complete;
+        }
+        match (delivery_count, max_deliveries) {
+            (Some(delivery_count), Some(max_deliveries)) if delivery_count >= max_deliveries => {
+                Self::DeadLetter
+            }
+            _ => Self::Abandon,
+        }
+    }
+}
+
+async fn settle(
+    subscription: &Subscription,
+    receiver: &mut ServiceBusReceiver,
+    message: &ServiceBusReceivedMessage,
+    result: Result<()>,
+) -> Result<()> {
+    let settlement = Settlement::of(
+        result.is_ok(),
+        message.delivery_count(),
+        subscription.max_deliveries,
+    );
+    let trigger_type = AzureServiceBusTrigger::TRIGGER_TYPE;
+    match settlement {
+        Settlement::Complete => receiver.complete_message(message).await?,
+        Settlement::Abandon => {
+            receiver.abandon_message(message, None).await?;
+            spin_telemetry::metrics::record_trigger_retry(trigger_type, &subscription.component);
+        }
+        Settlement::DeadLetter => {
+            tracing::warn!(
+                "Dead-lettering message from {} after {} failed deliveries",
+                subscription.entity,
+                message.delivery_count().unwrap_or_default()
+            );
+            let options = DeadLetterOptions {
+                dead_letter_reason: Some("HandlerFailed".to_owned()),
+                dead_letter_error_description: result.err().map(|e| e.to_string()),
+                properties_to_modify: None,
+            };
+            receiver.dead_letter_message(message, options).await?;
+            spin_telemetry::metrics::record_trigger_dead_letter(
+                trigger_type,
+                &subscription.component,
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: serde_json::Value) -> AzureServiceBusTriggerConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn entity_is_a_queue_or_topic_subscription() {
+        let queue = config(serde_json::json!({"component": "c", "queue": "orders"}));
+        assert_eq!(queue.entity().unwrap(), Entity::Queue("orders".into()));
+        assert_eq!(queue.entity().unwrap().to_string(), "queue orders");
+
+        let subscription = config(
+            serde_json::json!({"component": "c", "topic": "events", "subscription": "audit"}),
+        );
+        assert_eq!(
+            subscription.entity().unwrap(),
+            Entity::Subscription {
+                topic: "events".into(),
+                subscription: "audit".into()
+            }
+        );
+
+        let both = config(serde_json::json!({
+            "component": "c",
+            "queue": "orders",
+            "topic": "events",
+            "subscription": "audit",
+        }));
+        assert!(both.entity().is_err());
+        let topic_only = config(serde_json::json!({"component": "c", "topic": "events"}));
+        assert!(topic_only.entity().is_err());
+    }
+
+    #[test]
+    fn failed_messages_are_dead_lettered_after_max_deliveries() {
+        assert_eq!(Settlement::of(true, Some(9), Some(3)), Settlement::Complete);
+        assert_eq!(Settlement::of(false, Some(1), Some(3)), Settlement::Abandon);
+        assert_eq!(
+            Settlement::of(false, Some(3), Some(3)),
+            Settlement::DeadLetter
+        );
+        assert_eq!(Settlement::of(false, Some(9), None), Settlement::Abandon);
+        assert_eq!(Settlement::of(false, None, Some(3)), Settlement::Abandon);
+    }
+}
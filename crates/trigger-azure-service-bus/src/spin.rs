@@ -0,0 +1,57 @@
+use anyhow::{anyhow, Result};
+use spin_core::Instance;
+use spin_trigger::{EitherInstance, TriggerAppEngine};
+use spin_world::redis_types::{Error, PayloadParam};
+
+use crate::{AzureServiceBusTrigger, Store};
+
+/// Runs components which handle Service Bus messages through the
+/// `inbound-redis` interface, whose `handle-message` export receives a
+/// message's body.
+#[derive(Clone)]
+pub struct SpinServiceBusExecutor;
+
+impl SpinServiceBusExecutor {
+    pub async fn execute(
+        &self,
+        engine: &TriggerAppEngine<AzureServiceBusTrigger>,
+        component_id: &str,
+        body: &[u8],
+    ) -> Result<()> {
+        tracing::trace!("Executing message using the Spin executor for component {component_id}");
+
+        let mut reusable = engine.prepare_reusable_instance(component_id).await?;
+        let EitherInstance::Component(instance) = &reusable.instance else {
+            unreachable!()
+        };
+        let instance = *instance;
+
+        match Self::execute_impl(&mut reusable.store, instance, body).await {
+            Ok(()) => {
+                tracing::trace!("Message handled OK");
+                engine.release_instance(reusable);
+                Ok(())
+            }
+            Err(e) => {
+                tracing::trace!("Message handled with error {e}");
+                Err(e)
+            }
+        }
+    }
+
+    async fn execute_impl(store: &mut Store, instance: Instance, body: &[u8]) -> Result<()> {
+        let func = instance
+            .exports(&mut *store)
+            .instance("inbound-redis")
+            .ok_or_else(|| anyhow!("no inbound-redis instance found"))?
+            .typed_func::<(PayloadParam,), (Result<(), Error>,)>("handle-message")?;
+
+        let (result,) = func.call_async(&mut *store, (body,)).await?;
+        // Lets the instance handle another message, if it is reused.
+        func.post_return_async(store).await?;
+        match result {
+            Ok(()) | Err(Error::Success) => Ok(()),
+            _ => Err(anyhow!("`handle-message` returned an error")),
+        }
+    }
+}
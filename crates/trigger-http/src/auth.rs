@@ -0,0 +1,531 @@
+//! JWT bearer token authentication of requests to HTTP components.
+//!
+//! A component whose trigger config has an `auth` section is only invoked
+//! for requests with a valid token from the configured issuer. The token's
+//! claims are passed to the component as JSON in the [`AUTH_CLAIMS_HEADER`]
+//! header, which is removed from every incoming request so that clients
+//! cannot set it themselves.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use http::{header::AUTHORIZATION, HeaderMap, HeaderValue, StatusCode};
+use hyper::{Body, Response};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use spin_http::config::HttpAuthConfig;
+use tracing::log;
+
+/// The header in which the claims of a request's validated token are passed
+/// to the component.
+pub const AUTH_CLAIMS_HEADER: &str = "spin-auth-claims";
+
+// How long a fetched key set is used before it is fetched again.
+const KEY_SET_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+// A token signed with a key missing from the cached key set makes the key
+// set be fetched again, at most this often, in case the issuer has rotated
+// its keys.
+const KEY_SET_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+// How long fetching an issuer's key set, or discovering where it is, may
+// take before the request being authenticated fails.
+const KEY_SET_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Validates the bearer tokens of requests, caching the issuers' key sets.
+pub(crate) struct Authenticator {
+    client: reqwest::Client,
+    // Keyed by issuer and JWKS URL.
+    key_sets: Mutex<HashMap<(String, Option<String>), CachedKeySet>>,
+}
+
+struct CachedKeySet {
+    keys: Arc<JwkSet>,
+    fetched: Instant,
+}
+
+/// The reason a request was not authenticated.
+#[derive(Debug)]
+pub(crate) enum AuthError {
+    /// The request has no bearer token.
+    MissingToken,
+    /// The token is malformed, expired, or not signed by the issuer.
+    InvalidToken(String),
+    /// The token does not grant all of the required scopes.
+    InsufficientScope(String),
+    /// The issuer's key set could not be fetched.
+    KeySet(anyhow::Error),
+}
+
+impl AuthError {
+    /// Creates the response to a request which was not authenticated.
+    pub fn into_response(self) -> Result<Response<Body>> {
+        let (status, challenge) = match self {
+            Self::MissingToken => (StatusCode::UNAUTHORIZED, "Bearer".to_owned()),
+            Self::InvalidToken(reason) => {
+                log::info!("Rejected request with invalid token: {reason}");
+                (
+                    StatusCode::UNAUTHORIZED,
+                    r#"Bearer error="invalid_token""#.to_owned(),
+                )
+            }
+            Self::InsufficientScope(scope) => (
+                StatusCode::FORBIDDEN,
+                format!(r#"Bearer error="insufficient_scope", scope="{scope}""#),
+            ),
+            Self::KeySet(e) => {
+                log::error!("Unable to authenticate request: {e:?}");
+                return Ok(Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(Body::empty())?);
+            }
+        };
+        Ok(Response::builder()
+            .status(status)
+            .header(http::header::WWW_AUTHENTICATE, challenge)
+            .body(Body::empty())?)
+    }
+}
+
+impl Default for Authenticator {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(KEY_SET_FETCH_TIMEOUT)
+            .build()
+            .expect("HTTP client should be buildable");
+        Self {
+            client,
+            key_sets: Default::default(),
+        }
+    }
+}
+
+impl Authenticator {
+    /// Validates the bearer token in the headers against the config, and
+    /// returns its claims.
+    pub async fn authenticate(
+        &self,
+        config: &HttpAuthConfig,
+        headers: &HeaderMap,
+    ) -> Result<Map<String, Value>, AuthError> {
+        let token = bearer_token(headers).ok_or(AuthError::MissingToken)?;
+        let header = decode_header(token).map_err(invalid_token)?;
+        // Only the issuer's public keys may sign tokens, so a token which
+        // claims to be signed with a shared secret is never valid.
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(AuthError::InvalidToken(format!(
+                "unsupported algorithm {:?}",
+                header.alg
+            )));
+        }
+        let (key, alg) = self.decoding_key(config, header.kid.as_deref()).await?;
+        // The algorithm is the signing key's, so that a token cannot choose
+        // how its signature is checked.
+        if header.alg != alg {
+            return Err(AuthError::InvalidToken(format!(
+                "algorithm {:?} does not match the signing key's {alg:?}",
+                header.alg
+            )));
+        }
+
+        let mut validation = Validation::new(alg);
+        validation.set_issuer(&[&config.issuer]);
+        if config.audiences.is_empty() {
+            validation.set_required_spec_claims(&["exp", "iss"]);
+        } else {
+            validation.set_audience(&config.audiences);
+            validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        }
+        let claims = decode::<Map<String, Value>>(token, &key, &validation)
+            .map_err(invalid_token)?
+            .claims;
+
+        let granted = granted_scopes(&claims);
+        if !config
+            .scopes
+            .iter()
+            .all(|scope| granted.contains(&scope.as_str()))
+        {
+            return Err(AuthError::InsufficientScope(config.scopes.join(" ")));
+        }
+        Ok(claims)
+    }
+
+    async fn decoding_key(
+        &self,
+        config: &HttpAuthConfig,
+        kid: Option<&str>,
+    ) -> Result<(DecodingKey, Algorithm), AuthError> {
+        let key_set = self.key_set(config, false).await?;
+        let jwk = match find_key(&key_set, kid) {
+            Some(jwk) => jwk.clone(),
+            None => {
+                let key_set = self.key_set(config, true).await?;
+                find_key(&key_set, kid)
+                    .cloned()
+                    .ok_or_else(|| AuthError::InvalidToken("unknown signing key".into()))?
+            }
+        };
+        let alg = key_algorithm(&jwk)
+            .ok_or_else(|| AuthError::InvalidToken("unsupported signing key".into()))?;
+        Ok((DecodingKey::from_jwk(&jwk).map_err(invalid_token)?, alg))
+    }
+
+    // Returns the issuer's key set, from the cache unless it is stale. With
+    // `refresh`, a key set which was not fetched very recently is stale.
+    async fn key_set(
+        &self,
+        config: &HttpAuthConfig,
+        refresh: bool,
+    ) -> Result<Arc<JwkSet>, AuthError> {
+        let cache_key = (config.issuer.clone(), config.jwks_url.clone());
+        if let Some(cached) = self.key_sets.lock().unwrap().get(&cache_key) {
+            let age = cached.fetched.elapsed();
+            let max_age = if refresh {
+                KEY_SET_MIN_REFRESH_INTERVAL
+            } else {
+                KEY_SET_MAX_AGE
+            };
+            if age < max_age {
+                return Ok(cached.keys.clone());
+            }
+        }
+
+        let keys = Arc::new(
+            self.fetch_key_set(config)
+                .await
+                .map_err(AuthError::KeySet)?,
+        );
+        self.key_sets.lock().unwrap().insert(
+            cache_key,
+            CachedKeySet {
+                keys: keys.clone(),
+                fetched: Instant::now(),
+            },
+        );
+        Ok(keys)
+    }
+
+    async fn fetch_key_set(&self, config: &HttpAuthConfig) -> Result<JwkSet> {
+        let jwks_url = match &config.jwks_url {
+            Some(jwks_url) => jwks_url.clone(),
+            None => {
+                #[derive(serde::Deserialize)]
+                struct OpenIdConfiguration {
+                    jwks_uri: String,
+                }
+                let discovery_url = format!(
+                    "{}/.well-known/openid-configuration",
+                    config.issuer.trim_end_matches('/')
+                );
+                let discovered: OpenIdConfiguration =
+                    self.get_json(&discovery_url).await.with_context(|| {
+                        format!(
+                            "Failed to discover the JWKS URL of issuer {}",
+                            config.issuer
+                        )
+                    })?;
+                discovered.jwks_uri
+            }
+        };
+        self.get_json(&jwks_url)
+            .await
+            .with_context(|| format!("Failed to fetch JSON Web Key Set from {jwks_url}"))
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        Ok(self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+/// Sets the claims of a request's validated token in its headers.
+pub(crate) fn set_claims_header(
+    headers: &mut HeaderMap,
+    claims: &Map<String, Value>,
+) -> Result<()> {
+    let claims = serde_json::to_vec(claims)?;
+    headers.insert(AUTH_CLAIMS_HEADER, HeaderValue::from_bytes(&claims)?);
+    Ok(())
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+// Tokens without a key ID are accepted if the key set has only one key.
+fn find_key<'a>(key_set: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => key_set.find(kid),
+        None if key_set.keys.len() == 1 => key_set.keys.first(),
+        None => None,
+    }
+}
+
+// The algorithm a key signs with is its `alg` parameter or, for keys without
+// one, the algorithm its type is used with. Shared secrets are never valid
+// signing keys.
+fn key_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    let alg = match (jwk.common.algorithm, &jwk.algorithm) {
+        (Some(alg), _) => alg,
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::EllipticCurve(params)) => match params.curve {
+            EllipticCurve::P256 => Algorithm::ES256,
+            EllipticCurve::P384 => Algorithm::ES384,
+            _ => return None,
+        },
+        (None, _) => return None,
+    };
+    match (alg, &jwk.algorithm) {
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, _)
+        | (_, AlgorithmParameters::OctetKey(_)) => None,
+        (alg, _) => Some(alg),
+    }
+}
+
+// Scopes are granted by a space-separated `scope` claim (RFC 8693), or by a
+// `scp` claim, which some issuers set to a list.
+fn granted_scopes(claims: &Map<String, Value>) -> Vec<&str> {
+    match claims.get("scope").or_else(|| claims.get("scp")) {
+        Some(Value::String(scopes)) => scopes.split_whitespace().collect(),
+        Some(Value::Array(scopes)) => scopes.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    }
+}
+
+fn invalid_token(e: jsonwebtoken::errors::Error) -> AuthError {
+    AuthError::InvalidToken(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    const ISSUER: &str = "https://issuer.example.com/";
+    const KEY_ID: &str = "test-key";
+    // The signing key is the test TLS key; this is its modulus, as in a JWK.
+    const SIGNING_KEY: &str = include_str!("../tests/local.key.pem");
+    const SIGNING_KEY_MODULUS: &str = "wMbUZ2eoIaJfgcBJ2fILUViWYApnA9SU-Rufnm6DNm9Gy5-YThqxd_0mhbPwYVkfi2_3UddWDl3VPOAYcvYoHDqH0tHm10wo-UzYDDcNZB9enLRfGCv9Fful4bqNd3Vtx2xNwc8-F0WiljtYeMc-9wp7M5WWbKJqzKPeVQBADRlfGoG3jCLGaQ2fyVp_73nWdqbbluWJopxHph7v1alb_BxLcDi_tjWKgZutVr9ZtBBPDSjRbfjHarn6pibYZAWgzanpfsaSBdbpVNn1MQ_gNXIHmNFwfbsN0V-3LN_Z4VNZrkc-C7CjGhJOcBj0xtrSDhoHnOmDS_z-lBUdlNOUrQ";
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, authorization.parse().unwrap());
+        headers
+    }
+
+    fn config() -> HttpAuthConfig {
+        HttpAuthConfig {
+            issuer: ISSUER.into(),
+            jwks_url: Some("http://127.0.0.1:1/jwks.json".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bearer_tokens_are_read_from_authorization() {
+        assert_eq!(bearer_token(&headers("Bearer abc.def")), Some("abc.def"));
+        assert_eq!(bearer_token(&headers("bearer  abc.def ")), Some("abc.def"));
+        assert_eq!(bearer_token(&headers("Basic dXNlcg==")), None);
+        assert_eq!(bearer_token(&headers("Bearer ")), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn scopes_are_read_from_scope_or_scp() {
+        let claims: Map<String, Value> =
+            serde_json::from_str(r#"{"scope": "read write"}"#).unwrap();
+        assert_eq!(granted_scopes(&claims), ["read", "write"]);
+        let claims: Map<String, Value> = serde_json::from_str(r#"{"scp": ["read"]}"#).unwrap();
+        assert_eq!(granted_scopes(&claims), ["read"]);
+        assert!(granted_scopes(&Map::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn requests_without_valid_tokens_are_rejected() {
+        let authenticator = Authenticator::default();
+
+        let err = authenticator
+            .authenticate(&config(), &HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::MissingToken), "{err:?}");
+
+        let err = authenticator
+            .authenticate(&config(), &headers("Bearer not-a-jwt"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidToken(_)), "{err:?}");
+
+        // A token signed with a shared secret is rejected before the key set
+        // is fetched.
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &serde_json::json!({"iss": "https://issuer.example.com/", "exp": u32::MAX}),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        let err = authenticator
+            .authenticate(&config(), &headers(&format!("Bearer {token}")))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidToken(_)), "{err:?}");
+
+        let response = err.into_response().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response
+            .headers()
+            .contains_key(http::header::WWW_AUTHENTICATE));
+    }
+
+    // Serves the key set of the signing key, returning its URL.
+    async fn serve_key_set() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+        let key_set = serde_json::json!({
+            "keys": [{"kty": "RSA", "kid": KEY_ID, "n": SIGNING_KEY_MODULUS, "e": "AQAB"}]
+        })
+        .to_string();
+        tokio::spawn(async move {
+            loop {
+                let (mut connection, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match connection.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{key_set}",
+                    key_set.len()
+                );
+                let _ = connection.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    fn signed_token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KEY_ID.into());
+        let key = EncodingKey::from_rsa_pem(SIGNING_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    async fn authenticate_token(
+        claims: serde_json::Value,
+    ) -> Result<Map<String, Value>, AuthError> {
+        let config = HttpAuthConfig {
+            issuer: ISSUER.into(),
+            jwks_url: Some(serve_key_set().await),
+            audiences: vec!["my-app".into()],
+            ..Default::default()
+        };
+        let token = signed_token(claims);
+        Authenticator::default()
+            .authenticate(&config, &headers(&format!("Bearer {token}")))
+            .await
+    }
+
+    #[tokio::test]
+    async fn valid_tokens_pass_their_claims_to_the_component() {
+        let claims = authenticate_token(serde_json::json!({
+            "iss": ISSUER,
+            "aud": "my-app",
+            "sub": "alice",
+            "exp": now() + 3600,
+        }))
+        .await
+        .unwrap();
+        assert_eq!(claims["sub"], "alice");
+
+        let mut headers = HeaderMap::new();
+        set_claims_header(&mut headers, &claims).unwrap();
+        let passed: Map<String, Value> =
+            serde_json::from_slice(headers[AUTH_CLAIMS_HEADER].as_bytes()).unwrap();
+        assert_eq!(passed, claims);
+    }
+
+    #[tokio::test]
+    async fn tokens_from_other_issuers_are_rejected() {
+        let err = authenticate_token(serde_json::json!({
+            "iss": "https://attacker.example.com/",
+            "aud": "my-app",
+            "exp": now() + 3600,
+        }))
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidToken(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn tokens_for_other_audiences_are_rejected() {
+        let err = authenticate_token(serde_json::json!({
+            "iss": ISSUER,
+            "aud": "other-app",
+            "exp": now() + 3600,
+        }))
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidToken(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_rejected() {
+        let err = authenticate_token(serde_json::json!({
+            "iss": ISSUER,
+            "aud": "my-app",
+            "exp": now() - 3600,
+        }))
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidToken(_)), "{err:?}");
+    }
+
+    #[test]
+    fn key_algorithms_come_from_the_key() {
+        let jwk = |json| serde_json::from_value::<Jwk>(json).unwrap();
+        let rsa = jwk(serde_json::json!({"kty": "RSA", "n": SIGNING_KEY_MODULUS, "e": "AQAB"}));
+        assert_eq!(key_algorithm(&rsa), Some(Algorithm::RS256));
+        let rsa_pss = jwk(
+            serde_json::json!({"kty": "RSA", "alg": "PS256", "n": SIGNING_KEY_MODULUS, "e": "AQAB"}),
+        );
+        assert_eq!(key_algorithm(&rsa_pss), Some(Algorithm::PS256));
+        let secret = jwk(serde_json::json!({"kty": "oct", "k": "c2VjcmV0"}));
+        assert_eq!(key_algorithm(&secret), None);
+    }
+}
@@ -1,5 +1,6 @@
 //! Implementation for the Spin HTTP engine.
 
+mod auth;
 mod spin;
 mod tls;
 mod wagi;
@@ -9,38 +10,47 @@ use std::{
     future::ready,
     net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
 use anyhow::{Context, Error, Result};
 use async_trait::async_trait;
 use clap::Args;
 use futures_util::stream::StreamExt;
-use http::{uri::Scheme, StatusCode, Uri};
+use http::{uri::Scheme, HeaderValue, StatusCode, Uri};
 use hyper::{
     server::accept,
-    server::conn::AddrStream,
+    server::conn::{AddrIncoming, AddrStream},
     service::{make_service_fn, service_fn},
     Body, Request, Response, Server,
 };
 use serde::{Deserialize, Serialize};
 use spin_app::{AppComponent, MetadataKey};
-use spin_core::Engine;
+use spin_core::{Engine, LimitExceeded};
 use spin_http::{
     config::{HttpExecutorType, HttpTriggerConfig},
     routes::{RoutePattern, Router},
 };
 use spin_trigger::{
+    app_update::{self, AppUpdater, AppUpdates},
     locked::{BINDLE_VERSION_KEY, DESCRIPTION_KEY, VERSION_KEY},
-    EitherInstancePre, TriggerAppEngine, TriggerExecutor,
+    record::{
+        RecordedBody, RecordedRequest, RecordedResponse, Recorder, TriggerEvent,
+        CREDENTIAL_HEADERS, REDACTED,
+    },
+    EitherInstancePre, Invocation, InvocationRejected, TriggerAppEngine, TriggerExecutor,
 };
 use tls_listener::TlsListener;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
 use tokio_rustls::server::TlsStream;
-use tracing::log;
+use tracing::{instrument, log};
 
-use crate::{spin::SpinHttpExecutor, wagi::WagiHttpExecutor};
+use crate::{auth::Authenticator, spin::SpinHttpExecutor, wagi::WagiHttpExecutor};
 
+pub use auth::AUTH_CLAIMS_HEADER;
 pub use tls::TlsConfig;
 
 pub(crate) type RuntimeData = ();
@@ -48,6 +58,15 @@ pub(crate) type Store = spin_core::Store<RuntimeData>;
 
 const TRIGGER_METADATA_KEY: MetadataKey<TriggerMetadata> = MetadataKey::new("trigger");
 
+/// Set in the environment of each worker process of `spin up --workers`.
+/// The workers all listen on the same address, and the kernel spreads
+/// connections among them.
+pub const SPIN_HTTP_REUSE_PORT: &str = "SPIN_HTTP_REUSE_PORT";
+
+/// The environment variable which sets the address on which the HTTP
+/// trigger listens, if `--listen` is not given.
+pub const LISTEN_ADDR_ENV: &str = "SPIN_HTTP_LISTEN_ADDR";
+
 /// The Spin HTTP trigger.
 pub struct HttpTrigger {
     engine: TriggerAppEngine<Self>,
@@ -56,12 +75,21 @@ pub struct HttpTrigger {
     base: String,
     // Component ID -> component trigger config
     component_trigger_configs: HashMap<String, HttpTriggerConfig>,
+    // Validates the tokens of requests to components which require auth.
+    authenticator: Authenticator,
+    // The triggers built for app updates, once an updater has been created.
+    app_updates: Option<AppUpdates<Self>>,
 }
 
 #[derive(Args)]
 pub struct CliArgs {
     /// IP address and port to listen on
-    #[clap(long = "listen", default_value = "127.0.0.1:3000", value_parser = parse_listen_addr)]
+    #[clap(
+        long = "listen",
+        env = LISTEN_ADDR_ENV,
+        default_value = "127.0.0.1:3000",
+        value_parser = parse_listen_addr
+    )]
     pub address: SocketAddr,
 
     /// The path to the certificate to use for https, if this is not set, normal http will be used. The cert should be in PEM format
@@ -137,6 +165,8 @@ impl TriggerExecutor for HttpTrigger {
             router,
             base,
             component_trigger_configs,
+            authenticator: Authenticator::default(),
+            app_updates: None,
         })
     }
 
@@ -168,6 +198,19 @@ impl TriggerExecutor for HttpTrigger {
         Ok(())
     }
 
+    fn app_updater(&mut self) -> Option<AppUpdater<Self>> {
+        let (updater, updates) = app_update::channel();
+        self.app_updates = Some(updates);
+        Some(updater)
+    }
+
+    fn routes(&self) -> Vec<(String, String)> {
+        self.router
+            .routes()
+            .map(|(route, component_id)| (route.to_string(), component_id.clone()))
+            .collect()
+    }
+
     async fn instantiate_pre(
         engine: &Engine<Self::RuntimeData>,
         component: &AppComponent,
@@ -192,22 +235,119 @@ impl TriggerExecutor for HttpTrigger {
 }
 
 impl HttpTrigger {
+    /// Returns the engine with which the trigger runs the application's
+    /// components.
+    pub fn engine(&self) -> &TriggerAppEngine<Self> {
+        &self.engine
+    }
+
     /// Handles incoming requests using an HTTP executor.
+    #[instrument(
+        name = "spin_trigger_http.handle_http_request",
+        skip_all,
+        fields(
+            otel.kind = "server",
+            http.request.method = %req.method(),
+            url.path = req.uri().path(),
+            spin.component_id = tracing::field::Empty,
+            spin.request_id = tracing::field::Empty,
+        )
+    )]
     pub async fn handle(
+        &self,
+        req: Request<Body>,
+        scheme: Scheme,
+        addr: SocketAddr,
+    ) -> Result<Response<Body>> {
+        match self.engine.recorder() {
+            Some(recorder) => self.handle_recorded(recorder, req, scheme, addr).await,
+            None => self.handle_request(req, scheme, addr).await,
+        }
+    }
+
+    // Handles the request, recording it and the response. The bodies are
+    // buffered in full, so that they can be recorded.
+    async fn handle_recorded(
+        &self,
+        recorder: &Recorder,
+        req: Request<Body>,
+        scheme: Scheme,
+        addr: SocketAddr,
+    ) -> Result<Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        let request = RecordedRequest {
+            method: parts.method.to_string(),
+            path: parts
+                .uri
+                .path_and_query()
+                .map(|path| path.to_string())
+                .unwrap_or_else(|| "/".into()),
+            headers: recorded_headers(&parts.headers),
+            body: RecordedBody::from(&body[..]),
+        };
+        let component = self.router.route(parts.uri.path()).ok().map(str::to_owned);
+
+        let result = self
+            .handle_request(Request::from_parts(parts, body.into()), scheme, addr)
+            .await;
+        let (res, response) = match result {
+            Ok(res) => {
+                let (parts, body) = res.into_parts();
+                let body = hyper::body::to_bytes(body).await?;
+                let response = RecordedResponse {
+                    status: parts.status.as_u16(),
+                    headers: recorded_headers(&parts.headers),
+                    body: RecordedBody::from(&body[..]),
+                };
+                (Ok(Response::from_parts(parts, body.into())), Some(response))
+            }
+            Err(err) => (Err(err), None),
+        };
+        recorder.record(TriggerEvent::Http {
+            component,
+            request,
+            response,
+        });
+        res
+    }
+
+    async fn handle_request(
         &self,
         mut req: Request<Body>,
         scheme: Scheme,
         addr: SocketAddr,
     ) -> Result<Response<Body>> {
+        spin_telemetry::extract_trace_context(req.headers());
         set_req_uri(&mut req, scheme)?;
 
+        // Make the request ID visible to the component, and to the host
+        // calls it makes, through the current span.
+        let request_id = spin_telemetry::incoming_request_id(req.headers());
+        tracing::Span::current().record("spin.request_id", request_id.as_str());
+        let request_id = HeaderValue::from_str(&request_id)?;
+        req.headers_mut()
+            .insert(spin_telemetry::REQUEST_ID_HEADER, request_id.clone());
+
+        let mut res = self.route(req, addr).await?;
+        res.headers_mut()
+            .entry(spin_telemetry::REQUEST_ID_HEADER)
+            .or_insert(request_id);
+        Ok(res)
+    }
+
+    async fn route(&self, mut req: Request<Body>, addr: SocketAddr) -> Result<Response<Body>> {
         log::info!(
             "Processing request for application {} on URI {}",
             &self.engine.app_name,
             req.uri()
         );
 
-        let path = req.uri().path();
+        // Only the trigger may tell components the claims of a token.
+        req.headers_mut().remove(AUTH_CLAIMS_HEADER);
+
+        let path = req.uri().path().to_owned();
+        let path = path.as_str();
 
         // Handle well-known spin paths
         if let Some(well_known) = path.strip_prefix(spin_http::WELL_KNOWN_PREFIX) {
@@ -221,47 +361,70 @@ impl HttpTrigger {
         // Route to app component
         match self.router.route(path) {
             Ok(component_id) => {
+                tracing::Span::current().record("spin.component_id", component_id);
                 let trigger = self.component_trigger_configs.get(component_id).unwrap();
 
+                if let Some(auth) = &trigger.auth {
+                    match self.authenticator.authenticate(auth, req.headers()).await {
+                        Ok(claims) => auth::set_claims_header(req.headers_mut(), &claims)?,
+                        Err(e) => return e.into_response(),
+                    }
+                }
+
                 let executor = trigger.executor.as_ref().unwrap_or(&HttpExecutorType::Spin);
 
-                let res = match executor {
-                    HttpExecutorType::Spin => {
-                        let executor = SpinHttpExecutor;
-                        executor
-                            .execute(
-                                &self.engine,
-                                component_id,
-                                &self.base,
-                                &trigger.route,
-                                req,
-                                addr,
-                            )
-                            .await
-                    }
-                    HttpExecutorType::Wagi(wagi_config) => {
-                        let executor = WagiHttpExecutor {
-                            wagi_config: wagi_config.clone(),
-                        };
-                        executor
-                            .execute(
-                                &self.engine,
-                                component_id,
-                                &self.base,
-                                &trigger.route,
-                                req,
-                                addr,
-                            )
-                            .await
-                    }
+                let invocation = Invocation::new(Self::TRIGGER_TYPE, component_id)
+                    .with_metadata("http.method", req.method().as_str())
+                    .with_metadata("http.route", trigger.route.as_str())
+                    .with_metadata("url.path", path);
+                let _inflight = spin_telemetry::metrics::track_inflight("http");
+                let start = std::time::Instant::now();
+                let res = self
+                    .engine
+                    .invoke(&invocation, async {
+                        match executor {
+                            HttpExecutorType::Spin => {
+                                let executor = SpinHttpExecutor;
+                                executor
+                                    .execute(
+                                        &self.engine,
+                                        component_id,
+                                        &self.base,
+                                        &trigger.route,
+                                        req,
+                                        addr,
+                                    )
+                                    .await
+                            }
+                            HttpExecutorType::Wagi(wagi_config) => {
+                                let executor = WagiHttpExecutor {
+                                    wagi_config: wagi_config.clone(),
+                                };
+                                executor
+                                    .execute(
+                                        &self.engine,
+                                        component_id,
+                                        &self.base,
+                                        &trigger.route,
+                                        req,
+                                        addr,
+                                    )
+                                    .await
+                            }
+                        }
+                    })
+                    .await;
+                let res = match res {
+                    Ok(res) => res,
+                    Err(e) => Self::error_response(component_id, e)?,
                 };
-                match res {
-                    Ok(res) => Ok(res),
-                    Err(e) => {
-                        log::error!("Error processing request: {:?}", e);
-                        Self::internal_error(None)
-                    }
-                }
+                spin_telemetry::metrics::record_http_request(
+                    component_id,
+                    &trigger.route,
+                    res.status().as_u16(),
+                    start.elapsed(),
+                );
+                Ok(res)
             }
             Err(_) => Self::not_found(),
         }
@@ -280,6 +443,32 @@ impl HttpTrigger {
             .body(body.into())?)
     }
 
+    /// Logs an error from executing a component, and creates the response
+    /// for it. A component which exceeded a resource limit gets a 503 or 504
+    /// response rather than a 500.
+    fn error_response(component_id: &str, e: anyhow::Error) -> Result<Response<Body>> {
+        if e.downcast_ref::<InvocationRejected>().is_some() {
+            log::info!("Request for component {component_id:?} rejected: {e:#}");
+            return Ok(Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .body(Body::empty())?);
+        }
+        let status = match LimitExceeded::from_error(&e) {
+            Some(limit) => {
+                log::error!("Component {component_id:?} exceeded its {limit}");
+                match limit {
+                    LimitExceeded::Deadline => StatusCode::GATEWAY_TIMEOUT,
+                    LimitExceeded::Fuel => StatusCode::SERVICE_UNAVAILABLE,
+                }
+            }
+            None => {
+                log::error!("Error processing request: {:?}", e);
+                return Self::internal_error(None);
+            }
+        };
+        Ok(Response::builder().status(status).body(Body::empty())?)
+    }
+
     /// Creates an HTTP 500 response.
     fn internal_error(body: Option<&str>) -> Result<Response<Body>> {
         let body = match body {
@@ -299,37 +488,83 @@ impl HttpTrigger {
             .body(Body::empty())?)
     }
 
+    // Replaces the pooled instances taken by requests, and runs the tasks
+    // enqueued by requests, in the background.
+    fn spawn_background_work(self: &Arc<Self>) -> JoinHandle<()> {
+        let self_ = self.clone();
+        tokio::spawn(async move {
+            futures::join!(
+                self_.engine.maintain_instance_pools(),
+                self_.engine.run_background_tasks()
+            );
+        })
+    }
+
+    // Returns the trigger which requests are handled with. Each app update
+    // replaces it; requests already being handled complete with the trigger
+    // they started with.
+    fn start_serving(mut self) -> Arc<RwLock<Arc<Self>>> {
+        let updates = self.app_updates.take();
+        let trigger = Arc::new(self);
+        let mut background = trigger.spawn_background_work();
+        let current = Arc::new(RwLock::new(trigger));
+        if let Some(mut updates) = updates {
+            let current = current.clone();
+            tokio::spawn(async move {
+                while let Some(trigger) = updates.next().await {
+                    let trigger = Arc::new(trigger);
+                    background.abort();
+                    background = trigger.spawn_background_work();
+                    for (route, component_id) in trigger.router.routes() {
+                        log::info!("Updated route {route}: {component_id}");
+                    }
+                    *current.write().unwrap() = trigger;
+                }
+            });
+        }
+        current
+    }
+
     async fn serve(self, listen_addr: SocketAddr) -> Result<()> {
-        let self_ = Arc::new(self);
+        let listener = bind_listener(listen_addr)
+            .await
+            .with_context(|| format!("Unable to listen on {}", listen_addr))?;
+        self.serve_listener(listener).await
+    }
+
+    /// Serves HTTP requests on a listener which is already bound, e.g. to an
+    /// ephemeral port. This is useful for running an application in process,
+    /// for example in tests.
+    pub async fn serve_listener(self, listener: TcpListener) -> Result<()> {
+        let current = self.start_serving();
         let make_service = make_service_fn(|conn: &AddrStream| {
-            let self_ = self_.clone();
+            let current = current.clone();
             let addr = conn.remote_addr();
             async move {
                 let service = service_fn(move |req| {
-                    let self_ = self_.clone();
+                    let self_ = current.read().unwrap().clone();
                     async move { self_.handle(req, Scheme::HTTP, addr).await }
                 });
                 Ok::<_, Error>(service)
             }
         });
 
-        Server::try_bind(&listen_addr)
-            .with_context(|| format!("Unable to listen on {}", listen_addr))?
+        Server::builder(AddrIncoming::from_listener(listener)?)
             .serve(make_service)
             .await?;
         Ok(())
     }
 
     async fn serve_tls(self, listen_addr: SocketAddr, tls: TlsConfig) -> Result<()> {
-        let self_ = Arc::new(self);
+        let current = self.start_serving();
         let make_service = make_service_fn(|conn: &TlsStream<TcpStream>| {
-            let self_ = self_.clone();
+            let current = current.clone();
             let (inner_conn, _) = conn.get_ref();
             let addr_res = inner_conn.peer_addr().map_err(|err| err.to_string());
 
             async move {
                 let service = service_fn(move |req| {
-                    let self_ = self_.clone();
+                    let self_ = current.read().unwrap().clone();
                     let addr_res = addr_res.clone();
 
                     async move {
@@ -346,7 +581,7 @@ impl HttpTrigger {
             }
         });
 
-        let listener = TcpListener::bind(&listen_addr)
+        let listener = bind_listener(listen_addr)
             .await
             .with_context(|| format!("Unable to listen on {}", listen_addr))?;
 
@@ -366,6 +601,21 @@ impl HttpTrigger {
     }
 }
 
+async fn bind_listener(listen_addr: SocketAddr) -> std::io::Result<TcpListener> {
+    #[cfg(unix)]
+    if std::env::var_os(SPIN_HTTP_REUSE_PORT).is_some() {
+        let socket = match listen_addr {
+            SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6()?,
+        };
+        socket.set_reuseaddr(true)?;
+        socket.set_reuseport(true)?;
+        socket.bind(listen_addr)?;
+        return socket.listen(1024);
+    }
+    TcpListener::bind(listen_addr).await
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppInfo {
     pub name: String,
@@ -388,6 +638,22 @@ fn parse_listen_addr(addr: &str) -> anyhow::Result<SocketAddr> {
     addrs.into_iter().next().context("couldn't resolve address")
 }
 
+// Header values which aren't valid UTF-8 are recorded lossily, and those of
+// credential headers not at all.
+fn recorded_headers(headers: &http::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if CREDENTIAL_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
 fn set_req_uri(req: &mut Request<Body>, scheme: Scheme) -> Result<()> {
     const DEFAULT_HOST: &str = "localhost";
 
@@ -674,4 +940,18 @@ mod tests {
         assert_eq!(addr.ip(), Ipv4Addr::LOCALHOST);
         assert_eq!(addr.port(), 12345);
     }
+
+    #[test]
+    fn recorded_headers_redact_credentials() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("cookie", "session=secret".parse().unwrap());
+        headers.insert("x-custom", "kept".parse().unwrap());
+
+        let recorded = recorded_headers(&headers);
+        assert!(recorded.contains(&("authorization".into(), REDACTED.into())));
+        assert!(recorded.contains(&("cookie".into(), REDACTED.into())));
+        assert!(recorded.contains(&("x-custom".into(), "kept".into())));
+        assert!(!recorded.iter().any(|(_, value)| value.contains("secret")));
+    }
 }
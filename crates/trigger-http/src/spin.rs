@@ -27,14 +27,23 @@ impl HttpExecutor for SpinHttpExecutor {
             component_id
         );
 
-        let (instance, store) = engine.prepare_instance(component_id).await?;
-        let EitherInstance::Component(instance) = instance else {
+        let mut reusable = engine.prepare_reusable_instance(component_id).await?;
+        let EitherInstance::Component(instance) = &reusable.instance else {
             unreachable!()
         };
-
-        let resp = Self::execute_impl(store, instance, base, raw_route, req, client_addr)
-            .await
-            .map_err(contextualise_err)?;
+        let instance = *instance;
+
+        let resp = Self::execute_impl(
+            &mut reusable.store,
+            instance,
+            base,
+            raw_route,
+            req,
+            client_addr,
+        )
+        .await
+        .map_err(contextualise_err)?;
+        engine.release_instance(reusable);
 
         tracing::info!(
             "Request finished, sending response with status code {}",
@@ -46,7 +55,7 @@ impl HttpExecutor for SpinHttpExecutor {
 
 impl SpinHttpExecutor {
     pub async fn execute_impl(
-        mut store: Store,
+        store: &mut Store,
         instance: Instance,
         base: &str,
         raw_route: &str,
@@ -60,7 +69,7 @@ impl SpinHttpExecutor {
         }
 
         let func = instance
-            .exports(&mut store)
+            .exports(&mut *store)
             .instance("inbound-http")
             .ok_or_else(|| anyhow!("no inbound-http instance found"))?
             .typed_func::<(RequestParam,), (http_types::Response,)>("handle-request")?;
@@ -100,7 +109,9 @@ impl SpinHttpExecutor {
             body,
         };
 
-        let (resp,) = func.call_async(&mut store, (req,)).await?;
+        let (resp,) = func.call_async(&mut *store, (req,)).await?;
+        // Lets the instance handle another request, if it is reused.
+        func.post_return_async(&mut *store).await?;
 
         if resp.status < 100 || resp.status > 600 {
             tracing::error!("malformed HTTP status code");
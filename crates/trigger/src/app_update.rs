@@ -0,0 +1,668 @@
+//! Updating the application which a running trigger serves, without
+//! restarting the trigger.
+//!
+//! When a trigger is run with `--admin-listen`, it serves an admin API on the
+//! given address. A `PUT /app` request with a lock file as its body loads that
+//! application, and the trigger then swaps it in for the one it was serving:
+//! events which arrive afterwards are handled by the updated application,
+//! while those already being handled complete with the previous one.
+//!
+//! ```text
+//! curl -X PUT -H "Authorization: Bearer $TOKEN" --data-binary @spin.lock http://127.0.0.1:9091/app
+//! ```
+//!
+//! Requests must carry the admin token as a bearer token. Without
+//! `--admin-token`, the API may only listen on a loopback address, and a
+//! random token is generated and written to the trigger's working directory.
+//! An update may only use local files from the directories of the original
+//! application, and is refused if the runtime config requires signed
+//! applications, since a lock file sent to the API carries no signature.
+//!
+//! Only triggers which implement
+//! [`TriggerExecutor::app_updater`](crate::TriggerExecutor::app_updater)
+//! support updates.
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use hyper::{
+    header::{AUTHORIZATION, HOST},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use spin_app::locked::LockedApp;
+use tokio::sync::{mpsc, Mutex};
+
+/// Sends updated executors to a running trigger.
+pub struct AppUpdater<Executor> {
+    sender: mpsc::UnboundedSender<Executor>,
+}
+
+impl<Executor> AppUpdater<Executor> {
+    /// Sends the executor, built for the updated application, to the trigger,
+    /// which swaps it in for the executor it is running.
+    pub fn update(&self, executor: Executor) -> Result<()> {
+        self.sender
+            .send(executor)
+            .map_err(|_| anyhow!("the trigger is no longer running"))
+    }
+}
+
+impl<Executor> Clone for AppUpdater<Executor> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+/// Receives the executors sent with an [`AppUpdater`].
+pub struct AppUpdates<Executor> {
+    receiver: mpsc::UnboundedReceiver<Executor>,
+}
+
+impl<Executor> AppUpdates<Executor> {
+    /// Returns the next updated executor, or `None` once every
+    /// [`AppUpdater`] has been dropped.
+    pub async fn next(&mut self) -> Option<Executor> {
+        self.receiver.recv().await
+    }
+}
+
+/// Creates a connected [`AppUpdater`] and [`AppUpdates`].
+pub fn channel<Executor>() -> (AppUpdater<Executor>, AppUpdates<Executor>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (AppUpdater { sender }, AppUpdates { receiver })
+}
+
+/// The file in the trigger's working directory to which a generated admin
+/// token is written.
+pub const ADMIN_TOKEN_FILE: &str = "admin-token";
+
+// The largest lock file which the admin API accepts.
+const MAX_LOCK_FILE_SIZE: usize = 10 * 1024 * 1024;
+
+/// What the admin API accepts as an updated application.
+pub(crate) struct UpdatePolicy {
+    /// The directories in which the local files of an update must be: those
+    /// of the original application.
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Whether the runtime config requires signed applications.
+    pub signatures_required: bool,
+}
+
+impl UpdatePolicy {
+    /// Returns the policy for updates of the given original application,
+    /// whose local files are in its directory, if it has one, and in the
+    /// directories of the files it uses.
+    pub fn for_app(
+        original: &LockedApp,
+        app_dir: Option<PathBuf>,
+        signatures_required: bool,
+    ) -> Result<Self> {
+        let mut allowed_dirs = app_dir.into_iter().collect::<Vec<_>>();
+        for path in local_paths(original)? {
+            let dir = if path.is_dir() {
+                path
+            } else {
+                match path.parent() {
+                    Some(parent) => parent.to_owned(),
+                    None => continue,
+                }
+            };
+            allowed_dirs.push(dir);
+        }
+        let mut allowed_dirs = allowed_dirs
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect::<Vec<_>>();
+        allowed_dirs.sort();
+        allowed_dirs.dedup();
+        Ok(Self {
+            allowed_dirs,
+            signatures_required,
+        })
+    }
+
+    fn check(&self, locked: &LockedApp) -> Result<()> {
+        if self.signatures_required {
+            bail!("the runtime config requires signed applications, and an update through the admin API can't be verified");
+        }
+        for path in local_paths(locked)? {
+            let canonical = path
+                .canonicalize()
+                .with_context(|| format!("{} does not exist", path.display()))?;
+            if !self
+                .allowed_dirs
+                .iter()
+                .any(|dir| canonical.starts_with(dir))
+            {
+                bail!(
+                    "{} is outside the directories of the original application",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Binds a listener on `addr` which serves the admin API, returning a future
+/// which runs the server. Requests must carry `token` as a bearer token; if
+/// it is `None`, `addr` must be a loopback address, and a random token is
+/// generated and written to `working_dir`. Each updated lock file is written
+/// to `working_dir`, and `update` is called with its URL; updates are applied
+/// one at a time.
+pub(crate) fn serve_admin<F, Fut>(
+    addr: SocketAddr,
+    token: Option<String>,
+    policy: UpdatePolicy,
+    working_dir: PathBuf,
+    update: F,
+) -> Result<impl Future<Output = Result<()>>>
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    let token = match token {
+        Some(token) if token.is_empty() => bail!("The admin token must not be empty"),
+        Some(token) => token,
+        None if addr.ip().is_loopback() => generate_token(&working_dir)?,
+        None => bail!(
+            "The admin API on {addr} would be reachable from other machines; set a token with --admin-token, or listen on a loopback address"
+        ),
+    };
+    let admin = Arc::new(Admin {
+        token,
+        loopback: addr.ip().is_loopback(),
+        policy,
+        working_dir,
+        update,
+        updating: Mutex::new(()),
+    });
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("Failed to bind admin listener on {addr}"))?
+        .serve(make_service_fn(move |_| {
+            let admin = admin.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let admin = admin.clone();
+                    async move { Ok::<_, Infallible>(admin.handle(req).await) }
+                }))
+            }
+        }));
+    tracing::info!("Serving admin API on http://{addr}");
+    Ok(async move { server.await.context("Admin listener failed") })
+}
+
+struct Admin<F> {
+    token: String,
+    // Whether the API listens on a loopback address, in which case requests
+    // must name a loopback host, so that web pages can't reach the API by
+    // rebinding their own host name to the loopback address.
+    loopback: bool,
+    policy: UpdatePolicy,
+    working_dir: PathBuf,
+    update: F,
+    // Held while an update is applied, so that concurrent updates can't
+    // finish out of order.
+    updating: Mutex<()>,
+}
+
+impl<F, Fut> Admin<F>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    async fn handle(&self, req: Request<Body>) -> Response<Body> {
+        if req.uri().path() != "/app" {
+            return response(StatusCode::NOT_FOUND, "");
+        }
+        if self.loopback && !is_loopback_host(&req) {
+            return response(StatusCode::FORBIDDEN, "");
+        }
+        if !self.is_authorized(&req) {
+            let mut response = response(StatusCode::UNAUTHORIZED, "");
+            response.headers_mut().insert(
+                hyper::header::WWW_AUTHENTICATE,
+                hyper::header::HeaderValue::from_static("Bearer"),
+            );
+            return response;
+        }
+        if req.method() != Method::PUT {
+            return response(StatusCode::METHOD_NOT_ALLOWED, "");
+        }
+        let locked = match read_body(req.into_body(), MAX_LOCK_FILE_SIZE).await {
+            Ok(Some(body)) => LockedApp::from_json(&body).map_err(anyhow::Error::from),
+            Ok(None) => {
+                return response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("Lock file is larger than {MAX_LOCK_FILE_SIZE} bytes\n"),
+                )
+            }
+            Err(err) => Err(err.into()),
+        };
+        let locked = match locked {
+            Ok(locked) => locked,
+            Err(err) => {
+                return response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid lock file: {err:#}\n"),
+                )
+            }
+        };
+
+        if let Err(err) = self.policy.check(&locked) {
+            return response(
+                StatusCode::FORBIDDEN,
+                format!("Refused to update application: {err:#}\n"),
+            );
+        }
+
+        let _updating = self.updating.lock().await;
+        match self.apply(&locked).await {
+            Ok(()) => {
+                tracing::info!("Updated application");
+                response(StatusCode::OK, "Updated application\n")
+            }
+            Err(err) => {
+                tracing::error!("Failed to update application: {err:#}");
+                response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to update application: {err:#}\n"),
+                )
+            }
+        }
+    }
+
+    fn is_authorized(&self, req: &Request<Body>) -> bool {
+        let Some(token) = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return false;
+        };
+        constant_time_eq(token.trim().as_bytes(), self.token.as_bytes())
+    }
+
+    async fn apply(&self, locked: &LockedApp) -> Result<()> {
+        let path = write_locked_app(locked, &self.working_dir).await?;
+        let url = url::Url::from_file_path(&path)
+            .map_err(|_| anyhow!("Lock file path {path:?} is not absolute"));
+        let result = match url {
+            Ok(url) => (self.update)(url.to_string()).await,
+            Err(err) => Err(err),
+        };
+        // The updated executor has loaded the application by now.
+        if let Err(err) = tokio::fs::remove_file(&path).await {
+            tracing::warn!("Failed to remove lock file {path:?}: {err}");
+        }
+        result
+    }
+}
+
+// Reads the body, or returns `None` as soon as it is known to be larger than
+// `limit` bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+    use hyper::body::HttpBody;
+    if body.size_hint().lower() > limit as u64 {
+        return Ok(None);
+    }
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+// Writes the lock file for an update alongside the one the trigger started
+// with, returning its path.
+async fn write_locked_app(locked: &LockedApp, working_dir: &Path) -> Result<PathBuf> {
+    let path = working_dir.join(format!("spin-update-{}.lock", uuid::Uuid::new_v4()));
+    let contents = locked.to_json()?;
+    tokio::fs::write(&path, contents)
+        .await
+        .with_context(|| format!("Failed to write lock file {path:?}"))?;
+    Ok(path)
+}
+
+// Returns the paths of the local files which the application uses.
+fn local_paths(locked: &LockedApp) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for component in &locked.components {
+        let contents = std::iter::once(&component.source.content)
+            .chain(component.files.iter().map(|file| &file.content));
+        for content in contents {
+            match content.source.as_deref() {
+                Some(source) if source.starts_with("file:") => {
+                    paths.push(crate::parse_file_url(source)?)
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(paths)
+}
+
+// Generates a random admin token, and writes it to a file which only the
+// current user can read.
+fn generate_token(working_dir: &Path) -> Result<String> {
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let path = working_dir.join(ADMIN_TOKEN_FILE);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Failed to write admin token to {path:?}"))?;
+    // The mode only applies to new files.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to restrict permissions of {path:?}"))?;
+    }
+    std::io::Write::write_all(&mut file, token.as_bytes())?;
+    tracing::info!("Admin API token written to {}", path.display());
+    Ok(token)
+}
+
+// Whether the request's Host names this machine.
+fn is_loopback_host<B>(req: &Request<B>) -> bool {
+    let Some(host) = req.headers().get(HOST).and_then(|host| host.to_str().ok()) else {
+        return false;
+    };
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().map_or(false, |ip| ip.is_loopback())
+}
+
+// Compares secrets in time independent of where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn response(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    const TOKEN: &str = "secret-token";
+
+    const LOCKED_APP: &str = r#"{
+        "spin_lock_version": 0,
+        "triggers": [],
+        "components": []
+    }"#;
+
+    fn admin(
+        working_dir: PathBuf,
+        policy: UpdatePolicy,
+        updates: Arc<StdMutex<Vec<String>>>,
+    ) -> Admin<impl Fn(String) -> std::future::Ready<Result<()>>> {
+        Admin {
+            token: TOKEN.to_owned(),
+            loopback: true,
+            policy,
+            working_dir,
+            update: move |url| {
+                // The lock file exists while the update is applied.
+                let path = url::Url::parse(&url).unwrap().to_file_path().unwrap();
+                LockedApp::from_json(&std::fs::read(path).unwrap()).unwrap();
+                updates.lock().unwrap().push(url);
+                std::future::ready(Ok(()))
+            },
+            updating: Mutex::new(()),
+        }
+    }
+
+    fn any_policy() -> UpdatePolicy {
+        UpdatePolicy {
+            allowed_dirs: vec![],
+            signatures_required: false,
+        }
+    }
+
+    fn put_app(body: &str) -> Request<Body> {
+        Request::put("/app")
+            .header(HOST, "127.0.0.1:9091")
+            .header(AUTHORIZATION, format!("Bearer {TOKEN}"))
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    fn locked_app_with_source(path: &Path) -> String {
+        let url = url::Url::from_file_path(path).unwrap();
+        format!(
+            r#"{{
+                "spin_lock_version": 0,
+                "triggers": [],
+                "components": [{{
+                    "id": "hello",
+                    "source": {{ "content_type": "application/wasm", "source": "{url}" }}
+                }}]
+            }}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn updates_app_from_lock_file() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let updates = Arc::default();
+        let admin = admin(
+            working_dir.path().to_owned(),
+            any_policy(),
+            Arc::clone(&updates),
+        );
+
+        let resp = admin.handle(put_app(LOCKED_APP)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let updates = updates.lock().unwrap();
+        assert_eq!(updates.len(), 1);
+        let path = url::Url::parse(&updates[0])
+            .unwrap()
+            .to_file_path()
+            .unwrap();
+        assert!(path.starts_with(working_dir.path()));
+        // The lock file is removed once the update is applied.
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_requests() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let updates = Arc::<StdMutex<Vec<String>>>::default();
+        let admin = admin(
+            working_dir.path().to_owned(),
+            any_policy(),
+            Arc::clone(&updates),
+        );
+
+        let resp = admin.handle(put_app("not a lock file")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let get = Request::get("/app")
+            .header(HOST, "localhost:9091")
+            .header(AUTHORIZATION, format!("Bearer {TOKEN}"))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            admin.handle(get).await.status(),
+            StatusCode::METHOD_NOT_ALLOWED
+        );
+
+        let other = Request::put("/other").body(Body::empty()).unwrap();
+        assert_eq!(admin.handle(other).await.status(), StatusCode::NOT_FOUND);
+
+        assert!(updates.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn requires_token_and_loopback_host() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let updates = Arc::<StdMutex<Vec<String>>>::default();
+        let admin = admin(
+            working_dir.path().to_owned(),
+            any_policy(),
+            Arc::clone(&updates),
+        );
+
+        let unauthenticated = Request::put("/app")
+            .header(HOST, "127.0.0.1:9091")
+            .body(Body::from(LOCKED_APP))
+            .unwrap();
+        assert_eq!(
+            admin.handle(unauthenticated).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let wrong_token = Request::put("/app")
+            .header(HOST, "127.0.0.1:9091")
+            .header(AUTHORIZATION, "Bearer guess")
+            .body(Body::from(LOCKED_APP))
+            .unwrap();
+        assert_eq!(
+            admin.handle(wrong_token).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let rebound = Request::put("/app")
+            .header(HOST, "attacker.example.com")
+            .header(AUTHORIZATION, format!("Bearer {TOKEN}"))
+            .body(Body::from(LOCKED_APP))
+            .unwrap();
+        assert_eq!(admin.handle(rebound).await.status(), StatusCode::FORBIDDEN);
+
+        assert!(updates.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn refuses_files_outside_app_and_unsigned_apps() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let inside = app_dir.path().join("app.wasm");
+        let outside = other_dir.path().join("secret");
+        std::fs::write(&inside, b"").unwrap();
+        std::fs::write(&outside, b"").unwrap();
+        let original = LockedApp::from_json(locked_app_with_source(&inside).as_bytes()).unwrap();
+
+        let working_dir = tempfile::tempdir().unwrap();
+        let updates = Arc::<StdMutex<Vec<String>>>::default();
+        let policy = UpdatePolicy::for_app(&original, None, false).unwrap();
+        let admin = admin(working_dir.path().to_owned(), policy, Arc::clone(&updates));
+
+        let resp = admin
+            .handle(put_app(&locked_app_with_source(&outside)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let escaping = app_dir
+            .path()
+            .join("..")
+            .join(other_dir.path().file_name().unwrap())
+            .join("secret");
+        let resp = admin
+            .handle(put_app(&locked_app_with_source(&escaping)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(updates.lock().unwrap().is_empty());
+
+        let resp = admin
+            .handle(put_app(&locked_app_with_source(&inside)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let policy = UpdatePolicy::for_app(&original, None, true).unwrap();
+        let admin = self::admin(working_dir.path().to_owned(), policy, Arc::clone(&updates));
+        let resp = admin
+            .handle(put_app(&locked_app_with_source(&inside)))
+            .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_lock_file() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let updates = Arc::<StdMutex<Vec<String>>>::default();
+        let admin = admin(
+            working_dir.path().to_owned(),
+            any_policy(),
+            Arc::clone(&updates),
+        );
+
+        let oversized = " ".repeat(MAX_LOCK_FILE_SIZE) + LOCKED_APP;
+        let resp = admin.handle(put_app(&oversized)).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(updates.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn generated_token_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let working_dir = tempfile::tempdir().unwrap();
+        let path = working_dir.path().join(ADMIN_TOKEN_FILE);
+        std::fs::write(&path, "stale").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let token = generate_token(working_dir.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), token);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn non_loopback_admin_requires_token() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let update = |_| std::future::ready(Ok(()));
+        let public = "0.0.0.0:0".parse().unwrap();
+        assert!(serve_admin(
+            public,
+            None,
+            any_policy(),
+            working_dir.path().into(),
+            update
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn updates_are_received_in_order() {
+        let (updater, mut updates) = channel();
+        updater.update(1).unwrap();
+        updater.clone().update(2).unwrap();
+        drop(updater);
+        assert_eq!(updates.next().await, Some(1));
+        assert_eq!(updates.next().await, Some(2));
+        assert_eq!(updates.next().await, None);
+    }
+}
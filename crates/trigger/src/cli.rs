@@ -1,19 +1,34 @@
-use std::path::PathBuf;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{Context, Result};
 use clap::{Args, IntoApp, Parser};
+use futures::future::{AbortHandle, Abortable};
 use serde::de::DeserializeOwned;
-use spin_app::Loader;
+use spin_app::{locked::LockedApp, Loader};
 use spin_common::{arg_parser::parse_kv, sloth};
 
+use crate::app_update::{self, AppUpdater, UpdatePolicy};
+use crate::dry_run::{self, DryRunHook};
+use crate::record::Recorder;
 use crate::runtime_config::sqlite::SqlitePersistenceMessageHook;
+use crate::seed::Seed;
 use crate::stdio::StdioLoggingTriggerHooks;
 use crate::{
+    compile_cache::CompileCache,
     loader::TriggerLoader,
-    runtime_config::{key_value::KeyValuePersistenceMessageHook, RuntimeConfig},
+    runtime_config::{
+        faults::{parse_fault_flag, FaultInterface, FaultOpts},
+        key_value::KeyValuePersistenceMessageHook,
+        RuntimeConfig,
+    },
     stdio::FollowComponents,
 };
-use crate::{TriggerExecutor, TriggerExecutorBuilder};
+use crate::{parse_file_url, RuntimeConfigReloader, TriggerExecutor, TriggerExecutorBuilder};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
 pub const DISABLE_WASMTIME_CACHE: &str = "DISABLE_WASMTIME_CACHE";
@@ -25,6 +40,13 @@ pub const RUNTIME_CONFIG_FILE: &str = "RUNTIME_CONFIG_FILE";
 pub const SPIN_LOCKED_URL: &str = "SPIN_LOCKED_URL";
 pub const SPIN_LOCAL_APP_DIR: &str = "SPIN_LOCAL_APP_DIR";
 pub const SPIN_WORKING_DIR: &str = "SPIN_WORKING_DIR";
+pub const SPIN_RELOAD_COMPONENTS: &str = "SPIN_RELOAD_COMPONENTS";
+// Set by `spin precompile`
+pub const SPIN_PRECOMPILE_ONLY: &str = "SPIN_PRECOMPILE_ONLY";
+// Set by `spin up --dry-run`
+pub const SPIN_DRY_RUN: &str = "SPIN_DRY_RUN";
+// Set by `spin watch`
+pub const SPIN_SEED: &str = "SPIN_SEED";
 
 /// A command that runs a TriggerExecutor.
 #[derive(Parser, Debug)]
@@ -41,7 +63,25 @@ where
     )]
     pub log: Option<PathBuf>,
 
-    /// Disable Wasmtime cache.
+    /// Rotate component log files once they reach this size, in megabytes.
+    #[clap(long = "log-max-size")]
+    pub log_max_size_mb: Option<u64>,
+
+    /// Number of rotated files to keep for each component log file.
+    /// Defaults to 5.
+    #[clap(long = "log-max-files")]
+    pub log_max_files: Option<usize>,
+
+    /// Delete rotated component log files older than this many days.
+    #[clap(long = "log-max-age")]
+    pub log_max_age_days: Option<u64>,
+
+    /// Keep this many instances of each component ready ahead of requests,
+    /// to take instantiation off the request path. Defaults to 0 (disabled).
+    #[clap(long = "instance-pool-size")]
+    pub instance_pool_size: Option<usize>,
+
+    /// Disable Wasmtime cache, and the cache of compiled components.
     #[clap(
         name = DISABLE_WASMTIME_CACHE,
         long = "disable-cache",
@@ -98,6 +138,29 @@ where
     #[clap(long)]
     pub state_dir: Option<String>,
 
+    /// Serve Prometheus metrics at `/metrics` on the given address,
+    /// e.g. `127.0.0.1:9090`.
+    #[clap(long = "metrics-listen", env = "SPIN_METRICS_LISTEN")]
+    pub metrics_listen: Option<SocketAddr>,
+
+    /// Serve an admin API on the given address, e.g. `127.0.0.1:9091`, with
+    /// which the application can be updated without restarting the trigger:
+    /// `PUT /app` with a lock file as the body swaps in that application.
+    #[clap(long = "admin-listen", env = "SPIN_ADMIN_LISTEN")]
+    pub admin_listen: Option<SocketAddr>,
+
+    /// The bearer token which requests to the admin API must carry. Required
+    /// unless the admin API listens on a loopback address, in which case a
+    /// random token is generated and written to the working directory.
+    #[clap(long = "admin-token", env = "SPIN_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Record the events the trigger receives, such as HTTP requests and their
+    /// responses, to this file, for replay with `spin replay`. Events are
+    /// appended to any already in the file.
+    #[clap(long = "record")]
+    pub record: Option<PathBuf>,
+
     #[clap(flatten)]
     pub run_config: Executor::RunConfig,
 
@@ -107,6 +170,21 @@ where
     #[clap(long = "key-value", parse(try_from_str = parse_kv))]
     key_values: Vec<(String, String)>,
 
+    /// Seed the default stores of a local application from the files in its
+    /// `.spin/seed/` directory. `spin watch` always seeds them. Keys already
+    /// in the default key value store keep their values.
+    #[clap(long = "seed", env = SPIN_SEED, takes_value = false)]
+    pub seed: bool,
+
+    /// Inject latency and errors into a component's calls to a host
+    /// interface, as `<component>:<interface>:<settings>`. The interface is
+    /// `outbound_http`, `key_value` or `sqlite`, and the settings are
+    /// `latency_ms=<ms>` and/or `error_rate=<0 to 1>`, separated by commas,
+    /// e.g. `api:key_value:latency_ms=200,error_rate=0.1`. May be given more
+    /// than once.
+    #[clap(long = "inject-fault", parse(try_from_str = parse_fault_flag))]
+    inject_faults: Vec<(String, FaultInterface, FaultOpts)>,
+
     /// Run a sqlite migration against the default database
     #[clap(long = "sqlite", hide = true)]
     sqlite_statements: Vec<String>,
@@ -139,18 +217,81 @@ where
         let working_dir = std::env::var(SPIN_WORKING_DIR).context(SPIN_WORKING_DIR)?;
         let locked_url = std::env::var(SPIN_LOCKED_URL).context(SPIN_LOCKED_URL)?;
 
-        let init_data = crate::HostComponentInitData {
-            kv: self.key_values.clone(),
-            sqlite: self.sqlite_statements.clone(),
+        // A dry run describes the application's stores without writing to
+        // them.
+        let init_data = if std::env::var_os(SPIN_DRY_RUN).is_some() {
+            crate::HostComponentInitData::default()
+        } else {
+            self.init_data()?
         };
 
-        let loader = TriggerLoader::new(working_dir, self.allow_transient_write);
-        let executor = self.build_executor(loader, locked_url, init_data).await?;
+        let options = self.executor_options(working_dir.into());
+        let loader = options.loader()?;
+        let reloader = RuntimeConfigReloader::default();
+        let mut executor: Executor = options
+            .build_executor(loader, locked_url.clone(), init_data, reloader.clone())
+            .await?;
 
-        let run_fut = executor.run(self.run_config);
+        if std::env::var_os(SPIN_PRECOMPILE_ONLY).is_some() {
+            // Building the executor compiled every component into the cache.
+            return Ok(());
+        }
+
+        if std::env::var_os(SPIN_DRY_RUN).is_some() {
+            dry_run::print_routes(Executor::TRIGGER_TYPE, &executor.routes());
+            return Ok(());
+        }
+
+        if let Some(addr) = self.metrics_listen {
+            let metrics_server = spin_telemetry::metrics::serve(addr)?;
+            tokio::spawn(async move {
+                if let Err(err) = metrics_server.await {
+                    tracing::error!("{err:?}");
+                }
+            });
+        }
+
+        if let Some(addr) = self.admin_listen {
+            let updater = executor.app_updater().with_context(|| {
+                format!(
+                    "The {} trigger does not support updating the application with --admin-listen",
+                    Executor::TRIGGER_TYPE
+                )
+            })?;
+            let original = LockedApp::from_json(&std::fs::read(parse_file_url(&locked_url)?)?)?;
+            let signatures_required = options
+                .build_runtime_config()?
+                .registry_trust()?
+                .map_or(false, |trust| {
+                    trust.require_signatures || !trust.required_issuers.is_empty()
+                });
+            let policy = UpdatePolicy::for_app(
+                &original,
+                std::env::var_os(SPIN_LOCAL_APP_DIR).map(PathBuf::from),
+                signatures_required,
+            )?;
+            let admin_server = options.serve_app_updates(
+                addr,
+                self.admin_token.clone(),
+                policy,
+                updater,
+                reloader.clone(),
+            )?;
+            tokio::spawn(async move {
+                if let Err(err) = admin_server.await {
+                    tracing::error!("{err:?}");
+                }
+            });
+        }
 
-        let (abortable, abort_handle) = futures::future::abortable(run_fut);
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        #[cfg(unix)]
+        handle_signals(abort_handle, options.build_runtime_config()?, reloader)?;
+        #[cfg(not(unix))]
         ctrlc::set_handler(move || abort_handle.abort())?;
+
+        let run_fut = executor.run(self.run_config);
+        let abortable = Abortable::new(run_fut, abort_registration);
         match abortable.await {
             Ok(Ok(())) => {
                 tracing::info!("Trigger executor shut down: exiting");
@@ -167,26 +308,159 @@ where
         }
     }
 
-    async fn build_executor(
+    fn init_data(&self) -> Result<crate::HostComponentInitData> {
+        // SQL seeds are applied first, so that `--sqlite` can build on them.
+        let seed = match std::env::var_os(SPIN_LOCAL_APP_DIR) {
+            Some(app_dir) if self.seed => Seed::load(Path::new(&app_dir))?,
+            _ => Seed::default(),
+        };
+        Ok(crate::HostComponentInitData {
+            kv: self.key_values.clone(),
+            kv_seed: seed.kv,
+            sqlite: seed
+                .sqlite
+                .into_iter()
+                .chain(self.sqlite_statements.clone())
+                .collect(),
+        })
+    }
+
+    fn executor_options(&self, working_dir: PathBuf) -> ExecutorOptions {
+        ExecutorOptions {
+            working_dir,
+            allow_transient_write: self.allow_transient_write,
+            log: self.log.clone(),
+            log_max_size_mb: self.log_max_size_mb,
+            log_max_files: self.log_max_files,
+            log_max_age_days: self.log_max_age_days,
+            instance_pool_size: self.instance_pool_size,
+            inject_faults: self.inject_faults.clone(),
+            runtime_config_file: self.runtime_config_file.clone(),
+            state_dir: self.state_dir.clone(),
+            disable_cache: self.disable_cache,
+            cache: self.cache.clone(),
+            record: self.record.clone(),
+            follow_components: self.follow_components(),
+        }
+    }
+
+    fn follow_components(&self) -> FollowComponents {
+        if self.silence_component_logs {
+            FollowComponents::None
+        } else if self.follow_components.is_empty() {
+            FollowComponents::All
+        } else {
+            let followed = self.follow_components.clone().into_iter().collect();
+            FollowComponents::Named(followed)
+        }
+    }
+}
+
+// The options with which the command builds executors. They are kept apart
+// from the command, whose run config the running executor takes, so that
+// executors for app updates can be built while it runs.
+#[derive(Clone)]
+struct ExecutorOptions {
+    working_dir: PathBuf,
+    allow_transient_write: bool,
+    log: Option<PathBuf>,
+    log_max_size_mb: Option<u64>,
+    log_max_files: Option<usize>,
+    log_max_age_days: Option<u64>,
+    instance_pool_size: Option<usize>,
+    inject_faults: Vec<(String, FaultInterface, FaultOpts)>,
+    runtime_config_file: Option<PathBuf>,
+    state_dir: Option<String>,
+    disable_cache: bool,
+    cache: Option<PathBuf>,
+    record: Option<PathBuf>,
+    follow_components: FollowComponents,
+}
+
+impl ExecutorOptions {
+    fn loader(&self) -> Result<TriggerLoader> {
+        let mut loader = TriggerLoader::new(&self.working_dir, self.allow_transient_write);
+        loader.set_config_providers(self.build_runtime_config()?.config_providers()?);
+        if !self.disable_cache {
+            loader.enable_compile_cache(CompileCache::new(CompileCache::default_dir()?));
+        }
+        Ok(loader)
+    }
+
+    async fn build_executor<Executor: TriggerExecutor>(
         &self,
         loader: impl Loader + Send + Sync + 'static,
         locked_url: String,
         init_data: crate::HostComponentInitData,
-    ) -> Result<Executor> {
+        reloader: RuntimeConfigReloader,
+    ) -> Result<Executor>
+    where
+        Executor::TriggerConfig: DeserializeOwned,
+    {
         let runtime_config = self.build_runtime_config()?;
+        spin_telemetry::set_log_levels(runtime_config.log_levels()?);
 
         let _sloth_guard = warn_if_wasm_build_slothful();
 
         let mut builder = TriggerExecutorBuilder::new(loader);
-        self.update_wasmtime_config(builder.wasmtime_config_mut())?;
+        self.update_wasmtime_config(builder.wasmtime_config_mut(), &runtime_config)?;
+        if std::env::var_os(SPIN_RELOAD_COMPONENTS).is_some() {
+            builder.reload_changed_components();
+        }
+        builder.runtime_config_reloader(reloader);
+        if let Some(path) = &self.record {
+            builder.record_events(Recorder::open(path)?);
+        }
 
-        builder.hooks(StdioLoggingTriggerHooks::new(self.follow_components()));
-        builder.hooks(KeyValuePersistenceMessageHook);
-        builder.hooks(SqlitePersistenceMessageHook);
+        builder.hooks(StdioLoggingTriggerHooks::new(
+            self.follow_components.clone(),
+            spin_telemetry::LogFormat::from_env()?,
+            spin_telemetry::LogTarget::from_env()?,
+        ));
+        if std::env::var_os(SPIN_DRY_RUN).is_some() {
+            // Describes every store and database, not only the defaults.
+            builder.hooks(DryRunHook);
+            builder.dry_run();
+        } else {
+            builder.hooks(KeyValuePersistenceMessageHook);
+            builder.hooks(SqlitePersistenceMessageHook);
+        }
 
         builder.build(locked_url, runtime_config, init_data).await
     }
 
+    // Serves the admin API, building an executor for each updated app and
+    // sending it to the running executor.
+    fn serve_app_updates<Executor: TriggerExecutor>(
+        &self,
+        addr: SocketAddr,
+        token: Option<String>,
+        policy: UpdatePolicy,
+        updater: AppUpdater<Executor>,
+        reloader: RuntimeConfigReloader,
+    ) -> Result<impl Future<Output = Result<()>>>
+    where
+        Executor::TriggerConfig: DeserializeOwned,
+    {
+        let options = Arc::new(self.clone());
+        let working_dir = self.working_dir.clone();
+        app_update::serve_admin(addr, token, policy, working_dir, move |locked_url| {
+            let options = options.clone();
+            let updater = updater.clone();
+            let reloader = reloader.clone();
+            async move {
+                let loader = options.loader()?;
+                // The stores were seeded when the trigger started, so updates
+                // leave them as they are.
+                let init_data = crate::HostComponentInitData::default();
+                let executor = options
+                    .build_executor(loader, locked_url, init_data, reloader)
+                    .await?;
+                updater.update(executor)
+            }
+        })
+    }
+
     fn build_runtime_config(&self) -> Result<RuntimeConfig> {
         let local_app_dir = std::env::var_os(SPIN_LOCAL_APP_DIR);
         let mut config = RuntimeConfig::new(local_app_dir.map(Into::into));
@@ -196,24 +470,32 @@ where
         if let Some(log_dir) = &self.log {
             config.set_log_dir(log_dir);
         }
+        if let Some(max_size_mb) = self.log_max_size_mb {
+            config.set_log_max_size_mb(max_size_mb);
+        }
+        if let Some(max_files) = self.log_max_files {
+            config.set_log_max_files(max_files);
+        }
+        if let Some(max_age_days) = self.log_max_age_days {
+            config.set_log_max_age_days(max_age_days);
+        }
+        if let Some(size) = self.instance_pool_size {
+            config.set_instance_pool_size(size);
+        }
+        for (component_id, interface, opts) in &self.inject_faults {
+            config.add_component_fault(component_id, *interface, opts.clone());
+        }
         if let Some(config_file) = &self.runtime_config_file {
             config.merge_config_file(config_file)?;
         }
         Ok(config)
     }
 
-    fn follow_components(&self) -> FollowComponents {
-        if self.silence_component_logs {
-            FollowComponents::None
-        } else if self.follow_components.is_empty() {
-            FollowComponents::All
-        } else {
-            let followed = self.follow_components.clone().into_iter().collect();
-            FollowComponents::Named(followed)
-        }
-    }
-
-    fn update_wasmtime_config(&self, config: &mut spin_core::wasmtime::Config) -> Result<()> {
+    fn update_wasmtime_config(
+        &self,
+        config: &mut spin_core::wasmtime::Config,
+        runtime_config: &RuntimeConfig,
+    ) -> Result<()> {
         // Apply --cache / --disable-cache
         if !self.disable_cache {
             match &self.cache {
@@ -221,10 +503,49 @@ where
                 None => config.cache_config_load_default()?,
             };
         }
+        // Apply [wasmtime] runtime config
+        runtime_config
+            .wasmtime_opts()
+            .update_wasmtime_config(config)
+            .context("Invalid [wasmtime] runtime config")?;
         Ok(())
     }
 }
 
+// Shuts the trigger down on SIGINT or SIGTERM, and reloads the runtime config
+// file on SIGHUP.
+#[cfg(unix)]
+fn handle_signals(
+    abort_handle: AbortHandle,
+    mut runtime_config: RuntimeConfig,
+    reloader: RuntimeConfigReloader,
+) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupts = signal(SignalKind::interrupt())?;
+    let mut terminations = signal(SignalKind::terminate())?;
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = interrupts.recv() => break,
+                _ = terminations.recv() => break,
+                _ = hangups.recv() => {
+                    let reloaded = runtime_config
+                        .reload_config_files()
+                        .and_then(|()| reloader.reload(&runtime_config));
+                    match reloaded {
+                        Ok(()) => tracing::info!("Reloaded runtime config"),
+                        Err(err) => tracing::error!("Failed to reload runtime config: {err:#}"),
+                    }
+                }
+            }
+        }
+        abort_handle.abort();
+    });
+    Ok(())
+}
+
 const SLOTH_WARNING_DELAY_MILLIS: u64 = 1250;
 
 fn warn_if_wasm_build_slothful() -> sloth::SlothGuard {
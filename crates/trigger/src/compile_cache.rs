@@ -0,0 +1,126 @@
+//! A cache of ahead-of-time compiled components and modules.
+//!
+//! Compiled artifacts are stored under a directory for the engine's
+//! configuration, named by the SHA-256 digest of the Wasm they were compiled
+//! from. An engine only loads artifacts compiled with a compatible
+//! configuration, and by the same version of Wasmtime.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use spin_core::{wasmtime::Engine, Component, Module};
+
+/// A directory of compiled components and modules.
+pub struct CompileCache {
+    dir: PathBuf,
+}
+
+impl CompileCache {
+    /// Creates a cache which stores compiled artifacts in the given
+    /// directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The default cache directory, under Spin's data directory.
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(spin_common::data_dir::default_data_dir()?.join("compiled"))
+    }
+
+    /// Returns the compiled form of the given component, compiling it and
+    /// storing the result if it is not in the cache.
+    pub fn load_component(&self, engine: &Engine, wasm: &[u8]) -> Result<Component> {
+        let path = self.artifact_path(engine, wasm, "component");
+        if path.exists() {
+            // SAFETY: artifacts in the cache are written by `store` from the
+            // output of `Engine::precompile_component`.
+            match unsafe { Component::deserialize_file(engine, &path) } {
+                Ok(component) => {
+                    spin_common::cache_usage::mark_used(&path);
+                    return Ok(component);
+                }
+                Err(err) => tracing::debug!("Ignoring cached {path:?}: {err:#}"),
+            }
+        }
+        let compiled = engine.precompile_component(wasm)?;
+        self.store(&path, &compiled);
+        // SAFETY: `compiled` is the output of `Engine::precompile_component`
+        // for this engine.
+        unsafe { Component::deserialize(engine, &compiled) }
+    }
+
+    /// Returns the compiled form of the given module, compiling it and
+    /// storing the result if it is not in the cache.
+    pub fn load_module(&self, engine: &Engine, wasm: &[u8]) -> Result<Module> {
+        let path = self.artifact_path(engine, wasm, "module");
+        if path.exists() {
+            // SAFETY: artifacts in the cache are written by `store` from the
+            // output of `Engine::precompile_module`.
+            match unsafe { Module::deserialize_file(engine, &path) } {
+                Ok(module) => {
+                    spin_common::cache_usage::mark_used(&path);
+                    return Ok(module);
+                }
+                Err(err) => tracing::debug!("Ignoring cached {path:?}: {err:#}"),
+            }
+        }
+        let compiled = engine.precompile_module(wasm)?;
+        self.store(&path, &compiled);
+        // SAFETY: `compiled` is the output of `Engine::precompile_module` for
+        // this engine.
+        unsafe { Module::deserialize(engine, &compiled) }
+    }
+
+    fn artifact_path(&self, engine: &Engine, wasm: &[u8], kind: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let engine_dir = format!("{:016x}", hasher.finish());
+        let digest = spin_common::sha256::hex_digest_from_bytes(wasm);
+        self.dir
+            .join(engine_dir)
+            .join(format!("{digest}.{kind}.cwasm"))
+    }
+
+    // A failure to store an artifact only means that it must be compiled
+    // again next time, so is not an error.
+    fn store(&self, path: &Path, compiled: &[u8]) {
+        if let Err(err) = write_atomically(path, compiled) {
+            tracing::warn!("Failed to cache compiled Wasm at {path:?}: {err:#}");
+        }
+    }
+}
+
+// Writes via a temporary file, so that concurrent readers never see a
+// partially written artifact.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().context("cache path has no parent")?;
+    std::fs::create_dir_all(dir)?;
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_compiled_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CompileCache::new(dir.path());
+        let engine = Engine::default();
+        let wasm = br#"(module (func (export "f")))"#;
+
+        cache.load_module(&engine, wasm).unwrap();
+        let path = cache.artifact_path(&engine, wasm, "module");
+        assert!(path.exists(), "{path:?} was not written");
+
+        let module = cache.load_module(&engine, wasm).unwrap();
+        assert!(module.get_export("f").is_some());
+    }
+}
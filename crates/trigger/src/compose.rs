@@ -0,0 +1,200 @@
+//! Composition of components with the library components they depend on.
+//!
+//! A component may declare dependencies: components which satisfy its
+//! imports, keyed by import name. When the application is loaded, each such
+//! import is instantiated from its dependency, so that what is run is a
+//! single component whose remaining imports are provided by the host.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use spin_app::locked::{ContentRef, LockedComponent};
+use spin_common::sha256::hex_digest_from_bytes;
+use wasm_compose::{
+    composer::ComponentComposer,
+    config::{Config, Dependency},
+};
+
+use crate::{
+    locked::{LockedDependency, DEPENDENCIES_KEY},
+    parse_file_url,
+};
+
+/// Composes the component with its dependencies, if it has any. The
+/// composed component is written under `working_dir`, keyed by the digest of
+/// what it was composed from so that it is reused when the application is
+/// reloaded unchanged, and the component's source replaced with it.
+///
+/// This reads and writes files, so should not be called on an async task.
+pub(crate) fn compose_dependencies(
+    component: &mut LockedComponent,
+    working_dir: &Path,
+) -> Result<()> {
+    let Some(dependencies) = component.metadata.remove(DEPENDENCIES_KEY.as_ref()) else {
+        return Ok(());
+    };
+    let dependencies: HashMap<String, LockedDependency> =
+        serde_json::from_value(dependencies).context("invalid dependencies metadata")?;
+    let mut dependencies = dependencies.into_iter().collect::<Vec<_>>();
+    dependencies.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let source = component
+        .source
+        .content
+        .source
+        .as_deref()
+        .context("LockedComponentSource missing source field")?;
+    let root = read_component(&parse_file_url(source)?)?;
+
+    let mut prepared = vec![];
+    for (import, dependency) in dependencies {
+        let path = match dependency {
+            LockedDependency::Source(source) => parse_file_url(&source)?,
+            LockedDependency::Digest(digest) => {
+                bail!("dependency {import:?} on content {digest} has not been resolved")
+            }
+            LockedDependency::Registry(reference) => {
+                bail!("dependency {import:?} on {reference} has not been pulled from the registry")
+            }
+        };
+        let bytes = read_component(&path)
+            .with_context(|| format!("failed to prepare dependency {import:?}"))?;
+        prepared.push((import, bytes));
+    }
+
+    let mut key = hex_digest_from_bytes(&root);
+    for (import, bytes) in &prepared {
+        key.push_str(&format!("\n{import}={}", hex_digest_from_bytes(bytes)));
+    }
+    let dir = working_dir
+        .join("composed")
+        .join(hex_digest_from_bytes(key));
+    let composed_path = dir.join("composed.wasm");
+    if !composed_path.exists() {
+        std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {dir:?}"))?;
+        let root_path = write_component(&dir, "component.wasm", &root)?;
+        let mut config = Config::default();
+        for (index, (import, bytes)) in prepared.into_iter().enumerate() {
+            let path = write_component(&dir, &format!("dependency-{index}.wasm"), &bytes)?;
+            config.dependencies.insert(import, Dependency { path });
+        }
+        let composed = ComponentComposer::new(&root_path, &config).compose()?;
+        // Written aside and renamed into place, so that a partly written
+        // composition is never reused.
+        let temp_path = dir.join(format!("composed.wasm.{}", uuid::Uuid::new_v4()));
+        std::fs::write(&temp_path, composed)
+            .with_context(|| format!("failed to write {temp_path:?}"))?;
+        std::fs::rename(&temp_path, &composed_path)
+            .with_context(|| format!("failed to write {composed_path:?}"))?;
+    }
+
+    let url = url::Url::from_file_path(&composed_path)
+        .map_err(|_| anyhow!("Could not construct file URL for {composed_path:?}"))?;
+    component.source.content = ContentRef {
+        source: Some(url.to_string()),
+        ..Default::default()
+    };
+    Ok(())
+}
+
+// Reads the component at `path`, adapted to a component if it is a module.
+fn read_component(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to read component at {}", path.display()))?;
+    Ok(spin_componentize::componentize_if_necessary(&bytes)?.into_owned())
+}
+
+fn write_component(dir: &Path, name: &str, bytes: &[u8]) -> Result<PathBuf> {
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).with_context(|| format!("failed to write {path:?}"))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = r#"(component
+        (import "dep" (instance $dep (export "f" (func (result u32)))))
+        (alias export $dep "f" (func $f))
+        (export "f" (func $f))
+    )"#;
+
+    const DEPENDENCY: &str = r#"(component
+        (core module $m (func (export "f") (result i32) i32.const 42))
+        (core instance $i (instantiate $m))
+        (func (export "f") (result u32) (canon lift (core func $i "f")))
+    )"#;
+
+    fn write_wat(dir: &Path, name: &str, wat: &str) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, wat::parse_str(wat).unwrap()).unwrap();
+        url::Url::from_file_path(path).unwrap().to_string()
+    }
+
+    fn component_depending_on(source: String, dependency: LockedDependency) -> LockedComponent {
+        serde_json::from_value(serde_json::json!({
+            "id": "root",
+            "metadata": { "dependencies": { "dep": dependency } },
+            "source": { "content_type": "application/wasm", "source": source },
+        }))
+        .unwrap()
+    }
+
+    fn call_f(path: &Path) -> u32 {
+        let mut config = wasmtime::Config::new();
+        config.wasm_component_model(true);
+        let engine = wasmtime::Engine::new(&config).unwrap();
+        let component = wasmtime::component::Component::from_file(&engine, path).unwrap();
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance = wasmtime::component::Linker::new(&engine)
+            .instantiate(&mut store, &component)
+            .unwrap();
+        let f = instance
+            .get_typed_func::<(), (u32,)>(&mut store, "f")
+            .unwrap();
+        f.call(&mut store, ()).unwrap().0
+    }
+
+    #[test]
+    fn satisfies_imports_from_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write_wat(dir.path(), "root.wasm", ROOT);
+        let dependency = write_wat(dir.path(), "dependency.wasm", DEPENDENCY);
+        let mut component = component_depending_on(root, LockedDependency::Source(dependency));
+
+        compose_dependencies(&mut component, dir.path()).unwrap();
+        assert!(!component.metadata.contains_key(DEPENDENCIES_KEY.as_ref()));
+        let composed = parse_file_url(component.source.content.source.as_deref().unwrap()).unwrap();
+        assert_eq!(42, call_f(&composed));
+    }
+
+    #[test]
+    fn reuses_unchanged_composition() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write_wat(dir.path(), "root.wasm", ROOT);
+        let dependency = write_wat(dir.path(), "dependency.wasm", DEPENDENCY);
+        let component = component_depending_on(root, LockedDependency::Source(dependency));
+
+        let mut first = component.clone();
+        compose_dependencies(&mut first, dir.path()).unwrap();
+        let mut second = component;
+        compose_dependencies(&mut second, dir.path()).unwrap();
+        assert_eq!(first.source.content.source, second.source.content.source);
+    }
+
+    #[test]
+    fn unpulled_registry_dependency_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write_wat(dir.path(), "root.wasm", ROOT);
+        let mut component = component_depending_on(
+            root,
+            LockedDependency::Registry("ghcr.io/fermyon/dep:1.0.0".to_owned()),
+        );
+        let err = compose_dependencies(&mut component, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("has not been pulled"), "{err:#}");
+    }
+}
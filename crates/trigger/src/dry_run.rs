@@ -0,0 +1,109 @@
+//! Support for `spin up --dry-run`, which loads an application and builds
+//! its trigger executor, then describes what it would serve instead of
+//! serving it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use spin_app::{App, MetadataKey};
+use spin_blob_store::BLOB_CONTAINERS_KEY;
+use spin_key_value::KEY_VALUE_STORES_KEY;
+use spin_messaging::MESSAGING_CHANNELS_KEY;
+use spin_sqlite::DATABASES_KEY;
+
+use crate::{runtime_config::RuntimeConfig, TriggerHooks};
+
+/// Prints the backend of each key-value store, SQLite database, blob
+/// container and messaging channel which the application's components use,
+/// and fails if any of them is not configured.
+pub(crate) struct DryRunHook;
+
+impl TriggerHooks for DryRunHook {
+    fn app_loaded(&mut self, app: &App, runtime_config: &RuntimeConfig) -> Result<()> {
+        let mut missing = vec![];
+        missing.extend(print_labels(
+            "Key-value stores",
+            "key-value store",
+            labels_in_use(app, KEY_VALUE_STORES_KEY)?,
+            runtime_config.key_value_store_backends(),
+        ));
+        missing.extend(print_labels(
+            "SQLite databases",
+            "SQLite database",
+            labels_in_use(app, DATABASES_KEY)?,
+            runtime_config.sqlite_database_backends(),
+        ));
+        missing.extend(print_labels(
+            "Blob containers",
+            "blob container",
+            labels_in_use(app, BLOB_CONTAINERS_KEY)?,
+            runtime_config.blob_container_backends(),
+        ));
+        missing.extend(print_labels(
+            "Messaging channels",
+            "messaging channel",
+            labels_in_use(app, MESSAGING_CHANNELS_KEY)?,
+            runtime_config.messaging_channel_backends(),
+        ));
+        if !missing.is_empty() {
+            bail!("The runtime config does not define {}", missing.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// Prints the routes, or other targets, on which the trigger would invoke
+/// components.
+pub(crate) fn print_routes(trigger_type: &str, routes: &[(String, String)]) {
+    println!("Trigger: {trigger_type}");
+    if routes.is_empty() {
+        println!("  (no routes to list)");
+    }
+    for (route, component_id) in routes {
+        println!("  {route} -> {component_id}");
+    }
+}
+
+// Returns the components which use each label of the given kind.
+fn labels_in_use<T>(app: &App, key: MetadataKey<T>) -> Result<BTreeMap<String, BTreeSet<String>>>
+where
+    T: IntoIterator<Item = String> + DeserializeOwned,
+{
+    let mut labels = BTreeMap::<String, BTreeSet<String>>::new();
+    for component in app.components() {
+        for label in component.get_metadata(key)?.into_iter().flatten() {
+            labels
+                .entry(label)
+                .or_default()
+                .insert(component.id().to_owned());
+        }
+    }
+    Ok(labels)
+}
+
+// Prints each label in use with its backend, and returns descriptions of
+// those which have none.
+fn print_labels(
+    heading: &str,
+    kind: &str,
+    labels: BTreeMap<String, BTreeSet<String>>,
+    backends: BTreeMap<String, String>,
+) -> Vec<String> {
+    if labels.is_empty() {
+        return vec![];
+    }
+    println!("{heading}:");
+    let mut missing = vec![];
+    for (label, components) in labels {
+        let components = components.into_iter().collect::<Vec<_>>().join(", ");
+        match backends.get(&label) {
+            Some(backend) => println!("  {label}: {backend} (used by {components})"),
+            None => {
+                println!("  {label}: not configured (used by {components})");
+                missing.push(format!("{kind} {label:?}"));
+            }
+        }
+    }
+    missing
+}
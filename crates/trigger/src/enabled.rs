@@ -0,0 +1,140 @@
+//! Components which are enabled or disabled when the application is loaded.
+//!
+//! A component's `enabled` expression, such as `{{ admin_ui }}`, is resolved
+//! from the application's variables, so that optional features can be
+//! toggled per environment. A component which is not enabled is removed from
+//! the application along with its triggers.
+
+use std::{collections::HashSet, sync::Mutex};
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::OnceCell;
+use spin_app::locked::LockedApp;
+use spin_config::{Provider, Resolver};
+
+use crate::locked::ENABLED_KEY;
+
+/// Resolves the `enabled` expressions of components.
+pub(crate) struct EnabledResolver {
+    providers: Mutex<Vec<Box<dyn Provider>>>,
+    resolver: OnceCell<Resolver>,
+}
+
+impl EnabledResolver {
+    pub fn new(providers: Vec<Box<dyn Provider>>) -> Self {
+        Self {
+            providers: Mutex::new(providers),
+            resolver: OnceCell::new(),
+        }
+    }
+
+    /// Removes the components of the application which are not enabled, and
+    /// the triggers of those components.
+    pub async fn remove_disabled_components(&self, app: &mut LockedApp) -> Result<()> {
+        let expressions = app
+            .components
+            .iter()
+            .filter_map(|component| {
+                let expression = component.metadata.get(ENABLED_KEY.as_ref())?;
+                Some((component.id.clone(), expression.as_str().map(str::to_owned)))
+            })
+            .collect::<Vec<_>>();
+        if expressions.is_empty() {
+            return Ok(());
+        }
+
+        let resolver = self.resolver(app)?;
+        let mut disabled = HashSet::new();
+        for (id, expression) in expressions {
+            let expression =
+                expression.with_context(|| format!("component {id:?} has invalid `enabled`"))?;
+            let value = resolver
+                .resolve_expression(expression.clone())
+                .await
+                .with_context(|| format!("failed to resolve `enabled` of component {id:?}"))?;
+            if !is_enabled(&value)
+                .with_context(|| format!("invalid `enabled` {expression:?} of component {id:?}"))?
+            {
+                tracing::info!("Component {id:?} is disabled by {expression:?}");
+                disabled.insert(id);
+            }
+        }
+
+        app.components
+            .retain(|component| !disabled.contains(&component.id));
+        app.triggers.retain(|trigger| {
+            trigger
+                .trigger_config
+                .get("component")
+                .and_then(|component| component.as_str())
+                .map_or(true, |component| !disabled.contains(component))
+        });
+        Ok(())
+    }
+
+    fn resolver(&self, app: &LockedApp) -> Result<&Resolver> {
+        self.resolver.get_or_try_init(|| {
+            let mut resolver = Resolver::new(
+                app.variables
+                    .iter()
+                    .map(|(key, var)| (key.clone(), var.clone())),
+            )?;
+            for provider in self.providers.lock().unwrap().drain(..) {
+                resolver.add_provider(provider);
+            }
+            Ok::<_, anyhow::Error>(resolver)
+        })
+    }
+}
+
+fn is_enabled(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        other => bail!("{other:?} is not a boolean"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_enabled_values() {
+        assert!(is_enabled("true").unwrap());
+        assert!(is_enabled(" ON ").unwrap());
+        assert!(!is_enabled("0").unwrap());
+        assert!(!is_enabled("").unwrap());
+        assert!(is_enabled("maybe").is_err());
+    }
+
+    #[tokio::test]
+    async fn removes_disabled_components_and_triggers() {
+        let mut app: LockedApp = serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 0,
+            "variables": { "admin_ui": { "default": "false" } },
+            "triggers": [
+                { "id": "api", "trigger_type": "http", "trigger_config": { "component": "api", "route": "/..." } },
+                { "id": "admin", "trigger_type": "http", "trigger_config": { "component": "admin", "route": "/admin/..." } },
+            ],
+            "components": [
+                { "id": "api", "source": { "content_type": "application/wasm", "content": {} } },
+                {
+                    "id": "admin",
+                    "metadata": { "enabled": "{{ admin_ui }}" },
+                    "source": { "content_type": "application/wasm", "content": {} },
+                },
+            ],
+        }))
+        .unwrap();
+
+        EnabledResolver::new(vec![])
+            .remove_disabled_components(&mut app)
+            .await
+            .unwrap();
+        assert_eq!(1, app.components.len());
+        assert_eq!("api", app.components[0].id);
+        assert_eq!(1, app.triggers.len());
+        assert_eq!("api", app.triggers[0].id);
+    }
+}
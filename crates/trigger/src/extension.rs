@@ -0,0 +1,62 @@
+//! Extension points for platforms which embed Spin.
+//!
+//! A platform can add its own host interfaces to the runtime, alongside the
+//! built-in host components, by implementing [`HostComponentExtension`] and
+//! registering it with
+//! [`TriggerExecutorBuilder::add_host_component_extension`](crate::TriggerExecutorBuilder::add_host_component_extension).
+//!
+//! Each extension has a name, which ties together:
+//!
+//! - its runtime config, the `[extension.<name>]` table, which is available
+//!   through [`RuntimeConfig::extension_config`];
+//! - each component's allowlist for it, the `extensions.<name>` entry of the
+//!   component in the manifest, which is available through
+//!   [`allowed_resources`].
+//!
+//! ```toml
+//! # spin.toml
+//! [[component]]
+//! id = "checkout"
+//! extensions = { billing = ["invoices"] }
+//!
+//! # runtime-config.toml
+//! [extension.billing]
+//! endpoint = "https://billing.internal"
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use spin_app::{AppComponent, DynamicHostComponent, MetadataKey};
+
+use crate::RuntimeConfig;
+
+/// Metadata key for each extension's allowlist, keyed by extension name.
+pub const EXTENSIONS_KEY: MetadataKey<HashMap<String, Vec<String>>> =
+    MetadataKey::new("extensions");
+
+/// A host interface which a platform embedding Spin adds to the runtime.
+///
+/// The host component's [`HostComponent::add_to_linker`](spin_core::HostComponent::add_to_linker)
+/// links the interface's host functions, and its
+/// [`DynamicHostComponent::update_data`] configures each component's instances,
+/// e.g. from the component's [`allowed_resources`].
+pub trait HostComponentExtension: Send + 'static {
+    /// The host component which implements the interface.
+    type Component: DynamicHostComponent;
+
+    /// The name of the extension, which identifies its runtime config and
+    /// components' allowlists for it.
+    fn name(&self) -> &str;
+
+    /// Builds the host component for a trigger using the given runtime config.
+    fn build_component(self, runtime_config: &RuntimeConfig) -> Result<Self::Component>;
+}
+
+/// Returns the resources of the named extension which the component may use,
+/// or `None` if the component does not list the extension in its manifest.
+pub fn allowed_resources(component: &AppComponent, extension: &str) -> Result<Option<Vec<String>>> {
+    Ok(component
+        .get_metadata(EXTENSIONS_KEY)?
+        .and_then(|mut extensions| extensions.remove(extension)))
+}
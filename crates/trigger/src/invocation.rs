@@ -0,0 +1,176 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::Result;
+
+use crate::TriggerHooks;
+
+/// A guest invocation, such as the handling of an HTTP request or a Redis
+/// message, as seen by [`TriggerHooks::before_invocation`] and
+/// [`TriggerHooks::after_invocation`].
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    /// The type of the trigger which invokes the component, e.g. `http`.
+    pub trigger_type: &'static str,
+    /// The ID of the component invoked.
+    pub component_id: String,
+    /// Trigger-specific details of the invocation, such as the HTTP method
+    /// and route, as `(name, value)` pairs.
+    pub metadata: Vec<(&'static str, String)>,
+}
+
+impl Invocation {
+    /// Creates an invocation of the given component by a trigger of the given
+    /// type, with no metadata.
+    pub fn new(trigger_type: &'static str, component_id: impl Into<String>) -> Self {
+        Self {
+            trigger_type,
+            component_id: component_id.into(),
+            metadata: vec![],
+        }
+    }
+
+    /// Adds the given metadata to the invocation.
+    pub fn with_metadata(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.metadata.push((name, value.into()));
+        self
+    }
+
+    /// Returns the value of the named metadata, if set.
+    pub fn metadata(&self, name: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find_map(|(n, value)| (*n == name).then_some(value.as_str()))
+    }
+}
+
+/// The outcome of a completed guest invocation.
+#[derive(Debug)]
+pub struct InvocationOutcome<'a> {
+    /// How long the invocation took, including instantiation.
+    pub duration: Duration,
+    /// The error from the invocation, if it failed.
+    pub error: Option<&'a anyhow::Error>,
+}
+
+impl InvocationOutcome<'_> {
+    /// Returns whether the invocation succeeded.
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The context of the error returned for an invocation which a hook
+/// rejected, so that triggers can distinguish rejections from failures.
+#[derive(Debug)]
+pub struct InvocationRejected;
+
+impl std::fmt::Display for InvocationRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invocation rejected by trigger hook")
+    }
+}
+
+// Runs the invocation between the hooks. If a hook rejects the invocation,
+// it is not run, and the later hooks are not called.
+pub(crate) async fn invoke<T>(
+    hooks: &[Box<dyn TriggerHooks>],
+    invocation: &Invocation,
+    run: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    for hook in hooks {
+        hook.before_invocation(invocation)
+            .map_err(|err| err.context(InvocationRejected))?;
+    }
+    let start = std::time::Instant::now();
+    let result = run.await;
+    let outcome = InvocationOutcome {
+        duration: start.elapsed(),
+        error: result.as_ref().err(),
+    };
+    for hook in hooks {
+        hook.after_invocation(invocation, &outcome);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+
+    use anyhow::{anyhow, bail};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct RecordingHooks {
+        events: Arc<Mutex<Vec<String>>>,
+        reject: bool,
+    }
+
+    impl TriggerHooks for RecordingHooks {
+        fn before_invocation(&self, invocation: &Invocation) -> Result<()> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("before {}", invocation.component_id));
+            if self.reject {
+                bail!("quota exceeded");
+            }
+            Ok(())
+        }
+
+        fn after_invocation(&self, invocation: &Invocation, outcome: &InvocationOutcome) {
+            self.events.lock().unwrap().push(format!(
+                "after {} succeeded={}",
+                invocation.component_id,
+                outcome.succeeded()
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_see_invocation_and_outcome() {
+        let hooks = RecordingHooks::default();
+        let boxed: Vec<Box<dyn TriggerHooks>> = vec![Box::new(hooks.clone())];
+        let invocation = Invocation::new("http", "api").with_metadata("http.route", "/...");
+        assert_eq!(invocation.metadata("http.route"), Some("/..."));
+
+        invoke(&boxed, &invocation, async { Ok(()) }).await.unwrap();
+        invoke(&boxed, &invocation, async { Err::<(), _>(anyhow!("trap")) })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            *hooks.events.lock().unwrap(),
+            [
+                "before api",
+                "after api succeeded=true",
+                "before api",
+                "after api succeeded=false",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_invocations_do_not_run() {
+        let hooks = RecordingHooks {
+            reject: true,
+            ..Default::default()
+        };
+        let boxed: Vec<Box<dyn TriggerHooks>> = vec![Box::new(hooks.clone())];
+        let invocation = Invocation::new("redis", "worker");
+
+        let ran = AtomicBool::new(false);
+        let err = invoke(&boxed, &invocation, async {
+            ran.store(true, Ordering::SeqCst);
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+        assert!(err.downcast_ref::<InvocationRejected>().is_some());
+        assert!(!ran.load(Ordering::SeqCst));
+        assert_eq!(*hooks.events.lock().unwrap(), ["before worker"]);
+    }
+}
@@ -1,29 +1,75 @@
+pub mod app_update;
 pub mod cli;
+pub mod compile_cache;
+mod compose;
+mod dry_run;
+mod enabled;
+pub mod extension;
+mod invocation;
 pub mod loader;
 pub mod locked;
-mod runtime_config;
+pub mod record;
+pub mod runtime_config;
+mod seed;
 mod stdio;
 
-use std::{collections::HashMap, marker::PhantomData, path::PathBuf};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 pub use async_trait::async_trait;
 use indexmap::IndexMap;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::{Mutex, Notify};
 
-use spin_app::{App, AppComponent, AppLoader, AppTrigger, Loader, OwnedApp};
+use spin_app::{locked::LockedApp, App, AppComponent, AppLoader, AppTrigger, Loader, OwnedApp};
 use spin_core::{
-    Config, Engine, EngineBuilder, Instance, InstancePre, ModuleInstance, ModuleInstancePre, Store,
-    StoreBuilder, Wasi,
+    Config, Engine, EngineBuilder, HostComponentDataHandle, Instance, InstancePre, ModuleInstance,
+    ModuleInstancePre, Store, StoreBuilder, Wasi,
+};
+use spin_manifest::{AllowedOutboundHost, ResourceLimits};
+use spin_tasks::{Task, TaskStore, TasksComponent};
+
+use crate::{app_update::AppUpdater, extension::HostComponentExtension, record::Recorder};
+
+pub use crate::invocation::{Invocation, InvocationOutcome, InvocationRejected};
+pub use crate::runtime_config::{
+    builder::RuntimeConfigBuilder,
+    config_provider::{ConfigProvider, ConfigProviderFactory, ConfigProviderOpts},
+    registry_pull::RegistryPullOpts,
+    registry_trust::RegistryTrustOpts,
+    reload::RuntimeConfigReloader,
+    RuntimeConfig,
 };
 
-pub use crate::runtime_config::RuntimeConfig;
+// How often enqueued tasks are checked for, if none are enqueued by this
+// instance in the meantime. Tasks may also be enqueued by other instances
+// which share the task store.
+const TASK_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub enum EitherInstancePre<T> {
     Component(InstancePre<T>),
     Module(ModuleInstancePre<T>),
 }
 
+impl<T> Clone for EitherInstancePre<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Component(pre) => Self::Component(pre.clone()),
+            Self::Module(pre) => Self::Module(pre.clone()),
+        }
+    }
+}
+
 pub enum EitherInstance {
     Component(Instance),
     Module(ModuleInstance),
@@ -42,6 +88,19 @@ pub trait TriggerExecutor: Sized + Send + Sync {
     /// Run the trigger executor.
     async fn run(self, config: Self::RunConfig) -> Result<()>;
 
+    /// Returns a handle with which the application can be updated while the
+    /// executor runs, if the trigger supports it. See [`app_update`].
+    fn app_updater(&mut self) -> Option<AppUpdater<Self>> {
+        None
+    }
+
+    /// Returns the routes, channels or other targets on which the executor
+    /// invokes components, each with the ID of the component it invokes.
+    /// `spin up --dry-run` prints these instead of running the executor.
+    fn routes(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
     /// Make changes to the ExecutionContext using the given Builder.
     fn configure_engine(_builder: &mut EngineBuilder<Self::RuntimeData>) -> Result<()> {
         Ok(())
@@ -62,11 +121,21 @@ pub trait TriggerExecutor: Sized + Send + Sync {
     }
 }
 
+// Adds an embedder's host component, built from the runtime config, to the
+// engine.
+type AddExtension<T> =
+    Box<dyn FnOnce(&mut AppLoader, &mut EngineBuilder<T>, &RuntimeConfig) -> Result<()> + Send>;
+
 pub struct TriggerExecutorBuilder<Executor: TriggerExecutor> {
     loader: AppLoader,
     config: Config,
     hooks: Vec<Box<dyn TriggerHooks>>,
+    extensions: Vec<AddExtension<Executor::RuntimeData>>,
     disable_default_host_components: bool,
+    reload_changed_components: bool,
+    runtime_config_reloader: Option<RuntimeConfigReloader>,
+    recorder: Option<Recorder>,
+    dry_run: bool,
     _phantom: PhantomData<Executor>,
 }
 
@@ -77,7 +146,12 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
             loader: AppLoader::new(loader),
             config: Default::default(),
             hooks: Default::default(),
+            extensions: Default::default(),
             disable_default_host_components: false,
+            reload_changed_components: false,
+            runtime_config_reloader: None,
+            recorder: None,
+            dry_run: false,
             _phantom: PhantomData,
         }
     }
@@ -99,6 +173,55 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
         self
     }
 
+    /// Add the host interface of the given extension to the engine, alongside
+    /// the default host components. See [`extension`] for details.
+    pub fn add_host_component_extension(
+        &mut self,
+        extension: impl HostComponentExtension,
+    ) -> &mut Self {
+        self.extensions.push(Box::new(
+            move |loader: &mut AppLoader,
+                  builder: &mut EngineBuilder<Executor::RuntimeData>,
+                  runtime_config: &RuntimeConfig| {
+                let name = extension.name().to_owned();
+                let component = extension
+                    .build_component(runtime_config)
+                    .with_context(|| format!("Failed to build host extension {name:?}"))?;
+                loader.add_dynamic_host_component(builder, component)?;
+                Ok(())
+            },
+        ));
+        self
+    }
+
+    /// Before instantiating a component, reload it if its Wasm source file has
+    /// changed since it was loaded, so that rebuilt components take effect
+    /// without restarting the trigger.
+    pub fn reload_changed_components(&mut self) -> &mut Self {
+        self.reload_changed_components = true;
+        self
+    }
+
+    /// Build an executor which will not serve anything, but only describe
+    /// the application, so doesn't prepare instances for it.
+    pub fn dry_run(&mut self) -> &mut Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Let the given reloader apply changes to the runtime config to the
+    /// default host components while the trigger runs.
+    pub fn runtime_config_reloader(&mut self, reloader: RuntimeConfigReloader) -> &mut Self {
+        self.runtime_config_reloader = Some(reloader);
+        self
+    }
+
+    /// Record the events which the trigger receives with the given recorder.
+    pub fn record_events(&mut self, recorder: Recorder) -> &mut Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
     pub async fn build(
         mut self,
         app_uri: String,
@@ -108,6 +231,17 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
     where
         Executor::TriggerConfig: DeserializeOwned,
     {
+        // The app is inspected before the engine is built, as that depends
+        // on the app's limits, but can only be validated once the host
+        // components have been added.
+        let locked_app = self.loader.load_locked_app(&app_uri).await?;
+        let component_limits = resolve_component_limits(&locked_app, &runtime_config)?;
+        if component_limits.values().any(|l| l.max_fuel.is_some()) {
+            // Fuel metering slows execution, so is only enabled if needed.
+            self.config.consume_fuel(true);
+        }
+
+        let mut task_store = None;
         let engine = {
             let mut builder = Engine::builder(&self.config)?;
 
@@ -115,33 +249,78 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
                 builder.add_host_component(outbound_redis::OutboundRedisComponent)?;
                 builder.add_host_component(outbound_pg::OutboundPg::default())?;
                 builder.add_host_component(outbound_mysql::OutboundMysql::default())?;
-                self.loader.add_dynamic_host_component(
-                    &mut builder,
+                let (key_value_component, key_value) =
                     runtime_config::key_value::build_key_value_component(
                         &runtime_config,
                         &init_data.kv,
+                        &init_data.kv_seed,
                     )
-                    .await?,
+                    .await?;
+                self.loader
+                    .add_dynamic_host_component(&mut builder, key_value_component)?;
+                let (sqlite_component, sqlite) =
+                    runtime_config::sqlite::build_component(&runtime_config, &init_data.sqlite)?;
+                self.loader
+                    .add_dynamic_host_component(&mut builder, sqlite_component)?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    outbound_http::OutboundHttpComponent::new(
+                        &runtime_config.outbound_http_config()?,
+                    )?,
+                )?;
+                let config =
+                    spin_config::ConfigHostComponent::new(runtime_config.config_providers()?);
+                self.loader
+                    .add_dynamic_host_component(&mut builder, config.clone())?;
+                if let Some(reloader) = &self.runtime_config_reloader {
+                    reloader.set_components(runtime_config::reload::ReloadableComponents {
+                        config,
+                        key_value,
+                        sqlite,
+                    });
+                }
+                self.loader
+                    .add_dynamic_host_component(&mut builder, spin_observe::ObserveComponent)?;
+                builder.add_host_component(runtime_config::wasi_nn::build_component(
+                    &runtime_config,
+                )?)?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    runtime_config::smtp::build_component(&runtime_config)?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    runtime_config::sqlite::build_component(&runtime_config, &init_data.sqlite)?,
+                    runtime_config::blob_store::build_component(&runtime_config)?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    outbound_http::OutboundHttpComponent,
+                    runtime_config::messaging::build_component(&runtime_config)?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    spin_config::ConfigHostComponent::new(runtime_config.config_providers()),
+                    runtime_config::lock::build_component(&runtime_config)?,
                 )?;
+                let tasks = runtime_config::tasks::build_component(&runtime_config)?;
+                let store = tasks.store();
+                let handle = self
+                    .loader
+                    .add_dynamic_host_component(&mut builder, tasks)?;
+                task_store = Some((store, handle));
+            }
+
+            for add_extension in self.extensions.drain(..) {
+                add_extension(&mut self.loader, &mut builder, &runtime_config)?;
             }
 
             Executor::configure_engine(&mut builder)?;
             builder.build()
         };
 
-        let app = self.loader.load_owned_app(app_uri).await?;
+        let app = self
+            .loader
+            .load_owned_locked_app(app_uri, locked_app)
+            .await?;
+        let component_outbound_addrs = resolve_outbound_addrs(app.borrowed()).await?;
 
         let app_name = app.borrowed().require_metadata(locked::NAME_KEY)?;
 
@@ -149,8 +328,22 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
             .iter_mut()
             .try_for_each(|h| h.app_loaded(app.borrowed(), &runtime_config))?;
 
+        let mut engine = TriggerAppEngine::new(engine, app_name, app, self.hooks).await?;
+        engine.component_limits = component_limits;
+        engine.component_outbound_addrs = component_outbound_addrs;
+        engine.recorder = self.recorder;
+        engine.task_store = task_store;
+        if self.reload_changed_components {
+            engine.enable_component_reloads();
+        }
+        if !self.dry_run {
+            engine.enable_instance_pools(&runtime_config).await;
+        }
+        engine.enable_instance_reuse(&runtime_config)?;
+        engine.runtime_config = runtime_config;
+
         // Run trigger executor
-        Executor::new(TriggerAppEngine::new(engine, app_name, app, self.hooks).await?).await
+        Executor::new(engine).await
     }
 }
 
@@ -158,6 +351,9 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
 #[derive(Default)] // TODO: the implementation of Default is only for tests - would like to get rid of
 pub struct HostComponentInitData {
     kv: Vec<(String, String)>,
+    /// Key value entries which are set only if their keys are not yet in the
+    /// store.
+    kv_seed: Vec<(String, String)>,
     sqlite: Vec<String>,
 }
 
@@ -175,6 +371,79 @@ pub struct TriggerAppEngine<Executor: TriggerExecutor> {
     trigger_configs: Vec<Executor::TriggerConfig>,
     // Map of {Component ID -> InstancePre} for each component.
     component_instance_pres: HashMap<String, EitherInstancePre<Executor::RuntimeData>>,
+    // Map of {Component ID -> reloadable InstancePre} for each component
+    // which is reloaded when its source changes. These components are not in
+    // `component_instance_pres`.
+    component_reloads: HashMap<String, Mutex<ComponentReload<Executor::RuntimeData>>>,
+    // Map of {Component ID -> InstancePool} for each component which keeps
+    // instances ready ahead of requests.
+    instance_pools: HashMap<String, InstancePool<Executor::RuntimeData>>,
+    // Notified when an instance is taken from a pool.
+    instance_pool_refill: Notify,
+    // Map of {Component ID -> InstanceReuse} for each component whose
+    // instances handle more than one invocation.
+    instance_reuse: HashMap<String, InstanceReuse<Executor::RuntimeData>>,
+    // Map of {Component ID -> ResourceLimits} for each component.
+    component_limits: HashMap<String, ResourceLimits>,
+    // Map of {Component ID -> socket addresses} for each component which
+    // may open sockets.
+    component_outbound_addrs: HashMap<String, Arc<OutboundAddrs>>,
+    // Map of {Component ID -> host environment variables} for each component
+    // which forwards variables from the host.
+    component_env_passthrough: HashMap<String, Vec<(String, String)>>,
+    // Records the events the trigger receives, if enabled.
+    recorder: Option<Recorder>,
+    // The store of tasks enqueued by components, and the handle of the
+    // tasks host component's data, if the component is enabled.
+    task_store: Option<(Arc<TaskStore>, HostComponentDataHandle<TasksComponent>)>,
+    // The runtime config with which the app was built, for triggers which
+    // read their own sections of it.
+    runtime_config: RuntimeConfig,
+}
+
+// A component InstancePre which is replaced when the component's source file
+// is modified.
+struct ComponentReload<T> {
+    source: PathBuf,
+    modified: Option<SystemTime>,
+    pre: EitherInstancePre<T>,
+}
+
+// Stores and instances created ahead of time for a component, so that
+// requests need not wait for instantiation.
+struct InstancePool<T> {
+    size: usize,
+    ready: std::sync::Mutex<Vec<(EitherInstance, Store<T>)>>,
+}
+
+// The instances of a component which have handled invocations and may handle
+// more, and the bounds on how long they are reused.
+struct InstanceReuse<T> {
+    max_requests: Option<u32>,
+    max_age: Option<Duration>,
+    idle: std::sync::Mutex<Vec<ReusableInstance<T>>>,
+}
+
+impl<T> InstanceReuse<T> {
+    // Returns whether the instance may handle another invocation.
+    fn allows(&self, instance: &ReusableInstance<T>) -> bool {
+        self.max_requests.map_or(true, |max| instance.uses < max)
+            && self
+                .max_age
+                .map_or(true, |max| instance.created.elapsed() < max)
+    }
+}
+
+/// A Store and Instance prepared with
+/// [`TriggerAppEngine::prepare_reusable_instance`], which may have handled
+/// earlier invocations.
+pub struct ReusableInstance<T> {
+    pub instance: EitherInstance,
+    pub store: Store<T>,
+    component_id: String,
+    // The number of invocations the instance has handled.
+    uses: u32,
+    created: std::time::Instant,
 }
 
 impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
@@ -203,6 +472,7 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             .collect::<Result<IndexMap<_, _>>>()?;
 
         let mut component_instance_pres = HashMap::default();
+        let mut component_env_passthrough = HashMap::default();
         for component in app.borrowed().components() {
             let id = component.id();
             component_instance_pres.insert(
@@ -211,6 +481,10 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
                     .await
                     .with_context(|| format!("Failed to instantiate component '{id}'"))?,
             );
+            let passthrough = passthrough_env(&component)?;
+            if !passthrough.is_empty() {
+                component_env_passthrough.insert(id.to_owned(), passthrough);
+            }
         }
 
         Ok(Self {
@@ -220,9 +494,255 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             hooks,
             trigger_configs: trigger_configs.into_values().collect(),
             component_instance_pres,
+            component_reloads: HashMap::default(),
+            instance_pools: HashMap::default(),
+            instance_pool_refill: Notify::new(),
+            instance_reuse: HashMap::default(),
+            component_limits: HashMap::default(),
+            component_outbound_addrs: HashMap::default(),
+            component_env_passthrough,
+            recorder: None,
+            task_store: None,
+            runtime_config: RuntimeConfig::default(),
         })
     }
 
+    // Makes each component with a local source file reload when that file
+    // is modified.
+    fn enable_component_reloads(&mut self) {
+        let sources = self
+            .app()
+            .components()
+            .filter_map(|component| {
+                let source = component.source().content.source.as_deref()?;
+                Some((component.id().to_owned(), parse_file_url(source).ok()?))
+            })
+            .collect::<Vec<_>>();
+        for (id, source) in sources {
+            let Some(pre) = self.component_instance_pres.remove(&id) else {
+                continue;
+            };
+            let reload = ComponentReload {
+                modified: modified_time(&source),
+                source,
+                pre,
+            };
+            self.component_reloads.insert(id, Mutex::new(reload));
+        }
+    }
+
+    // Creates an instance pool for each component with a configured pool
+    // size, and fills the pools. Components which are reloaded when their
+    // source changes are not pooled, as their instances may go stale.
+    async fn enable_instance_pools(&mut self, runtime_config: &RuntimeConfig) {
+        let sizes = self
+            .app()
+            .components()
+            .map(|component| {
+                let id = component.id().to_owned();
+                let size = runtime_config.instance_pool_size(&id);
+                (id, size)
+            })
+            .collect::<Vec<_>>();
+        for (id, size) in sizes {
+            if size == 0 || self.component_reloads.contains_key(&id) {
+                continue;
+            }
+            let pool = InstancePool {
+                size,
+                ready: Default::default(),
+            };
+            self.instance_pools.insert(id, pool);
+        }
+        self.fill_instance_pools().await;
+    }
+
+    // Keeps the instances of each component which is configured for reuse
+    // after they handle invocations. Components which are reloaded when their
+    // source changes are not reused, as their instances may go stale.
+    fn enable_instance_reuse(&mut self, runtime_config: &RuntimeConfig) -> Result<()> {
+        let ids = self
+            .app()
+            .components()
+            .map(|component| component.id().to_owned())
+            .collect::<Vec<_>>();
+        for id in ids {
+            let Some(opts) = runtime_config.instance_reuse(&id)? else {
+                continue;
+            };
+            if self.component_reloads.contains_key(&id) {
+                continue;
+            }
+            let reuse = InstanceReuse {
+                max_requests: opts.max_requests,
+                max_age: opts.max_age_secs.map(Duration::from_secs),
+                idle: Default::default(),
+            };
+            self.instance_reuse.insert(id, reuse);
+        }
+        Ok(())
+    }
+
+    // Tops up each instance pool to its configured size.
+    async fn fill_instance_pools(&self) {
+        for (component_id, pool) in &self.instance_pools {
+            loop {
+                let ready = pool.ready.lock().unwrap().len();
+                if ready >= pool.size {
+                    break;
+                }
+                match self.new_instance(component_id).await {
+                    Ok(instance) => pool.ready.lock().unwrap().push(instance),
+                    Err(err) => {
+                        // Requests will instantiate the component as usual,
+                        // and report the error if it persists.
+                        tracing::warn!(
+                            "Failed to fill instance pool of component {component_id:?}: {err:?}"
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replaces the instances taken from instance pools, running until
+    /// dropped. A trigger which uses [`Self::prepare_instance`] should run
+    /// this alongside handling its events, so that the pools stay filled.
+    pub async fn maintain_instance_pools(&self) {
+        if self.instance_pools.is_empty() {
+            return std::future::pending().await;
+        }
+        loop {
+            self.instance_pool_refill.notified().await;
+            self.fill_instance_pools().await;
+        }
+    }
+
+    /// Runs the tasks enqueued for this trigger's components as they become
+    /// due, running until dropped. A trigger should run this alongside
+    /// handling its events.
+    pub async fn run_background_tasks(&self) {
+        let Some((task_store, _)) = &self.task_store else {
+            return std::future::pending().await;
+        };
+        let component_ids = self
+            .trigger_configs()
+            .filter_map(|(trigger, _)| Some(trigger.component().ok()?.id().to_owned()))
+            .collect::<Vec<_>>();
+        loop {
+            match task_store.claim_due(&self.app_name, &component_ids).await {
+                Ok(due) => {
+                    futures::future::join_all(
+                        due.iter().map(|task| self.run_task(task_store, task)),
+                    )
+                    .await;
+                }
+                Err(err) => tracing::warn!("Failed to take due tasks: {err:?}"),
+            }
+            tokio::select! {
+                _ = task_store.enqueued() => {}
+                _ = tokio::time::sleep(TASK_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn run_task(&self, task_store: &TaskStore, task: &Task) {
+        let invocation = Invocation::new(Executor::TRIGGER_TYPE, &task.component_id)
+            .with_metadata("task_id", &task.id)
+            .with_metadata("attempt", task.attempt.to_string());
+        let result = self.invoke(&invocation, self.handle_task(task)).await;
+        if let Err(err) = &result {
+            tracing::warn!(
+                "Task {:?} of component {:?} failed on attempt {}: {err:?}",
+                task.id,
+                task.component_id,
+                task.attempt
+            );
+        }
+        match task_store.finish(task, result.is_ok()).await {
+            Ok(true) => tracing::info!("Task {:?} will be retried", task.id),
+            Ok(false) => (),
+            Err(err) => tracing::warn!("Failed to update task {:?}: {err:?}", task.id),
+        }
+    }
+
+    async fn handle_task(&self, task: &Task) -> Result<()> {
+        let (instance, mut store) = self.prepare_instance(&task.component_id).await?;
+        let EitherInstance::Component(instance) = instance else {
+            bail!(
+                "component {:?} is a module, which can't handle tasks",
+                task.component_id
+            );
+        };
+        let func = instance
+            .exports(&mut store)
+            .instance("inbound-task")
+            .context("no inbound-task instance found")?
+            .typed_func::<(&str, &[u8], u32), (Result<(), String>,)>("handle-task")?;
+        let (result,) = func
+            .call_async(&mut store, (&task.id, &task.payload, task.attempt))
+            .await?;
+        result.map_err(|err| anyhow!("`handle-task` returned an error: {err}"))
+    }
+
+    // Returns the InstancePre for the given component, first reloading it if
+    // its source has been modified.
+    async fn instance_pre(
+        &self,
+        component_id: &str,
+    ) -> Result<EitherInstancePre<Executor::RuntimeData>> {
+        let Some(reload) = self.component_reloads.get(component_id) else {
+            let pre = self
+                .component_instance_pres
+                .get(component_id)
+                .expect("component_instance_pres missing valid component_id");
+            return Ok(pre.clone());
+        };
+
+        let mut reload = reload.lock().await;
+        let modified = modified_time(&reload.source);
+        if modified != reload.modified {
+            // Don't retry a failed reload until the source changes again.
+            reload.modified = modified;
+            match self.reload_instance_pre(component_id).await {
+                Ok(pre) => {
+                    tracing::info!("Reloaded component {component_id:?}");
+                    reload.pre = pre;
+                }
+                Err(err) => tracing::error!(
+                    "Failed to reload component {component_id:?}, continuing with the previous version: {err:?}"
+                ),
+            }
+        }
+        Ok(reload.pre.clone())
+    }
+
+    async fn reload_instance_pre(
+        &self,
+        component_id: &str,
+    ) -> Result<EitherInstancePre<Executor::RuntimeData>> {
+        let component = self.get_component(component_id)?;
+        let config = self
+            .trigger_configs()
+            .find_map(|(trigger, config)| {
+                (trigger.component().ok()?.id() == component_id).then_some(config)
+            })
+            .with_context(|| format!("no trigger for component {component_id:?}"))?;
+        Executor::instantiate_pre(&self.engine, &component, config).await
+    }
+
+    /// Returns the recorder to which the trigger should record the events it
+    /// receives, if recording is enabled.
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Returns the runtime config with which the app was built.
+    pub fn runtime_config(&self) -> &RuntimeConfig {
+        &self.runtime_config
+    }
+
     /// Returns a reference to the App.
     pub fn app(&self) -> &App {
         self.app.borrowed()
@@ -245,10 +765,83 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
         Ok(builder)
     }
 
-    /// Returns a new Store and Instance for the given component ID.
+    /// Returns a new Store and Instance for the given component ID, taking
+    /// them from the component's instance pool if it has one.
     pub async fn prepare_instance(
         &self,
         component_id: &str,
+    ) -> Result<(EitherInstance, Store<Executor::RuntimeData>)> {
+        if let Some(pool) = self.instance_pools.get(component_id) {
+            let ready = pool.ready.lock().unwrap().pop();
+            spin_telemetry::metrics::record_instance_pool_take(component_id, ready.is_some());
+            self.instance_pool_refill.notify_one();
+            if let Some((instance, mut store)) = ready {
+                // The instance's time limit runs from when it is taken.
+                self.start_deadline(component_id, &mut store);
+                return Ok((instance, store));
+            }
+        }
+        self.new_instance(component_id).await
+    }
+
+    /// Returns a Store and Instance for the given component ID, as
+    /// [`Self::prepare_instance`] does, except that if the component reuses
+    /// instances, they may have handled earlier invocations. Once it has
+    /// handled an invocation successfully, the instance should be returned
+    /// with [`Self::release_instance`]. A reused instance's fuel limit, if
+    /// any, covers all the invocations it handles.
+    pub async fn prepare_reusable_instance(
+        &self,
+        component_id: &str,
+    ) -> Result<ReusableInstance<Executor::RuntimeData>> {
+        if let Some(reuse) = self.instance_reuse.get(component_id) {
+            loop {
+                let Some(mut reused) = reuse.idle.lock().unwrap().pop() else {
+                    break;
+                };
+                // Instances which expired while idle are dropped.
+                if reuse.allows(&reused) {
+                    self.start_deadline(component_id, &mut reused.store);
+                    return Ok(reused);
+                }
+            }
+        }
+        let (instance, store) = self.prepare_instance(component_id).await?;
+        Ok(ReusableInstance {
+            instance,
+            store,
+            component_id: component_id.to_owned(),
+            uses: 0,
+            created: std::time::Instant::now(),
+        })
+    }
+
+    /// Returns an instance which has handled an invocation successfully, so
+    /// that it can handle later ones if its component reuses instances and
+    /// the instance is within the component's bounds. Otherwise the instance
+    /// is dropped.
+    pub fn release_instance(&self, mut instance: ReusableInstance<Executor::RuntimeData>) {
+        let Some(reuse) = self.instance_reuse.get(&instance.component_id) else {
+            return;
+        };
+        instance.uses += 1;
+        // The tasks enqueued by the invocation which the instance handled
+        // may now run.
+        if let Some((_, handle)) = self.task_store {
+            instance
+                .store
+                .host_components_data()
+                .get_or_insert(handle)
+                .invocation_finished();
+        }
+        if reuse.allows(&instance) {
+            reuse.idle.lock().unwrap().push(instance);
+        }
+    }
+
+    async fn new_instance(
+        &self,
+        component_id: &str,
     ) -> Result<(EitherInstance, Store<Executor::RuntimeData>)> {
         let store_builder = self.store_builder(component_id, Wasi::new_preview2())?;
         self.prepare_instance_with_store(component_id, store_builder)
@@ -262,16 +855,31 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
         mut store_builder: StoreBuilder,
     ) -> Result<(EitherInstance, Store<Executor::RuntimeData>)> {
         let component = self.get_component(component_id)?;
+        let limits = self.component_limits.get(component_id);
 
         // Build Store
         component.apply_store_config(&mut store_builder).await?;
+        if let Some(vars) = self.component_env_passthrough.get(component_id) {
+            store_builder.env(vars)?;
+        }
+        if let Some(addrs) = self.component_outbound_addrs.get(component_id) {
+            store_builder.allow_outbound_addrs(&addrs.current())?;
+        }
+        // Sizes which overflow are refused when the limits are resolved.
+        if let Some(max_memory_size) = limits
+            .and_then(|l| l.max_memory_mb)
+            .and_then(memory_size_bytes)
+        {
+            store_builder.max_memory_size(max_memory_size);
+        }
+        if let Some(max_fuel) = limits.and_then(|l| l.max_fuel) {
+            store_builder.fuel(max_fuel);
+        }
         let mut store = store_builder.build()?;
 
         // Instantiate
-        let pre = self
-            .component_instance_pres
-            .get(component_id)
-            .expect("component_instance_pres missing valid component_id");
+        let instantiation_start = std::time::Instant::now();
+        let pre = self.instance_pre(component_id).await?;
 
         let instance = match pre {
             EitherInstancePre::Component(pre) => pre
@@ -290,10 +898,37 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
                 self.app_name, component_id
             )
         })?;
+        spin_telemetry::metrics::record_instantiation(component_id, instantiation_start.elapsed());
+        self.start_deadline(component_id, &mut store);
 
         Ok((instance, store))
     }
 
+    // Sets the store's deadline from the component's time limit, if any.
+    fn start_deadline(&self, component_id: &str, store: &mut Store<Executor::RuntimeData>) {
+        let timeout = self
+            .component_limits
+            .get(component_id)
+            .and_then(|l| l.timeout_ms);
+        if let Some(timeout_ms) = timeout {
+            store.set_deadline(std::time::Instant::now() + Duration::from_millis(timeout_ms));
+        }
+    }
+
+    /// Runs a guest invocation, calling each hook's
+    /// [`TriggerHooks::before_invocation`] before it and
+    /// [`TriggerHooks::after_invocation`] once it completes. `run` should
+    /// include the component's instantiation. If a hook rejects the
+    /// invocation, `run` is not run, and the error has [`InvocationRejected`]
+    /// as its context.
+    pub async fn invoke<T>(
+        &self,
+        invocation: &Invocation,
+        run: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        invocation::invoke(&self.hooks, invocation, run).await
+    }
+
     pub fn get_component(&self, component_id: &str) -> Result<AppComponent> {
         self.app().get_component(component_id).with_context(|| {
             format!(
@@ -324,6 +959,17 @@ pub trait TriggerHooks: Send + Sync {
     ) -> Result<()> {
         Ok(())
     }
+
+    /// Called before each guest invocation, before the component is
+    /// instantiated. Returning an error rejects the invocation, e.g. if the
+    /// caller is not authorized or is over quota.
+    fn before_invocation(&self, invocation: &Invocation) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when each guest invocation which was not rejected completes,
+    /// whether or not it succeeded.
+    fn after_invocation(&self, invocation: &Invocation, outcome: &InvocationOutcome) {}
 }
 
 impl TriggerHooks for () {}
@@ -335,19 +981,203 @@ pub fn parse_file_url(url: &str) -> Result<PathBuf> {
         .map_err(|_| anyhow!("Invalid file URL path: {url:?}"))
 }
 
+// Returns the resource limits of each component. Limits set by the runtime
+// config take precedence over those set in the application manifest.
+fn resolve_component_limits(
+    app: &LockedApp,
+    runtime_config: &RuntimeConfig,
+) -> Result<HashMap<String, ResourceLimits>> {
+    app.components
+        .iter()
+        .map(|component| {
+            let manifest_limits = component
+                .metadata
+                .get(locked::LIMITS_KEY.as_ref())
+                .map(ResourceLimits::deserialize)
+                .transpose()
+                .with_context(|| format!("invalid limits for component {:?}", component.id))?;
+            let limits = runtime_config
+                .component_limits(&component.id)
+                .or(&manifest_limits.unwrap_or_default());
+            if let Some(max_memory_mb) = limits.max_memory_mb {
+                ensure!(
+                    memory_size_bytes(max_memory_mb).is_some(),
+                    "max_memory_mb of {max_memory_mb} for component {:?} is too large",
+                    component.id
+                );
+            }
+            Ok((component.id.clone(), limits))
+        })
+        .collect()
+}
+
+fn memory_size_bytes(mb: u64) -> Option<usize> {
+    mb.checked_mul(1024 * 1024)
+        .and_then(|size| usize::try_from(size).ok())
+}
+
+// Returns the socket addresses which each component may connect to. Host
+// names are resolved when the trigger starts, so that failures are reported
+// early, and then again in the background as instances are prepared.
+async fn resolve_outbound_addrs(app: &App) -> Result<HashMap<String, Arc<OutboundAddrs>>> {
+    let mut component_addrs = HashMap::new();
+    for component in app.components() {
+        let hosts = component
+            .get_metadata(locked::ALLOWED_OUTBOUND_HOSTS_KEY)?
+            .unwrap_or_default()
+            .iter()
+            .map(|host| host.parse())
+            .collect::<Result<Vec<AllowedOutboundHost>, _>>()?;
+        if hosts.is_empty() {
+            continue;
+        }
+        let addrs = OutboundAddrs::resolve(component.id(), hosts).await;
+        component_addrs.insert(component.id().to_owned(), Arc::new(addrs));
+    }
+    Ok(component_addrs)
+}
+
+// How long resolved outbound host addresses are used before the host names
+// are resolved again.
+const OUTBOUND_ADDRS_TTL: Duration = Duration::from_secs(30);
+
+// How long resolving an outbound host name may take, after which the host's
+// last known addresses are kept.
+const OUTBOUND_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The socket addresses of the hosts a component may connect to. Instances
+// are given the last known addresses without waiting for DNS. Once those
+// are older than `OUTBOUND_ADDRS_TTL`, the host names are resolved again in
+// the background, so that instances follow DNS changes; if a host can't be
+// resolved, its last known addresses are kept.
+struct OutboundAddrs {
+    component_id: String,
+    hosts: Vec<AllowedOutboundHost>,
+    // The addresses of each host, and when they were resolved.
+    resolved: std::sync::Mutex<(Instant, Vec<Vec<SocketAddr>>)>,
+    refreshing: AtomicBool,
+}
+
+impl OutboundAddrs {
+    async fn resolve(component_id: &str, hosts: Vec<AllowedOutboundHost>) -> Self {
+        let addrs = futures::future::join_all(
+            hosts
+                .iter()
+                .map(|host| lookup_outbound_host(component_id, host)),
+        )
+        .await
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .collect();
+        Self {
+            component_id: component_id.to_owned(),
+            hosts,
+            resolved: std::sync::Mutex::new((Instant::now(), addrs)),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    // Returns the last known addresses, starting to resolve the host names
+    // again if they are out of date.
+    fn current(self: &Arc<Self>) -> Vec<SocketAddr> {
+        let resolved = self.resolved.lock().unwrap();
+        if resolved.0.elapsed() >= OUTBOUND_ADDRS_TTL
+            && !self.refreshing.swap(true, Ordering::AcqRel)
+        {
+            let this = self.clone();
+            tokio::spawn(async move {
+                this.refresh().await;
+                this.refreshing.store(false, Ordering::Release);
+            });
+        }
+        resolved.1.iter().flatten().copied().collect()
+    }
+
+    async fn refresh(&self) {
+        let lookups = self
+            .hosts
+            .iter()
+            .map(|host| lookup_outbound_host(&self.component_id, host));
+        let results = futures::future::join_all(lookups).await;
+        let mut resolved = self.resolved.lock().unwrap();
+        for (addrs, result) in resolved.1.iter_mut().zip(results) {
+            if let Some(result) = result {
+                *addrs = result;
+            }
+        }
+        resolved.0 = Instant::now();
+    }
+}
+
+// Resolves an outbound host name, returning `None` if that fails or takes
+// longer than `OUTBOUND_LOOKUP_TIMEOUT`.
+async fn lookup_outbound_host(
+    component_id: &str,
+    host: &AllowedOutboundHost,
+) -> Option<Vec<SocketAddr>> {
+    let lookup = tokio::net::lookup_host((host.host.as_str(), host.port));
+    let err = match tokio::time::timeout(OUTBOUND_LOOKUP_TIMEOUT, lookup).await {
+        Ok(Ok(addrs)) => return Some(addrs.collect()),
+        Ok(Err(err)) => err.to_string(),
+        Err(_) => format!("timed out after {OUTBOUND_LOOKUP_TIMEOUT:?}"),
+    };
+    tracing::warn!(
+        "Component {component_id:?} may not connect to {host}: failed to resolve host: {err}"
+    );
+    None
+}
+
+// Returns the host environment variables which the component forwards, other
+// than those which it sets itself.
+fn passthrough_env(component: &AppComponent) -> Result<Vec<(String, String)>> {
+    let patterns = component
+        .get_metadata(locked::ENVIRONMENT_PASSTHROUGH_KEY)?
+        .unwrap_or_default();
+    if patterns.is_empty() {
+        return Ok(vec![]);
+    }
+    let explicit = component.environment().map(|(k, _)| k).collect::<Vec<_>>();
+    Ok(std::env::vars()
+        .filter(|(name, _)| !explicit.contains(&name))
+        .filter(|(name, _)| patterns.iter().any(|p| env_name_matches(p, name)))
+        .collect())
+}
+
+// Whether an `environment_passthrough` entry matches the variable name. A
+// bare `*`, which would forward the whole environment, matches nothing.
+fn env_name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => !prefix.is_empty() && name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
 pub fn decode_preinstantiation_error(e: anyhow::Error) -> anyhow::Error {
     let err_text = e.to_string();
 
     if err_text.contains("unknown import") && err_text.contains("has not been defined") {
         // TODO: how to maintain this list?
         let sdk_imported_interfaces = &[
+            "blob-store",
             "config",
             "http",
             "key-value",
+            "lock",
+            "messaging",
             "mysql",
+            "observe",
             "postgres",
             "redis",
+            "smtp",
             "sqlite",
+            "tasks",
+            "wasi-nn",
         ];
 
         if sdk_imported_interfaces
@@ -363,3 +1193,18 @@ pub fn decode_preinstantiation_error(e: anyhow::Error) -> anyhow::Error {
 
     e
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_name_matches_exact_names_and_prefixes() {
+        assert!(env_name_matches("LANG", "LANG"));
+        assert!(!env_name_matches("LANG", "LANGUAGE"));
+        assert!(env_name_matches("AWS_*", "AWS_REGION"));
+        assert!(env_name_matches("AWS_*", "AWS_"));
+        assert!(!env_name_matches("AWS_*", "AWSREGION"));
+        assert!(!env_name_matches("*", "AWS_REGION"));
+    }
+}
@@ -11,11 +11,16 @@ use spin_app::{
 use spin_core::StoreBuilder;
 use tokio::fs;
 
-use crate::parse_file_url;
+use crate::{
+    compile_cache::CompileCache, compose, enabled::EnabledResolver, locked::TMP_DIR_KEY,
+    parse_file_url,
+};
 
 pub struct TriggerLoader {
     working_dir: PathBuf,
     allow_transient_write: bool,
+    compile_cache: Option<CompileCache>,
+    enabled: EnabledResolver,
 }
 
 impl TriggerLoader {
@@ -23,8 +28,22 @@ impl TriggerLoader {
         Self {
             working_dir: working_dir.into(),
             allow_transient_write,
+            compile_cache: None,
+            enabled: EnabledResolver::new(vec![]),
         }
     }
+
+    /// Loads compiled components and modules from the given cache, compiling
+    /// and storing those which are not in it.
+    pub fn enable_compile_cache(&mut self, cache: CompileCache) {
+        self.compile_cache = Some(cache);
+    }
+
+    /// Resolves the `enabled` expressions of components with the given
+    /// config providers, rather than from the defaults of variables alone.
+    pub fn set_config_providers(&mut self, providers: Vec<Box<dyn spin_config::Provider>>) {
+        self.enabled = EnabledResolver::new(providers);
+    }
 }
 
 #[async_trait]
@@ -33,9 +52,22 @@ impl Loader for TriggerLoader {
         let path = parse_file_url(url)?;
         let contents =
             std::fs::read(&path).with_context(|| format!("failed to read manifest at {path:?}"))?;
-        let app =
+        let mut app: LockedApp =
             serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
-        Ok(app)
+        self.enabled.remove_disabled_components(&mut app).await?;
+        let working_dir = self.working_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            for component in &mut app.components {
+                compose::compose_dependencies(component, &working_dir).with_context(|| {
+                    format!(
+                        "failed to compose component {:?} with its dependencies",
+                        component.id
+                    )
+                })?;
+            }
+            Ok(app)
+        })
+        .await?
     }
 
     async fn load_component(
@@ -49,17 +81,18 @@ impl Loader for TriggerLoader {
             .as_ref()
             .context("LockedComponentSource missing source field")?;
         let path = parse_file_url(source)?;
-        spin_core::Component::new(
-            engine,
-            spin_componentize::componentize_if_necessary(&fs::read(&path).await.with_context(
-                || {
-                    format!(
-                        "failed to read component source from disk at path '{}'",
-                        path.display()
-                    )
-                },
-            )?)?,
-        )
+        let bytes = spin_componentize::componentize_if_necessary(
+            &fs::read(&path).await.with_context(|| {
+                format!(
+                    "failed to read component source from disk at path '{}'",
+                    path.display()
+                )
+            })?,
+        )?;
+        match &self.compile_cache {
+            Some(cache) => cache.load_component(engine, &bytes),
+            None => spin_core::Component::new(engine, bytes),
+        }
         .with_context(|| format!("loading module {path:?}"))
     }
 
@@ -74,8 +107,14 @@ impl Loader for TriggerLoader {
             .as_ref()
             .context("LockedComponentSource missing source field")?;
         let path = parse_file_url(source)?;
-        spin_core::Module::from_file(engine, &path)
-            .with_context(|| format!("loading module {path:?}"))
+        match &self.compile_cache {
+            Some(cache) => {
+                let bytes = fs::read(&path).await?;
+                cache.load_module(engine, &bytes)
+            }
+            None => spin_core::Module::from_file(engine, &path),
+        }
+        .with_context(|| format!("loading module {path:?}"))
     }
 
     async fn mount_files(
@@ -95,12 +134,16 @@ impl Loader for TriggerLoader {
                 "TriggerLoader only supports directory mounts; {source_path:?} is not a directory"
             );
             let guest_path = content_dir.path.clone();
-            if self.allow_transient_write {
+            if content_dir.writable || self.allow_transient_write {
                 store_builder.read_write_preopened_dir(source_path, guest_path)?;
             } else {
                 store_builder.read_only_preopened_dir(source_path, guest_path)?;
             }
         }
+        if let Some(tmp_dir) = component.get_metadata(TMP_DIR_KEY)? {
+            let max_size = tmp_dir.max_size_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+            store_builder.scratch_dir("/tmp".into(), max_size)?;
+        }
         Ok(())
     }
 }
@@ -1,9 +1,13 @@
 #![allow(dead_code)] // Refactor WIP
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, bail, Context, Result};
-use outbound_http::ALLOWED_HTTP_HOSTS_KEY;
+use outbound_http::{ALLOWED_HTTP_HOSTS_KEY, OUTBOUND_HTTP_CACHE_KEY};
+use serde::{Deserialize, Serialize};
 use spin_app::{
     locked::{
         self, ContentPath, ContentRef, LockedApp, LockedComponent, LockedComponentSource,
@@ -12,18 +16,46 @@ use spin_app::{
     values::{ValuesMap, ValuesMapBuilder},
     MetadataKey,
 };
+use spin_blob_store::BLOB_CONTAINERS_KEY;
 use spin_key_value::KEY_VALUE_STORES_KEY;
 use spin_manifest::{
-    Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger, CoreComponent,
-    HttpConfig, HttpTriggerConfiguration, RedisConfig, TriggerConfig,
+    Application, ApplicationInformation, ApplicationOrigin, ApplicationTrigger,
+    ComponentDependency, CoreComponent, HttpConfig, HttpTriggerConfiguration, RedisConfig,
+    ResourceLimits, TmpDir, TriggerConfig,
 };
+use spin_messaging::MESSAGING_CHANNELS_KEY;
 use spin_sqlite::DATABASES_KEY;
 
+use crate::extension::EXTENSIONS_KEY;
+
 pub const NAME_KEY: MetadataKey = MetadataKey::new("name");
 pub const VERSION_KEY: MetadataKey = MetadataKey::new("version");
 pub const DESCRIPTION_KEY: MetadataKey = MetadataKey::new("description");
 pub const BINDLE_VERSION_KEY: MetadataKey = MetadataKey::new("bindle_version");
 pub const ORIGIN_KEY: MetadataKey = MetadataKey::new("origin");
+pub const LIMITS_KEY: MetadataKey<ResourceLimits> = MetadataKey::new("limits");
+pub const TMP_DIR_KEY: MetadataKey<TmpDir> = MetadataKey::new("tmp_dir");
+pub const ALLOWED_OUTBOUND_HOSTS_KEY: MetadataKey<Vec<String>> =
+    MetadataKey::new("allowed_outbound_hosts");
+pub const ENVIRONMENT_PASSTHROUGH_KEY: MetadataKey<Vec<String>> =
+    MetadataKey::new("environment_passthrough");
+pub const DEPENDENCIES_KEY: MetadataKey<HashMap<String, LockedDependency>> =
+    MetadataKey::new("dependencies");
+pub const ENABLED_KEY: MetadataKey = MetadataKey::new("enabled");
+
+/// A component which satisfies an import of a locked component, to be
+/// composed with it when it is loaded.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockedDependency {
+    /// A component file, by `file://` URL.
+    Source(String),
+    /// A component stored with a pushed application, by content digest.
+    Digest(String),
+    /// A component published to a registry, which must be pulled and
+    /// replaced with its `Source` before the application is run.
+    Registry(String),
+}
 
 const WASM_CONTENT_TYPE: &str = "application/wasm";
 
@@ -34,6 +66,39 @@ pub fn build_locked_app(app: Application, working_dir: impl Into<PathBuf>) -> Re
     LockedAppBuilder { working_dir }.build(app)
 }
 
+/// Removes the `environment_passthrough` entries of the locked app's
+/// components, returning the IDs of the components which had any. Apps
+/// pulled from a registry don't read the host environment unless the
+/// operator allows it.
+pub fn remove_environment_passthrough(locked_app: &mut LockedApp) -> Vec<String> {
+    locked_app
+        .components
+        .iter_mut()
+        .filter(|component| {
+            component
+                .metadata
+                .remove(ENVIRONMENT_PASSTHROUGH_KEY.as_ref())
+                .is_some()
+        })
+        .map(|component| component.id.clone())
+        .collect()
+}
+
+/// Writes the locked app to `spin.lock` in the given working directory,
+/// returning the file URL from which a trigger loads it.
+pub async fn write_locked_app(locked_app: &LockedApp, working_dir: &Path) -> Result<String> {
+    let locked_path = working_dir.join("spin.lock");
+    let locked_app_contents =
+        serde_json::to_vec_pretty(&locked_app).context("failed to serialize locked app")?;
+    tokio::fs::write(&locked_path, locked_app_contents)
+        .await
+        .with_context(|| format!("failed to write {:?}", locked_path))?;
+    let locked_url = url::Url::from_file_path(&locked_path)
+        .map_err(|_| anyhow!("cannot convert to file URL: {locked_path:?}"))?
+        .to_string();
+    Ok(locked_url)
+}
+
 struct LockedAppBuilder {
     working_dir: PathBuf,
 }
@@ -103,10 +168,13 @@ impl LockedAppBuilder {
 
                 let trigger_type;
                 match (app_trigger, config) {
-                    (ApplicationTrigger::Http(HttpTriggerConfiguration{base: _}), TriggerConfig::Http(HttpConfig{ route, executor })) => {
+                    (ApplicationTrigger::Http(HttpTriggerConfiguration{base: _}), TriggerConfig::Http(HttpConfig{ route, executor, auth })) => {
                         trigger_type = "http";
                         builder.string("route", route);
                         builder.serializable("executor", executor)?;
+                        if let Some(auth) = auth {
+                            builder.serializable("auth", auth)?;
+                        }
                     },
                     (ApplicationTrigger::Redis(_), TriggerConfig::Redis(RedisConfig{ channel, executor: _ })) => {
                         trigger_type = "redis";
@@ -143,12 +211,56 @@ impl LockedAppBuilder {
     fn build_component(&self, component: CoreComponent) -> Result<LockedComponent> {
         let id = component.id;
 
-        let metadata = ValuesMapBuilder::new()
+        let mut metadata = ValuesMapBuilder::new();
+        metadata
             .string_option(DESCRIPTION_KEY, component.description)
+            .string_option(ENABLED_KEY, component.enabled)
             .string_array(ALLOWED_HTTP_HOSTS_KEY, component.wasm.allowed_http_hosts)
+            .string_array(
+                ALLOWED_OUTBOUND_HOSTS_KEY,
+                component.wasm.allowed_outbound_hosts,
+            )
+            .string_array(
+                ENVIRONMENT_PASSTHROUGH_KEY,
+                component.wasm.environment_passthrough,
+            )
             .string_array(KEY_VALUE_STORES_KEY, component.wasm.key_value_stores)
             .string_array(DATABASES_KEY, component.wasm.sqlite_databases)
-            .take();
+            .string_array(BLOB_CONTAINERS_KEY, component.wasm.blob_containers)
+            .string_array(MESSAGING_CHANNELS_KEY, component.wasm.messaging_channels);
+        if component.wasm.outbound_http_cache {
+            metadata.entry(OUTBOUND_HTTP_CACHE_KEY, true);
+        }
+        if component.wasm.limits != ResourceLimits::default() {
+            metadata.serializable(LIMITS_KEY, component.wasm.limits)?;
+        }
+        if let Some(tmp_dir) = component.wasm.tmp_dir {
+            metadata.serializable(TMP_DIR_KEY, tmp_dir)?;
+        }
+        if !component.wasm.extensions.is_empty() {
+            metadata.serializable(EXTENSIONS_KEY, component.wasm.extensions)?;
+        }
+        if !component.dependencies.is_empty() {
+            let dependencies = component
+                .dependencies
+                .into_iter()
+                .map(|(import, dependency)| {
+                    let locked = match dependency {
+                        ComponentDependency::Path(path) => {
+                            LockedDependency::Source(file_uri(&path).with_context(|| {
+                                format!("failed to resolve dependency {import:?} at {path:?}")
+                            })?)
+                        }
+                        ComponentDependency::Registry(reference) => {
+                            LockedDependency::Registry(reference)
+                        }
+                    };
+                    Ok((import, locked))
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
+            metadata.serializable(DEPENDENCIES_KEY, dependencies)?;
+        }
+        let metadata = metadata.build();
 
         let source = {
             let path = match component.source {
@@ -177,6 +289,7 @@ impl LockedAppBuilder {
                 Ok(ContentPath {
                     content: content_ref_path(&mount.host)?,
                     path: mount.guest.into(),
+                    writable: mount.writable,
                 })
             })
             .collect::<Result<_>>()?;
@@ -227,10 +340,19 @@ mod tests {
         [[component]]
         id = "test-component"
         source = "test-source.wasm"
-        files = ["static.txt"]
+        files = ["static.txt", { source = "data", destination = "/data", writable = true }]
         allowed_http_hosts = ["example.com"]
+        allowed_outbound_hosts = ["tcp://smtp.example.com:587"]
+        [component.limits]
+        max_memory_mb = 64
+        timeout_ms = 500
+        [component.tmp_dir]
+        max_size_mb = 16
         [component.config]
         test_config = "{{test_var}}"
+        [component.dependencies]
+        "cache" = { path = "libs/cache.wasm" }
+        "auth" = { registry = "ghcr.io/fermyon/auth:1.0.0" }
         [component.trigger]
         route = "/"
 
@@ -240,6 +362,9 @@ mod tests {
         allowed_http_hosts = ["insecure:allow-all"]
         [component.trigger]
         route = "/other"
+        [component.trigger.auth]
+        issuer = "https://issuer.example.com/"
+        audiences = ["api"]
     "#;
 
     async fn test_app() -> (Application, TempDir) {
@@ -249,6 +374,7 @@ mod tests {
         std::fs::write(dir.join("spin.toml"), TEST_MANIFEST).expect("write manifest");
         std::fs::write(dir.join("test-source.wasm"), "not actual wasm").expect("write source");
         std::fs::write(dir.join("static.txt"), "content").expect("write static");
+        std::fs::create_dir(dir.join("data")).expect("create data dir");
         let app = spin_loader::local::from_file(dir.join("spin.toml"), Some(&tempdir))
             .await
             .expect("load app");
@@ -262,6 +388,10 @@ mod tests {
         assert_eq!(locked.metadata["name"], "test-app");
         assert!(locked.variables.contains_key("test_var"));
         assert_eq!(locked.triggers[0].trigger_config["route"], "/");
+        assert!(!locked.triggers[0].trigger_config.contains_key("auth"));
+        let auth = &locked.triggers[1].trigger_config["auth"];
+        assert_eq!(auth["issuer"], "https://issuer.example.com/");
+        assert_eq!(auth["audiences"][0], "api");
 
         let component = &locked.components[0];
 
@@ -271,6 +401,32 @@ mod tests {
         let mount = component.files[0].content.source.as_deref().unwrap();
         let mount_path = url::Url::try_from(mount).unwrap().to_file_path().unwrap();
         assert!(mount_path.is_dir(), "{mount:?} is not a dir");
+        assert!(!component.files[0].writable);
+
+        let writable = &component.files[1];
+        assert_eq!(writable.path, PathBuf::from("/data"));
+        assert!(writable.writable);
+        let writable_source = writable.content.source.as_deref().unwrap();
+        assert!(writable_source.ends_with("/data"), "{writable_source:?}");
+
+        assert_eq!(component.metadata["limits"]["max_memory_mb"], 64);
+        assert_eq!(component.metadata["limits"]["timeout_ms"], 500);
+        assert!(!locked.components[1].metadata.contains_key("limits"));
+        assert_eq!(
+            component.metadata["allowed_outbound_hosts"][0],
+            "tcp://smtp.example.com:587"
+        );
+        assert_eq!(component.metadata["tmp_dir"]["max_size_mb"], 16);
+        assert!(!locked.components[1].metadata.contains_key("tmp_dir"));
+
+        let dependencies = component.metadata["dependencies"].as_object().unwrap();
+        let cache = dependencies["cache"]["source"].as_str().unwrap();
+        assert!(cache.ends_with("libs/cache.wasm"), "{cache:?}");
+        assert_eq!(
+            dependencies["auth"]["registry"],
+            "ghcr.io/fermyon/auth:1.0.0"
+        );
+        assert!(!locked.components[1].metadata.contains_key("dependencies"));
     }
 
     #[tokio::test]
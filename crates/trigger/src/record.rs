@@ -0,0 +1,237 @@
+//! Recording of the events which triggers receive, so that they can be
+//! replayed against another build of the application with `spin replay`.
+//!
+//! A recording is a file of JSON lines, one [`RecordedEvent`] per line, which
+//! is appended to as events arrive.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Headers whose values are credentials. Their values are not recorded, and
+/// `spin replay` does not send them.
+pub const CREDENTIAL_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// The value recorded in place of a credential header's.
+pub const REDACTED: &str = "[redacted]";
+
+/// An event received by a trigger, with when it was received.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// When the event was received, in milliseconds since the Unix epoch.
+    pub received_at_ms: u64,
+    /// The event.
+    #[serde(flatten)]
+    pub event: TriggerEvent,
+}
+
+/// An event received by a trigger.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "trigger", rename_all = "snake_case")]
+pub enum TriggerEvent {
+    /// An HTTP request, and the response the application sent.
+    Http {
+        /// The component which handled the request, if any.
+        component: Option<String>,
+        /// The request.
+        request: RecordedRequest,
+        /// The response, or None if the request failed without one.
+        response: Option<RecordedResponse>,
+    },
+    /// A Redis message.
+    Redis {
+        /// The component which handled the message, if any.
+        component: Option<String>,
+        /// The channel on which the message was published.
+        channel: String,
+        /// The message payload.
+        payload: RecordedBody,
+        /// Whether the component handled the message successfully.
+        succeeded: bool,
+    },
+}
+
+/// An HTTP request received by the HTTP trigger.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// The request method.
+    pub method: String,
+    /// The request path, with any query string.
+    pub path: String,
+    /// The request headers.
+    pub headers: Vec<(String, String)>,
+    /// The request body.
+    pub body: RecordedBody,
+}
+
+/// An HTTP response sent by the HTTP trigger.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// The response status code.
+    pub status: u16,
+    /// The response headers.
+    pub headers: Vec<(String, String)>,
+    /// The response body.
+    pub body: RecordedBody,
+}
+
+/// A request, response or message body, which is recorded as text if it is
+/// valid UTF-8 and as base64 otherwise.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedBody {
+    /// A UTF-8 body.
+    Text(String),
+    /// A binary body, encoded as base64.
+    Base64(String),
+}
+
+impl RecordedBody {
+    /// Returns the body's bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Text(text) => Ok(text.clone().into_bytes()),
+            Self::Base64(encoded) => STANDARD
+                .decode(encoded)
+                .context("invalid base64 in recorded body"),
+        }
+    }
+}
+
+impl From<&[u8]> for RecordedBody {
+    fn from(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_owned()),
+            Err(_) => Self::Base64(STANDARD.encode(bytes)),
+        }
+    }
+}
+
+/// Appends the events received by triggers to a recording file.
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Opens the recording file at the given path, creating it if it does
+    /// not exist. Events are appended to any already recorded.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {parent:?}"))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open recording file {path:?}"))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Records the event. Failures are logged rather than returned, so that
+    /// they don't affect the handling of the event.
+    pub fn record(&self, event: TriggerEvent) {
+        let received_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let event = RecordedEvent {
+            received_at_ms,
+            event,
+        };
+        let result = serde_json::to_vec(&event)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                let mut file = self.file.lock().unwrap();
+                Ok(file.write_all(&line)?)
+            });
+        if let Err(err) = result {
+            tracing::warn!("Failed to record trigger event: {err:?}");
+        }
+    }
+}
+
+/// Reads the events from a recording file, in the order they were received.
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedEvent>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open recording file {path:?}"))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(index, line)| {
+            let line = line.with_context(|| format!("Failed to read {path:?}"))?;
+            serde_json::from_str(&line)
+                .with_context(|| format!("Invalid event on line {} of {path:?}", index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_are_text_or_base64() {
+        let text = RecordedBody::from(&b"hello"[..]);
+        assert_eq!(text, RecordedBody::Text("hello".into()));
+        let binary = RecordedBody::from(&[0xff, 0x00][..]);
+        assert!(matches!(binary, RecordedBody::Base64(_)));
+        assert_eq!(binary.to_bytes().unwrap(), [0xff, 0x00]);
+    }
+
+    #[test]
+    fn recorded_events_are_read_back_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recordings/events.jsonl");
+        let recorder = Recorder::open(&path).unwrap();
+        recorder.record(TriggerEvent::Http {
+            component: Some("api".into()),
+            request: RecordedRequest {
+                method: "POST".into(),
+                path: "/api/items?limit=1".into(),
+                headers: vec![("content-type".into(), "text/plain".into())],
+                body: RecordedBody::from(&b"item"[..]),
+            },
+            response: Some(RecordedResponse {
+                status: 201,
+                headers: vec![],
+                body: RecordedBody::from(&b""[..]),
+            }),
+        });
+        recorder.record(TriggerEvent::Redis {
+            component: None,
+            channel: "jobs".into(),
+            payload: RecordedBody::from(&b"job"[..]),
+            succeeded: true,
+        });
+
+        let events = read_recording(&path).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0].event,
+            TriggerEvent::Http { request, response: Some(response), .. }
+                if request.path == "/api/items?limit=1" && response.status == 201
+        ));
+        assert!(matches!(
+            &events[1].event,
+            TriggerEvent::Redis { channel, .. } if channel == "jobs"
+        ));
+    }
+}
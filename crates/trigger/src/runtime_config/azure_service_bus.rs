@@ -0,0 +1,69 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use super::RuntimeConfig;
+
+/// A namespace from an `[azure_service_bus.<name>]` runtime config section,
+/// from which the Azure Service Bus trigger receives messages.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureServiceBusOpts {
+    /// A shared access connection string for the namespace.
+    #[serde(default)]
+    pub connection_string: Option<String>,
+
+    /// The fully qualified namespace, e.g. "example.servicebus.windows.net",
+    /// to authenticate to with a managed identity.
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// The client ID of a user-assigned managed identity. Defaults to the
+    /// system-assigned identity.
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// How to connect to an Azure Service Bus namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AzureServiceBusAuth {
+    ConnectionString(String),
+    ManagedIdentity {
+        namespace: String,
+        client_id: Option<String>,
+    },
+}
+
+impl AzureServiceBusOpts {
+    fn auth(&self, name: &str) -> Result<AzureServiceBusAuth> {
+        match (&self.connection_string, &self.namespace) {
+            (Some(connection_string), None) => {
+                if self.client_id.is_some() {
+                    bail!("Azure Service Bus {name:?} can only set client_id with namespace");
+                }
+                Ok(AzureServiceBusAuth::ConnectionString(
+                    connection_string.clone(),
+                ))
+            }
+            (None, Some(namespace)) => Ok(AzureServiceBusAuth::ManagedIdentity {
+                namespace: namespace.clone(),
+                client_id: self.client_id.clone(),
+            }),
+            _ => bail!(
+                "Azure Service Bus {name:?} must set exactly one of connection_string or namespace"
+            ),
+        }
+    }
+}
+
+/// Returns how to connect to the named Azure Service Bus namespace, from the
+/// highest precedence runtime config file which names it.
+pub fn azure_service_bus(
+    config: &RuntimeConfig,
+    name: &str,
+) -> Result<Option<AzureServiceBusAuth>> {
+    config
+        .opts_layers()
+        .find_map(|opts| opts.azure_service_buses.get(name))
+        .map(|opts| opts.auth(name))
+        .transpose()
+}
@@ -0,0 +1,179 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use spin_blob_store::{
+    object_store::{
+        aws::AmazonS3Builder, azure::MicrosoftAzureBuilder, gcp::GoogleCloudStorageBuilder,
+        local::LocalFileSystem, memory::InMemory, ObjectStore,
+    },
+    BlobStoreComponent,
+};
+
+use super::{resolve_config_path, RuntimeConfig, RuntimeConfigOpts};
+
+const DEFAULT_BLOB_DIR: &str = "blobs";
+
+pub type BlobContainer = Arc<dyn ObjectStore>;
+
+/// Builds a [`BlobStoreComponent`] from the given [`RuntimeConfig`].
+pub(crate) fn build_component(runtime_config: &RuntimeConfig) -> Result<BlobStoreComponent> {
+    let containers = runtime_config
+        .blob_containers()
+        .context("Failed to build blob store component")?;
+    Ok(BlobStoreComponent::new(containers))
+}
+
+// Holds deserialized options from a `[blob_container.<name>]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum BlobContainerOpts {
+    Local(LocalBlobContainerOpts),
+    S3(S3BlobContainerOpts),
+    AzureBlob(AzureBlobContainerOpts),
+    Gcs(GcsBlobContainerOpts),
+}
+
+impl BlobContainerOpts {
+    pub fn default_container_opts(runtime_config: &RuntimeConfig) -> Self {
+        // If the state dir is set, store the default container's objects in it
+        let path = runtime_config
+            .state_dir()
+            .map(|dir| dir.join(DEFAULT_BLOB_DIR));
+        Self::Local(LocalBlobContainerOpts { path })
+    }
+
+    pub fn build_container(&self, config_opts: &RuntimeConfigOpts) -> Result<BlobContainer> {
+        match self {
+            Self::Local(opts) => opts.build_container(config_opts),
+            Self::S3(opts) => opts.build_container(),
+            Self::AzureBlob(opts) => opts.build_container(),
+            Self::Gcs(opts) => opts.build_container(),
+        }
+    }
+
+    /// Describes the container's backend, without any credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Local(opts) => match &opts.path {
+                Some(path) => format!("local ({})", path.display()),
+                None => "local (in memory)".into(),
+            },
+            Self::S3(opts) => match &opts.endpoint {
+                Some(endpoint) => format!("s3 (bucket: {}, endpoint: {endpoint})", opts.bucket),
+                None => format!("s3 (bucket: {})", opts.bucket),
+            },
+            Self::AzureBlob(opts) => format!(
+                "azure_blob (account: {}, container: {})",
+                opts.account, opts.container
+            ),
+            Self::Gcs(opts) => format!("gcs (bucket: {})", opts.bucket),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocalBlobContainerOpts {
+    /// The directory holding the container's objects, relative to the
+    /// runtime config file. If unset, objects are only kept in memory.
+    pub path: Option<PathBuf>,
+}
+
+impl LocalBlobContainerOpts {
+    fn build_container(&self, config_opts: &RuntimeConfigOpts) -> Result<BlobContainer> {
+        let Some(path) = &self.path else {
+            return Ok(Arc::new(InMemory::new()));
+        };
+        let path = resolve_config_path(path, config_opts)?;
+        fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create blob container directory {path:?}"))?;
+        Ok(Arc::new(LocalFileSystem::new_with_prefix(path)?))
+    }
+}
+
+/// An S3 or S3-compatible bucket. Credentials which are not set are read
+/// from the usual `AWS_*` environment variables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3BlobContainerOpts {
+    pub bucket: String,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// The service's URL, for S3-compatible services such as MinIO.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl S3BlobContainerOpts {
+    fn build_container(&self) -> Result<BlobContainer> {
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(&self.bucket);
+        if let Some(region) = &self.region {
+            builder = builder.with_region(region);
+        }
+        if let Some(endpoint) = &self.endpoint {
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+        if let Some(access_key_id) = &self.access_key_id {
+            builder = builder.with_access_key_id(access_key_id);
+        }
+        if let Some(secret_access_key) = &self.secret_access_key {
+            builder = builder.with_secret_access_key(secret_access_key);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.with_token(token);
+        }
+        Ok(Arc::new(builder.build()?))
+    }
+}
+
+/// An Azure Blob Storage container. Credentials which are not set are read
+/// from the usual `AZURE_*` environment variables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobContainerOpts {
+    pub account: String,
+    pub container: String,
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl AzureBlobContainerOpts {
+    fn build_container(&self) -> Result<BlobContainer> {
+        let mut builder = MicrosoftAzureBuilder::from_env()
+            .with_account(&self.account)
+            .with_container_name(&self.container);
+        if let Some(key) = &self.key {
+            builder = builder.with_access_key(key);
+        }
+        Ok(Arc::new(builder.build()?))
+    }
+}
+
+/// A Google Cloud Storage bucket. If no service account file is set, it is
+/// read from the usual `GOOGLE_*` environment variables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcsBlobContainerOpts {
+    pub bucket: String,
+    #[serde(default)]
+    pub service_account_path: Option<String>,
+}
+
+impl GcsBlobContainerOpts {
+    fn build_container(&self) -> Result<BlobContainer> {
+        let mut builder = GoogleCloudStorageBuilder::from_env().with_bucket_name(&self.bucket);
+        if let Some(path) = &self.service_account_path {
+            builder = builder.with_service_account_path(path);
+        }
+        Ok(Arc::new(builder.build()?))
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{
+    config_provider::{
+        ConfigProviderFactory, ConfigProviderOpts, EnvConfigProviderOpts, VaultConfigProviderOpts,
+    },
+    key_value::KeyValueStoreOpts,
+    sqlite::SqliteDatabaseOpts,
+    RuntimeConfig,
+};
+
+/// Builds a [`RuntimeConfig`] in code, for embedders and tests which would
+/// otherwise write a runtime config file.
+///
+/// Settings made with the builder take precedence over any runtime config
+/// files it merges, as command line options do.
+#[derive(Debug, Default)]
+pub struct RuntimeConfigBuilder {
+    config: RuntimeConfig,
+    config_files: Vec<PathBuf>,
+    config_providers: Vec<Result<ConfigProviderOpts>>,
+}
+
+impl RuntimeConfigBuilder {
+    /// Creates a builder for the runtime config of the local application in
+    /// the given directory, if any, which determines the default state
+    /// directory.
+    pub fn new(local_app_dir: Option<PathBuf>) -> Self {
+        Self {
+            config: RuntimeConfig::new(local_app_dir),
+            ..Default::default()
+        }
+    }
+
+    /// Merges the given runtime config file when the config is built. Later
+    /// files take precedence over earlier ones.
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_files.push(path.into());
+        self
+    }
+
+    /// Sets the directory for the default key value store and database.
+    pub fn state_dir(mut self, state_dir: impl Into<String>) -> Self {
+        self.config.set_state_dir(state_dir);
+        self
+    }
+
+    /// Sets the directory to which component logs are written.
+    pub fn log_dir(mut self, log_dir: impl Into<PathBuf>) -> Self {
+        self.config.set_log_dir(log_dir);
+        self
+    }
+
+    /// Configures the named key value store.
+    pub fn key_value_store(mut self, name: impl Into<String>, opts: KeyValueStoreOpts) -> Self {
+        self.config.add_key_value_store(name, opts);
+        self
+    }
+
+    /// Configures the named SQLite database.
+    pub fn sqlite_database(mut self, name: impl Into<String>, opts: SqliteDatabaseOpts) -> Self {
+        self.config.add_sqlite_database(name, opts);
+        self
+    }
+
+    /// Adds a provider of the given `type` with the given options, as if from
+    /// a `[[config_provider]]` section. Providers resolve variables in the
+    /// order they are added, before any from runtime config files.
+    pub fn config_provider(mut self, provider_type: &str, opts: impl Serialize) -> Self {
+        self.config_providers
+            .push(ConfigProviderOpts::new(provider_type, opts));
+        self
+    }
+
+    /// Adds a provider which resolves variables from the environment.
+    pub fn env_config_provider(self, opts: EnvConfigProviderOpts) -> Self {
+        self.config_provider("env", opts)
+    }
+
+    /// Adds a provider which resolves variables from Vault.
+    pub fn vault_config_provider(self, opts: VaultConfigProviderOpts) -> Self {
+        self.config_provider("vault", opts)
+    }
+
+    /// Registers a factory for config providers of another `type`. See
+    /// [`RuntimeConfig::register_config_provider_factory`].
+    pub fn config_provider_factory(mut self, factory: impl ConfigProviderFactory) -> Self {
+        self.config.register_config_provider_factory(factory);
+        self
+    }
+
+    /// Sets the number of instances kept ready for each component.
+    pub fn instance_pool_size(mut self, size: usize) -> Self {
+        self.config.set_instance_pool_size(size);
+        self
+    }
+
+    /// Builds the runtime config, merging any runtime config files.
+    pub fn build(self) -> Result<RuntimeConfig> {
+        let mut config = self.config;
+        for path in self.config_files {
+            config.merge_config_file(path)?;
+        }
+        for opts in self.config_providers {
+            config.add_config_provider(opts?);
+        }
+        Ok(config)
+    }
+}
+
+impl RuntimeConfig {
+    /// Returns a builder for a runtime config. See [`RuntimeConfigBuilder::new`].
+    pub fn builder(local_app_dir: Option<PathBuf>) -> RuntimeConfigBuilder {
+        RuntimeConfigBuilder::new(local_app_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+    use crate::runtime_config::{key_value::SpinKeyValueStoreOpts, sqlite::SpinSqliteDatabaseOpts};
+
+    #[test]
+    fn builds_config_without_files() -> Result<()> {
+        let state_dir = tempfile::tempdir()?;
+        let config = RuntimeConfig::builder(None)
+            .state_dir(state_dir.path().to_string_lossy())
+            .key_value_store(
+                "cache",
+                KeyValueStoreOpts::Spin(SpinKeyValueStoreOpts { path: None }),
+            )
+            .sqlite_database(
+                "reports",
+                SqliteDatabaseOpts::Spin(SpinSqliteDatabaseOpts { path: None }),
+            )
+            .env_config_provider(EnvConfigProviderOpts {
+                prefix: "APP".into(),
+                dotenv_path: None,
+            })
+            .build()?;
+
+        assert_eq!(config.state_dir(), Some(state_dir.path().to_owned()));
+        let mut stores = config
+            .key_value_stores()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        stores.sort();
+        assert_eq!(stores, ["cache", "default"]);
+        assert_eq!(config.sqlite_databases()?.into_iter().count(), 2);
+        let providers = config.described_config_providers()?;
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[1].0, r#"env (prefix "APP")"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_settings_override_files() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(file, "instance_pool_size = 2")?;
+        let config = RuntimeConfig::builder(None)
+            .config_file(file.path())
+            .instance_pool_size(8)
+            .build()?;
+        assert_eq!(config.instance_pool_size("any"), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_config_provider_fails_build() {
+        let result = RuntimeConfig::builder(None)
+            .config_provider("custom", "not a table")
+            .build();
+        assert!(result.is_err());
+    }
+}
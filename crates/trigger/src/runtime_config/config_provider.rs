@@ -1,7 +1,11 @@
-use std::path::PathBuf;
+use std::{fmt::Debug, path::PathBuf};
 
-use serde::Deserialize;
-use spin_config::provider::{env::EnvProvider, vault::VaultProvider};
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use spin_config::provider::{
+    env::EnvProvider,
+    vault::{VaultAuth, VaultProvider, DEFAULT_KUBERNETES_JWT_PATH},
+};
 
 use super::RuntimeConfig;
 
@@ -9,28 +13,129 @@ pub type ConfigProvider = Box<dyn spin_config::Provider>;
 
 const DEFAULT_ENV_PREFIX: &str = "SPIN_CONFIG";
 
+/// Builds the config providers of one `type` of `[[config_provider]]`
+/// runtime config section. Factories for types other than the built-in `env`
+/// and `vault` are registered with
+/// [`RuntimeConfig::register_config_provider_factory`].
+pub trait ConfigProviderFactory: Debug + Send + Sync + 'static {
+    /// The `type` of the sections whose providers this builds, e.g.
+    /// `"onepassword"`.
+    fn provider_type(&self) -> &str;
+
+    /// Builds a provider from a section's options.
+    fn build_provider(&self, opts: &ConfigProviderOpts) -> Result<ConfigProvider>;
+
+    /// A human-readable description of the provider built from the given
+    /// options, omitting any secrets.
+    #[allow(unused_variables)]
+    fn description(&self, opts: &ConfigProviderOpts) -> String {
+        self.provider_type().to_owned()
+    }
+}
+
 // Holds deserialized options from a `[[config_provider]]` runtime config section.
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case", tag = "type")]
-pub enum ConfigProviderOpts {
-    Env(EnvConfigProviderOpts),
-    Vault(VaultConfigProviderOpts),
+#[derive(Clone, Debug)]
+pub struct ConfigProviderOpts {
+    provider_type: String,
+    opts: toml::Value,
 }
 
 impl ConfigProviderOpts {
-    pub fn default_provider_opts(runtime_config: &RuntimeConfig) -> Self {
-        Self::Env(EnvConfigProviderOpts::default_provider_opts(runtime_config))
+    /// Creates the options of a provider of the given `type`, as if from a
+    /// runtime config section.
+    pub fn new(provider_type: impl Into<String>, opts: impl Serialize) -> Result<Self> {
+        let provider_type = provider_type.into();
+        let opts = toml::Value::try_from(opts)
+            .with_context(|| format!("invalid options for {provider_type:?} config provider"))?;
+        if !opts.is_table() {
+            bail!("options for {provider_type:?} config provider must be a table");
+        }
+        Ok(Self {
+            provider_type,
+            opts,
+        })
     }
 
-    pub fn build_provider(&self) -> ConfigProvider {
-        match self {
-            Self::Env(opts) => opts.build_provider(),
-            Self::Vault(opts) => opts.build_provider(),
+    /// The `type` of the section.
+    pub fn provider_type(&self) -> &str {
+        &self.provider_type
+    }
+
+    /// Deserializes the section's options, other than `type`.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        self.opts.clone().try_into().with_context(|| {
+            format!(
+                "invalid options for {:?} config provider",
+                self.provider_type
+            )
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigProviderOpts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let mut table = toml::value::Table::deserialize(deserializer)?;
+        let provider_type = match table.remove("type") {
+            Some(toml::Value::String(provider_type)) => provider_type,
+            Some(_) => return Err(D::Error::custom("config provider `type` must be a string")),
+            None => return Err(D::Error::missing_field("type")),
+        };
+        Ok(Self {
+            provider_type,
+            opts: toml::Value::Table(table),
+        })
+    }
+}
+
+/// The factory for `type = "env"` config providers.
+#[derive(Debug)]
+pub struct EnvConfigProviderFactory;
+
+impl ConfigProviderFactory for EnvConfigProviderFactory {
+    fn provider_type(&self) -> &str {
+        "env"
+    }
+
+    fn build_provider(&self, opts: &ConfigProviderOpts) -> Result<ConfigProvider> {
+        Ok(opts.parse::<EnvConfigProviderOpts>()?.build_provider())
+    }
+
+    fn description(&self, opts: &ConfigProviderOpts) -> String {
+        match opts.parse::<EnvConfigProviderOpts>() {
+            Ok(opts) => opts.description(),
+            Err(_) => self.provider_type().to_owned(),
+        }
+    }
+}
+
+/// The factory for `type = "vault"` config providers.
+#[derive(Debug)]
+pub struct VaultConfigProviderFactory;
+
+impl ConfigProviderFactory for VaultConfigProviderFactory {
+    fn provider_type(&self) -> &str {
+        "vault"
+    }
+
+    fn build_provider(&self, opts: &ConfigProviderOpts) -> Result<ConfigProvider> {
+        opts.parse::<VaultConfigProviderOpts>()?.build_provider()
+    }
+
+    fn description(&self, opts: &ConfigProviderOpts) -> String {
+        match opts.parse::<VaultConfigProviderOpts>() {
+            Ok(opts) => format!("vault ({}, mount {:?})", opts.url, opts.mount),
+            Err(_) => self.provider_type().to_owned(),
         }
     }
 }
 
-#[derive(Debug, Default, Deserialize)]
+/// The factories for the built-in config provider types.
+pub(crate) const BUILTIN_FACTORIES: &[&dyn ConfigProviderFactory] =
+    &[&EnvConfigProviderFactory, &VaultConfigProviderFactory];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct EnvConfigProviderOpts {
     /// A prefix to add to variable names when resolving from the environment.
@@ -56,25 +161,90 @@ impl EnvConfigProviderOpts {
     pub fn build_provider(&self) -> ConfigProvider {
         Box::new(EnvProvider::new(&self.prefix, self.dotenv_path.clone()))
     }
+
+    /// A human-readable description of the provider.
+    pub fn description(&self) -> String {
+        match &self.dotenv_path {
+            Some(path) => format!("env (prefix {:?}, dotenv {})", self.prefix, path.display()),
+            None => format!("env (prefix {:?})", self.prefix),
+        }
+    }
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct VaultConfigProviderOpts {
     pub url: String,
-    pub token: String,
     pub mount: String,
     #[serde(default)]
     pub prefix: Option<String>,
+    /// Vault Enterprise namespace.
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Static token auth. Exactly one of `token`, `approle` or `kubernetes`
+    /// must be set.
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub approle: Option<VaultAppRoleOpts>,
+    #[serde(default)]
+    pub kubernetes: Option<VaultKubernetesOpts>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultAppRoleOpts {
+    #[serde(default = "default_approle_mount")]
+    pub mount: String,
+    pub role_id: String,
+    pub secret_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultKubernetesOpts {
+    #[serde(default = "default_kubernetes_mount")]
+    pub mount: String,
+    pub role: String,
+    #[serde(default = "default_kubernetes_jwt_path")]
+    pub jwt_path: PathBuf,
+}
+
+fn default_approle_mount() -> String {
+    "approle".into()
+}
+
+fn default_kubernetes_mount() -> String {
+    "kubernetes".into()
+}
+
+fn default_kubernetes_jwt_path() -> PathBuf {
+    DEFAULT_KUBERNETES_JWT_PATH.into()
 }
 
 impl VaultConfigProviderOpts {
-    pub fn build_provider(&self) -> ConfigProvider {
-        Box::new(VaultProvider::new(
-            &self.url,
-            &self.token,
-            &self.mount,
-            self.prefix.as_deref(),
-        ))
+    pub fn build_provider(&self) -> Result<ConfigProvider> {
+        let auth = match (&self.token, &self.approle, &self.kubernetes) {
+            (Some(token), None, None) => VaultAuth::Token(token.clone()),
+            (None, Some(approle), None) => VaultAuth::AppRole {
+                mount: approle.mount.clone(),
+                role_id: approle.role_id.clone(),
+                secret_id: approle.secret_id.clone(),
+            },
+            (None, None, Some(kubernetes)) => VaultAuth::Kubernetes {
+                mount: kubernetes.mount.clone(),
+                role: kubernetes.role.clone(),
+                jwt_path: kubernetes.jwt_path.clone(),
+            },
+            (None, None, None) => bail!(
+                "Vault config provider requires one of 'token', 'approle' or 'kubernetes' auth"
+            ),
+            _ => bail!(
+                "Vault config provider must set only one of 'token', 'approle' or 'kubernetes' auth"
+            ),
+        };
+        let provider = VaultProvider::new(&self.url, auth, &self.mount, self.prefix.as_deref())
+            .with_namespace(self.namespace.as_deref());
+        Ok(Box::new(provider))
     }
 }
@@ -0,0 +1,318 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::Deserialize;
+use spin_core::{async_trait, FaultInjection};
+use spin_key_value::{Error as KeyValueError, Store, StoreManager};
+use spin_sqlite::{Connection, ConnectionsStore};
+use spin_world::sqlite;
+
+use super::RuntimeConfig;
+
+/// The host interfaces which faults can be injected into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultInterface {
+    OutboundHttp,
+    KeyValue,
+    Sqlite,
+}
+
+impl FromStr for FaultInterface {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "outbound_http" => Ok(Self::OutboundHttp),
+            "key_value" => Ok(Self::KeyValue),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => bail!("unknown interface {s:?}; expected outbound_http, key_value or sqlite"),
+        }
+    }
+}
+
+// Holds deserialized options from a `[component_faults.<component>]` runtime
+// config section.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentFaultsOpts {
+    #[serde(default)]
+    pub outbound_http: Option<FaultOpts>,
+    #[serde(default)]
+    pub key_value: Option<FaultOpts>,
+    #[serde(default)]
+    pub sqlite: Option<FaultOpts>,
+}
+
+impl ComponentFaultsOpts {
+    fn get(&self, interface: FaultInterface) -> Option<&FaultOpts> {
+        match interface {
+            FaultInterface::OutboundHttp => self.outbound_http.as_ref(),
+            FaultInterface::KeyValue => self.key_value.as_ref(),
+            FaultInterface::Sqlite => self.sqlite.as_ref(),
+        }
+    }
+
+    pub(crate) fn set(&mut self, interface: FaultInterface, opts: FaultOpts) {
+        let field = match interface {
+            FaultInterface::OutboundHttp => &mut self.outbound_http,
+            FaultInterface::KeyValue => &mut self.key_value,
+            FaultInterface::Sqlite => &mut self.sqlite,
+        };
+        *field = Some(opts);
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaultOpts {
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    #[serde(default)]
+    pub error_rate: Option<f64>,
+}
+
+impl FaultOpts {
+    fn build(&self) -> Result<FaultInjection> {
+        let error_rate = self.error_rate.unwrap_or_default();
+        ensure!(
+            (0.0..=1.0).contains(&error_rate),
+            "error_rate must be between 0 and 1, not {error_rate}"
+        );
+        Ok(FaultInjection {
+            latency: Duration::from_millis(self.latency_ms.unwrap_or_default()),
+            error_rate,
+        })
+    }
+}
+
+/// Parses a `--inject-fault` value of the form
+/// `<component>:<interface>:<key>=<value>,...`, e.g.
+/// `api:key_value:latency_ms=200,error_rate=0.1`.
+pub fn parse_fault_flag(s: &str) -> Result<(String, FaultInterface, FaultOpts)> {
+    let mut parts = s.splitn(3, ':');
+    let (Some(component), Some(interface), Some(settings)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("expected <component>:<interface>:<settings>, e.g. api:key_value:error_rate=0.1");
+    };
+    let interface = interface.parse()?;
+    let mut opts = FaultOpts::default();
+    for setting in settings.split(',') {
+        let (key, value) = setting
+            .split_once('=')
+            .with_context(|| format!("expected <key>=<value>, not {setting:?}"))?;
+        match key {
+            "latency_ms" => {
+                opts.latency_ms = Some(value.parse().context("invalid latency_ms")?);
+            }
+            "error_rate" => {
+                opts.error_rate = Some(value.parse().context("invalid error_rate")?);
+            }
+            _ => bail!("unknown setting {key:?}; expected latency_ms or error_rate"),
+        }
+    }
+    opts.build()?;
+    Ok((component.to_owned(), interface, opts))
+}
+
+/// Returns the faults to inject into each component's calls to the given
+/// interface. The faults for a component and interface come from the highest
+/// precedence layer which sets them.
+pub(crate) fn build_faults(
+    runtime_config: &RuntimeConfig,
+    interface: FaultInterface,
+) -> Result<HashMap<String, FaultInjection>> {
+    let mut faults = HashMap::new();
+    for opts in runtime_config.opts_layers() {
+        for (component_id, component_faults) in &opts.component_faults {
+            if faults.contains_key(component_id) {
+                continue;
+            }
+            if let Some(fault_opts) = component_faults.get(interface) {
+                let fault = fault_opts.build().with_context(|| {
+                    format!("invalid component_faults for component {component_id:?}")
+                })?;
+                faults.insert(component_id.clone(), fault);
+            }
+        }
+    }
+    Ok(faults)
+}
+
+/// A [`StoreManager`] whose stores inject faults into every operation.
+pub(crate) struct FaultInjectingStoreManager {
+    pub inner: Arc<dyn StoreManager>,
+    pub faults: FaultInjection,
+}
+
+#[async_trait]
+impl StoreManager for FaultInjectingStoreManager {
+    async fn get(&self, name: &str) -> Result<Arc<dyn Store>, KeyValueError> {
+        Ok(Arc::new(FaultInjectingStore {
+            inner: self.inner.get(name).await?,
+            faults: self.faults,
+        }))
+    }
+
+    fn is_defined(&self, store_name: &str) -> bool {
+        self.inner.is_defined(store_name)
+    }
+}
+
+struct FaultInjectingStore {
+    inner: Arc<dyn Store>,
+    faults: FaultInjection,
+}
+
+impl FaultInjectingStore {
+    async fn inject(&self) -> Result<(), KeyValueError> {
+        tokio::time::sleep(self.faults.latency).await;
+        if self.faults.should_fail() {
+            return Err(KeyValueError::Io("injected fault".into()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Store for FaultInjectingStore {
+    async fn get(&self, key: &str) -> Result<Vec<u8>, KeyValueError> {
+        self.inject().await?;
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), KeyValueError> {
+        self.inject().await?;
+        self.inner.set(key, value).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), KeyValueError> {
+        self.inject().await?;
+        self.inner.delete(key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, KeyValueError> {
+        self.inject().await?;
+        self.inner.exists(key).await
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, KeyValueError> {
+        self.inject().await?;
+        self.inner.get_keys().await
+    }
+}
+
+/// A [`ConnectionsStore`] whose connections inject faults into every query.
+pub(crate) struct FaultInjectingConnectionsStore {
+    pub inner: Arc<dyn ConnectionsStore>,
+    pub faults: FaultInjection,
+}
+
+impl ConnectionsStore for FaultInjectingConnectionsStore {
+    fn get_connection(
+        &self,
+        database: &str,
+    ) -> Result<Option<Arc<dyn Connection + 'static>>, sqlite::Error> {
+        Ok(self.inner.get_connection(database)?.map(|inner| {
+            Arc::new(FaultInjectingConnection {
+                inner,
+                faults: self.faults,
+            }) as Arc<dyn Connection>
+        }))
+    }
+
+    fn has_connection_for(&self, database: &str) -> bool {
+        self.inner.has_connection_for(database)
+    }
+}
+
+struct FaultInjectingConnection {
+    inner: Arc<dyn Connection>,
+    faults: FaultInjection,
+}
+
+#[async_trait]
+impl Connection for FaultInjectingConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<sqlite::Value>,
+    ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        tokio::time::sleep(self.faults.latency).await;
+        if self.faults.should_fail() {
+            return Err(sqlite::Error::Io("injected fault".into()));
+        }
+        self.inner.query(query, parameters).await
+    }
+
+    // Batches are executed synchronously, so the latency blocks the calling
+    // thread, as the batch itself would.
+    fn execute_batch(&self, statements: &str) -> anyhow::Result<()> {
+        std::thread::sleep(self.faults.latency);
+        if self.faults.should_fail() {
+            bail!("injected fault");
+        }
+        self.inner.execute_batch(statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fault_flag() {
+        let (component, interface, opts) =
+            parse_fault_flag("api:key_value:latency_ms=200,error_rate=0.25").unwrap();
+        assert_eq!(component, "api");
+        assert_eq!(interface, FaultInterface::KeyValue);
+        assert_eq!(
+            opts.build().unwrap(),
+            FaultInjection {
+                latency: Duration::from_millis(200),
+                error_rate: 0.25,
+            }
+        );
+    }
+
+    struct NoopConnection;
+
+    #[async_trait]
+    impl Connection for NoopConnection {
+        async fn query(
+            &self,
+            _query: &str,
+            _parameters: Vec<sqlite::Value>,
+        ) -> Result<sqlite::QueryResult, sqlite::Error> {
+            Ok(sqlite::QueryResult {
+                columns: vec![],
+                rows: vec![],
+            })
+        }
+
+        fn execute_batch(&self, _statements: &str) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn faults_are_injected_into_batches() {
+        let connection = |error_rate| FaultInjectingConnection {
+            inner: Arc::new(NoopConnection),
+            faults: FaultInjection {
+                latency: Duration::ZERO,
+                error_rate,
+            },
+        };
+        assert!(connection(1.0).execute_batch("SELECT 1").is_err());
+        assert!(connection(0.0).execute_batch("SELECT 1").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_fault_flags() {
+        assert!(parse_fault_flag("api:key_value").is_err());
+        assert!(parse_fault_flag("api:redis:error_rate=0.1").is_err());
+        assert!(parse_fault_flag("api:sqlite:error_rate=2").is_err());
+        assert!(parse_fault_flag("api:sqlite:jitter_ms=5").is_err());
+    }
+}
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 use crate::{runtime_config::RuntimeConfig, TriggerHooks};
 use anyhow::{bail, Context, Result};
@@ -10,30 +15,52 @@ use spin_key_value::{
 use spin_key_value_azure::KeyValueAzureCosmos;
 use spin_key_value_sqlite::{DatabaseLocation, KeyValueSqlite};
 
-use super::{resolve_config_path, RuntimeConfigOpts};
+use super::{
+    faults::{build_faults, FaultInjectingStoreManager, FaultInterface},
+    resolve_config_path, RuntimeConfigOpts,
+};
 
 const DEFAULT_SPIN_STORE_FILENAME: &str = "sqlite_key_value.db";
 
 pub type KeyValueStore = Arc<dyn StoreManager>;
 
-/// Builds a [`KeyValueComponent`] from the given [`RuntimeConfig`].
-pub async fn build_key_value_component(
+/// The store manager given to new instances, which is replaced when the
+/// runtime config is reloaded.
+pub(crate) type SharedStoreManager = Arc<RwLock<Arc<dyn StoreManager>>>;
+
+/// Builds a [`KeyValueComponent`] from the given [`RuntimeConfig`], returning
+/// the component's store manager so that it can be replaced later. The
+/// `init_data` entries are set in the default store, as are the `seed_data`
+/// entries whose keys it does not already contain.
+pub(crate) async fn build_key_value_component(
     runtime_config: &RuntimeConfig,
     init_data: &[(String, String)],
-) -> Result<KeyValueComponent> {
-    let stores: HashMap<_, _> = runtime_config
-        .key_value_stores()
-        .context("Failed to build key-value component")?
-        .into_iter()
-        .collect();
+    seed_data: &[(String, String)],
+) -> Result<(KeyValueComponent, SharedStoreManager)> {
+    let stores = build_stores(runtime_config)?;
 
     // Avoid creating a database as a side-effect if one is not needed.
-    if !init_data.is_empty() {
+    if !init_data.is_empty() || !seed_data.is_empty() {
         if let Some(manager) = stores.get("default") {
             let default_store = manager
                 .get("default")
                 .await
                 .context("Failed to access key-value store to set requested entries")?;
+            for (key, value) in seed_data {
+                let exists = default_store.exists(key).await.with_context(|| {
+                    format!("Failed to check for seed entry {key} in key-value store")
+                })?;
+                if exists {
+                    tracing::debug!("Not seeding key-value entry {key}, which already exists");
+                    continue;
+                }
+                default_store
+                    .set(key, value.as_bytes())
+                    .await
+                    .with_context(|| {
+                        format!("Failed to set seed entry {key} in key-value store")
+                    })?;
+            }
             for (key, value) in init_data {
                 default_store
                     .set(key, value.as_bytes())
@@ -47,11 +74,40 @@ pub async fn build_key_value_component(
         }
     }
 
+    let faults = build_faults(runtime_config, FaultInterface::KeyValue)?;
+    let shared_manager = Arc::new(RwLock::new(build_store_manager(stores)));
+    let component_manager = shared_manager.clone();
+    let component = KeyValueComponent::new(spin_key_value::manager(move |component| {
+        let manager = component_manager.read().unwrap().clone();
+        match faults.get(component.id()) {
+            Some(&faults) => Arc::new(FaultInjectingStoreManager {
+                inner: manager,
+                faults,
+            }) as Arc<dyn StoreManager>,
+            None => manager,
+        }
+    }));
+    Ok((component, shared_manager))
+}
+
+/// Builds the store manager for the stores in the given [`RuntimeConfig`].
+pub(crate) fn build_reloaded_store_manager(
+    runtime_config: &RuntimeConfig,
+) -> Result<Arc<dyn StoreManager>> {
+    Ok(build_store_manager(build_stores(runtime_config)?))
+}
+
+fn build_stores(runtime_config: &RuntimeConfig) -> Result<HashMap<String, KeyValueStore>> {
+    Ok(runtime_config
+        .key_value_stores()
+        .context("Failed to build key-value component")?
+        .into_iter()
+        .collect())
+}
+
+fn build_store_manager(stores: HashMap<String, KeyValueStore>) -> Arc<dyn StoreManager> {
     let delegating_manager = DelegatingStoreManager::new(stores);
-    let caching_manager = Arc::new(CachingStoreManager::new(delegating_manager));
-    Ok(KeyValueComponent::new(spin_key_value::manager(move |_| {
-        caching_manager.clone()
-    })))
+    Arc::new(CachingStoreManager::new(delegating_manager))
 }
 
 // Holds deserialized options from a `[key_value_store.<name>]` runtime config section.
@@ -75,6 +131,22 @@ impl KeyValueStoreOpts {
             Self::AzureCosmos(opts) => opts.build_store(),
         }
     }
+
+    /// Describes the store's backend, without any credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Spin(opts) => match &opts.path {
+                Some(path) => format!("spin ({})", path.display()),
+                None => "spin (in memory)".into(),
+            },
+            Self::Redis(opts) if !opts.cluster_urls.is_empty() => "redis (cluster)".into(),
+            Self::Redis(_) => "redis".into(),
+            Self::AzureCosmos(opts) => format!(
+                "azure_cosmos (account: {}, database: {}, container: {})",
+                opts.account, opts.database, opts.container
+            ),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -109,22 +181,34 @@ impl SpinKeyValueStoreOpts {
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct RedisKeyValueStoreOpts {
-    pub url: String,
+    /// The Redis server.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The seed nodes of a Redis Cluster, instead of `url`. The rest of the
+    /// cluster's nodes are discovered from them. Use `rediss://` URLs for TLS.
+    #[serde(default)]
+    pub cluster_urls: Vec<String>,
 }
 
 impl RedisKeyValueStoreOpts {
     fn build_store(&self) -> Result<KeyValueStore> {
-        let kv_redis = spin_key_value_redis::KeyValueRedis::new(self.url.clone())?;
+        let kv_redis = match (&self.url, self.cluster_urls.is_empty()) {
+            (Some(url), true) => spin_key_value_redis::KeyValueRedis::new(url.clone())?,
+            (None, false) => {
+                spin_key_value_redis::KeyValueRedis::new_cluster(self.cluster_urls.clone())?
+            }
+            _ => bail!("A Redis key value store needs exactly one of `url` and `cluster_urls`"),
+        };
         Ok(Arc::new(kv_redis))
     }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct AzureCosmosConfig {
-    key: String,
-    account: String,
-    database: String,
-    container: String,
+    pub key: String,
+    pub account: String,
+    pub database: String,
+    pub container: String,
 }
 
 impl AzureCosmosConfig {
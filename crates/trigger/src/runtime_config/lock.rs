@@ -0,0 +1,73 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use spin_lock::{DatabaseLocation, LockComponent, RedisLockStore, SqliteLockStore};
+
+use super::{
+    resolve_config_path, sqlite::SpinSqliteDatabaseOpts, RuntimeConfig, RuntimeConfigOpts,
+};
+
+/// Builds a [`LockComponent`] with the store from the highest precedence
+/// runtime config file which configures one, or the default store.
+pub(crate) fn build_component(runtime_config: &RuntimeConfig) -> Result<LockComponent> {
+    let component = match runtime_config
+        .opts_layers()
+        .find_map(|layer| Some((layer.lock.as_ref()?, layer)))
+    {
+        Some((opts, layer)) => opts.build_component(layer),
+        None => LockStoreOpts::default_store_opts(runtime_config)
+            .build_component(&RuntimeConfigOpts::default()),
+    };
+    component.context("Failed to build lock component")
+}
+
+// Holds deserialized options from the `[lock]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum LockStoreOpts {
+    Spin(SpinLockStoreOpts),
+    Redis(RedisLockStoreOpts),
+}
+
+impl LockStoreOpts {
+    fn default_store_opts(runtime_config: &RuntimeConfig) -> Self {
+        // Locks are kept in their own table of the default SQLite database's
+        // file, rather than in a file of their own.
+        let path = SpinSqliteDatabaseOpts::default(runtime_config).path;
+        Self::Spin(SpinLockStoreOpts { path })
+    }
+
+    fn build_component(&self, config_opts: &RuntimeConfigOpts) -> Result<LockComponent> {
+        match self {
+            Self::Spin(opts) => {
+                let location = match &opts.path {
+                    Some(path) => {
+                        let path = resolve_config_path(path, config_opts)?;
+                        // Create the store's parent directory if necessary
+                        fs::create_dir_all(path.parent().unwrap())
+                            .context("Failed to create lock store")?;
+                        DatabaseLocation::Path(path)
+                    }
+                    None => DatabaseLocation::InMemory,
+                };
+                Ok(LockComponent::new(SqliteLockStore::new(location)))
+            }
+            Self::Redis(opts) => Ok(LockComponent::new(RedisLockStore::new(&opts.url)?)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinLockStoreOpts {
+    /// The SQLite database file, which Spin instances on the same host share
+    /// locks through. If unset, locks are only shared within the instance.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedisLockStoreOpts {
+    pub url: String,
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, num::NonZeroUsize, path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use outbound_http::{
+    CircuitBreakerConfig, ConnectionConfig, HttpVersionPreference, MockConfig, MockResponse,
+    OutboundHttpConfig, ProxyConfig, ResponseCacheConfig, RetryConfig, TlsConfig,
+};
+use serde::Deserialize;
+
+use super::{
+    faults::{build_faults, FaultInterface},
+    resolve_config_path, RuntimeConfig, RuntimeConfigOpts,
+};
+
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 1000;
+
+/// Runtime configuration for outbound HTTP requests made by components.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundHttpOpts {
+    #[serde(default)]
+    pub proxy: Option<ProxyOpts>,
+
+    /// TLS settings keyed by destination host name.
+    #[serde(default)]
+    pub tls: HashMap<String, TlsOpts>,
+
+    /// Retry policies keyed by destination host name.
+    #[serde(default)]
+    pub retry: HashMap<String, RetryOpts>,
+
+    /// Circuit breaker settings keyed by destination host name.
+    #[serde(default)]
+    pub circuit_breaker: HashMap<String, CircuitBreakerOpts>,
+
+    /// The response cache used by components with `outbound_http_cache` set.
+    #[serde(default)]
+    pub cache: Option<ResponseCacheOpts>,
+
+    /// Connection pooling and protocol settings.
+    #[serde(default)]
+    pub connection: ConnectionOpts,
+
+    /// Canned responses for requests matching a host and path, which are
+    /// then not sent.
+    #[serde(default)]
+    pub mock: Vec<MockOpts>,
+}
+
+/// Connection pooling and protocol settings for outbound HTTP. Each field is
+/// taken from the highest precedence runtime config file that sets it.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionOpts {
+    #[serde(default)]
+    pub max_idle_per_host: Option<usize>,
+    #[serde(default)]
+    pub idle_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub http_version: Option<HttpVersionOpt>,
+    #[serde(default)]
+    pub tcp_keepalive_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersionOpt {
+    Auto,
+    Http1,
+    Http2,
+}
+
+impl From<HttpVersionOpt> for HttpVersionPreference {
+    fn from(opt: HttpVersionOpt) -> Self {
+        match opt {
+            HttpVersionOpt::Auto => Self::Auto,
+            HttpVersionOpt::Http1 => Self::Http1,
+            HttpVersionOpt::Http2 => Self::Http2,
+        }
+    }
+}
+
+/// An explicit proxy for outbound HTTP, used instead of any proxy set via the
+/// `HTTP_PROXY`/`HTTPS_PROXY` environment variables.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyOpts {
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyOpts {
+    fn build_config(&self) -> ProxyConfig {
+        ProxyConfig {
+            url: self.url.clone(),
+            username: self.username.clone(),
+            password: self.password.clone(),
+            no_proxy: self.no_proxy.clone(),
+        }
+    }
+}
+
+/// TLS settings for outbound HTTP requests to a particular host.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsOpts {
+    /// PEM files with additional root certificates, relative to the runtime
+    /// config file.
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// Hex-encoded SHA-256 fingerprints of accepted server certificates.
+    #[serde(default)]
+    pub pinned_certs: Vec<String>,
+    /// Disable certificate verification, e.g. for self-signed dev services.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsOpts {
+    fn build_config(&self, config_opts: &RuntimeConfigOpts) -> Result<TlsConfig> {
+        let ca_certs = self
+            .ca_certs
+            .iter()
+            .map(|path| resolve_config_path(path, config_opts))
+            .collect::<Result<_>>()?;
+        Ok(TlsConfig {
+            ca_certs,
+            pinned_certs: self.pinned_certs.clone(),
+            insecure_skip_verify: self.insecure_skip_verify,
+        })
+    }
+}
+
+/// Retry policy for outbound HTTP requests to a particular host. Unset
+/// fields use the defaults from [`RetryConfig`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryOpts {
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    #[serde(default)]
+    pub initial_backoff_ms: Option<u64>,
+    #[serde(default)]
+    pub max_backoff_ms: Option<u64>,
+}
+
+impl RetryOpts {
+    fn build_config(&self) -> RetryConfig {
+        let default = RetryConfig::default();
+        RetryConfig {
+            max_attempts: self.max_attempts.unwrap_or(default.max_attempts),
+            initial_backoff: self
+                .initial_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.initial_backoff),
+            max_backoff: self
+                .max_backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.max_backoff),
+        }
+    }
+}
+
+/// Circuit breaker settings for outbound HTTP requests to a particular host.
+/// Unset fields use the defaults from [`CircuitBreakerConfig`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerOpts {
+    #[serde(default)]
+    pub failure_threshold: Option<u32>,
+    #[serde(default)]
+    pub reset_timeout_ms: Option<u64>,
+}
+
+impl CircuitBreakerOpts {
+    fn build_config(&self) -> CircuitBreakerConfig {
+        let default = CircuitBreakerConfig::default();
+        CircuitBreakerConfig {
+            failure_threshold: self.failure_threshold.unwrap_or(default.failure_threshold),
+            reset_timeout: self
+                .reset_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.reset_timeout),
+        }
+    }
+}
+
+/// A canned response for outbound HTTP requests matching a host and path,
+/// from an `[[outbound_http.mock]]` runtime config section. The body may be
+/// given inline or read from a file relative to the runtime config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MockOpts {
+    pub host: String,
+    #[serde(default = "default_mock_path")]
+    pub path: String,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default = "default_mock_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub body_file: Option<PathBuf>,
+}
+
+fn default_mock_path() -> String {
+    "/...".into()
+}
+
+fn default_mock_status() -> u16 {
+    200
+}
+
+impl MockOpts {
+    fn build_config(&self, config_opts: &RuntimeConfigOpts) -> Result<MockConfig> {
+        let method = self
+            .method
+            .as_deref()
+            .map(|method| method.to_ascii_uppercase().parse())
+            .transpose()
+            .with_context(|| format!("Invalid method in outbound HTTP mock for {:?}", self.host))?;
+        let body = match (&self.body, &self.body_file) {
+            (Some(_), Some(_)) => bail!(
+                "Outbound HTTP mock for {:?} may set only one of `body` and `body_file`",
+                self.host
+            ),
+            (Some(body), None) => body.clone().into_bytes(),
+            (None, Some(path)) => {
+                let path = resolve_config_path(path, config_opts)?;
+                std::fs::read(&path).with_context(|| {
+                    format!("Failed to read outbound HTTP mock body from {path:?}")
+                })?
+            }
+            (None, None) => vec![],
+        };
+        Ok(MockConfig {
+            host: self.host.clone(),
+            path: self.path.clone(),
+            method,
+            response: MockResponse {
+                status: self.status,
+                headers: self
+                    .headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+                body,
+            },
+        })
+    }
+}
+
+// Holds deserialized options from an `[outbound_http.cache]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type", deny_unknown_fields)]
+pub enum ResponseCacheOpts {
+    Memory {
+        #[serde(default)]
+        max_entries: Option<usize>,
+    },
+    KeyValue {
+        store: String,
+    },
+}
+
+impl ResponseCacheOpts {
+    fn build_config(&self, runtime_config: &RuntimeConfig) -> Result<ResponseCacheConfig> {
+        match self {
+            Self::Memory { max_entries } => {
+                let max_entries = max_entries.unwrap_or(DEFAULT_CACHE_MAX_ENTRIES);
+                Ok(ResponseCacheConfig::Memory {
+                    max_entries: NonZeroUsize::new(max_entries)
+                        .context("outbound HTTP cache max_entries must be greater than zero")?,
+                })
+            }
+            Self::KeyValue { store } => {
+                let manager = runtime_config
+                    .key_value_stores()?
+                    .into_iter()
+                    .find_map(|(name, manager)| (&name == store).then_some(manager))
+                    .with_context(|| {
+                        format!("outbound HTTP cache uses unknown key-value store {store:?}")
+                    })?;
+                Ok(ResponseCacheConfig::KeyValue {
+                    manager,
+                    store: store.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Builds an [`OutboundHttpConfig`] from the given [`RuntimeConfig`]. Each
+/// setting is taken from the highest precedence layer that sets it.
+pub(crate) fn build_config(runtime_config: &RuntimeConfig) -> Result<OutboundHttpConfig> {
+    let sections = || {
+        runtime_config
+            .opts_layers()
+            .filter_map(|opts| Some((opts, opts.outbound_http.as_ref()?)))
+    };
+
+    let proxy = sections()
+        .find_map(|(_, http)| http.proxy.as_ref())
+        .map(ProxyOpts::build_config);
+
+    let connection = ConnectionConfig {
+        max_idle_per_host: sections().find_map(|(_, http)| http.connection.max_idle_per_host),
+        idle_timeout: sections()
+            .find_map(|(_, http)| http.connection.idle_timeout_ms)
+            .map(Duration::from_millis),
+        http_version: sections()
+            .find_map(|(_, http)| http.connection.http_version)
+            .map(Into::into)
+            .unwrap_or_default(),
+        tcp_keepalive: sections()
+            .find_map(|(_, http)| http.connection.tcp_keepalive_ms)
+            .map(Duration::from_millis),
+    };
+
+    let cache = sections()
+        .find_map(|(_, http)| http.cache.as_ref())
+        .map(|opts| opts.build_config(runtime_config))
+        .transpose()?;
+    // Per-host settings come from the highest precedence layer that
+    // configures that host.
+    let mut tls = HashMap::new();
+    let mut retry = HashMap::new();
+    let mut circuit_breakers = HashMap::new();
+    // Mocks from every layer apply, with those from higher precedence layers
+    // matched first.
+    let mut mocks = vec![];
+    for (opts, http) in sections() {
+        for (host, tls_opts) in &http.tls {
+            if !tls.contains_key(host) {
+                tls.insert(host.to_owned(), tls_opts.build_config(opts)?);
+            }
+        }
+        for (host, retry_opts) in &http.retry {
+            retry
+                .entry(host.to_owned())
+                .or_insert_with(|| retry_opts.build_config());
+        }
+        for (host, breaker_opts) in &http.circuit_breaker {
+            circuit_breakers
+                .entry(host.to_owned())
+                .or_insert_with(|| breaker_opts.build_config());
+        }
+        for mock_opts in &http.mock {
+            mocks.push(mock_opts.build_config(opts)?);
+        }
+    }
+
+    Ok(OutboundHttpConfig {
+        proxy,
+        tls,
+        retry,
+        circuit_breakers,
+        cache,
+        connection,
+        mocks,
+        faults: build_faults(runtime_config, FaultInterface::OutboundHttp)?,
+    })
+}
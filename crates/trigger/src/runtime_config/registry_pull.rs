@@ -0,0 +1,45 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{resolve_config_path, RuntimeConfig};
+
+/// Runtime configuration for where applications are pulled from registries
+/// to, and through.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryPullOpts {
+    /// Mirrors for each registry, such as `"ghcr.io" =
+    /// ["mirror.example.com/ghcr"]`, tried in order before the registry
+    /// itself.
+    #[serde(default)]
+    pub mirrors: HashMap<String, Vec<String>>,
+
+    /// The directory in which to cache pulled applications. Content is
+    /// stored by digest, so the cache may be shared by several applications
+    /// and Spin processes. Relative paths are relative to the runtime config
+    /// file.
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// Returns the registry pull settings from the highest precedence runtime
+/// config file which has them, with the cache path resolved.
+pub(super) fn build_opts(config: &RuntimeConfig) -> Result<Option<RegistryPullOpts>> {
+    let Some(opts) = config
+        .opts_layers()
+        .find(|opts| opts.registry_pull.is_some())
+    else {
+        return Ok(None);
+    };
+    let pull = opts.registry_pull.as_ref().unwrap();
+    let cache_dir = pull
+        .cache_dir
+        .as_ref()
+        .map(|path| resolve_config_path(path, opts))
+        .transpose()?;
+    Ok(Some(RegistryPullOpts {
+        cache_dir,
+        ..pull.clone()
+    }))
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{resolve_config_path, RuntimeConfig};
+
+/// Runtime configuration for verifying the signatures of applications pulled
+/// from OCI registries, and restricting which references may be pulled.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryTrustOpts {
+    /// Public key files for the keys whose signatures are trusted. Relative
+    /// paths are relative to the runtime config file.
+    #[serde(default)]
+    pub trusted_keys: Vec<PathBuf>,
+
+    /// Whether to refuse to run applications without a trusted signature.
+    #[serde(default)]
+    pub require_signatures: bool,
+
+    /// Public key files for the keys trusted to sign as each issuer, as
+    /// named by signers with `spin registry push --issuer`. These keys are
+    /// also trusted as `trusted_keys` are. Relative paths are relative to the
+    /// runtime config file.
+    #[serde(default)]
+    pub issuer_keys: HashMap<String, Vec<PathBuf>>,
+
+    /// Issuers, one of which must have made a trusted signature with a key
+    /// in `issuer_keys` for that issuer. Setting this requires signatures.
+    #[serde(default)]
+    pub required_issuers: Vec<String>,
+
+    /// Registries, such as `ghcr.io`, or repository prefixes, such as
+    /// `ghcr.io/fermyon`, from which applications may be pulled. If empty,
+    /// any registry is allowed.
+    #[serde(default)]
+    pub allowed_registries: Vec<String>,
+
+    /// Whether to refuse references which do not name a digest.
+    #[serde(default)]
+    pub require_digest: bool,
+
+    /// Whether to refuse references to the `latest` tag.
+    #[serde(default)]
+    pub deny_latest: bool,
+}
+
+/// Returns the registry trust settings from the highest precedence runtime
+/// config file which has them, with key paths resolved.
+pub(super) fn build_opts(config: &RuntimeConfig) -> Result<Option<RegistryTrustOpts>> {
+    let Some(opts) = config
+        .opts_layers()
+        .find(|opts| opts.registry_trust.is_some())
+    else {
+        return Ok(None);
+    };
+    let trust = opts.registry_trust.as_ref().unwrap();
+    let resolve_paths = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .map(|path| resolve_config_path(path, opts))
+            .collect::<Result<Vec<_>>>()
+    };
+    let trusted_keys = resolve_paths(&trust.trusted_keys)?;
+    let issuer_keys = trust
+        .issuer_keys
+        .iter()
+        .map(|(issuer, paths)| Ok((issuer.clone(), resolve_paths(paths)?)))
+        .collect::<Result<_>>()?;
+    Ok(Some(RegistryTrustOpts {
+        trusted_keys,
+        issuer_keys,
+        ..trust.clone()
+    }))
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use spin_config::ConfigHostComponent;
+
+use super::{
+    key_value::{self, SharedStoreManager},
+    sqlite::{self, SharedConnectionsStore},
+    RuntimeConfig,
+};
+
+/// Applies a changed [`RuntimeConfig`] to a running trigger.
+///
+/// Only settings which can be changed without disrupting running instances
+/// are applied: log levels, config providers, and the key-value stores and
+/// SQLite databases given to new instances. Other settings take effect when
+/// the trigger is restarted.
+#[derive(Clone, Default)]
+pub struct RuntimeConfigReloader {
+    components: Arc<Mutex<Option<ReloadableComponents>>>,
+}
+
+// The host components whose runtime config can be replaced.
+pub(crate) struct ReloadableComponents {
+    pub config: ConfigHostComponent,
+    pub key_value: SharedStoreManager,
+    pub sqlite: SharedConnectionsStore,
+}
+
+impl RuntimeConfigReloader {
+    pub(crate) fn set_components(&self, components: ReloadableComponents) {
+        *self.components.lock().unwrap() = Some(components);
+    }
+
+    /// Applies the given runtime config. If any part of it is invalid,
+    /// nothing is applied.
+    pub fn reload(&self, runtime_config: &RuntimeConfig) -> Result<()> {
+        let log_levels = runtime_config.log_levels()?;
+        let components = self.components.lock().unwrap();
+        if let Some(components) = components.as_ref() {
+            let config_providers = runtime_config.config_providers()?;
+            let store_manager = key_value::build_reloaded_store_manager(runtime_config)?;
+            let connections_store = sqlite::build_reloaded_connections_store(runtime_config)?;
+
+            components.config.set_providers(config_providers);
+            *components.key_value.write().unwrap() = store_manager;
+            *components.sqlite.write().unwrap() = connections_store;
+        }
+        spin_telemetry::set_log_levels(log_levels);
+        Ok(())
+    }
+}
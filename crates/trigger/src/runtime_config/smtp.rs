@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use outbound_smtp::{OutboundSmtpComponent, SmtpServerConfig, SmtpTls};
+use serde::Deserialize;
+
+use super::RuntimeConfig;
+
+/// A server from a `[smtp_server.<name>]` runtime config section, which
+/// components send email through by name.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpServerOpts {
+    pub host: String,
+
+    /// Defaults to the usual port for the TLS mode: 587 for STARTTLS, 465
+    /// for TLS and 25 for none.
+    #[serde(default)]
+    pub port: Option<u16>,
+
+    #[serde(default)]
+    pub tls: SmtpTlsOpts,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// The sender of messages which don't give one.
+    #[serde(default)]
+    pub default_sender: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTlsOpts {
+    #[default]
+    Starttls,
+    Tls,
+    None,
+}
+
+impl SmtpServerOpts {
+    fn config(&self, name: &str) -> Result<SmtpServerConfig> {
+        let credentials = match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+            (None, None) => None,
+            _ => bail!("SMTP server {name:?} must set both username and password, or neither"),
+        };
+        let (tls, default_port) = match self.tls {
+            SmtpTlsOpts::Starttls => (SmtpTls::Starttls, 587),
+            SmtpTlsOpts::Tls => (SmtpTls::Tls, 465),
+            SmtpTlsOpts::None => (SmtpTls::None, 25),
+        };
+        Ok(SmtpServerConfig {
+            host: self.host.clone(),
+            port: self.port.unwrap_or(default_port),
+            tls,
+            credentials,
+            default_sender: self.default_sender.clone(),
+        })
+    }
+}
+
+/// Returns the configured SMTP servers by name. A server is configured by
+/// the highest precedence runtime config file which names it.
+pub fn smtp_servers(config: &RuntimeConfig) -> Result<HashMap<String, SmtpServerConfig>> {
+    let mut servers = HashMap::new();
+    for opts in config.opts_layers() {
+        for (name, server) in &opts.smtp_servers {
+            if !servers.contains_key(name) {
+                servers.insert(name.to_owned(), server.config(name)?);
+            }
+        }
+    }
+    Ok(servers)
+}
+
+/// Builds the smtp host component from the configured servers.
+pub(crate) fn build_component(config: &RuntimeConfig) -> Result<OutboundSmtpComponent> {
+    OutboundSmtpComponent::new(smtp_servers(config)?)
+}
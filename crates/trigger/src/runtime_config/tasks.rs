@@ -0,0 +1,21 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use spin_tasks::{DatabaseLocation, TaskStore, TasksComponent};
+
+use super::{sqlite::SpinSqliteDatabaseOpts, RuntimeConfig};
+
+/// Builds a [`TasksComponent`] which persists tasks in their own table of
+/// the default SQLite database's file, or only in memory if the state
+/// directory is unset.
+pub(crate) fn build_component(runtime_config: &RuntimeConfig) -> Result<TasksComponent> {
+    let location = match SpinSqliteDatabaseOpts::default(runtime_config).path {
+        Some(path) => {
+            // Create the store's parent directory if necessary
+            fs::create_dir_all(path.parent().unwrap()).context("Failed to create task store")?;
+            DatabaseLocation::Path(path)
+        }
+        None => DatabaseLocation::InMemory,
+    };
+    Ok(TasksComponent::new(TaskStore::new(location)))
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{bail, ensure, Result};
+use serde::Deserialize;
+use spin_wasi_nn::{WasiNn, WasiNnComponent};
+use spin_world::wasi_nn::{ExecutionTarget, GraphEncoding};
+
+use super::{resolve_config_path, RuntimeConfig};
+
+/// Runtime configuration for the wasi-nn interface.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasiNnOpts {
+    /// The backends components may load graphs with. If unset, all those
+    /// which Spin was built with are enabled.
+    #[serde(default)]
+    pub backends: Option<Vec<WasiNnBackend>>,
+
+    /// Models which components load by name, keyed by name.
+    #[serde(rename = "model", default)]
+    pub models: HashMap<String, WasiNnModelOpts>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WasiNnBackend {
+    Onnx,
+    Openvino,
+}
+
+impl WasiNnBackend {
+    // The backends which Spin was built with, each with its cargo feature.
+    const BUILT: &'static [Self] = &[
+        #[cfg(feature = "wasi-nn-onnx")]
+        Self::Onnx,
+        #[cfg(feature = "wasi-nn-openvino")]
+        Self::Openvino,
+    ];
+
+    fn feature(self) -> &'static str {
+        match self {
+            Self::Onnx => "wasi-nn-onnx",
+            Self::Openvino => "wasi-nn-openvino",
+        }
+    }
+
+    fn encoding(self) -> GraphEncoding {
+        match self {
+            Self::Onnx => GraphEncoding::Onnx,
+            Self::Openvino => GraphEncoding::Openvino,
+        }
+    }
+}
+
+/// A model from a `[wasi_nn.model.<name>]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasiNnModelOpts {
+    pub backend: WasiNnBackend,
+
+    /// The files holding the model's serialized parts, e.g. an OpenVINO
+    /// model's XML description and weights, in that order. Relative paths
+    /// are relative to the runtime config file.
+    pub files: Vec<PathBuf>,
+
+    #[serde(default)]
+    pub target: WasiNnTarget,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WasiNnTarget {
+    #[default]
+    Cpu,
+    Gpu,
+    Tpu,
+}
+
+impl From<WasiNnTarget> for ExecutionTarget {
+    fn from(target: WasiNnTarget) -> Self {
+        match target {
+            WasiNnTarget::Cpu => Self::Cpu,
+            WasiNnTarget::Gpu => Self::Gpu,
+            WasiNnTarget::Tpu => Self::Tpu,
+        }
+    }
+}
+
+/// Builds the wasi-nn host component from the highest precedence runtime
+/// config file which configures it, with model paths resolved.
+pub(crate) fn build_component(config: &RuntimeConfig) -> Result<WasiNnComponent> {
+    let layer = config.opts_layers().find(|opts| opts.wasi_nn.is_some());
+    let default_opts = WasiNnOpts::default();
+    let opts = layer
+        .and_then(|layer| layer.wasi_nn.as_ref())
+        .unwrap_or(&default_opts);
+
+    let backends = opts.backends.as_deref().unwrap_or(WasiNnBackend::BUILT);
+    let mut wasi_nn = WasiNn::new();
+    for &backend in backends {
+        if !WasiNnBackend::BUILT.contains(&backend) {
+            bail!(
+                "the wasi-nn {backend:?} backend is not available; Spin must be built with the `{}` feature",
+                backend.feature()
+            );
+        }
+        match backend {
+            #[cfg(feature = "wasi-nn-onnx")]
+            WasiNnBackend::Onnx => {
+                wasi_nn.add_backend(GraphEncoding::Onnx, spin_wasi_nn::OnnxBackend)
+            }
+            #[cfg(feature = "wasi-nn-openvino")]
+            WasiNnBackend::Openvino => wasi_nn.add_backend(
+                GraphEncoding::Openvino,
+                spin_wasi_nn::OpenvinoBackend::default(),
+            ),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("only built backends are added"),
+        }
+    }
+
+    for (name, model) in &opts.models {
+        ensure!(
+            backends.contains(&model.backend),
+            "wasi-nn model {name:?} uses the {:?} backend, which is not enabled",
+            model.backend
+        );
+        let files = model
+            .files
+            .iter()
+            // A model is only configured if a layer has wasi-nn settings.
+            .map(|path| resolve_config_path(path, layer.unwrap()))
+            .collect::<Result<_>>()?;
+        wasi_nn.add_model(name, model.backend.encoding(), model.target.into(), files);
+    }
+    Ok(WasiNnComponent::new(wasi_nn))
+}
@@ -0,0 +1,156 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use spin_core::wasmtime::{self, InstanceAllocationStrategy, PoolingAllocationConfig};
+
+use super::RuntimeConfig;
+
+/// Runtime configuration for the Wasmtime engine. Unset values use
+/// Wasmtime's defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmtimeOpts {
+    /// How memory for instances is allocated. Defaults to `on_demand`.
+    #[serde(default)]
+    pub allocation_strategy: Option<AllocationStrategyOpt>,
+
+    /// Limits for the pooling allocator, used when `allocation_strategy` is
+    /// `pooling`.
+    #[serde(default)]
+    pub pooling: PoolingOpts,
+
+    /// The maximum size, in bytes, of linear memories which are allocated
+    /// up front and never moved.
+    #[serde(default)]
+    pub static_memory_maximum_size: Option<u64>,
+    /// The size, in bytes, of the guard region after static memories.
+    #[serde(default)]
+    pub static_memory_guard_size: Option<u64>,
+    /// The size, in bytes, of the guard region after dynamic memories.
+    #[serde(default)]
+    pub dynamic_memory_guard_size: Option<u64>,
+    /// The size, in bytes, reserved for dynamic memories to grow into
+    /// without being moved.
+    #[serde(default)]
+    pub dynamic_memory_reserved_for_growth: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationStrategyOpt {
+    /// Allocate memory for each instance as it is created.
+    OnDemand,
+    /// Allocate memory for a fixed number of instances at startup, and reuse
+    /// it for each instance.
+    Pooling,
+}
+
+/// Limits for Wasmtime's pooling allocator. Each limit applies to every
+/// instance in the pool.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolingOpts {
+    /// The maximum number of concurrent instances.
+    #[serde(default)]
+    pub instance_count: Option<u32>,
+    /// The maximum number of linear memories per instance.
+    #[serde(default)]
+    pub instance_memories: Option<u32>,
+    /// The maximum number of tables per instance.
+    #[serde(default)]
+    pub instance_tables: Option<u32>,
+    /// The maximum number of 64 KiB pages in each linear memory.
+    #[serde(default)]
+    pub instance_memory_pages: Option<u64>,
+    /// The maximum number of elements in each table.
+    #[serde(default)]
+    pub instance_table_elements: Option<u32>,
+    /// The maximum size, in bytes, of each instance's runtime state.
+    #[serde(default)]
+    pub instance_size: Option<usize>,
+    /// The maximum number of unused slots kept warm for reuse.
+    #[serde(default)]
+    pub max_unused_warm_slots: Option<u32>,
+}
+
+impl WasmtimeOpts {
+    /// Applies these options to the given Wasmtime config.
+    pub fn update_wasmtime_config(&self, config: &mut wasmtime::Config) -> Result<()> {
+        if let Some(size) = self.static_memory_maximum_size {
+            config.static_memory_maximum_size(size);
+        }
+        if let Some(size) = self.static_memory_guard_size {
+            config.static_memory_guard_size(size);
+        }
+        if let Some(size) = self.dynamic_memory_guard_size {
+            config.dynamic_memory_guard_size(size);
+        }
+        if let Some(size) = self.dynamic_memory_reserved_for_growth {
+            config.dynamic_memory_reserved_for_growth(size);
+        }
+
+        let pooling = self.allocation_strategy == Some(AllocationStrategyOpt::Pooling);
+        if pooling {
+            config.allocation_strategy(InstanceAllocationStrategy::Pooling(
+                self.pooling.build_config(),
+            ));
+        } else if self.pooling != PoolingOpts::default() {
+            bail!("[wasmtime.pooling] limits require wasmtime allocation_strategy = \"pooling\"");
+        }
+        Ok(())
+    }
+}
+
+impl PoolingOpts {
+    fn build_config(&self) -> PoolingAllocationConfig {
+        let mut config = PoolingAllocationConfig::default();
+        if let Some(count) = self.instance_count {
+            config.instance_count(count);
+        }
+        if let Some(memories) = self.instance_memories {
+            config.instance_memories(memories);
+        }
+        if let Some(tables) = self.instance_tables {
+            config.instance_tables(tables);
+        }
+        if let Some(pages) = self.instance_memory_pages {
+            config.instance_memory_pages(pages);
+        }
+        if let Some(elements) = self.instance_table_elements {
+            config.instance_table_elements(elements);
+        }
+        if let Some(size) = self.instance_size {
+            config.instance_size(size);
+        }
+        if let Some(slots) = self.max_unused_warm_slots {
+            config.max_unused_warm_slots(slots);
+        }
+        config
+    }
+}
+
+/// Builds the [`WasmtimeOpts`] from the given [`RuntimeConfig`]. Each setting
+/// is taken from the highest precedence layer that sets it.
+pub(crate) fn build_opts(runtime_config: &RuntimeConfig) -> WasmtimeOpts {
+    let sections = || {
+        runtime_config
+            .opts_layers()
+            .filter_map(|opts| opts.wasmtime.as_ref())
+    };
+    WasmtimeOpts {
+        allocation_strategy: sections().find_map(|w| w.allocation_strategy),
+        pooling: PoolingOpts {
+            instance_count: sections().find_map(|w| w.pooling.instance_count),
+            instance_memories: sections().find_map(|w| w.pooling.instance_memories),
+            instance_tables: sections().find_map(|w| w.pooling.instance_tables),
+            instance_memory_pages: sections().find_map(|w| w.pooling.instance_memory_pages),
+            instance_table_elements: sections().find_map(|w| w.pooling.instance_table_elements),
+            instance_size: sections().find_map(|w| w.pooling.instance_size),
+            max_unused_warm_slots: sections().find_map(|w| w.pooling.max_unused_warm_slots),
+        },
+        static_memory_maximum_size: sections().find_map(|w| w.static_memory_maximum_size),
+        static_memory_guard_size: sections().find_map(|w| w.static_memory_guard_size),
+        dynamic_memory_guard_size: sections().find_map(|w| w.dynamic_memory_guard_size),
+        dynamic_memory_reserved_for_growth: sections()
+            .find_map(|w| w.dynamic_memory_reserved_for_growth),
+    }
+}
@@ -0,0 +1,130 @@
+//! Seed data for local applications.
+//!
+//! When a local application starts under `spin watch`, or with `--seed`, the
+//! files in its `.spin/seed/` directory are applied to its default stores, so
+//! that it starts from known data:
+//!
+//! - Each `*.sql` file is executed against the default SQLite database.
+//! - Each `*.json` file is an object whose entries are set in the default key
+//!   value store, unless it already contains their keys. String values are
+//!   stored as they are, and other values as JSON.
+//!
+//! Files are applied in order of name. Seeds are applied every time the
+//! application starts, so SQL seeds should be idempotent, e.g. using
+//! `CREATE TABLE IF NOT EXISTS` and `INSERT OR REPLACE`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// The directory, relative to the application directory, which holds seeds.
+pub(crate) const SEED_DIR: &str = ".spin/seed";
+
+/// The data with which to seed an application's default stores.
+#[derive(Debug, Default)]
+pub(crate) struct Seed {
+    /// SQL statements for the default database, as `@<file>` references.
+    pub sqlite: Vec<String>,
+    /// Entries for the default key value store.
+    pub kv: Vec<(String, String)>,
+}
+
+impl Seed {
+    /// Loads the seeds of the application in the given directory. If the
+    /// application has no seed directory, the seed is empty.
+    pub fn load(app_dir: &Path) -> Result<Self> {
+        let dir = app_dir.join(SEED_DIR);
+        if !dir.is_dir() {
+            return Ok(Self::default());
+        }
+        let mut files = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read seed directory {dir:?}"))?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()
+            .with_context(|| format!("Failed to read seed directory {dir:?}"))?;
+        files.sort();
+
+        let mut seed = Self::default();
+        for file in files {
+            match file.extension().and_then(|ext| ext.to_str()) {
+                Some("sql") => seed.sqlite.push(format!("@{}", file.display())),
+                Some("json") => seed.kv.extend(read_kv_fixture(&file)?),
+                _ => tracing::debug!("Ignoring {file:?} in seed directory"),
+            }
+        }
+        if !seed.sqlite.is_empty() || !seed.kv.is_empty() {
+            tracing::info!("Seeding default stores from {dir:?}");
+        }
+        Ok(seed)
+    }
+}
+
+fn read_kv_fixture(path: &Path) -> Result<Vec<(String, String)>> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Failed to read seed file {path:?}"))?;
+    let Value::Object(entries) = serde_json::from_slice(&contents)
+        .with_context(|| format!("Seed file {path:?} is not valid JSON"))?
+    else {
+        bail!("Seed file {path:?} must contain a JSON object of keys and values");
+    };
+    Ok(entries
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s),
+            value => (key, value.to_string()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_loaded_in_name_order() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let dir = app_dir.path().join(SEED_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("02-data.sql"), "INSERT INTO t VALUES (1);").unwrap();
+        std::fs::write(dir.join("01-schema.sql"), "CREATE TABLE t (x);").unwrap();
+        std::fs::write(
+            dir.join("kv.json"),
+            r#"{"greeting": "hello", "limits": {"max": 3}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.md"), "ignored").unwrap();
+
+        let seed = Seed::load(app_dir.path()).unwrap();
+        assert_eq!(
+            seed.sqlite,
+            [
+                format!("@{}", dir.join("01-schema.sql").display()),
+                format!("@{}", dir.join("02-data.sql").display()),
+            ]
+        );
+        assert_eq!(
+            seed.kv,
+            [
+                ("greeting".to_owned(), "hello".to_owned()),
+                ("limits".to_owned(), r#"{"max":3}"#.to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_seed_directory_is_empty() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let seed = Seed::load(app_dir.path()).unwrap();
+        assert!(seed.sqlite.is_empty() && seed.kv.is_empty());
+    }
+
+    #[test]
+    fn kv_fixtures_must_be_objects() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let dir = app_dir.path().join(SEED_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("kv.json"), "[1, 2]").unwrap();
+        assert!(Seed::load(app_dir.path()).is_err());
+    }
+}
@@ -1,10 +1,14 @@
 use std::{
     collections::HashSet,
     fs::File,
+    io::ErrorKind,
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 
 use anyhow::{Context, Result};
+use spin_telemetry::{LogFormat, LogLevels, LogTarget};
+use tracing::{level_filters::LevelFilter, Level};
 
 use crate::{runtime_config::RuntimeConfig, TriggerHooks};
 
@@ -36,31 +40,78 @@ impl Default for FollowComponents {
     }
 }
 
+/// Limits on the size and age of component log files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Rotate a log file once it reaches this many bytes. If unset, log files
+    /// are never rotated.
+    pub max_size: Option<u64>,
+    /// The number of rotated files to keep for each log file.
+    pub max_files: usize,
+    /// Delete rotated files which were last written longer ago than this.
+    pub max_age: Option<Duration>,
+}
+
 /// Implements TriggerHooks, writing logs to a log file and (optionally) stderr
 pub struct StdioLoggingTriggerHooks {
     follow_components: FollowComponents,
+    log_format: LogFormat,
+    log_target: LogTarget,
     log_dir: Option<PathBuf>,
+    log_rotation: Option<LogRotation>,
+    log_levels: LogLevels,
 }
 
 impl StdioLoggingTriggerHooks {
-    pub fn new(follow_components: FollowComponents) -> Self {
+    pub fn new(
+        follow_components: FollowComponents,
+        log_format: LogFormat,
+        log_target: LogTarget,
+    ) -> Self {
         Self {
             follow_components,
+            log_format,
+            log_target,
             log_dir: None,
+            log_rotation: None,
+            log_levels: LogLevels::default(),
         }
     }
 
     fn component_stdio_writer(
         &self,
         component_id: &str,
-        log_suffix: &str,
+        log_suffix: &'static str,
         log_dir: &Path,
+        request_id: &str,
     ) -> Result<ComponentStdioWriter> {
         let sanitized_component_id = sanitize_filename::sanitize(component_id);
         let log_path = log_dir.join(format!("{sanitized_component_id}_{log_suffix}.txt"));
-        let follow = self.follow_components.should_follow(component_id);
-        ComponentStdioWriter::new(&log_path, follow)
-            .with_context(|| format!("Failed to open log file {log_path:?}"))
+        let level = self
+            .log_levels
+            .component_level(component_id)
+            .unwrap_or(LevelFilter::TRACE);
+        let follow =
+            self.follow_components.should_follow(component_id) && level >= stream_level(log_suffix);
+        let mut writer = ComponentStdioWriter::new(&log_path, follow)
+            .with_context(|| format!("Failed to open log file {log_path:?}"))?;
+        if let Some(rotation) = &self.log_rotation {
+            writer = writer.with_rotation(rotation.clone());
+        }
+        let json = self.log_format == LogFormat::Json;
+        let events = self.log_target != LogTarget::Stderr;
+        if json || events {
+            writer = writer.with_line_output(LineOutput {
+                context: RecordContext {
+                    component_id: component_id.to_owned(),
+                    request_id: request_id.to_owned(),
+                    stream: log_suffix,
+                },
+                json,
+                events,
+            });
+        }
+        Ok(writer)
     }
 
     fn validate_follows(&self, app: &spin_app::App) -> anyhow::Result<()> {
@@ -90,6 +141,8 @@ impl TriggerHooks for StdioLoggingTriggerHooks {
         runtime_config: &RuntimeConfig,
     ) -> anyhow::Result<()> {
         self.log_dir = runtime_config.log_dir();
+        self.log_rotation = Some(runtime_config.log_rotation());
+        self.log_levels = runtime_config.log_levels()?;
 
         self.validate_follows(app)?;
 
@@ -98,6 +151,12 @@ impl TriggerHooks for StdioLoggingTriggerHooks {
             std::fs::create_dir_all(dir)
                 .with_context(|| format!("Failed to create log dir {dir:?}"))?;
 
+            if let Some(max_age) = self.log_rotation.as_ref().and_then(|r| r.max_age) {
+                if let Err(err) = remove_expired_logs(dir, max_age) {
+                    tracing::warn!("Failed to clean up log dir {dir:?}: {err}");
+                }
+            }
+
             println!("Logging component stdio to {:?}", dir.join(""))
         }
 
@@ -111,8 +170,25 @@ impl TriggerHooks for StdioLoggingTriggerHooks {
     ) -> anyhow::Result<()> {
         match &self.log_dir {
             Some(l) => {
-                builder.stdout_pipe(self.component_stdio_writer(component.id(), "stdout", l)?);
-                builder.stderr_pipe(self.component_stdio_writer(component.id(), "stderr", l)?);
+                // Each store serves a single request, so its output can be
+                // correlated by the request's ID, or failing that by an ID
+                // generated here. Pooled stores are built ahead of their
+                // request, so the writer also looks up the request's ID as
+                // it writes.
+                let request_id = spin_telemetry::current_request_id()
+                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                builder.stdout_pipe(self.component_stdio_writer(
+                    component.id(),
+                    "stdout",
+                    l,
+                    &request_id,
+                )?);
+                builder.stderr_pipe(self.component_stdio_writer(
+                    component.id(),
+                    "stderr",
+                    l,
+                    &request_id,
+                )?);
             }
             None => {
                 builder.inherit_stdout();
@@ -127,23 +203,180 @@ impl TriggerHooks for StdioLoggingTriggerHooks {
 /// ComponentStdioWriter forwards output to a log file and (optionally) stderr
 pub struct ComponentStdioWriter {
     log_file: File,
+    log_path: PathBuf,
     follow: bool,
+    lines: Option<LineRecords>,
+    rotation: Option<ActiveRotation>,
+}
+
+struct ActiveRotation {
+    policy: LogRotation,
+    max_size: u64,
+}
+
+/// Identifies the source of component output which is written line by line.
+pub struct RecordContext {
+    pub component_id: String,
+    /// The request ID used when output is written outside of any request.
+    pub request_id: String,
+    pub stream: &'static str,
+}
+
+impl RecordContext {
+    // The ID of the request being handled as output is written.
+    fn current_request_id(&self) -> String {
+        spin_telemetry::current_request_id().unwrap_or_else(|| self.request_id.clone())
+    }
+}
+
+/// How component output is written when it is written line by line.
+pub struct LineOutput {
+    pub context: RecordContext,
+    /// Write each line as a JSON record rather than verbatim.
+    pub json: bool,
+    /// Emit followed lines as log events, for the configured log target,
+    /// rather than writing them to stderr.
+    pub events: bool,
+}
+
+struct LineRecords {
+    output: LineOutput,
+    // Output not yet terminated by a newline.
+    pending: Vec<u8>,
 }
 
 impl ComponentStdioWriter {
     pub fn new(log_path: &Path, follow: bool) -> anyhow::Result<Self> {
-        let log_file = File::options().create(true).append(true).open(log_path)?;
-        Ok(Self { log_file, follow })
+        let log_file = open_log_file(log_path)?;
+        Ok(Self {
+            log_file,
+            log_path: log_path.to_owned(),
+            follow,
+            lines: None,
+            rotation: None,
+        })
+    }
+
+    /// Rotates the log file according to the given policy.
+    pub fn with_rotation(mut self, policy: LogRotation) -> Self {
+        if let Some(max_size) = policy.max_size {
+            self.rotation = Some(ActiveRotation { policy, max_size });
+        }
+        self
+    }
+
+    // Many writers, in this and other processes, may share a log file, so
+    // any of them may have rotated it. The write goes on to whichever file is
+    // current, so failing to rotate doesn't fail it.
+    fn rotate_if_needed(&mut self) -> std::io::Result<()> {
+        let Some(rotation) = &self.rotation else {
+            return Ok(());
+        };
+        if log_file_size(&self.log_path) >= rotation.max_size {
+            if let Err(err) = rotate_log_files(&self.log_path, rotation) {
+                tracing::warn!("Failed to rotate log file {:?}: {err}", self.log_path);
+            }
+        }
+        if !is_current_log_file(&self.log_file, &self.log_path) {
+            self.log_file = open_log_file(&self.log_path)?;
+        }
+        Ok(())
+    }
+
+    /// Writes output line by line rather than as it is received.
+    pub fn with_line_output(mut self, output: LineOutput) -> Self {
+        self.lines = Some(LineRecords {
+            output,
+            pending: vec![],
+        });
+        self
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let Some(lines) = &self.lines else {
+            return Ok(());
+        };
+        let LineOutput {
+            context,
+            json,
+            events,
+        } = &lines.output;
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        let level = stream_level(context.stream);
+        let request_id = context.current_request_id();
+
+        let mut record = if *json {
+            serde_json::to_vec(&serde_json::json!({
+                "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+                "level": level.as_str(),
+                "component_id": context.component_id,
+                "request_id": request_id,
+                "stream": context.stream,
+                "message": line,
+            }))?
+        } else {
+            line.as_bytes().to_vec()
+        };
+        record.push(b'\n');
+
+        if self.follow {
+            if *events {
+                emit_line_event(context, &request_id, level, line);
+            } else {
+                std::io::stderr().write_all(&record)?;
+            }
+        }
+        self.log_file.write_all(&record)
+    }
+}
+
+fn emit_line_event(context: &RecordContext, request_id: &str, level: Level, line: &str) {
+    let RecordContext {
+        component_id,
+        stream,
+        ..
+    } = context;
+    if level == Level::WARN {
+        tracing::warn!(
+            target: spin_telemetry::COMPONENT_OUTPUT_TARGET,
+            %component_id,
+            %request_id,
+            stream,
+            "{line}"
+        );
+    } else {
+        tracing::info!(
+            target: spin_telemetry::COMPONENT_OUTPUT_TARGET,
+            %component_id,
+            %request_id,
+            stream,
+            "{line}"
+        );
     }
 }
 
 impl std::io::Write for ComponentStdioWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let written = self.log_file.write(buf)?;
-        if self.follow {
-            std::io::stderr().write_all(&buf[..written])?;
+        self.rotate_if_needed()?;
+        let Some(lines) = &mut self.lines else {
+            let written = self.log_file.write(buf)?;
+            if self.follow {
+                std::io::stderr().write_all(&buf[..written])?;
+            }
+            return Ok(written);
+        };
+        lines.pending.extend_from_slice(buf);
+        let mut complete = vec![];
+        while let Some(end) = lines.pending.iter().position(|b| *b == b'\n') {
+            let mut line: Vec<u8> = lines.pending.drain(..=end).collect();
+            line.pop();
+            complete.push(line);
+        }
+        for line in complete {
+            self.write_line(&line)?;
         }
-        Ok(written)
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -155,6 +388,189 @@ impl std::io::Write for ComponentStdioWriter {
     }
 }
 
+impl Drop for ComponentStdioWriter {
+    fn drop(&mut self) {
+        let pending = match &mut self.lines {
+            Some(lines) if !lines.pending.is_empty() => std::mem::take(&mut lines.pending),
+            _ => return,
+        };
+        if let Err(err) = self.write_line(&pending) {
+            tracing::warn!("Failed to write component output: {err}");
+        }
+    }
+}
+
+// The level at which component output is logged: stderr lines are treated as
+// warnings and stdout lines as informational.
+fn stream_level(stream: &str) -> Level {
+    match stream {
+        "stderr" => Level::WARN,
+        _ => Level::INFO,
+    }
+}
+
+fn open_log_file(log_path: &Path) -> std::io::Result<File> {
+    File::options().create(true).append(true).open(log_path)
+}
+
+fn log_file_size(log_path: &Path) -> u64 {
+    std::fs::metadata(log_path)
+        .map(|m| m.len())
+        .unwrap_or_default()
+}
+
+// Whether the open log file is still the one at its path, rather than one
+// which has since been rotated.
+#[cfg(unix)]
+fn is_current_log_file(file: &File, log_path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), std::fs::metadata(log_path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+// Whether the open log file is still the one at its path, rather than one
+// which has since been rotated. Log files are only appended to, so files of
+// different sizes must be different files.
+#[cfg(not(unix))]
+fn is_current_log_file(file: &File, log_path: &Path) -> bool {
+    match (file.metadata(), std::fs::metadata(log_path)) {
+        (Ok(open), Ok(current)) => open.len() == current.len(),
+        _ => false,
+    }
+}
+
+fn rotated_log_path(log_path: &Path, index: usize) -> PathBuf {
+    let mut path = log_path.as_os_str().to_owned();
+    path.push(format!(".{index}"));
+    path.into()
+}
+
+// Whether the file is a rotated component log file, named
+// `<component>_<stream>.txt.<index>`.
+fn is_rotated_log_path(path: &Path) -> bool {
+    let Some((log_name, index)) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.rsplit_once('.'))
+    else {
+        return false;
+    };
+    index.parse::<usize>().is_ok()
+        && ["_stdout.txt", "_stderr.txt"].iter().any(|suffix| {
+            log_name
+                .strip_suffix(suffix)
+                .map_or(false, |component| !component.is_empty())
+        })
+}
+
+// A rotation lock held for a log file by creating `<log>.lock`, so that
+// writers in different processes don't rotate the same file at once.
+struct RotationLock(PathBuf);
+
+// A lock older than this was left behind by a writer which stopped while
+// rotating, and is removed.
+const STALE_ROTATION_LOCK_AGE: Duration = Duration::from_secs(10);
+
+impl RotationLock {
+    // Returns `None` if another writer holds the lock.
+    fn try_acquire(log_path: &Path) -> std::io::Result<Option<Self>> {
+        let mut lock_path = log_path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+        match File::options()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(_) => Ok(Some(Self(lock_path))),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                let stale = std::fs::metadata(&lock_path)
+                    .and_then(|m| m.modified())
+                    .map(|modified| {
+                        modified.elapsed().unwrap_or_default() > STALE_ROTATION_LOCK_AGE
+                    })
+                    .unwrap_or_default();
+                if stale {
+                    ignore_not_found(std::fs::remove_file(&lock_path))?;
+                }
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Drop for RotationLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Renames `<log>` to `<log>.1`, shifting existing rotated files up by one and
+// dropping any beyond `max_files`. If another writer is rotating the file,
+// or already has, this does nothing.
+fn rotate_log_files(log_path: &Path, rotation: &ActiveRotation) -> std::io::Result<()> {
+    let Some(_lock) = RotationLock::try_acquire(log_path)? else {
+        return Ok(());
+    };
+    if log_file_size(log_path) < rotation.max_size {
+        return Ok(());
+    }
+    let policy = &rotation.policy;
+    if policy.max_files == 0 {
+        return ignore_not_found(std::fs::remove_file(log_path));
+    }
+    ignore_not_found(std::fs::remove_file(rotated_log_path(
+        log_path,
+        policy.max_files,
+    )))?;
+    for index in (1..policy.max_files).rev() {
+        ignore_not_found(std::fs::rename(
+            rotated_log_path(log_path, index),
+            rotated_log_path(log_path, index + 1),
+        ))?;
+    }
+    ignore_not_found(std::fs::rename(log_path, rotated_log_path(log_path, 1)))?;
+
+    if let (Some(max_age), Some(dir)) = (policy.max_age, log_path.parent()) {
+        if let Err(err) = remove_expired_logs(dir, max_age) {
+            tracing::warn!("Failed to clean up log dir {dir:?}: {err}");
+        }
+    }
+    Ok(())
+}
+
+// Deletes rotated component log files in `dir` which were last modified
+// longer ago than `max_age`. Other files in the directory are left alone.
+fn remove_expired_logs(dir: &Path, max_age: Duration) -> std::io::Result<()> {
+    let now = SystemTime::now();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !is_rotated_log_path(&path) {
+            continue;
+        }
+        // Another writer may be rotating or removing the same files.
+        let modified = match std::fs::metadata(&path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        if now.duration_since(modified).unwrap_or_default() > max_age {
+            ignore_not_found(std::fs::remove_file(&path))?;
+        }
+    }
+    Ok(())
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
 fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> String {
     items
         .into_iter()
@@ -162,3 +578,185 @@ fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> Stri
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn rotates_log_files_at_max_size() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("component_stdout.txt");
+        let policy = LogRotation {
+            max_size: Some(10),
+            max_files: 2,
+            max_age: None,
+        };
+        let mut writer = ComponentStdioWriter::new(&log_path, false)?.with_rotation(policy);
+
+        for line in ["first line\n", "second line\n", "third line\n", "fourth\n"] {
+            writer.write_all(line.as_bytes())?;
+        }
+
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&log_path), "fourth\n");
+        assert_eq!(read(&rotated_log_path(&log_path, 1)), "third line\n");
+        assert_eq!(read(&rotated_log_path(&log_path, 2)), "second line\n");
+        assert!(!rotated_log_path(&log_path, 3).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn removes_only_expired_rotated_logs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let current = dir.path().join("component_stdout.txt");
+        let rotated = rotated_log_path(&current, 1);
+        std::fs::write(&current, "current")?;
+        std::fs::write(&rotated, "rotated")?;
+
+        remove_expired_logs(dir.path(), Duration::from_secs(60))?;
+        assert!(rotated.exists());
+
+        std::thread::sleep(Duration::from_millis(10));
+        remove_expired_logs(dir.path(), Duration::ZERO)?;
+        assert!(current.exists());
+        assert!(!rotated.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_other_numbered_files_in_log_dir() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let others = [
+            "backup.2023",
+            "db.1",
+            "_stdout.txt.1",
+            "component_stdout.txt.old",
+        ]
+        .map(|name| dir.path().join(name));
+        for path in &others {
+            std::fs::write(path, "other")?;
+        }
+        let rotated = rotated_log_path(&dir.path().join("component_stderr.txt"), 3);
+        std::fs::write(&rotated, "rotated")?;
+
+        std::thread::sleep(Duration::from_millis(10));
+        remove_expired_logs(dir.path(), Duration::ZERO)?;
+        assert!(others.iter().all(|path| path.exists()));
+        assert!(!rotated.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_is_skipped_while_another_writer_rotates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("component_stdout.txt");
+        let policy = LogRotation {
+            max_size: Some(10),
+            max_files: 1,
+            max_age: None,
+        };
+        let mut writer = ComponentStdioWriter::new(&log_path, false)?.with_rotation(policy);
+        writer.write_all(b"first line\n")?;
+
+        let lock = RotationLock::try_acquire(&log_path)?.expect("lock");
+        assert!(RotationLock::try_acquire(&log_path)?.is_none());
+        writer.write_all(b"second line\n")?;
+        assert!(!rotated_log_path(&log_path, 1).exists());
+
+        drop(lock);
+        writer.write_all(b"third line\n")?;
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&log_path), "third line\n");
+        assert_eq!(
+            read(&rotated_log_path(&log_path, 1)),
+            "first line\nsecond line\n"
+        );
+        Ok(())
+    }
+
+    fn json_writer(log_path: &Path, stream: &'static str) -> Result<ComponentStdioWriter> {
+        Ok(
+            ComponentStdioWriter::new(log_path, false)?.with_line_output(LineOutput {
+                context: RecordContext {
+                    component_id: "hello".to_owned(),
+                    request_id: "startup".to_owned(),
+                    stream,
+                },
+                json: true,
+                events: false,
+            }),
+        )
+    }
+
+    fn read_records(log_path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(log_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_json_record_per_line() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stderr.txt");
+        let mut writer = json_writer(&log_path, "stderr")?;
+        writer.write_all(b"first\r\nsec")?;
+        writer.write_all(b"ond\nunterminated")?;
+        drop(writer);
+
+        let records = read_records(&log_path);
+        let messages = records
+            .iter()
+            .map(|record| record["message"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["first", "second", "unterminated"]);
+
+        let record = records[0].as_object().unwrap();
+        let mut keys = record.keys().map(String::as_str).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "component_id",
+                "level",
+                "message",
+                "request_id",
+                "stream",
+                "timestamp"
+            ]
+        );
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["component_id"], "hello");
+        assert_eq!(record["request_id"], "startup");
+        assert_eq!(record["stream"], "stderr");
+        let timestamp = record["timestamp"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_json_record_messages() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stdout.txt");
+        let message = "say \"hi\"\tto C:\\temp \u{1b}[31mred\u{1b}[0m caf\u{e9}";
+        let mut writer = json_writer(&log_path, "stdout")?;
+        writer.write_all(format!("{message}\n").as_bytes())?;
+        writer.write_all(b"invalid \xff utf-8\n")?;
+        drop(writer);
+
+        let contents = std::fs::read_to_string(&log_path)?;
+        // Control characters are escaped, so each record is a single line.
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents.contains(r#"say \"hi\"\tto C:\\temp \u001b[31mred"#));
+
+        let records = read_records(&log_path);
+        assert_eq!(records[0]["message"], message);
+        assert_eq!(records[0]["level"], "INFO");
+        assert_eq!(records[1]["message"], "invalid \u{fffd} utf-8");
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use spin_core::HostComponent;
+use spin_world::wasi_nn;
+
+use crate::{WasiNn, WasiNnDispatch};
+
+/// The wasi-nn host component, which gives every component the same
+/// backends and named models.
+pub struct WasiNnComponent {
+    wasi_nn: Arc<WasiNn>,
+}
+
+impl WasiNnComponent {
+    pub fn new(wasi_nn: WasiNn) -> Self {
+        Self {
+            wasi_nn: Arc::new(wasi_nn),
+        }
+    }
+}
+
+impl HostComponent for WasiNnComponent {
+    type Data = WasiNnDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        wasi_nn::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        WasiNnDispatch::new(self.wasi_nn.clone())
+    }
+}
@@ -0,0 +1,384 @@
+//! Lets components run inference on machine learning models with the wasi-nn
+//! interface, using the host's ONNX and OpenVINO backends. Each backend is
+//! only built with its cargo feature, `onnx` or `openvino`.
+
+mod host_component;
+#[cfg(feature = "onnx")]
+mod onnx;
+#[cfg(feature = "openvino")]
+mod openvino;
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use spin_app::async_trait;
+use spin_key_value::table;
+use spin_world::wasi_nn::{self, ExecutionTarget, GraphEncoding, Tensor};
+use tracing::instrument;
+
+pub use host_component::WasiNnComponent;
+#[cfg(feature = "onnx")]
+pub use onnx::OnnxBackend;
+#[cfg(feature = "openvino")]
+pub use openvino::OpenvinoBackend;
+
+/// An ML backend, which loads graphs in one encoding.
+pub trait Backend: Send + Sync {
+    /// Loads a graph from its serialized parts, to run on the given target.
+    fn load(&self, builders: &[Vec<u8>], target: ExecutionTarget) -> Result<Arc<dyn BackendGraph>>;
+}
+
+/// A graph loaded by a [`Backend`].
+pub trait BackendGraph: Send + Sync {
+    /// Creates the state for an inference with the graph.
+    fn init_execution_context(&self) -> Result<Box<dyn BackendExecutionContext>>;
+}
+
+/// The state of an inference with a [`BackendGraph`].
+pub trait BackendExecutionContext: Send {
+    /// Sets the input tensor at the given index.
+    fn set_input(&mut self, index: u32, tensor: &Tensor) -> Result<()>;
+
+    /// Runs inference with the inputs which have been set.
+    fn compute(&mut self) -> Result<()>;
+
+    /// Returns the data of the output tensor at the given index.
+    fn get_output(&mut self, index: u32) -> Result<Vec<u8>>;
+}
+
+/// The backends and named models available to components.
+#[derive(Default)]
+pub struct WasiNn {
+    backends: Vec<(GraphEncoding, Arc<dyn Backend>)>,
+    models: HashMap<String, NamedModel>,
+}
+
+// A model configured by the host, which components load by name. Its graph
+// is loaded on first use and then shared by all instances.
+struct NamedModel {
+    encoding: GraphEncoding,
+    target: ExecutionTarget,
+    files: Vec<PathBuf>,
+    graph: Mutex<Option<Arc<dyn BackendGraph>>>,
+}
+
+impl WasiNn {
+    /// Creates a [`WasiNn`] with no backends, so that components can't load
+    /// any graphs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets components load graphs of the given encoding with the backend.
+    pub fn add_backend(&mut self, encoding: GraphEncoding, backend: impl Backend + 'static) {
+        self.backends.push((encoding, Arc::new(backend)));
+    }
+
+    /// Lets components load the model in the given files by name. The files
+    /// are the graph's serialized parts, e.g. an OpenVINO model's XML
+    /// description and weights, in that order.
+    pub fn add_model(
+        &mut self,
+        name: impl Into<String>,
+        encoding: GraphEncoding,
+        target: ExecutionTarget,
+        files: Vec<PathBuf>,
+    ) {
+        let model = NamedModel {
+            encoding,
+            target,
+            files,
+            graph: Default::default(),
+        };
+        self.models.insert(name.into(), model);
+    }
+
+    fn backend(&self, encoding: GraphEncoding) -> Option<&Arc<dyn Backend>> {
+        self.backends
+            .iter()
+            .find_map(|(e, backend)| (*e == encoding).then_some(backend))
+    }
+
+    fn load(
+        &self,
+        builders: &[Vec<u8>],
+        encoding: GraphEncoding,
+        target: ExecutionTarget,
+    ) -> Result<Arc<dyn BackendGraph>, wasi_nn::Error> {
+        let backend = self
+            .backend(encoding)
+            .ok_or(wasi_nn::Error::InvalidEncoding)?;
+        backend.load(builders, target).map_err(runtime_error)
+    }
+
+    fn load_by_name(&self, name: &str) -> Result<Arc<dyn BackendGraph>, wasi_nn::Error> {
+        let model = self.models.get(name).ok_or(wasi_nn::Error::NotFound)?;
+        let mut graph = model.graph.lock().unwrap();
+        if let Some(graph) = graph.as_ref() {
+            return Ok(graph.clone());
+        }
+        let builders = model
+            .files
+            .iter()
+            .map(|path| {
+                std::fs::read(path).map_err(|err| {
+                    tracing::error!("Failed to read model {name:?} file {path:?}: {err}");
+                    wasi_nn::Error::RuntimeError
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let loaded = self.load(&builders, model.encoding, model.target)?;
+        *graph = Some(loaded.clone());
+        Ok(loaded)
+    }
+}
+
+// Logs a backend's error, which the interface has no way to describe.
+fn runtime_error(err: anyhow::Error) -> wasi_nn::Error {
+    tracing::error!("wasi-nn backend failed: {err:?}");
+    wasi_nn::Error::RuntimeError
+}
+
+/// An implementation of the wasi-nn host interface for a component.
+pub struct WasiNnDispatch {
+    wasi_nn: Arc<WasiNn>,
+    graphs: table::Table<Arc<dyn BackendGraph>>,
+    contexts: table::Table<Box<dyn BackendExecutionContext>>,
+}
+
+impl WasiNnDispatch {
+    fn new(wasi_nn: Arc<WasiNn>) -> Self {
+        Self {
+            wasi_nn,
+            graphs: table::Table::new(64),
+            contexts: table::Table::new(256),
+        }
+    }
+
+    fn push_graph(
+        &mut self,
+        graph: Arc<dyn BackendGraph>,
+    ) -> Result<wasi_nn::Graph, wasi_nn::Error> {
+        self.graphs.push(graph).map_err(|()| wasi_nn::Error::Busy)
+    }
+
+    fn context(
+        &mut self,
+        ctx: wasi_nn::GraphExecutionContext,
+    ) -> Result<&mut Box<dyn BackendExecutionContext>, wasi_nn::Error> {
+        self.contexts
+            .get_mut(ctx)
+            .ok_or(wasi_nn::Error::InvalidArgument)
+    }
+}
+
+// Backends are synchronous, and inference is CPU-bound, so each call blocks
+// the thread it runs on.
+#[async_trait]
+impl wasi_nn::Host for WasiNnDispatch {
+    #[instrument(name = "spin_wasi_nn.load", skip_all, fields(otel.kind = "client"))]
+    async fn load(
+        &mut self,
+        builder: Vec<wasi_nn::GraphBuilder>,
+        encoding: GraphEncoding,
+        target: ExecutionTarget,
+    ) -> anyhow::Result<Result<wasi_nn::Graph, wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            let graph = self.wasi_nn.load(&builder, encoding, target)?;
+            self.push_graph(graph)
+        }))
+    }
+
+    #[instrument(name = "spin_wasi_nn.load_by_name", skip(self), fields(otel.kind = "client"))]
+    async fn load_by_name(
+        &mut self,
+        name: String,
+    ) -> anyhow::Result<Result<wasi_nn::Graph, wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            let graph = self.wasi_nn.load_by_name(&name)?;
+            self.push_graph(graph)
+        }))
+    }
+
+    async fn init_execution_context(
+        &mut self,
+        graph: wasi_nn::Graph,
+    ) -> anyhow::Result<Result<wasi_nn::GraphExecutionContext, wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            let graph = self
+                .graphs
+                .get(graph)
+                .ok_or(wasi_nn::Error::InvalidArgument)?;
+            let context = graph.init_execution_context().map_err(runtime_error)?;
+            self.contexts
+                .push(context)
+                .map_err(|()| wasi_nn::Error::Busy)
+        }))
+    }
+
+    async fn set_input(
+        &mut self,
+        ctx: wasi_nn::GraphExecutionContext,
+        index: u32,
+        tensor: Tensor,
+    ) -> anyhow::Result<Result<(), wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            self.context(ctx)?
+                .set_input(index, &tensor)
+                .map_err(runtime_error)
+        }))
+    }
+
+    #[instrument(name = "spin_wasi_nn.compute", skip_all, fields(otel.kind = "client"))]
+    async fn compute(
+        &mut self,
+        ctx: wasi_nn::GraphExecutionContext,
+    ) -> anyhow::Result<Result<(), wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            self.context(ctx)?.compute().map_err(runtime_error)
+        }))
+    }
+
+    async fn get_output(
+        &mut self,
+        ctx: wasi_nn::GraphExecutionContext,
+        index: u32,
+    ) -> anyhow::Result<Result<wasi_nn::TensorData, wasi_nn::Error>> {
+        Ok(tokio::task::block_in_place(|| {
+            self.context(ctx)?.get_output(index).map_err(runtime_error)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    // A backend whose graphs double their single fp32 input.
+    #[derive(Default)]
+    struct DoublingBackend {
+        loads: Arc<AtomicUsize>,
+    }
+
+    struct DoublingGraph;
+
+    #[derive(Default)]
+    struct DoublingContext {
+        input: Vec<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Backend for DoublingBackend {
+        fn load(&self, _: &[Vec<u8>], _: ExecutionTarget) -> Result<Arc<dyn BackendGraph>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(DoublingGraph))
+        }
+    }
+
+    impl BackendGraph for DoublingGraph {
+        fn init_execution_context(&self) -> Result<Box<dyn BackendExecutionContext>> {
+            Ok(Box::<DoublingContext>::default())
+        }
+    }
+
+    impl BackendExecutionContext for DoublingContext {
+        fn set_input(&mut self, _index: u32, tensor: &Tensor) -> Result<()> {
+            self.input = tensor.data.clone();
+            Ok(())
+        }
+
+        fn compute(&mut self) -> Result<()> {
+            self.output = self
+                .input
+                .chunks_exact(4)
+                .flat_map(|b| (f32::from_le_bytes(b.try_into().unwrap()) * 2.0).to_le_bytes())
+                .collect();
+            Ok(())
+        }
+
+        fn get_output(&mut self, _index: u32) -> Result<Vec<u8>> {
+            Ok(self.output.clone())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_inference_with_backend() -> anyhow::Result<()> {
+        use wasi_nn::Host;
+
+        let mut wasi_nn = WasiNn::new();
+        wasi_nn.add_backend(GraphEncoding::Onnx, DoublingBackend::default());
+        let mut dispatch = WasiNnDispatch::new(Arc::new(wasi_nn));
+
+        let unsupported = dispatch
+            .load(vec![], GraphEncoding::Openvino, ExecutionTarget::Cpu)
+            .await?;
+        assert!(matches!(unsupported, Err(wasi_nn::Error::InvalidEncoding)));
+
+        let graph = dispatch
+            .load(vec![vec![]], GraphEncoding::Onnx, ExecutionTarget::Cpu)
+            .await?
+            .unwrap();
+        let ctx = dispatch.init_execution_context(graph).await?.unwrap();
+        let tensor = Tensor {
+            dimensions: vec![2],
+            tensor_type: wasi_nn::TensorType::Fp32,
+            data: [1.5f32, -2.0]
+                .iter()
+                .flat_map(|f| f.to_le_bytes())
+                .collect(),
+        };
+        dispatch.set_input(ctx, 0, tensor).await?.unwrap();
+        dispatch.compute(ctx).await?.unwrap();
+        let output = dispatch.get_output(ctx, 0).await?.unwrap();
+        let expected: Vec<u8> = [3.0f32, -4.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        assert_eq!(output, expected);
+
+        let invalid = dispatch.compute(ctx + 1).await?;
+        assert!(matches!(invalid, Err(wasi_nn::Error::InvalidArgument)));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn named_models_load_once() -> anyhow::Result<()> {
+        use wasi_nn::Host;
+
+        let mut model = NamedTempFile::new()?;
+        model.write_all(b"model")?;
+        let backend = DoublingBackend::default();
+        let loads = backend.loads.clone();
+        let mut wasi_nn = WasiNn::new();
+        wasi_nn.add_backend(GraphEncoding::Onnx, backend);
+        wasi_nn.add_model(
+            "doubler",
+            GraphEncoding::Onnx,
+            ExecutionTarget::Cpu,
+            vec![model.path().to_owned()],
+        );
+        let wasi_nn = Arc::new(wasi_nn);
+
+        for _ in 0..2 {
+            let mut dispatch = WasiNnDispatch::new(wasi_nn.clone());
+            dispatch.load_by_name("doubler".into()).await?.unwrap();
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let mut dispatch = WasiNnDispatch::new(wasi_nn);
+        let missing = dispatch.load_by_name("missing".into()).await?;
+        assert!(matches!(missing, Err(wasi_nn::Error::NotFound)));
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use spin_world::wasi_nn::{ExecutionTarget, Tensor, TensorType};
+use tract_onnx::prelude::*;
+
+use crate::{Backend, BackendExecutionContext, BackendGraph};
+
+/// A [`Backend`] which runs ONNX models on the CPU.
+#[derive(Default)]
+pub struct OnnxBackend;
+
+impl Backend for OnnxBackend {
+    fn load(&self, builders: &[Vec<u8>], target: ExecutionTarget) -> Result<Arc<dyn BackendGraph>> {
+        let [model] = builders else {
+            bail!("an ONNX graph has 1 part, not {}", builders.len());
+        };
+        if target != ExecutionTarget::Cpu {
+            bail!("the ONNX backend only supports the CPU, not {target:?}");
+        }
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut &model[..])
+            .context("invalid ONNX model")?;
+        Ok(Arc::new(OnnxGraph { model }))
+    }
+}
+
+struct OnnxGraph {
+    model: InferenceModel,
+}
+
+impl BackendGraph for OnnxGraph {
+    fn init_execution_context(&self) -> Result<Box<dyn BackendExecutionContext>> {
+        Ok(Box::new(OnnxExecutionContext {
+            model: self.model.clone(),
+            inputs: vec![],
+            outputs: vec![],
+        }))
+    }
+}
+
+// The model is only optimized once its input shapes are known, i.e. when
+// inference is run.
+struct OnnxExecutionContext {
+    model: InferenceModel,
+    inputs: Vec<Option<tract_onnx::prelude::Tensor>>,
+    outputs: Vec<tract_onnx::prelude::Tensor>,
+}
+
+impl BackendExecutionContext for OnnxExecutionContext {
+    fn set_input(&mut self, index: u32, tensor: &Tensor) -> Result<()> {
+        let index = index as usize;
+        let shape = tensor
+            .dimensions
+            .iter()
+            .map(|d| *d as usize)
+            .collect::<Vec<_>>();
+        let datum_type = datum_type(tensor.tensor_type)?;
+        // Safety: `from_raw_dt` checks that the data's length matches the
+        // shape and type, and every bit pattern is valid for these types.
+        let input =
+            unsafe { tract_onnx::prelude::Tensor::from_raw_dt(datum_type, &shape, &tensor.data)? };
+        if self.inputs.len() <= index {
+            self.inputs.resize(index + 1, None);
+        }
+        self.inputs[index] = Some(input);
+        Ok(())
+    }
+
+    fn compute(&mut self) -> Result<()> {
+        let mut model = self.model.clone();
+        let mut inputs = TVec::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            let input = input
+                .clone()
+                .with_context(|| format!("input {index} has not been set"))?;
+            model.set_input_fact(
+                index,
+                InferenceFact::dt_shape(input.datum_type(), input.shape()),
+            )?;
+            inputs.push(input.into());
+        }
+        let outputs = model.into_optimized()?.into_runnable()?.run(inputs)?;
+        self.outputs = outputs
+            .into_iter()
+            .map(|output| output.into_tensor())
+            .collect();
+        Ok(())
+    }
+
+    fn get_output(&mut self, index: u32) -> Result<Vec<u8>> {
+        let output = self
+            .outputs
+            .get(index as usize)
+            .with_context(|| format!("no output {index}"))?;
+        Ok(output.as_bytes().to_vec())
+    }
+}
+
+fn datum_type(tensor_type: TensorType) -> Result<DatumType> {
+    Ok(match tensor_type {
+        TensorType::Fp16 => DatumType::F16,
+        TensorType::Fp32 => DatumType::F32,
+        TensorType::Fp64 => DatumType::F64,
+        TensorType::U8 => DatumType::U8,
+        TensorType::I32 => DatumType::I32,
+        TensorType::I64 => DatumType::I64,
+        TensorType::Bf16 => bail!("the ONNX backend does not support bf16 tensors"),
+    })
+}
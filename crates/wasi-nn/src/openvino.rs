@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use openvino::{Blob, Core, ExecutableNetwork, InferRequest, Layout, Precision, TensorDesc};
+use spin_world::wasi_nn::{ExecutionTarget, Tensor, TensorType};
+
+use crate::{Backend, BackendExecutionContext, BackendGraph};
+
+/// A [`Backend`] which loads OpenVINO models, given as their XML description
+/// and their weights. The OpenVINO libraries are loaded when the first model
+/// is, so that hosts without them can run components which don't use them.
+#[derive(Default)]
+pub struct OpenvinoBackend {
+    openvino: Arc<Mutex<Openvino>>,
+}
+
+// The backend's OpenVINO objects. They are only reached through the backend's
+// mutex, so every call into OpenVINO, including those which free the objects,
+// is made by one thread at a time; graphs and execution contexts refer to
+// their objects by ID.
+#[derive(Default)]
+struct Openvino {
+    core: Option<Core>,
+    executables: HashMap<u64, ExecutableNetwork>,
+    requests: HashMap<u64, InferRequest>,
+    next_id: u64,
+}
+
+// OpenVINO's objects hold raw pointers, so aren't Send, but aren't tied to the
+// thread which created them.
+unsafe impl Send for Openvino {}
+
+impl Openvino {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn request(&mut self, id: u64) -> &mut InferRequest {
+        self.requests.get_mut(&id).expect("request should be live")
+    }
+}
+
+impl Backend for OpenvinoBackend {
+    fn load(&self, builders: &[Vec<u8>], target: ExecutionTarget) -> Result<Arc<dyn BackendGraph>> {
+        let [xml, weights] = builders else {
+            bail!(
+                "an OpenVINO graph has 2 parts, its XML description and weights, not {}",
+                builders.len()
+            );
+        };
+        let mut openvino = self.openvino.lock().unwrap();
+        if openvino.core.is_none() {
+            openvino.core = Some(Core::new(None).context("failed to load OpenVINO")?);
+        }
+        let core = openvino.core.as_mut().unwrap();
+
+        let mut network = core.read_network_from_buffer(xml, weights)?;
+        // Tensors are given to the backend as their raw data, in NHWC layout.
+        let inputs = (0..network.get_inputs_len()?)
+            .map(|index| network.get_input_name(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        for name in &inputs {
+            network.set_input_layout(name, Layout::NHWC)?;
+        }
+        let outputs = (0..network.get_outputs_len()?)
+            .map(|index| network.get_output_name(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let executable = core.load_network(&network, device(target))?;
+        // Frees the network while the lock is held.
+        drop(network);
+
+        let id = openvino.next_id();
+        openvino.executables.insert(id, executable);
+        Ok(Arc::new(OpenvinoGraph(Arc::new(GraphHandle {
+            openvino: self.openvino.clone(),
+            id,
+            inputs,
+            outputs,
+        }))))
+    }
+}
+
+struct OpenvinoGraph(Arc<GraphHandle>);
+
+// A loaded graph, which its execution contexts keep alive.
+struct GraphHandle {
+    openvino: Arc<Mutex<Openvino>>,
+    id: u64,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl Drop for GraphHandle {
+    fn drop(&mut self) {
+        let mut openvino = self.openvino.lock().unwrap();
+        openvino.executables.remove(&self.id);
+    }
+}
+
+impl BackendGraph for OpenvinoGraph {
+    fn init_execution_context(&self) -> Result<Box<dyn BackendExecutionContext>> {
+        let mut openvino = self.0.openvino.lock().unwrap();
+        let request = openvino
+            .executables
+            .get_mut(&self.0.id)
+            .expect("graph should be live")
+            .create_infer_request()?;
+        let id = openvino.next_id();
+        openvino.requests.insert(id, request);
+        Ok(Box::new(OpenvinoExecutionContext {
+            graph: self.0.clone(),
+            id,
+        }))
+    }
+}
+
+struct OpenvinoExecutionContext {
+    graph: Arc<GraphHandle>,
+    id: u64,
+}
+
+impl Drop for OpenvinoExecutionContext {
+    fn drop(&mut self) {
+        let mut openvino = self.graph.openvino.lock().unwrap();
+        openvino.requests.remove(&self.id);
+    }
+}
+
+impl BackendExecutionContext for OpenvinoExecutionContext {
+    fn set_input(&mut self, index: u32, tensor: &Tensor) -> Result<()> {
+        let name = tensor_name(&self.graph.inputs, "input", index)?;
+        let dimensions = tensor
+            .dimensions
+            .iter()
+            .map(|d| *d as usize)
+            .collect::<Vec<_>>();
+        let mut openvino = self.graph.openvino.lock().unwrap();
+        let desc = TensorDesc::new(Layout::NHWC, &dimensions, precision(tensor.tensor_type)?);
+        let blob = Blob::new(&desc, &tensor.data)?;
+        openvino.request(self.id).set_blob(name, &blob)?;
+        Ok(())
+    }
+
+    fn compute(&mut self) -> Result<()> {
+        let mut openvino = self.graph.openvino.lock().unwrap();
+        openvino.request(self.id).infer()?;
+        Ok(())
+    }
+
+    fn get_output(&mut self, index: u32) -> Result<Vec<u8>> {
+        let name = tensor_name(&self.graph.outputs, "output", index)?;
+        let mut openvino = self.graph.openvino.lock().unwrap();
+        let blob = openvino.request(self.id).get_blob(name)?;
+        let data = blob.buffer()?.to_vec();
+        ensure!(!data.is_empty(), "output {name:?} is empty");
+        Ok(data)
+    }
+}
+
+fn tensor_name<'a>(names: &'a [String], kind: &str, index: u32) -> Result<&'a str> {
+    names
+        .get(index as usize)
+        .map(String::as_str)
+        .with_context(|| format!("the graph has no {kind} {index}"))
+}
+
+fn device(target: ExecutionTarget) -> &'static str {
+    match target {
+        ExecutionTarget::Cpu => "CPU",
+        ExecutionTarget::Gpu => "GPU",
+        ExecutionTarget::Tpu => "MYRIAD",
+    }
+}
+
+fn precision(tensor_type: TensorType) -> Result<Precision> {
+    Ok(match tensor_type {
+        TensorType::Fp16 => Precision::FP16,
+        TensorType::Fp32 => Precision::FP32,
+        TensorType::U8 => Precision::U8,
+        TensorType::I32 => Precision::I32,
+        other => bail!("OpenVINO does not support {other:?} tensors"),
+    })
+}
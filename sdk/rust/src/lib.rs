@@ -12,6 +12,10 @@ pub mod key_value;
 #[cfg(feature = "experimental")]
 pub mod sqlite;
 
+/// Custom spans and metrics.
+#[cfg(feature = "experimental")]
+pub mod observe;
+
 /// Exports the procedural macros for writing handlers for Spin components.
 pub use spin_macro::*;
 
@@ -0,0 +1,46 @@
+wit_bindgen_rust::import!("../../wit/ephemeral/observe.wit");
+
+/// Errors which may be raised by the functions in this module
+pub type Error = observe::Error;
+
+/// A span of work within the handling of a trigger event, exported along
+/// with the spans of the Spin runtime. The span is closed when dropped.
+#[derive(Debug)]
+pub struct Span(observe::Span);
+
+impl Span {
+    /// Open a span which is a child of the span of the trigger event being handled
+    pub fn open(name: &str) -> Result<Self, Error> {
+        Ok(Self(observe::open_span(name, None)?))
+    }
+
+    /// Open a span which is a child of this span
+    pub fn child(&self, name: &str) -> Result<Self, Error> {
+        Ok(Self(observe::open_span(name, Some(self.0))?))
+    }
+
+    /// Set an attribute on this span
+    pub fn set_attribute(&self, key: &str, value: &str) -> Result<(), Error> {
+        observe::set_attribute(self.0, key, value)
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        observe::close_span(self.0)
+    }
+}
+
+/// Add `value` to the counter named `name`.
+///
+/// Each use of a counter must give the same label names.
+pub fn increment_counter(name: &str, value: u64, labels: &[(&str, &str)]) -> Result<(), Error> {
+    observe::increment_counter(name, value, labels)
+}
+
+/// Record `value` in the histogram named `name`.
+///
+/// Each use of a histogram must give the same label names.
+pub fn record_histogram(name: &str, value: f64, labels: &[(&str, &str)]) -> Result<(), Error> {
+    observe::record_histogram(name, value, labels)
+}
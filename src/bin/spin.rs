@@ -1,23 +1,41 @@
 use anyhow::Error;
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use is_terminal::IsTerminal;
 use lazy_static::lazy_static;
 use spin_cli::build_info::*;
 use spin_cli::commands::{
+    bench::BenchCommand,
     build::BuildCommand,
+    cache::CacheCommands,
     cloud::{CloudCommand, DeployCommand, LoginCommand},
+    completion::CompletionCommand,
     doctor::DoctorCommand,
     external::execute_external_subcommand,
+    info::InfoCommand,
+    inspect::InspectCommand,
+    logs::LogsCommand,
+    manifest::ManifestCommands,
     new::{AddCommand, NewCommand},
     plugins::PluginCommands,
+    precompile::PrecompileCommand,
+    ps::PsCommand,
     registry::RegistryCommands,
+    replay::ReplayCommand,
+    scaffold::ScaffoldCommands,
+    service::ServiceCommands,
+    shell::ShellCommand,
+    stop::StopCommand,
+    telemetry::TelemetryCommands,
     templates::TemplateCommands,
+    test::TestCommand,
     up::UpCommand,
+    variables::VariablesCommand,
     watch::WatchCommand,
 };
 use spin_redis_engine::RedisTrigger;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
 use spin_trigger::cli::TriggerExecutorCommand;
+use spin_trigger_azure_service_bus::AzureServiceBusTrigger;
 use spin_trigger_http::HttpTrigger;
 
 #[tokio::main]
@@ -30,15 +48,30 @@ async fn main() {
 }
 
 async fn _main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("watchexec=off".parse()?),
-        )
-        .with_ansi(std::io::stderr().is_terminal())
-        .init();
-    SpinApp::parse().run().await
+    // Read first, as its defaults are used by everything after.
+    let user_config = spin_cli::user_config::UserConfig::init()?;
+
+    let _telemetry = spin_telemetry::init(
+        tracing_subscriber::EnvFilter::from_default_env().add_directive("watchexec=off".parse()?),
+        use_log_colors(),
+        spin_telemetry::LogFormat::from_env()?,
+        spin_telemetry::LogTarget::from_env()?,
+    )?;
+
+    let args = std::env::args().collect::<Vec<_>>();
+    let matches = user_config.apply_defaults(SpinApp::command()).get_matches();
+    let app = SpinApp::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let result = app.run().await;
+    spin_cli::usage::record(&SpinApp::command(), &args, &result).await;
+    result
+}
+
+fn use_log_colors() -> bool {
+    match terminal::color_preference().as_deref() {
+        Ok("always") => true,
+        Ok("never") => false,
+        _ => std::io::stderr().is_terminal(),
+    }
 }
 
 fn print_error_chain(err: anyhow::Error) {
@@ -92,12 +125,36 @@ enum SpinApp {
     External(Vec<String>),
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    Variables(VariablesCommand),
+    Logs(LogsCommand),
+    Stop(StopCommand),
+    Ps(PsCommand),
+    Precompile(PrecompileCommand),
+    #[clap(subcommand)]
+    Manifest(ManifestCommands),
+    Inspect(InspectCommand),
+    Test(TestCommand),
+    Replay(ReplayCommand),
+    Bench(BenchCommand),
+    Completion(CompletionCommand),
+    Info(InfoCommand),
+    #[clap(subcommand)]
+    Telemetry(TelemetryCommands),
+    #[clap(subcommand)]
+    Service(ServiceCommands),
+    #[clap(subcommand)]
+    Scaffold(ScaffoldCommands),
+    #[clap(subcommand)]
+    Cache(CacheCommands),
+    Shell(ShellCommand),
 }
 
 #[derive(Subcommand)]
 enum TriggerCommands {
     Http(TriggerExecutorCommand<HttpTrigger>),
     Redis(TriggerExecutorCommand<RedisTrigger>),
+    #[clap(name = "azure-service-bus")]
+    AzureServiceBus(TriggerExecutorCommand<AzureServiceBusTrigger>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(TriggerExecutorCommand<HelpArgsOnlyTrigger>),
 }
@@ -117,11 +174,29 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::AzureServiceBus(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, SpinApp::command()).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Variables(cmd) => cmd.run().await,
+            Self::Logs(cmd) => cmd.run().await,
+            Self::Stop(cmd) => cmd.run().await,
+            Self::Ps(cmd) => cmd.run().await,
+            Self::Precompile(cmd) => cmd.run().await,
+            Self::Manifest(cmd) => cmd.run().await,
+            Self::Inspect(cmd) => cmd.run().await,
+            Self::Test(cmd) => cmd.run().await,
+            Self::Replay(cmd) => cmd.run().await,
+            Self::Bench(cmd) => cmd.run().await,
+            Self::Completion(cmd) => cmd.run(SpinApp::command()).await,
+            Self::Info(cmd) => cmd.run().await,
+            Self::Telemetry(cmd) => cmd.run().await,
+            Self::Service(cmd) => cmd.run().await,
+            Self::Scaffold(cmd) => cmd.run().await,
+            Self::Cache(cmd) => cmd.run().await,
+            Self::Shell(cmd) => cmd.run().await,
         }
     }
 }
@@ -22,3 +22,5 @@ pub const SPIN_BRANCH: &str = env!("VERGEN_GIT_BRANCH");
 pub const SPIN_TARGET_TRIPLE: &str = env!("VERGEN_CARGO_TARGET_TRIPLE");
 /// The profile of the Spin CLI.
 pub const SPIN_DEBUG: &str = env!("VERGEN_CARGO_DEBUG");
+/// The version of Wasmtime the Spin CLI is built with.
+pub const SPIN_WASMTIME_VERSION: &str = env!("SPIN_WASMTIME_VERSION");
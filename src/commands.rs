@@ -1,22 +1,56 @@
 //! Commands for the Spin CLI.
 
+/// Command for benchmarking an application's HTTP routes.
+pub mod bench;
 /// Commands for building Spin applications.
 pub mod build;
+/// Commands for listing and pruning the caches Spin shares across applications.
+pub mod cache;
 /// Commands for publishing applications to the Fermyon Platform.
 pub mod cloud;
+/// Command for generating shell completion scripts.
+pub mod completion;
 /// Command for running the Spin Doctor.
 pub mod doctor;
 /// Commands for external subcommands (i.e. plugins)
 pub mod external;
+/// Command for printing information about Spin and its environment.
+pub mod info;
+/// Command for showing what an application will do before running it.
+pub mod inspect;
+/// Command for showing the logs of a local application.
+pub mod logs;
+/// Commands for validating application manifests and printing their schema.
+pub mod manifest;
 /// Command for creating a new application.
 pub mod new;
 /// Command for adding a plugin to Spin
 pub mod plugins;
+/// Command for compiling an application's components ahead of time.
+pub mod precompile;
+/// Command for listing applications running in the background.
+pub mod ps;
 /// Commands for working with OCI registries.
 pub mod registry;
+/// Command for replaying recorded trigger events against an application.
+pub mod replay;
+/// Commands for generating container and Kubernetes deployment files.
+pub mod scaffold;
+/// Commands for running applications as services of the operating system.
+pub mod service;
+/// Command for invoking an application's components interactively.
+pub mod shell;
+/// Command for stopping applications running in the background.
+pub mod stop;
+/// Commands for opting in to and inspecting usage telemetry.
+pub mod telemetry;
 /// Commands for working with templates.
 pub mod templates;
+/// Command for running an application's test components.
+pub mod test;
 /// Commands for starting the runtime.
 pub mod up;
+/// Command for listing and resolving application variables.
+pub mod variables;
 /// Command for rebuilding and restarting a Spin app when files change.
 pub mod watch;
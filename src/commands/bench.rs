@@ -0,0 +1,315 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use hyper::{http::uri::Scheme, Body, Method, Request};
+use spin_trigger::locked::write_locked_app;
+use spin_trigger_http::HttpTrigger;
+use tokio::{
+    sync::Mutex,
+    time::{Interval, MissedTickBehavior},
+};
+
+use crate::{
+    commands::up::{in_process::InProcessTrigger, UpCommand, APPLICATION_OPT},
+    opts::*,
+};
+
+const PERCENTILES: &[f64] = &[50.0, 90.0, 99.0, 99.9];
+
+/// Benchmark an application's HTTP route in process.
+///
+/// Requests are sent directly to the HTTP trigger, without a listener, so the
+/// results measure the runtime and the component rather than the network.
+/// With `--rps`, requests are sent at that rate, up to `--concurrency` at a
+/// time; otherwise `--concurrency` requests are kept in flight throughout.
+#[derive(Parser, Debug)]
+#[clap(about = "Benchmark an HTTP route of an application")]
+pub struct BenchCommand {
+    /// The application to benchmark. This may be a manifest (spin.toml) file,
+    /// or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(name = APPLICATION_OPT, short = 'f', long = "from")]
+    pub app_source: Option<String>,
+
+    /// The environment, such as `prod`, whose overrides in the manifest's
+    /// `[profile.<environment>]` table apply.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// The build profile, such as `release`, whose modules to benchmark.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Runtime configuration file for the benchmark. Key value stores and
+    /// databases which it does not configure are in memory.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Disable the cache of compiled components.
+    #[clap(long = "disable-cache", takes_value = false)]
+    pub disable_cache: bool,
+
+    /// The path, with any query string, to send requests to.
+    #[clap(long = "path", default_value = "/")]
+    pub path: String,
+
+    /// The method of the requests.
+    #[clap(long = "method", default_value = "GET")]
+    pub method: Method,
+
+    /// The body of the requests.
+    #[clap(long = "body")]
+    pub body: Option<String>,
+
+    /// The maximum number of requests in flight at once.
+    #[clap(short = 'c', long = "concurrency", default_value = "10")]
+    pub concurrency: usize,
+
+    /// The rate, in requests per second, at which to send requests. If
+    /// omitted, requests are sent as fast as the application handles them.
+    #[clap(long = "rps")]
+    pub rps: Option<u32>,
+
+    /// How long to send requests for, in seconds.
+    #[clap(short = 'd', long = "duration", default_value = "10")]
+    pub duration: u64,
+}
+
+/// The outcome of a single request.
+#[derive(Debug)]
+struct Sample {
+    latency: Duration,
+    status: Option<u16>,
+}
+
+impl Sample {
+    fn is_error(&self) -> bool {
+        self.status.map_or(true, |status| status >= 500)
+    }
+}
+
+impl BenchCommand {
+    pub async fn run(self) -> Result<()> {
+        if self.concurrency == 0 {
+            bail!("--concurrency must be at least 1");
+        }
+        if self.rps == Some(0) {
+            bail!("--rps must be at least 1");
+        }
+
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-bench-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let loader = UpCommand {
+            app_source: self.app_source.iter().cloned().collect(),
+            environment: self.environment.clone(),
+            profile: self.profile.clone(),
+            ..Default::default()
+        };
+        let app = loader.load_app(working_dir.path()).await?;
+        let component_ids = app
+            .components
+            .iter()
+            .map(|c| c.id.clone())
+            .collect::<Vec<_>>();
+        let locked_url = write_locked_app(&app, working_dir.path()).await?;
+
+        let trigger: HttpTrigger = InProcessTrigger {
+            runtime_config_file: self.runtime_config_file.as_deref(),
+            disable_cache: self.disable_cache,
+            ..Default::default()
+        }
+        .build(locked_url, working_dir.path())
+        .await?;
+        let trigger = Arc::new(trigger);
+
+        // Check the route before starting, so that a typo is reported once
+        // rather than as a benchmark of 404s.
+        let warmup = send(&trigger, &self.method, &self.path, self.body.as_deref()).await;
+        match warmup.status {
+            Some(404) => bail!("No component handles {} {}", self.method, self.path),
+            None => bail!("Request to {} {} failed", self.method, self.path),
+            _ => {}
+        }
+
+        let pacer = self.rps.map(|rps| {
+            let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rps as f64));
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            Arc::new(Mutex::new(interval))
+        });
+        let instantiations_before = instantiation_totals(&component_ids);
+        println!(
+            "Benchmarking {} {} for {}s with {} concurrent requests{}",
+            self.method,
+            self.path,
+            self.duration,
+            self.concurrency,
+            self.rps
+                .map(|rps| format!(" at {rps} requests/s"))
+                .unwrap_or_default()
+        );
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_secs(self.duration);
+        let workers = (0..self.concurrency)
+            .map(|_| {
+                let trigger = trigger.clone();
+                let pacer = pacer.clone();
+                let method = self.method.clone();
+                let path = self.path.clone();
+                let body = self.body.clone();
+                tokio::spawn(async move {
+                    let mut samples = vec![];
+                    loop {
+                        if let Some(pacer) = &pacer {
+                            tick(pacer).await;
+                        }
+                        if Instant::now() >= deadline {
+                            break;
+                        }
+                        samples.push(send(&trigger, &method, &path, body.as_deref()).await);
+                    }
+                    samples
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut samples = vec![];
+        for worker in workers {
+            samples.extend(worker.await?);
+        }
+        let elapsed = start.elapsed();
+        let instantiations_after = instantiation_totals(&component_ids);
+
+        print_report(&samples, elapsed);
+        let instantiations = instantiations_after.0 - instantiations_before.0;
+        if instantiations > 0 {
+            let overhead = instantiations_after
+                .1
+                .saturating_sub(instantiations_before.1)
+                / instantiations as u32;
+            println!(
+                "Instantiation: {instantiations} instances; {} mean per instance",
+                format_duration(overhead)
+            );
+        }
+        Ok(())
+    }
+}
+
+async fn tick(pacer: &Mutex<Interval>) {
+    pacer.lock().await.tick().await;
+}
+
+async fn send(trigger: &HttpTrigger, method: &Method, path: &str, body: Option<&str>) -> Sample {
+    let start = Instant::now();
+    let status = async {
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(hyper::header::HOST, "localhost")
+            .body(body.map(|b| Body::from(b.to_owned())).unwrap_or_default())?;
+        let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let res = trigger.handle(req, Scheme::HTTP, client_addr).await?;
+        let status = res.status().as_u16();
+        hyper::body::to_bytes(res.into_body()).await?;
+        anyhow::Ok(status)
+    }
+    .await;
+    if let Err(err) = &status {
+        tracing::debug!("Benchmark request failed: {err:?}");
+    }
+    Sample {
+        latency: start.elapsed(),
+        status: status.ok(),
+    }
+}
+
+fn instantiation_totals(component_ids: &[String]) -> (u64, Duration) {
+    component_ids
+        .iter()
+        .map(|id| spin_telemetry::metrics::instantiation_totals(id))
+        .fold((0, Duration::ZERO), |(count, total), (c, t)| {
+            (count + c, total + t)
+        })
+}
+
+fn print_report(samples: &[Sample], elapsed: Duration) {
+    if samples.is_empty() {
+        println!("\nNo requests completed");
+        return;
+    }
+    let errors = samples.iter().filter(|s| s.is_error()).count();
+    println!(
+        "\nRequests:  {} in {:.2}s ({:.1} requests/s)",
+        samples.len(),
+        elapsed.as_secs_f64(),
+        samples.len() as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "Errors:    {errors} ({:.2}%)",
+        errors as f64 * 100.0 / samples.len() as f64
+    );
+
+    let mut latencies = samples.iter().map(|s| s.latency).collect::<Vec<_>>();
+    latencies.sort();
+    let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    println!("Latency:   mean {}", format_duration(mean));
+    for p in PERCENTILES {
+        println!(
+            "           p{p:<5} {}",
+            format_duration(percentile(&latencies, *p))
+        );
+    }
+    println!(
+        "           max    {}",
+        format_duration(latencies[latencies.len() - 1])
+    );
+}
+
+/// Returns the nearest-rank percentile of the sorted, non-empty latencies.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_secs_f64() * 1_000_000.0;
+    if micros < 1000.0 {
+        format!("{micros:.0}µs")
+    } else {
+        format!("{:.2}ms", micros / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let latencies = (1..=200).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(100));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(198));
+        assert_eq!(percentile(&latencies, 99.9), Duration::from_millis(200));
+        assert_eq!(percentile(&latencies[..1], 0.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn failed_requests_and_server_errors_are_errors() {
+        let sample = |status| Sample {
+            latency: Duration::ZERO,
+            status,
+        };
+        assert!(!sample(Some(200)).is_error());
+        assert!(!sample(Some(404)).is_error());
+        assert!(sample(Some(503)).is_error());
+        assert!(sample(None).is_error());
+    }
+}
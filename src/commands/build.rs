@@ -3,7 +3,10 @@ use std::{ffi::OsString, path::PathBuf};
 use anyhow::Result;
 use clap::Parser;
 
-use crate::opts::{APP_MANIFEST_FILE_OPT, BUILD_UP_OPT, DEFAULT_MANIFEST_FILE};
+use crate::{
+    opts::{APP_MANIFEST_FILE_OPT, BUILD_UP_OPT, DEFAULT_MANIFEST_FILE},
+    output::OutputOpts,
+};
 
 use super::up::UpCommand;
 
@@ -27,6 +30,67 @@ pub struct BuildCommand {
     #[clap(short = 'c', long, multiple = true)]
     pub component_id: Vec<String>,
 
+    /// The maximum number of components to build at once. Defaults to the
+    /// number of CPUs. When components are built concurrently, each line of
+    /// build output is prefixed with the component ID.
+    #[clap(short = 'j', long = "jobs")]
+    pub jobs: Option<usize>,
+
+    /// Build every component, even those whose sources are unchanged since
+    /// they were last built. Otherwise, components with `watch` patterns
+    /// are only built when the files matching them have changed.
+    #[clap(long = "force", takes_value = false)]
+    pub force: bool,
+
+    /// The build profile to use, such as `release`. Components which define
+    /// the profile are built with its build command, and `--up` runs the
+    /// modules it builds.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// The environment, such as `prod`, whose overrides in the manifest's
+    /// `[profile.<environment>]` table apply. Components are built with the
+    /// environment's build commands, and `--up` runs in the environment.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// Check the manifest strictly before building: keys Spin does not
+    /// recognise, variables no component uses, and deprecated settings are
+    /// errors rather than being ignored. `--up` runs with the same check.
+    #[clap(long = "strict", takes_value = false)]
+    pub strict: bool,
+
+    /// The format of build progress and build command output: "human" or
+    /// "json". In "json" format, each line is a JSON message describing
+    /// build progress, a line of command output, or a compiler diagnostic.
+    /// `--format json` is the same as "json".
+    #[clap(long = "message-format", default_value = "human")]
+    pub message_format: spin_build::MessageFormat,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+
+    /// Run each component's build commands in a container of the image given
+    /// by the `image` field of its `[component.build]` table, rather than
+    /// with the local toolchain. The container runtime defaults to `docker`,
+    /// and can be changed with the SPIN_CONTAINER_RUNTIME environment variable.
+    #[clap(long = "in-container", takes_value = false)]
+    pub in_container: bool,
+
+    /// Cache built modules, keyed by the component's sources and build
+    /// configuration, and restore them from the cache instead of rebuilding
+    /// when the same sources are built again, e.g. after switching branches.
+    /// Only components with `watch` patterns are cached.
+    #[clap(long = "cache", takes_value = false)]
+    pub cache: bool,
+
+    /// The URL of a remote HTTP build cache to use as well as the local
+    /// cache. Modules are fetched from it with GET and added to it with PUT,
+    /// and are only used if their contents match their recorded SHA-256
+    /// digest. Implies `--cache`.
+    #[clap(long = "cache-url", env = "SPIN_BUILD_CACHE_URL")]
+    pub cache_url: Option<url::Url>,
+
     /// Run the application after building.
     #[clap(name = BUILD_UP_OPT, short = 'u', long = "up")]
     pub up: bool,
@@ -37,8 +101,41 @@ pub struct BuildCommand {
 
 impl BuildCommand {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         let manifest_file = crate::manifest::resolve_file_path(&self.app_source)?;
-        spin_build::build(&manifest_file, &self.component_id).await?;
+        if self.strict {
+            spin_loader::local::check_manifest_strictly(
+                &manifest_file,
+                self.environment.as_deref(),
+            )
+            .await?;
+        }
+        let jobs = match self.jobs {
+            Some(jobs) => jobs,
+            None => std::thread::available_parallelism().map_or(1, Into::into),
+        };
+        let cache = if self.cache || self.cache_url.is_some() {
+            Some(spin_build::ArtifactCache::new(
+                None,
+                self.cache_url.clone(),
+            )?)
+        } else {
+            None
+        };
+        let options = spin_build::BuildOptions {
+            jobs,
+            force: self.force,
+            profile: self.profile.clone(),
+            environment: self.environment.clone(),
+            message_format: if self.output.is_json() {
+                spin_build::MessageFormat::Json
+            } else {
+                self.message_format
+            },
+            in_container: self.in_container,
+            cache,
+        };
+        spin_build::build_with_options(&manifest_file, &self.component_id, &options).await?;
 
         if self.up {
             let mut cmd = UpCommand::parse_from(
@@ -49,6 +146,13 @@ impl BuildCommand {
                 .chain(self.up_args),
             );
             cmd.file_source = Some(manifest_file);
+            if self.profile.is_some() {
+                cmd.profile = self.profile;
+            }
+            if self.environment.is_some() {
+                cmd.environment = self.environment;
+            }
+            cmd.strict |= self.strict;
             cmd.run().await
         } else {
             Ok(())
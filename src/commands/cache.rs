@@ -0,0 +1,386 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use comfy_table::Table;
+use serde::Serialize;
+use spin_build::ArtifactCache;
+use spin_loader::cache::Cache;
+use spin_trigger::compile_cache::CompileCache;
+
+use crate::{commands::logs::parse_since, output::OutputOpts};
+
+/// The environment variable setting the default of `spin cache prune --max-age`.
+pub const CACHE_MAX_AGE_ENV: &str = "SPIN_CACHE_MAX_AGE";
+/// The environment variable setting the default of `spin cache prune --max-size`.
+pub const CACHE_MAX_SIZE_ENV: &str = "SPIN_CACHE_MAX_SIZE";
+
+/// Commands for inspecting and pruning the caches which Spin shares across
+/// applications.
+#[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    /// List Spin's caches: layers pulled from registries, compiled
+    /// components, and built modules.
+    #[clap(alias = "list")]
+    Ls(List),
+
+    /// Delete the cache entries which have not been used within a time, or
+    /// the least recently used entries beyond a total size.
+    Prune(Prune),
+
+    /// Delete every entry in Spin's caches.
+    Clear(Clear),
+}
+
+impl CacheCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            CacheCommands::Ls(cmd) => cmd.run().await,
+            CacheCommands::Prune(cmd) => cmd.run().await,
+            CacheCommands::Clear(cmd) => cmd.run().await,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct List {
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+impl List {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        let mut summaries = vec![];
+        for cache in caches().await? {
+            let entries = cache.entries()?;
+            summaries.push(CacheSummary {
+                name: cache.name,
+                entries: entries.len(),
+                size: entries.iter().map(|entry| entry.size).sum(),
+                dirs: cache.dirs,
+            });
+        }
+        self.output.result(&summaries, |summaries| {
+            let mut table = Table::new();
+            table.set_header(vec!["Cache", "Entries", "Size", "Directory"]);
+            table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+            for summary in summaries {
+                let dirs = summary
+                    .dirs
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>();
+                table.add_row(vec![
+                    summary.name.to_owned(),
+                    summary.entries.to_string(),
+                    format_size(summary.size),
+                    dirs.join("\n"),
+                ]);
+            }
+            println!("{table}");
+        })
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Prune {
+    /// Delete the entries which have not been used within this long, e.g.
+    /// "12h" or "30d".
+    #[clap(long = "max-age", env = CACHE_MAX_AGE_ENV, parse(try_from_str = parse_since))]
+    pub max_age: Option<Duration>,
+
+    /// Delete the least recently used entries until the caches take up at
+    /// most this much space in total, e.g. "500MB" or "2GB".
+    #[clap(long = "max-size", env = CACHE_MAX_SIZE_ENV, parse(try_from_str = parse_size))]
+    pub max_size: Option<u64>,
+
+    /// Only prune this cache: "registry", "compiled" or "build".
+    #[clap(long = "cache")]
+    pub cache: Option<String>,
+
+    /// Print the entries which would be deleted, without deleting them.
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+impl Prune {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        if self.max_age.is_none() && self.max_size.is_none() {
+            bail!(
+                "Specify which entries to prune with --max-age or --max-size, set {} or {}, or set defaults in the user config's [cache] section",
+                CACHE_MAX_AGE_ENV,
+                CACHE_MAX_SIZE_ENV
+            );
+        }
+        let mut entries = vec![];
+        for cache in selected_caches(self.cache.as_deref()).await? {
+            entries.extend(cache.entries()?);
+        }
+        let pruned = select_for_pruning(entries, SystemTime::now(), self.max_age, self.max_size);
+        remove_entries(&pruned, self.dry_run, &self.output)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Clear {
+    /// Only clear this cache: "registry", "compiled" or "build".
+    #[clap(long = "cache")]
+    pub cache: Option<String>,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+impl Clear {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        let mut entries = vec![];
+        for cache in selected_caches(self.cache.as_deref()).await? {
+            entries.extend(cache.entries()?);
+        }
+        remove_entries(&entries, false, &self.output)
+    }
+}
+
+// A cache, whose entries are the files in its directories.
+struct CacheDirs {
+    name: &'static str,
+    dirs: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct CacheSummary {
+    name: &'static str,
+    entries: usize,
+    size: u64,
+    dirs: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct CacheEntry {
+    cache: &'static str,
+    path: PathBuf,
+    size: u64,
+    // Caches update an entry's modification time when they use it.
+    #[serde(skip)]
+    last_used: SystemTime,
+}
+
+impl CacheDirs {
+    fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries = vec![];
+        for dir in &self.dirs {
+            collect_entries(self.name, dir, &mut entries)
+                .with_context(|| format!("Failed to read cache directory {}", dir.display()))?;
+        }
+        Ok(entries)
+    }
+}
+
+async fn caches() -> Result<Vec<CacheDirs>> {
+    let registry = Cache::new(None).await?;
+    Ok(vec![
+        CacheDirs {
+            name: "registry",
+            dirs: vec![registry.wasm_dir(), registry.data_dir()],
+        },
+        CacheDirs {
+            name: "compiled",
+            dirs: vec![CompileCache::default_dir()?],
+        },
+        CacheDirs {
+            name: "build",
+            dirs: vec![ArtifactCache::default_dir()?],
+        },
+    ])
+}
+
+async fn selected_caches(name: Option<&str>) -> Result<Vec<CacheDirs>> {
+    let caches = caches().await?;
+    let Some(name) = name else {
+        return Ok(caches);
+    };
+    let selected = caches
+        .into_iter()
+        .filter(|cache| cache.name == name)
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        bail!("Unknown cache {name:?}: expected \"registry\", \"compiled\" or \"build\"");
+    }
+    Ok(selected)
+}
+
+fn collect_entries(cache: &'static str, dir: &Path, entries: &mut Vec<CacheEntry>) -> Result<()> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    for dir_entry in read_dir {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        if metadata.is_dir() {
+            collect_entries(cache, &dir_entry.path(), entries)?;
+        } else {
+            entries.push(CacheEntry {
+                cache,
+                path: dir_entry.path(),
+                size: metadata.len(),
+                last_used: metadata.modified()?,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Returns the entries not used within `max_age`, and then the least recently
+// used of the rest until those left total at most `max_size`.
+fn select_for_pruning(
+    mut entries: Vec<CacheEntry>,
+    now: SystemTime,
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+) -> Vec<CacheEntry> {
+    // Most recently used first, so that entries are pruned from the end.
+    entries.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    let mut kept_size = 0;
+    let mut pruned = vec![];
+    for entry in entries {
+        let age = now.duration_since(entry.last_used).unwrap_or_default();
+        let too_old = max_age.map_or(false, |max_age| age > max_age);
+        let too_big = max_size.map_or(false, |max_size| kept_size + entry.size > max_size);
+        if too_old || too_big {
+            pruned.push(entry);
+        } else {
+            kept_size += entry.size;
+        }
+    }
+    pruned
+}
+
+fn remove_entries(entries: &[CacheEntry], dry_run: bool, output: &OutputOpts) -> Result<()> {
+    for entry in entries {
+        output.detail(format!("{} {}", entry.cache, entry.path.display()));
+        if !dry_run {
+            match std::fs::remove_file(&entry.path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to delete {}", entry.path.display()))
+                }
+                _ => (),
+            }
+        }
+    }
+    let size = format_size(entries.iter().map(|entry| entry.size).sum());
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    output.status(format!("{verb} {} cache entries, {size}", entries.len()));
+    if output.is_json() {
+        output.result(entries, |_| ())?;
+    }
+    Ok(())
+}
+
+// Parses a size such as "2GB", "500MB" or "1024", in bytes if there is no
+// unit.
+fn parse_size(value: &str) -> Result<u64> {
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1_000,
+        "M" | "MB" => 1_000_000,
+        "G" | "GB" => 1_000_000_000,
+        "T" | "TB" => 1_000_000_000_000,
+        _ => bail!("Expected a size such as '500MB' or '2GB'"),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid size {value:?}"))?;
+    number
+        .checked_mul(multiplier)
+        .with_context(|| format!("Size {value:?} is too large"))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+    if bytes < 1_000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1_000.0 {
+            break;
+        }
+        size /= 1_000.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64, age_secs: u64, now: SystemTime) -> CacheEntry {
+        CacheEntry {
+            cache: "registry",
+            path: PathBuf::from(name),
+            size,
+            last_used: now - Duration::from_secs(age_secs),
+        }
+    }
+
+    fn names(entries: &[CacheEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn prunes_old_then_least_recently_used() {
+        let now = SystemTime::now();
+        let entries = vec![
+            entry("old", 10, 100_000, now),
+            entry("recent", 30, 10, now),
+            entry("older", 30, 1_000, now),
+            entry("newest", 30, 1, now),
+        ];
+
+        let pruned = select_for_pruning(
+            entries.clone(),
+            now,
+            Some(Duration::from_secs(10_000)),
+            None,
+        );
+        assert_eq!(names(&pruned), ["old"]);
+
+        let pruned = select_for_pruning(entries.clone(), now, None, Some(60));
+        assert_eq!(names(&pruned), ["older", "old"]);
+
+        let pruned = select_for_pruning(entries, now, None, Some(0));
+        assert_eq!(pruned.len(), 4);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("500MB").unwrap(), 500_000_000);
+        assert_eq!(parse_size("2g").unwrap(), 2_000_000_000);
+        assert!(parse_size("2 parsecs").is_err());
+        assert!(parse_size("MB").is_err());
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1_500_000), "1.5 MB");
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use spin_plugins::PluginStore;
+use spin_templates::TemplateManager;
+
+use crate::opts::DEFAULT_MANIFEST_FILE;
+
+/// Generate a shell completion script.
+///
+/// The script completes subcommands and flags, and also plugin names,
+/// template names, and the component ids of the application in the current
+/// directory. For example, to enable completions in the current bash
+/// session, run `source <(spin completion bash)`.
+#[derive(Parser, Debug)]
+pub struct CompletionCommand {
+    /// The shell to generate a completion script for.
+    #[clap(value_enum, required_unless_present = "dynamic")]
+    pub shell: Option<CompletionShell>,
+
+    /// Print the values to complete after the given command line words, one
+    /// per line. This is used by the completion scripts.
+    #[clap(long = "dynamic", hide = true, conflicts_with = "shell")]
+    pub dynamic: bool,
+
+    /// The words of the command line before the one being completed,
+    /// starting with the program name.
+    #[clap(last = true, hide = true)]
+    pub words: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl CompletionCommand {
+    pub async fn run(self, mut app: clap::App<'_>) -> Result<()> {
+        if self.dynamic {
+            // Completion should never print errors into the user's terminal,
+            // so anything which can't be listed is left out.
+            if let Some(kind) = dynamic_kind(&self.words) {
+                for value in kind.values().await.unwrap_or_default() {
+                    println!("{value}");
+                }
+            }
+            return Ok(());
+        }
+
+        let shell = self.shell.expect("clap requires a shell without --dynamic");
+        let name = app.get_name().to_owned();
+        let mut script = vec![];
+        clap_complete::generate(shell.generator(), &mut app, &name, &mut script);
+        let script = String::from_utf8(script)?;
+        print!("{}", shell.add_dynamic_completion(&script)?);
+        Ok(())
+    }
+}
+
+impl CompletionShell {
+    fn generator(self) -> clap_complete::Shell {
+        match self {
+            Self::Bash => clap_complete::Shell::Bash,
+            Self::Zsh => clap_complete::Shell::Zsh,
+            Self::Fish => clap_complete::Shell::Fish,
+            Self::Powershell => clap_complete::Shell::PowerShell,
+        }
+    }
+
+    // Adds to the generated script a completer which asks `spin completion
+    // --dynamic` for values, and falls back to the generated completions
+    // when there are none.
+    fn add_dynamic_completion(self, script: &str) -> Result<String> {
+        Ok(match self {
+            Self::Bash => format!("{script}\n{BASH_DYNAMIC}"),
+            Self::Zsh => format!("{script}\n{ZSH_DYNAMIC}"),
+            Self::Fish => format!("{script}\n{FISH_DYNAMIC}"),
+            Self::Powershell => {
+                // The generated completer is kept as a script block, for the
+                // dynamic one to call.
+                if !script.contains(POWERSHELL_REGISTER) {
+                    bail!("Unexpected PowerShell completion script");
+                }
+                let script = script.replacen(POWERSHELL_REGISTER, POWERSHELL_STATIC, 1);
+                format!("{script}\n{POWERSHELL_DYNAMIC}")
+            }
+        })
+    }
+}
+
+const BASH_DYNAMIC: &str = r#"_spin_dynamic() {
+    local values
+    values="$(spin completion --dynamic -- "${COMP_WORDS[@]:0:COMP_CWORD}" 2>/dev/null)"
+    if [[ -n "$values" ]]; then
+        COMPREPLY=( $(compgen -W "$values" -- "${COMP_WORDS[COMP_CWORD]}") )
+    else
+        _spin "$@"
+    fi
+}
+
+complete -F _spin_dynamic -o bashdefault -o default spin
+"#;
+
+const ZSH_DYNAMIC: &str = r#"_spin_dynamic() {
+    local -a values
+    values=(${(f)"$(spin completion --dynamic -- "${(@)words[1,CURRENT-1]}" 2>/dev/null)"})
+    if (( ${#values} )); then
+        compadd -a values
+    else
+        _spin "$@"
+    fi
+}
+
+compdef _spin_dynamic spin
+"#;
+
+const FISH_DYNAMIC: &str = r#"function __fish_spin_dynamic
+    spin completion --dynamic -- (commandline -opc) 2>/dev/null
+end
+
+complete -c spin -f -n 'test -n "$(__fish_spin_dynamic)"' -a '(__fish_spin_dynamic)'
+"#;
+
+const POWERSHELL_REGISTER: &str =
+    "Register-ArgumentCompleter -Native -CommandName 'spin' -ScriptBlock {";
+
+const POWERSHELL_STATIC: &str = "$global:__SpinStaticCompleter = {";
+
+const POWERSHELL_DYNAMIC: &str = r#"Register-ArgumentCompleter -Native -CommandName 'spin' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements |
+        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |
+        ForEach-Object { $_.ToString() })
+    $values = @(spin completion --dynamic -- @words 2>$null)
+    if ($values.Count -gt 0) {
+        $values | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+            [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_)
+        }
+    } else {
+        & $global:__SpinStaticCompleter $wordToComplete $commandAst $cursorPosition
+    }
+}
+"#;
+
+/// The kinds of values which completion scripts can't list themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DynamicKind {
+    Plugins,
+    Templates,
+    Components,
+}
+
+impl DynamicKind {
+    async fn values(self) -> Result<Vec<String>> {
+        Ok(match self {
+            Self::Plugins => PluginStore::try_default()?
+                .installed_manifests()?
+                .iter()
+                .map(|manifest| manifest.name())
+                .collect(),
+            Self::Templates => TemplateManager::try_default()?
+                .list()
+                .await?
+                .templates
+                .iter()
+                .map(|template| template.id().to_owned())
+                .collect(),
+            Self::Components => spin_loader::local::raw_manifest_from_file(&DEFAULT_MANIFEST_FILE)
+                .await?
+                .into_v1()
+                .components
+                .into_iter()
+                .map(|component| component.id)
+                .collect(),
+        })
+    }
+}
+
+// Returns the kind of values to complete after the given words. Words which
+// don't start with `-` are taken to be subcommands or positional arguments,
+// which is close enough for the commands completed here.
+fn dynamic_kind(words: &[String]) -> Option<DynamicKind> {
+    let args = words.get(1..).unwrap_or_default();
+    let last = args.last().map(String::as_str);
+    let commands = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect::<Vec<_>>();
+
+    match (commands.as_slice(), last) {
+        (["build", ..], Some("-c" | "--component-id"))
+        | (["logs", ..], Some("-c" | "--component")) => Some(DynamicKind::Components),
+        // Other flags, and their values, aren't completed here.
+        (_, Some(last)) if last.starts_with('-') => None,
+        (["new" | "add"], _)
+        | (["templates" | "template", "uninstall"], _)
+        | (["templates" | "template", "export", ..], _) => Some(DynamicKind::Templates),
+        (["plugins" | "plugin", "uninstall" | "upgrade"], _) => Some(DynamicKind::Plugins),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(line: &str) -> Option<DynamicKind> {
+        let words = line
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        dynamic_kind(&words)
+    }
+
+    #[test]
+    fn completes_dynamic_values_in_context() {
+        assert_eq!(kind("spin plugins uninstall"), Some(DynamicKind::Plugins));
+        assert_eq!(kind("spin plugin upgrade -y"), None);
+        assert_eq!(kind("spin new"), Some(DynamicKind::Templates));
+        assert_eq!(kind("spin new http-rust"), None);
+        assert_eq!(
+            kind("spin templates export http-rust"),
+            Some(DynamicKind::Templates)
+        );
+        assert_eq!(kind("spin build --up -c"), Some(DynamicKind::Components));
+        assert_eq!(kind("spin logs --component"), Some(DynamicKind::Components));
+        assert_eq!(kind("spin up"), None);
+        assert_eq!(kind("spin"), None);
+    }
+
+    #[test]
+    fn powershell_keeps_generated_completer() {
+        let script = "using namespace System.Management.Automation\n\nRegister-ArgumentCompleter -Native -CommandName 'spin' -ScriptBlock {\n}\n";
+        let script = CompletionShell::Powershell
+            .add_dynamic_completion(script)
+            .unwrap();
+        assert!(script.contains("$global:__SpinStaticCompleter = {"));
+        assert_eq!(script.matches("Register-ArgumentCompleter").count(), 1);
+    }
+}
@@ -1,12 +1,21 @@
-use std::{fmt::Debug, path::PathBuf};
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use clap::Parser;
 use dialoguer::{console::Emoji, Confirm, Select};
 use futures::FutureExt;
-use spin_doctor::{Diagnosis, DryRunNotSupported};
+use serde::Serialize;
+use spin_doctor::{plugin::PluginDiagnostic, Checkup, Diagnosis, DryRunNotSupported, PatientApp};
+use spin_plugins::PluginStore;
+use spin_trigger::cli::RUNTIME_CONFIG_FILE;
 
-use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+use crate::{
+    opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE, OUTPUT_FORMAT_ENV},
+    output::OutputFormat,
+};
 
 #[derive(Parser, Debug)]
 #[clap(hide = true, about = "Detect and fix problems with Spin applications")]
@@ -22,64 +31,171 @@ pub struct DoctorCommand {
         default_value = DEFAULT_MANIFEST_FILE
     )]
     pub app_source: PathBuf,
+
+    /// The runtime config file the application is run with. If given, the
+    /// key value stores, databases and config providers it configures are
+    /// checked to be reachable and usable.
+    #[clap(long = "runtime-config-file", env = RUNTIME_CONFIG_FILE)]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// The format in which to report problems. `json` reports every problem
+    /// without offering to fix it, for use by editors and other tools.
+    #[clap(
+        value_enum,
+        long = "format",
+        env = OUTPUT_FORMAT_ENV,
+        default_value = "plain"
+    )]
+    pub format: OutputFormat,
 }
 
 impl DoctorCommand {
     pub async fn run(self) -> Result<()> {
         let manifest_file = crate::manifest::resolve_file_path(&self.app_source)?;
 
-        println!("{icon}The Spin Doctor is in.", icon = Emoji("📟 ", ""));
-        println!(
-            "{icon}Checking {}...",
-            manifest_file.display(),
-            icon = Emoji("🩺 ", "")
-        );
-
-        let count = spin_doctor::Checkup::new(manifest_file)
-            .for_each_diagnosis(move |diagnosis, patient| {
-                async move {
-                    show_diagnosis(&*diagnosis);
-
-                    if let Some(treatment) = diagnosis.treatment() {
-                        let dry_run = match treatment.dry_run(patient).await {
-                            Ok(desc) => Some(desc),
-                            Err(err) => {
-                                if !err.is::<DryRunNotSupported>() {
-                                    show_error("Treatment dry run failed: ", err);
-                                }
-                                return Ok(());
+        let mut checkup = Checkup::new(&manifest_file);
+        if let Some(runtime_config_file) = &self.runtime_config_file {
+            checkup.with_runtime_config_file(runtime_config_file);
+        }
+        for plugin in doctor_plugins() {
+            checkup.add_diagnostic_instance(plugin);
+        }
+
+        match self.format {
+            OutputFormat::Plain => run_interactive(&checkup, &manifest_file).await,
+            OutputFormat::Json => run_json(&checkup).await,
+        }
+    }
+}
+
+async fn run_interactive(checkup: &Checkup, manifest_file: &Path) -> Result<()> {
+    println!("{icon}The Spin Doctor is in.", icon = Emoji("📟 ", ""));
+    println!(
+        "{icon}Checking {}...",
+        manifest_file.display(),
+        icon = Emoji("🩺 ", "")
+    );
+
+    let count = checkup
+        .for_each_diagnosis(move |diagnosis, patient| {
+            async move {
+                show_diagnosis(&*diagnosis);
+
+                if let Some(treatment) = diagnosis.treatment() {
+                    let dry_run = match treatment.dry_run(patient).await {
+                        Ok(desc) => Some(desc),
+                        Err(err) => {
+                            if !err.is::<DryRunNotSupported>() {
+                                show_error("Treatment dry run failed: ", err);
                             }
-                        };
-
-                        let should_treat = prompt_treatment(treatment.summary(), dry_run)
-                            .unwrap_or_else(|err| {
-                                show_error("Prompt error: ", err);
-                                false
-                            });
-
-                        if should_treat {
-                            match treatment.treat(patient).await {
-                                Ok(()) => {
-                                    println!("{icon}Treatment applied!", icon = Emoji("❤  ", ""));
-                                }
-                                Err(err) => {
-                                    show_error("Treatment failed: ", err);
-                                }
+                            return Ok(());
+                        }
+                    };
+
+                    let should_treat = prompt_treatment(treatment.summary(), dry_run)
+                        .unwrap_or_else(|err| {
+                            show_error("Prompt error: ", err);
+                            false
+                        });
+
+                    if should_treat {
+                        match treatment.treat(patient).await {
+                            Ok(()) => {
+                                println!("{icon}Treatment applied!", icon = Emoji("❤  ", ""));
+                            }
+                            Err(err) => {
+                                show_error("Treatment failed: ", err);
                             }
                         }
                     }
-                    Ok(())
                 }
-                .boxed()
-            })
-            .await?;
-        if count == 0 {
-            println!("{icon}No problems found.", icon = Emoji("❤  ", ""));
+                Ok(())
+            }
+            .boxed()
+        })
+        .await?;
+    if count == 0 {
+        println!("{icon}No problems found.", icon = Emoji("❤  ", ""));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct DiagnosisReport {
+    id: String,
+    severity: &'static str,
+    message: String,
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+    fixable: bool,
+    fix: Option<String>,
+}
+
+impl DiagnosisReport {
+    fn new(diagnosis: &dyn Diagnosis, patient: &PatientApp) -> Self {
+        let location = diagnosis
+            .location()
+            .and_then(|location| patient.resolve_location(&location));
+        let treatment = diagnosis.treatment();
+        Self {
+            id: diagnosis.id(),
+            severity: if diagnosis.is_critical() {
+                "error"
+            } else {
+                "warning"
+            },
+            message: diagnosis.description(),
+            file: location.as_ref().map(|location| location.path.clone()),
+            line: location.as_ref().and_then(|location| location.line),
+            column: location.as_ref().and_then(|location| location.column),
+            fixable: treatment.is_some(),
+            fix: treatment.map(|treatment| treatment.summary()),
         }
-        Ok(())
     }
 }
 
+async fn run_json(checkup: &Checkup) -> Result<()> {
+    let mut reports = vec![];
+    let reports_ref = &mut reports;
+    checkup
+        .for_each_diagnosis(move |diagnosis, patient| {
+            reports_ref.push(DiagnosisReport::new(&*diagnosis, patient));
+            async { Ok(()) }.boxed()
+        })
+        .await?;
+    let output = serde_json::json!({ "diagnoses": reports });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+// Installed plugins which contribute their own checks.
+fn doctor_plugins() -> Vec<PluginDiagnostic> {
+    let store = match PluginStore::try_default() {
+        Ok(store) => store,
+        Err(err) => {
+            tracing::debug!("Couldn't open plugin store: {err:?}");
+            return vec![];
+        }
+    };
+    let manifests = store.installed_manifests().unwrap_or_else(|err| {
+        tracing::debug!("Couldn't list installed plugins: {err:?}");
+        vec![]
+    });
+    manifests
+        .iter()
+        .filter_map(|manifest| {
+            let doctor = manifest.doctor()?;
+            let name = manifest.name();
+            Some(PluginDiagnostic::new(
+                &name,
+                store.installed_binary_path(&name),
+                doctor.args.clone(),
+            ))
+        })
+        .collect()
+}
+
 fn show_diagnosis(diagnosis: &dyn Diagnosis) {
     let icon = if diagnosis.is_critical() {
         Emoji("❗ ", "")
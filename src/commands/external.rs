@@ -59,12 +59,13 @@ pub async fn execute_external_subcommand(
                     remote_manifest_src: None,
                     override_compatibility_check: false,
                     version: None,
+                    output: Default::default(),
                 };
                 // Automatically update plugins if the cloud plugin manifest does not exist
                 // TODO: remove this eventually once very unlikely to not have updated
                 if let Err(e) = plugin_installer.run().await {
                     if let Some(PluginError::NotFound(_)) = e.downcast_ref::<PluginError>() {
-                        update().await?;
+                        update(&plugin_installer.output).await?;
                     }
                     plugin_installer.run().await?;
                 }
@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use serde::Serialize;
+use spin_common::data_dir::default_data_dir;
+use spin_plugins::PluginStore;
+
+use crate::{build_info::*, opts::OUTPUT_FORMAT_ENV, output::OutputFormat};
+
+const SECRET_MASK: &str = "********";
+
+// Variables whose values may be secrets, such as application variables.
+const SECRET_ENV_PREFIXES: &[&str] = &["SPIN_VARIABLE_", "SPIN_CONFIG_"];
+
+// Variables which affect Spin but don't start with `SPIN_`.
+const OTHER_ENV_VARS: &[&str] = &[
+    "RUST_LOG",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "TEST_PLUGINS_DIRECTORY",
+    "HOMEBREW_PREFIX",
+];
+
+/// Print information about Spin and its environment, for including in bug
+/// reports.
+#[derive(Parser, Debug)]
+#[clap(about = "Print information about Spin and its environment")]
+pub struct InfoCommand {
+    /// The format in which to print the information.
+    #[clap(
+        value_enum,
+        long = "format",
+        env = OUTPUT_FORMAT_ENV,
+        default_value = "plain"
+    )]
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct InfoReport {
+    version: &'static str,
+    commit_sha: &'static str,
+    commit_date: &'static str,
+    build_date: &'static str,
+    target: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    wasmtime_version: &'static str,
+    directories: Vec<DirectoryReport>,
+    plugins: Vec<PluginReport>,
+    environment: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct DirectoryReport {
+    name: &'static str,
+    path: Option<PathBuf>,
+    exists: bool,
+}
+
+#[derive(Serialize)]
+struct PluginReport {
+    name: String,
+    version: String,
+}
+
+impl InfoCommand {
+    pub async fn run(self) -> Result<()> {
+        let report = report();
+        match self.format {
+            OutputFormat::Plain => print_report(&report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+        Ok(())
+    }
+}
+
+// Gathers what can be found; anything which can't is reported as missing
+// rather than failing, as this is often run on broken installations.
+fn report() -> InfoReport {
+    let plugin_store = PluginStore::try_default().ok();
+    let mut plugins = plugin_store
+        .as_ref()
+        .and_then(|store| store.installed_manifests().ok())
+        .unwrap_or_default()
+        .iter()
+        .map(|manifest| PluginReport {
+            name: manifest.name(),
+            version: manifest.version().to_owned(),
+        })
+        .collect::<Vec<_>>();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let data_dir = default_data_dir().ok();
+    let directories = [
+        ("data", data_dir.clone()),
+        (
+            "plugins",
+            plugin_store.map(|store| store.get_plugins_directory().to_owned()),
+        ),
+        ("templates", data_dir.map(|dir| dir.join("templates"))),
+        (
+            "registry cache",
+            dirs::cache_dir().map(|dir| dir.join("spin").join("registry")),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, path)| DirectoryReport {
+        name,
+        exists: path.as_ref().map_or(false, |path| path.exists()),
+        path,
+    })
+    .collect();
+
+    InfoReport {
+        version: SPIN_VERSION,
+        commit_sha: SPIN_COMMIT_SHA,
+        commit_date: SPIN_COMMIT_DATE,
+        build_date: SPIN_BUILD_DATE,
+        target: SPIN_TARGET_TRIPLE,
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        wasmtime_version: SPIN_WASMTIME_VERSION,
+        directories,
+        plugins,
+        environment: environment(std::env::vars()),
+    }
+}
+
+// Returns the variables which affect Spin, sorted by name, with the values
+// of those which may be secrets masked.
+fn environment(vars: impl IntoIterator<Item = (String, String)>) -> Vec<(String, String)> {
+    let mut environment = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with("SPIN_") || OTHER_ENV_VARS.contains(&name.as_str()))
+        .map(|(name, value)| {
+            if SECRET_ENV_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+            {
+                (name, SECRET_MASK.to_owned())
+            } else {
+                (name, value)
+            }
+        })
+        .collect::<Vec<_>>();
+    environment.sort();
+    environment
+}
+
+fn print_report(report: &InfoReport) {
+    println!(
+        "Spin:     {} ({} {})",
+        report.version, report.commit_sha, report.commit_date
+    );
+    println!("Built:    {} for {}", report.build_date, report.target);
+    println!("OS:       {} ({})", report.os, report.arch);
+    println!("Wasmtime: {}", report.wasmtime_version);
+
+    println!("\nDirectories:");
+    for directory in &report.directories {
+        match &directory.path {
+            Some(path) if directory.exists => println!("  {}: {}", directory.name, path.display()),
+            Some(path) => println!("  {}: {} (not present)", directory.name, path.display()),
+            None => println!("  {}: unknown", directory.name),
+        }
+    }
+
+    println!("\nPlugins:");
+    if report.plugins.is_empty() {
+        println!("  (none installed)");
+    }
+    for plugin in &report.plugins {
+        println!("  {} {}", plugin.name, plugin.version);
+    }
+
+    println!("\nEnvironment:");
+    if report.environment.is_empty() {
+        println!("  (no Spin variables set)");
+    }
+    for (name, value) in &report.environment {
+        println!("  {name}={value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_is_filtered_and_masked() {
+        let vars = [
+            ("PATH", "/usr/bin"),
+            ("SPIN_VARIABLE_API_KEY", "secret"),
+            ("SPIN_LOG_FORMAT", "json"),
+            ("RUST_LOG", "spin=trace"),
+        ]
+        .map(|(name, value)| (name.to_owned(), value.to_owned()));
+
+        let environment = environment(vars);
+        assert_eq!(
+            environment,
+            [
+                ("RUST_LOG".to_owned(), "spin=trace".to_owned()),
+                ("SPIN_LOG_FORMAT".to_owned(), "json".to_owned()),
+                ("SPIN_VARIABLE_API_KEY".to_owned(), SECRET_MASK.to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use comfy_table::Table;
+use serde::Serialize;
+use serde_json::Value;
+use spin_app::locked::{ContentRef, LockedApp, LockedComponent};
+use spin_trigger::locked::{
+    ALLOWED_OUTBOUND_HOSTS_KEY, DESCRIPTION_KEY, ENABLED_KEY, NAME_KEY, VERSION_KEY,
+};
+
+use crate::{
+    commands::up::{AppSource, UpCommand, APPLICATION_OPT},
+    opts::*,
+    output::OutputFormat,
+};
+
+const SECRET_MASK: &str = "********";
+
+/// Show what an application will do before running it: its components,
+/// triggers, variables, network access and files.
+#[derive(Parser, Debug)]
+#[clap(about = "Show the components, triggers and permissions of an application")]
+pub struct InspectCommand {
+    /// The application to inspect. This may be a manifest (spin.toml) file, a
+    /// directory containing a spin.toml file, or a registry reference.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(name = APPLICATION_OPT, short = 'f', long = "from")]
+    pub app_source: Option<String>,
+
+    /// Ignore server certificate errors from the registry.
+    #[clap(name = INSECURE_OPT, short = 'k', long = "insecure", takes_value = false)]
+    pub insecure: bool,
+
+    /// The environment, such as `prod`, whose overrides in the manifest's
+    /// `[profile.<environment>]` table apply. Local applications only.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// The build profile, such as `release`, whose modules to inspect.
+    /// Local applications only.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// The format in which to show the application.
+    #[clap(
+        value_enum,
+        long = "format",
+        env = OUTPUT_FORMAT_ENV,
+        default_value = "plain"
+    )]
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct AppReport {
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+    components: Vec<ComponentReport>,
+    variables: Vec<VariableReport>,
+}
+
+#[derive(Serialize)]
+struct ComponentReport {
+    id: String,
+    description: Option<String>,
+    source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enabled: Option<String>,
+    triggers: Vec<TriggerReport>,
+    allowed_http_hosts: Vec<String>,
+    allowed_outbound_hosts: Vec<String>,
+    key_value_stores: Vec<String>,
+    sqlite_databases: Vec<String>,
+    files: Vec<FileReport>,
+    environment: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct TriggerReport {
+    trigger_type: String,
+    // The route, channel or other setting which selects the component
+    target: String,
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: PathBuf,
+    source: Option<String>,
+    writable: bool,
+}
+
+#[derive(Serialize)]
+struct VariableReport {
+    name: String,
+    required: bool,
+    default: Option<String>,
+    secret: bool,
+}
+
+impl InspectCommand {
+    pub async fn run(self) -> Result<()> {
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-inspect-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let loader = UpCommand {
+            app_source: self.app_source.iter().cloned().collect(),
+            insecure: self.insecure,
+            environment: self.environment.clone(),
+            profile: self.profile.clone(),
+            ..Default::default()
+        };
+        // Registry applications are already built, but a local application's
+        // git sources would be built by loading it normally.
+        let app = match loader.resolve_app_source() {
+            AppSource::File(manifest_path) => {
+                self.load_local_app(&manifest_path, working_dir.path())
+                    .await?
+            }
+            _ => loader.load_app(working_dir.path()).await?,
+        };
+        let report = report(&app);
+
+        match self.format {
+            OutputFormat::Plain => print_report(&report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+        Ok(())
+    }
+
+    /// Loads a local application without fetching or building the modules of
+    /// its URL and git sources, which are reported by where they come from.
+    async fn load_local_app(&self, manifest_path: &Path, working_dir: &Path) -> Result<LockedApp> {
+        let app = spin_loader::local::from_file_without_fetching(
+            manifest_path,
+            Some(working_dir),
+            self.profile.as_deref(),
+            self.environment.as_deref(),
+        )
+        .await?;
+        let unfetched = app
+            .components
+            .iter()
+            .filter_map(|component| match &component.source {
+                spin_manifest::ModuleSource::Buffer(_, name) => {
+                    Some((component.id.clone(), name.clone()))
+                }
+                spin_manifest::ModuleSource::FileReference(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut locked_app = spin_trigger::locked::build_locked_app(app, working_dir)?;
+        for (id, name) in unfetched {
+            if let Some(component) = locked_app.components.iter_mut().find(|c| c.id == id) {
+                component.source.content = ContentRef {
+                    source: Some(name),
+                    digest: None,
+                };
+            }
+        }
+        Ok(locked_app)
+    }
+}
+
+fn report(app: &LockedApp) -> AppReport {
+    let mut components = app
+        .components
+        .iter()
+        .map(|component| component_report(app, component))
+        .collect::<Vec<_>>();
+    components.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut variables = app
+        .variables
+        .iter()
+        .map(|(name, variable)| VariableReport {
+            name: name.clone(),
+            required: variable.default.is_none(),
+            default: match &variable.default {
+                Some(_) if variable.secret => Some(SECRET_MASK.to_owned()),
+                default => default.clone(),
+            },
+            secret: variable.secret,
+        })
+        .collect::<Vec<_>>();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    AppReport {
+        name: string(&app.metadata, NAME_KEY.as_ref()),
+        version: string(&app.metadata, VERSION_KEY.as_ref()),
+        description: string(&app.metadata, DESCRIPTION_KEY.as_ref()),
+        components,
+        variables,
+    }
+}
+
+fn component_report(app: &LockedApp, component: &LockedComponent) -> ComponentReport {
+    let triggers = app
+        .triggers
+        .iter()
+        .filter(|trigger| {
+            trigger
+                .trigger_config
+                .get("component")
+                .and_then(Value::as_str)
+                == Some(component.id.as_str())
+        })
+        .map(|trigger| TriggerReport {
+            trigger_type: trigger.trigger_type.clone(),
+            target: trigger_target(&trigger.trigger_config),
+        })
+        .collect();
+    let mut environment = component.env.keys().cloned().collect::<Vec<_>>();
+    environment.sort();
+
+    ComponentReport {
+        id: component.id.clone(),
+        description: string(&component.metadata, DESCRIPTION_KEY.as_ref()),
+        source: component.source.content.source.clone(),
+        enabled: string(&component.metadata, ENABLED_KEY.as_ref()),
+        triggers,
+        allowed_http_hosts: strings(
+            &component.metadata,
+            outbound_http::ALLOWED_HTTP_HOSTS_KEY.as_ref(),
+        ),
+        allowed_outbound_hosts: strings(&component.metadata, ALLOWED_OUTBOUND_HOSTS_KEY.as_ref()),
+        key_value_stores: strings(
+            &component.metadata,
+            spin_key_value::KEY_VALUE_STORES_KEY.as_ref(),
+        ),
+        sqlite_databases: strings(&component.metadata, "databases"),
+        files: component
+            .files
+            .iter()
+            .map(|file| FileReport {
+                path: file.path.clone(),
+                source: file.content.source.clone(),
+                writable: file.writable,
+            })
+            .collect(),
+        environment,
+    }
+}
+
+fn trigger_target(config: &Value) -> String {
+    for key in ["route", "channel"] {
+        if let Some(target) = config.get(key).and_then(Value::as_str) {
+            return target.to_owned();
+        }
+    }
+    let mut config = config.clone();
+    if let Some(config) = config.as_object_mut() {
+        config.remove("component");
+    }
+    config.to_string()
+}
+
+fn string(metadata: &serde_json::Map<String, Value>, key: &str) -> Option<String> {
+    metadata.get(key).and_then(Value::as_str).map(str::to_owned)
+}
+
+fn strings(metadata: &serde_json::Map<String, Value>, key: &str) -> Vec<String> {
+    let mut values = metadata
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    values.sort();
+    values
+}
+
+fn print_report(report: &AppReport) {
+    let name = report.name.as_deref().unwrap_or("(unnamed)");
+    match &report.version {
+        Some(version) => println!("{name} {version}"),
+        None => println!("{name}"),
+    }
+    if let Some(description) = &report.description {
+        println!("{description}");
+    }
+
+    for component in &report.components {
+        println!();
+        println!("Component {}", component.id);
+        if let Some(description) = &component.description {
+            println!("  {description}");
+        }
+        print_field("Source", component.source.iter());
+        print_field("Enabled when", component.enabled.iter());
+        print_field(
+            "Triggers",
+            component
+                .triggers
+                .iter()
+                .map(|t| format!("{} {}", t.trigger_type, t.target)),
+        );
+        print_field("Allowed HTTP hosts", component.allowed_http_hosts.iter());
+        print_field(
+            "Allowed outbound hosts",
+            component.allowed_outbound_hosts.iter(),
+        );
+        print_field("Key-value stores", component.key_value_stores.iter());
+        print_field("SQLite databases", component.sqlite_databases.iter());
+        print_field(
+            "Files",
+            component.files.iter().map(|file| {
+                format!(
+                    "{} <- {}{}",
+                    file.path.display(),
+                    file.source.as_deref().unwrap_or("(inline)"),
+                    if file.writable { " (writable)" } else { "" }
+                )
+            }),
+        );
+        print_field("Environment variables", component.environment.iter());
+    }
+
+    if !report.variables.is_empty() {
+        let mut table = Table::new();
+        table.set_header(vec!["Variable", "Required", "Default", "Secret"]);
+        table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        for variable in &report.variables {
+            table.add_row(vec![
+                variable.name.clone(),
+                yes_no(variable.required).to_owned(),
+                variable.default.clone().unwrap_or_default(),
+                yes_no(variable.secret).to_owned(),
+            ]);
+        }
+        println!();
+        println!("{table}");
+    }
+}
+
+fn print_field(label: &str, values: impl Iterator<Item = impl std::fmt::Display>) {
+    let values = values.map(|v| v.to_string()).collect::<Vec<_>>();
+    match values.as_slice() {
+        [] => {}
+        [value] => println!("  {label}: {value}"),
+        values => {
+            println!("  {label}:");
+            for value in values {
+                println!("    {value}");
+            }
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
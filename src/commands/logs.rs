@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use spin_trigger::{cli::RUNTIME_CONFIG_FILE, RuntimeConfig};
+
+use crate::opts::*;
+
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const LOG_STREAMS: [&str; 2] = ["stdout", "stderr"];
+
+/// Show the output written by the components of a local application.
+#[derive(Parser, Debug)]
+#[clap(about = "Show component output logged by a local application")]
+pub struct LogsCommand {
+    /// The application whose logs to show. This may be a manifest (spin.toml) file,
+    /// or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// The log directory, if the application was run with `--log-dir`.
+    #[clap(short = 'L', long = "log-dir")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Runtime configuration file the application was run with.
+    #[clap(long = "runtime-config-file", env = RUNTIME_CONFIG_FILE)]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Only show output from this component. Can be used multiple times.
+    #[clap(short = 'c', long = "component")]
+    pub components: Vec<String>,
+
+    /// Only show output written within this long ago, e.g. "30s", "10m",
+    /// "2h" or "1d". Plain text output has no timestamps, so for plain
+    /// text this selects the log files written to within that time.
+    #[clap(long = "since", parse(try_from_str = parse_log_since))]
+    pub since: Option<Duration>,
+
+    /// Keep showing output as it is written.
+    #[clap(long = "follow", takes_value = false)]
+    pub follow: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct LogFileId {
+    component: String,
+    stream: &'static str,
+}
+
+impl LogsCommand {
+    pub async fn run(self) -> Result<()> {
+        let log_dir = self.resolve_log_dir()?;
+        if !log_dir.is_dir() {
+            bail!(
+                "No logs found at {}. Has the application been run with `spin up`?",
+                log_dir.display()
+            );
+        }
+
+        // `--since` is checked against the clock as it is parsed.
+        let since = self
+            .since
+            .and_then(|since| SystemTime::now().checked_sub(since));
+        let log_files = self.log_files(&log_dir)?;
+
+        let mut positions = BTreeMap::new();
+        for (id, path) in &log_files {
+            let position = print_log_file(id, path, since)?;
+            positions.insert(path.clone(), position);
+        }
+
+        if self.follow {
+            loop {
+                tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+                for (id, path) in self.log_files(&log_dir)? {
+                    let position = positions.entry(path.clone()).or_default();
+                    *position = print_new_output(&id, &path, *position)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_log_dir(&self) -> Result<PathBuf> {
+        if let Some(log_dir) = &self.log_dir {
+            return Ok(log_dir.clone());
+        }
+        let manifest_file = crate::manifest::resolve_file_path(&self.app_source)?;
+        let local_app_dir = spin_loader::local::parent_dir(&manifest_file)?;
+        let mut runtime_config = RuntimeConfig::new(Some(local_app_dir));
+        if let Some(config_file) = &self.runtime_config_file {
+            runtime_config.merge_config_file(config_file)?;
+        }
+        runtime_config
+            .log_dir()
+            .context("The application's runtime configuration does not set a log directory")
+    }
+
+    // Returns the current log files to show, keyed by component and stream.
+    fn log_files(&self, log_dir: &Path) -> Result<BTreeMap<LogFileId, PathBuf>> {
+        let mut log_files = BTreeMap::new();
+        for entry in std::fs::read_dir(log_dir)
+            .with_context(|| format!("Failed to read log directory {}", log_dir.display()))?
+        {
+            let path = entry?.path();
+            let id = path
+                .file_name()
+                .and_then(|name| parse_log_file_name(&name.to_string_lossy()));
+            let Some(id) = id else {
+                continue;
+            };
+            if self.components.is_empty() || self.components.contains(&id.component) {
+                log_files.insert(id, path);
+            }
+        }
+
+        let found: HashSet<_> = log_files.keys().map(|id| &id.component).collect();
+        let missing: Vec<_> = self
+            .components
+            .iter()
+            .filter(|component| !found.contains(component))
+            .collect();
+        if !missing.is_empty() && !self.follow {
+            bail!(
+                "No logs found for component(s): {}",
+                missing
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(log_files)
+    }
+}
+
+// Parses a log file name of the form `<component>_<stream>.txt`. Rotated
+// files (`<component>_<stream>.txt.1` etc.) are not matched.
+fn parse_log_file_name(name: &str) -> Option<LogFileId> {
+    let stem = name.strip_suffix(".txt")?;
+    LOG_STREAMS.into_iter().find_map(|stream| {
+        let component = stem.strip_suffix(stream)?.strip_suffix('_')?;
+        (!component.is_empty()).then(|| LogFileId {
+            component: component.to_owned(),
+            stream,
+        })
+    })
+}
+
+// Prints the lines of a log file written since the given time, returning the
+// position at which the file ended.
+fn print_log_file(id: &LogFileId, path: &Path, since: Option<SystemTime>) -> Result<u64> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let length = file.metadata()?.len();
+    if let Some(since) = since {
+        if file.metadata()?.modified()? < since {
+            return Ok(length);
+        }
+    }
+    for line in BufReader::new(file.take(length)).split(b'\n') {
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        if let (Some(since), Some(timestamp)) = (since, record_timestamp(&line)) {
+            if timestamp < since {
+                continue;
+            }
+        }
+        print_line(id, &line);
+    }
+    Ok(length)
+}
+
+// Prints the complete lines written to a log file after `position`,
+// returning the position after the last of them.
+fn print_new_output(id: &LogFileId, path: &Path, position: u64) -> Result<u64> {
+    let Ok(mut file) = File::open(path) else {
+        return Ok(0);
+    };
+    let length = file.metadata()?.len();
+    // The file is smaller than when last read, so has been rotated.
+    let position = if length < position { 0 } else { position };
+    if length == position {
+        return Ok(position);
+    }
+
+    file.seek(SeekFrom::Start(position))?;
+    let mut output = vec![];
+    file.take(length - position).read_to_end(&mut output)?;
+    let Some(end) = output.iter().rposition(|b| *b == b'\n') else {
+        return Ok(position);
+    };
+    for line in output[..end].split(|b| *b == b'\n') {
+        print_line(id, &String::from_utf8_lossy(line));
+    }
+    Ok(position + end as u64 + 1)
+}
+
+fn print_line(id: &LogFileId, line: &str) {
+    println!("{} {}: {line}", id.component, id.stream);
+}
+
+// Returns the timestamp of a line written as a JSON record (see
+// `--log-format json`).
+fn record_timestamp(line: &str) -> Option<SystemTime> {
+    let record: serde_json::Value = serde_json::from_str(line).ok()?;
+    let timestamp = record.get("timestamp")?.as_str()?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(timestamp.with_timezone(&chrono::Utc).into())
+}
+
+pub(crate) fn parse_since(value: &str) -> Result<Duration> {
+    let (number, unit_secs) = match value.char_indices().last() {
+        Some((index, 's')) => (&value[..index], 1),
+        Some((index, 'm')) => (&value[..index], 60),
+        Some((index, 'h')) => (&value[..index], 60 * 60),
+        Some((index, 'd')) => (&value[..index], 24 * 60 * 60),
+        _ => bail!("Expected a duration such as '30s', '10m', '2h' or '1d'"),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration {value:?}"))?;
+    let secs = number
+        .checked_mul(unit_secs)
+        .with_context(|| format!("Duration {value:?} is too long"))?;
+    Ok(Duration::from_secs(secs))
+}
+
+// Parses a `--since` duration, which may reach back no further than the
+// system clock can represent.
+fn parse_log_since(value: &str) -> Result<Duration> {
+    let since = parse_since(value)?;
+    SystemTime::now()
+        .checked_sub(since)
+        .with_context(|| format!("Duration {value:?} is too long"))?;
+    Ok(since)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_log_file_names() {
+        assert_eq!(
+            parse_log_file_name("hello_stdout.txt"),
+            Some(LogFileId {
+                component: "hello".to_owned(),
+                stream: "stdout"
+            })
+        );
+        assert_eq!(
+            parse_log_file_name("my_component_stderr.txt"),
+            Some(LogFileId {
+                component: "my_component".to_owned(),
+                stream: "stderr"
+            })
+        );
+        assert_eq!(parse_log_file_name("hello_stdout.txt.1"), None);
+        assert_eq!(parse_log_file_name("_stdout.txt"), None);
+        assert_eq!(parse_log_file_name("hello.txt"), None);
+    }
+
+    #[test]
+    fn parses_since_durations() {
+        assert_eq!(parse_since("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_since("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_since("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_since("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_since("10").is_err());
+        assert!(parse_since("xm").is_err());
+        assert!(parse_since(&format!("{}d", u64::MAX / 2)).is_err());
+        assert!(parse_log_since(&format!("{}s", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn reads_timestamps_of_json_records() {
+        let line = r#"{"timestamp":"2023-05-01T12:00:00.000000Z","level":"INFO","message":"hi"}"#;
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(1682942400);
+        assert_eq!(record_timestamp(line), Some(expected));
+        assert_eq!(record_timestamp("plain text"), None);
+    }
+}
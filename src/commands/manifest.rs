@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use spin_loader::local::{
+    schema::manifest_schema,
+    validate::{validate_manifest_file, Severity},
+};
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+/// Commands for working with application manifests.
+#[derive(Subcommand, Debug)]
+pub enum ManifestCommands {
+    /// Check a spin.toml file for errors and deprecated settings.
+    Validate(Validate),
+    /// Print the JSON schema of spin.toml files, for use by editors.
+    Schema(Schema),
+}
+
+impl ManifestCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            ManifestCommands::Validate(cmd) => cmd.run().await,
+            ManifestCommands::Schema(cmd) => cmd.run().await,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Validate {
+    /// The manifest to check. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+}
+
+impl Validate {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = crate::manifest::resolve_file_path(&self.app_source)?;
+        let diagnostics = validate_manifest_file(&manifest_file).await?;
+
+        for diagnostic in &diagnostics {
+            match diagnostic.location {
+                Some(_) => eprintln!("{}:{diagnostic}", manifest_file.display()),
+                None => eprintln!("{}: {diagnostic}", manifest_file.display()),
+            }
+        }
+        let errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            bail!("{} has {errors} error(s)", manifest_file.display());
+        }
+        println!("{} is valid", manifest_file.display());
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Schema {
+    /// The file to write the schema to. If omitted, the schema is printed.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+impl Schema {
+    pub async fn run(self) -> Result<()> {
+        let schema = serde_json::to_string_pretty(&manifest_schema())?;
+        match &self.output {
+            Some(output) => std::fs::write(output, schema)
+                .with_context(|| format!("Failed to write schema to {}", output.display()))?,
+            None => println!("{schema}"),
+        }
+        Ok(())
+    }
+}
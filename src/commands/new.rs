@@ -10,7 +10,10 @@ use path_absolutize::Absolutize;
 use tokio;
 
 use spin_loader::local::absolutize;
-use spin_templates::{RunOptions, Template, TemplateManager, TemplateVariantInfo};
+use spin_templates::{
+    RunOptions, Template, TemplateManager, TemplateVariantInfo, TemplatesLockfile,
+    TEMPLATES_LOCKFILE,
+};
 
 use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
 
@@ -51,6 +54,11 @@ pub struct TemplateNewCommandCore {
     /// by accepting the defaults if available on the template
     #[clap(short = 'a', long = "accept-defaults", takes_value = false)]
     pub accept_defaults: bool,
+
+    /// Show the files that would be created, and the changes that would be
+    /// made to spin.toml, without writing anything.
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
 }
 
 /// Scaffold a new application based on a template.
@@ -141,6 +149,10 @@ impl TemplateNewCommandCore {
             return Ok(());
         }
 
+        if let TemplateVariantInfo::AddComponent { manifest_path } = &variant {
+            warn_if_not_pinned_revision(&template, manifest_path);
+        }
+
         let name = match &self.name {
             Some(name) => name.to_owned(),
             None => prompt_name(&variant).await?,
@@ -163,7 +175,14 @@ impl TemplateNewCommandCore {
             accept_defaults: self.accept_defaults,
         };
 
-        template.run(options).interactive().await
+        let run = template.run(options);
+        if self.dry_run {
+            if let Some(preview) = run.interactive_dry_run().await? {
+                print!("{preview}");
+            }
+            return Ok(());
+        }
+        run.interactive().await
     }
 }
 
@@ -280,6 +299,31 @@ lazy_static::lazy_static! {
     static ref NAME: regex::Regex = regex::Regex::new("^[a-zA-Z].*").expect("Invalid name regex");
 }
 
+// Components added to a project should come from the template revision the
+// project pins, so that they match what the rest of the team generates.
+fn warn_if_not_pinned_revision(template: &Template, manifest_path: &Path) {
+    let Some(app_dir) = manifest_path.parent() else {
+        return;
+    };
+    let Ok(Some(lockfile)) = TemplatesLockfile::load_if_exists(&app_dir.join(TEMPLATES_LOCKFILE))
+    else {
+        return;
+    };
+    let Some(repo) = template.source_repo() else {
+        return;
+    };
+    let Some(pinned) = lockfile.revision(repo) else {
+        return;
+    };
+    if template.source_revision() != Some(pinned) {
+        eprintln!(
+            "Warning: this project pins the templates from {repo} to revision {pinned}, but template {} was installed from a different revision.",
+            template.id()
+        );
+        eprintln!("Run `spin templates upgrade` in the project directory to install the pinned templates.");
+    }
+}
+
 fn path_safe(text: &str) -> PathBuf {
     let path = PATH_UNSAFE_CHARACTERS.replace_all(text, "_");
     PathBuf::from(path.to_string())
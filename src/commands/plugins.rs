@@ -4,9 +4,10 @@
 use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
 use semver::Version;
+use serde::Serialize;
 use spin_plugins::{
     error::Error,
-    lookup::{fetch_plugins_repo, plugins_repo_url, PluginLookup},
+    lookup::{fetch_plugins_repo, plugins_repo_urls, PluginLookup},
     manager::{self, InstallAction, ManifestLocation, PluginManager},
     manifest::{PluginManifest, PluginPackage},
 };
@@ -16,6 +17,7 @@ use url::Url;
 
 use crate::build_info::*;
 use crate::opts::*;
+use crate::output::OutputOpts;
 
 /// Install/uninstall Spin plugins.
 #[derive(Subcommand, Debug)]
@@ -36,7 +38,7 @@ pub enum PluginCommands {
     Upgrade(Upgrade),
 
     /// Fetch the latest Spin plugins from the spin-plugins repository.
-    Update,
+    Update(Update),
 }
 
 impl PluginCommands {
@@ -46,7 +48,7 @@ impl PluginCommands {
             PluginCommands::List(cmd) => cmd.run().await,
             PluginCommands::Uninstall(cmd) => cmd.run().await,
             PluginCommands::Upgrade(cmd) => cmd.run().await,
-            PluginCommands::Update => update().await,
+            PluginCommands::Update(cmd) => cmd.run().await,
         }
     }
 }
@@ -101,6 +103,9 @@ pub struct Install {
         requires(PLUGIN_NAME_OPT)
     )]
     pub version: Option<Version>,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 impl Install {
@@ -111,19 +116,21 @@ impl Install {
             (None, None, Some(name)) => ManifestLocation::PluginsRepository(PluginLookup::new(name, self.version.clone())),
             _ => return Err(anyhow::anyhow!("For plugin lookup, must provide exactly one of: plugin name, url to manifest, local path to manifest")),
         };
+        self.output.init();
         let manager = PluginManager::try_default()?;
         // Downgrades are only allowed via the `upgrade` subcommand
         let downgrade = false;
         let manifest = manager.get_manifest(&manifest_location).await?;
-        try_install(
+        let installed = try_install(
             &manifest,
             &manager,
             self.yes_to_all,
             self.override_compatibility_check,
             downgrade,
+            &self.output,
         )
         .await?;
-        Ok(())
+        self.output.result(&installed, |_| ())
     }
 }
 
@@ -132,21 +139,35 @@ impl Install {
 pub struct Uninstall {
     /// Name of Spin plugin.
     pub name: String,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+#[derive(Serialize)]
+struct Uninstalled<'a> {
+    name: &'a str,
+    uninstalled: bool,
 }
 
 impl Uninstall {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         let manager = PluginManager::try_default()?;
-        let uninstalled = manager.uninstall(&self.name)?;
-        if uninstalled {
-            println!("Plugin {} was successfully uninstalled", self.name);
-        } else {
-            println!(
-                "Plugin {} isn't present, so no changes were made",
-                self.name
-            );
-        }
-        Ok(())
+        let uninstalled = Uninstalled {
+            name: &self.name,
+            uninstalled: manager.uninstall(&self.name)?,
+        };
+        self.output.result(&uninstalled, |result| {
+            if result.uninstalled {
+                println!("Plugin {} was successfully uninstalled", result.name);
+            } else {
+                println!(
+                    "Plugin {} isn't present, so no changes were made",
+                    result.name
+                );
+            }
+        })
     }
 }
 
@@ -213,6 +234,9 @@ pub struct Upgrade {
     /// Allow downgrading a plugin's version.
     #[clap(short = 'd', long = "downgrade", takes_value = false)]
     pub downgrade: bool,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 impl Upgrade {
@@ -220,29 +244,31 @@ impl Upgrade {
     /// version of a plugin. If downgrade is specified, first uninstalls the
     /// plugin.
     pub async fn run(self) -> Result<()> {
+        self.output.init();
+        let output = self.output.clone();
         let manager = PluginManager::try_default()?;
         let manifests_dir = manager.store().installed_manifests_directory();
 
         // Check if no plugins are currently installed
-        if !manifests_dir.exists() {
-            println!("No currently installed plugins to upgrade.");
-            return Ok(());
-        }
-
-        if self.all {
-            self.upgrade_all(manifests_dir).await
+        let upgraded = if !manifests_dir.exists() {
+            output.status("No currently installed plugins to upgrade.");
+            vec![]
+        } else if self.all {
+            self.upgrade_all(manifests_dir).await?
         } else {
             let plugin_name = self
                 .name
                 .clone()
                 .context("plugin name is required for upgrades")?;
-            self.upgrade_one(&plugin_name).await
-        }
+            self.upgrade_one(&plugin_name).await?.into_iter().collect()
+        };
+        output.result(&upgraded, |_| ())
     }
 
     // Install the latest of all currently installed plugins
-    async fn upgrade_all(&self, manifests_dir: impl AsRef<Path>) -> Result<()> {
+    async fn upgrade_all(&self, manifests_dir: impl AsRef<Path>) -> Result<Vec<InstalledPlugin>> {
         let manager = PluginManager::try_default()?;
+        let mut upgraded = vec![];
         for plugin in std::fs::read_dir(manifests_dir)? {
             let path = plugin?.path();
             let name = path
@@ -261,19 +287,22 @@ impl Upgrade {
                 Err(e) => return Err(e.into()),
                 Ok(m) => m,
             };
-            try_install(
-                &manifest,
-                &manager,
-                self.yes_to_all,
-                self.override_compatibility_check,
-                self.downgrade,
-            )
-            .await?;
+            upgraded.extend(
+                try_install(
+                    &manifest,
+                    &manager,
+                    self.yes_to_all,
+                    self.override_compatibility_check,
+                    self.downgrade,
+                    &self.output,
+                )
+                .await?,
+            );
         }
-        Ok(())
+        Ok(upgraded)
     }
 
-    async fn upgrade_one(self, name: &str) -> Result<()> {
+    async fn upgrade_one(self, name: &str) -> Result<Option<InstalledPlugin>> {
         let manager = PluginManager::try_default()?;
         let manifest_location = match (self.local_manifest_src, self.remote_manifest_src) {
             (Some(path), None) => ManifestLocation::Local(path),
@@ -287,9 +316,9 @@ impl Upgrade {
             self.yes_to_all,
             self.override_compatibility_check,
             self.downgrade,
+            &self.output,
         )
-        .await?;
-        Ok(())
+        .await
     }
 }
 
@@ -299,10 +328,14 @@ pub struct List {
     /// List only installed plugins.
     #[clap(long = "installed", takes_value = false)]
     pub installed: bool,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 impl List {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         let mut plugins = if self.installed {
             Self::list_installed_plugins()
         } else {
@@ -311,8 +344,7 @@ impl List {
 
         plugins.sort_by(|p, q| p.cmp(q));
 
-        Self::print(&plugins);
-        Ok(())
+        self.output.result(plugins.as_slice(), Self::print)
     }
 
     fn list_installed_plugins() -> Result<Vec<PluginDescriptor>> {
@@ -364,7 +396,8 @@ impl List {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub(crate) enum PluginCompatibility {
     Compatible,
     IncompatibleSpin(String),
@@ -386,7 +419,7 @@ impl PluginCompatibility {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct PluginDescriptor {
     name: String,
     version: String,
@@ -408,13 +441,27 @@ impl PluginDescriptor {
     }
 }
 
+#[derive(Parser, Debug)]
+pub struct Update {
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+impl Update {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        update(&self.output).await
+    }
+}
+
 /// Updates the locally cached spin-plugins repository, fetching the latest plugins.
-pub(crate) async fn update() -> Result<()> {
+pub(crate) async fn update(output: &OutputOpts) -> Result<()> {
     let manager = PluginManager::try_default()?;
     let plugins_dir = manager.store().get_plugins_directory();
-    let url = plugins_repo_url()?;
-    fetch_plugins_repo(&url, plugins_dir, true).await?;
-    println!("Plugin information updated successfully");
+    for url in plugins_repo_urls()? {
+        fetch_plugins_repo(&url, plugins_dir, true).await?;
+    }
+    output.status("Plugin information updated successfully");
     Ok(())
 }
 
@@ -422,11 +469,16 @@ fn continue_to_install(
     manifest: &PluginManifest,
     package: &PluginPackage,
     yes_to_all: bool,
+    output: &OutputOpts,
 ) -> Result<bool> {
-    Ok(yes_to_all || prompt_confirm_install(manifest, package)?)
+    Ok(yes_to_all || prompt_confirm_install(manifest, package, output)?)
 }
 
-fn prompt_confirm_install(manifest: &PluginManifest, package: &PluginPackage) -> Result<bool> {
+fn prompt_confirm_install(
+    manifest: &PluginManifest,
+    package: &PluginPackage,
+    output: &OutputOpts,
+) -> Result<bool> {
     let prompt = format!(
         "Are you sure you want to install plugin '{}' with license {} from {}?",
         manifest.name(),
@@ -439,18 +491,29 @@ fn prompt_confirm_install(manifest: &PluginManifest, package: &PluginPackage) ->
         .interact_opt()?
         .unwrap_or(false);
     if !install {
-        println!("Plugin '{}' will not be installed", manifest.name());
+        output.status(format!(
+            "Plugin '{}' will not be installed",
+            manifest.name()
+        ));
     }
     Ok(install)
 }
 
+/// A plugin installed by `spin plugins install` or `spin plugins upgrade`.
+#[derive(Debug, Serialize)]
+struct InstalledPlugin {
+    name: String,
+    version: String,
+}
+
 async fn try_install(
     manifest: &PluginManifest,
     manager: &PluginManager,
     yes_to_all: bool,
     override_compatibility_check: bool,
     downgrade: bool,
-) -> Result<bool> {
+    output: &OutputOpts,
+) -> Result<Option<InstalledPlugin>> {
     let install_action = manager.check_manifest(
         manifest,
         SPIN_VERSION,
@@ -459,27 +522,30 @@ async fn try_install(
     )?;
 
     if let InstallAction::NoAction { name, version } = install_action {
-        eprintln!("Plugin '{name}' is already installed with version {version}.");
-        return Ok(false);
+        if !output.quiet {
+            eprintln!("Plugin '{name}' is already installed with version {version}.");
+        }
+        return Ok(None);
     }
 
     let package = manager::get_package(manifest)?;
-    if continue_to_install(manifest, package, yes_to_all)? {
+    if continue_to_install(manifest, package, yes_to_all, output)? {
         let installed = manager.install(manifest, package).await?;
-        println!("Plugin '{installed}' was installed successfully!");
+        output.status(format!("Plugin '{installed}' was installed successfully!"));
 
         if let Some(description) = manifest.description() {
-            println!("\nDescription:");
-            println!("\t{description}");
+            output.status(format!("\nDescription:\n\t{description}"));
         }
 
         if let Some(homepage) = manifest.homepage_url().filter(|h| h.scheme() == "https") {
-            println!("\nHomepage:");
-            println!("\t{homepage}");
+            output.status(format!("\nHomepage:\n\t{homepage}"));
         }
 
-        Ok(true)
+        Ok(Some(InstalledPlugin {
+            name: installed,
+            version: manifest.version().to_owned(),
+        }))
     } else {
-        Ok(false)
+        Ok(None)
     }
 }
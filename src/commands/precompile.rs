@@ -0,0 +1,48 @@
+use std::ffi::OsString;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use spin_trigger::{cli::SPIN_PRECOMPILE_ONLY, compile_cache::CompileCache};
+
+use super::up::UpCommand;
+
+/// Compile the components of an application into the cache, so that later
+/// runs of the application start without compiling them.
+#[derive(Parser, Debug)]
+#[clap(
+    about = "Compile the Spin application's components ahead of time",
+    allow_hyphen_values = true
+)]
+pub struct PrecompileCommand {
+    /// Options for `spin up`, such as `--from` and `--runtime-config-file`.
+    /// Components are compiled with the same Wasmtime configuration that
+    /// `spin up` would use with these options.
+    pub up_args: Vec<OsString>,
+}
+
+impl PrecompileCommand {
+    pub async fn run(self) -> Result<()> {
+        let cmd = UpCommand::parse_from(
+            std::iter::once(OsString::from(format!(
+                "{} up",
+                std::env::args().next().unwrap()
+            )))
+            .chain(self.up_args),
+        );
+        if cmd.watch || cmd.detach || cmd.help {
+            bail!("`--watch`, `--detach` and `--help` cannot be used with `spin precompile`");
+        }
+
+        // The trigger exits once it has loaded, and so compiled, every
+        // component.
+        std::env::set_var(SPIN_PRECOMPILE_ONLY, "1");
+        cmd.run().await?;
+
+        terminal::step!(
+            "Finished",
+            "compiling components into {}",
+            CompileCache::default_dir()?.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use clap::Parser;
+use comfy_table::Table;
+
+use crate::daemon;
+
+/// List the applications running in the background.
+#[derive(Parser, Debug)]
+#[clap(about = "List applications running in the background")]
+pub struct PsCommand {}
+
+impl PsCommand {
+    pub async fn run(self) -> Result<()> {
+        let records = daemon::list()?;
+        if records.is_empty() {
+            println!("No applications are running in the background");
+            return Ok(());
+        }
+
+        let mut table = Table::new();
+        table.set_header(vec!["PID", "Application", "Started", "Output"]);
+        table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        for record in records {
+            table.add_row(vec![
+                record.pid.to_string(),
+                record.app,
+                record.started_at,
+                record.log_file.display().to_string(),
+            ]);
+        }
+        println!("{table}");
+        Ok(())
+    }
+}
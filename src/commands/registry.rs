@@ -1,9 +1,11 @@
 use crate::opts::*;
-use anyhow::{Context, Result};
+use crate::output::OutputOpts;
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 use spin_oci::Client;
-use std::{io::Read, path::PathBuf, time::Duration};
+use std::{collections::HashMap, io::Read, path::PathBuf, time::Duration};
 
 /// Commands for working with OCI registries to distribute applications.
 #[derive(Subcommand, Debug)]
@@ -14,6 +16,18 @@ pub enum RegistryCommands {
     Pull(Pull),
     /// Log in to a registry.
     Login(Login),
+    /// Save a Spin application from a registry to an archive file.
+    Save(Save),
+    /// Load a Spin application from an archive file into a registry or the
+    /// local cache.
+    Load(Load),
+    /// Generate a key pair for signing applications.
+    GenerateKeyPair(GenerateKeyPair),
+    /// Fetch the SBOM attached to a Spin application in a registry.
+    Sbom(Sbom),
+    /// List or pull the artifacts, such as signatures and SBOMs, attached to
+    /// a Spin application in a registry.
+    Referrers(Referrers),
 }
 
 impl RegistryCommands {
@@ -22,6 +36,11 @@ impl RegistryCommands {
             RegistryCommands::Push(cmd) => cmd.run().await,
             RegistryCommands::Pull(cmd) => cmd.run().await,
             RegistryCommands::Login(cmd) => cmd.run().await,
+            RegistryCommands::Save(cmd) => cmd.run().await,
+            RegistryCommands::Load(cmd) => cmd.run().await,
+            RegistryCommands::GenerateKeyPair(cmd) => cmd.run().await,
+            RegistryCommands::Sbom(cmd) => cmd.run().await,
+            RegistryCommands::Referrers(cmd) => cmd.run().await,
         }
     }
 }
@@ -49,29 +68,220 @@ pub struct Push {
     )]
     pub insecure: bool,
 
+    /// Add an annotation (key=value) to the pushed artifact. This may be a
+    /// standard `org.opencontainers.image.*` annotation or a custom key, and
+    /// replaces any annotation derived from the application manifest.
+    #[clap(long = "annotation", multiple_occurrences = true, parse(try_from_str = parse_annotation))]
+    pub annotations: Vec<(String, String)>,
+
+    /// When the artifact was created, as an RFC 3339 timestamp. The default
+    /// is the time of the push.
+    #[clap(long = "created")]
+    pub created: Option<String>,
+
+    /// The authors of the artifact. The default is the authors listed in the
+    /// application manifest.
+    #[clap(long = "authors")]
+    pub authors: Option<String>,
+
+    /// Push a variant of the application, as `<VARIANT>=<MANIFEST>`, where
+    /// the variant is a platform such as `linux/arm64` or a name such as
+    /// `production`. May be given more than once: the variants are pushed
+    /// as an index, from which `spin up` and `spin registry pull` select the
+    /// variant for their platform, or the one named with `--variant`.
+    #[clap(
+        long = "variant",
+        multiple_occurrences = true,
+        conflicts_with = APP_MANIFEST_FILE_OPT,
+        parse(try_from_str = parse_variant_source)
+    )]
+    pub variants: Vec<(spin_oci::Variant, PathBuf)>,
+
+    /// Sign the pushed application, so that it can be verified when it is
+    /// pulled.
+    #[clap(long = "sign", takes_value = false, requires = "signing-key")]
+    pub sign: bool,
+
+    /// The private key with which to sign the application, as generated by
+    /// `spin registry generate-key-pair`.
+    #[clap(name = "signing-key", long = "signing-key", env = "SPIN_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
+
+    /// The issuer to name as the signer, for trust policies which require
+    /// signatures from particular issuers. Those policies must trust the
+    /// signing key for the issuer.
+    #[clap(long = "issuer", requires = "sign")]
+    pub issuer: Option<String>,
+
+    /// Generate an SBOM listing the application's components, their hashes
+    /// and declared dependencies, and the templates pinned in the
+    /// application's templates lockfile, and attach it to the pushed
+    /// application.
+    #[clap(long = "sbom", takes_value = false, conflicts_with = "variants")]
+    pub sbom: bool,
+
+    /// The format of the SBOM: `cyclonedx` or `spdx`.
+    #[clap(long = "sbom-format", default_value = "cyclonedx")]
+    pub sbom_format: spin_oci::SbomFormat,
+
     /// Reference of the Spin application
     #[clap()]
     pub reference: String,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
+}
+
+#[derive(Serialize)]
+struct Pushed {
+    reference: String,
+    digest: Option<String>,
+    signed: bool,
+    sbom_digest: Option<String>,
 }
 
 impl Push {
     pub async fn run(self) -> Result<()> {
-        let app_file = crate::manifest::resolve_file_path(&self.app_source)?;
-
+        self.output_opts.init();
         let dir = tempfile::tempdir()?;
-        let app = spin_loader::local::from_file(&app_file, Some(dir.path())).await?;
+        let mut apps = Vec::new();
+        let mut sbom = None;
+        if self.variants.is_empty() {
+            let app_file = crate::manifest::resolve_file_path(&self.app_source)?;
+            let app = spin_loader::local::from_file(&app_file, Some(dir.path())).await?;
+            if self.sbom {
+                let templates = pinned_templates(&app_file)?;
+                sbom = Some(spin_oci::Sbom::from_app(&app, templates)?);
+            }
+            apps.push(app);
+        } else {
+            for (index, (_, source)) in self.variants.iter().enumerate() {
+                let app_file = crate::manifest::resolve_file_path(source)?;
+                let asset_dir = dir.path().join(index.to_string());
+                apps.push(spin_loader::local::from_file(&app_file, Some(&asset_dir)).await?);
+            }
+        }
+
+        let signing_key = match (&self.signing_key, self.sign) {
+            (Some(path), true) => Some(spin_oci::SigningKey::load(path)?),
+            _ => None,
+        };
 
         let mut client = spin_oci::Client::new(self.insecure, None).await?;
 
-        let _spinner = create_dotted_spinner(2000, "Pushing app to the Registry".to_owned());
+        let _spinner = create_dotted_spinner(
+            2000,
+            "Pushing app to the Registry".to_owned(),
+            &self.output_opts,
+        );
+
+        let mut annotations: HashMap<_, _> = self.annotations.into_iter().collect();
+        if let Some(created) = self.created {
+            chrono::DateTime::parse_from_rfc3339(&created).with_context(|| {
+                format!("Invalid creation time {created:?}: expected an RFC 3339 timestamp")
+            })?;
+            annotations.insert(spin_oci::ANNOTATION_CREATED.to_owned(), created);
+        }
+        if let Some(authors) = self.authors {
+            annotations.insert(spin_oci::ANNOTATION_AUTHORS.to_owned(), authors);
+        }
+
+        let digest = if self.variants.is_empty() {
+            client.push(&apps[0], &self.reference, annotations).await?
+        } else {
+            let variants = self
+                .variants
+                .into_iter()
+                .map(|(variant, _)| variant)
+                .zip(apps)
+                .collect::<Vec<_>>();
+            client
+                .push_variants(&variants, &self.reference, annotations)
+                .await?
+        };
+        match &digest {
+            Some(digest) => self
+                .output_opts
+                .status(format!("Pushed with digest {digest}")),
+            None => self
+                .output_opts
+                .status("Pushed; the registry did not return the digest"),
+        };
 
-        let digest = client.push(&app, &self.reference).await?;
-        match digest {
-            Some(digest) => println!("Pushed with digest {digest}"),
-            None => println!("Pushed; the registry did not return the digest"),
+        let mut pushed = Pushed {
+            reference: self.reference.clone(),
+            digest: digest.clone(),
+            signed: false,
+            sbom_digest: None,
         };
 
-        Ok(())
+        if let Some(key) = signing_key {
+            let digest = digest
+                .as_deref()
+                .context("Cannot sign the application: the registry did not return its digest")?;
+            client
+                .sign(&self.reference, digest, &key, self.issuer.as_deref())
+                .await?;
+            self.output_opts.status(format!("Signed {digest}"));
+            pushed.signed = true;
+        }
+
+        if let Some(sbom) = sbom {
+            let digest = digest.as_deref().context(
+                "Cannot attach the SBOM to the application: the registry did not return its digest",
+            )?;
+            let sbom_digest = client
+                .attach_sbom(&self.reference, digest, &sbom, self.sbom_format)
+                .await?;
+            self.output_opts
+                .status(format!("Attached {} SBOM {sbom_digest}", self.sbom_format));
+            pushed.sbom_digest = Some(sbom_digest);
+        }
+
+        self.output_opts.result(&pushed, |_| ())
+    }
+}
+
+// The template repositories pinned next to the manifest, from which the
+// application's components were generated.
+fn pinned_templates(app_file: &std::path::Path) -> Result<Vec<spin_oci::TemplateProvenance>> {
+    let app_dir = app_file
+        .parent()
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let lockfile = spin_templates::TemplatesLockfile::load_if_exists(
+        &app_dir.join(spin_templates::TEMPLATES_LOCKFILE),
+    )?;
+    Ok(lockfile
+        .iter()
+        .flat_map(|lockfile| lockfile.repositories())
+        .map(|r| spin_oci::TemplateProvenance {
+            repository: r.git.clone(),
+            revision: r.revision.clone(),
+        })
+        .collect())
+}
+
+fn parse_variant_source(s: &str) -> Result<(spin_oci::Variant, PathBuf)> {
+    let (variant, source) = s
+        .split_once('=')
+        .context("Variant must be of the form `<variant>=<manifest>`")?;
+    Ok((variant.parse()?, PathBuf::from(source)))
+}
+
+/// Parses a registry mirror given as `<registry>=<mirror>`.
+pub(crate) fn parse_registry_mirror(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((registry, mirror)) if !registry.is_empty() && !mirror.is_empty() => {
+            Ok((registry.to_owned(), mirror.to_owned()))
+        }
+        _ => anyhow::bail!("Registry mirror must be of the form `<registry>=<mirror>`"),
+    }
+}
+
+fn parse_annotation(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => anyhow::bail!("Annotation must be of the form `key=value`"),
     }
 }
 
@@ -86,24 +296,208 @@ pub struct Pull {
     )]
     pub insecure: bool,
 
+    /// A public key whose signatures are trusted, as generated by `spin
+    /// registry generate-key-pair`. If given, a signature which does not
+    /// verify with any trusted key fails the pull.
+    #[clap(long = "trusted-key", multiple_occurrences = true)]
+    pub trusted_keys: Vec<PathBuf>,
+
+    /// Refuse to pull the application unless it has a trusted signature.
+    #[clap(long = "require-signature", takes_value = false)]
+    pub require_signature: bool,
+
+    /// If the application was pushed with variants, the variant to pull: a
+    /// platform such as `linux/arm64` or a variant name. The default is the
+    /// variant for this platform.
+    #[clap(long = "variant")]
+    pub variant: Option<spin_oci::Variant>,
+
+    /// Pull through a mirror, as `<REGISTRY>=<MIRROR>`, e.g.
+    /// `ghcr.io=mirror.example.com/ghcr`. May be given more than once;
+    /// mirrors are tried in order, and the registry itself is tried last.
+    #[clap(
+        long = "registry-mirror",
+        multiple_occurrences = true,
+        parse(try_from_str = parse_registry_mirror)
+    )]
+    pub registry_mirrors: Vec<(String, String)>,
+
     /// Reference of the Spin application
     #[clap()]
     pub reference: String,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
 }
 
 impl Pull {
     /// Pull a Spin application from an OCI registry
     pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
         let mut client = spin_oci::Client::new(self.insecure, None).await?;
+        if !self.trusted_keys.is_empty() || self.require_signature {
+            client.set_trust_policy(spin_oci::TrustPolicy::load(
+                &self.trusted_keys,
+                self.require_signature,
+            )?);
+        }
+        if let Some(variant) = self.variant {
+            client.set_variant(variant);
+        }
+        let mut mirrors = spin_oci::Mirrors::default();
+        for (registry, mirror) in &self.registry_mirrors {
+            mirrors.add(registry, mirror);
+        }
+        client.set_mirrors(mirrors);
 
-        let _spinner = create_dotted_spinner(2000, "Pulling app from the Registry".to_owned());
+        let _spinner = create_dotted_spinner(
+            2000,
+            "Pulling app from the Registry".to_owned(),
+            &self.output_opts,
+        );
 
         client.pull(&self.reference).await?;
-        println!("Successfully pulled the app from the registry");
-        Ok(())
+        self.output_opts
+            .status("Successfully pulled the app from the registry");
+        self.output_opts.result(
+            &Pulled {
+                reference: &self.reference,
+            },
+            |_| (),
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct Pulled<'a> {
+    reference: &'a str,
+}
+
+#[derive(Parser, Debug)]
+pub struct Save {
+    /// Ignore server certificate errors
+    #[clap(
+        name = INSECURE_OPT,
+        short = 'k',
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+
+    /// The archive file to write.
+    #[clap(short = 'o', long = "output")]
+    pub output: PathBuf,
+
+    /// Reference of the Spin application
+    #[clap()]
+    pub reference: String,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
+}
+
+impl Save {
+    pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
+        let mut client = spin_oci::Client::new(self.insecure, None).await?;
+
+        let _spinner = create_dotted_spinner(
+            2000,
+            "Saving app from the Registry".to_owned(),
+            &self.output_opts,
+        );
+
+        client.save(&self.reference, &self.output).await?;
+        self.output_opts.status(format!(
+            "Saved {} to {}",
+            self.reference,
+            self.output.display()
+        ));
+        let saved = Saved {
+            reference: &self.reference,
+            archive: &self.output,
+        };
+        self.output_opts.result(&saved, |_| ())
     }
 }
 
+#[derive(Serialize)]
+struct Saved<'a> {
+    reference: &'a str,
+    archive: &'a std::path::Path,
+}
+
+#[derive(Parser, Debug)]
+pub struct Load {
+    /// Ignore server certificate errors
+    #[clap(
+        name = INSECURE_OPT,
+        short = 'k',
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+
+    /// The registry reference to push the application to.
+    #[clap(long = "to")]
+    pub to: Option<String>,
+
+    /// Load the application into the local cache under this reference,
+    /// rather than pushing it, so that `spin up --from` can run it without
+    /// access to that registry.
+    #[clap(long = "as", conflicts_with = "to")]
+    pub cache_as: Option<String>,
+
+    /// The archive file written by `spin registry save`.
+    #[clap()]
+    pub archive: PathBuf,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
+}
+
+impl Load {
+    pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
+        let mut client = spin_oci::Client::new(self.insecure, None).await?;
+
+        let _spinner = create_dotted_spinner(2000, "Loading app".to_owned(), &self.output_opts);
+
+        let reference = match (&self.to, &self.cache_as) {
+            (Some(to), _) => client.load(&self.archive, to).await?,
+            (None, Some(cache_as)) => client.load_into_cache(&self.archive, cache_as).await?,
+            (None, None) => match spin_oci::Client::archived_reference(&self.archive)? {
+                Some(saved_from) => bail!(
+                    "Give --to to push the application, or --as to load it into the cache. It was saved from {saved_from}: to run it as that, use `--as {saved_from}`"
+                ),
+                None => bail!("Give --to to push the application, or --as to load it into the cache"),
+            },
+        };
+        match self.to {
+            Some(_) => self
+                .output_opts
+                .status(format!("Pushed {} to {reference}", self.archive.display())),
+            None => self.output_opts.status(format!(
+                "Loaded {} into the cache as {reference}",
+                self.archive.display()
+            )),
+        }
+        let loaded = Loaded {
+            archive: &self.archive,
+            reference: &reference,
+            pushed: self.to.is_some(),
+        };
+        self.output_opts.result(&loaded, |_| ())
+    }
+}
+
+#[derive(Serialize)]
+struct Loaded<'a> {
+    archive: &'a std::path::Path,
+    reference: &'a str,
+    pushed: bool,
+}
+
 #[derive(Parser, Debug)]
 pub struct Login {
     /// Username for the registry
@@ -124,10 +518,14 @@ pub struct Login {
 
     #[clap()]
     pub server: String,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
 }
 
 impl Login {
     pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
         let username = match self.username {
             Some(u) => u,
             None => {
@@ -164,15 +562,181 @@ impl Login {
             .await
             .context("cannot log in to the registry")?;
 
-        println!(
+        self.output_opts.status(format!(
             "Successfully logged in as {} to registry {}",
             username, &self.server
-        );
+        ));
         Ok(())
     }
 }
 
-fn create_dotted_spinner(interval: u64, message: String) -> ProgressBar {
+#[derive(Parser, Debug)]
+pub struct GenerateKeyPair {
+    /// The path prefix of the key files. The private key is written to
+    /// `<PREFIX>.key` and the public key to `<PREFIX>.pub`.
+    #[clap(long = "output", short = 'o', default_value = "spin")]
+    pub output: PathBuf,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
+}
+
+impl GenerateKeyPair {
+    pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
+        let private_path = self.output.with_extension("key");
+        let public_path = self.output.with_extension("pub");
+        for path in [&private_path, &public_path] {
+            if path.exists() {
+                anyhow::bail!("{} already exists", path.display());
+            }
+        }
+
+        let key = spin_oci::SigningKey::generate();
+        key.save(&private_path)?;
+        key.verifying_key().save(&public_path)?;
+
+        self.output_opts.status(format!(
+            "Private key written to {}. Keep it secret: use it with `spin registry push --sign --signing-key`.",
+            private_path.display()
+        ));
+        self.output_opts.status(format!(
+            "Public key written to {}. Use it with `--trusted-key` or in runtime config `[registry_trust]` to verify signatures.",
+            public_path.display()
+        ));
+        let keys = KeyPairPaths {
+            private_key: &private_path,
+            public_key: &public_path,
+        };
+        self.output_opts.result(&keys, |_| ())
+    }
+}
+
+#[derive(Serialize)]
+struct KeyPairPaths<'a> {
+    private_key: &'a std::path::Path,
+    public_key: &'a std::path::Path,
+}
+
+// This has no output options, as its `--format` is the format of the SBOM,
+// which it prints as is.
+#[derive(Parser, Debug)]
+pub struct Sbom {
+    /// Ignore server certificate errors
+    #[clap(
+        name = INSECURE_OPT,
+        short = 'k',
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+
+    /// The format of SBOM to fetch: `cyclonedx` or `spdx`. If omitted, the
+    /// most recently attached SBOM in either format is fetched.
+    #[clap(long = "format")]
+    pub format: Option<spin_oci::SbomFormat>,
+
+    /// The file to write the SBOM to. If omitted, it is written to stdout.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+
+    /// Reference of the Spin application
+    #[clap()]
+    pub reference: String,
+}
+
+impl Sbom {
+    pub async fn run(self) -> Result<()> {
+        let mut client = spin_oci::Client::new(self.insecure, None).await?;
+        let (format, sbom) = client.pull_sbom(&self.reference, self.format).await?;
+        match &self.output {
+            Some(path) => {
+                std::fs::write(path, sbom)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                eprintln!("Wrote {format} SBOM to {}", path.display());
+            }
+            None => {
+                use std::io::Write;
+                std::io::stdout().write_all(&sbom)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Referrers {
+    /// Ignore server certificate errors
+    #[clap(
+        name = INSECURE_OPT,
+        short = 'k',
+        long = "insecure",
+        takes_value = false,
+    )]
+    pub insecure: bool,
+
+    /// List only artifacts of this type, such as `application/spdx+json`.
+    #[clap(long = "artifact-type")]
+    pub artifact_type: Option<String>,
+
+    /// Pull the attached artifact with this digest, rather than listing the
+    /// attached artifacts.
+    #[clap(long = "pull")]
+    pub pull: Option<String>,
+
+    /// The directory to pull the artifact's files into.
+    #[clap(short = 'o', long = "output", default_value = ".")]
+    pub output: PathBuf,
+
+    /// Reference of the Spin application
+    #[clap()]
+    pub reference: String,
+
+    #[clap(flatten)]
+    pub output_opts: OutputOpts,
+}
+
+impl Referrers {
+    pub async fn run(self) -> Result<()> {
+        self.output_opts.init();
+        let mut client = spin_oci::Client::new(self.insecure, None).await?;
+
+        if let Some(digest) = &self.pull {
+            let paths = client
+                .pull_referrer(&self.reference, digest, &self.output)
+                .await?;
+            return self.output_opts.result(&paths, |paths| {
+                for path in paths {
+                    println!("{}", path.display());
+                }
+            });
+        }
+
+        let referrers = client
+            .referrers(&self.reference, self.artifact_type.as_deref())
+            .await?;
+        self.output_opts.result(&referrers, |referrers| {
+            if referrers.is_empty() {
+                println!("No artifacts are attached to {}", self.reference);
+            }
+            for referrer in referrers {
+                let artifact_type = referrer
+                    .artifact_type
+                    .as_deref()
+                    .unwrap_or("(unknown type)");
+                match &referrer.tag {
+                    Some(tag) => println!("{}  {artifact_type}  (tag {tag})", referrer.digest),
+                    None => println!("{}  {artifact_type}", referrer.digest),
+                }
+            }
+        })
+    }
+}
+
+fn create_dotted_spinner(interval: u64, message: String, output: &OutputOpts) -> ProgressBar {
+    if output.quiet {
+        return ProgressBar::hidden();
+    }
     let spinner = ProgressBar::new_spinner();
     spinner.enable_steady_tick(Duration::from_millis(interval));
     spinner.set_style(
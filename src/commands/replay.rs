@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use reqwest::{header::HeaderName, Method, Url};
+use spin_trigger::record::{
+    read_recording, RecordedRequest, RecordedResponse, TriggerEvent, CREDENTIAL_HEADERS, REDACTED,
+};
+
+// Headers which describe the original connection rather than the request.
+const SKIPPED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    spin_telemetry::REQUEST_ID_HEADER,
+];
+
+/// Re-send the events recorded by `spin up --record` to a running application,
+/// and report the responses which differ from those recorded.
+#[derive(Parser, Debug)]
+#[clap(about = "Replay recorded trigger events against a running application")]
+pub struct ReplayCommand {
+    /// The recording file written by `spin up --record`.
+    pub recording: PathBuf,
+
+    /// The URL at which the application's HTTP trigger listens.
+    #[clap(long = "http-url", default_value = "http://127.0.0.1:3000")]
+    pub http_url: Url,
+
+    /// The Redis server to which to publish recorded Redis messages. If
+    /// omitted, Redis messages are skipped.
+    #[clap(long = "redis-url")]
+    pub redis_url: Option<String>,
+
+    /// Compare response bodies as well as status codes.
+    #[clap(long = "compare-body", takes_value = false)]
+    pub compare_body: bool,
+}
+
+enum Outcome {
+    Matched,
+    Differed(String),
+    Skipped(&'static str),
+}
+
+impl ReplayCommand {
+    pub async fn run(self) -> Result<()> {
+        let events = read_recording(&self.recording)?;
+        let http = reqwest::Client::new();
+        let mut redis = match &self.redis_url {
+            Some(url) => Some(
+                async {
+                    redis::Client::open(url.as_str())?
+                        .get_async_connection()
+                        .await
+                }
+                .await
+                .with_context(|| format!("Failed to connect to Redis at {url}"))?,
+            ),
+            None => None,
+        };
+
+        let (mut matched, mut differed, mut skipped) = (0, 0, 0);
+        for event in &events {
+            let (description, outcome) = match &event.event {
+                TriggerEvent::Http {
+                    request, response, ..
+                } => (
+                    format!("http {} {}", request.method, request.path),
+                    self.replay_http(&http, request, response.as_ref()).await,
+                ),
+                TriggerEvent::Redis {
+                    channel, payload, ..
+                } => (
+                    format!("redis {channel}"),
+                    match &mut redis {
+                        Some(redis) => publish(redis, channel, &payload.to_bytes()?).await,
+                        None => Ok(Outcome::Skipped("no --redis-url")),
+                    },
+                ),
+            };
+            match outcome {
+                Ok(Outcome::Matched) => {
+                    matched += 1;
+                    println!("{description} ... ok");
+                }
+                Ok(Outcome::Differed(difference)) => {
+                    differed += 1;
+                    println!("{description} ... DIFFERS: {difference}");
+                }
+                Ok(Outcome::Skipped(reason)) => {
+                    skipped += 1;
+                    println!("{description} ... skipped ({reason})");
+                }
+                Err(err) => {
+                    differed += 1;
+                    println!("{description} ... FAILED: {err:#}");
+                }
+            }
+        }
+
+        println!(
+            "\nreplayed {} events: {matched} matched; {differed} differed; {skipped} skipped",
+            events.len()
+        );
+        if differed > 0 {
+            bail!("{differed} of {} events differed", events.len());
+        }
+        Ok(())
+    }
+
+    async fn replay_http(
+        &self,
+        client: &reqwest::Client,
+        request: &RecordedRequest,
+        recorded: Option<&RecordedResponse>,
+    ) -> Result<Outcome> {
+        let url = replay_url(&self.http_url, &request.path)?;
+        let method = Method::from_bytes(request.method.as_bytes())?;
+        let mut builder = client.request(method, url);
+        for (name, value) in &request.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())?;
+            // Credentials were not recorded, so there is nothing to resend.
+            let redacted = CREDENTIAL_HEADERS.contains(&name.as_str()) && value == REDACTED;
+            if !SKIPPED_HEADERS.contains(&name.as_str()) && !redacted {
+                builder = builder.header(name, value);
+            }
+        }
+        let response = builder.body(request.body.to_bytes()?).send().await?;
+
+        let Some(recorded) = recorded else {
+            return Ok(Outcome::Skipped("no recorded response"));
+        };
+        let status = response.status().as_u16();
+        if status != recorded.status {
+            return Ok(Outcome::Differed(format!(
+                "expected status {}, got {status}",
+                recorded.status
+            )));
+        }
+        if self.compare_body && response.bytes().await? != recorded.body.to_bytes()? {
+            return Ok(Outcome::Differed("response body differs".into()));
+        }
+        Ok(Outcome::Matched)
+    }
+}
+
+// Resolves a recorded request's path and query against the application's URL.
+// Only the path and query are taken from the recording, so that a recorded
+// absolute or scheme-relative path cannot send the request to another host.
+fn replay_url(base: &Url, path: &str) -> Result<Url> {
+    if !path.starts_with('/') || path.starts_with("//") {
+        bail!("Invalid request path {path:?}: expected an absolute path");
+    }
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+    let mut url = base.clone();
+    url.set_path(path);
+    url.set_query(query);
+    url.set_fragment(None);
+    Ok(url)
+}
+
+async fn publish(
+    redis: &mut redis::aio::Connection,
+    channel: &str,
+    payload: &[u8],
+) -> Result<Outcome> {
+    redis::cmd("PUBLISH")
+        .arg(channel)
+        .arg(payload)
+        .query_async::<_, i64>(redis)
+        .await
+        .with_context(|| format!("Failed to publish to channel {channel:?}"))?;
+    Ok(Outcome::Matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_url_keeps_the_application_host() {
+        let base: Url = "http://127.0.0.1:3000".parse().unwrap();
+        let url = replay_url(&base, "/hello/world?a=b").unwrap();
+        assert_eq!(url.as_str(), "http://127.0.0.1:3000/hello/world?a=b");
+
+        assert!(replay_url(&base, "//example.com/steal").is_err());
+        assert!(replay_url(&base, "https://example.com/steal").is_err());
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use spin_loader::local::config::{RawAppManifest, RawFileMount, RawModuleSource};
+use spin_manifest::ApplicationTrigger;
+
+use crate::{manifest::resolve_file_path, opts::DEFAULT_MANIFEST_FILE};
+
+// The file in the image root from which containerd-shim-spin reads runtime
+// config.
+const IMAGE_RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+// The port on which containerd-shim-spin serves HTTP applications.
+const SHIM_HTTP_PORT: u16 = 80;
+const RUNTIME_CLASS: &str = "wasmtime-spin-v2";
+const VARIABLE_ENV_PREFIX: &str = "SPIN_VARIABLE_";
+
+/// Commands for generating files which deploy the application with
+/// containers.
+#[derive(Subcommand, Debug)]
+pub enum ScaffoldCommands {
+    /// Generate a Dockerfile which packages the application as an image for
+    /// containerd-shim-spin.
+    Docker(Docker),
+
+    /// Generate Kubernetes resources which run the application's image: a
+    /// SpinApp for the Spin Operator, or a Deployment and Service for
+    /// nodes with containerd-shim-spin.
+    #[clap(alias = "k8s")]
+    Kubernetes(Kubernetes),
+}
+
+impl ScaffoldCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            ScaffoldCommands::Docker(cmd) => cmd.run().await,
+            ScaffoldCommands::Kubernetes(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Options shared by the scaffold commands.
+#[derive(Args, Debug)]
+pub struct ScaffoldOpts {
+    /// The application to scaffold. This may be a manifest (spin.toml)
+    /// file, or a directory containing a spin.toml file. If omitted, it
+    /// defaults to "spin.toml".
+    #[clap(short = 'f', long = "from")]
+    pub app_source: Option<PathBuf>,
+
+    /// A runtime config file to wire in: mounted into the container when it
+    /// runs, as the Dockerfile describes, or loaded from a Secret by the
+    /// Kubernetes resources. It is never copied into the image, as it may
+    /// hold credentials.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// The file to write, relative to the application directory, or `-` to
+    /// print to stdout.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite the output file if it exists.
+    #[clap(long = "force", takes_value = false)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct Docker {
+    #[clap(flatten)]
+    pub opts: ScaffoldOpts,
+}
+
+#[derive(Parser, Debug)]
+pub struct Kubernetes {
+    #[clap(flatten)]
+    pub opts: ScaffoldOpts,
+
+    /// The image to run, e.g. `ghcr.io/example/app:v1`.
+    #[clap(long = "image")]
+    pub image: String,
+
+    /// The number of replicas to run.
+    #[clap(long = "replicas", default_value = "1")]
+    pub replicas: u32,
+
+    /// Generate a Deployment and Service, using the containerd-shim-spin
+    /// runtime class, rather than a SpinApp for the Spin Operator.
+    #[clap(long = "deployment", takes_value = false)]
+    pub deployment: bool,
+}
+
+/// The application being scaffolded.
+struct ScaffoldApp {
+    dir: PathBuf,
+    manifest_file: PathBuf,
+    manifest: RawAppManifest,
+}
+
+impl Docker {
+    pub async fn run(self) -> Result<()> {
+        let app = self.opts.load().await?;
+        let runtime_config = match &self.opts.runtime_config_file {
+            Some(file) => Some(app.relative_path(file)?),
+            None => None,
+        };
+        let dockerfile = dockerfile(&app, runtime_config.as_deref());
+        self.opts.write(&app, "Dockerfile", &dockerfile)
+    }
+}
+
+impl Kubernetes {
+    pub async fn run(self) -> Result<()> {
+        let app = self.opts.load().await?;
+        let name = kubernetes_name(&app.manifest.info.name);
+        let resources = KubernetesResources {
+            name: &name,
+            image: &self.image,
+            replicas: self.replicas,
+            runtime_config: self.opts.runtime_config_file.as_deref(),
+        };
+        let (default_file, yaml) = if self.deployment {
+            ("deployment.yaml", resources.deployment(&app.manifest))
+        } else {
+            ("spinapp.yaml", resources.spin_app(&app.manifest))
+        };
+        self.opts.write(&app, default_file, &yaml)
+    }
+}
+
+impl ScaffoldOpts {
+    async fn load(&self) -> Result<ScaffoldApp> {
+        let source = self
+            .app_source
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_MANIFEST_FILE));
+        let manifest_file = resolve_file_path(&source)?;
+        let manifest = spin_loader::local::raw_manifest_from_file(&manifest_file)
+            .await?
+            .into_v1();
+        let dir = match manifest_file.parent() {
+            Some(dir) if dir != Path::new("") => dir.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        Ok(ScaffoldApp {
+            dir,
+            manifest_file,
+            manifest,
+        })
+    }
+
+    fn write(&self, app: &ScaffoldApp, default_file: &str, contents: &str) -> Result<()> {
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(default_file));
+        if output == Path::new("-") {
+            print!("{contents}");
+            return Ok(());
+        }
+        let path = app.dir.join(output);
+        if path.exists() && !self.force {
+            bail!(
+                "{} already exists. Use --force to overwrite it.",
+                path.display()
+            );
+        }
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        terminal::step!("Generated", "{}", path.display());
+        Ok(())
+    }
+}
+
+impl ScaffoldApp {
+    // Returns the path of a file in the application directory relative to
+    // it, as the image is built from the application directory.
+    fn relative_path(&self, file: &Path) -> Result<PathBuf> {
+        let dir = self.dir.canonicalize()?;
+        let file = file
+            .canonicalize()
+            .with_context(|| format!("Failed to find {}", file.display()))?;
+        file.strip_prefix(&dir)
+            .map(Path::to_owned)
+            .with_context(|| {
+                format!(
+                    "{} must be in the application directory {}, which the image is built from",
+                    file.display(),
+                    dir.display()
+                )
+            })
+    }
+}
+
+fn dockerfile(app: &ScaffoldApp, runtime_config: Option<&Path>) -> String {
+    let manifest = &app.manifest;
+    let manifest_file_name = app
+        .manifest_file
+        .file_name()
+        .map_or(DEFAULT_MANIFEST_FILE.into(), |name| name.to_string_lossy());
+
+    let mut dockerfile = format!(
+        "# Packages the Spin application {} for containerd-shim-spin. Build the\n\
+         # application with `spin build` before building the image.\n\
+         FROM scratch\n",
+        manifest.info.name
+    );
+    dockerfile.push_str(&copy_instruction(&manifest_file_name, "/spin.toml"));
+    for path in image_files(manifest) {
+        let path = path.display().to_string().replace('\\', "/");
+        dockerfile.push_str(&copy_instruction(&path, &format!("/{path}")));
+    }
+    if let Some(runtime_config) = runtime_config {
+        // Runtime config may hold credentials, which don't belong in the
+        // image.
+        let path = runtime_config.display().to_string().replace('\\', "/");
+        dockerfile.push_str(&format!(
+            "# Mount the runtime config at /{IMAGE_RUNTIME_CONFIG_FILE} when running the image, e.g.\n\
+             #   --volume \"$PWD/{path}:/{IMAGE_RUNTIME_CONFIG_FILE}:ro\"\n"
+        ));
+    }
+
+    let remote_sources = manifest
+        .components
+        .iter()
+        .filter(|component| !matches!(component.source, RawModuleSource::FileReference(_)))
+        .map(|component| component.id.as_str())
+        .collect::<Vec<_>>();
+    if !remote_sources.is_empty() {
+        dockerfile.push_str(&format!(
+            "# These components' modules are fetched when the application starts: {}\n",
+            remote_sources.join(", ")
+        ));
+    }
+
+    let required = required_variables(manifest);
+    if !required.is_empty() {
+        dockerfile.push_str("# Set these variables when running the image:\n");
+        for name in required {
+            dockerfile.push_str(&format!("#   {}\n", variable_env_name(name)));
+        }
+    }
+    dockerfile
+}
+
+// Returns a COPY instruction in its JSON form, so that paths with spaces or
+// quotes are copied as they are.
+fn copy_instruction(source: &str, destination: &str) -> String {
+    format!("COPY [{}, {}]\n", quote(source), quote(destination))
+}
+
+// Returns the files and directories, relative to the application
+// directory, which the application needs at runtime. Directories with
+// glob patterns are copied whole.
+fn image_files(manifest: &RawAppManifest) -> BTreeSet<PathBuf> {
+    let mut files = BTreeSet::new();
+    for component in &manifest.components {
+        if let RawModuleSource::FileReference(path) = &component.source {
+            files.insert(path.clone());
+        }
+        for mount in component.wasm.files.iter().flatten() {
+            match mount {
+                RawFileMount::Placement(placement) => {
+                    files.insert(placement.source.clone());
+                }
+                RawFileMount::Pattern(pattern) => {
+                    files.insert(pattern_base(pattern));
+                }
+            }
+        }
+    }
+    files
+}
+
+// Returns the part of a glob pattern before its first wildcard, or the
+// application directory itself if it starts with one.
+fn pattern_base(pattern: &str) -> PathBuf {
+    let base = Path::new(pattern)
+        .components()
+        .take_while(|part| {
+            !part
+                .as_os_str()
+                .to_string_lossy()
+                .contains(|c| matches!(c, '*' | '?' | '[' | '{'))
+        })
+        .collect::<PathBuf>();
+    if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    }
+}
+
+struct KubernetesResources<'a> {
+    name: &'a str,
+    image: &'a str,
+    replicas: u32,
+    runtime_config: Option<&'a Path>,
+}
+
+impl KubernetesResources<'_> {
+    fn spin_app(&self, manifest: &RawAppManifest) -> String {
+        let mut yaml = self.secrets_comment(manifest);
+        yaml.push_str(&format!(
+            "apiVersion: core.spinoperator.dev/v1alpha1\n\
+             kind: SpinApp\n\
+             metadata:\n  name: {name}\n\
+             spec:\n  image: {image}\n  executor: containerd-shim-spin\n  replicas: {replicas}\n",
+            name = self.name,
+            image = quote(self.image),
+            replicas = self.replicas,
+        ));
+        let variables = variables(manifest);
+        if !variables.is_empty() {
+            yaml.push_str("  variables:\n");
+            for variable in variables {
+                yaml.push_str(&format!("    - name: {}\n", variable.name));
+                yaml.push_str(&self.variable_value(&variable, "      "));
+            }
+        }
+        if self.runtime_config.is_some() {
+            yaml.push_str(&format!(
+                "  runtimeConfig:\n    loadFromSecret: {}\n",
+                self.runtime_config_secret()
+            ));
+        }
+        yaml
+    }
+
+    fn deployment(&self, manifest: &RawAppManifest) -> String {
+        let mut yaml = self.secrets_comment(manifest);
+        yaml.push_str(&format!(
+            "apiVersion: apps/v1\n\
+             kind: Deployment\n\
+             metadata:\n  name: {name}\n\
+             spec:\n  replicas: {replicas}\n  selector:\n    matchLabels:\n      app: {name}\n\
+             \x20 template:\n    metadata:\n      labels:\n        app: {name}\n\
+             \x20   spec:\n      runtimeClassName: {RUNTIME_CLASS}\n      containers:\n\
+             \x20       - name: {name}\n          image: {image}\n          command: [\"/\"]\n",
+            name = self.name,
+            image = quote(self.image),
+            replicas = self.replicas,
+        ));
+        let is_http = matches!(manifest.info.trigger, ApplicationTrigger::Http(_));
+        if is_http {
+            yaml.push_str(&format!(
+                "          ports:\n            - containerPort: {SHIM_HTTP_PORT}\n"
+            ));
+        }
+        let variables = variables(manifest);
+        if !variables.is_empty() {
+            yaml.push_str("          env:\n");
+            for variable in variables {
+                yaml.push_str(&format!(
+                    "            - name: {}\n",
+                    variable_env_name(variable.name)
+                ));
+                yaml.push_str(&self.variable_value(&variable, "              "));
+            }
+        }
+        if self.runtime_config.is_some() {
+            yaml.push_str(&format!(
+                "          volumeMounts:\n\
+                 \x20           - name: runtime-config\n\
+                 \x20             mountPath: /{IMAGE_RUNTIME_CONFIG_FILE}\n\
+                 \x20             subPath: {IMAGE_RUNTIME_CONFIG_FILE}\n\
+                 \x20     volumes:\n\
+                 \x20       - name: runtime-config\n\
+                 \x20         secret:\n\
+                 \x20           secretName: {}\n",
+                self.runtime_config_secret()
+            ));
+        }
+        if is_http {
+            yaml.push_str(&format!(
+                "---\n\
+                 apiVersion: v1\n\
+                 kind: Service\n\
+                 metadata:\n  name: {name}\n\
+                 spec:\n  selector:\n    app: {name}\n  ports:\n\
+                 \x20   - protocol: TCP\n      port: 80\n      targetPort: {SHIM_HTTP_PORT}\n",
+                name = self.name,
+            ));
+        }
+        yaml
+    }
+
+    fn variable_value(&self, variable: &Variable, indent: &str) -> String {
+        match variable.default {
+            Some(default) if !variable.secret => {
+                format!("{indent}value: {}\n", quote(default))
+            }
+            _ => format!(
+                "{indent}valueFrom:\n\
+                 {indent}  secretKeyRef:\n\
+                 {indent}    name: {}\n\
+                 {indent}    key: {}\n",
+                self.variables_secret(),
+                variable.name
+            ),
+        }
+    }
+
+    fn secrets_comment(&self, manifest: &RawAppManifest) -> String {
+        let mut comment = String::new();
+        let secret_variables = variables(manifest)
+            .into_iter()
+            .filter(|variable| variable.from_secret())
+            .map(|variable| format!(" --from-literal={}=...", variable.name))
+            .collect::<String>();
+        if !secret_variables.is_empty() {
+            comment.push_str(&format!(
+                "# Create the Secret holding variable values before applying this file:\n\
+                 #   kubectl create secret generic {}{secret_variables}\n",
+                self.variables_secret()
+            ));
+        }
+        if let Some(runtime_config) = self.runtime_config {
+            comment.push_str(&format!(
+                "# Create the Secret holding the runtime config before applying this file:\n\
+                 #   kubectl create secret generic {} --from-file={IMAGE_RUNTIME_CONFIG_FILE}={}\n",
+                self.runtime_config_secret(),
+                runtime_config.display()
+            ));
+        }
+        comment
+    }
+
+    fn variables_secret(&self) -> String {
+        format!("{}-variables", self.name)
+    }
+
+    fn runtime_config_secret(&self) -> String {
+        format!("{}-runtime-config", self.name)
+    }
+}
+
+/// An application variable.
+struct Variable<'a> {
+    name: &'a str,
+    default: Option<&'a str>,
+    secret: bool,
+}
+
+impl Variable<'_> {
+    // Values which are secret, or which have no default, are read from a
+    // Secret, which is not generated, so that they are not written to the
+    // file.
+    fn from_secret(&self) -> bool {
+        self.default.is_none() || self.secret
+    }
+}
+
+// Returns the application's variables, sorted by name.
+fn variables(manifest: &RawAppManifest) -> Vec<Variable> {
+    let mut variables = manifest
+        .variables
+        .iter()
+        .map(|(name, variable)| Variable {
+            name,
+            default: variable.default.as_deref(),
+            secret: variable.secret,
+        })
+        .collect::<Vec<_>>();
+    variables.sort_by_key(|variable| variable.name);
+    variables
+}
+
+fn required_variables(manifest: &RawAppManifest) -> Vec<&str> {
+    variables(manifest)
+        .into_iter()
+        .filter(|variable| variable.default.is_none())
+        .map(|variable| variable.name)
+        .collect()
+}
+
+fn variable_env_name(name: &str) -> String {
+    format!("{VARIABLE_ENV_PREFIX}{}", name.to_ascii_uppercase())
+}
+
+// Kubernetes names are lowercase letters, digits and `-`.
+fn kubernetes_name(app_name: &str) -> String {
+    let name = app_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    name.trim_matches('-').to_owned()
+}
+
+// JSON strings are valid YAML scalars, and Dockerfile JSON arguments.
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).expect("strings serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spin_loader::local::config::RawAppManifestAnyVersion;
+
+    const MANIFEST: &str = r#"
+        spin_manifest_version = "1"
+        name = "Hello App"
+        version = "1.0.0"
+        trigger = { type = "http", base = "/" }
+
+        [variables]
+        greeting = { default = "Hello" }
+        api_key = { required = true, secret = true }
+
+        [[component]]
+        id = "hello"
+        source = "target/hello.wasm"
+        files = ["static/**/*", { source = "assets", destination = "/" }]
+        [component.trigger]
+        route = "/..."
+    "#;
+
+    fn app() -> ScaffoldApp {
+        let manifest = toml::from_str::<RawAppManifestAnyVersion>(MANIFEST)
+            .unwrap()
+            .into_v1();
+        ScaffoldApp {
+            dir: PathBuf::from("."),
+            manifest_file: PathBuf::from("spin.toml"),
+            manifest,
+        }
+    }
+
+    #[test]
+    fn dockerfile_copies_runtime_files() {
+        let dockerfile = dockerfile(&app(), Some(Path::new("config/runtime.toml")));
+        assert!(dockerfile.contains("COPY [\"spin.toml\", \"/spin.toml\"]\n"));
+        assert!(dockerfile.contains("COPY [\"target/hello.wasm\", \"/target/hello.wasm\"]\n"));
+        assert!(dockerfile.contains("COPY [\"static\", \"/static\"]\n"));
+        assert!(dockerfile.contains("COPY [\"assets\", \"/assets\"]\n"));
+        assert!(!dockerfile.contains("runtime.toml\","));
+        assert!(dockerfile.contains("\"$PWD/config/runtime.toml:/runtime-config.toml:ro\""));
+        assert!(dockerfile.contains("#   SPIN_VARIABLE_API_KEY\n"));
+    }
+
+    #[test]
+    fn copy_instructions_quote_paths() {
+        assert_eq!(
+            copy_instruction("my assets/\"logo\".png", "/my assets/\"logo\".png"),
+            "COPY [\"my assets/\\\"logo\\\".png\", \"/my assets/\\\"logo\\\".png\"]\n"
+        );
+    }
+
+    #[test]
+    fn spin_app_reads_secret_variables_from_secret() {
+        let app = app();
+        let resources = KubernetesResources {
+            name: &kubernetes_name(&app.manifest.info.name),
+            image: "ghcr.io/example/hello:v1",
+            replicas: 2,
+            runtime_config: None,
+        };
+        let yaml = resources.spin_app(&app.manifest);
+        assert!(yaml.contains("  name: hello-app\n"));
+        assert!(yaml.contains("  image: \"ghcr.io/example/hello:v1\"\n"));
+        assert!(yaml.contains("    - name: greeting\n      value: \"Hello\"\n"));
+        assert!(yaml.contains("          name: hello-app-variables\n          key: api_key\n"));
+        assert!(!yaml.contains("runtimeConfig"));
+    }
+
+    #[test]
+    fn pattern_bases_stop_at_wildcards() {
+        assert_eq!(pattern_base("static/**/*"), Path::new("static"));
+        assert_eq!(pattern_base("*.txt"), Path::new("."));
+        assert_eq!(pattern_base("docs/readme.md"), Path::new("docs/readme.md"));
+    }
+}
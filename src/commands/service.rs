@@ -0,0 +1,923 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use spin_common::{arg_parser::parse_kv, data_dir::default_data_dir};
+
+use crate::{
+    commands::up::{ENV_FILE_VARIABLE_PREFIX, VARIABLE_ENV_PREFIX},
+    manifest::resolve_file_path,
+    opts::DEFAULT_MANIFEST_FILE,
+};
+
+// The launchd label and scheduled task folder of installed services.
+const LAUNCHD_LABEL_PREFIX: &str = "com.fermyon.spin.";
+const WINDOWS_TASK_FOLDER: &str = "Spin";
+
+// The SYSTEM account and the Administrators group, as `icacls` names them.
+const WINDOWS_SYSTEM: &str = "*S-1-5-18";
+const WINDOWS_ADMINISTRATORS: &str = "*S-1-5-32-544";
+
+/// Commands for running applications as services of the operating system.
+#[derive(Subcommand, Debug)]
+pub enum ServiceCommands {
+    /// Install a service which runs an application with `spin up`, starting
+    /// it when the system starts (or the user logs in) and restarting it if
+    /// it fails, and start it.
+    Install(Install),
+
+    /// Stop and remove a service installed with `spin service install`.
+    Uninstall(Uninstall),
+}
+
+impl ServiceCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            ServiceCommands::Install(cmd) => cmd.run().await,
+            ServiceCommands::Uninstall(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// The kinds of service definition which can be generated.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceKind {
+    /// A systemd unit (Linux).
+    Systemd,
+    /// A launchd property list (macOS).
+    Launchd,
+    /// A Task Scheduler task (Windows). Spin is not a Windows service
+    /// program, so it runs as a task which starts with the system.
+    WindowsTask,
+}
+
+/// When a service is restarted after its application exits.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Whenever it exits. Windows tasks are restarted only on failure.
+    Always,
+    /// Only if it fails.
+    OnFailure,
+    #[clap(alias = "no")]
+    Never,
+}
+
+#[derive(Parser, Debug)]
+pub struct Install {
+    /// The application to run. This may be a manifest (spin.toml) file, a
+    /// directory containing a spin.toml file, or a remote registry
+    /// reference. If omitted, it defaults to "spin.toml".
+    #[clap(short = 'f', long = "from")]
+    pub app_source: Option<String>,
+
+    /// The name of the service. Defaults to `spin-` followed by the
+    /// application name, which is required for registry applications.
+    #[clap(long = "name")]
+    pub name: Option<String>,
+
+    /// The kind of service to install. Defaults to the kind for this
+    /// operating system.
+    #[clap(value_enum, long = "kind")]
+    pub kind: Option<ServiceKind>,
+
+    /// When to restart the application after it exits.
+    #[clap(value_enum, long = "restart", default_value = "on-failure")]
+    pub restart: RestartPolicy,
+
+    /// Set an environment variable (key=value) for the service. As in an
+    /// `--env-file`, `SPIN_VARIABLE_<NAME>` sets the application variable
+    /// `<name>`. May be given more than once.
+    #[clap(short = 'e', long = "env", parse(try_from_str = parse_kv))]
+    pub env: Vec<(String, String)>,
+
+    /// A directory for logs: the output of `spin up` is appended to
+    /// `spin.log` in it, and it is the log directory of the application's
+    /// components. Without it, systemd services log to the journal.
+    #[clap(long = "log-dir")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Install the service for the whole system, rather than for the
+    /// current user. This usually needs administrator privileges.
+    #[clap(long = "system", takes_value = false)]
+    pub system: bool,
+
+    /// Print the service definition, and where it would be installed,
+    /// without installing it.
+    #[clap(long = "print", takes_value = false)]
+    pub print: bool,
+
+    /// Other options for `spin up`, such as `--listen`, after `--`.
+    #[clap(last = true)]
+    pub up_args: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Uninstall {
+    /// The name of the service.
+    pub name: String,
+
+    /// The kind of service to uninstall. Defaults to the kind for this
+    /// operating system.
+    #[clap(value_enum, long = "kind")]
+    pub kind: Option<ServiceKind>,
+
+    /// Uninstall a service installed for the whole system.
+    #[clap(long = "system", takes_value = false)]
+    pub system: bool,
+}
+
+/// What a service runs, and how.
+#[derive(Debug)]
+struct ServiceSpec {
+    name: String,
+    app: String,
+    program: PathBuf,
+    args: Vec<String>,
+    working_dir: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    // Environment variables are kept out of service definitions, which are
+    // usually readable by every user, in a file only the owner can read.
+    env_file: Option<PathBuf>,
+    restart: RestartPolicy,
+    log_file: Option<PathBuf>,
+    system: bool,
+}
+
+impl Install {
+    pub async fn run(self) -> Result<()> {
+        let kind = self.kind.unwrap_or_else(ServiceKind::for_this_os);
+        let spec = self.spec(kind).await?;
+        let definition = kind.render(&spec);
+        let path = kind.definition_path(&spec.name, spec.system)?;
+        let env_file = spec
+            .env_file
+            .as_ref()
+            .map(|env_path| (env_path, kind.render_env_file(&spec)));
+
+        if self.print {
+            println!("# {}", path.display());
+            print!("{definition}");
+            if let Some((env_path, contents)) = &env_file {
+                println!("# {}", env_path.display());
+                print!("{contents}");
+            }
+            return Ok(());
+        }
+
+        if let Some(log_dir) = &self.log_dir {
+            std::fs::create_dir_all(log_dir)
+                .with_context(|| format!("Failed to create {}", log_dir.display()))?;
+        }
+        if let Some(dir) = path.parent() {
+            kind.create_dir(dir, spec.system)?;
+        }
+        if let Some((env_path, contents)) = &env_file {
+            if let Some(dir) = env_path.parent() {
+                kind.create_dir(dir, spec.system)?;
+            }
+            kind.write_env_file(env_path, contents)?;
+        }
+        kind.write_definition(&path, &definition)?;
+        kind.enable(&spec.name, &path, spec.system)?;
+
+        terminal::step!("Installed", "service {} from {}", spec.name, path.display());
+        println!("Run `spin service uninstall {}` to remove it.", spec.name);
+        Ok(())
+    }
+
+    async fn spec(&self, kind: ServiceKind) -> Result<ServiceSpec> {
+        let source = self.app_source.as_deref().unwrap_or(DEFAULT_MANIFEST_FILE);
+        let (app, working_dir, app_name) = if Path::new(source).exists() {
+            // Services don't start in the current directory, so local
+            // applications are run by absolute path.
+            let manifest = resolve_file_path(source)?
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve {source}"))?;
+            let app_name = spin_loader::local::raw_manifest_from_file(&manifest)
+                .await?
+                .into_v1()
+                .info
+                .name;
+            let working_dir = manifest.parent().map(Path::to_owned);
+            (manifest.display().to_string(), working_dir, Some(app_name))
+        } else {
+            (source.to_owned(), None, None)
+        };
+
+        let name = match (&self.name, app_name) {
+            (Some(name), _) => name.clone(),
+            (None, Some(app_name)) => default_service_name(&app_name),
+            (None, None) => bail!("--name is required for registry applications"),
+        };
+        validate_service_name(&name)?;
+        for (key, _) in &self.env {
+            validate_env_name(key)?;
+        }
+        let env_file = if self.env.is_empty() {
+            None
+        } else {
+            Some(kind.env_file_path(&name, self.system)?)
+        };
+
+        let mut args = vec!["up".to_owned(), "--from".to_owned(), app.clone()];
+        let log_dir = match &self.log_dir {
+            Some(dir) => Some(absolute(dir)?),
+            None => None,
+        };
+        if let Some(log_dir) = &log_dir {
+            args.extend(["--log-dir".to_owned(), log_dir.display().to_string()]);
+        }
+        args.extend(self.up_args.iter().cloned());
+
+        Ok(ServiceSpec {
+            name,
+            app,
+            program: std::env::current_exe().context("Failed to find the Spin executable")?,
+            args,
+            working_dir,
+            env: self.env.iter().map(service_env_var).collect(),
+            env_file,
+            restart: self.restart,
+            log_file: log_dir.map(|dir| dir.join("spin.log")),
+            system: self.system,
+        })
+    }
+}
+
+impl Uninstall {
+    pub async fn run(self) -> Result<()> {
+        validate_service_name(&self.name)?;
+        let kind = self.kind.unwrap_or_else(ServiceKind::for_this_os);
+        let path = kind.definition_path(&self.name, self.system)?;
+        if !path.exists() {
+            bail!(
+                "No service named {} is installed at {}",
+                self.name,
+                path.display()
+            );
+        }
+        kind.disable(&self.name, &path, self.system)?;
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+        let env_file = kind.env_file_path(&self.name, self.system)?;
+        if env_file.exists() {
+            std::fs::remove_file(&env_file)
+                .with_context(|| format!("Failed to remove {}", env_file.display()))?;
+        }
+        terminal::step!("Uninstalled", "service {}", self.name);
+        Ok(())
+    }
+}
+
+impl ServiceKind {
+    fn for_this_os() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::Launchd
+        } else if cfg!(windows) {
+            Self::WindowsTask
+        } else {
+            Self::Systemd
+        }
+    }
+
+    fn render(self, spec: &ServiceSpec) -> String {
+        match self {
+            Self::Systemd => systemd_unit(spec),
+            Self::Launchd => launchd_plist(spec),
+            Self::WindowsTask => windows_task(spec),
+        }
+    }
+
+    fn render_env_file(self, spec: &ServiceSpec) -> String {
+        match self {
+            Self::Systemd | Self::Launchd => posix_env_file(spec),
+            Self::WindowsTask => windows_launcher(spec),
+        }
+    }
+
+    // On Windows the environment is set by a PowerShell script which then
+    // runs Spin, as tasks have no environment of their own.
+    fn env_file_path(self, name: &str, system: bool) -> Result<PathBuf> {
+        let dir = match (self, system) {
+            (Self::Systemd | Self::Launchd, true) => PathBuf::from("/etc/spin/services"),
+            (Self::WindowsTask, true) => windows_system_services_dir(),
+            _ => default_data_dir()?.join("services"),
+        };
+        Ok(match self {
+            Self::WindowsTask => dir.join(format!("{name}.ps1")),
+            _ => dir.join(format!("{name}.env")),
+        })
+    }
+
+    fn definition_path(self, name: &str, system: bool) -> Result<PathBuf> {
+        Ok(match (self, system) {
+            (Self::Systemd, true) => {
+                PathBuf::from("/etc/systemd/system").join(format!("{name}.service"))
+            }
+            (Self::Systemd, false) => dirs::config_dir()
+                .context("Cannot find the user's config directory")?
+                .join("systemd")
+                .join("user")
+                .join(format!("{name}.service")),
+            (Self::Launchd, true) => PathBuf::from("/Library/LaunchDaemons")
+                .join(format!("{LAUNCHD_LABEL_PREFIX}{name}.plist")),
+            (Self::Launchd, false) => dirs::home_dir()
+                .context("Cannot find the user's home directory")?
+                .join("Library")
+                .join("LaunchAgents")
+                .join(format!("{LAUNCHD_LABEL_PREFIX}{name}.plist")),
+            // Task Scheduler keeps its own copy; this one is kept to register
+            // the task from, and to know that it was installed by Spin.
+            (Self::WindowsTask, true) => windows_system_services_dir().join(format!("{name}.xml")),
+            (Self::WindowsTask, false) => default_data_dir()?
+                .join("services")
+                .join(format!("{name}.xml")),
+        })
+    }
+
+    // Creates a directory for the service's files. A system Windows task runs
+    // its launcher as SYSTEM, so its directory is made writable only by
+    // administrators: they own it and everything in it, and nothing is
+    // inherited from ProgramData, in which users may create files.
+    fn create_dir(self, dir: &Path, system: bool) -> Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        if let (Self::WindowsTask, true) = (self, system) {
+            let dir = dir.to_string_lossy();
+            run(
+                "icacls",
+                &[dir.as_ref(), "/setowner", WINDOWS_ADMINISTRATORS, "/T"],
+            )?;
+            run("icacls", &[dir.as_ref(), "/reset", "/T"])?;
+            run(
+                "icacls",
+                &[
+                    dir.as_ref(),
+                    "/inheritance:r",
+                    "/grant:r",
+                    &format!("{WINDOWS_SYSTEM}:(OI)(CI)F"),
+                    &format!("{WINDOWS_ADMINISTRATORS}:(OI)(CI)F"),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_definition(self, path: &Path, definition: &str) -> Result<()> {
+        let data = match self {
+            // Task Scheduler reads task definitions as UTF-16.
+            Self::WindowsTask => std::iter::once(0xFEFF)
+                .chain(definition.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            _ => definition.as_bytes().to_vec(),
+        };
+        std::fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn write_env_file(self, path: &Path, contents: &str) -> Result<()> {
+        let data = match self {
+            // Windows PowerShell reads scripts without a byte order mark in
+            // the system code page.
+            Self::WindowsTask => [&b"\xEF\xBB\xBF"[..], contents.as_bytes()].concat(),
+            _ => contents.as_bytes().to_vec(),
+        };
+        write_private(path, &data).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn enable(self, name: &str, path: &Path, system: bool) -> Result<()> {
+        match self {
+            Self::Systemd => {
+                let scope = systemctl_scope(system);
+                let unit = format!("{name}.service");
+                run("systemctl", &[scope, "daemon-reload"])?;
+                run("systemctl", &[scope, "enable", "--now", unit.as_str()])
+            }
+            Self::Launchd => run(
+                "launchctl",
+                &["load", "-w", path.to_string_lossy().as_ref()],
+            ),
+            Self::WindowsTask => {
+                let task = windows_task_name(name);
+                let path = path.to_string_lossy();
+                let mut args = vec!["/Create", "/TN", task.as_str(), "/XML", path.as_ref(), "/F"];
+                if system {
+                    args.extend(["/RU", "SYSTEM"]);
+                }
+                run("schtasks", &args)?;
+                run("schtasks", &["/Run", "/TN", task.as_str()])
+            }
+        }
+    }
+
+    fn disable(self, name: &str, path: &Path, system: bool) -> Result<()> {
+        match self {
+            Self::Systemd => {
+                let scope = systemctl_scope(system);
+                let unit = format!("{name}.service");
+                run("systemctl", &[scope, "disable", "--now", unit.as_str()])?;
+                run("systemctl", &[scope, "daemon-reload"])
+            }
+            Self::Launchd => run(
+                "launchctl",
+                &["unload", "-w", path.to_string_lossy().as_ref()],
+            ),
+            Self::WindowsTask => {
+                let task = windows_task_name(name);
+                // Ending a task which isn't running fails, which is fine.
+                let _ = run("schtasks", &["/End", "/TN", task.as_str()]);
+                run("schtasks", &["/Delete", "/TN", task.as_str(), "/F"])
+            }
+        }
+    }
+}
+
+fn systemd_unit(spec: &ServiceSpec) -> String {
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=Spin application {}\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n",
+        spec.app
+    );
+    let command = std::iter::once(spec.program.display().to_string())
+        .chain(spec.args.iter().cloned())
+        .map(|arg| systemd_quote(&arg))
+        .collect::<Vec<_>>();
+    unit.push_str(&format!("ExecStart={}\n", command.join(" ")));
+    if let Some(dir) = &spec.working_dir {
+        unit.push_str(&format!(
+            "WorkingDirectory={}\n",
+            systemd_quote(&dir.display().to_string())
+        ));
+    }
+    if let Some(env_file) = &spec.env_file {
+        unit.push_str(&format!(
+            "EnvironmentFile={}\n",
+            env_file.display().to_string().replace('%', "%%")
+        ));
+    }
+    let restart = match spec.restart {
+        RestartPolicy::Always => "always",
+        RestartPolicy::OnFailure => "on-failure",
+        RestartPolicy::Never => "no",
+    };
+    unit.push_str(&format!("Restart={restart}\nRestartSec=5\n"));
+    if let Some(log_file) = &spec.log_file {
+        let log_file = log_file.display();
+        unit.push_str(&format!(
+            "StandardOutput=append:{log_file}\nStandardError=append:{log_file}\n"
+        ));
+    }
+    let target = if spec.system {
+        "multi-user.target"
+    } else {
+        "default.target"
+    };
+    unit.push_str(&format!("\n[Install]\nWantedBy={target}\n"));
+    unit
+}
+
+fn launchd_plist(spec: &ServiceSpec) -> String {
+    let mut plist = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n",
+    );
+    let mut entry = |key: &str, value: String| {
+        plist.push_str(&format!("  <key>{key}</key>\n  {value}\n"));
+    };
+    let string = |value: &str| format!("<string>{}</string>", xml_escape(value));
+
+    entry(
+        "Label",
+        string(&format!("{LAUNCHD_LABEL_PREFIX}{}", spec.name)),
+    );
+    // launchd can't read an environment file, so the shell does so before
+    // running Spin.
+    let env_prefix = spec.env_file.iter().flat_map(|env_file| {
+        [
+            "/bin/sh".to_owned(),
+            "-c".to_owned(),
+            "set -a && . \"$0\" && set +a && exec \"$@\"".to_owned(),
+            env_file.display().to_string(),
+        ]
+    });
+    let arguments = env_prefix
+        .chain(std::iter::once(spec.program.display().to_string()))
+        .chain(spec.args.iter().cloned())
+        .map(|arg| format!("    {}\n", string(&arg)))
+        .collect::<String>();
+    entry(
+        "ProgramArguments",
+        format!("<array>\n{arguments}  </array>"),
+    );
+    if let Some(dir) = &spec.working_dir {
+        entry("WorkingDirectory", string(&dir.display().to_string()));
+    }
+    entry("RunAtLoad", "<true/>".into());
+    let keep_alive = match spec.restart {
+        RestartPolicy::Always => "<true/>".into(),
+        RestartPolicy::OnFailure => {
+            "<dict>\n    <key>SuccessfulExit</key>\n    <false/>\n  </dict>".into()
+        }
+        RestartPolicy::Never => "<false/>".into(),
+    };
+    entry("KeepAlive", keep_alive);
+    if let Some(log_file) = &spec.log_file {
+        let log_file = log_file.display().to_string();
+        entry("StandardOutPath", string(&log_file));
+        entry("StandardErrorPath", string(&log_file));
+    }
+
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+// The environment file of systemd units and launchd agents, which both
+// systemd and the shell read.
+fn posix_env_file(spec: &ServiceSpec) -> String {
+    spec.env
+        .iter()
+        .map(|(key, value)| format!("{key}={}\n", posix_quote(value)))
+        .collect()
+}
+
+// The command which runs Spin, without its environment.
+fn windows_command(spec: &ServiceSpec) -> (String, String) {
+    let args = spec
+        .args
+        .iter()
+        .map(|arg| windows_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // Tasks have no output redirection of their own, so that is done by
+    // running Spin from cmd.
+    match &spec.log_file {
+        None => (spec.program.display().to_string(), args),
+        Some(log_file) => {
+            let script = format!(
+                "{} {args} >> \"{}\" 2>&1",
+                windows_quote(&spec.program.display().to_string()),
+                log_file.display()
+            );
+            ("cmd.exe".to_owned(), format!("/d /s /c \"{script}\""))
+        }
+    }
+}
+
+// A PowerShell script which sets the environment and runs Spin. Values are
+// only ever PowerShell string literals, never part of a command line.
+fn windows_launcher(spec: &ServiceSpec) -> String {
+    let (command, arguments) = windows_command(spec);
+    let mut script = spec
+        .env
+        .iter()
+        .map(|(key, value)| format!("$env:{key} = {}\r\n", powershell_quote(value)))
+        .collect::<String>();
+    script.push_str(&format!(
+        "$spin = Start-Process -FilePath {} -ArgumentList {} -NoNewWindow -Wait -PassThru\r\n\
+         exit $spin.ExitCode\r\n",
+        powershell_quote(&command),
+        powershell_quote(&arguments)
+    ));
+    script
+}
+
+fn windows_task(spec: &ServiceSpec) -> String {
+    let (command, arguments) = match &spec.env_file {
+        None => windows_command(spec),
+        Some(launcher) => (
+            "powershell.exe".to_owned(),
+            format!(
+                "-NoProfile -NonInteractive -ExecutionPolicy Bypass -File {}",
+                windows_quote(&launcher.display().to_string())
+            ),
+        ),
+    };
+
+    let (trigger, principal) = if spec.system {
+        (
+            "<BootTrigger>\n      <Enabled>true</Enabled>\n    </BootTrigger>",
+            "<UserId>S-1-5-18</UserId>\n      <RunLevel>HighestAvailable</RunLevel>",
+        )
+    } else {
+        (
+            "<LogonTrigger>\n      <Enabled>true</Enabled>\n    </LogonTrigger>",
+            "<LogonType>InteractiveToken</LogonType>\n      <RunLevel>LeastPrivilege</RunLevel>",
+        )
+    };
+    let restart = match spec.restart {
+        RestartPolicy::Always | RestartPolicy::OnFailure => {
+            "\n    <RestartOnFailure>\n      <Interval>PT1M</Interval>\n      <Count>999</Count>\n    </RestartOnFailure>"
+        }
+        RestartPolicy::Never => "",
+    };
+    let working_dir = spec
+        .working_dir
+        .as_ref()
+        .map(|dir| {
+            format!(
+                "\n      <WorkingDirectory>{}</WorkingDirectory>",
+                xml_escape(&dir.display().to_string())
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Spin application {app}</Description>
+  </RegistrationInfo>
+  <Triggers>
+    {trigger}
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      {principal}
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>{restart}
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+      <Arguments>{arguments}</Arguments>{working_dir}
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        app = xml_escape(&spec.app),
+        command = xml_escape(&command),
+        arguments = xml_escape(&arguments),
+    )
+}
+
+// `spin up` reads application variables from its environment as
+// `SPIN_CONFIG_<NAME>`, so the `SPIN_VARIABLE_<NAME>` form which env files
+// use is translated to that.
+fn service_env_var((key, value): &(String, String)) -> (String, String) {
+    let key = match key.strip_prefix(ENV_FILE_VARIABLE_PREFIX) {
+        Some(name) => format!("{VARIABLE_ENV_PREFIX}{}", name.to_ascii_uppercase()),
+        None => key.clone(),
+    };
+    (key, value.clone())
+}
+
+// Service names become file names and unit names, so are kept simple.
+fn validate_service_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        bail!("Invalid service name '{name}': names may contain only letters, digits, '-', '_' and '.'");
+    }
+    Ok(())
+}
+
+// Variable names are written unquoted to environment files and scripts.
+fn validate_env_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("Invalid environment variable name '{name}': names may contain only letters, digits and '_', and may not start with a digit");
+    }
+    Ok(())
+}
+
+fn default_service_name(app_name: &str) -> String {
+    let name = app_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("spin-{name}")
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    Ok(if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir()?.join(path)
+    })
+}
+
+fn systemctl_scope(system: bool) -> &'static str {
+    if system {
+        "--system"
+    } else {
+        "--user"
+    }
+}
+
+fn windows_task_name(name: &str) -> String {
+    format!("{WINDOWS_TASK_FOLDER}\\{name}")
+}
+
+// The files of system Windows tasks are kept under ProgramData rather than
+// in the installing user's data directory, which that user could change.
+fn windows_system_services_dir() -> PathBuf {
+    std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("C:\\ProgramData"))
+        .join("Spin")
+        .join("services")
+}
+
+// Creates (or replaces) a file which only its owner can read.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // The mode only applies to new files.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path)?.write_all(data)
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !status.success() {
+        bail!("`{program} {}` failed: {status}", args.join(" "));
+    }
+    Ok(())
+}
+
+// Quotes a word of a systemd command line or setting, in which `%`
+// introduces a specifier.
+fn systemd_quote(word: &str) -> String {
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%");
+    if escaped.is_empty() || escaped.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+    {
+        format!("\"{escaped}\"")
+    } else {
+        escaped
+    }
+}
+
+// Quotes a value for both the shell and systemd environment files, in which
+// nothing inside single quotes is special.
+fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Quotes a PowerShell string literal, in which only (any kind of) single
+// quote is special, and is escaped by doubling it.
+fn powershell_quote(value: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn windows_quote(word: &str) -> String {
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '"') {
+        format!("\"{}\"", word.replace('"', "\\\""))
+    } else {
+        word.to_owned()
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            name: "spin-hello".into(),
+            app: "/srv/hello/spin.toml".into(),
+            program: "/usr/local/bin/spin".into(),
+            args: vec![
+                "up".into(),
+                "--from".into(),
+                "/srv/hello/spin.toml".into(),
+                "--listen".into(),
+                "0.0.0.0:80".into(),
+            ],
+            working_dir: Some("/srv/hello".into()),
+            env: vec![("SPIN_VARIABLE_GREETING".into(), "Hello, world".into())],
+            env_file: Some("/etc/spin/services/spin-hello.env".into()),
+            restart: RestartPolicy::OnFailure,
+            log_file: Some("/var/log/hello/spin.log".into()),
+            system: true,
+        }
+    }
+
+    #[test]
+    fn systemd_unit_runs_spin_up() {
+        let unit = ServiceKind::Systemd.render(&spec());
+        assert!(unit.contains(
+            "ExecStart=/usr/local/bin/spin up --from /srv/hello/spin.toml --listen 0.0.0.0:80\n"
+        ));
+        assert!(unit.contains("EnvironmentFile=/etc/spin/services/spin-hello.env\n"));
+        assert!(!unit.contains("Hello, world"));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("StandardOutput=append:/var/log/hello/spin.log\n"));
+        assert!(unit.contains("WantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn launchd_plist_restarts_on_failure() {
+        let plist = ServiceKind::Launchd.render(&spec());
+        assert!(plist.contains("<string>com.fermyon.spin.spin-hello</string>"));
+        assert!(plist.contains("<key>SuccessfulExit</key>\n    <false/>"));
+        assert!(plist.contains(
+            "<string>/etc/spin/services/spin-hello.env</string>\n    <string>/usr/local/bin/spin</string>"
+        ));
+        assert!(!plist.contains("Hello, world"));
+    }
+
+    #[test]
+    fn env_files_quote_values() {
+        let mut spec = spec();
+        spec.env = vec![("SECRET".into(), "it's $HOME; `id`".into())];
+        assert_eq!(
+            ServiceKind::Systemd.render_env_file(&spec),
+            "SECRET='it'\\''s $HOME; `id`'\n"
+        );
+        validate_env_name("SPIN_VARIABLE_X").unwrap();
+        validate_env_name("BAD;NAME").unwrap_err();
+        validate_env_name("1BAD").unwrap_err();
+    }
+
+    #[test]
+    fn variables_are_set_as_spin_up_reads_them() {
+        let var = |key: &str| service_env_var(&(key.to_owned(), "value".to_owned())).0;
+        assert_eq!(var("SPIN_VARIABLE_api_key"), "SPIN_CONFIG_API_KEY");
+        assert_eq!(var("SPIN_CONFIG_API_KEY"), "SPIN_CONFIG_API_KEY");
+        assert_eq!(var("RUST_LOG"), "RUST_LOG");
+    }
+
+    #[test]
+    fn windows_task_sets_environment_from_script() {
+        let mut spec = spec();
+        spec.env = vec![("SECRET".into(), "a\" & calc & \"%PATH%^'".into())];
+        spec.env_file = Some("C:\\spin\\services\\spin-hello.ps1".into());
+        let task = ServiceKind::WindowsTask.render(&spec);
+        assert!(task.contains("<Command>powershell.exe</Command>"));
+        assert!(!task.contains("calc"));
+        let launcher = ServiceKind::WindowsTask.render_env_file(&spec);
+        assert!(launcher.starts_with("$env:SECRET = 'a\" & calc & \"%PATH%^'''\r\n"));
+    }
+
+    #[test]
+    fn systemd_words_are_quoted() {
+        assert_eq!(systemd_quote("plain"), "plain");
+        assert_eq!(systemd_quote("two words"), "\"two words\"");
+        assert_eq!(systemd_quote("100%"), "100%%");
+    }
+
+    #[test]
+    fn default_names_are_valid() {
+        let name = default_service_name("Hello World!");
+        assert_eq!(name, "spin-hello-world-");
+        validate_service_name(&name).unwrap();
+        validate_service_name("../etc").unwrap_err();
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    io::Write,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use hyper::{http::uri::Scheme, Body, Method, Request};
+use serde_json::Value;
+use spin_app::locked::LockedApp;
+use spin_redis_engine::RedisTrigger;
+use spin_trigger::{locked::write_locked_app, EitherInstance, TriggerAppEngine, TriggerExecutor};
+use spin_trigger_http::HttpTrigger;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use wasmtime::component::{Type, Val};
+
+use crate::{
+    commands::up::{in_process::InProcessTrigger, UpCommand, APPLICATION_OPT},
+    opts::*,
+};
+
+const HELP: &str = "\
+Commands:
+  components                       List the components and what triggers them
+  http <METHOD> <PATH> [BODY]      Send an HTTP request, e.g. `http POST /items '{\"id\": 1}'`
+  header [NAME [VALUE]]            Set a header for later requests, remove it if no value
+                                   is given, or list the headers if no name is given
+  redis <CHANNEL> [PAYLOAD]        Handle a message as if published to a Redis channel
+  call <COMPONENT> <EXPORT> [ARGS] Call an exported function, e.g. `call web handle-job 42`;
+                                   a function in an exported interface is `<interface>.<function>`
+  reload                           Load the application again, e.g. after changing spin.toml
+  help                             Show this help
+  exit                             Leave the shell
+
+Arguments containing spaces can be quoted. Rebuilt components are reloaded
+before each invocation.";
+
+/// Load an application and invoke its components interactively.
+///
+/// The application runs in process, without listening for HTTP requests or
+/// subscribing to Redis, and each command invokes a component directly and
+/// prints what it returns. Commands are read from stdin, so they can also
+/// be piped in.
+#[derive(Parser, Debug)]
+#[clap(about = "Invoke an application's components interactively")]
+pub struct ShellCommand {
+    /// The application to load. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(name = APPLICATION_OPT, short = 'f', long = "from")]
+    pub app_source: Option<String>,
+
+    /// The environment, such as `prod`, whose overrides in the manifest's
+    /// `[profile.<environment>]` table apply.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// The build profile, such as `release`, whose modules to load.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Runtime configuration file for the application. Key value stores and
+    /// databases which it does not configure are in memory, unless
+    /// `--state-dir` is given.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// The directory in which to keep the default key value store and
+    /// database, e.g. `.spin` to share those of `spin up`.
+    #[clap(long = "state-dir")]
+    pub state_dir: Option<String>,
+
+    /// Disable the cache of compiled components.
+    #[clap(long = "disable-cache", takes_value = false)]
+    pub disable_cache: bool,
+}
+
+// The application's trigger, through which components are invoked.
+enum ShellTrigger {
+    Http(HttpTrigger),
+    Redis(RedisTrigger),
+}
+
+struct Shell {
+    trigger: ShellTrigger,
+    // Headers sent with every HTTP request.
+    headers: Vec<(String, String)>,
+}
+
+impl ShellCommand {
+    pub async fn run(self) -> Result<()> {
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-shell-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let mut shell = Shell {
+            trigger: self.load(working_dir.path()).await?,
+            headers: vec![],
+        };
+        println!("Type `help` for commands, or `exit` to leave.");
+
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        loop {
+            print!("spin> ");
+            std::io::stdout().flush()?;
+            let Some(line) = lines.next_line().await? else {
+                println!();
+                return Ok(());
+            };
+            let args = match shell_words::split(&line) {
+                Ok(args) => args,
+                Err(err) => {
+                    terminal::error!("{err}");
+                    continue;
+                }
+            };
+            let Some((command, args)) = args.split_first() else {
+                continue;
+            };
+            let result = match command.as_str() {
+                "exit" | "quit" => return Ok(()),
+                "help" => {
+                    println!("{HELP}");
+                    Ok(())
+                }
+                "reload" => match self.load(working_dir.path()).await {
+                    Ok(trigger) => {
+                        shell.trigger = trigger;
+                        Ok(())
+                    }
+                    Err(err) => Err(err),
+                },
+                _ => shell.run_command(command, args).await,
+            };
+            if let Err(err) = result {
+                terminal::error!("{err:#}");
+            }
+        }
+    }
+
+    // Loads the application, and builds its trigger.
+    async fn load(&self, working_dir: &Path) -> Result<ShellTrigger> {
+        let loader = UpCommand {
+            app_source: self.app_source.iter().cloned().collect(),
+            environment: self.environment.clone(),
+            profile: self.profile.clone(),
+            ..Default::default()
+        };
+        let app = loader.load_app(working_dir).await?;
+        let trigger_type = trigger_type(&app)?;
+        let locked_url = write_locked_app(&app, working_dir).await?;
+
+        let in_process = InProcessTrigger {
+            runtime_config_file: self.runtime_config_file.as_deref(),
+            state_dir: self.state_dir.as_deref(),
+            disable_cache: self.disable_cache,
+            reload_changed_components: true,
+        };
+        let trigger = match trigger_type.as_str() {
+            "http" => ShellTrigger::Http(in_process.build(locked_url, working_dir).await?),
+            "redis" => ShellTrigger::Redis(in_process.build(locked_url, working_dir).await?),
+            other => bail!("The shell does not support {other:?} applications"),
+        };
+        println!(
+            "Loaded {} application {:?} with {} components",
+            trigger_type,
+            app.metadata
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+            app.components.len()
+        );
+        Ok(trigger)
+    }
+}
+
+impl Shell {
+    async fn run_command(&mut self, command: &str, args: &[String]) -> Result<()> {
+        match (command, args) {
+            ("components", []) => {
+                self.print_components();
+                Ok(())
+            }
+            ("http", [method, path, body @ ..]) if body.len() <= 1 => {
+                self.http(method, path, body.first()).await
+            }
+            ("header", []) => {
+                for (name, value) in &self.headers {
+                    println!("{name}: {value}");
+                }
+                Ok(())
+            }
+            ("header", [name]) => {
+                self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                Ok(())
+            }
+            ("header", [name, value]) => {
+                self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                self.headers.push((name.clone(), value.clone()));
+                Ok(())
+            }
+            ("redis", [channel, payload @ ..]) if payload.len() <= 1 => {
+                let payload = payload.first().map_or(&[][..], |p| p.as_bytes());
+                self.redis(channel, payload).await
+            }
+            ("call", [component_id, export, args @ ..]) => {
+                let start = Instant::now();
+                let results = match &self.trigger {
+                    ShellTrigger::Http(trigger) => {
+                        call_export(trigger.engine(), component_id, export, args).await?
+                    }
+                    ShellTrigger::Redis(trigger) => {
+                        call_export(trigger.engine(), component_id, export, args).await?
+                    }
+                };
+                for result in results {
+                    println!("{result:?}");
+                }
+                print_elapsed(start);
+                Ok(())
+            }
+            ("components" | "http" | "header" | "redis" | "call", _) => {
+                bail!("Wrong arguments to `{command}`. Type `help` for usage.")
+            }
+            _ => bail!("Unknown command `{command}`. Type `help` for commands."),
+        }
+    }
+
+    fn print_components(&self) {
+        let mut routes = match &self.trigger {
+            ShellTrigger::Http(trigger) => trigger.routes(),
+            ShellTrigger::Redis(trigger) => trigger.routes(),
+        };
+        routes.sort_by(|a, b| a.1.cmp(&b.1));
+        for (route, component_id) in routes {
+            println!("  {component_id}: {route}");
+        }
+    }
+
+    async fn http(&self, method: &str, path: &str, body: Option<&String>) -> Result<()> {
+        let ShellTrigger::Http(trigger) = &self.trigger else {
+            bail!("The application has no HTTP trigger");
+        };
+        let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+            .with_context(|| format!("Invalid method {method:?}"))?;
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(hyper::header::HOST, "localhost");
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let req = builder.body(body.map_or_else(Body::empty, |body| Body::from(body.clone())))?;
+
+        let start = Instant::now();
+        let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let res = trigger.handle(req, Scheme::HTTP, client_addr).await?;
+        println!("{:?} {}", res.version(), res.status());
+        for (name, value) in res.headers() {
+            println!("{name}: {}", String::from_utf8_lossy(value.as_bytes()));
+        }
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+        if !body.is_empty() {
+            println!("\n{}", String::from_utf8_lossy(&body));
+        }
+        print_elapsed(start);
+        Ok(())
+    }
+
+    async fn redis(&self, channel: &str, payload: &[u8]) -> Result<()> {
+        let ShellTrigger::Redis(trigger) = &self.trigger else {
+            bail!("The application has no Redis trigger");
+        };
+        let start = Instant::now();
+        trigger.handle_message(channel, payload).await?;
+        println!("Message handled");
+        print_elapsed(start);
+        Ok(())
+    }
+}
+
+// Calls a function exported by the component, with arguments parsed
+// according to its parameter types, and returns its results.
+async fn call_export<Executor: TriggerExecutor>(
+    engine: &TriggerAppEngine<Executor>,
+    component_id: &str,
+    export: &str,
+    args: &[String],
+) -> Result<Vec<Val>> {
+    let (instance, mut store) = engine.prepare_instance(component_id).await?;
+    let EitherInstance::Component(instance) = instance else {
+        bail!("Component {component_id:?} is a module, whose exports can't be called");
+    };
+    let func = {
+        let mut exports = instance.exports(&mut store);
+        match export.rsplit_once('.') {
+            Some((interface, function)) => exports
+                .instance(interface)
+                .with_context(|| format!("Component exports no interface {interface:?}"))?
+                .func(function),
+            None => exports.root().func(export),
+        }
+        .with_context(|| format!("Component exports no function {export:?}"))?
+    };
+
+    let param_types = func.params(&store);
+    if args.len() != param_types.len() {
+        bail!(
+            "{export} takes {} arguments, but {} were given",
+            param_types.len(),
+            args.len()
+        );
+    }
+    let params = param_types
+        .iter()
+        .zip(args)
+        .map(|(ty, arg)| parse_val(ty, arg))
+        .collect::<Result<Vec<_>>>()?;
+    let mut results = vec![Val::Bool(false); func.results(&store).len()];
+    func.call_async(&mut store, &params, &mut results).await?;
+    func.post_return_async(&mut store).await?;
+    Ok(results)
+}
+
+// Parses a command line argument as a value of the given type. Only
+// primitive types, and lists of bytes given as text, are supported.
+fn parse_val(ty: &Type, arg: &str) -> Result<Val> {
+    let invalid = || anyhow!("Invalid {ty:?} argument {arg:?}");
+    Ok(match ty {
+        Type::Bool => Val::Bool(arg.parse().map_err(|_| invalid())?),
+        Type::S8 => Val::S8(arg.parse().map_err(|_| invalid())?),
+        Type::U8 => Val::U8(arg.parse().map_err(|_| invalid())?),
+        Type::S16 => Val::S16(arg.parse().map_err(|_| invalid())?),
+        Type::U16 => Val::U16(arg.parse().map_err(|_| invalid())?),
+        Type::S32 => Val::S32(arg.parse().map_err(|_| invalid())?),
+        Type::U32 => Val::U32(arg.parse().map_err(|_| invalid())?),
+        Type::S64 => Val::S64(arg.parse().map_err(|_| invalid())?),
+        Type::U64 => Val::U64(arg.parse().map_err(|_| invalid())?),
+        Type::Char => Val::Char(arg.parse().map_err(|_| invalid())?),
+        Type::String => Val::String(arg.into()),
+        Type::List(list) if matches!(list.ty(), Type::U8) => {
+            let bytes = arg.bytes().map(Val::U8).collect();
+            list.new_val(bytes)?
+        }
+        _ => bail!("Arguments of type {ty:?} are not supported"),
+    })
+}
+
+fn trigger_type(app: &LockedApp) -> Result<String> {
+    app.metadata
+        .get("trigger")
+        .and_then(|trigger| trigger.get("type"))
+        .and_then(Value::as_str)
+        .map(ToOwned::to_owned)
+        .context("Application has no trigger type")
+}
+
+fn print_elapsed(start: Instant) {
+    println!("({} ms)", start.elapsed().as_millis());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_parsed_by_parameter_type() {
+        assert!(matches!(parse_val(&Type::U32, "42").unwrap(), Val::U32(42)));
+        assert!(matches!(
+            parse_val(&Type::Bool, "true").unwrap(),
+            Val::Bool(true)
+        ));
+        assert!(
+            matches!(parse_val(&Type::String, "hello world").unwrap(), Val::String(s) if &*s == "hello world")
+        );
+        assert!(parse_val(&Type::U8, "256").is_err());
+        assert!(parse_val(&Type::S32, "forty-two").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Parser;
+
+use crate::{daemon, opts::*};
+
+/// Stop applications running in the background.
+#[derive(Parser, Debug)]
+#[clap(about = "Stop an application running in the background")]
+pub struct StopCommand {
+    /// The application to stop. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file. If omitted, it defaults to
+    /// "spin.toml", or to an application run from the registry in the
+    /// current directory.
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        conflicts_with_all = &["pid", "all"],
+    )]
+    pub app_source: Option<PathBuf>,
+
+    /// Stop the application running in the background with this process ID,
+    /// as shown by `spin ps`.
+    #[clap(long = "pid", conflicts_with = "all")]
+    pub pid: Option<u32>,
+
+    /// Stop all applications running in the background.
+    #[clap(long = "all", takes_value = false)]
+    pub all: bool,
+}
+
+impl StopCommand {
+    pub async fn run(self) -> Result<()> {
+        let records = if self.all {
+            daemon::list()?
+        } else if let Some(pid) = self.pid {
+            match daemon::list()?.into_iter().find(|r| r.pid == pid) {
+                Some(record) => vec![record],
+                None => bail!("No application is running in the background with process ID {pid}"),
+            }
+        } else {
+            let state_dir = self.state_dir()?;
+            match daemon::load(&state_dir)? {
+                Some(record) if record.is_running() => vec![record],
+                Some(record) => {
+                    record.remove()?;
+                    bail!("The application is no longer running")
+                }
+                None => bail!(
+                    "No application is running in the background from {}",
+                    state_dir.parent().unwrap_or(&state_dir).display()
+                ),
+            }
+        };
+
+        if records.is_empty() {
+            println!("No applications are running in the background");
+        }
+        for record in records {
+            record.stop().await?;
+            terminal::step!("Stopped", "{} (process {})", record.app, record.pid);
+        }
+        Ok(())
+    }
+
+    fn state_dir(&self) -> Result<PathBuf> {
+        let default_manifest = PathBuf::from(DEFAULT_MANIFEST_FILE);
+        let app_source = match &self.app_source {
+            Some(app_source) => app_source,
+            None if default_manifest.exists() => &default_manifest,
+            // An application run from a registry keeps its state in the
+            // directory it was run from.
+            None => return Ok(std::env::current_dir()?.join(daemon::STATE_DIR)),
+        };
+        let manifest_file = crate::manifest::resolve_file_path(app_source)?;
+        Ok(spin_loader::local::parent_dir(manifest_file)?.join(daemon::STATE_DIR))
+    }
+}
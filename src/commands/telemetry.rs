@@ -0,0 +1,104 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+use crate::usage::{self, Settings};
+
+/// Commands for opting in to, and inspecting, anonymous usage telemetry.
+#[derive(Subcommand, Debug)]
+pub enum TelemetryCommands {
+    /// Start recording which commands are run, which trigger types
+    /// applications use, and the kinds of errors commands fail with.
+    Enable(Enable),
+
+    /// Stop recording usage, and delete events which have not been sent.
+    Disable(Disable),
+
+    /// Show whether usage is recorded, and where it is sent.
+    Status(Status),
+
+    /// Print exactly what would be sent: the recorded events which have not
+    /// been sent yet, as JSON.
+    Show(Show),
+}
+
+impl TelemetryCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            TelemetryCommands::Enable(cmd) => cmd.run().await,
+            TelemetryCommands::Disable(cmd) => cmd.run().await,
+            TelemetryCommands::Status(cmd) => cmd.run().await,
+            TelemetryCommands::Show(cmd) => cmd.run().await,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Enable {}
+
+impl Enable {
+    pub async fn run(self) -> Result<()> {
+        Settings { enabled: true }.save()?;
+        println!("Usage telemetry is enabled. Thank you!");
+        println!("Spin records the commands you run, without their arguments, the trigger types");
+        println!("of your applications, and the kinds of errors commands fail with. Run");
+        println!("`spin telemetry show` to see exactly what would be sent.");
+        if usage::do_not_track() {
+            println!(
+                "{} is set, so nothing is recorded.",
+                usage::DO_NOT_TRACK_ENV
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Disable {}
+
+impl Disable {
+    pub async fn run(self) -> Result<()> {
+        Settings { enabled: false }.save()?;
+        usage::clear_events()?;
+        println!("Usage telemetry is disabled");
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Status {}
+
+impl Status {
+    pub async fn run(self) -> Result<()> {
+        let enabled = Settings::load()?.enabled;
+        if usage::do_not_track() {
+            println!("Usage telemetry is disabled by {}", usage::DO_NOT_TRACK_ENV);
+        } else if enabled {
+            println!("Usage telemetry is enabled");
+        } else {
+            println!("Usage telemetry is disabled");
+        }
+        match std::env::var(usage::ENDPOINT_ENV) {
+            Ok(endpoint) => println!("Events are sent to {endpoint}"),
+            Err(_) => println!(
+                "Events are kept locally, as {} is not set",
+                usage::ENDPOINT_ENV
+            ),
+        }
+        println!(
+            "{} events have not been sent",
+            usage::pending_events()?.len()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Show {}
+
+impl Show {
+    pub async fn run(self) -> Result<()> {
+        let events = usage::pending_events()?;
+        println!("{}", serde_json::to_string_pretty(&events)?);
+        Ok(())
+    }
+}
@@ -1,20 +1,27 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::PathBuf,
+};
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Parser, Subcommand};
 use comfy_table::Table;
 use path_absolutize::Absolutize;
 
 use serde::Serialize;
 use spin_templates::{
     InstallOptions, InstallationResults, InstalledTemplateWarning, ListResults, ProgressReporter,
-    SkippedReason, Template, TemplateManager, TemplateSource,
+    SkippedReason, Template, TemplateManager, TemplateSource, TemplatesLockfile,
+    TEMPLATES_LOCKFILE,
 };
 
 use crate::build_info::*;
+use crate::output::OutputOpts;
 
 const INSTALL_FROM_DIR_OPT: &str = "FROM_DIR";
 const INSTALL_FROM_GIT_OPT: &str = "FROM_GIT";
+const INSTALL_FROM_OCI_OPT: &str = "FROM_OCI";
+const INSTALL_FROM_BUNDLE_OPT: &str = "FROM_BUNDLE";
 const UPGRADE_ONLY: &str = "GIT_URL";
 
 const DEFAULT_TEMPLATES_INSTALL_PROMPT: &str =
@@ -24,7 +31,8 @@ const DEFAULT_TEMPLATE_REPO: &str = "https://github.com/fermyon/spin";
 /// Commands for working with WebAssembly component templates.
 #[derive(Subcommand, Debug)]
 pub enum TemplateCommands {
-    /// Install templates from a Git repository or local directory.
+    /// Install templates from a Git repository, local directory, OCI
+    /// registry or exported bundle.
     ///
     /// The files of the templates are copied to the local template store: a
     /// directory in your data or home directory.
@@ -33,14 +41,29 @@ pub enum TemplateCommands {
     /// Upgrade templates to match your current version of Spin.
     ///
     /// The files of the templates are copied to the local template store: a
-    /// directory in your data or home directory.
+    /// directory in your data or home directory. Repositories pinned in a
+    /// templates lockfile are upgraded to the pinned revision.
     Upgrade(Upgrade),
 
+    /// Pin the installed template repositories to their installed revisions,
+    /// in a lockfile in the current directory.
+    ///
+    /// Commit the lockfile with your project so that `spin templates upgrade`
+    /// installs the same templates for everyone working on it.
+    Pin(Pin),
+
     /// Remove a template from your installation.
     Uninstall(Uninstall),
 
     /// List the installed templates.
     List(List),
+
+    /// Export installed templates to a bundle, which can be installed on
+    /// another machine with `spin templates install --from`.
+    ///
+    /// Use this to move templates to machines which can't access the
+    /// templates' Git repositories or registries.
+    Export(Export),
 }
 
 impl TemplateCommands {
@@ -48,13 +71,16 @@ impl TemplateCommands {
         match self {
             TemplateCommands::Install(cmd) => cmd.run().await,
             TemplateCommands::Upgrade(cmd) => cmd.run().await,
+            TemplateCommands::Pin(cmd) => cmd.run().await,
             TemplateCommands::Uninstall(cmd) => cmd.run().await,
             TemplateCommands::List(cmd) => cmd.run().await,
+            TemplateCommands::Export(cmd) => cmd.run().await,
         }
     }
 }
 
-/// Install templates from a Git repository or local directory.
+/// Install templates from a Git repository, local directory, OCI registry or
+/// exported bundle.
 #[derive(Parser, Debug)]
 pub struct Install {
     /// The URL of the templates git repository.
@@ -63,6 +89,8 @@ pub struct Install {
         name = INSTALL_FROM_GIT_OPT,
         long = "git",
         conflicts_with = INSTALL_FROM_DIR_OPT,
+        conflicts_with = INSTALL_FROM_OCI_OPT,
+        conflicts_with = INSTALL_FROM_BUNDLE_OPT,
     )]
     pub git: Option<String>,
 
@@ -75,12 +103,44 @@ pub struct Install {
         name = INSTALL_FROM_DIR_OPT,
         long = "dir",
         conflicts_with = INSTALL_FROM_GIT_OPT,
+        conflicts_with = INSTALL_FROM_OCI_OPT,
+        conflicts_with = INSTALL_FROM_BUNDLE_OPT,
     )]
     pub dir: Option<PathBuf>,
 
+    /// The OCI reference of a template pack, such as
+    /// `ghcr.io/org/templates:v1` or `ghcr.io/org/templates@sha256:...` to
+    /// pin a digest. A pack pulled before can be installed again without
+    /// network access.
+    #[clap(
+        name = INSTALL_FROM_OCI_OPT,
+        long = "oci",
+        conflicts_with = INSTALL_FROM_GIT_OPT,
+        conflicts_with = INSTALL_FROM_DIR_OPT,
+        conflicts_with = INSTALL_FROM_BUNDLE_OPT,
+    )]
+    pub oci: Option<String>,
+
+    /// A template bundle created by `spin templates export`.
+    #[clap(
+        name = INSTALL_FROM_BUNDLE_OPT,
+        long = "from",
+        conflicts_with = INSTALL_FROM_GIT_OPT,
+        conflicts_with = INSTALL_FROM_DIR_OPT,
+        conflicts_with = INSTALL_FROM_OCI_OPT,
+    )]
+    pub from: Option<PathBuf>,
+
+    /// Ignore server certificate errors from the OCI registry.
+    #[clap(long = "insecure", requires = INSTALL_FROM_OCI_OPT, takes_value = false)]
+    pub insecure: bool,
+
     /// If present, updates existing templates instead of skipping.
     #[clap(long = "upgrade", alias = "update")]
     pub update: bool,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 /// Upgrade existing template repositories from their source.
@@ -105,6 +165,46 @@ pub struct Upgrade {
     /// upgrade all repositories without prompting.
     #[clap(long = "all", conflicts_with = UPGRADE_ONLY)]
     pub all: bool,
+
+    /// The templates lockfile whose pins to honour. Defaults to
+    /// `spin-templates.lock` in the current directory, if it exists.
+    #[clap(long = "lockfile")]
+    pub lockfile: Option<PathBuf>,
+
+    /// Install the revisions pinned in the templates lockfile over your
+    /// installed templates without prompting for confirmation.
+    #[clap(long = "force")]
+    pub force: bool,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+/// Pin the installed template repositories to their installed revisions.
+#[derive(Parser, Debug)]
+pub struct Pin {
+    /// The templates lockfile to write. Defaults to `spin-templates.lock` in
+    /// the current directory.
+    #[clap(long = "lockfile")]
+    pub lockfile: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
+}
+
+/// Export installed templates to a bundle.
+#[derive(Parser, Debug)]
+pub struct Export {
+    /// The templates to export. By default, all installed templates are
+    /// exported.
+    pub template_ids: Vec<String>,
+
+    /// The path of the bundle to create, such as `templates.tar.gz`.
+    #[clap(long = "to")]
+    pub to: PathBuf,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 /// Remove a template from your installation.
@@ -112,22 +212,39 @@ pub struct Upgrade {
 pub struct Uninstall {
     /// The template to uninstall.
     pub template_id: String,
+
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 impl Install {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         let template_manager = TemplateManager::try_default()
             .context("Failed to construct template directory path")?;
-        let source = match (&self.git, &self.dir) {
-            (Some(git), None) => TemplateSource::try_from_git(git, &self.branch, SPIN_VERSION)?,
-            (None, Some(dir)) => {
+        let source = match (&self.git, &self.dir, &self.oci, &self.from) {
+            (Some(git), None, None, None) => {
+                TemplateSource::try_from_git(git, &self.branch, SPIN_VERSION)?
+            }
+            (None, Some(dir), None, None) => {
                 let abs_dir = dir.absolutize().map(|d| d.to_path_buf());
                 TemplateSource::File(abs_dir.unwrap_or_else(|_| dir.clone()))
             }
-            _ => anyhow::bail!("Exactly one of `git` and `dir` sources must be specified"),
+            (None, None, Some(reference), None) => {
+                let mut client = spin_oci::Client::new(self.insecure, None).await?;
+                let dir = client
+                    .pull_templates(reference)
+                    .await
+                    .with_context(|| format!("Failed to pull template pack {reference}"))?;
+                TemplateSource::from_pulled_oci(reference, dir)
+            }
+            (None, None, None, Some(bundle)) => TemplateSource::Bundle(bundle.clone()),
+            _ => anyhow::bail!(
+                "Exactly one of `git`, `dir`, `oci` and `from` sources must be specified"
+            ),
         };
 
-        let reporter = ConsoleProgressReporter;
+        let reporter = ConsoleProgressReporter(&self.output);
         let options = InstallOptions::default().update(self.update);
 
         let installation_results = template_manager
@@ -135,9 +252,24 @@ impl Install {
             .await
             .context("Failed to install one or more templates")?;
 
-        self.print_installed_templates(&installation_results);
-
-        Ok(())
+        let json = TemplatesInstalledJson {
+            installed: installation_results
+                .installed
+                .iter()
+                .map(json_list_format)
+                .collect(),
+            skipped: installation_results
+                .skipped
+                .iter()
+                .map(|(id, reason)| SkippedTemplateJson {
+                    id: id.clone(),
+                    reason: skipped_reason_text(reason),
+                })
+                .collect(),
+        };
+        self.output.result(&json, |_| {
+            self.print_installed_templates(&installation_results)
+        })
     }
 
     fn print_installed_templates(&self, installation_results: &InstallationResults) {
@@ -181,49 +313,94 @@ impl Install {
 
 impl Upgrade {
     pub async fn run(&self) -> Result<()> {
-        if self.git.is_some() {
-            // This is equivalent to `install --update`
-            let install = Install {
-                git: self.git.clone(),
-                branch: self.branch.clone(),
-                dir: None,
-                update: true,
-            };
-
-            install.run().await
-        } else {
-            let template_manager = TemplateManager::try_default()?;
-            let reporter = ConsoleProgressReporter;
-            let options = InstallOptions::default().update(true);
-
-            let selected_sources = match self.repos_to_upgrade(&template_manager).await? {
+        self.output.init();
+        let lockfile = self.lockfile()?;
+        let template_manager = TemplateManager::try_default()?;
+
+        let selected_sources = match &self.git {
+            Some(git) => {
+                let pinned = lockfile.as_ref().and_then(|l| l.revision(git));
+                match pinned {
+                    Some(revision) if self.branch.is_none() => {
+                        let source = RepoSelection::from_repo(git, Some(revision))
+                            .await
+                            .with_context(|| format!("Invalid repository URL {git}"))?;
+                        vec![source]
+                    }
+                    _ => {
+                        // This is equivalent to `install --update`
+                        let install = Install {
+                            git: self.git.clone(),
+                            branch: self.branch.clone(),
+                            dir: None,
+                            oci: None,
+                            insecure: false,
+                            from: None,
+                            update: true,
+                            output: self.output.clone(),
+                        };
+
+                        return install.run().await;
+                    }
+                }
+            }
+            None => match self
+                .repos_to_upgrade(&template_manager, lockfile.as_ref())
+                .await?
+            {
                 Some(sources) => sources,
                 None => return Ok(()),
-            };
+            },
+        };
 
-            let mut summary = UpgradeSummary::new();
+        if !self.force && !confirm_pinned_sources(&selected_sources)? {
+            return Ok(());
+        }
 
-            for source in selected_sources {
-                println!("Upgrading templates from {}...", source.repo);
+        let reporter = ConsoleProgressReporter(&self.output);
+        let options = InstallOptions::default().update(true);
 
-                let installation_results = template_manager
-                    .install(&source.template_source, &options, &reporter)
-                    .await;
+        let mut summary = UpgradeSummary::new();
 
-                summary.extend_with(&source.repo, installation_results);
+        for source in selected_sources {
+            self.output
+                .status(format!("Upgrading templates from {}...", source));
 
-                println!();
-            }
+            let installation_results = template_manager
+                .install(&source.template_source, &options, &reporter)
+                .await;
 
-            self.print_upgrade_summary(&summary);
+            summary.extend_with(&source.repo, installation_results);
 
-            Ok(())
+            self.output.status("");
+        }
+
+        let json = TemplatesUpgradedJson {
+            upgraded: summary.upgraded.iter().map(json_list_format).collect(),
+            errors: summary
+                .errored_repos
+                .iter()
+                .map(|(repository, error)| RepoErrorJson {
+                    repository: repository.clone(),
+                    error: error.clone(),
+                })
+                .collect(),
+        };
+        self.output
+            .result(&json, |_| self.print_upgrade_summary(&summary))
+    }
+
+    fn lockfile(&self) -> Result<Option<TemplatesLockfile>> {
+        match &self.lockfile {
+            Some(path) => TemplatesLockfile::load(path).map(Some),
+            None => TemplatesLockfile::load_if_exists(TEMPLATES_LOCKFILE.as_ref()),
         }
     }
 
     async fn repos_to_upgrade(
         &self,
         template_manager: &TemplateManager,
+        lockfile: Option<&TemplatesLockfile>,
     ) -> anyhow::Result<Option<Vec<RepoSelection>>> {
         let existing_templates = template_manager.list().await?.templates;
         let (origin, no_origin): (Vec<_>, Vec<_>) = existing_templates
@@ -246,9 +423,16 @@ impl Upgrade {
             repos.insert("https://github.com/fermyon/spin-js-sdk");
         }
 
+        // Pinned repos are installed even if they weren't before, so that
+        // everyone working on the project has the same templates.
+        if let Some(lockfile) = lockfile {
+            repos.extend(lockfile.repositories().iter().map(|r| r.git.as_str()));
+        }
+
         let mut sources = vec![];
         for repo in repos {
-            if let Some(source) = RepoSelection::from_repo(repo).await {
+            let pinned = lockfile.and_then(|l| l.revision(repo));
+            if let Some(source) = RepoSelection::from_repo(repo, pinned).await {
                 sources.push(source);
             }
         }
@@ -360,16 +544,28 @@ struct RepoSelection {
     repo: String,
     template_source: TemplateSource,
     resolved_tag: Option<String>,
+    pinned_revision: Option<String>,
 }
 
 impl RepoSelection {
-    async fn from_repo(repo: &str) -> Option<Self> {
-        let template_source = TemplateSource::try_from_git(repo, &None, SPIN_VERSION).ok()?;
-        let resolved_tag = template_source.resolved_tag().await;
+    async fn from_repo(repo: &str, pinned_revision: Option<&str>) -> Option<Self> {
+        let (template_source, resolved_tag) = match pinned_revision {
+            Some(revision) => (
+                TemplateSource::try_from_pinned_git(repo, revision, SPIN_VERSION).ok()?,
+                None,
+            ),
+            None => {
+                let template_source =
+                    TemplateSource::try_from_git(repo, &None, SPIN_VERSION).ok()?;
+                let resolved_tag = template_source.resolved_tag().await;
+                (template_source, resolved_tag)
+            }
+        };
         Some(Self {
             repo: repo.to_owned(),
             template_source,
             resolved_tag,
+            pinned_revision: pinned_revision.map(|r| r.to_owned()),
         })
     }
 }
@@ -377,13 +573,42 @@ impl RepoSelection {
 impl std::fmt::Display for RepoSelection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.repo))?;
-        if let Some(tag) = &self.resolved_tag {
+        if let Some(revision) = &self.pinned_revision {
+            f.write_fmt(format_args!(" (pinned at {revision})"))?;
+        } else if let Some(tag) = &self.resolved_tag {
             f.write_fmt(format_args!(" (at {tag})"))?;
         };
         Ok(())
     }
 }
 
+// A lockfile usually comes with a project, and so may not be trustworthy:
+// confirm before installing its pins over the installed templates.
+fn confirm_pinned_sources(sources: &[RepoSelection]) -> anyhow::Result<bool> {
+    let pinned = sources
+        .iter()
+        .filter(|s| s.pinned_revision.is_some())
+        .collect::<Vec<_>>();
+    if pinned.is_empty() {
+        return Ok(true);
+    }
+
+    eprintln!("The templates lockfile pins the following template repositories:");
+    for source in &pinned {
+        eprintln!("- {source}");
+    }
+    let should_install = dialoguer::Confirm::new()
+        .with_prompt("Install these templates, replacing any you have installed from them?")
+        .default(false)
+        .interact_opt()
+        .context("Failed to confirm the pinned templates. To install them without confirmation, pass --force")?;
+    if should_install != Some(true) {
+        eprintln!("No templates were installed");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
 fn elements_at<T>(source: Vec<T>, indexes: Vec<usize>) -> Vec<T> {
     source
         .into_iter()
@@ -423,8 +648,88 @@ impl UpgradeSummary {
     }
 }
 
+impl Pin {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        let template_manager = TemplateManager::try_default()
+            .context("Failed to construct template directory path")?;
+        let path = self
+            .lockfile
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(TEMPLATES_LOCKFILE));
+
+        let mut lockfile = TemplatesLockfile::load_if_exists(&path)?.unwrap_or_default();
+        let mut unrecorded = BTreeSet::new();
+        for template in template_manager.list().await?.templates {
+            match (template.source_repo(), template.source_revision()) {
+                (Some(repo), Some(revision)) => lockfile.pin(repo, revision),
+                (Some(repo), None) => {
+                    unrecorded.insert(repo.to_owned());
+                }
+                (None, _) => (),
+            }
+        }
+
+        if !unrecorded.is_empty() {
+            eprintln!("Spin could not determine the installed revision of the following template repositories:");
+            for repo in &unrecorded {
+                eprintln!("- {repo}");
+            }
+            eprintln!("To pin them, run `spin templates upgrade` to reinstall them, then run `spin templates pin` again");
+            eprintln!();
+        }
+
+        if lockfile.repositories().is_empty() {
+            self.output.status(
+                "No template repositories to pin. Only templates installed from Git can be pinned.",
+            );
+            return Ok(());
+        }
+
+        lockfile.save(&path)?;
+
+        self.output.result(lockfile.repositories(), |repositories| {
+            println!("Pinned template repositories in {}", path.display());
+            let mut table = Table::new();
+            table.set_header(vec!["Repository", "Revision"]);
+            table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+            for repo in repositories {
+                table.add_row(vec![&repo.git, &repo.revision]);
+            }
+            println!();
+            println!("{}", table);
+        })
+    }
+}
+
+impl Export {
+    pub async fn run(self) -> Result<()> {
+        self.output.init();
+        let template_manager = TemplateManager::try_default()
+            .context("Failed to construct template directory path")?;
+
+        let exported = template_manager
+            .export(&self.template_ids, &self.to)
+            .await
+            .context("Failed to export templates")?;
+
+        self.output.result(&exported, |exported| {
+            println!(
+                "Exported {} template(s) to {}",
+                exported.len(),
+                self.to.display()
+            );
+            println!(
+                "To install them on another machine, run `spin templates install --from {}`",
+                self.to.display()
+            );
+        })
+    }
+}
+
 impl Uninstall {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         let template_manager = TemplateManager::try_default()
             .context("Failed to construct template directory path")?;
 
@@ -433,6 +738,8 @@ impl Uninstall {
             .await
             .context("Failed to uninstall template")?;
 
+        self.output
+            .status(format!("Uninstalled template {}", self.template_id));
         Ok(())
     }
 }
@@ -444,19 +751,9 @@ pub struct List {
     #[clap(long = "tag", multiple_occurrences = true)]
     pub tags: Vec<String>,
 
-    /// The format in which to list the templates.
-    #[clap(value_enum, long = "format", default_value = "table", hide = true)]
-    pub format: ListFormat,
-
-    /// Whether to show additional template details in the list.
-    #[clap(long = "verbose", takes_value = false)]
-    pub verbose: bool,
-}
-
-#[derive(ValueEnum, Clone, Debug)]
-pub enum ListFormat {
-    Table,
-    Json,
+    // With `--verbose`, additional template details are shown.
+    #[clap(flatten)]
+    pub output: OutputOpts,
 }
 
 impl List {
@@ -469,15 +766,18 @@ impl List {
             .await
             .context("Failed to list templates")?;
 
-        match self.format {
-            ListFormat::Table if list_results.needs_install() => {
-                prompt_install_default_templates(&template_manager).await?;
-            }
-            ListFormat::Table => self.print_templates_table(&list_results),
-            ListFormat::Json => self.print_templates_json(&list_results)?,
-        };
+        if !self.output.is_json() && list_results.needs_install() {
+            prompt_install_default_templates(&template_manager).await?;
+            return Ok(());
+        }
 
-        Ok(())
+        let json_vals: Vec<_> = list_results
+            .templates
+            .iter()
+            .map(json_list_format)
+            .collect();
+        self.output
+            .result(&json_vals, |_| self.print_templates_table(&list_results))
     }
 
     fn print_templates_table(&self, list_results: &ListResults) {
@@ -498,7 +798,7 @@ impl List {
             let mut table = Table::new();
 
             let mut header = vec!["Name", "Description"];
-            if self.verbose {
+            if self.output.verbose {
                 header.push("Installed from");
             }
 
@@ -507,7 +807,7 @@ impl List {
 
             for template in templates {
                 let mut row = vec![template.id(), template.description_or_empty()];
-                if self.verbose {
+                if self.output.verbose {
                     row.push(template.installed_from_or_empty());
                 }
                 table.add_row(row);
@@ -530,18 +830,6 @@ impl List {
             }
         }
     }
-
-    fn print_templates_json(&self, list_results: &ListResults) -> anyhow::Result<()> {
-        let json_vals: Vec<_> = list_results
-            .templates
-            .iter()
-            .map(json_list_format)
-            .collect();
-
-        let json_text = serde_json::to_string_pretty(&json_vals)?;
-        println!("{}", json_text);
-        Ok(())
-    }
 }
 
 fn json_list_format(template: &Template) -> TemplateListJson {
@@ -558,11 +846,35 @@ struct TemplateListJson {
     description: Option<String>,
 }
 
-struct ConsoleProgressReporter;
+#[derive(Serialize)]
+struct TemplatesInstalledJson {
+    installed: Vec<TemplateListJson>,
+    skipped: Vec<SkippedTemplateJson>,
+}
+
+#[derive(Serialize)]
+struct SkippedTemplateJson {
+    id: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct TemplatesUpgradedJson {
+    upgraded: Vec<TemplateListJson>,
+    errors: Vec<RepoErrorJson>,
+}
+
+#[derive(Serialize)]
+struct RepoErrorJson {
+    repository: String,
+    error: String,
+}
+
+struct ConsoleProgressReporter<'a>(&'a OutputOpts);
 
-impl ProgressReporter for ConsoleProgressReporter {
+impl ProgressReporter for ConsoleProgressReporter<'_> {
     fn report(&self, message: impl AsRef<str>) {
-        println!("{}", message.as_ref());
+        self.0.status(message.as_ref());
     }
 }
 
@@ -603,7 +915,11 @@ async fn install_default_templates() -> anyhow::Result<()> {
         git: Some(DEFAULT_TEMPLATE_REPO.to_owned()),
         branch: None,
         dir: None,
+        oci: None,
+        insecure: false,
+        from: None,
         update: false,
+        output: Default::default(),
     };
     install_cmd
         .run()
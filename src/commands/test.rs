@@ -0,0 +1,269 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use hyper::{http::uri::Scheme, Body, Request};
+use serde_json::Value;
+use spin_app::locked::LockedApp;
+use spin_http::routes::RoutePattern;
+use spin_trigger::locked::write_locked_app;
+use spin_trigger_http::HttpTrigger;
+
+use crate::{
+    commands::up::{in_process::InProcessTrigger, UpCommand, APPLICATION_OPT},
+    opts::*,
+};
+
+/// The ID of a test component, or the prefix of the IDs of test components.
+const TEST_COMPONENT_ID: &str = "test";
+
+/// Run the test components of an application.
+///
+/// A test component is an HTTP component whose ID is `test` or starts with
+/// `test-`. Each test component is sent a GET request for its route, and the
+/// test passes if it responds with a success status. Failing tests should
+/// respond with an error status, and may describe the failure in the body.
+///
+/// Each test runs in its own in-process runtime, whose default key value store
+/// and SQLite database are in memory and start empty, so tests cannot see each
+/// other's data or that of `spin up`.
+#[derive(Parser, Debug)]
+#[clap(about = "Run the test components of an application")]
+pub struct TestCommand {
+    /// The application to test. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(name = APPLICATION_OPT, short = 'f', long = "from")]
+    pub app_source: Option<String>,
+
+    /// The environment, such as `prod`, whose overrides in the manifest's
+    /// `[profile.<environment>]` table apply.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// The build profile, such as `release`, whose modules to test.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Runtime configuration file for the tests, e.g. to mock outbound HTTP
+    /// requests with `[[outbound_http.mock]]` sections. Key value stores and
+    /// databases which it does not configure are in memory.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Disable the cache of compiled components.
+    #[clap(long = "disable-cache", takes_value = false)]
+    pub disable_cache: bool,
+
+    /// Run only the tests whose component IDs contain this string.
+    pub filter: Option<String>,
+}
+
+/// A test component, and the path of its route.
+#[derive(Debug, PartialEq)]
+struct TestCase {
+    component_id: String,
+    path: String,
+}
+
+enum TestOutcome {
+    Passed,
+    Failed(String),
+}
+
+impl TestCommand {
+    pub async fn run(self) -> Result<()> {
+        let working_dir = tempfile::Builder::new()
+            .prefix("spin-test-")
+            .tempdir()
+            .context("Failed to create working directory")?;
+        let loader = UpCommand {
+            app_source: self.app_source.iter().cloned().collect(),
+            environment: self.environment.clone(),
+            profile: self.profile.clone(),
+            ..Default::default()
+        };
+        let app = loader.load_app(working_dir.path()).await?;
+        let tests = test_cases(&app)?
+            .into_iter()
+            .filter(|test| match &self.filter {
+                Some(filter) => test.component_id.contains(filter),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if tests.is_empty() {
+            println!(
+                "No tests found. Test components are HTTP components whose IDs are \
+                 `{TEST_COMPONENT_ID}` or start with `{TEST_COMPONENT_ID}-`."
+            );
+            return Ok(());
+        }
+        let locked_url = write_locked_app(&app, working_dir.path()).await?;
+
+        println!("\nrunning {} tests", tests.len());
+        let mut failures = vec![];
+        for test in &tests {
+            let outcome = self
+                .run_test(test, &locked_url, working_dir.path())
+                .await
+                .unwrap_or_else(|err| TestOutcome::Failed(format!("{err:?}")));
+            match outcome {
+                TestOutcome::Passed => println!("test {} ... ok", test.component_id),
+                TestOutcome::Failed(message) => {
+                    println!("test {} ... FAILED", test.component_id);
+                    failures.push((&test.component_id, message));
+                }
+            }
+        }
+
+        if !failures.is_empty() {
+            println!("\nfailures:");
+            for (component_id, message) in &failures {
+                println!("\n---- {component_id} ----\n{}", message.trim_end());
+            }
+        }
+        let passed = tests.len() - failures.len();
+        let result = if failures.is_empty() { "ok" } else { "FAILED" };
+        println!(
+            "\ntest result: {result}. {passed} passed; {} failed\n",
+            failures.len()
+        );
+        if !failures.is_empty() {
+            bail!("{} of {} tests failed", failures.len(), tests.len());
+        }
+        Ok(())
+    }
+
+    // Runs the test in a runtime of its own, so that its state is isolated.
+    async fn run_test(
+        &self,
+        test: &TestCase,
+        locked_url: &str,
+        working_dir: &Path,
+    ) -> Result<TestOutcome> {
+        let trigger: HttpTrigger = InProcessTrigger {
+            runtime_config_file: self.runtime_config_file.as_deref(),
+            disable_cache: self.disable_cache,
+            ..Default::default()
+        }
+        .build(locked_url.to_owned(), working_dir)
+        .await?;
+
+        let req = Request::get(&test.path)
+            .header(hyper::header::HOST, "localhost")
+            .body(Body::empty())?;
+        let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let res = trigger.handle(req, Scheme::HTTP, client_addr).await?;
+        let status = res.status();
+        if status.is_success() {
+            return Ok(TestOutcome::Passed);
+        }
+        let body = hyper::body::to_bytes(res.into_body()).await?;
+        Ok(TestOutcome::Failed(format!(
+            "{status}\n{}",
+            String::from_utf8_lossy(&body)
+        )))
+    }
+}
+
+fn is_test_component(id: &str) -> bool {
+    id == TEST_COMPONENT_ID
+        || id
+            .strip_prefix(TEST_COMPONENT_ID)
+            .map_or(false, |rest| rest.starts_with('-'))
+}
+
+fn test_cases(app: &LockedApp) -> Result<Vec<TestCase>> {
+    let base = app
+        .metadata
+        .get("trigger")
+        .and_then(|trigger| trigger.get("base"))
+        .and_then(Value::as_str)
+        .unwrap_or("/");
+    let mut tests = vec![];
+    for trigger in &app.triggers {
+        let config = &trigger.trigger_config;
+        let Some(component_id) = config.get("component").and_then(Value::as_str) else {
+            continue;
+        };
+        if !is_test_component(component_id) {
+            continue;
+        }
+        if trigger.trigger_type != "http" {
+            bail!(
+                "Test component {component_id:?} has a {} trigger; only HTTP components can be tests",
+                trigger.trigger_type
+            );
+        }
+        let route = config
+            .get("route")
+            .and_then(Value::as_str)
+            .with_context(|| format!("Test component {component_id:?} has no route"))?;
+        let path = match RoutePattern::from(base, route).path_or_prefix() {
+            "" => "/".to_owned(),
+            path => path.to_owned(),
+        };
+        tests.push(TestCase {
+            component_id: component_id.to_owned(),
+            path,
+        });
+    }
+    tests.sort_by(|a, b| a.component_id.cmp(&b.component_id));
+    Ok(tests)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn locked_app(triggers: Value) -> LockedApp {
+        LockedApp::from_json(
+            json!({
+                "spin_lock_version": 0,
+                "metadata": { "trigger": { "type": "http", "base": "/api" } },
+                "triggers": triggers,
+                "components": [],
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_components_are_found_by_id() {
+        let app = locked_app(json!([
+            { "id": "1", "trigger_type": "http", "trigger_config": { "component": "web", "route": "/..." } },
+            { "id": "2", "trigger_type": "http", "trigger_config": { "component": "test-kv", "route": "/test/kv/..." } },
+            { "id": "3", "trigger_type": "http", "trigger_config": { "component": "test", "route": "/test" } },
+            { "id": "4", "trigger_type": "http", "trigger_config": { "component": "testing", "route": "/testing" } },
+        ]));
+        let tests = test_cases(&app).unwrap();
+        assert_eq!(
+            tests,
+            vec![
+                TestCase {
+                    component_id: "test".into(),
+                    path: "/api/test".into()
+                },
+                TestCase {
+                    component_id: "test-kv".into(),
+                    path: "/api/test/kv".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn non_http_test_components_are_rejected() {
+        let app = locked_app(json!([
+            { "id": "1", "trigger_type": "redis", "trigger_config": { "component": "test-queue", "channel": "jobs" } },
+        ]));
+        assert!(test_cases(&app).is_err());
+    }
+}
@@ -6,16 +6,36 @@ use std::{
 
 use anyhow::{anyhow, bail, Context, Result};
 use clap::{CommandFactory, Parser};
-use reqwest::Url;
 use spin_app::locked::LockedApp;
 use spin_manifest::ApplicationTrigger;
 use spin_oci::OciLoader;
-use spin_trigger::cli::{SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR};
+use spin_trigger::{
+    cli::{
+        RUNTIME_CONFIG_FILE, SPIN_DRY_RUN, SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR,
+    },
+    locked::{remove_environment_passthrough, write_locked_app},
+    RuntimeConfig,
+};
 use tempfile::TempDir;
 
-use crate::opts::*;
+use crate::{daemon, opts::*, output::OutputOpts};
+
+mod directory;
+pub(crate) mod in_process;
+mod multi;
+mod watch;
+#[cfg(not(windows))]
+mod workers;
+
+pub(crate) const APPLICATION_OPT: &str = "APPLICATION";
 
-const APPLICATION_OPT: &str = "APPLICATION";
+// Env file entries with this prefix set application variable values.
+pub(crate) const ENV_FILE_VARIABLE_PREFIX: &str = "SPIN_VARIABLE_";
+// The prefix used by the trigger's default env config provider.
+pub(crate) const VARIABLE_ENV_PREFIX: &str = "SPIN_CONFIG_";
+
+// Env file entries by key. Entries in later files replace earlier ones.
+type EnvFileEntries = std::collections::BTreeMap<String, String>;
 
 /// Start the Fermyon runtime.
 #[derive(Parser, Debug, Default)]
@@ -31,13 +51,18 @@ pub struct UpCommand {
     /// The application to run. This may be a manifest (spin.toml) file, a
     /// directory containing a spin.toml file, or a remote registry reference.
     /// If omitted, it defaults to "spin.toml".
+    ///
+    /// This may be given more than once, or be a directory whose
+    /// subdirectories contain applications, to serve several HTTP
+    /// applications from one listener (see `--app-routing`).
     #[clap(
         name = APPLICATION_OPT,
         short = 'f',
         long = "from",
         group = "source",
+        multiple_occurrences = true
     )]
-    pub app_source: Option<String>,
+    pub app_source: Vec<String>,
 
     /// The application to run. This is the same as `--from` but forces the
     /// application to be interpreted as a file or directory path.
@@ -73,6 +98,21 @@ pub struct UpCommand {
     #[clap(short = 'e', long = "env", parse(try_from_str = parse_env_var))]
     pub env: Vec<(String, String)>,
 
+    /// Load environment variables from a file of `KEY=value` lines. Entries
+    /// named `SPIN_VARIABLE_<NAME>` set the value of the application variable
+    /// `<name>`; all others are passed to all components of the application.
+    /// May be given more than once: later files override earlier ones, and
+    /// `--env` overrides them all.
+    #[clap(long = "env-file")]
+    pub env_files: Vec<PathBuf>,
+
+    /// Let the components of an application from a registry read the host
+    /// environment variables named by their `environment_passthrough`
+    /// entries. Without this, those entries are ignored for registry
+    /// applications.
+    #[clap(long = "allow-env-passthrough", takes_value = false)]
+    pub allow_env_passthrough: bool,
+
     /// Temporary directory for the static assets of the components.
     #[clap(long = "temp")]
     pub tmp: Option<PathBuf>,
@@ -85,6 +125,108 @@ pub struct UpCommand {
     #[clap(long, takes_value = false)]
     pub direct_mounts: bool,
 
+    /// The format of runtime and component log output: "text" or "json".
+    /// In "json" format, each log line is written as a JSON record.
+    #[clap(long = "log-format", env = spin_telemetry::LOG_FORMAT_ENV)]
+    pub log_format: Option<spin_telemetry::LogFormat>,
+
+    /// Where to write runtime and component log output: "stderr", "syslog" or
+    /// "journald". Component output is still written to the log directory.
+    #[clap(long = "log-target", env = spin_telemetry::LOG_TARGET_ENV)]
+    pub log_target: Option<spin_telemetry::LogTarget>,
+
+    // `--format json` writes logs as JSON unless `--log-format` is given, and
+    // `--quiet` silences component output as the trigger's `--quiet` does.
+    #[clap(flatten)]
+    pub output: OutputOpts,
+
+    /// Rebuild components when their sources change, and reload them without
+    /// restarting the application. Changes to the manifest or to component
+    /// files restart the application. This can only be used with local apps.
+    #[clap(long = "watch", takes_value = false)]
+    pub watch: bool,
+
+    /// Run the application in the background. Its output is written to
+    /// `.spin/spin-up.log`. Use `spin ps` to list background applications and
+    /// `spin stop` to stop them.
+    #[clap(short = 'd', long = "detach", takes_value = false)]
+    pub detach: bool,
+
+    /// When serving several applications, how requests are routed to them:
+    /// "prefix" serves each application under `/<app name>`, and "host" serves
+    /// each application at the hostname `<app name>.<domain>`, e.g.
+    /// `myapp.localhost`.
+    #[clap(long = "app-routing", default_value = "prefix")]
+    pub app_routing: multi::AppRouting,
+
+    /// Serve every application in a directory, starting, restarting and
+    /// stopping them as their files appear, change and disappear. Each entry
+    /// is an application: a subdirectory containing spin.toml, a manifest
+    /// `<name>.toml`, or a file `<name>.ref` containing a registry reference.
+    /// Each application has its own state directory, and its own variables
+    /// and runtime config from `<name>.env` and `<name>.runtime-config.toml`
+    /// beside it, if they exist.
+    #[clap(
+        long = "apps-dir",
+        conflicts_with_all = &[APPLICATION_OPT, APP_MANIFEST_FILE_OPT, FROM_REGISTRY_OPT]
+    )]
+    pub apps_dir: Option<PathBuf>,
+
+    /// Run an HTTP application in this many worker processes, which share the
+    /// listening address so that requests are spread among them. Workers
+    /// which exit are restarted. Not supported on Windows.
+    #[clap(long = "workers")]
+    pub workers: Option<usize>,
+
+    /// Build the application's components before running it. This can only
+    /// be used with local apps.
+    #[clap(long = "build", takes_value = false)]
+    pub build: bool,
+
+    /// The build profile to use, such as `release`. Components which define
+    /// the profile run the modules it builds, and `--build` and `--watch`
+    /// build them with its build command.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// The environment to run in, such as `prod`. The overrides of
+    /// variables, allowed hosts, build commands and triggers in the
+    /// manifest's `[profile.<environment>]` table apply. This can only be
+    /// used with local apps.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// Check the manifest strictly: keys Spin does not recognise, variables
+    /// no component uses, and deprecated settings are errors rather than
+    /// being ignored. This can only be used with local apps.
+    #[clap(long = "strict", takes_value = false)]
+    pub strict: bool,
+
+    /// Load the application and check its runtime config, then print the
+    /// routes it would serve and the backend of each key-value store and
+    /// SQLite database its components use, and exit without serving it.
+    /// Fails if a component uses a store or database which is not
+    /// configured.
+    #[clap(long = "dry-run", takes_value = false)]
+    pub dry_run: bool,
+
+    /// If the application is from a registry and was pushed with variants,
+    /// the variant to run: a platform such as `linux/arm64` or a variant
+    /// name. The default is the variant for this platform.
+    #[clap(long = "variant")]
+    pub variant: Option<spin_oci::Variant>,
+
+    /// Pull registry applications through a mirror, as
+    /// `<REGISTRY>=<MIRROR>`, e.g. `ghcr.io=mirror.example.com/ghcr`. May be
+    /// given more than once; mirrors are tried before those in the runtime
+    /// config file, and the registry itself is tried last.
+    #[clap(
+        long = "registry-mirror",
+        multiple_occurrences = true,
+        parse(try_from_str = crate::commands::registry::parse_registry_mirror)
+    )]
+    pub registry_mirrors: Vec<(String, String)>,
+
     /// All other args, to be passed through to the trigger
     #[clap(hide = true)]
     pub trigger_args: Vec<OsString>,
@@ -92,6 +234,7 @@ pub struct UpCommand {
 
 impl UpCommand {
     pub async fn run(self) -> Result<()> {
+        self.output.init();
         // For displaying help, first print `spin up`'s own usage text, then
         // attempt to load an app and print trigger-type-specific usage.
         let help = self.help;
@@ -101,6 +244,14 @@ impl UpCommand {
                 .bin_name("spin up")
                 .print_help()?;
             println!();
+        } else if self.dry_run && (self.detach || self.watch || self.workers.is_some()) {
+            bail!("--dry-run cannot be used with --detach, --watch or --workers");
+        } else if self.apps_dir.is_some()
+            && (self.detach || self.watch || self.workers.is_some() || self.dry_run)
+        {
+            bail!("--apps-dir cannot be used with --detach, --watch, --workers or --dry-run");
+        } else if self.detach && std::env::var_os(daemon::SPIN_DETACHED).is_none() {
+            return self.run_detached();
         }
         self.run_inner().await.or_else(|err| {
             if help {
@@ -112,7 +263,82 @@ impl UpCommand {
         })
     }
 
+    #[cfg(windows)]
+    fn run_detached(&self) -> Result<()> {
+        bail!("--detach is not supported on Windows");
+    }
+
+    // Runs `spin up` again as a background process, recording it in the
+    // application's state directory.
+    #[cfg(not(windows))]
+    fn run_detached(&self) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let (app, state_dir) = match self.resolve_app_source() {
+            AppSource::None => bail!("Default file '{DEFAULT_MANIFEST_FILE}' not found. Run `spin up --from <APPLICATION>`, or `spin up --help` for usage."),
+            AppSource::File(path) => (
+                path.display().to_string(),
+                spin_loader::local::parent_dir(&path)?.join(daemon::STATE_DIR),
+            ),
+            AppSource::OciRegistry(reference) => {
+                (reference, std::env::current_dir()?.join(daemon::STATE_DIR))
+            }
+            AppSource::Multiple(_) => (
+                self.app_source.join(", "),
+                std::env::current_dir()?.join(daemon::STATE_DIR),
+            ),
+            AppSource::Unresolvable(err) => bail!("{err}"),
+        };
+
+        if let Some(existing) = daemon::load(&state_dir)? {
+            if existing.is_running() {
+                bail!(
+                    "The application is already running in the background (process {}). Stop it with `spin stop`.",
+                    existing.pid
+                );
+            }
+        }
+
+        std::fs::create_dir_all(&state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let log_file = daemon::log_file(&state_dir);
+        let log = std::fs::File::create(&log_file)
+            .with_context(|| format!("Failed to create {}", log_file.display()))?;
+
+        let mut cmd = std::process::Command::new(std::env::current_exe()?);
+        cmd.args(std::env::args_os().skip(1))
+            .env(daemon::SPIN_DETACHED, "1")
+            .stdin(std::process::Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            // Use a separate process group so that the application doesn't
+            // receive signals, such as Ctrl+C, meant for the terminal.
+            .process_group(0);
+
+        let child = cmd.spawn().context("Failed to start background process")?;
+        let record = daemon::AppRecord::new(child.id(), &app, &state_dir);
+        record.save()?;
+
+        terminal::step!(
+            "Started",
+            "{app} in the background (process {})",
+            record.pid
+        );
+        self.output
+            .status(format!("Output is written to {}", log_file.display()));
+        self.output
+            .status("Use `spin stop` to stop the application");
+        Ok(())
+    }
+
     async fn run_inner(self) -> Result<()> {
+        if let (Some(apps_dir), false) = (&self.apps_dir, self.help) {
+            let apps_dir = apps_dir.clone();
+            let working_dir_holder = self.working_directory()?;
+            let working_dir = working_dir_holder.path().canonicalize()?;
+            return directory::run_directory(self, apps_dir, working_dir).await;
+        }
+
         let app_source = self.resolve_app_source();
 
         if app_source == AppSource::None {
@@ -125,26 +351,86 @@ impl UpCommand {
             }
         }
 
-        let working_dir_holder = match &self.tmp {
-            None => WorkingDirectory::Temporary(tempfile::tempdir()?),
-            Some(d) => WorkingDirectory::Given(d.to_owned()),
-        };
+        let working_dir_holder = self.working_directory()?;
         let working_dir = working_dir_holder.path().canonicalize()?;
 
+        if self.workers.is_some() && !self.help {
+            if self.watch || matches!(app_source, AppSource::Multiple(_)) {
+                bail!("--workers cannot be used with --watch or with several applications");
+            }
+            if cfg!(windows) {
+                bail!("--workers is not supported on Windows");
+            }
+        }
+
+        if (self.build || self.profile.is_some() || self.environment.is_some() || self.strict)
+            && !self.help
+        {
+            let AppSource::File(manifest_file) = &app_source else {
+                bail!(
+                    "--build, --profile, --environment and --strict can only be used with local applications"
+                );
+            };
+            if self.strict {
+                spin_loader::local::check_manifest_strictly(
+                    manifest_file,
+                    self.environment.as_deref(),
+                )
+                .await?;
+            }
+            if self.build {
+                let options = spin_build::BuildOptions {
+                    jobs: std::thread::available_parallelism().map_or(1, Into::into),
+                    profile: self.profile.clone(),
+                    environment: self.environment.clone(),
+                    ..Default::default()
+                };
+                spin_build::build_with_options(manifest_file, &[], &options).await?;
+            }
+        }
+
+        if let AppSource::Multiple(sources) = &app_source {
+            if self.dry_run && !self.help {
+                bail!("--dry-run can only be used with a single application");
+            }
+            if self.help {
+                return self
+                    .run_trigger(trigger_command(HELP_ARGS_ONLY_TRIGGER_TYPE), None)
+                    .await;
+            }
+            let sources = sources.clone();
+            return multi::run_multiple(self, sources, working_dir).await;
+        }
+
+        if self.watch && !self.help {
+            let AppSource::File(manifest_file) = &app_source else {
+                bail!("--watch can only be used with local applications");
+            };
+            let manifest_file = manifest_file.clone();
+            return watch::run_watched(self, manifest_file, working_dir).await;
+        }
+
         let mut locked_app = match &app_source {
             AppSource::None => bail!("Internal error - should have shown help"),
             AppSource::File(path) => self.prepare_app_from_file(path, &working_dir).await?,
             AppSource::OciRegistry(oci) => self.prepare_app_from_oci(oci, &working_dir).await?,
+            AppSource::Multiple(_) => bail!("Internal error - should have served multiple apps"),
             AppSource::Unresolvable(err) => bail!("{err}"),
         };
 
         let trigger_cmd = trigger_command_from_locked_app(&locked_app)?;
+        crate::usage::note_trigger_types(
+            locked_app
+                .triggers
+                .iter()
+                .map(|trigger| trigger.trigger_type.as_str()),
+        );
 
         if self.help {
             return self.run_trigger(trigger_cmd, None).await;
         }
 
-        self.update_locked_app(&mut locked_app);
+        self.update_locked_app(&mut locked_app)?;
 
         let local_app_dir = app_source.local_app_dir().map(Into::into);
 
@@ -162,22 +448,104 @@ impl UpCommand {
         trigger_cmd: Vec<String>,
         opts: Option<RunTriggerOpts>,
     ) -> Result<(), anyhow::Error> {
+        #[cfg(not(windows))]
+        if let (Some(count), Some(_)) = (self.workers, &opts) {
+            if count == 0 {
+                bail!("--workers must be at least 1");
+            }
+            if trigger_cmd != trigger_command("http") {
+                bail!("--workers can only be used with HTTP applications");
+            }
+            let cmd = self
+                .trigger_process(trigger_cmd, &self.trigger_args, opts)
+                .await?;
+            return workers::run_workers(cmd, count).await;
+        }
+
+        let mut cmd = self
+            .trigger_process(trigger_cmd, &self.trigger_args, opts)
+            .await?;
+
+        tracing::trace!("Running trigger executor: {:?}", cmd);
+
+        let mut child = cmd.spawn().context("Failed to execute trigger")?;
+
+        // Terminate trigger executor if `spin up` itself receives a termination signal
+        #[cfg(not(windows))]
+        {
+            // https://github.com/nix-rust/nix/issues/656
+            let pid = nix::unistd::Pid::from_raw(child.id() as i32);
+            forward_signals(vec![pid])?;
+        }
+
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            bail!(status);
+        }
+    }
+
+    // Returns the command which runs the trigger executor with the given
+    // trigger arguments.
+    async fn trigger_process(
+        &self,
+        trigger_cmd: Vec<String>,
+        trigger_args: &[OsString],
+        opts: Option<RunTriggerOpts>,
+    ) -> Result<std::process::Command> {
         // The docs for `current_exe` warn that this may be insecure because it could be executed
         // via hard-link. I think it should be fine as long as we aren't `setuid`ing this binary.
         let mut cmd = std::process::Command::new(std::env::current_exe().unwrap());
         cmd.args(&trigger_cmd);
 
+        if let Some(log_format) = self.log_format {
+            cmd.env(spin_telemetry::LOG_FORMAT_ENV, log_format.as_str());
+        } else if self.output.is_json() {
+            cmd.env(
+                spin_telemetry::LOG_FORMAT_ENV,
+                spin_telemetry::LogFormat::Json.as_str(),
+            );
+        }
+        if let Some(log_target) = self.log_target {
+            cmd.env(spin_telemetry::LOG_TARGET_ENV, log_target.as_str());
+        }
+        // The trigger prints as much as this command does.
+        if terminal::verbosity() != terminal::Verbosity::Normal {
+            cmd.env(terminal::VERBOSITY_ENV, terminal::verbosity().as_str());
+        }
+
+        // Variable values from --env-file are resolved by the default env
+        // config provider, but do not override those set in the environment.
+        let (variables, _) = self.load_env_files()?;
+        for (name, value) in variables {
+            let env_key = format!("{VARIABLE_ENV_PREFIX}{}", name.to_ascii_uppercase());
+            if std::env::var_os(&env_key).is_none() {
+                cmd.env(env_key, value);
+            }
+        }
+
         if let Some(RunTriggerOpts {
             locked_app,
             working_dir,
             local_app_dir,
         }) = opts
         {
-            let locked_url = self.write_locked_app(&locked_app, &working_dir).await?;
+            let locked_url = write_locked_app(&locked_app, &working_dir).await?;
 
+            self.output.detail(format!(
+                "Application files are in {}",
+                working_dir.display()
+            ));
             cmd.env(SPIN_LOCKED_URL, locked_url)
                 .env(SPIN_WORKING_DIR, &working_dir)
-                .args(&self.trigger_args);
+                .args(trigger_args);
+            if self.output.quiet {
+                cmd.arg("--quiet");
+            }
+            if self.dry_run {
+                cmd.env(SPIN_DRY_RUN, "1");
+            }
 
             if let Some(local_app_dir) = local_app_dir {
                 cmd.env(SPIN_LOCAL_APP_DIR, local_app_dir);
@@ -186,36 +554,53 @@ impl UpCommand {
             cmd.arg("--help-args-only");
         }
 
-        tracing::trace!("Running trigger executor: {:?}", cmd);
-
-        let mut child = cmd.spawn().context("Failed to execute trigger")?;
+        Ok(cmd)
+    }
 
-        // Terminate trigger executor if `spin up` itself receives a termination signal
-        #[cfg(not(windows))]
-        {
-            // https://github.com/nix-rust/nix/issues/656
-            let pid = nix::unistd::Pid::from_raw(child.id() as i32);
-            ctrlc::set_handler(move || {
-                if let Err(err) = nix::sys::signal::kill(pid, nix::sys::signal::SIGTERM) {
-                    tracing::warn!("Failed to kill trigger handler process: {:?}", err)
-                }
-            })?;
+    /// Loads the single application given by the command's source options
+    /// into the working directory, without running it.
+    pub(crate) async fn load_app(&self, working_dir: &Path) -> Result<LockedApp> {
+        match self.resolve_app_source() {
+            AppSource::None => {
+                bail!("No application specified, and no spin.toml in the current directory")
+            }
+            AppSource::File(path) => self.prepare_app_from_file(&path, working_dir).await,
+            AppSource::OciRegistry(reference) => {
+                self.prepare_app_from_oci(&reference, working_dir).await
+            }
+            AppSource::Multiple(_) => bail!("Only one application can be loaded at a time"),
+            AppSource::Unresolvable(err) => bail!("{err}"),
         }
+    }
 
-        let status = child.wait()?;
-        if status.success() {
-            Ok(())
-        } else {
-            bail!(status);
-        }
+    fn working_directory(&self) -> Result<WorkingDirectory> {
+        Ok(match &self.tmp {
+            None => WorkingDirectory::Temporary(tempfile::tempdir()?),
+            Some(d) => WorkingDirectory::Given(d.to_owned()),
+        })
     }
 
-    fn resolve_app_source(&self) -> AppSource {
-        match (&self.app_source, &self.file_source, &self.registry_source) {
-            (None, None, None) => self.default_manifest_or_none(),
-            (Some(source), None, None) => Self::infer_source(source),
-            (None, Some(file), None) => Self::infer_file_source(file.to_owned()),
-            (None, None, Some(reference)) => AppSource::OciRegistry(reference.to_owned()),
+    pub(crate) fn resolve_app_source(&self) -> AppSource {
+        match (
+            self.app_source.as_slice(),
+            &self.file_source,
+            &self.registry_source,
+        ) {
+            ([], None, None) => self.default_manifest_or_none(),
+            ([source], None, None) => Self::infer_source(source),
+            ([], Some(file), None) => Self::infer_file_source(file.to_owned()),
+            ([], None, Some(reference)) => AppSource::OciRegistry(reference.to_owned()),
+            (sources, None, None) => {
+                let mut apps = vec![];
+                for source in sources {
+                    match Self::infer_source(source) {
+                        AppSource::Multiple(nested) => apps.extend(nested),
+                        AppSource::Unresolvable(err) => return AppSource::Unresolvable(err),
+                        app => apps.push(app),
+                    }
+                }
+                AppSource::Multiple(apps)
+            }
             _ => AppSource::unresolvable("More than one application source was specified"),
         }
     }
@@ -247,12 +632,28 @@ impl UpCommand {
     }
 
     fn infer_file_source(path: impl Into<PathBuf>) -> AppSource {
-        match crate::manifest::resolve_file_path(path.into()) {
+        let path = path.into();
+        match crate::manifest::resolve_file_path(&path) {
             Ok(file) => AppSource::File(file),
-            Err(e) => AppSource::Unresolvable(e.to_string()),
+            Err(e) => match Self::apps_in_directory(&path) {
+                Some(apps) => AppSource::Multiple(apps),
+                None => AppSource::Unresolvable(e.to_string()),
+            },
         }
     }
 
+    // Finds the applications in the subdirectories of a directory which does
+    // not itself contain an application.
+    fn apps_in_directory(dir: &Path) -> Option<Vec<AppSource>> {
+        let mut manifests = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| Some(entry.ok()?.path().join(DEFAULT_MANIFEST_FILE)))
+            .filter(|manifest| manifest.is_file())
+            .collect::<Vec<_>>();
+        manifests.sort();
+        (!manifests.is_empty()).then(|| manifests.into_iter().map(AppSource::File).collect())
+    }
+
     fn trigger_args_look_file_like(&self) -> bool {
         // Heuristic for the user typing `spin up foo` instead of `spin up -f foo` - in the
         // first case `foo` gets interpreted as a trigger arg which is probably not what the
@@ -260,24 +661,6 @@ impl UpCommand {
         !self.trigger_args.is_empty() && !self.trigger_args[0].to_string_lossy().starts_with('-')
     }
 
-    async fn write_locked_app(
-        &self,
-        locked_app: &LockedApp,
-        working_dir: &Path,
-    ) -> Result<String, anyhow::Error> {
-        let locked_path = working_dir.join("spin.lock");
-        let locked_app_contents =
-            serde_json::to_vec_pretty(&locked_app).context("failed to serialize locked app")?;
-        tokio::fs::write(&locked_path, locked_app_contents)
-            .await
-            .with_context(|| format!("failed to write {:?}", locked_path))?;
-        let locked_url = Url::from_file_path(&locked_path)
-            .map_err(|_| anyhow!("cannot convert to file URL: {locked_path:?}"))?
-            .to_string();
-
-        Ok(locked_url)
-    }
-
     async fn prepare_app_from_file(
         &self,
         manifest_path: &Path,
@@ -289,31 +672,122 @@ impl UpCommand {
             Some(working_dir)
         };
 
-        let app = spin_loader::from_file(manifest_path, asset_dst).await?;
-
-        spin_trigger::locked::build_locked_app(app, working_dir)
+        let app = spin_loader::local::from_file_with_profile(
+            manifest_path,
+            asset_dst,
+            self.profile.as_deref(),
+            self.environment.as_deref(),
+        )
+        .await?;
+
+        let mut locked_app = spin_trigger::locked::build_locked_app(app, working_dir)?;
+        if spin_oci::has_registry_dependencies(&locked_app)? {
+            let mut client = self.registry_client().await?;
+            client.resolve_dependencies(&mut locked_app).await?;
+        }
+        Ok(locked_app)
     }
 
     async fn prepare_app_from_oci(&self, reference: &str, working_dir: &Path) -> Result<LockedApp> {
-        let mut client = spin_oci::Client::new(self.insecure, None)
-            .await
-            .context("cannot create registry client")?;
+        let mut client = self.registry_client().await?;
+        if let Some(variant) = &self.variant {
+            client.set_variant(variant.clone());
+        }
 
-        OciLoader::new(working_dir)
+        let mut locked_app = OciLoader::new(working_dir)
             .load_app(&mut client, reference)
+            .await?;
+        client.resolve_dependencies(&mut locked_app).await?;
+        if !self.allow_env_passthrough {
+            let ids = remove_environment_passthrough(&mut locked_app);
+            if !ids.is_empty() {
+                terminal::warn!(
+                    "Ignoring environment_passthrough for components {}. Use --allow-env-passthrough to let them read the host environment.",
+                    ids.join(", ")
+                );
+            }
+        }
+        Ok(locked_app)
+    }
+
+    async fn registry_client(&self) -> Result<spin_oci::Client> {
+        let runtime_config = self.registry_runtime_config()?;
+        let mut mirrors = spin_oci::Mirrors::default();
+        for (registry, mirror) in &self.registry_mirrors {
+            mirrors.add(registry, mirror);
+        }
+        spin_oci::Client::from_runtime_config(self.insecure, runtime_config.as_ref(), mirrors)
             .await
+            .context("cannot create registry client")
     }
 
-    fn update_locked_app(&self, locked_app: &mut LockedApp) {
-        // Apply --env to component environments
-        if !self.env.is_empty() {
+    // Registry trust and pull settings come from the runtime config file
+    // passed to the trigger, which is read here because apps are pulled
+    // before the trigger starts. They apply to every `--from` registry
+    // reference.
+    fn registry_runtime_config(&self) -> Result<Option<RuntimeConfig>> {
+        let mut args = self.trigger_args.iter();
+        let mut config_file = std::env::var_os(RUNTIME_CONFIG_FILE).map(PathBuf::from);
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            if arg_str == "--runtime-config-file" {
+                config_file = args.next().map(PathBuf::from);
+            } else if let Some(value) = arg_str.strip_prefix("--runtime-config-file=") {
+                config_file = Some(PathBuf::from(value));
+            }
+        }
+        let Some(config_file) = config_file else {
+            return Ok(None);
+        };
+        let mut config = RuntimeConfig::new(None);
+        config.merge_config_file(config_file)?;
+        Ok(Some(config))
+    }
+
+    fn update_locked_app(&self, locked_app: &mut LockedApp) -> Result<()> {
+        // Apply --env-file and then --env to component environments
+        let (_, component_env) = self.load_env_files()?;
+        if !component_env.is_empty() || !self.env.is_empty() {
             for component in locked_app.components.iter_mut() {
+                component.env.extend(component_env.iter().cloned());
                 component.env.extend(self.env.iter().cloned());
             }
         }
+        Ok(())
+    }
+
+    // Returns the application variable values and component environment
+    // variables set by --env-file, in that order.
+    fn load_env_files(&self) -> Result<(EnvFileEntries, EnvFileEntries)> {
+        let mut variables = EnvFileEntries::new();
+        let mut component_env = EnvFileEntries::new();
+        for path in &self.env_files {
+            read_env_file(path, &mut variables, &mut component_env)?;
+        }
+        Ok((variables, component_env))
     }
 }
 
+// Adds the application variable values and component environment variables
+// set by an env file to those given.
+fn read_env_file(
+    path: &Path,
+    variables: &mut EnvFileEntries,
+    component_env: &mut EnvFileEntries,
+) -> Result<()> {
+    let entries = dotenvy::from_path_iter(path)
+        .with_context(|| format!("Failed to read env file {}", path.display()))?;
+    for entry in entries {
+        let (key, value) =
+            entry.with_context(|| format!("Failed to parse env file {}", path.display()))?;
+        match key.strip_prefix(ENV_FILE_VARIABLE_PREFIX) {
+            Some(name) => variables.insert(name.to_owned(), value),
+            None => component_env.insert(key, value),
+        };
+    }
+    Ok(())
+}
+
 struct RunTriggerOpts {
     locked_app: LockedApp,
     working_dir: PathBuf,
@@ -334,6 +808,33 @@ impl WorkingDirectory {
     }
 }
 
+// Terminates the given trigger processes when `spin up` is interrupted or
+// terminated, and passes on SIGHUP so that they reload their runtime config.
+#[cfg(not(windows))]
+fn forward_signals(pids: Vec<nix::unistd::Pid>) -> Result<()> {
+    use nix::sys::signal::Signal;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupts = signal(SignalKind::interrupt())?;
+    let mut terminations = signal(SignalKind::terminate())?;
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        loop {
+            let forwarded = tokio::select! {
+                _ = interrupts.recv() => Signal::SIGTERM,
+                _ = terminations.recv() => Signal::SIGTERM,
+                _ = hangups.recv() => Signal::SIGHUP,
+            };
+            for pid in &pids {
+                if let Err(err) = nix::sys::signal::kill(*pid, forwarded) {
+                    tracing::warn!("Failed to signal trigger handler process: {:?}", err)
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
 // Parse the environment variables passed in `key=value` pairs.
 fn parse_env_var(s: &str) -> Result<(String, String)> {
     let parts: Vec<_> = s.splitn(2, '=').collect();
@@ -393,17 +894,21 @@ fn trigger_command_from_locked_app(locked_app: &LockedApp) -> Result<Vec<String>
     match trigger_info {
         ApplicationTrigger::Http(_) => Ok(trigger_command("http")),
         ApplicationTrigger::Redis(_) => Ok(trigger_command("redis")),
+        ApplicationTrigger::External(cfg) if cfg.trigger_type() == "azure-service-bus" => {
+            Ok(trigger_command("azure-service-bus"))
+        }
         ApplicationTrigger::External(cfg) => {
             resolve_trigger_plugin(cfg.trigger_type()).map(|p| vec![p])
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum AppSource {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum AppSource {
     None,
     File(PathBuf),
     OciRegistry(String),
+    Multiple(Vec<AppSource>),
     Unresolvable(String),
 }
 
@@ -438,7 +943,7 @@ mod test {
         let file = repo_path("examples/http-rust/spin.toml");
 
         let source = UpCommand {
-            app_source: Some(file.clone()),
+            app_source: vec![file.clone()],
             ..Default::default()
         }
         .resolve_app_source();
@@ -451,7 +956,7 @@ mod test {
         let dir = repo_path("examples/http-rust");
 
         let source = UpCommand {
-            app_source: Some(dir.clone()),
+            app_source: vec![dir.clone()],
             ..Default::default()
         }
         .resolve_app_source();
@@ -467,7 +972,7 @@ mod test {
         let file = repo_path("src/commands/biscuits.toml");
 
         let source = UpCommand {
-            app_source: Some(file),
+            app_source: vec![file],
             ..Default::default()
         }
         .resolve_app_source();
@@ -480,7 +985,7 @@ mod test {
         let file = "zoink/honk/biscuits.toml".to_owned(); // NOBODY CREATE THIS OKAY
 
         let source = UpCommand {
-            app_source: Some(file),
+            app_source: vec![file],
             ..Default::default()
         }
         .resolve_app_source();
@@ -493,7 +998,7 @@ mod test {
         let dir = repo_path("src/commands");
 
         let source = UpCommand {
-            app_source: Some(dir),
+            app_source: vec![dir],
             ..Default::default()
         }
         .resolve_app_source();
@@ -506,7 +1011,7 @@ mod test {
         let reference = "ghcr.io/fermyon/noodles:v1".to_owned();
 
         let source = UpCommand {
-            app_source: Some(reference.clone()),
+            app_source: vec![reference.clone()],
             ..Default::default()
         }
         .resolve_app_source();
@@ -520,7 +1025,7 @@ mod test {
         let reference = "docker.io/fermyon/noodles".to_owned();
 
         let source = UpCommand {
-            app_source: Some(reference.clone()),
+            app_source: vec![reference.clone()],
             ..Default::default()
         }
         .resolve_app_source();
@@ -533,7 +1038,7 @@ mod test {
         let garbage = repo_path("ftp://🤡***🤡 HELLO MR CLOWN?!");
 
         let source = UpCommand {
-            app_source: Some(garbage),
+            app_source: vec![garbage],
             ..Default::default()
         }
         .resolve_app_source();
@@ -542,6 +1047,26 @@ mod test {
         assert!(matches!(source, AppSource::Unresolvable(_)));
     }
 
+    #[test]
+    fn can_infer_multiple_sources() {
+        let dir = repo_path("examples/http-rust");
+        let reference = "ghcr.io/fermyon/noodles:v1".to_owned();
+
+        let source = UpCommand {
+            app_source: vec![dir.clone(), reference.clone()],
+            ..Default::default()
+        }
+        .resolve_app_source();
+
+        assert_eq!(
+            AppSource::Multiple(vec![
+                AppSource::File(PathBuf::from(dir).join("spin.toml")),
+                AppSource::OciRegistry(reference),
+            ]),
+            source
+        );
+    }
+
     #[test]
     fn parses_untyped_source() {
         UpCommand::try_parse_from(["up", "-f", "ghcr.io/example/test:v1"])
@@ -587,4 +1112,39 @@ mod test {
         UpCommand::try_parse_from(["up", "--listen", "127.0.0.1:39453"])
             .expect("Failed to parse implicit source with trigger option");
     }
+
+    #[test]
+    fn parses_workers() {
+        let up = UpCommand::try_parse_from(["up", "--workers", "4", "--listen", "127.0.0.1:39453"])
+            .expect("Failed to parse --workers");
+        assert_eq!(up.workers, Some(4));
+        assert_eq!(up.trigger_args, ["--listen", "127.0.0.1:39453"]);
+    }
+
+    #[test]
+    fn later_env_files_override_earlier() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.env");
+        let local = dir.path().join("local.env");
+        std::fs::write(&base, "GREETING=hello\nLEVEL=info\nSPIN_VARIABLE_DB_URL=a").unwrap();
+        std::fs::write(&local, "LEVEL=debug\nSPIN_VARIABLE_DB_URL=b").unwrap();
+
+        let (variables, component_env) = UpCommand {
+            env_files: vec![base, local],
+            ..Default::default()
+        }
+        .load_env_files()
+        .unwrap();
+
+        assert_eq!(variables.get("DB_URL").map(String::as_str), Some("b"));
+        assert_eq!(
+            component_env.get("GREETING").map(String::as_str),
+            Some("hello")
+        );
+        assert_eq!(
+            component_env.get("LEVEL").map(String::as_str),
+            Some("debug")
+        );
+        assert!(!component_env.contains_key("SPIN_VARIABLE_DB_URL"));
+    }
 }
@@ -0,0 +1,423 @@
+//! Serving a directory of applications with `spin up --apps-dir`.
+//!
+//! Each entry of the directory is an application, which runs as with
+//! several applications (see [`super::multi`]): in its own trigger process,
+//! with its own state directory, and with HTTP applications served from the
+//! shared listener. Beside each application may be an env file of its
+//! variables and component environment, and a runtime config file, which
+//! replaces the one given to `spin up` and so sets the application's own
+//! resource limits and stores. The directory is polled, and applications
+//! are started, restarted and stopped as their files appear, change and
+//! disappear.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Child,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result};
+
+use super::{
+    multi::{self, AppOptions, Routes},
+    AppSource, UpCommand,
+};
+use crate::opts::DEFAULT_MANIFEST_FILE;
+
+const DIRECTORY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// How long a stopped application's process has to exit after SIGTERM before
+// it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const MANIFEST_EXTENSION: &str = "toml";
+const REFERENCE_EXTENSION: &str = "ref";
+const ENV_FILE_SUFFIX: &str = ".env";
+const RUNTIME_CONFIG_SUFFIX: &str = ".runtime-config.toml";
+
+/// Serves the applications in the directory until interrupted.
+pub(super) async fn run_directory(
+    up: UpCommand,
+    apps_dir: PathBuf,
+    working_dir: PathBuf,
+) -> Result<()> {
+    let apps_dir = apps_dir.canonicalize().with_context(|| {
+        format!(
+            "Failed to read applications directory {}",
+            apps_dir.display()
+        )
+    })?;
+    let (listen_addr, trigger_args) = multi::split_listen_arg(&up.trigger_args)?;
+
+    let routes = Arc::new(RwLock::new(Routes::new(up.app_routing)));
+    let server = multi::serve(listen_addr, routes.clone())?;
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            tracing::error!("{err:?}");
+        }
+    });
+    terminal::step!(
+        "\nServing",
+        "applications in {} on http://{listen_addr}",
+        apps_dir.display()
+    );
+
+    let mut apps = DirectoryApps {
+        up,
+        apps_dir,
+        working_dir,
+        trigger_args,
+        routes,
+        running: BTreeMap::new(),
+        failed: BTreeMap::new(),
+    };
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        apps.update(listen_addr).await;
+        tokio::select! {
+            result = &mut shutdown => {
+                apps.stop_all();
+                return result;
+            }
+            _ = tokio::time::sleep(DIRECTORY_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+// The files of an application in the directory. Its modification times tell
+// when the application must be restarted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AppEntry {
+    source: AppSource,
+    env_file: Option<PathBuf>,
+    runtime_config_file: Option<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+struct RunningApp {
+    entry: AppEntry,
+    child: Child,
+}
+
+struct DirectoryApps {
+    up: UpCommand,
+    apps_dir: PathBuf,
+    working_dir: PathBuf,
+    trigger_args: Vec<std::ffi::OsString>,
+    routes: Arc<RwLock<Routes>>,
+    running: BTreeMap<String, RunningApp>,
+    // Applications which failed to start, or exited. They are started again
+    // once their files change.
+    failed: BTreeMap<String, AppEntry>,
+}
+
+impl DirectoryApps {
+    // Stops the applications which were removed or changed, or which have
+    // exited, and starts those which were added or changed.
+    async fn update(&mut self, listen_addr: std::net::SocketAddr) {
+        let entries = match scan_apps_dir(&self.apps_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                terminal::error!("{err:#}");
+                return;
+            }
+        };
+
+        let names = self.running.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            let running = self.running.get_mut(&name).unwrap();
+            if entries.get(&name) != Some(&running.entry) {
+                terminal::step!("Stopping", "{name}");
+                self.stop(&name);
+            } else if let Ok(Some(status)) = running.child.try_wait() {
+                terminal::error!("Application {name} exited: {status}");
+                let running = self.running.remove(&name).unwrap();
+                self.routes.write().unwrap().remove(&name);
+                self.failed.insert(name, running.entry);
+            }
+        }
+        self.failed
+            .retain(|name, entry| entries.get(name) == Some(&*entry));
+
+        for (name, entry) in entries {
+            if self.running.contains_key(&name) || self.failed.contains_key(&name) {
+                continue;
+            }
+            terminal::step!("Starting", "{name}");
+            match self.start(&name, &entry).await {
+                Ok(port) => {
+                    if port.is_some() {
+                        let url = self.routes.read().unwrap().url(&name, listen_addr);
+                        println!("  {name}: {url}");
+                    }
+                }
+                Err(err) => {
+                    terminal::error!("Failed to start application {name}: {err:#}");
+                    self.failed.insert(name, entry);
+                }
+            }
+        }
+    }
+
+    // Starts the application, returning the port of an HTTP application.
+    async fn start(&mut self, name: &str, entry: &AppEntry) -> Result<Option<u16>> {
+        // Files from an earlier run of the application are not reused.
+        let app_working_dir = self.working_dir.join(name);
+        if app_working_dir.exists() {
+            std::fs::remove_dir_all(&app_working_dir)?;
+        }
+        std::fs::create_dir_all(&app_working_dir)?;
+
+        let options = AppOptions {
+            name: Some(name.to_owned()),
+            state_dir: Some(self.apps_dir.join(crate::daemon::STATE_DIR).join(name)),
+            env_file: entry.env_file.clone(),
+            runtime_config_file: entry.runtime_config_file.clone(),
+        };
+        let running_names = self.running.keys().cloned().collect::<Vec<_>>();
+        let app = multi::start_app(
+            &self.up,
+            &entry.source,
+            app_working_dir,
+            &self.trigger_args,
+            &running_names,
+            options,
+        )
+        .await?;
+        if let Some(port) = app.port {
+            self.routes.write().unwrap().add(app.name.clone(), port);
+        }
+        self.running.insert(
+            name.to_owned(),
+            RunningApp {
+                entry: entry.clone(),
+                child: app.child,
+            },
+        );
+        Ok(app.port)
+    }
+
+    fn stop(&mut self, name: &str) {
+        self.routes.write().unwrap().remove(name);
+        let Some(mut running) = self.running.remove(name) else {
+            return;
+        };
+        #[cfg(not(windows))]
+        {
+            let pid = nix::unistd::Pid::from_raw(running.child.id() as i32);
+            if let Err(err) = nix::sys::signal::kill(pid, nix::sys::signal::SIGTERM) {
+                tracing::warn!("Failed to kill trigger handler process: {:?}", err)
+            }
+        }
+        #[cfg(windows)]
+        {
+            if let Err(err) = running.child.kill() {
+                tracing::warn!("Failed to kill trigger handler process: {:?}", err)
+            }
+        }
+        wait_or_kill(&mut running.child, STOP_TIMEOUT);
+    }
+
+    fn stop_all(&mut self) {
+        let names = self.running.keys().cloned().collect::<Vec<_>>();
+        for name in names {
+            self.stop(&name);
+        }
+    }
+}
+
+// Waits for the process to exit, killing it if it is still running after the
+// timeout.
+fn wait_or_kill(child: &mut Child, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(STOP_POLL_INTERVAL),
+            Ok(None) => {
+                tracing::warn!("Trigger handler process did not exit in time; killing it");
+                break;
+            }
+            Err(err) => {
+                tracing::warn!("Failed to wait for trigger handler process: {:?}", err);
+                break;
+            }
+        }
+    }
+    if let Err(err) = child.kill() {
+        tracing::warn!("Failed to kill trigger handler process: {:?}", err)
+    }
+    if let Err(err) = child.wait() {
+        tracing::warn!("Failed to wait for trigger handler process: {:?}", err)
+    }
+}
+
+// Returns the applications in the directory by name. Hidden entries, such as
+// the `.spin` directory which holds the applications' state, are skipped.
+fn scan_apps_dir(apps_dir: &Path) -> Result<BTreeMap<String, AppEntry>> {
+    let read_dir = std::fs::read_dir(apps_dir).with_context(|| {
+        format!(
+            "Failed to read applications directory {}",
+            apps_dir.display()
+        )
+    })?;
+    let mut paths = read_dir
+        .map(|entry| Ok(entry?.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+
+    let mut apps = BTreeMap::new();
+    for path in paths {
+        let (entry_name, source, file) = match app_in_path(&path) {
+            Ok(Some(app)) => app,
+            Ok(None) => continue,
+            Err(err) => {
+                terminal::warn!("Ignoring {}: {err:#}", path.display());
+                continue;
+            }
+        };
+        let name = multi::sanitize_route_name(&entry_name);
+        if apps.contains_key(&name) {
+            tracing::warn!(
+                "Ignoring {}: there is already an application named {name}",
+                path.display()
+            );
+            continue;
+        }
+        let env_file = sibling_file(apps_dir, &entry_name, ENV_FILE_SUFFIX);
+        let runtime_config_file = sibling_file(apps_dir, &entry_name, RUNTIME_CONFIG_SUFFIX);
+        let modified = [Some(&file), env_file.as_ref(), runtime_config_file.as_ref()]
+            .into_iter()
+            .map(|file| file.and_then(|file| file.metadata().ok()?.modified().ok()))
+            .collect();
+        apps.insert(
+            name,
+            AppEntry {
+                source,
+                env_file,
+                runtime_config_file,
+                modified,
+            },
+        );
+    }
+    Ok(apps)
+}
+
+// Returns the name, source and manifest or reference file of the application
+// at the path, if it is one. The name is that of the directory, or the file
+// without its extension.
+fn app_in_path(path: &Path) -> Result<Option<(String, AppSource, PathBuf)>> {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(None);
+    };
+    if file_name.starts_with('.') || file_name.ends_with(RUNTIME_CONFIG_SUFFIX) {
+        return Ok(None);
+    }
+    if path.is_dir() {
+        let manifest = path.join(DEFAULT_MANIFEST_FILE);
+        return Ok(manifest.is_file().then(|| {
+            (
+                file_name.to_owned(),
+                AppSource::File(manifest.clone()),
+                manifest,
+            )
+        }));
+    }
+    let (Some(name), Some(extension)) = (
+        path.file_stem().and_then(|stem| stem.to_str()),
+        path.extension().and_then(|extension| extension.to_str()),
+    ) else {
+        return Ok(None);
+    };
+    match extension {
+        MANIFEST_EXTENSION => Ok(Some((
+            name.to_owned(),
+            AppSource::File(path.to_owned()),
+            path.to_owned(),
+        ))),
+        REFERENCE_EXTENSION => {
+            let reference = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok(Some((
+                name.to_owned(),
+                AppSource::OciRegistry(reference.trim().to_owned()),
+                path.to_owned(),
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn sibling_file(apps_dir: &Path, name: &str, suffix: &str) -> Option<PathBuf> {
+    let path = apps_dir.join(format!("{name}{suffix}"));
+    path.is_file().then_some(path)
+}
+
+// Completes when `spin up` is interrupted or terminated.
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(not(windows))]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminations = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminations.recv() => {}
+        }
+    }
+    #[cfg(windows)]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn processes_which_do_not_exit_are_killed() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        wait_or_kill(&mut child, Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn finds_apps_and_their_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::create_dir_all(dir.join("shop")).unwrap();
+        std::fs::write(dir.join("shop").join(DEFAULT_MANIFEST_FILE), "").unwrap();
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        std::fs::write(dir.join("blog.toml"), "").unwrap();
+        std::fs::write(dir.join("blog.env"), "SPIN_VARIABLE_TITLE=Blog").unwrap();
+        std::fs::write(dir.join("blog.runtime-config.toml"), "").unwrap();
+        std::fs::write(dir.join("api.ref"), "ghcr.io/example/api:1.0\n").unwrap();
+        std::fs::create_dir_all(dir.join(".spin")).unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let apps = scan_apps_dir(dir).unwrap();
+        assert_eq!(apps.keys().collect::<Vec<_>>(), ["api", "blog", "shop"]);
+        assert_eq!(
+            apps["api"].source,
+            AppSource::OciRegistry("ghcr.io/example/api:1.0".to_owned())
+        );
+        assert_eq!(apps["blog"].env_file, Some(dir.join("blog.env")));
+        assert_eq!(
+            apps["blog"].runtime_config_file,
+            Some(dir.join("blog.runtime-config.toml"))
+        );
+        assert_eq!(
+            apps["shop"].source,
+            AppSource::File(dir.join("shop").join(DEFAULT_MANIFEST_FILE))
+        );
+        assert_eq!(apps["shop"].env_file, None);
+    }
+}
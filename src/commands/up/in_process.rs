@@ -0,0 +1,61 @@
+//! Building an application's trigger in the `spin` process, for the commands
+//! which hand it events directly rather than serving them: `spin shell`,
+//! `spin test` and `spin bench`.
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use spin_trigger::{
+    compile_cache::CompileCache, loader::TriggerLoader, HostComponentInitData, RuntimeConfig,
+    TriggerExecutor, TriggerExecutorBuilder,
+};
+
+/// How to build a trigger in process.
+#[derive(Debug, Default)]
+pub(crate) struct InProcessTrigger<'a> {
+    /// A runtime config file to merge.
+    pub runtime_config_file: Option<&'a Path>,
+    /// The directory for the default key value store and database. Without
+    /// one, they are in memory.
+    pub state_dir: Option<&'a str>,
+    /// Whether to compile components without the compilation cache.
+    pub disable_cache: bool,
+    /// Whether to reload components whose Wasm source files change while
+    /// the trigger runs.
+    pub reload_changed_components: bool,
+}
+
+impl InProcessTrigger<'_> {
+    /// Builds the trigger for the locked application at `locked_url`, whose
+    /// files are in `working_dir`.
+    pub async fn build<Executor: TriggerExecutor>(
+        &self,
+        locked_url: String,
+        working_dir: &Path,
+    ) -> Result<Executor>
+    where
+        Executor::TriggerConfig: DeserializeOwned,
+    {
+        let mut loader = TriggerLoader::new(working_dir, false);
+        if !self.disable_cache {
+            loader.enable_compile_cache(CompileCache::new(CompileCache::default_dir()?));
+        }
+        // There is no application directory in which to keep state.
+        let mut runtime_config = RuntimeConfig::new(None);
+        if let Some(state_dir) = self.state_dir {
+            runtime_config.set_state_dir(state_dir);
+        }
+        if let Some(runtime_config_file) = self.runtime_config_file {
+            runtime_config.merge_config_file(runtime_config_file)?;
+        }
+
+        let mut builder = TriggerExecutorBuilder::<Executor>::new(loader);
+        if self.reload_changed_components {
+            builder.reload_changed_components();
+        }
+        builder
+            .build(locked_url, runtime_config, HostComponentInitData::default())
+            .await
+    }
+}
@@ -0,0 +1,562 @@
+//! Serving several applications from one `spin up`.
+//!
+//! Each application runs in its own trigger process, with its own state
+//! directory. HTTP applications listen on internal ports, and `spin up`
+//! forwards requests to them from the shared listener, choosing the
+//! application by path prefix or by hostname.
+
+use std::{
+    convert::Infallible,
+    ffi::OsString,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::PathBuf,
+    process::Child,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use hyper::{
+    client::HttpConnector,
+    service::{make_service_fn, service_fn},
+    Body, Client, Request, Response, Server, StatusCode,
+};
+use spin_app::locked::LockedApp;
+use spin_trigger::locked::NAME_KEY;
+
+use super::{trigger_command_from_locked_app, AppSource, RunTriggerOpts, UpCommand};
+
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:3000";
+
+/// How requests on the shared listener are routed to applications.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AppRouting {
+    /// Serve each application under `/<app name>`.
+    #[default]
+    Prefix,
+    /// Serve each application at the hostname `<app name>.<domain>`.
+    Host,
+}
+
+impl FromStr for AppRouting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "prefix" => Ok(Self::Prefix),
+            "host" => Ok(Self::Host),
+            _ => bail!("unknown app routing {s:?}: expected 'prefix' or 'host'"),
+        }
+    }
+}
+
+/// Runs each of the given applications, serving the HTTP applications from
+/// one listener, until they all exit.
+pub(super) async fn run_multiple(
+    up: UpCommand,
+    sources: Vec<AppSource>,
+    working_dir: PathBuf,
+) -> Result<()> {
+    let (listen_addr, trigger_args) = split_listen_arg(&up.trigger_args)?;
+
+    let mut routes = Routes::new(up.app_routing);
+    let mut children = vec![];
+    for (index, source) in sources.iter().enumerate() {
+        let app_working_dir = working_dir.join(index.to_string());
+        std::fs::create_dir_all(&app_working_dir)?;
+
+        let names = children
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let app = start_app(
+            &up,
+            source,
+            app_working_dir,
+            &trigger_args,
+            &names,
+            AppOptions::default(),
+        )
+        .await?;
+        if let Some(port) = app.port {
+            routes.add(app.name.clone(), port);
+        }
+        children.push((app.name, app.child));
+    }
+
+    // Terminate trigger executors if `spin up` itself receives a termination signal
+    #[cfg(not(windows))]
+    {
+        let pids = children
+            .iter()
+            .map(|(_, child)| nix::unistd::Pid::from_raw(child.id() as i32))
+            .collect::<Vec<_>>();
+        super::forward_signals(pids)?;
+    }
+
+    if !routes.apps.is_empty() {
+        routes.print(listen_addr);
+        let server = serve(listen_addr, Arc::new(RwLock::new(routes)))?;
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                tracing::error!("{err:?}");
+            }
+        });
+    }
+
+    wait_for_all(children).await
+}
+
+/// Settings of one of several applications served together, where they
+/// differ from those given to `spin up`.
+#[derive(Default)]
+pub(super) struct AppOptions {
+    /// The name under which the application is routed, instead of its own.
+    pub name: Option<String>,
+    /// The state directory, instead of `.spin/<name>` in the application's
+    /// directory.
+    pub state_dir: Option<PathBuf>,
+    /// An env file of the application's variables and component
+    /// environment, in addition to `--env-file`.
+    pub env_file: Option<PathBuf>,
+    /// A runtime config file, instead of `--runtime-config-file`.
+    pub runtime_config_file: Option<PathBuf>,
+}
+
+/// An application running in its own trigger process.
+pub(super) struct StartedApp {
+    pub name: String,
+    pub child: Child,
+    /// The internal port of an HTTP application.
+    pub port: Option<u16>,
+}
+
+/// Loads an application and starts its trigger process. An HTTP application
+/// listens on an internal port, to which requests for it are to be
+/// forwarded. The application's name must differ from the names of those
+/// already running.
+pub(super) async fn start_app(
+    up: &UpCommand,
+    source: &AppSource,
+    working_dir: PathBuf,
+    trigger_args: &[OsString],
+    running_names: &[String],
+    options: AppOptions,
+) -> Result<StartedApp> {
+    let (mut locked_app, local_app_dir) = match source {
+        AppSource::File(path) => (
+            up.prepare_app_from_file(path, &working_dir).await?,
+            Some(spin_loader::local::parent_dir(path)?),
+        ),
+        AppSource::OciRegistry(reference) => (
+            up.prepare_app_from_oci(reference, &working_dir).await?,
+            None,
+        ),
+        AppSource::Unresolvable(err) => bail!("{err}"),
+        _ => bail!("Cannot serve {source:?} alongside other applications"),
+    };
+    let trigger_cmd = trigger_command_from_locked_app(&locked_app)?;
+    up.update_locked_app(&mut locked_app)?;
+
+    let name = match options.name {
+        Some(name) => sanitize_route_name(&name),
+        None => route_name(&locked_app)?,
+    };
+    if running_names.contains(&name) {
+        bail!("More than one application is named '{name}'. Applications served together must have different names.");
+    }
+
+    let mut variables = super::EnvFileEntries::new();
+    if let Some(env_file) = &options.env_file {
+        let mut component_env = super::EnvFileEntries::new();
+        super::read_env_file(env_file, &mut variables, &mut component_env)?;
+        for component in locked_app.components.iter_mut() {
+            component.env.extend(component_env.iter().cloned());
+        }
+    }
+
+    // Each application gets its own state directory.
+    let state_dir = match options.state_dir {
+        Some(state_dir) => state_dir,
+        None => local_app_dir
+            .clone()
+            .unwrap_or(std::env::current_dir()?)
+            .join(crate::daemon::STATE_DIR)
+            .join(&name),
+    };
+    let mut app_args = match &options.runtime_config_file {
+        Some(runtime_config_file) => {
+            let mut app_args = remove_runtime_config_arg(trigger_args);
+            app_args.push("--runtime-config-file".into());
+            app_args.push(runtime_config_file.into());
+            app_args
+        }
+        None => trigger_args.to_vec(),
+    };
+    app_args.push("--state-dir".into());
+    app_args.push(state_dir.into_os_string());
+
+    let mut port = None;
+    if is_http_app(&locked_app) {
+        let app_port = unused_port()?;
+        if up.app_routing == AppRouting::Prefix {
+            prefix_http_base(&mut locked_app, &name)?;
+        }
+        app_args.push("--listen".into());
+        app_args.push(format!("{}:{app_port}", Ipv4Addr::LOCALHOST).into());
+        port = Some(app_port);
+    }
+
+    let run_opts = RunTriggerOpts {
+        locked_app,
+        working_dir,
+        local_app_dir,
+    };
+    let mut cmd = up
+        .trigger_process(trigger_cmd, &app_args, Some(run_opts))
+        .await?;
+    // The application's own variable values take precedence over those of
+    // `spin up`.
+    for (name, value) in variables {
+        cmd.env(
+            format!(
+                "{}{}",
+                super::VARIABLE_ENV_PREFIX,
+                name.to_ascii_uppercase()
+            ),
+            value,
+        );
+    }
+    tracing::trace!("Running trigger executor for {name}: {:?}", cmd);
+    let child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to execute trigger for {name}"))?;
+    Ok(StartedApp { name, child, port })
+}
+
+// Separates the `--listen` option, which applies to the shared listener,
+// from the other trigger arguments.
+pub(super) fn split_listen_arg(trigger_args: &[OsString]) -> Result<(SocketAddr, Vec<OsString>)> {
+    let mut listen = None;
+    let mut other_args = vec![];
+    let mut args = trigger_args.iter();
+    while let Some(arg) = args.next() {
+        let arg_str = arg.to_string_lossy();
+        if arg_str == "--listen" {
+            let value = args.next().context("--listen requires a value")?;
+            listen = Some(value.to_string_lossy().into_owned());
+        } else if let Some(value) = arg_str.strip_prefix("--listen=") {
+            listen = Some(value.to_owned());
+        } else {
+            other_args.push(arg.clone());
+        }
+    }
+    let listen = listen.as_deref().unwrap_or(DEFAULT_LISTEN_ADDR);
+    let addr = listen
+        .parse()
+        .with_context(|| format!("Invalid listen address {listen:?}"))?;
+    Ok((addr, other_args))
+}
+
+// The name under which an application is routed: its name, made safe for
+// use in paths and hostnames.
+fn route_name(locked_app: &LockedApp) -> Result<String> {
+    let name_key: &str = NAME_KEY.as_ref();
+    let name = locked_app
+        .metadata
+        .get(name_key)
+        .and_then(|name| name.as_str())
+        .context("missing name in locked application")?;
+    Ok(sanitize_route_name(name))
+}
+
+pub(super) fn sanitize_route_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn is_http_app(locked_app: &LockedApp) -> bool {
+    let trigger_type = locked_app
+        .metadata
+        .get("trigger")
+        .and_then(|trigger| trigger.get("type"))
+        .and_then(|ty| ty.as_str());
+    trigger_type == Some("http")
+}
+
+// Moves the routes of an HTTP application under `/<name>`.
+fn prefix_http_base(locked_app: &mut LockedApp, name: &str) -> Result<()> {
+    let trigger = locked_app
+        .metadata
+        .get_mut("trigger")
+        .and_then(|trigger| trigger.as_object_mut())
+        .context("missing trigger metadata in locked application")?;
+    let base = trigger
+        .get("base")
+        .and_then(|base| base.as_str())
+        .unwrap_or("/");
+    let base = format!("/{name}{}", base.trim_end_matches('/'));
+    trigger.insert("base".to_owned(), base.into());
+    Ok(())
+}
+
+// Removes the `--runtime-config-file` option from the trigger arguments.
+fn remove_runtime_config_arg(trigger_args: &[OsString]) -> Vec<OsString> {
+    let mut other_args = vec![];
+    let mut args = trigger_args.iter();
+    while let Some(arg) = args.next() {
+        let arg_str = arg.to_string_lossy();
+        if arg_str == "--runtime-config-file" {
+            args.next();
+        } else if !arg_str.starts_with("--runtime-config-file=") {
+            other_args.push(arg.clone());
+        }
+    }
+    other_args
+}
+
+fn unused_port() -> Result<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .context("Failed to find a port for an application")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// The HTTP applications served from the shared listener.
+pub(super) struct Routes {
+    routing: AppRouting,
+    apps: Vec<RoutedApp>,
+}
+
+struct RoutedApp {
+    name: String,
+    port: u16,
+}
+
+impl Routes {
+    pub fn new(routing: AppRouting) -> Self {
+        Self {
+            routing,
+            apps: vec![],
+        }
+    }
+
+    pub fn add(&mut self, name: String, port: u16) {
+        self.apps.push(RoutedApp { name, port });
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.apps.retain(|app| app.name != name);
+    }
+
+    // Returns the port of the application which serves the request.
+    fn find<B>(&self, req: &Request<B>) -> Option<u16> {
+        let name = match self.routing {
+            AppRouting::Prefix => req.uri().path().trim_start_matches('/').split('/').next(),
+            AppRouting::Host => req
+                .headers()
+                .get(hyper::header::HOST)
+                .and_then(|host| host.to_str().ok())
+                .or_else(|| req.uri().host())
+                .and_then(|host| host.split(['.', ':']).next()),
+        }?;
+        self.apps
+            .iter()
+            .find(|app| app.name == name)
+            .map(|app| app.port)
+    }
+
+    fn print(&self, listen_addr: SocketAddr) {
+        terminal::step!("\nServing", "http://{listen_addr}");
+        println!("Applications:");
+        for app in &self.apps {
+            println!("  {}: {}", app.name, self.url(&app.name, listen_addr));
+        }
+    }
+
+    /// Returns the URL at which the named application is served.
+    pub fn url(&self, name: &str, listen_addr: SocketAddr) -> String {
+        match self.routing {
+            AppRouting::Prefix => format!("http://{listen_addr}/{name}"),
+            AppRouting::Host => format!("http://{name}.localhost:{}", listen_addr.port()),
+        }
+    }
+}
+
+/// Returns the server which forwards requests on the shared listener to the
+/// applications in the routes.
+pub(super) fn serve(
+    listen_addr: SocketAddr,
+    routes: Arc<RwLock<Routes>>,
+) -> Result<impl std::future::Future<Output = hyper::Result<()>>> {
+    let client = Client::new();
+    let make_service = make_service_fn(move |_| {
+        let routes = routes.clone();
+        let client = client.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                forward(routes.clone(), client.clone(), req)
+            }))
+        }
+    });
+    let server = Server::try_bind(&listen_addr)
+        .with_context(|| format!("Failed to listen on {listen_addr}"))?
+        .serve(make_service);
+    Ok(server)
+}
+
+// Forwards a request to the application which serves it.
+async fn forward(
+    routes: Arc<RwLock<Routes>>,
+    client: Client<HttpConnector>,
+    mut req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let Some(port) = routes.read().unwrap().find(&req) else {
+        return Ok(status_response(StatusCode::NOT_FOUND));
+    };
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let uri = format!("http://{}:{port}{path_and_query}", Ipv4Addr::LOCALHOST);
+    match uri.parse() {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(_) => return Ok(status_response(StatusCode::BAD_REQUEST)),
+    }
+    match client.request(req).await {
+        Ok(res) => Ok(res),
+        Err(err) => {
+            tracing::error!("Error forwarding request to application: {err}");
+            Ok(status_response(StatusCode::BAD_GATEWAY))
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = status;
+    res
+}
+
+async fn wait_for_all(children: Vec<(String, Child)>) -> Result<()> {
+    let waits = children
+        .into_iter()
+        .map(|(name, mut child)| tokio::task::spawn_blocking(move || (name, child.wait())));
+    let mut failed = vec![];
+    for result in futures::future::join_all(waits).await {
+        let (name, status) = result?;
+        let status = status?;
+        if !status.success() {
+            terminal::error!("Application {name} exited: {status}");
+            failed.push(name);
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Application(s) failed: {}", failed.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn routes(routing: AppRouting) -> Routes {
+        Routes {
+            routing,
+            apps: vec![
+                RoutedApp {
+                    name: "shop".to_owned(),
+                    port: 4001,
+                },
+                RoutedApp {
+                    name: "blog".to_owned(),
+                    port: 4002,
+                },
+            ],
+        }
+    }
+
+    fn request(uri: &str, host: &str) -> Request<()> {
+        Request::builder()
+            .uri(uri)
+            .header(hyper::header::HOST, host)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn routes_by_prefix() {
+        let routes = routes(AppRouting::Prefix);
+        assert_eq!(routes.find(&request("/shop", "localhost")), Some(4001));
+        assert_eq!(
+            routes.find(&request("/blog/posts/1", "localhost")),
+            Some(4002)
+        );
+        assert_eq!(routes.find(&request("/shopping", "localhost")), None);
+        assert_eq!(routes.find(&request("/", "localhost")), None);
+    }
+
+    #[test]
+    fn routes_by_host() {
+        let routes = routes(AppRouting::Host);
+        assert_eq!(
+            routes.find(&request("/", "shop.localhost:3000")),
+            Some(4001)
+        );
+        assert_eq!(
+            routes.find(&request("/blog", "blog.example.com")),
+            Some(4002)
+        );
+        assert_eq!(routes.find(&request("/shop", "localhost:3000")), None);
+    }
+
+    #[test]
+    fn separates_listen_arg() {
+        let args = ["--listen", "0.0.0.0:8080", "--quiet"].map(OsString::from);
+        let (addr, other) = split_listen_arg(&args).unwrap();
+        assert_eq!(addr, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(other, [OsString::from("--quiet")]);
+
+        let (addr, other) = split_listen_arg(&[]).unwrap();
+        assert_eq!(addr, DEFAULT_LISTEN_ADDR.parse().unwrap());
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn removes_runtime_config_arg() {
+        let args = [
+            "--runtime-config-file",
+            "shared.toml",
+            "--quiet",
+            "--runtime-config-file=other.toml",
+        ]
+        .map(OsString::from);
+        assert_eq!(
+            remove_runtime_config_arg(&args),
+            [OsString::from("--quiet")]
+        );
+    }
+
+    #[test]
+    fn prefixes_http_base() {
+        let mut locked_app: LockedApp = serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 0,
+            "metadata": { "trigger": { "type": "http", "base": "/" } },
+            "triggers": [],
+            "components": [],
+        }))
+        .unwrap();
+        prefix_http_base(&mut locked_app, "shop").unwrap();
+        assert_eq!(locked_app.metadata["trigger"]["base"], "/shop");
+        assert_eq!(sanitize_route_name("My App"), "my-app");
+    }
+}
@@ -0,0 +1,330 @@
+//! Hot reload for `spin up --watch`.
+//!
+//! The trigger runs with component reloading enabled, so that it reloads a
+//! component whenever the component's Wasm file changes. When a component's
+//! sources change, only that component is rebuilt, and the running trigger
+//! picks up the new Wasm without closing its listeners. Changes to the
+//! manifest, or to files mounted into components, require the application
+//! to be loaded again, so restart the trigger.
+
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    path::{Path, PathBuf},
+    process::Child,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use spin_loader::local::parent_dir;
+use spin_trigger::cli::SPIN_RELOAD_COMPONENTS;
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use watchexec::{
+    action::{Action, Outcome},
+    config::{InitConfig, RuntimeConfig},
+    event::Event,
+    handler::SyncFnHandler,
+    signal::source::MainSignal::{Interrupt, Terminate},
+    ErrorHook, Watchexec,
+};
+
+use super::{trigger_command_from_locked_app, RunTriggerOpts, UpCommand};
+use crate::{
+    commands::watch::WatchCommand,
+    watch_filter::{Config, Filter, WatchPattern},
+};
+
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Runs the application, rebuilding and reloading components as their
+/// sources change, until interrupted.
+pub(super) async fn run_watched(
+    up: UpCommand,
+    manifest_file: PathBuf,
+    working_dir: PathBuf,
+) -> Result<()> {
+    let app_dir = parent_dir(&manifest_file)?;
+    let plan = WatchPlan::new(&manifest_file, &app_dir, up.direct_mounts).await?;
+    let filter = Arc::new(Filter::new(plan.filter_config())?);
+
+    let app = Arc::new(WatchedApp {
+        up,
+        manifest_file,
+        working_dir,
+        running: Mutex::new(None),
+    });
+    app.start().await?;
+
+    let mut init_config = InitConfig::default();
+    init_config.on_error(SyncFnHandler::from(
+        |err: ErrorHook| -> std::result::Result<(), Infallible> {
+            tracing::error!("{}", err.error);
+            Ok(())
+        },
+    ));
+
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.pathset([app_dir]);
+    runtime_config.filterer(filter.clone());
+    runtime_config.action_throttle(WATCH_DEBOUNCE);
+    let plan = Arc::new(plan);
+    let handler_app = app.clone();
+    runtime_config.on_action(move |action: Action| {
+        let app = handler_app.clone();
+        let filter = filter.clone();
+        let plan = plan.clone();
+        async move {
+            if action
+                .events
+                .iter()
+                .any(|event| {
+                    event
+                        .signals()
+                        .any(|s| s.eq(&Interrupt) || s.eq(&Terminate))
+                })
+            {
+                app.stop().await;
+                action.outcome(Outcome::Exit);
+                return Ok::<(), Infallible>(());
+            }
+
+            let mut rebuild = BTreeSet::new();
+            let mut restart = false;
+            for event in action.events.iter() {
+                if filter.matches_manifest_pattern(event) {
+                    eprintln!("Application manifest has changed. If this included changes to the watch configuration, please restart Spin.");
+                    restart = true;
+                }
+                if filter.matches_artifact_pattern(event) {
+                    restart = true;
+                }
+                rebuild.extend(plan.components_with_changed_sources(event));
+            }
+
+            if !rebuild.is_empty() {
+                let component_ids = rebuild.into_iter().collect::<Vec<_>>();
+                terminal::step!("Rebuilding", "{}", component_ids.join(", "));
+                // The running trigger reloads each rebuilt component on its
+                // next use.
+                let options = spin_build::BuildOptions {
+                    profile: app.up.profile.clone(),
+                    environment: app.up.environment.clone(),
+                    ..Default::default()
+                };
+                if let Err(err) =
+                    spin_build::build_with_options(&app.manifest_file, &component_ids, &options)
+                        .await
+                {
+                    terminal::error!("{err:#}");
+                }
+            }
+            if restart {
+                terminal::step!("Restarting", "application");
+                app.stop().await;
+                if let Err(err) = app.start().await {
+                    terminal::error!("Failed to restart application: {err:#}");
+                }
+            }
+
+            action.outcome(Outcome::DoNothing);
+            Ok::<(), Infallible>(())
+        }
+    });
+
+    let runtime = Watchexec::new(init_config, runtime_config)?;
+    let result = runtime.main().await;
+    app.stop().await;
+    result??;
+    Ok(())
+}
+
+// The files which are watched, and which components they belong to.
+struct WatchPlan {
+    manifest_pattern: WatchPattern,
+    // Component ID -> patterns for the component's build sources
+    component_sources: Vec<(String, Vec<WatchPattern>)>,
+    // Patterns for the files mounted into components, if they must be copied
+    // again when changed.
+    files_patterns: Vec<WatchPattern>,
+}
+
+impl WatchPlan {
+    async fn new(manifest_file: &Path, app_dir: &Path, direct_mounts: bool) -> Result<Self> {
+        let app_manifest = spin_loader::local::raw_manifest_from_file(&manifest_file)
+            .await?
+            .into_v1();
+
+        let manifest_pattern = WatchPattern::new(
+            manifest_file
+                .to_str()
+                .with_context(|| format!("non-unicode manifest path {manifest_file:?}"))?
+                .to_owned(),
+            app_dir,
+        )?;
+
+        let component_sources = app_manifest
+            .components
+            .iter()
+            .filter_map(|c| {
+                let patterns = WatchCommand::create_source_pattern(c, app_dir)?;
+                Some(
+                    patterns
+                        .into_iter()
+                        .collect::<Result<Vec<_>>>()
+                        .map(|patterns| (c.id.clone(), patterns)),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Directly mounted files are seen by components as soon as they change.
+        let files_patterns = if direct_mounts {
+            vec![]
+        } else {
+            app_manifest
+                .components
+                .iter()
+                .map(|c| WatchCommand::create_files_patterns(c, app_dir))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect()
+        };
+
+        Ok(Self {
+            manifest_pattern,
+            component_sources,
+            files_patterns,
+        })
+    }
+
+    fn filter_config(&self) -> Config {
+        Config {
+            manifest_pattern: self.manifest_pattern.clone(),
+            source_patterns: self
+                .component_sources
+                .iter()
+                .flat_map(|(_, patterns)| patterns.iter().cloned())
+                .collect(),
+            artifact_patterns: self.files_patterns.clone(),
+            ignore_patterns: Filter::default_ignore_patterns(),
+        }
+    }
+
+    fn components_with_changed_sources<'a>(
+        &'a self,
+        event: &'a Event,
+    ) -> impl Iterator<Item = String> + 'a {
+        self.component_sources
+            .iter()
+            .filter(|(_, patterns)| {
+                event
+                    .paths()
+                    .any(|(path, _)| patterns.iter().any(|wp| wp.pattern.matches_path(path)))
+            })
+            .map(|(id, _)| id.clone())
+    }
+}
+
+struct WatchedApp {
+    up: UpCommand,
+    manifest_file: PathBuf,
+    working_dir: PathBuf,
+    running: Mutex<Option<RunningTrigger>>,
+}
+
+struct RunningTrigger {
+    child: Child,
+    // Each run of the trigger gets its own working directory, as the assets
+    // copied into it are read-only.
+    _working_dir: TempDir,
+}
+
+impl WatchedApp {
+    async fn start(&self) -> Result<()> {
+        let mut running = self.running.lock().await;
+
+        let run_dir = tempfile::tempdir_in(&self.working_dir)?;
+        let working_dir = run_dir.path().canonicalize()?;
+        let mut locked_app = self
+            .up
+            .prepare_app_from_file(&self.manifest_file, &working_dir)
+            .await?;
+        let trigger_cmd = trigger_command_from_locked_app(&locked_app)?;
+        self.up.update_locked_app(&mut locked_app)?;
+
+        let local_app_dir = parent_dir(&self.manifest_file)?;
+        let run_opts = RunTriggerOpts {
+            locked_app,
+            working_dir,
+            local_app_dir: Some(local_app_dir),
+        };
+        let mut cmd = self
+            .up
+            .trigger_process(trigger_cmd, &self.up.trigger_args, Some(run_opts))
+            .await?;
+        cmd.env(SPIN_RELOAD_COMPONENTS, "1");
+
+        tracing::trace!("Running trigger executor: {:?}", cmd);
+        let child = cmd.spawn().context("Failed to execute trigger")?;
+        *running = Some(RunningTrigger {
+            child,
+            _working_dir: run_dir,
+        });
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        let Some(mut running) = self.running.lock().await.take() else {
+            return;
+        };
+        #[cfg(not(windows))]
+        {
+            let pid = nix::unistd::Pid::from_raw(running.child.id() as i32);
+            if let Err(err) = nix::sys::signal::kill(pid, nix::sys::signal::SIGTERM) {
+                tracing::warn!("Failed to kill trigger handler process: {:?}", err)
+            }
+        }
+        #[cfg(windows)]
+        {
+            if let Err(err) = running.child.kill() {
+                tracing::warn!("Failed to kill trigger handler process: {:?}", err)
+            }
+        }
+        if let Err(err) = running.child.wait() {
+            tracing::warn!("Failed to wait for trigger handler process: {:?}", err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn maps_changed_sources_to_components() {
+        let manifest_file = PathBuf::from("tests/watch/http-rust/spin.toml")
+            .canonicalize()
+            .unwrap();
+        let app_dir = parent_dir(&manifest_file).unwrap();
+        let plan = WatchPlan::new(&manifest_file, &app_dir, false)
+            .await
+            .unwrap();
+
+        let changed = |path: &str| {
+            let event = Event {
+                tags: vec![watchexec::event::Tag::Path {
+                    path: app_dir.join(path),
+                    file_type: None,
+                }],
+                metadata: Default::default(),
+            };
+            plan.components_with_changed_sources(&event)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changed("src/lib.rs"), ["hello"]);
+        assert_eq!(changed("subcomponent/main.go"), ["subcomponent"]);
+        assert!(changed("README.md").is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+//! Multi-process worker mode for `spin up --workers`.
+//!
+//! The trigger runs in several worker processes, which all listen on the
+//! same address so that the kernel spreads connections among them. `spin up`
+//! supervises the workers, restarting any which exits, so that a crash in
+//! one worker leaves the others serving.
+
+use std::{
+    process::ExitStatus,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use futures::FutureExt;
+use nix::{sys::signal::Signal, unistd::Pid};
+use tokio::{
+    process::{Child, Command},
+    signal::unix::{signal, SignalKind},
+};
+
+// A worker which exits sooner than this after starting is not restarted.
+const MIN_WORKER_UPTIME: Duration = Duration::from_secs(5);
+
+/// Runs the given trigger command in `count` worker processes until `spin
+/// up` is interrupted or terminated.
+pub(super) async fn run_workers(cmd: std::process::Command, count: usize) -> Result<()> {
+    let mut cmd = Command::from(cmd);
+    cmd.env(spin_trigger_http::SPIN_HTTP_REUSE_PORT, "1");
+
+    let mut workers = Vec::with_capacity(count);
+    for _ in 0..count {
+        workers.push(Worker::spawn(&mut cmd)?);
+    }
+
+    let mut interrupts = signal(SignalKind::interrupt())?;
+    let mut terminations = signal(SignalKind::terminate())?;
+    let mut hangups = signal(SignalKind::hangup())?;
+    let result = loop {
+        let exited =
+            futures::future::select_all(workers.iter_mut().map(|w| Box::pin(w.child.wait())))
+                .map(|(status, index, _)| (status, index));
+        tokio::select! {
+            _ = interrupts.recv() => break Ok(()),
+            _ = terminations.recv() => break Ok(()),
+            _ = hangups.recv() => {
+                workers.iter().for_each(|w| w.signal(Signal::SIGHUP));
+            }
+            (status, index) = exited => {
+                if let Err(err) = workers[index].restart(&mut cmd, status) {
+                    break Err(err);
+                }
+            }
+        }
+    };
+
+    for worker in &workers {
+        worker.signal(Signal::SIGTERM);
+    }
+    for worker in &mut workers {
+        if let Err(err) = worker.child.wait().await {
+            tracing::warn!(
+                "Failed to wait for worker process {}: {:?}",
+                worker.pid,
+                err
+            );
+        }
+    }
+    result
+}
+
+struct Worker {
+    child: Child,
+    pid: Pid,
+    started: Instant,
+}
+
+impl Worker {
+    fn spawn(cmd: &mut Command) -> Result<Self> {
+        tracing::trace!("Running trigger worker: {:?}", cmd);
+        let child = cmd.spawn().context("Failed to execute trigger")?;
+        let pid = child.id().context("Worker process exited immediately")?;
+        Ok(Self {
+            child,
+            // https://github.com/nix-rust/nix/issues/656
+            pid: Pid::from_raw(pid as i32),
+            started: Instant::now(),
+        })
+    }
+
+    // Replaces this worker, which has exited, unless it failed to start.
+    fn restart(&mut self, cmd: &mut Command, status: std::io::Result<ExitStatus>) -> Result<()> {
+        let status = status.context("Failed to wait for worker process")?;
+        if self.started.elapsed() < MIN_WORKER_UPTIME {
+            // Restarting a worker which cannot start would never end.
+            bail!(
+                "Worker process {} exited during startup ({status})",
+                self.pid
+            );
+        }
+        terminal::step!("Restarting", "worker process {} ({status})", self.pid);
+        *self = Self::spawn(cmd)?;
+        Ok(())
+    }
+
+    fn signal(&self, signal: Signal) {
+        if let Err(err) = nix::sys::signal::kill(self.pid, signal) {
+            tracing::warn!("Failed to signal worker process {}: {:?}", self.pid, err)
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use comfy_table::Table;
+use spin_trigger::{
+    cli::RUNTIME_CONFIG_FILE, runtime_config::config_provider::ConfigProvider, RuntimeConfig,
+};
+
+use crate::opts::*;
+
+const SECRET_MASK: &str = "********";
+
+/// List the variables declared by an application, optionally resolving them
+/// against the configured providers.
+#[derive(Parser, Debug)]
+#[clap(about = "List and resolve application variables")]
+pub struct VariablesCommand {
+    /// The application to inspect. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// The environment, such as `prod`, whose variable defaults in the
+    /// manifest's `[profile.<environment>]` table apply.
+    #[clap(long = "environment", env = "SPIN_ENVIRONMENT")]
+    pub environment: Option<String>,
+
+    /// Runtime configuration file used to find config providers.
+    #[clap(long = "runtime-config-file", env = RUNTIME_CONFIG_FILE)]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Resolve each variable and show which provider supplied its value.
+    /// Values of secret variables are masked.
+    #[clap(long = "resolve", takes_value = false)]
+    pub resolve: bool,
+}
+
+struct VariableInfo {
+    name: String,
+    default: Option<String>,
+    required: bool,
+    secret: bool,
+}
+
+impl VariablesCommand {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = crate::manifest::resolve_file_path(&self.app_source)?;
+        let manifest = spin_loader::local::raw_manifest_from_file_in_environment(
+            &manifest_file,
+            self.environment.as_deref(),
+        )
+        .await?
+        .into_v1();
+
+        let mut variables = manifest
+            .variables
+            .into_iter()
+            .map(|(name, var)| VariableInfo {
+                name,
+                required: var.required,
+                default: var.default,
+                secret: var.secret,
+            })
+            .collect::<Vec<_>>();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        if variables.is_empty() {
+            println!("The application declares no variables");
+            return Ok(());
+        }
+
+        if self.resolve {
+            self.print_resolved(&manifest_file, &variables).await
+        } else {
+            print_declared(&variables);
+            Ok(())
+        }
+    }
+
+    async fn print_resolved(&self, manifest_file: &Path, variables: &[VariableInfo]) -> Result<()> {
+        let local_app_dir = spin_loader::local::parent_dir(manifest_file)?;
+        let mut runtime_config = RuntimeConfig::new(Some(local_app_dir));
+        if let Some(config_file) = &self.runtime_config_file {
+            runtime_config.merge_config_file(config_file)?;
+        }
+        let providers = runtime_config
+            .described_config_providers()
+            .context("Failed to configure variable providers")?;
+
+        let (table, unresolved) = resolve_variables(variables, &providers).await?;
+        println!("{table}");
+
+        if !unresolved.is_empty() {
+            println!();
+            bail!(
+                "The following variables could not be resolved: {}",
+                unresolved.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+// Resolves each variable from the first provider which has a value for it,
+// or else from its default. Returns a table of the resolutions, and the names
+// of the variables which could not be resolved.
+async fn resolve_variables<'a>(
+    variables: &'a [VariableInfo],
+    providers: &[(String, ConfigProvider)],
+) -> Result<(Table, Vec<&'a str>)> {
+    let mut table = Table::new();
+    table.set_header(vec!["Name", "Source", "Value"]);
+    table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+
+    let mut unresolved = vec![];
+    for variable in variables {
+        let key = spin_config::Key::new(&variable.name)
+            .with_context(|| format!("Invalid variable name {:?}", variable.name))?;
+
+        let mut resolution = None;
+        for (description, provider) in providers {
+            match provider.get(&key).await {
+                Ok(Some(value)) => {
+                    resolution = Some((description.clone(), Some(value)));
+                    break;
+                }
+                Ok(None) => continue,
+                Err(err) => {
+                    resolution = Some((format!("{description}: error: {err:#}"), None));
+                    break;
+                }
+            }
+        }
+        let (source, value) = resolution.unwrap_or_else(|| match &variable.default {
+            Some(default) => ("default".to_owned(), Some(default.clone())),
+            None => ("NOT SET".to_owned(), None),
+        });
+        if value.is_none() {
+            unresolved.push(variable.name.as_str());
+        }
+        table.add_row(vec![
+            variable.name.clone(),
+            source,
+            display_value(value.as_deref(), variable.secret),
+        ]);
+    }
+    Ok((table, unresolved))
+}
+
+fn print_declared(variables: &[VariableInfo]) {
+    println!("{}", declared_table(variables));
+}
+
+fn declared_table(variables: &[VariableInfo]) -> Table {
+    let mut table = Table::new();
+    table.set_header(vec!["Name", "Required", "Default", "Secret"]);
+    table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+
+    for variable in variables {
+        table.add_row(vec![
+            variable.name.clone(),
+            yes_no(variable.required).to_owned(),
+            display_value(variable.default.as_deref(), variable.secret),
+            yes_no(variable.secret).to_owned(),
+        ]);
+    }
+    table
+}
+
+// The values of secret variables are masked, though whether they have a
+// value is shown.
+fn display_value(value: Option<&str>, secret: bool) -> String {
+    match value {
+        Some(_) if secret => SECRET_MASK.to_owned(),
+        Some(value) => value.to_owned(),
+        None => String::new(),
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, default: Option<&str>, secret: bool) -> VariableInfo {
+        VariableInfo {
+            name: name.to_owned(),
+            default: default.map(str::to_owned),
+            required: default.is_none(),
+            secret,
+        }
+    }
+
+    fn providers() -> Vec<(String, ConfigProvider)> {
+        RuntimeConfig::new(None)
+            .described_config_providers()
+            .unwrap()
+    }
+
+    #[test]
+    fn masks_only_secret_values() {
+        assert_eq!(display_value(Some("hunter2"), true), SECRET_MASK);
+        assert_eq!(display_value(Some("hunter2"), false), "hunter2");
+        assert_eq!(display_value(None, true), "");
+
+        let declared = declared_table(&[
+            variable("password", Some("hunter2"), true),
+            variable("greeting", Some("hello"), false),
+        ])
+        .to_string();
+        assert!(!declared.contains("hunter2"));
+        assert!(declared.contains(SECRET_MASK));
+        assert!(declared.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn masks_resolved_secret_values() {
+        let variables = [variable("password", Some("hunter2"), true)];
+        let (table, unresolved) = resolve_variables(&variables, &providers()).await.unwrap();
+        let table = table.to_string();
+        assert!(!table.contains("hunter2"));
+        assert!(table.contains(SECRET_MASK));
+        assert!(table.contains("default"));
+        assert!(unresolved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_unresolvable_variables() {
+        let variables = [
+            variable("greeting", Some("hello"), false),
+            variable("spin_test_unset_variable", None, true),
+        ];
+        let (table, unresolved) = resolve_variables(&variables, &providers()).await.unwrap();
+        assert_eq!(unresolved, ["spin_test_unset_variable"]);
+        assert!(table.to_string().contains("NOT SET"));
+    }
+}
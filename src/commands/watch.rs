@@ -119,9 +119,15 @@ impl WatchCommand {
             async move {
                 // Dynamically modify the command we're running based on the watch state
                 let state = watch_state.get_state();
+                let seed = matches!(state, State::Running);
                 let spin_args = WatchCommand::generate_arguments(state, up_args, manifest_path);
                 let mut cmd = prespawn.command().await.unwrap();
                 cmd.args(spin_args);
+                // Each run of the application under development starts from
+                // its seed data.
+                if seed {
+                    cmd.env(spin_trigger::cli::SPIN_SEED, "1");
+                }
                 tracing::debug!("modifying command to: {cmd:?}");
                 Ok::<(), Infallible>(())
             }
@@ -256,18 +262,11 @@ impl WatchCommand {
         let files_patterns = app_manifest
             .components
             .iter()
-            .filter_map(|c| c.wasm.files.as_ref())
+            .map(|c| WatchCommand::create_files_patterns(c, &app_dir))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
             .flatten()
-            .filter_map(|raw_file_mount| match raw_file_mount {
-                RawFileMount::Placement(raw_directory_placement) => raw_directory_placement
-                    .source
-                    .join("**/*")
-                    .to_str()
-                    .map(String::from),
-                RawFileMount::Pattern(pattern) => Some(pattern.to_string()),
-            })
-            .map(|p| WatchPattern::new(p, app_dir.as_path()))
-            .collect::<Result<Vec<WatchPattern>>>()?;
+            .collect::<Vec<WatchPattern>>();
 
         let artifact_patterns = component_source_patterns
             .into_iter()
@@ -282,7 +281,9 @@ impl WatchCommand {
         })
     }
 
-    fn create_source_pattern(
+    /// Returns the patterns for the files which a component's build watches,
+    /// if it has a build with a watch configuration.
+    pub(crate) fn create_source_pattern(
         c: &RawComponentManifestImpl<TriggerConfig>,
         app_dir: &Path,
     ) -> Option<Vec<Result<WatchPattern>>> {
@@ -311,6 +312,27 @@ impl WatchCommand {
                 .collect::<Vec<Result<WatchPattern>>>(),
         )
     }
+
+    /// Returns the patterns for the files which a component mounts.
+    pub(crate) fn create_files_patterns(
+        c: &RawComponentManifestImpl<TriggerConfig>,
+        app_dir: &Path,
+    ) -> Result<Vec<WatchPattern>> {
+        c.wasm
+            .files
+            .iter()
+            .flatten()
+            .filter_map(|raw_file_mount| match raw_file_mount {
+                RawFileMount::Placement(raw_directory_placement) => raw_directory_placement
+                    .source
+                    .join("**/*")
+                    .to_str()
+                    .map(String::from),
+                RawFileMount::Pattern(pattern) => Some(pattern.to_string()),
+            })
+            .map(|p| WatchPattern::new(p, app_dir))
+            .collect()
+    }
 }
 
 #[cfg(test)]
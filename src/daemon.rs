@@ -0,0 +1,247 @@
+//! State records for applications running in the background (see
+//! `spin up --detach`).
+//!
+//! A detached application writes a pidfile and a state record to its state
+//! directory (`.spin/`), and a copy of the state record to Spin's data
+//! directory, so that `spin ps` can list every application running locally.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The name of the application state directory, relative to the manifest.
+pub const STATE_DIR: &str = ".spin";
+/// Set in the environment of the background `spin up` process.
+pub const SPIN_DETACHED: &str = "SPIN_DETACHED";
+
+const PID_FILE: &str = "spin.pid";
+const RECORD_FILE: &str = "spin-up.json";
+const LOG_FILE: &str = "spin-up.log";
+
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Describes an application running in the background.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppRecord {
+    /// The ID of the `spin up` process.
+    pub pid: u32,
+    /// The application that was run: a manifest path or registry reference.
+    pub app: String,
+    /// The state directory holding the pidfile and log file.
+    pub state_dir: PathBuf,
+    /// The file to which the output of `spin up` is written.
+    pub log_file: PathBuf,
+    /// When the application was started, in RFC 3339 format.
+    pub started_at: String,
+    /// The start time and command line of the process, which tell it apart
+    /// from a later process which reuses its ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+}
+
+impl AppRecord {
+    /// Creates a record of the given process running an application with
+    /// the given state directory.
+    pub fn new(pid: u32, app: impl Into<String>, state_dir: &Path) -> Self {
+        Self {
+            pid,
+            app: app.into(),
+            state_dir: state_dir.to_owned(),
+            log_file: log_file(state_dir),
+            started_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            process: process_identity(pid),
+        }
+    }
+
+    /// Writes the pidfile and state record.
+    pub fn save(&self) -> Result<()> {
+        let record = serde_json::to_vec_pretty(self)?;
+        std::fs::create_dir_all(&self.state_dir)
+            .with_context(|| format!("Failed to create {}", self.state_dir.display()))?;
+        std::fs::write(self.state_dir.join(PID_FILE), self.pid.to_string())?;
+        std::fs::write(self.state_dir.join(RECORD_FILE), &record)?;
+
+        let index_dir = index_dir()?;
+        std::fs::create_dir_all(&index_dir)
+            .with_context(|| format!("Failed to create {}", index_dir.display()))?;
+        std::fs::write(index_dir.join(format!("{}.json", self.pid)), &record)?;
+        Ok(())
+    }
+
+    /// Removes the pidfile and state record.
+    pub fn remove(&self) -> Result<()> {
+        // The state directory may hold the record of a later run.
+        if load(&self.state_dir)?.as_ref() == Some(self) {
+            remove_if_exists(&self.state_dir.join(PID_FILE))?;
+            remove_if_exists(&self.state_dir.join(RECORD_FILE))?;
+        }
+        remove_if_exists(&index_dir()?.join(format!("{}.json", self.pid)))
+    }
+
+    /// Whether the process is still running. A process which has reused the
+    /// ID is not counted.
+    pub fn is_running(&self) -> bool {
+        is_running(self.pid)
+            && (self.process.is_none() || process_identity(self.pid) == self.process)
+    }
+
+    /// Asks the process to shut down, waiting for it to exit, and removes its
+    /// records.
+    pub async fn stop(&self) -> Result<()> {
+        if self.is_running() {
+            // Without a record of the process, signalling it could stop
+            // something else which has taken its ID.
+            if self.process.is_none() {
+                bail!(
+                    "Spin could not verify that process {} is still the application. Stop it manually if it is.",
+                    self.pid
+                );
+            }
+            terminate(self.pid)?;
+            let deadline = std::time::Instant::now() + STOP_TIMEOUT;
+            while self.is_running() {
+                if std::time::Instant::now() > deadline {
+                    bail!("Process {} did not exit after {STOP_TIMEOUT:?}", self.pid);
+                }
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+        }
+        self.remove()
+    }
+}
+
+/// The file to which a detached `spin up` with the given state directory
+/// writes its output.
+pub fn log_file(state_dir: &Path) -> PathBuf {
+    state_dir.join(LOG_FILE)
+}
+
+/// Loads the state record from a state directory, if there is one.
+pub fn load(state_dir: &Path) -> Result<Option<AppRecord>> {
+    read_record(&state_dir.join(RECORD_FILE))
+}
+
+/// Lists the applications running in the background, removing the records
+/// of any which have exited.
+pub fn list() -> Result<Vec<AppRecord>> {
+    let index_dir = index_dir()?;
+    if !index_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut records = vec![];
+    for entry in std::fs::read_dir(&index_dir)
+        .with_context(|| format!("Failed to read {}", index_dir.display()))?
+    {
+        let Some(record) = read_record(&entry?.path())? else {
+            continue;
+        };
+        if record.is_running() {
+            records.push(record);
+        } else {
+            record.remove()?;
+        }
+    }
+    records.sort_by_key(|r| r.pid);
+    Ok(records)
+}
+
+fn index_dir() -> Result<PathBuf> {
+    Ok(spin_common::data_dir::default_data_dir()?.join("running"))
+}
+
+fn read_record(path: &Path) -> Result<Option<AppRecord>> {
+    match std::fs::read(path) {
+        Ok(contents) => {
+            let record = serde_json::from_slice(&contents)
+                .with_context(|| format!("Invalid state record {}", path.display()))?;
+            Ok(Some(record))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(windows))]
+fn is_running(pid: u32) -> bool {
+    let pid = nix::unistd::Pid::from_raw(pid as i32);
+    // Signal 0 checks for the process without signalling it.
+    nix::sys::signal::kill(pid, None).is_ok()
+}
+
+// Returns the start time and command line of a process, if it is running.
+#[cfg(not(windows))]
+fn process_identity(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "lstart=", "-o", "command="])
+        .output()
+        .ok()?;
+    let identity = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (output.status.success() && !identity.is_empty()).then_some(identity)
+}
+
+#[cfg(not(windows))]
+fn terminate(pid: u32) -> Result<()> {
+    let pid = nix::unistd::Pid::from_raw(pid as i32);
+    nix::sys::signal::kill(pid, nix::sys::signal::SIGTERM)
+        .with_context(|| format!("Failed to stop process {pid}"))
+}
+
+#[cfg(windows)]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(windows)]
+fn process_identity(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+fn terminate(_pid: u32) -> Result<()> {
+    bail!("Stopping background applications is not supported on Windows")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loads_saved_record() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let record = AppRecord::new(1234, "spin.toml", state_dir.path());
+        std::fs::write(
+            state_dir.path().join(RECORD_FILE),
+            serde_json::to_vec(&record).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(load(state_dir.path()).unwrap(), Some(record));
+        assert_eq!(load(&state_dir.path().join("missing")).unwrap(), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn process_with_reused_id_is_not_running() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let mut record = AppRecord::new(std::process::id(), "spin.toml", state_dir.path());
+        assert!(record.process.is_some());
+        assert!(record.is_running());
+
+        record.process = Some("Thu Jan  1 00:00:00 1970 spin up".to_owned());
+        assert!(!record.is_running());
+    }
+}
@@ -1,7 +1,11 @@
 pub mod build_info;
 pub mod commands;
+mod daemon;
 pub mod manifest;
 pub(crate) mod opts;
+pub mod output;
+pub mod usage;
+pub mod user_config;
 mod watch_filter;
 mod watch_state;
 
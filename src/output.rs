@@ -0,0 +1,134 @@
+//! Output options shared by commands, so that they all support the same
+//! formats and verbosity.
+
+use std::fmt::Display;
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use terminal::Verbosity;
+
+use crate::opts::OUTPUT_FORMAT_ENV;
+
+/// The `--format`, `--quiet` and `--verbose` options of a command.
+#[derive(Args, Clone, Debug, Default)]
+pub struct OutputOpts {
+    /// The format of the command's output: "plain" text, or "json" for
+    /// programs to read. In "json" format, progress and status messages are
+    /// written to stderr, so that stdout holds only JSON.
+    #[clap(
+        value_enum,
+        long = "format",
+        env = OUTPUT_FORMAT_ENV,
+        default_value = "plain"
+    )]
+    pub format: OutputFormat,
+
+    /// Print only errors and the command's results, without progress or
+    /// status messages.
+    #[clap(short = 'q', long = "quiet", takes_value = false)]
+    pub quiet: bool,
+
+    /// Print additional details.
+    #[clap(long = "verbose", takes_value = false, conflicts_with = "quiet")]
+    pub verbose: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    // Commands used to call this "human", and `spin templates list` "table".
+    #[default]
+    #[clap(alias = "human", alias = "table")]
+    Plain,
+    Json,
+}
+
+impl OutputOpts {
+    /// Applies the options to everything the command prints, including from
+    /// other crates. Commands call this before printing anything, and pass
+    /// [`terminal::verbosity`] to the processes they run.
+    pub fn init(&self) {
+        // Without either flag, the verbosity is that which the process was
+        // run with, if any.
+        if self.quiet || self.verbose {
+            terminal::set_verbosity(self.verbosity());
+        }
+        if self.is_json() {
+            terminal::print_status_to_stderr();
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    /// Prints a progress or status message, unless quiet.
+    pub fn status(&self, message: impl Display) {
+        if self.quiet {
+            return;
+        }
+        if self.is_json() {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    }
+
+    /// Prints a message only if verbose.
+    pub fn detail(&self, message: impl Display) {
+        if self.verbose {
+            self.status(message);
+        }
+    }
+
+    /// Prints the result of a command: as JSON, or in plain format with the
+    /// given function.
+    pub fn result<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        print_plain: impl FnOnce(&T),
+    ) -> Result<()> {
+        match self.format {
+            OutputFormat::Plain => print_plain(value),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Command {
+        #[clap(flatten)]
+        output: OutputOpts,
+    }
+
+    #[test]
+    fn older_format_names_are_accepted() {
+        for name in ["plain", "human", "table"] {
+            let command = Command::try_parse_from(["spin", "--format", name]).unwrap();
+            assert_eq!(command.output.format, OutputFormat::Plain);
+        }
+    }
+
+    #[test]
+    fn quiet_and_verbose_conflict() {
+        let command = Command::try_parse_from(["spin", "-q"]).unwrap();
+        assert_eq!(command.output.verbosity(), Verbosity::Quiet);
+        assert!(Command::try_parse_from(["spin", "--quiet", "--verbose"]).is_err());
+    }
+}
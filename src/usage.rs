@@ -0,0 +1,307 @@
+//! Opt-in, anonymous usage telemetry: which commands are run, which trigger
+//! types applications use, and the categories of errors commands fail with.
+//!
+//! Nothing is recorded unless the user runs `spin telemetry enable`. Events
+//! never include arguments, paths, application names or other identifying
+//! details. They are kept in `telemetry/events.jsonl` in Spin's data
+//! directory, where `spin telemetry show` prints exactly what would be sent,
+//! and are only sent if `SPIN_USAGE_TELEMETRY_URL` names an endpoint to
+//! send them to. `DO_NOT_TRACK=1` turns recording off regardless of the
+//! setting.
+
+use std::{io::Write, path::PathBuf, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spin_common::data_dir::default_data_dir;
+
+use crate::build_info::SPIN_VERSION;
+
+/// The endpoint to which recorded events are sent. Events are only kept
+/// locally if this is not set.
+pub const ENDPOINT_ENV: &str = "SPIN_USAGE_TELEMETRY_URL";
+/// Turns recording off, whatever the setting, if set to anything but `0`.
+pub const DO_NOT_TRACK_ENV: &str = "DO_NOT_TRACK";
+
+const SETTINGS_FILE: &str = "settings.json";
+const EVENTS_FILE: &str = "events.jsonl";
+// Older events are dropped beyond this, if they can't be sent.
+const MAX_EVENTS: usize = 1000;
+const SEND_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Commands which are run by Spin itself or on every keypress, rather than by
+// the user.
+const UNRECORDED_COMMANDS: &[&str] = &["trigger", "completion"];
+
+static TRIGGER_TYPES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Whether the user has opted in to usage telemetry.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Settings {
+    pub enabled: bool,
+}
+
+/// A record of one command run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageEvent {
+    /// The day the command was run, e.g. `2023-06-01`.
+    pub date: String,
+    pub spin_version: String,
+    pub os: String,
+    pub arch: String,
+    /// The subcommand run, e.g. `plugins install`, or `plugin` for any
+    /// plugin.
+    pub command: String,
+    /// The trigger types of the application the command ran, if any.
+    pub trigger_types: Vec<String>,
+    /// If the command failed, the kind of error: `io`, `network`,
+    /// `manifest` or `other`.
+    pub error_category: Option<String>,
+}
+
+impl Settings {
+    pub fn load() -> Result<Self> {
+        let path = telemetry_dir()?.join(SETTINGS_FILE);
+        match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("Invalid telemetry settings in {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let dir = telemetry_dir()?;
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        std::fs::write(dir.join(SETTINGS_FILE), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Whether events are recorded: the user has opted in, and `DO_NOT_TRACK`
+/// is not set.
+pub fn is_enabled() -> bool {
+    !do_not_track() && Settings::load().map_or(false, |settings| settings.enabled)
+}
+
+/// Whether `DO_NOT_TRACK` turns recording off.
+pub fn do_not_track() -> bool {
+    std::env::var_os(DO_NOT_TRACK_ENV).map_or(false, |value| value != "0")
+}
+
+/// Notes the trigger types of the application the command is running, to
+/// be recorded with the command.
+pub fn note_trigger_types<'a>(trigger_types: impl IntoIterator<Item = &'a str>) {
+    let mut noted = TRIGGER_TYPES.lock().unwrap();
+    for trigger_type in trigger_types {
+        if !noted.iter().any(|noted| noted == trigger_type) {
+            noted.push(trigger_type.to_owned());
+        }
+    }
+}
+
+/// Records the command given by `args`, and its result, if the user has
+/// opted in, and sends recorded events if there is an endpoint to send them
+/// to. Telemetry must never get in the way of the command, so failures are
+/// only logged.
+pub async fn record(app: &clap::Command<'_>, args: &[String], result: &Result<()>) {
+    if !is_enabled() {
+        return;
+    }
+    let command = command_name(app, args);
+    if UNRECORDED_COMMANDS.contains(&command.split(' ').next().unwrap_or_default()) {
+        return;
+    }
+    let event = UsageEvent {
+        date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        spin_version: SPIN_VERSION.to_owned(),
+        os: std::env::consts::OS.to_owned(),
+        arch: std::env::consts::ARCH.to_owned(),
+        command,
+        trigger_types: TRIGGER_TYPES.lock().unwrap().clone(),
+        error_category: result
+            .as_ref()
+            .err()
+            .map(|err| error_category(err).to_owned()),
+    };
+    if let Err(err) = append_event(&event) {
+        tracing::debug!("Failed to record usage telemetry: {err:?}");
+        return;
+    }
+    if let Some(endpoint) = std::env::var_os(ENDPOINT_ENV) {
+        if let Err(err) = send_events(&endpoint.to_string_lossy()).await {
+            tracing::debug!("Failed to send usage telemetry: {err:?}");
+        }
+    }
+}
+
+/// Returns the recorded events which have not been sent.
+pub fn pending_events() -> Result<Vec<UsageEvent>> {
+    let path = telemetry_dir()?.join(EVENTS_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    // A line which can't be read, e.g. from a newer version of Spin, is
+    // left out rather than failing.
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Deletes the recorded events which have not been sent.
+pub fn clear_events() -> Result<()> {
+    let path = telemetry_dir()?.join(EVENTS_FILE);
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to delete {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn append_event(event: &UsageEvent) -> Result<()> {
+    let dir = telemetry_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let mut events = pending_events()?;
+    if events.len() >= MAX_EVENTS {
+        events.drain(..=events.len() - MAX_EVENTS);
+        write_events(&events)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(EVENTS_FILE))?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+fn write_events(events: &[UsageEvent]) -> Result<()> {
+    let mut text = String::new();
+    for event in events {
+        text.push_str(&serde_json::to_string(event)?);
+        text.push('\n');
+    }
+    std::fs::write(telemetry_dir()?.join(EVENTS_FILE), text)?;
+    Ok(())
+}
+
+// Sends the pending events as a JSON array, which is what `spin telemetry
+// show` prints, and deletes them once they are accepted.
+async fn send_events(endpoint: &str) -> Result<()> {
+    let events = pending_events()?;
+    if events.is_empty() {
+        return Ok(());
+    }
+    reqwest::Client::builder()
+        .timeout(SEND_TIMEOUT)
+        .build()?
+        .post(endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(&events)?)
+        .send()
+        .await?
+        .error_for_status()?;
+    clear_events()
+}
+
+fn telemetry_dir() -> Result<PathBuf> {
+    Ok(default_data_dir()?.join("telemetry"))
+}
+
+// Returns the subcommand named by the leading arguments, e.g. `plugins
+// install`, without any arguments to it. Arguments which are neither flags
+// nor subcommands end the subcommand, and if the first is not a Spin
+// subcommand then it names a plugin.
+fn command_name(app: &clap::Command, args: &[String]) -> String {
+    let mut names = vec![];
+    let mut command = app;
+    for arg in args.iter().skip(1) {
+        if arg.starts_with('-') {
+            continue;
+        }
+        match command
+            .get_subcommands()
+            .find(|sub| sub.get_name() == arg || sub.get_all_aliases().any(|alias| alias == arg))
+        {
+            Some(sub) => {
+                names.push(sub.get_name());
+                command = sub;
+            }
+            None if names.is_empty() => return "plugin".to_owned(),
+            None => break,
+        }
+    }
+    if names.is_empty() {
+        "spin".to_owned()
+    } else {
+        names.join(" ")
+    }
+}
+
+// Classifies an error by the first cause in its chain which is of a known
+// kind.
+fn error_category(err: &anyhow::Error) -> &'static str {
+    for cause in err.chain() {
+        if cause.is::<std::io::Error>() {
+            return "io";
+        }
+        if cause.is::<reqwest::Error>() {
+            return "network";
+        }
+        if cause.is::<toml::de::Error>() {
+            return "manifest";
+        }
+    }
+    "other"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, Parser, Subcommand};
+
+    #[derive(Parser)]
+    enum TestApp {
+        #[clap(subcommand, alias = "plugin")]
+        Plugins(PluginCommands),
+        Up {
+            #[clap(short = 'f')]
+            from: Option<String>,
+        },
+    }
+
+    #[derive(Subcommand)]
+    enum PluginCommands {
+        Install { name: String },
+    }
+
+    fn name(line: &str) -> String {
+        let args = line
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        command_name(&TestApp::command(), &args)
+    }
+
+    #[test]
+    fn command_names_leave_out_arguments() {
+        assert_eq!(name("spin up -f my-secret-app"), "up");
+        assert_eq!(name("spin plugin install --yes js2wasm"), "plugins install");
+        assert_eq!(name("spin my-plugin --token abc"), "plugin");
+        assert_eq!(name("spin --version"), "spin");
+    }
+
+    #[test]
+    fn errors_are_categorized_by_cause() {
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let err = anyhow::Error::new(not_found).context("Failed to read manifest");
+        assert_eq!(error_category(&err), "io");
+
+        let err = anyhow::anyhow!("no such component");
+        assert_eq!(error_category(&err), "other");
+    }
+}
@@ -0,0 +1,314 @@
+//! User-level defaults for the Spin CLI, such as the registry of references
+//! which don't name one, or the address on which `spin up` listens.
+//!
+//! The defaults are read from `spin/config.toml` in the user's config
+//! directory, e.g. `~/.config/spin/config.toml` on Linux, or from the file
+//! named by `SPIN_USER_CONFIG`. Command line flags and environment variables
+//! take precedence over them. Defaults of command line options are applied as
+//! the options' default values, and others through the libraries which use
+//! them; neither changes the environment, so plugins which Spin runs don't see
+//! them. Triggers, which Spin runs as processes of its own, read the same
+//! file.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::Command;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::{
+    commands::cache::{CACHE_MAX_AGE_ENV, CACHE_MAX_SIZE_ENV},
+    opts::OUTPUT_FORMAT_ENV,
+};
+
+/// The environment variable naming the user config file, if it is not in
+/// the default location.
+pub const USER_CONFIG_ENV: &str = "SPIN_USER_CONFIG";
+
+static USER_CONFIG: OnceCell<UserConfig> = OnceCell::new();
+
+/// User-level defaults for the Spin CLI.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    /// The registry of references which don't name one, such as
+    /// `fermyon/app:v1`, instead of Docker Hub.
+    #[serde(default)]
+    pub default_registry: Option<String>,
+
+    /// Git repositories of plugin manifests to install plugins from, instead
+    /// of the spin-plugins repository. Plugins are looked up in each in turn.
+    #[serde(default)]
+    pub plugins_repositories: Vec<String>,
+
+    /// The address on which HTTP applications listen, instead of
+    /// `127.0.0.1:3000`.
+    #[serde(default)]
+    pub listen: Option<String>,
+
+    /// Export of trace spans, which is off unless configured.
+    #[serde(default)]
+    pub telemetry: Option<TelemetryConfig>,
+
+    /// Whether output is colored.
+    #[serde(default)]
+    pub color: Option<ColorPreference>,
+
+    /// The format of log lines.
+    #[serde(default)]
+    pub log_format: Option<Format>,
+
+    /// The format of the output of commands which support `--format json`.
+    #[serde(default)]
+    pub output_format: Option<Format>,
+
+    /// Which entries `spin cache prune` deletes by default.
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+}
+
+/// Settings for exporting trace spans to an OpenTelemetry collector.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// The OTLP endpoint to which spans are exported.
+    pub otlp_endpoint: String,
+}
+
+/// The default policy of `spin cache prune`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Entries not used within this long are deleted, e.g. "30d".
+    #[serde(default)]
+    pub max_age: Option<String>,
+    /// The least recently used entries are deleted until the caches take up
+    /// at most this much space, e.g. "2GB".
+    #[serde(default)]
+    pub max_size: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorPreference {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorPreference {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl From<Format> for spin_telemetry::LogFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Text => Self::Text,
+            Format::Json => Self::Json,
+        }
+    }
+}
+
+impl UserConfig {
+    /// Reads the user config file, if there is one.
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text)
+                .with_context(|| format!("Invalid user config file {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+                .with_context(|| format!("Failed to read user config file {}", path.display())),
+        }
+    }
+
+    /// Reads the user config file, if there is one, and gives its defaults
+    /// to the libraries which use them. The config is returned so that its
+    /// defaults can be applied to the command line with
+    /// [`UserConfig::apply_defaults`].
+    pub fn init() -> Result<&'static Self> {
+        let config = USER_CONFIG.get_or_try_init(Self::load)?;
+        if let Some(registry) = &config.default_registry {
+            spin_oci::set_default_registry(registry.clone());
+        }
+        if !config.plugins_repositories.is_empty() {
+            spin_plugins::lookup::set_default_plugins_repos(config.plugins_repositories.clone());
+        }
+        if let Some(telemetry) = &config.telemetry {
+            spin_telemetry::set_default_otlp_endpoint(telemetry.otlp_endpoint.clone());
+        }
+        if let Some(color) = config.color {
+            terminal::set_default_color(color.as_str());
+        }
+        if let Some(format) = config.log_format {
+            spin_telemetry::LogFormat::set_default(format.into());
+        }
+        Ok(config)
+    }
+
+    /// Makes the configured defaults the default values of the command line
+    /// options, in all subcommands, which read the corresponding environment
+    /// variables.
+    pub fn apply_defaults(&'static self, command: Command<'static>) -> Command<'static> {
+        with_arg_defaults(command, &self.arg_defaults())
+    }
+
+    // The defaults of command line options, keyed by the environment variable
+    // which each option reads.
+    fn arg_defaults(&self) -> Vec<(&'static str, &str)> {
+        let mut defaults = vec![];
+        if let Some(listen) = &self.listen {
+            defaults.push((spin_trigger_http::LISTEN_ADDR_ENV, listen.as_str()));
+        }
+        if let Some(format) = self.output_format {
+            // Commands call text output "plain".
+            let format = match format {
+                Format::Text => "plain",
+                Format::Json => "json",
+            };
+            defaults.push((OUTPUT_FORMAT_ENV, format));
+        }
+        if let Some(cache) = &self.cache {
+            if let Some(max_age) = &cache.max_age {
+                defaults.push((CACHE_MAX_AGE_ENV, max_age.as_str()));
+            }
+            if let Some(max_size) = &cache.max_size {
+                defaults.push((CACHE_MAX_SIZE_ENV, max_size.as_str()));
+            }
+        }
+        defaults
+    }
+}
+
+fn with_arg_defaults(
+    mut command: Command<'static>,
+    defaults: &[(&str, &'static str)],
+) -> Command<'static> {
+    let matching = command
+        .get_arguments()
+        .filter_map(|arg| {
+            let env = arg.get_env()?.to_str()?;
+            let (_, value) = defaults.iter().find(|(name, _)| *name == env)?;
+            Some((arg.get_id(), *value))
+        })
+        .collect::<Vec<_>>();
+    for (id, value) in matching {
+        command = command.mut_arg(id, |arg| arg.default_value(value));
+    }
+    for subcommand in command.get_subcommands_mut() {
+        *subcommand = with_arg_defaults(std::mem::take(subcommand), defaults);
+    }
+    command
+}
+
+fn config_path() -> Option<PathBuf> {
+    match std::env::var_os(USER_CONFIG_ENV) {
+        Some(path) => Some(path.into()),
+        None => dirs::config_dir().map(|dir| dir.join("spin").join("config.toml")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{FromArgMatches, Parser, Subcommand};
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestApp {
+        #[clap(subcommand)]
+        command: TestCommand,
+    }
+
+    #[derive(Subcommand)]
+    enum TestCommand {
+        Up {
+            #[clap(
+                long = "listen",
+                env = spin_trigger_http::LISTEN_ADDR_ENV,
+                default_value = "127.0.0.1:3000"
+            )]
+            listen: String,
+            #[clap(long = "max-size", env = CACHE_MAX_SIZE_ENV)]
+            max_size: Option<String>,
+        },
+    }
+
+    fn parse(config: &'static UserConfig, args: &[&str]) -> (String, Option<String>) {
+        let matches = config
+            .apply_defaults(<TestApp as clap::CommandFactory>::command())
+            .try_get_matches_from(args)
+            .unwrap();
+        let TestCommand::Up { listen, max_size } =
+            TestApp::from_arg_matches(&matches).unwrap().command;
+        (listen, max_size)
+    }
+
+    #[test]
+    fn config_sets_option_defaults() {
+        let config: &'static UserConfig = Box::leak(Box::new(
+            toml::from_str(
+                r#"
+                default_registry = "ghcr.io"
+                listen = "0.0.0.0:8080"
+                color = "never"
+                output_format = "json"
+
+                [telemetry]
+                otlp_endpoint = "http://localhost:4318"
+
+                [cache]
+                max_size = "2GB"
+                "#,
+            )
+            .unwrap(),
+        ));
+        assert_eq!(
+            config.arg_defaults(),
+            [
+                ("SPIN_HTTP_LISTEN_ADDR", "0.0.0.0:8080"),
+                ("SPIN_OUTPUT_FORMAT", "json"),
+                ("SPIN_CACHE_MAX_SIZE", "2GB"),
+            ]
+        );
+
+        assert_eq!(
+            parse(config, &["spin", "up"]),
+            ("0.0.0.0:8080".to_owned(), Some("2GB".to_owned()))
+        );
+        assert_eq!(
+            parse(config, &["spin", "up", "--listen", "127.0.0.1:4000"]).0,
+            "127.0.0.1:4000"
+        );
+    }
+
+    #[test]
+    fn plugins_repositories_are_a_list() {
+        let config: UserConfig = toml::from_str(
+            r#"plugins_repositories = ["https://a.example/", "https://b.example/"]"#,
+        )
+        .unwrap();
+        assert_eq!(config.plugins_repositories.len(), 2);
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        toml::from_str::<UserConfig>("default_registy = \"ghcr.io\"").unwrap_err();
+    }
+}
@@ -210,6 +210,7 @@ pub struct Config {
 }
 
 /// Describes a glob file pattern that should be watched.
+#[derive(Clone)]
 pub struct WatchPattern {
     /// String version of the absolute glob pattern.
     pub glob: String,